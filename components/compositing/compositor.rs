@@ -15,7 +15,7 @@ use crate::windowing::{
 use crate::{CompositionPipeline, ConstellationMsg, SendableFrameTree};
 use canvas::canvas_paint_thread::ImageUpdate;
 use crossbeam_channel::Sender;
-use embedder_traits::Cursor;
+use embedder_traits::{Cursor, PictureInPictureFrame, PictureInPictureRequest};
 use euclid::{Point2D, Rect, Scale, Vector2D};
 use gfx_traits::{Epoch, FontData};
 #[cfg(feature = "gl")]
@@ -33,7 +33,8 @@ use profile_traits::time::{self as profile_time, profile, ProfilerCategory};
 use script_traits::CompositorEvent::{MouseButtonEvent, MouseMoveEvent, TouchEvent, WheelEvent};
 use script_traits::{AnimationState, AnimationTickType, LayoutControlMsg};
 use script_traits::{
-    MouseButton, MouseEventType, ScrollState, TouchEventType, TouchId, WheelDelta,
+    MouseButton, MouseEventType, PictureInPictureAction, ScrollState, TouchEventType, TouchId,
+    WheelDelta,
 };
 use script_traits::{UntrustedNodeAddress, WindowSizeData, WindowSizeType};
 use servo_geometry::{DeviceIndependentPixel, FramebufferUintLength};
@@ -41,12 +42,14 @@ use std::collections::HashMap;
 use std::env;
 use std::fs::{create_dir_all, File};
 use std::io::Write;
+use std::mem;
 use std::num::NonZeroU32;
 use std::rc::Rc;
 use style_traits::viewport::ViewportConstraints;
 use style_traits::{CSSPixel, DevicePixel, PinchZoomFactor};
 use time::{now, precise_time_ns, precise_time_s};
-use webrender_api::units::{DeviceIntPoint, DeviceIntSize, DevicePoint, LayoutVector2D};
+use webrender_api::units::{DeviceIntPoint, DeviceIntRect, DeviceIntSize, DevicePoint};
+use webrender_api::units::{LayoutRect, LayoutSize, LayoutVector2D};
 use webrender_api::{self, HitTestFlags, HitTestResult, ScrollLocation};
 use webrender_surfman::WebrenderSurfman;
 
@@ -66,6 +69,21 @@ enum NotReadyToPaint {
 const MAX_ZOOM: f32 = 8.0;
 const MIN_ZOOM: f32 = 0.1;
 
+/// The WebRender pipeline of the picture-in-picture surface, which can't
+/// collide with the pipelines of the constellation.
+const PICTURE_IN_PICTURE_PIPELINE: webrender_api::PipelineId =
+    webrender_api::PipelineId(u32::MAX, u32::MAX);
+
+/// The picture-in-picture surface is drawn above the page.
+const PICTURE_IN_PICTURE_DOCUMENT_LAYER: webrender_api::DocumentLayer = 1;
+
+/// The fraction of the viewport width taken by the picture-in-picture surface.
+const PICTURE_IN_PICTURE_WIDTH_RATIO: f32 = 0.3;
+
+/// The distance between the picture-in-picture surface and the corner of
+/// the viewport, in device independent pixels.
+const PICTURE_IN_PICTURE_MARGIN: f32 = 16.0;
+
 trait ConvertPipelineIdFromWebRender {
     fn from_webrender(&self) -> PipelineId;
 }
@@ -79,6 +97,27 @@ impl ConvertPipelineIdFromWebRender for webrender_api::PipelineId {
     }
 }
 
+/// The area of a picture-in-picture surface showing a video of the given size
+/// in the bottom right corner of the viewport, keeping the aspect ratio of the
+/// video.
+fn picture_in_picture_rect(
+    coordinates: &EmbedderCoordinates,
+    video_size: DeviceIntSize,
+) -> DeviceIntRect {
+    let viewport = coordinates.viewport;
+    let margin = (PICTURE_IN_PICTURE_MARGIN * coordinates.hidpi_factor.get()) as i32;
+    let aspect_ratio = video_size.height.max(1) as f32 / video_size.width.max(1) as f32;
+    let width = (viewport.size.width as f32 * PICTURE_IN_PICTURE_WIDTH_RATIO) as i32;
+    let width = width.min(video_size.width).max(1);
+    let height = (width as f32 * aspect_ratio) as i32;
+    let height = height.min(viewport.size.height - 2 * margin).max(1);
+    let origin = Point2D::new(
+        viewport.max_x() - margin - width,
+        viewport.max_y() - margin - height,
+    );
+    DeviceIntRect::new(origin, DeviceIntSize::new(width, height))
+}
+
 /// Holds the state when running reftests that determines when it is
 /// safe to save the output image.
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -220,6 +259,20 @@ pub struct IOCompositor<Window: WindowMethods + ?Sized> {
     /// taken before the render is complete will not reflect the
     /// most up to date rendering.
     waiting_on_pending_frame: bool,
+
+    /// The picture-in-picture surface shown above the page, if any.
+    picture_in_picture: Option<PictureInPictureSurface>,
+}
+
+/// A video hosted in the bottom right corner of the viewport, in a WebRender
+/// document of its own so that it stays above the page.
+struct PictureInPictureSurface {
+    /// The WebRender document the video is rendered to.
+    document: webrender_api::DocumentId,
+    /// The video frame shown by the surface.
+    frame: PictureInPictureFrame,
+    /// The area of the surface in the viewport.
+    rect: DeviceIntRect,
 }
 
 #[derive(Clone, Copy)]
@@ -336,6 +389,7 @@ impl<Window: WindowMethods + ?Sized> IOCompositor<Window> {
             exit_after_load,
             convert_mouse_to_touch,
             waiting_on_pending_frame: false,
+            picture_in_picture: None,
         }
     }
 
@@ -719,6 +773,13 @@ impl<Window: WindowMethods + ?Sized> IOCompositor<Window> {
                 }
                 self.webrender_api
                     .send_transaction(self.webrender_document, txn);
+                // Media players update their video frames in place, so the
+                // picture-in-picture surface has to be rendered again.
+                if let Some(ref surface) = self.picture_in_picture {
+                    let mut txn = webrender_api::Transaction::new();
+                    txn.generate_frame();
+                    self.webrender_api.send_transaction(surface.document, txn);
+                }
             },
         }
     }
@@ -855,9 +916,122 @@ impl<Window: WindowMethods + ?Sized> IOCompositor<Window> {
         }
 
         self.send_window_size(WindowSizeType::Resize);
+        self.update_picture_in_picture_rect();
+        self.send_picture_in_picture_display_list();
         self.composite_if_necessary(CompositingReason::Resize);
     }
 
+    /// Hosts the picture-in-picture surface requested by script above the page,
+    /// in the bottom right corner of the viewport.
+    pub fn on_picture_in_picture_request(&mut self, request: PictureInPictureRequest) {
+        match request {
+            PictureInPictureRequest::Enter(frame, sender) => {
+                let document = match self.picture_in_picture.take() {
+                    Some(surface) => surface.document,
+                    None => self.webrender_api.add_document(
+                        self.embedder_coordinates.framebuffer,
+                        PICTURE_IN_PICTURE_DOCUMENT_LAYER,
+                    ),
+                };
+                let rect = picture_in_picture_rect(&self.embedder_coordinates, frame.size);
+                self.picture_in_picture = Some(PictureInPictureSurface {
+                    document,
+                    frame,
+                    rect,
+                });
+                self.send_picture_in_picture_display_list();
+                if let Err(e) = sender.send(Some(rect.size)) {
+                    warn!("Sending picture-in-picture size failed ({:?}).", e);
+                }
+            },
+            PictureInPictureRequest::UpdateFrame(frame) => {
+                match self.picture_in_picture {
+                    Some(ref mut surface) => surface.frame = frame,
+                    None => return,
+                }
+                self.update_picture_in_picture_rect();
+                self.send_picture_in_picture_display_list();
+            },
+            PictureInPictureRequest::Exit => {
+                if let Some(surface) = self.picture_in_picture.take() {
+                    self.webrender_api.delete_document(surface.document);
+                }
+            },
+        }
+        self.composite_if_necessary(CompositingReason::NewWebRenderFrame);
+    }
+
+    /// Moves the picture-in-picture surface after the viewport or the video
+    /// changed size, and lets script know if the surface was resized.
+    fn update_picture_in_picture_rect(&mut self) {
+        let coordinates = self.embedder_coordinates;
+        let surface = match self.picture_in_picture {
+            Some(ref mut surface) => surface,
+            None => return,
+        };
+        let rect = picture_in_picture_rect(&coordinates, surface.frame.size);
+        let old_rect = mem::replace(&mut surface.rect, rect);
+        if old_rect.size == rect.size {
+            return;
+        }
+        let msg =
+            ConstellationMsg::PictureInPictureAction(PictureInPictureAction::Resize(rect.size));
+        if let Err(e) = self.constellation_chan.send(msg) {
+            warn!(
+                "Sending picture-in-picture resize to constellation failed ({:?}).",
+                e
+            );
+        }
+    }
+
+    fn send_picture_in_picture_display_list(&self) {
+        let surface = match self.picture_in_picture {
+            Some(ref surface) => surface,
+            None => return,
+        };
+        let size = surface.rect.size.to_f32();
+        let content_size = LayoutSize::new(size.width, size.height);
+        let bounds = LayoutRect::new(Point2D::zero(), content_size);
+        let pipeline_id = PICTURE_IN_PICTURE_PIPELINE;
+        let mut builder = webrender_api::DisplayListBuilder::new(pipeline_id, content_size);
+        let common = webrender_api::CommonItemProperties::new(
+            bounds,
+            webrender_api::SpaceAndClipInfo::root_scroll(pipeline_id),
+        );
+        match surface.frame.image_key {
+            Some(image_key) => builder.push_image(
+                &common,
+                bounds,
+                webrender_api::ImageRendering::Auto,
+                webrender_api::AlphaType::PremultipliedAlpha,
+                image_key,
+                webrender_api::ColorF::WHITE,
+            ),
+            // The video didn't render any frame yet.
+            None => builder.push_rect(&common, bounds, webrender_api::ColorF::BLACK),
+        }
+
+        let mut txn = webrender_api::Transaction::new();
+        txn.set_display_list(
+            webrender_api::Epoch(0),
+            Some(webrender_api::ColorF::BLACK),
+            content_size,
+            builder.finalize(),
+            false,
+        );
+        txn.set_root_pipeline(pipeline_id);
+        txn.generate_frame();
+        self.webrender_api.send_transaction(surface.document, txn);
+
+        let mut view = surface.rect;
+        view.origin.y = self.embedder_coordinates.framebuffer.height - view.max_y();
+        self.webrender_api.set_document_view(
+            surface.document,
+            view,
+            self.embedder_coordinates.hidpi_factor.get(),
+        );
+    }
+
     pub fn on_mouse_window_event_class(&mut self, mouse_window_event: MouseWindowEvent) {
         if self.convert_mouse_to_touch {
             match mouse_window_event {
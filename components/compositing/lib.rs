@@ -11,7 +11,7 @@ pub use crate::compositor::CompositingReason;
 pub use crate::compositor::IOCompositor;
 pub use crate::compositor::ShutdownState;
 pub use crate::compositor_thread::CompositorProxy;
-use embedder_traits::Cursor;
+use embedder_traits::{AudioOutputBuffer, Cursor};
 use gfx_traits::Epoch;
 use ipc_channel::ipc::IpcSender;
 use keyboard_types::KeyboardEvent;
@@ -23,6 +23,7 @@ use script_traits::{
 };
 use script_traits::{
     CompositorEvent, ConstellationControlMsg, LayoutControlMsg, MediaSessionActionType,
+    PictureInPictureAction,
 };
 use servo_url::ServoUrl;
 use std::collections::HashMap;
@@ -109,6 +110,13 @@ pub enum ConstellationMsg {
     ExitFullScreen(TopLevelBrowsingContextId),
     /// Media session action.
     MediaSessionAction(MediaSessionActionType),
+    /// Picture-in-picture surface action.
+    PictureInPictureAction(PictureInPictureAction),
+    /// Start or stop capturing the audio output of a browser.
+    CaptureAudioOutput(
+        TopLevelBrowsingContextId,
+        Option<IpcSender<AudioOutputBuffer>>,
+    ),
     /// Toggle browser visibility.
     ChangeBrowserVisibility(TopLevelBrowsingContextId, bool),
     /// Virtual keyboard was dismissed
@@ -143,6 +151,8 @@ impl fmt::Debug for ConstellationMsg {
             DisableProfiler => "DisableProfiler",
             ExitFullScreen(..) => "ExitFullScreen",
             MediaSessionAction(..) => "MediaSessionAction",
+            PictureInPictureAction(..) => "PictureInPictureAction",
+            CaptureAudioOutput(..) => "CaptureAudioOutput",
             ChangeBrowserVisibility(..) => "ChangeBrowserVisibility",
             IMEDismissed => "IMEDismissed",
             ClearCache => "ClearCache",
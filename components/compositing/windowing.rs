@@ -4,11 +4,13 @@
 
 //! Abstract windowing methods. The concrete implementations of these can be found in `platform/`.
 
-use embedder_traits::{EmbedderProxy, EventLoopWaker};
+use embedder_traits::{AudioOutputBuffer, EmbedderProxy, EventLoopWaker, PictureInPictureRequest};
 use euclid::Scale;
+use ipc_channel::ipc::IpcSender;
 use keyboard_types::KeyboardEvent;
 use msg::constellation_msg::{PipelineId, TopLevelBrowsingContextId, TraversalDirection};
-use script_traits::{MediaSessionActionType, MouseButton, TouchEventType, TouchId, WheelDelta};
+use script_traits::{MediaSessionActionType, MouseButton, PictureInPictureAction};
+use script_traits::{TouchEventType, TouchId, WheelDelta};
 use servo_geometry::DeviceIndependentPixel;
 use servo_media::player::context::{GlApi, GlContext, NativeDisplay};
 use servo_url::ServoUrl;
@@ -102,6 +104,17 @@ pub enum WindowEvent {
     /// Sent when the user triggers a media action through the UA exposed media UI
     /// (play, pause, seek, etc.).
     MediaSessionAction(MediaSessionActionType),
+    /// Let Servo host the picture-in-picture surface requested through
+    /// `EmbedderMsg::PictureInPicture` above the page.
+    PictureInPicture(PictureInPictureRequest),
+    /// Sent when the user resizes or closes the picture-in-picture surface.
+    PictureInPictureAction(PictureInPictureAction),
+    /// Start sending the mixed audio output of a browser to the given channel,
+    /// or stop when `None` is given. Captured audio is not played by Servo.
+    CaptureAudioOutput(
+        TopLevelBrowsingContextId,
+        Option<IpcSender<AudioOutputBuffer>>,
+    ),
     /// Set browser visibility. A hidden browser will not tick the animations.
     ChangeBrowserVisibility(TopLevelBrowsingContextId, bool),
     /// Virtual keyboard was dismissed
@@ -137,6 +150,9 @@ impl Debug for WindowEvent {
             WindowEvent::ToggleSamplingProfiler(..) => write!(f, "ToggleSamplingProfiler"),
             WindowEvent::ExitFullScreen(..) => write!(f, "ExitFullScreen"),
             WindowEvent::MediaSessionAction(..) => write!(f, "MediaSessionAction"),
+            WindowEvent::PictureInPicture(..) => write!(f, "PictureInPicture"),
+            WindowEvent::PictureInPictureAction(..) => write!(f, "PictureInPictureAction"),
+            WindowEvent::CaptureAudioOutput(..) => write!(f, "CaptureAudioOutput"),
             WindowEvent::ChangeBrowserVisibility(..) => write!(f, "ChangeBrowserVisibility"),
             WindowEvent::IMEDismissed => write!(f, "IMEDismissed"),
             WindowEvent::ClearCache => write!(f, "ClearCache"),
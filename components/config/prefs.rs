@@ -139,6 +139,10 @@ mod gen {
                         enabled: bool,
                     }
                 },
+                caches: {
+                    enabled: bool,
+                    quota: i64,
+                },
                 canvas_capture: {
                     enabled: bool,
                 },
@@ -192,6 +196,10 @@ mod gen {
                         allowed_in_nonsecure_contexts: bool,
                     }
                 },
+                picture_in_picture: {
+                    enabled: bool,
+                    test: bool,
+                },
                 script: {
                     asynch: bool,
                 },
@@ -259,6 +267,9 @@ mod gen {
                     #[serde(default)]
                     enabled: bool,
                 },
+                webcodecs: {
+                    enabled: bool,
+                },
                 webgl: {
                     dom_to_texture: {
                         enabled: bool,
@@ -456,11 +467,16 @@ mod gen {
                 }
             },
             media: {
+                fake_devices: {
+                    enabled: bool,
+                    source: String,
+                },
                 glvideo: {
                     enabled: bool,
                 },
                 testing: {
                     enabled: bool,
+                    capture_permission: String,
                 }
             },
             network: {
@@ -475,6 +491,19 @@ mod gen {
                 },
                 mime: {
                     sniff: bool,
+                },
+                mixed_content: {
+                    blocking: {
+                        enabled: bool,
+                    }
+                },
+                private_network_access: {
+                    enabled: bool,
+                },
+                websocket: {
+                    permessage_deflate: {
+                        enabled: bool,
+                    }
                 }
             },
             session_history: {
@@ -0,0 +1,199 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Mixing of the audio output of a browser, for embedders which capture it.
+
+use crossbeam_channel::Receiver;
+use embedder_traits::AudioOutputBuffer;
+use ipc_channel::ipc::{self, IpcSender};
+use ipc_channel::router::ROUTER;
+use script_traits::{AudioOutputMsg, AudioOutputSourceId, AUDIO_OUTPUT_SAMPLE_RATE};
+use std::collections::{HashMap, VecDeque};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The number of channels of the mix.
+const CHANNELS: usize = 2;
+
+/// How often the mix is sent to the embedder.
+const MIX_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How many samples of a channel are kept before the oldest ones get dropped,
+/// for sources which produce audio faster than it is consumed.
+const MAX_BUFFERED_SAMPLES: usize = AUDIO_OUTPUT_SAMPLE_RATE as usize;
+
+/// A channel of a source, resampled to the sample rate of the mix.
+#[derive(Default)]
+struct SourceChannel {
+    /// The resampled samples not mixed yet.
+    queue: VecDeque<f32>,
+    /// The last sample received.
+    previous: f32,
+    /// The position of the next output sample, in input samples since
+    /// `previous`.
+    position: f64,
+}
+
+impl SourceChannel {
+    fn new() -> SourceChannel {
+        SourceChannel {
+            position: 1.,
+            ..Default::default()
+        }
+    }
+
+    /// Resample `samples` to the sample rate of the mix, with a linear
+    /// interpolation.
+    fn push(&mut self, sample_rate: f32, samples: Vec<f32>) {
+        let last = match samples.last() {
+            Some(last) => *last,
+            None => return,
+        };
+        if sample_rate as u32 == AUDIO_OUTPUT_SAMPLE_RATE || !(sample_rate > 0.) {
+            self.queue.extend(samples);
+        } else {
+            let step = sample_rate as f64 / AUDIO_OUTPUT_SAMPLE_RATE as f64;
+            let len = samples.len() as f64;
+            // Sample `0` is `previous`, sample `i` is `samples[i - 1]`.
+            let previous = self.previous;
+            let sample_at = |index: usize| {
+                if index == 0 {
+                    previous
+                } else {
+                    samples[index - 1]
+                }
+            };
+            let mut position = self.position;
+            while position <= len {
+                let index = position.floor();
+                let fraction = (position - index) as f32;
+                let index = index as usize;
+                let sample = if fraction == 0. {
+                    sample_at(index)
+                } else {
+                    sample_at(index) * (1. - fraction) + sample_at(index + 1) * fraction
+                };
+                self.queue.push_back(sample);
+                position += step;
+            }
+            self.position = position - len;
+        }
+        self.previous = last;
+        if self.queue.len() > MAX_BUFFERED_SAMPLES {
+            let excess = self.queue.len() - MAX_BUFFERED_SAMPLES;
+            self.queue.drain(..excess);
+        }
+    }
+}
+
+pub struct AudioOutputMixer {
+    receiver: Receiver<AudioOutputMsg>,
+    sender: IpcSender<AudioOutputBuffer>,
+    /// The samples not mixed yet, per channel, of each source.
+    sources: HashMap<AudioOutputSourceId, Vec<SourceChannel>>,
+    /// The number of frames sent to the embedder so far.
+    frames_sent: u64,
+}
+
+impl AudioOutputMixer {
+    /// Start a thread mixing the audio sent to the returned channel, and
+    /// sending the mix to the embedder in real time.
+    pub fn start(sender: IpcSender<AudioOutputBuffer>) -> IpcSender<AudioOutputMsg> {
+        let (ipc_sender, ipc_receiver) = ipc::channel().expect("ipc channel failure");
+        let receiver = ROUTER.route_ipc_receiver_to_new_crossbeam_receiver(ipc_receiver);
+        thread::Builder::new()
+            .name("AudioOutputMixer".to_owned())
+            .spawn(move || {
+                AudioOutputMixer {
+                    receiver,
+                    sender,
+                    sources: HashMap::new(),
+                    frames_sent: 0,
+                }
+                .run()
+            })
+            .expect("Thread spawning failed");
+        ipc_sender
+    }
+
+    fn run(&mut self) {
+        let start = Instant::now();
+        let ticker = crossbeam_channel::tick(MIX_INTERVAL);
+        loop {
+            select! {
+                recv(self.receiver) -> msg => match msg {
+                    Ok(AudioOutputMsg::Samples(source, sample_rate, channel, samples)) => {
+                        self.push(source, sample_rate, channel as usize, samples)
+                    },
+                    Ok(AudioOutputMsg::RemoveSource(source)) => {
+                        self.sources.remove(&source);
+                    },
+                    Ok(AudioOutputMsg::Exit) | Err(_) => break,
+                },
+                recv(ticker) -> _ => {
+                    // Work out the number of frames from the elapsed time,
+                    // so that missed ticks don't leave gaps in the output.
+                    let elapsed = start.elapsed().as_micros() as u64;
+                    let frames_due = elapsed * AUDIO_OUTPUT_SAMPLE_RATE as u64 / 1_000_000;
+                    let frames = (frames_due - self.frames_sent) as usize;
+                    if frames == 0 {
+                        continue;
+                    }
+                    self.frames_sent = frames_due;
+                    if let Err(e) = self.sender.send(self.mix(frames)) {
+                        debug!("Audio output capture ended ({:?}).", e);
+                        break;
+                    }
+                },
+            }
+        }
+    }
+
+    fn push(
+        &mut self,
+        source: AudioOutputSourceId,
+        sample_rate: f32,
+        channel: usize,
+        samples: Vec<f32>,
+    ) {
+        let channels = self.sources.entry(source).or_default();
+        if channels.len() <= channel {
+            channels.resize_with(channel + 1, SourceChannel::new);
+        }
+        channels[channel].push(sample_rate, samples);
+    }
+
+    /// Mix the next `frames` frames of all the sources. Sources which did not
+    /// produce enough samples are padded with silence.
+    fn mix(&mut self, frames: usize) -> AudioOutputBuffer {
+        let mut samples = vec![0.; frames * CHANNELS];
+        for channels in self.sources.values_mut() {
+            let mono = channels.len() == 1;
+            for (index, channel) in channels.iter_mut().enumerate() {
+                let queue = &mut channel.queue;
+                // Mono sources are played on all the channels, and sources
+                // with more channels than the mix are folded into it.
+                let outputs = if mono {
+                    0..CHANNELS
+                } else {
+                    (index % CHANNELS)..(index % CHANNELS + 1)
+                };
+                let available = frames.min(queue.len());
+                for (frame, sample) in queue.drain(..available).enumerate() {
+                    for output in outputs.clone() {
+                        samples[frame * CHANNELS + output] += sample;
+                    }
+                }
+            }
+        }
+        for sample in samples.iter_mut() {
+            *sample = sample.max(-1.).min(1.);
+        }
+        AudioOutputBuffer {
+            sample_rate: AUDIO_OUTPUT_SAMPLE_RATE,
+            channels: CHANNELS as u32,
+            samples,
+        }
+    }
+}
@@ -89,6 +89,7 @@
 //!
 //! See https://github.com/servo/servo/issues/14704
 
+use crate::audio_output::AudioOutputMixer;
 use crate::browsingcontext::NewBrowsingContextInfo;
 use crate::browsingcontext::{
     AllBrowsingContextsIterator, BrowsingContext, FullyActiveBrowsingContextsIterator,
@@ -116,8 +117,8 @@ use devtools_traits::{
     ChromeToDevtoolsControlMsg, DevtoolsControlMsg, DevtoolsPageInfo, NavigationState,
     ScriptToDevtoolsControlMsg,
 };
-use embedder_traits::{Cursor, EmbedderMsg, EmbedderProxy, EventLoopWaker};
-use embedder_traits::{MediaSessionEvent, MediaSessionPlaybackState};
+use embedder_traits::{AudioOutputBuffer, Cursor, EmbedderMsg, EmbedderProxy, EventLoopWaker};
+use embedder_traits::{MediaSessionEvent, MediaSessionPlaybackState, PictureInPictureRequest};
 use euclid::{default::Size2D as UntypedSize2D, Size2D};
 use gfx::font_cache_thread::FontCacheThread;
 use gfx_traits::Epoch;
@@ -134,14 +135,14 @@ use msg::constellation_msg::{
 };
 use msg::constellation_msg::{
     BroadcastChannelRouterId, MessagePortId, MessagePortRouterId, PipelineNamespace,
-    PipelineNamespaceId, PipelineNamespaceRequest, TraversalDirection,
+    PipelineNamespaceId, PipelineNamespaceRequest, SharedWorkerId, TraversalDirection,
 };
 use msg::constellation_msg::{
     BrowsingContextGroupId, BrowsingContextId, HistoryStateId, PipelineId,
     TopLevelBrowsingContextId,
 };
 use net_traits::pub_domains::reg_host;
-use net_traits::request::{Referrer, RequestBuilder};
+use net_traits::request::{CredentialsMode, Referrer, RequestBuilder};
 use net_traits::storage_thread::{StorageThreadMsg, StorageType};
 use net_traits::{self, FetchResponseMsg, IpcSend, ResourceThreads};
 use profile_traits::mem;
@@ -152,7 +153,7 @@ use script_traits::{
     AnimationState, AnimationTickType, AuxiliaryBrowsingContextLoadInfo, BroadcastMsg,
     CompositorEvent,
 };
-use script_traits::{ConstellationControlMsg, DiscardBrowsingContext};
+use script_traits::{AudioOutputMsg, ConstellationControlMsg, DiscardBrowsingContext};
 use script_traits::{DocumentActivity, DocumentState, LayoutControlMsg, LoadData, LoadOrigin};
 use script_traits::{HistoryEntryReplacement, IFrameSizeMsg, WindowSizeData, WindowSizeType};
 use script_traits::{
@@ -162,9 +163,10 @@ use script_traits::{
     Job, LayoutMsg as FromLayoutMsg, ScriptMsg as FromScriptMsg, ScriptThreadFactory,
     ServiceWorkerManagerFactory,
 };
-use script_traits::{MediaSessionActionType, MouseEventType};
+use script_traits::{MediaSessionActionType, MouseEventType, PictureInPictureAction};
 use script_traits::{MessagePortMsg, PortMessageTask, StructuredSerializedData};
 use script_traits::{SWManagerMsg, SWManagerSenders, UpdatePipelineIdReason, WebDriverCommandMsg};
+use script_traits::{SharedWorkerConnectInfo, SharedWorkerConnectionMsg, SharedWorkerControlMsg};
 use serde::{Deserialize, Serialize};
 use servo_config::{opts, pref};
 use servo_rand::{random, Rng, ServoRng, SliceRandom};
@@ -222,6 +224,47 @@ struct MessagePortInfo {
     entangled_with: Option<MessagePortId>,
 }
 
+/// The key of a shared worker: its origin, script url and name.
+/// <https://html.spec.whatwg.org/multipage/#dom-sharedworker>
+type SharedWorkerKey = (ImmutableOrigin, ServoUrl, String);
+
+/// Info related to a running shared worker tracked by the constellation.
+struct SharedWorkerInfo {
+    /// The id of the worker.
+    id: SharedWorkerId,
+
+    /// A channel to send control messages to the worker.
+    control_sender: IpcSender<SharedWorkerControlMsg>,
+
+    /// Whether the worker script is a module script.
+    module: bool,
+
+    /// The credentials mode of the worker script request.
+    credentials: CredentialsMode,
+
+    /// The pipelines connected to the worker, with a channel per connection.
+    /// The worker is shut down once all of them have closed.
+    clients: HashMap<PipelineId, Vec<IpcSender<SharedWorkerConnectionMsg>>>,
+
+    /// The event loop of the client that started the worker, which runs it,
+    /// and without which the worker is gone.
+    event_loop: Weak<EventLoop>,
+}
+
+impl SharedWorkerInfo {
+    fn is_running(&self) -> bool {
+        self.event_loop.upgrade().is_some()
+    }
+
+    /// Tell the clients the worker went away with its event loop, so that
+    /// an error event is fired at their `SharedWorker` objects.
+    fn notify_clients_of_closed_event_loop(&self) {
+        for sender in self.clients.values().flatten() {
+            let _ = sender.send(SharedWorkerConnectionMsg::Error);
+        }
+    }
+}
+
 /// Webrender related objects required by WebGPU threads
 struct WebrenderWGPU {
     /// Webrender API.
@@ -417,6 +460,9 @@ pub struct Constellation<Message, LTF, STF, SWF> {
     /// A map of router-id to ipc-sender, to route messages to ports.
     message_port_routers: HashMap<MessagePortRouterId, IpcSender<MessagePortMsg>>,
 
+    /// The running shared workers, keyed by origin, script url and name.
+    shared_workers: HashMap<SharedWorkerKey, SharedWorkerInfo>,
+
     /// A map of broadcast routers to their IPC sender.
     broadcast_routers: HashMap<BroadcastChannelRouterId, IpcSender<BroadcastMsg>>,
 
@@ -513,6 +559,12 @@ pub struct Constellation<Message, LTF, STF, SWF> {
     /// Pipeline ID of the active media session.
     active_media_session: Option<PipelineId>,
 
+    /// Pipeline ID of the document whose video is shown in picture-in-picture.
+    picture_in_picture_pipeline: Option<PipelineId>,
+
+    /// The mixers of the browsers whose audio output is captured by the embedder.
+    audio_output_captures: HashMap<TopLevelBrowsingContextId, IpcSender<AudioOutputMsg>>,
+
     /// User agent string to report in network requests.
     user_agent: Cow<'static, str>,
 }
@@ -880,6 +932,7 @@ where
                     browsing_context_group_next_id: Default::default(),
                     message_ports: HashMap::new(),
                     message_port_routers: HashMap::new(),
+                    shared_workers: HashMap::new(),
                     broadcast_routers: HashMap::new(),
                     broadcast_channels: HashMap::new(),
                     pipelines: HashMap::new(),
@@ -927,6 +980,8 @@ where
                     player_context: state.player_context,
                     event_loop_waker: state.event_loop_waker,
                     active_media_session: None,
+                    picture_in_picture_pipeline: None,
+                    audio_output_captures: HashMap::new(),
                     user_agent: state.user_agent,
                 };
 
@@ -1645,6 +1700,12 @@ where
             FromCompositorMsg::MediaSessionAction(action) => {
                 self.handle_media_session_action_msg(action);
             },
+            FromCompositorMsg::PictureInPictureAction(action) => {
+                self.handle_picture_in_picture_action_msg(action);
+            },
+            FromCompositorMsg::CaptureAudioOutput(top_level_browsing_context_id, sender) => {
+                self.handle_capture_audio_output_msg(top_level_browsing_context_id, sender);
+            },
             FromCompositorMsg::ChangeBrowserVisibility(top_level_browsing_context_id, visible) => {
                 self.handle_change_browser_visibility(top_level_browsing_context_id, visible);
             },
@@ -1658,44 +1719,64 @@ where
             content, source_pipeline_id
         );
 
-        let source_top_ctx_id = match self
-            .pipelines
-            .get(&source_pipeline_id)
-            .map(|pipeline| pipeline.top_level_browsing_context_id)
-        {
-            None => return warn!("ScriptMsg from closed pipeline {:?}.", source_pipeline_id),
-            Some(ctx) => ctx,
-        };
-
-        match content {
+        // Shared workers use the pipeline that created them,
+        // but can outlive it while other clients remain connected,
+        // so their messaging is handled regardless of the state of that pipeline.
+        let content = match content {
             FromScriptMsg::CompleteMessagePortTransfer(router_id, ports) => {
                 self.handle_complete_message_port_transfer(router_id, ports);
+                return;
             },
             FromScriptMsg::MessagePortTransferResult(router_id, succeeded, failed) => {
                 self.handle_message_port_transfer_completed(router_id, succeeded);
                 self.handle_message_port_transfer_failed(failed);
+                return;
             },
             FromScriptMsg::RerouteMessagePort(port_id, task) => {
                 self.handle_reroute_messageport(port_id, task);
+                return;
             },
             FromScriptMsg::MessagePortShipped(port_id) => {
                 self.handle_messageport_shipped(port_id);
+                return;
             },
             FromScriptMsg::NewMessagePortRouter(router_id, ipc_sender) => {
                 self.handle_new_messageport_router(router_id, ipc_sender);
+                return;
             },
             FromScriptMsg::RemoveMessagePortRouter(router_id) => {
                 self.handle_remove_messageport_router(router_id);
+                return;
             },
             FromScriptMsg::NewMessagePort(router_id, port_id) => {
                 self.handle_new_messageport(router_id, port_id);
+                return;
             },
             FromScriptMsg::RemoveMessagePort(port_id) => {
                 self.handle_remove_messageport(port_id);
+                return;
             },
             FromScriptMsg::EntanglePorts(port1, port2) => {
                 self.handle_entangle_messageports(port1, port2);
+                return;
             },
+            FromScriptMsg::SharedWorkerClosed(worker_id) => {
+                self.handle_shared_worker_closed(worker_id);
+                return;
+            },
+            content => content,
+        };
+
+        let source_top_ctx_id = match self
+            .pipelines
+            .get(&source_pipeline_id)
+            .map(|pipeline| pipeline.top_level_browsing_context_id)
+        {
+            None => return warn!("ScriptMsg from closed pipeline {:?}.", source_pipeline_id),
+            Some(ctx) => ctx,
+        };
+
+        match content {
             FromScriptMsg::NewBroadcastChannelRouter(router_id, ipc_sender, origin) => {
                 self.handle_new_broadcast_channel_router(
                     source_pipeline_id,
@@ -1726,6 +1807,9 @@ where
             FromScriptMsg::ScheduleBroadcast(router_id, message) => {
                 self.handle_schedule_broadcast(source_pipeline_id, router_id, message);
             },
+            FromScriptMsg::ConnectToSharedWorker(info, response_sender) => {
+                self.handle_connect_to_shared_worker(source_pipeline_id, info, response_sender);
+            },
             FromScriptMsg::ForwardToEmbedder(embedder_msg) => {
                 self.embedder_proxy
                     .send((Some(source_top_ctx_id), embedder_msg));
@@ -1898,6 +1982,13 @@ where
                     warn!("Unable to forward DOMMessage for postMessage call");
                 }
             },
+            FromScriptMsg::UpdateNavigationPreload(scope_url, update) => {
+                if let Some(mgr) = self.sw_managers.get(&scope_url.origin()) {
+                    let _ = mgr.send(ServiceWorkerMsg::UpdateNavigationPreload(scope_url, update));
+                } else {
+                    warn!("Unable to update navigation preload without a service worker manager");
+                }
+            },
             FromScriptMsg::BroadcastStorageEvent(storage, url, key, old_value, new_value) => {
                 self.handle_broadcast_storage_event(
                     source_pipeline_id,
@@ -1933,6 +2024,37 @@ where
                     EmbedderMsg::MediaSessionEvent(event),
                 ));
             },
+            FromScriptMsg::PictureInPicture(request) => {
+                // There is a single picture-in-picture surface, which the last
+                // pipeline entering picture-in-picture takes over. Requests
+                // coming from other pipelines are discarded.
+                match request {
+                    PictureInPictureRequest::Enter(..) => {
+                        let previous = self.picture_in_picture_pipeline.replace(source_pipeline_id);
+                        if let Some(previous) = previous.filter(|id| *id != source_pipeline_id) {
+                            self.send_picture_in_picture_action(
+                                previous,
+                                PictureInPictureAction::Close,
+                            );
+                        }
+                    },
+                    PictureInPictureRequest::UpdateFrame(..) => {
+                        if self.picture_in_picture_pipeline != Some(source_pipeline_id) {
+                            return;
+                        }
+                    },
+                    PictureInPictureRequest::Exit => {
+                        if self.picture_in_picture_pipeline != Some(source_pipeline_id) {
+                            return;
+                        }
+                        self.picture_in_picture_pipeline = None;
+                    },
+                }
+                self.embedder_proxy.send((
+                    Some(source_top_ctx_id),
+                    EmbedderMsg::PictureInPicture(request),
+                ));
+            },
             FromScriptMsg::RequestAdapter(sender, options, ids) => self.handle_wgpu_request(
                 source_pipeline_id,
                 BrowsingContextId::from(source_top_ctx_id),
@@ -1948,6 +2070,18 @@ where
                     pipeline.title = title;
                 }
             },
+            FromScriptMsg::CompleteMessagePortTransfer(..) |
+            FromScriptMsg::MessagePortTransferResult(..) |
+            FromScriptMsg::RerouteMessagePort(..) |
+            FromScriptMsg::MessagePortShipped(..) |
+            FromScriptMsg::NewMessagePortRouter(..) |
+            FromScriptMsg::RemoveMessagePortRouter(..) |
+            FromScriptMsg::NewMessagePort(..) |
+            FromScriptMsg::RemoveMessagePort(..) |
+            FromScriptMsg::EntanglePorts(..) |
+            FromScriptMsg::SharedWorkerClosed(..) => {
+                unreachable!("Handled before checking the source pipeline.")
+            },
         }
     }
 
@@ -2621,6 +2755,135 @@ where
         let _ = sw_manager.send(ServiceWorkerMsg::ScheduleJob(job));
     }
 
+    /// <https://html.spec.whatwg.org/multipage/#dom-sharedworker>
+    /// Connect a client to the shared worker matching its key,
+    /// asking the client to start a new worker if there is none.
+    fn handle_connect_to_shared_worker(
+        &mut self,
+        pipeline_id: PipelineId,
+        info: SharedWorkerConnectInfo,
+        response_sender: IpcSender<SharedWorkerConnectionMsg>,
+    ) {
+        let SharedWorkerConnectInfo {
+            origin,
+            script_url,
+            name,
+            module,
+            credentials,
+            port,
+            control_sender,
+        } = info;
+
+        if self
+            .check_origin_against_pipeline(&pipeline_id, &origin)
+            .is_err()
+        {
+            let _ = response_sender.send(SharedWorkerConnectionMsg::Error);
+            return warn!("Attempt to connect to a shared worker from a mismatched origin.");
+        }
+
+        let event_loop = match self.pipelines.get(&pipeline_id) {
+            Some(pipeline) => Rc::downgrade(&pipeline.event_loop),
+            None => {
+                let _ = response_sender.send(SharedWorkerConnectionMsg::Error);
+                return warn!("Attempt to connect to a shared worker from a closed pipeline.");
+            },
+        };
+
+        // The control messages of a new worker are buffered in the channel
+        // until the client has started it.
+        let new_worker = SharedWorkerInfo {
+            id: SharedWorkerId::new(),
+            control_sender,
+            module,
+            credentials,
+            clients: HashMap::new(),
+            event_loop,
+        };
+
+        let (worker, new_worker_id) = match self.shared_workers.entry((origin, script_url, name)) {
+            Entry::Occupied(entry) if entry.get().is_running() => (entry.into_mut(), None),
+            Entry::Occupied(mut entry) => {
+                // The worker went away with its event loop, start a new one.
+                debug!("Restarting shared worker {}.", entry.get().id);
+                let id = new_worker.id;
+                entry
+                    .insert(new_worker)
+                    .notify_clients_of_closed_event_loop();
+                (entry.into_mut(), Some(id))
+            },
+            Entry::Vacant(entry) => {
+                let id = new_worker.id;
+                (entry.insert(new_worker), Some(id))
+            },
+        };
+
+        // Step 11.2, a running worker of another type or credentials mode
+        // can't be connected to.
+        if worker.module != module || worker.credentials != credentials {
+            let _ = response_sender.send(SharedWorkerConnectionMsg::Error);
+            return debug!(
+                "Type or credentials mismatch connecting to shared worker {}.",
+                worker.id
+            );
+        }
+
+        if let Err(e) = worker
+            .control_sender
+            .send(SharedWorkerControlMsg::Connect(port))
+        {
+            warn!("Connecting to shared worker failed ({})", e);
+        }
+        let reply = match new_worker_id {
+            Some(id) => SharedWorkerConnectionMsg::Start(id),
+            None => SharedWorkerConnectionMsg::Connected,
+        };
+        if let Err(e) = response_sender.send(reply) {
+            warn!("Replying to shared worker connection failed ({})", e);
+        }
+        worker
+            .clients
+            .entry(pipeline_id)
+            .or_default()
+            .push(response_sender);
+    }
+
+    /// A shared worker closed itself, new clients will start a new worker.
+    fn handle_shared_worker_closed(&mut self, worker_id: SharedWorkerId) {
+        self.shared_workers
+            .retain(|_, worker| worker.id != worker_id);
+    }
+
+    /// Disconnect a closed pipeline from the shared workers it is a client of,
+    /// shutting down the workers left without any client.
+    fn disconnect_pipeline_from_shared_workers(&mut self, pipeline_id: PipelineId) {
+        self.shared_workers.retain(|_, worker| {
+            if worker.clients.remove(&pipeline_id).is_none() || !worker.clients.is_empty() {
+                return true;
+            }
+            debug!("Exiting shared worker {}.", worker.id);
+            if let Err(e) = worker.control_sender.send(SharedWorkerControlMsg::Exit) {
+                warn!("Exit shared worker failed ({})", e);
+            }
+            false
+        });
+    }
+
+    /// Forget the shared workers whose event loop has closed, which are gone
+    /// even if they still have clients. New clients start a new worker.
+    fn remove_shared_workers_without_event_loop(&mut self) {
+        self.shared_workers.retain(|_, worker| {
+            if worker.is_running() {
+                return true;
+            }
+            debug!("Shared worker {} closed with its event loop.", worker.id);
+            worker.notify_clients_of_closed_event_loop();
+            // In single-process mode the worker thread outlives its event loop.
+            let _ = worker.control_sender.send(SharedWorkerControlMsg::Exit);
+            false
+        });
+    }
+
     fn handle_broadcast_storage_event(
         &self,
         pipeline_id: PipelineId,
@@ -2773,6 +3036,13 @@ where
             }
         }
 
+        debug!("Exiting shared workers.");
+        for (_, worker) in self.shared_workers.drain() {
+            if let Err(e) = worker.control_sender.send(SharedWorkerControlMsg::Exit) {
+                warn!("Exit shared worker failed ({})", e);
+            }
+        }
+
         debug!("Exiting Canvas Paint thread.");
         if let Err(e) = self.canvas_chan.send(ConstellationCanvasMsg::Exit) {
             warn!("Exit Canvas Paint thread failed ({})", e);
@@ -2837,7 +3107,25 @@ where
 
     fn handle_pipeline_exited(&mut self, pipeline_id: PipelineId) {
         debug!("Pipeline {:?} exited.", pipeline_id);
-        self.pipelines.remove(&pipeline_id);
+        let top_level_browsing_context_id = self
+            .pipelines
+            .remove(&pipeline_id)
+            .map(|pipeline| pipeline.top_level_browsing_context_id);
+        if self.picture_in_picture_pipeline == Some(pipeline_id) {
+            self.picture_in_picture_pipeline = None;
+            self.embedder_proxy.send((
+                top_level_browsing_context_id,
+                EmbedderMsg::PictureInPicture(PictureInPictureRequest::Exit),
+            ));
+        }
+
+        // The event loop of the pipeline may have gone with it,
+        // along with the shared workers it ran.
+        self.remove_shared_workers_without_event_loop();
+
+        for sw_manager in self.sw_managers.values() {
+            let _ = sw_manager.send(ServiceWorkerMsg::ClientExited(pipeline_id));
+        }
     }
 
     fn handle_send_error(&mut self, pipeline_id: PipelineId, err: IpcError) {
@@ -3090,6 +3378,12 @@ where
         let browsing_context_id = BrowsingContextId::from(top_level_browsing_context_id);
         self.close_browsing_context(browsing_context_id, ExitPipelineMode::Normal);
         self.browsers.remove(&top_level_browsing_context_id);
+        if let Some(mixer) = self
+            .audio_output_captures
+            .remove(&top_level_browsing_context_id)
+        {
+            let _ = mixer.send(AudioOutputMsg::Exit);
+        }
         if self.active_browser_id == Some(top_level_browsing_context_id) {
             self.active_browser_id = None;
         }
@@ -4939,6 +5233,20 @@ where
     fn handle_activate_document_msg(&mut self, pipeline_id: PipelineId) {
         debug!("Document ready to activate {}", pipeline_id);
 
+        // Documents of a browser whose audio output is captured join the mix.
+        if let Some(pipeline) = self.pipelines.get(&pipeline_id) {
+            if let Some(mixer) = self
+                .audio_output_captures
+                .get(&pipeline.top_level_browsing_context_id)
+            {
+                let msg = ConstellationControlMsg::SetAudioOutputCapture(
+                    pipeline_id,
+                    Some(mixer.clone()),
+                );
+                let _ = pipeline.event_loop.send(msg);
+            }
+        }
+
         // Find the pending change whose new pipeline id is pipeline_id.
         let pending_index = self
             .pending_changes
@@ -5414,6 +5722,9 @@ where
             self.close_browsing_context(*child_browsing_context, exit_mode);
         }
 
+        // A closed pipeline is no longer a client of any shared worker.
+        self.disconnect_pipeline_from_shared_workers(pipeline_id);
+
         // Note, we don't remove the pipeline now, we wait for the message to come back from
         // the pipeline.
         let pipeline = match self.pipelines.get(&pipeline_id) {
@@ -5577,4 +5888,67 @@ where
             error!("Got a media session action but no active media session is registered");
         }
     }
+
+    fn handle_capture_audio_output_msg(
+        &mut self,
+        top_level_browsing_context_id: TopLevelBrowsingContextId,
+        sender: Option<IpcSender<AudioOutputBuffer>>,
+    ) {
+        if let Some(mixer) = self
+            .audio_output_captures
+            .remove(&top_level_browsing_context_id)
+        {
+            let _ = mixer.send(AudioOutputMsg::Exit);
+        }
+        let mixer = sender.map(AudioOutputMixer::start);
+        if let Some(ref mixer) = mixer {
+            self.audio_output_captures
+                .insert(top_level_browsing_context_id, mixer.clone());
+        }
+
+        let pipelines = self.pipelines.values().filter(|pipeline| {
+            pipeline.top_level_browsing_context_id == top_level_browsing_context_id
+        });
+        for pipeline in pipelines {
+            let msg = ConstellationControlMsg::SetAudioOutputCapture(pipeline.id, mixer.clone());
+            if let Err(e) = pipeline.event_loop.send(msg) {
+                warn!(
+                    "Sending audio output capture to pipeline {} failed ({:?}).",
+                    pipeline.id, e
+                );
+            }
+        }
+    }
+
+    fn handle_picture_in_picture_action_msg(&mut self, action: PictureInPictureAction) {
+        // The pipeline exits picture-in-picture when asked to close, and the
+        // embedder is told once it did.
+        if let Some(pipeline_id) = self.picture_in_picture_pipeline {
+            self.send_picture_in_picture_action(pipeline_id, action);
+        } else {
+            warn!("Got a picture-in-picture action but no video is in picture-in-picture");
+        }
+    }
+
+    fn send_picture_in_picture_action(
+        &mut self,
+        pipeline_id: PipelineId,
+        action: PictureInPictureAction,
+    ) {
+        let result = match self.pipelines.get(&pipeline_id) {
+            None => {
+                return warn!(
+                    "Pipeline {} got picture-in-picture action after closure.",
+                    pipeline_id,
+                )
+            },
+            Some(pipeline) => {
+                let msg = ConstellationControlMsg::PictureInPictureAction(pipeline_id, action);
+                pipeline.event_loop.send(msg)
+            },
+        };
+        if let Err(e) = result {
+            self.handle_send_error(pipeline_id, e);
+        }
+    }
 }
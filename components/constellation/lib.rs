@@ -11,6 +11,7 @@ extern crate log;
 #[macro_use]
 extern crate serde;
 
+mod audio_output;
 mod browsingcontext;
 mod constellation;
 mod event_loop;
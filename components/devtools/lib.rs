@@ -36,6 +36,7 @@ use devtools_traits::{
     DevtoolScriptControlMsg, DevtoolsPageInfo, LogLevel, NavigationState, NetworkEvent,
 };
 use devtools_traits::{PageError, ScriptToDevtoolsControlMsg, WorkerId};
+use devtools_traits::{WebSocketEventType, WebSocketFrame};
 use embedder_traits::{EmbedderMsg, EmbedderProxy, PromptDefinition, PromptOrigin, PromptResult};
 use ipc_channel::ipc::{self, IpcSender};
 use msg::constellation_msg::{BrowsingContextId, PipelineId};
@@ -105,6 +106,47 @@ struct SecurityInfoUpdateMsg {
     state: String,
 }
 
+#[derive(Serialize)]
+struct WebSocketOpenedMsg {
+    from: String,
+    #[serde(rename = "type")]
+    type_: String,
+    httpChannelId: String,
+    effectiveURI: String,
+    protocols: String,
+    extensions: String,
+}
+
+#[derive(Serialize)]
+struct WebSocketFrameData {
+    #[serde(rename = "type")]
+    type_: String,
+    payload: String,
+    opCode: u8,
+    finBit: bool,
+    timeStamp: u64,
+}
+
+#[derive(Serialize)]
+struct WebSocketFrameMsg {
+    from: String,
+    #[serde(rename = "type")]
+    type_: String,
+    httpChannelId: String,
+    data: WebSocketFrameData,
+}
+
+#[derive(Serialize)]
+struct WebSocketClosedMsg {
+    from: String,
+    #[serde(rename = "type")]
+    type_: String,
+    httpChannelId: String,
+    wasClean: bool,
+    code: u16,
+    reason: String,
+}
+
 #[derive(Serialize)]
 struct ResponseStartUpdateMsg {
     from: String,
@@ -442,13 +484,14 @@ fn run_server(
             Some(name) => name,
             None => return,
         };
-        let netevent_actor_name =
-            find_network_event_actor(actors.clone(), actor_requests, request_id);
-        let mut actors = actors.lock().unwrap();
-        let actor = actors.find_mut::<NetworkEventActor>(&netevent_actor_name);
 
         match network_event {
             NetworkEvent::HttpRequest(httprequest) => {
+                let netevent_actor_name =
+                    find_network_event_actor(actors.clone(), actor_requests, request_id);
+                let mut actors = actors.lock().unwrap();
+                let actor = actors.find_mut::<NetworkEventActor>(&netevent_actor_name);
+
                 //Store the request information in the actor
                 actor.add_request(httprequest);
 
@@ -463,6 +506,11 @@ fn run_server(
                 }
             },
             NetworkEvent::HttpResponse(httpresponse) => {
+                let netevent_actor_name =
+                    find_network_event_actor(actors.clone(), actor_requests, request_id);
+                let mut actors = actors.lock().unwrap();
+                let actor = actors.find_mut::<NetworkEventActor>(&netevent_actor_name);
+
                 //Store the response information in the actor
                 actor.add_response(httpresponse);
 
@@ -546,6 +594,89 @@ fn run_server(
                     let _ = stream.write_merged_json_packet(&msg, &actor.response_headers());
                 }
             },
+            NetworkEvent::WebSocket(event) => {
+                handle_websocket_event(connections, console_actor_name, request_id, event.event);
+            },
+        }
+    }
+
+    // Mirror the events that the Firefox webSocket actor emits, so that
+    // frames show up in the network monitor's messages panel.
+    fn handle_websocket_event(
+        mut connections: Vec<TcpStream>,
+        console_actor_name: String,
+        request_id: String,
+        event: WebSocketEventType,
+    ) {
+        fn frame_data(type_: &str, frame: WebSocketFrame) -> WebSocketFrameData {
+            WebSocketFrameData {
+                type_: type_.to_owned(),
+                payload: String::from_utf8_lossy(&frame.payload).into_owned(),
+                opCode: frame.opcode,
+                finBit: true,
+                timeStamp: time::precise_time_ns() / 1000,
+            }
+        }
+
+        match event {
+            WebSocketEventType::Opened {
+                url,
+                protocol,
+                extensions,
+            } => {
+                let msg = WebSocketOpenedMsg {
+                    from: console_actor_name,
+                    type_: "webSocketOpened".to_owned(),
+                    httpChannelId: request_id,
+                    effectiveURI: url.into_string(),
+                    protocols: protocol.unwrap_or_default(),
+                    extensions: extensions.unwrap_or_default(),
+                };
+                for stream in &mut connections {
+                    let _ = stream.write_json_packet(&msg);
+                }
+            },
+            WebSocketEventType::FrameSent(frame) => {
+                let msg = WebSocketFrameMsg {
+                    from: console_actor_name,
+                    type_: "frameSent".to_owned(),
+                    httpChannelId: request_id,
+                    data: frame_data("sent", frame),
+                };
+                for stream in &mut connections {
+                    let _ = stream.write_json_packet(&msg);
+                }
+            },
+            WebSocketEventType::FrameReceived(frame) => {
+                let msg = WebSocketFrameMsg {
+                    from: console_actor_name,
+                    type_: "frameReceived".to_owned(),
+                    httpChannelId: request_id,
+                    data: frame_data("received", frame),
+                };
+                for stream in &mut connections {
+                    let _ = stream.write_json_packet(&msg);
+                }
+            },
+            WebSocketEventType::Closed {
+                code,
+                reason,
+                was_clean,
+            } => {
+                let msg = WebSocketClosedMsg {
+                    from: console_actor_name,
+                    type_: "webSocketClosed".to_owned(),
+                    httpChannelId: request_id,
+                    wasClean: was_clean,
+                    // No status code was received.
+                    // https://tools.ietf.org/html/rfc6455#section-7.1.5
+                    code: code.unwrap_or(1005),
+                    reason,
+                };
+                for stream in &mut connections {
+                    let _ = stream.write_json_packet(&msg);
+                }
+            },
         }
     }
 
@@ -695,6 +826,7 @@ fn run_server(
                 let pipeline_id = match network_event {
                     NetworkEvent::HttpResponse(ref response) => response.pipeline_id,
                     NetworkEvent::HttpRequest(ref request) => request.pipeline_id,
+                    NetworkEvent::WebSocket(ref event) => event.pipeline_id,
                 };
                 handle_network_event(
                     actors.clone(),
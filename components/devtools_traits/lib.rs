@@ -325,10 +325,39 @@ pub struct HttpResponse {
     pub pipeline_id: PipelineId,
 }
 
+#[derive(Debug, PartialEq)]
+pub struct WebSocketFrame {
+    pub opcode: u8,
+    pub payload: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum WebSocketEventType {
+    Opened {
+        url: ServoUrl,
+        protocol: Option<String>,
+        extensions: Option<String>,
+    },
+    FrameSent(WebSocketFrame),
+    FrameReceived(WebSocketFrame),
+    Closed {
+        code: Option<u16>,
+        reason: String,
+        was_clean: bool,
+    },
+}
+
+#[derive(Debug, PartialEq)]
+pub struct WebSocketEvent {
+    pub pipeline_id: PipelineId,
+    pub event: WebSocketEventType,
+}
+
 #[derive(Debug)]
 pub enum NetworkEvent {
     HttpRequest(HttpRequest),
     HttpResponse(HttpResponse),
+    WebSocket(WebSocketEvent),
 }
 
 impl TimelineMarker {
@@ -20,6 +20,7 @@ use msg::constellation_msg::{InputMethodType, PipelineId, TopLevelBrowsingContex
 use servo_url::ServoUrl;
 use std::fmt::{Debug, Error, Formatter};
 use webrender_api::units::{DeviceIntPoint, DeviceIntRect, DeviceIntSize};
+use webrender_api::ImageKey;
 
 pub use webxr_api::MainThreadWaker as EventLoopWaker;
 
@@ -207,6 +208,9 @@ pub enum EmbedderMsg {
     /// Notifies the embedder about media session events
     /// (i.e. when there is metadata for the active media session, playback state changes...).
     MediaSessionEvent(MediaSessionEvent),
+    /// Notifies the embedder about a video entering, updating or leaving
+    /// picture-in-picture.
+    PictureInPicture(PictureInPictureRequest),
     /// Report the status of Devtools Server with a token that can be used to bypass the permission prompt.
     OnDevtoolsStarted(Result<u16, ()>, String),
 }
@@ -243,6 +247,7 @@ impl Debug for EmbedderMsg {
             EmbedderMsg::BrowserCreated(..) => write!(f, "BrowserCreated"),
             EmbedderMsg::ReportProfile(..) => write!(f, "ReportProfile"),
             EmbedderMsg::MediaSessionEvent(..) => write!(f, "MediaSessionEvent"),
+            EmbedderMsg::PictureInPicture(..) => write!(f, "PictureInPicture"),
             EmbedderMsg::OnDevtoolsStarted(..) => write!(f, "OnDevtoolsStarted"),
             EmbedderMsg::ShowContextMenu(..) => write!(f, "ShowContextMenu"),
         }
@@ -316,6 +321,40 @@ pub enum MediaSessionEvent {
     SetPositionState(MediaPositionState),
 }
 
+/// A chunk of the mixed audio output of a webview, captured for the embedder.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AudioOutputBuffer {
+    /// The sample rate of the samples, in Hz.
+    pub sample_rate: u32,
+    /// The number of interleaved channels.
+    pub channels: u32,
+    /// Interleaved 32-bit float PCM samples.
+    pub samples: Vec<f32>,
+}
+
+/// The video frame shown in a picture-in-picture surface.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct PictureInPictureFrame {
+    /// The WebRender image the video renders its frames to, if any frame was
+    /// rendered yet. The key stays the same as long as the video size does.
+    pub image_key: Option<ImageKey>,
+    /// The intrinsic size of the video.
+    pub size: DeviceIntSize,
+}
+
+/// Type of requests sent from script to the embedder about picture-in-picture.
+/// https://w3c.github.io/picture-in-picture/
+#[derive(Clone, Deserialize, Serialize)]
+pub enum PictureInPictureRequest {
+    /// Host the given video frames in a separate surface. The embedder replies
+    /// with the size of the surface, or `None` if it refused to open one.
+    Enter(PictureInPictureFrame, IpcSender<Option<DeviceIntSize>>),
+    /// The video shown in picture-in-picture now renders to another image.
+    UpdateFrame(PictureInPictureFrame),
+    /// Close the picture-in-picture surface.
+    Exit,
+}
+
 /// Enum with variants that match the DOM PermissionName enum
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum PermissionName {
@@ -2024,6 +2024,7 @@ where
             PseudoElementType::After => &mut data.after_flow_construction_result,
             PseudoElementType::DetailsSummary => &mut data.details_summary_flow_construction_result,
             PseudoElementType::DetailsContent => &mut data.details_content_flow_construction_result,
+            PseudoElementType::Backdrop => &mut data.backdrop_flow_construction_result,
             PseudoElementType::Normal => &mut data.flow_construction_result,
         }
     }
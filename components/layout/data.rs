@@ -28,6 +28,8 @@ pub struct LayoutData {
 
     pub details_content_flow_construction_result: ConstructionResult,
 
+    pub backdrop_flow_construction_result: ConstructionResult,
+
     /// Various flags.
     pub flags: LayoutDataFlags,
 }
@@ -41,6 +43,7 @@ impl LayoutData {
             after_flow_construction_result: ConstructionResult::None,
             details_summary_flow_construction_result: ConstructionResult::None,
             details_content_flow_construction_result: ConstructionResult::None,
+            backdrop_flow_construction_result: ConstructionResult::None,
             flags: LayoutDataFlags::empty(),
         }
     }
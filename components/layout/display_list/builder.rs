@@ -53,6 +53,7 @@ use std::default::Default;
 use std::f32;
 use std::mem;
 use std::sync::Arc;
+use style::computed_values::_servo_top_layer::T as InTopLayer;
 use style::computed_values::border_style::T as BorderStyle;
 use style::computed_values::overflow_x::T as StyleOverflow;
 use style::computed_values::pointer_events::T as PointerEvents;
@@ -202,10 +203,16 @@ pub struct StackingContextCollectionState {
 
     /// The flow parent's content box, used to calculate sticky constraints.
     parent_stacking_relative_content_box: Rect<Au>,
+
+    /// The nodes of the elements in the top layer, from the bottom to the top.
+    top_layer: Vec<OpaqueNode>,
 }
 
 impl StackingContextCollectionState {
-    pub fn new(pipeline_id: PipelineId) -> StackingContextCollectionState {
+    pub fn new(
+        pipeline_id: PipelineId,
+        top_layer: Vec<OpaqueNode>,
+    ) -> StackingContextCollectionState {
         let root_clip_indices =
             ClippingAndScrolling::simple(ClipScrollNodeIndex::root_scroll_node());
 
@@ -236,9 +243,17 @@ impl StackingContextCollectionState {
             clip_stack: Vec::new(),
             containing_block_clip_stack: Vec::new(),
             parent_stacking_relative_content_box: Rect::zero(),
+            top_layer,
         }
     }
 
+    /// The position of `node` in the top layer, if it is there.
+    fn top_layer_index(&self, node: OpaqueNode) -> Option<usize> {
+        self.top_layer
+            .iter()
+            .position(|candidate| *candidate == node)
+    }
+
     fn allocate_stacking_context_info(
         &mut self,
         stacking_context_type: StackingContextType,
@@ -265,6 +280,16 @@ impl StackingContextCollectionState {
         parent_id: StackingContextId,
         stacking_context: StackingContext,
     ) {
+        // Elements in the top layer, and their ::backdrop, are painted above
+        // every other stacking context of the document, in the order of the
+        // top layer.
+        //
+        // https://fullscreen.spec.whatwg.org/#new-stacking-layer
+        let parent_id = if stacking_context.in_top_layer == InTopLayer::Top {
+            StackingContextId::root()
+        } else {
+            parent_id
+        };
         self.stacking_context_info
             .get_mut(&parent_id)
             .unwrap()
@@ -643,6 +668,7 @@ impl Fragment {
             StackingContextType::Real,
             established_reference_frame,
             state.current_clipping_and_scrolling,
+            state.top_layer_index(self.node),
         );
         state.add_stacking_context(current_stacking_context_id, stacking_context);
         true
@@ -1951,6 +1977,7 @@ impl Fragment {
         context_type: StackingContextType,
         established_reference_frame: Option<ClipScrollNodeIndex>,
         parent_clipping_and_scrolling: ClippingAndScrolling,
+        top_layer_index: Option<usize>,
     ) -> StackingContext {
         let border_box = self.stacking_relative_border_box(
             &base_flow.stacking_relative_position,
@@ -1987,6 +2014,7 @@ impl Fragment {
             overflow.to_layout(),
             self.effective_z_index(),
             self.style().get_box()._servo_top_layer,
+            top_layer_index,
             filters,
             self.style().get_effects().mix_blend_mode.to_layout(),
             self.transform_matrix(&border_box),
@@ -2729,6 +2757,7 @@ impl BlockFlow {
             stacking_context_type,
             None,
             parent_clipping_and_scrolling,
+            state.top_layer_index(self.fragment.node),
         );
         state.add_stacking_context(parent_stacking_context_id, new_context);
 
@@ -2762,6 +2791,7 @@ impl BlockFlow {
             StackingContextType::Real,
             established_reference_frame,
             parent_clipping_and_scrolling,
+            state.top_layer_index(self.fragment.node),
         );
 
         state.add_stacking_context(parent_stacking_context_id, stacking_context);
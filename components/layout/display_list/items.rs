@@ -177,6 +177,10 @@ pub struct StackingContext {
     /// Whether this is the top layer.
     pub in_top_layer: InTopLayer,
 
+    /// The position in the top layer of the element this stacking context
+    /// belongs to, if any, which orders the stacking contexts of the top layer.
+    pub top_layer_index: Option<usize>,
+
     /// CSS filters to be applied to this stacking context (including opacity).
     pub filters: Vec<FilterOp>,
 
@@ -209,6 +213,7 @@ impl StackingContext {
         overflow: LayoutRect,
         z_index: i32,
         in_top_layer: InTopLayer,
+        top_layer_index: Option<usize>,
         filters: Vec<FilterOp>,
         mix_blend_mode: MixBlendMode,
         transform: Option<LayoutTransform>,
@@ -230,6 +235,7 @@ impl StackingContext {
             overflow,
             z_index,
             in_top_layer,
+            top_layer_index,
             filters,
             mix_blend_mode,
             transform,
@@ -249,6 +255,7 @@ impl StackingContext {
             LayoutRect::zero(),
             0,
             InTopLayer::None,
+            None,
             vec![],
             MixBlendMode::Normal,
             None,
@@ -283,7 +290,7 @@ impl Ord for StackingContext {
     fn cmp(&self, other: &Self) -> Ordering {
         if self.in_top_layer == InTopLayer::Top {
             if other.in_top_layer == InTopLayer::Top {
-                return Ordering::Equal;
+                return self.top_layer_index.cmp(&other.top_layer_index);
             } else {
                 return Ordering::Greater;
             }
@@ -916,6 +916,8 @@ fn process_resolved_style_request_internal<'dom>(
     let layout_el = match *pseudo {
         Some(PseudoElement::Before) => layout_el.get_before_pseudo(),
         Some(PseudoElement::After) => layout_el.get_after_pseudo(),
+        Some(PseudoElement::Backdrop) => layout_el.get_backdrop_pseudo(),
+        Some(PseudoElement::Cue) |
         Some(PseudoElement::DetailsSummary) |
         Some(PseudoElement::DetailsContent) |
         Some(PseudoElement::Selection) => None,
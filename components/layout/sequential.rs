@@ -18,6 +18,7 @@ use crate::traversal::{InorderFlowTraversal, PostorderFlowTraversal, PreorderFlo
 use app_units::Au;
 use euclid::default::{Point2D, Rect, Size2D, Vector2D};
 use servo_config::opts;
+use style::dom::OpaqueNode;
 use style::servo::restyle_damage::ServoRestyleDamage;
 use webrender_api::units::LayoutPoint;
 use webrender_api::PropertyBinding;
@@ -77,8 +78,9 @@ pub fn build_display_list_for_subtree<'a>(
     layout_context: &'a LayoutContext,
     background_color: webrender_api::ColorF,
     client_size: Size2D<Au>,
+    top_layer: Vec<OpaqueNode>,
 ) -> DisplayListBuildState<'a> {
-    let mut state = StackingContextCollectionState::new(layout_context.id);
+    let mut state = StackingContextCollectionState::new(layout_context.id, top_layer);
     flow_root.collect_stacking_contexts(&mut state);
 
     let mut state = DisplayListBuildState::new(layout_context, state);
@@ -18,6 +18,7 @@ use style::computed_values::float::T as ComputedFloat;
 use style::computed_values::mix_blend_mode::T as ComputedMixBlendMode;
 use style::computed_values::overflow_x::T as ComputedOverflow;
 use style::computed_values::position::T as ComputedPosition;
+use style::dom::OpaqueNode;
 use style::properties::ComputedValues;
 use style::values::computed::ClipRectOrAuto;
 use style::values::computed::Length;
@@ -70,17 +71,42 @@ pub(crate) struct StackingContextBuilder<'a> {
     nearest_reference_frame: wr::SpatialId,
 
     wr: &'a mut wr::DisplayListBuilder,
+
+    /// The nodes of the elements in the top layer, from the bottom to the top.
+    top_layer: &'a [OpaqueNode],
+
+    /// The stacking contexts established by the elements of the top layer and
+    /// their `::backdrop`, along with the position of the element in the top
+    /// layer.
+    top_layer_stacking_contexts: Vec<(Option<usize>, StackingContext)>,
 }
 
 impl<'a> StackingContextBuilder<'a> {
-    pub fn new(wr: &'a mut wr::DisplayListBuilder) -> Self {
+    pub fn new(wr: &'a mut wr::DisplayListBuilder, top_layer: &'a [OpaqueNode]) -> Self {
         Self {
             current_space_and_clip: wr::SpaceAndClipInfo::root_scroll(wr.pipeline_id),
             nearest_reference_frame: wr::SpatialId::root_reference_frame(wr.pipeline_id),
             wr,
+            top_layer,
+            top_layer_stacking_contexts: vec![],
         }
     }
 
+    /// Appends the stacking contexts of the top layer to the already sorted
+    /// root stacking context, so that they are painted above every other one,
+    /// in the order of the top layer.
+    ///
+    /// <https://fullscreen.spec.whatwg.org/#new-stacking-layer>
+    pub(crate) fn append_top_layer(&mut self, root: &mut StackingContext) {
+        let mut top_layer = mem::replace(&mut self.top_layer_stacking_contexts, vec![]);
+        top_layer.sort_by_key(|(index, _)| *index);
+        root.stacking_contexts.extend(
+            top_layer
+                .into_iter()
+                .map(|(_, stacking_context)| stacking_context),
+        );
+    }
+
     fn clipping_and_scrolling_scope<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
         let previous_space_and_clip = self.current_space_and_clip;
         let previous_nearest_reference_frame = self.nearest_reference_frame;
@@ -642,9 +668,20 @@ impl BoxFragment {
         }
 
         child_stacking_context.sort();
-        parent_stacking_context
-            .stacking_contexts
-            .push(child_stacking_context);
+        if self.style.in_top_layer() {
+            let node = self.tag.node();
+            let index = builder
+                .top_layer
+                .iter()
+                .position(|element| *element == node);
+            builder
+                .top_layer_stacking_contexts
+                .push((index, child_stacking_context));
+        } else {
+            parent_stacking_context
+                .stacking_contexts
+                .push(child_stacking_context);
+        }
         parent_stacking_context
             .stacking_contexts
             .append(&mut stolen_children);
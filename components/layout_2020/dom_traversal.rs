@@ -20,7 +20,7 @@ use servo_arc::Arc as ServoArc;
 use std::borrow::Cow;
 use std::marker::PhantomData as marker;
 use std::sync::{Arc, Mutex};
-use style::dom::{OpaqueNode, TNode};
+use style::dom::{OpaqueNode, TElement, TNode};
 use style::properties::ComputedValues;
 use style::selector_parser::PseudoElement;
 use style::values::generics::counters::Content;
@@ -30,6 +30,7 @@ use style::values::generics::counters::ContentItem;
 pub(crate) enum WhichPseudoElement {
     Before,
     After,
+    Backdrop,
 }
 
 /// A data structure used to pass and store related layout information together to
@@ -155,6 +156,10 @@ fn traverse_element<'dom, Node>(
             }
         },
         Display::GeneratingBox(display) => {
+            // The `::backdrop` of an element in the top layer is right below it.
+            // https://fullscreen.spec.whatwg.org/#::backdrop-pseudo-element
+            traverse_pseudo_element(WhichPseudoElement::Backdrop, element, context, handler);
+
             let contents = replaced.map_or(Contents::OfElement, Contents::Replaced);
             let box_slot = element.element_box_slot();
             let info = NodeAndStyleInfo::new(element, style);
@@ -300,10 +305,13 @@ where
     match which {
         WhichPseudoElement::Before => element.to_threadsafe().get_before_pseudo(),
         WhichPseudoElement::After => element.to_threadsafe().get_after_pseudo(),
+        WhichPseudoElement::Backdrop => element.to_threadsafe().get_backdrop_pseudo(),
     }
     .and_then(|pseudo_element| {
         let style = pseudo_element.style(context.shared_context());
-        if style.ineffective_content_property() {
+        // Unlike `::before` and `::after`, `::backdrop` generates a box
+        // whatever its `content` is.
+        if style.ineffective_content_property() && !matches!(which, WhichPseudoElement::Backdrop) {
             None
         } else {
             Some(style)
@@ -350,7 +358,8 @@ where
             }
             vec
         },
-        Content::Normal | Content::None => unreachable!(),
+        // Only `::backdrop` gets here without any content.
+        Content::Normal | Content::None => vec![],
     }
 }
 
@@ -396,8 +405,8 @@ pub(crate) trait NodeExt<'dom>: 'dom + Copy + LayoutNode<'dom> + Send + Sync {
     /// adjusted for `image_density`.
     fn as_image(self) -> Option<(Option<Arc<NetImage>>, PhysicalSize<f64>)>;
     fn as_canvas(self) -> Option<(CanvasInfo, PhysicalSize<f64>)>;
-    fn first_child(self) -> Option<Self>;
-    fn next_sibling(self) -> Option<Self>;
+
+    /// The parent of this node in the flat tree.
     fn parent_node(self) -> Option<Self>;
     fn style(self, context: &LayoutContext) -> ServoArc<ComputedValues>;
 
@@ -407,7 +416,8 @@ pub(crate) trait NodeExt<'dom>: 'dom + Copy + LayoutNode<'dom> + Send + Sync {
     fn pseudo_element_box_slot(&self, which: WhichPseudoElement) -> BoxSlot<'dom>;
     fn unset_pseudo_element_box(self, which: WhichPseudoElement);
 
-    /// Remove boxes for the element itself, and its `:before` and `:after` if any.
+    /// Remove boxes for the element itself, and its `:before`, `:after` and
+    /// `::backdrop` if any.
     fn unset_all_boxes(self);
 }
 
@@ -462,16 +472,8 @@ where
         ))
     }
 
-    fn first_child(self) -> Option<Self> {
-        TNode::first_child(&self)
-    }
-
-    fn next_sibling(self) -> Option<Self> {
-        TNode::next_sibling(&self)
-    }
-
     fn parent_node(self) -> Option<Self> {
-        TNode::parent_node(&self)
+        self.traversal_parent().map(|parent| parent.as_node())
     }
 
     fn style(self, context: &LayoutContext) -> ServoArc<ComputedValues> {
@@ -498,6 +500,7 @@ where
         let cell = match which {
             WhichPseudoElement::Before => &data.pseudo_before_box,
             WhichPseudoElement::After => &data.pseudo_after_box,
+            WhichPseudoElement::Backdrop => &data.pseudo_backdrop_box,
         };
         BoxSlot::new(cell.clone())
     }
@@ -507,6 +510,7 @@ where
         let cell = match which {
             WhichPseudoElement::Before => &data.pseudo_before_box,
             WhichPseudoElement::After => &data.pseudo_after_box,
+            WhichPseudoElement::Backdrop => &data.pseudo_backdrop_box,
         };
         *cell.borrow_mut() = None;
     }
@@ -516,20 +520,21 @@ where
         *data.self_box.borrow_mut() = None;
         *data.pseudo_before_box.borrow_mut() = None;
         *data.pseudo_after_box.borrow_mut() = None;
+        *data.pseudo_backdrop_box.borrow_mut() = None;
         // Stylo already takes care of removing all layout data
         // for DOM descendants of elements with `display: none`.
     }
 }
 
+/// Iterates over the children of `parent` in the flat tree, that is the
+/// children of its shadow root if it is a shadow host, or its assigned nodes
+/// if it is a slot.
 pub(crate) fn iter_child_nodes<'dom, Node>(parent: Node) -> impl Iterator<Item = Node>
 where
     Node: NodeExt<'dom>,
 {
-    let mut next = parent.first_child();
-    std::iter::from_fn(move || {
-        next.map(|child| {
-            next = child.next_sibling();
-            child
-        })
-    })
+    parent
+        .as_element()
+        .into_iter()
+        .flat_map(|element| element.traversal_children())
 }
@@ -12,6 +12,7 @@ pub struct LayoutDataForElement {
     pub(super) self_box: ArcRefCell<Option<LayoutBox>>,
     pub(super) pseudo_before_box: ArcRefCell<Option<LayoutBox>>,
     pub(super) pseudo_after_box: ArcRefCell<Option<LayoutBox>>,
+    pub(super) pseudo_backdrop_box: ArcRefCell<Option<LayoutBox>>,
 }
 
 pub(super) enum LayoutBox {
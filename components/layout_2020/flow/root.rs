@@ -385,10 +385,15 @@ impl BoxTree {
 }
 
 impl FragmentTree {
-    pub fn build_display_list(&self, builder: &mut crate::display_list::DisplayListBuilder) {
+    pub fn build_display_list(
+        &self,
+        builder: &mut crate::display_list::DisplayListBuilder,
+        top_layer: &[OpaqueNode],
+    ) {
         let mut stacking_context = StackingContext::create_root(&builder.wr);
         {
-            let mut stacking_context_builder = StackingContextBuilder::new(&mut builder.wr);
+            let mut stacking_context_builder =
+                StackingContextBuilder::new(&mut builder.wr, top_layer);
             let containing_block_info = ContainingBlockInfo {
                 rect: self.initial_containing_block,
                 nearest_containing_block: None,
@@ -407,9 +412,10 @@ impl FragmentTree {
                     StackingContextBuildMode::SkipHoisted,
                 );
             }
-        }
 
-        stacking_context.sort();
+            stacking_context.sort();
+            stacking_context_builder.append_top_layer(&mut stacking_context);
+        }
 
         // Paint the canvas’ background (if any) before/under everything else
         stacking_context.build_canvas_background_display_list(
@@ -452,6 +458,7 @@ impl FragmentTree {
                 Tag::Node(node) => (node, None),
                 Tag::BeforePseudo(node) => (node, Some(PseudoElement::Before)),
                 Tag::AfterPseudo(node) => (node, Some(PseudoElement::After)),
+                Tag::BackdropPseudo(node) => (node, Some(PseudoElement::Backdrop)),
             };
             set.remove(&AnimationSetKey::new(node, pseudo));
             None::<()>
@@ -32,12 +32,16 @@ pub(crate) enum Tag {
     Node(OpaqueNode),
     BeforePseudo(OpaqueNode),
     AfterPseudo(OpaqueNode),
+    BackdropPseudo(OpaqueNode),
 }
 
 impl Tag {
     pub(crate) fn node(&self) -> OpaqueNode {
         match self {
-            Self::Node(node) | Self::AfterPseudo(node) | Self::BeforePseudo(node) => *node,
+            Self::Node(node) |
+            Self::AfterPseudo(node) |
+            Self::BeforePseudo(node) |
+            Self::BackdropPseudo(node) => *node,
         }
     }
 
@@ -46,6 +50,7 @@ impl Tag {
             Self::Node(node) => (node, FragmentType::FragmentBody),
             Self::AfterPseudo(node) => (node, FragmentType::BeforePseudoContent),
             Self::BeforePseudo(node) => (node, FragmentType::AfterPseudoContent),
+            Self::BackdropPseudo(node) => (node, FragmentType::BackdropPseudoContent),
         };
         combine_id_with_fragment_type(node.id() as usize, content_type) as u64
     }
@@ -59,6 +64,7 @@ impl Tag {
             None => Self::Node(opaque_node),
             Some(WhichPseudoElement::Before) => Self::BeforePseudo(opaque_node),
             Some(WhichPseudoElement::After) => Self::AfterPseudo(opaque_node),
+            Some(WhichPseudoElement::Backdrop) => Self::BackdropPseudo(opaque_node),
         }
     }
 }
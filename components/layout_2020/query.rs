@@ -230,6 +230,7 @@ pub fn process_resolved_style_request<'dom>(
         None => Some(layout_element),
         Some(PseudoElement::Before) => layout_element.get_before_pseudo(),
         Some(PseudoElement::After) => layout_element.get_after_pseudo(),
+        Some(PseudoElement::Backdrop) => layout_element.get_backdrop_pseudo(),
         Some(_) => {
             warn!("Got unexpected pseudo element type!");
             None
@@ -266,6 +267,7 @@ pub fn process_resolved_style_request<'dom>(
         None => Tag::Node(opaque),
         Some(PseudoElement::Before) => Tag::BeforePseudo(opaque),
         Some(PseudoElement::After) => Tag::AfterPseudo(opaque),
+        Some(PseudoElement::Backdrop) => Tag::BackdropPseudo(opaque),
         Some(_) => unreachable!("Should have returned before this point."),
     };
 
@@ -1041,11 +1041,19 @@ impl LayoutThread {
                 {
                     if reflow_goal.needs_display_list() {
                         let background_color = get_root_flow_background_color(layout_root);
+                        let top_layer = document.map_or(vec![], |document| {
+                            document
+                                .top_layer()
+                                .into_iter()
+                                .map(|element| element.as_node().opaque())
+                                .collect()
+                        });
                         let mut build_state = sequential::build_display_list_for_subtree(
                             layout_root,
                             layout_context,
                             background_color,
                             data.page_clip_rect.size,
+                            top_layer,
                         );
 
                         debug!("Done building display list.");
@@ -70,6 +70,7 @@ use std::borrow::Cow;
 use std::fmt;
 use std::fmt::Debug;
 use std::hash::{Hash, Hasher};
+use std::slice;
 use std::sync::atomic::Ordering;
 use std::sync::Arc as StdArc;
 use style::animation::AnimationSetKey;
@@ -77,7 +78,7 @@ use style::applicable_declarations::ApplicableDeclarationBlock;
 use style::attr::AttrValue;
 use style::context::SharedStyleContext;
 use style::data::ElementData;
-use style::dom::{DomChildren, LayoutIterator, NodeInfo, OpaqueNode};
+use style::dom::{LayoutIterator, NodeInfo, OpaqueNode};
 use style::dom::{TDocument, TElement, TNode, TShadowRoot};
 use style::element_state::*;
 use style::font_metrics::ServoMetricsProvider;
@@ -151,6 +152,35 @@ impl<'ln> NodeInfo for ServoLayoutNode<'ln> {
     }
 }
 
+/// An iterator over the children of a node in the flat tree, where the
+/// children of a slot are its assigned nodes.
+pub enum ServoFlatTreeChildren<'dom> {
+    /// The children of a node that isn't a slot with assigned nodes.
+    Children(Option<ServoLayoutNode<'dom>>),
+    /// The nodes assigned to a slot.
+    AssignedNodes(slice::Iter<'dom, LayoutDom<'dom, Node>>),
+}
+
+impl<'dom> Iterator for ServoFlatTreeChildren<'dom> {
+    type Item = ServoLayoutNode<'dom>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match *self {
+            ServoFlatTreeChildren::Children(ref mut next) => {
+                let node = next.take()?;
+                *next = node
+                    .node
+                    .next_sibling_ref()
+                    .map(ServoLayoutNode::from_layout_js);
+                Some(node)
+            },
+            ServoFlatTreeChildren::AssignedNodes(ref mut nodes) => {
+                nodes.next().cloned().map(ServoLayoutNode::from_layout_js)
+            },
+        }
+    }
+}
+
 #[derive(Clone, Copy, PartialEq)]
 pub struct ServoShadowRoot<'dom> {
     /// The wrapped shadow root.
@@ -230,6 +260,9 @@ impl<'ln> TNode for ServoLayoutNode<'ln> {
     }
 
     fn traversal_parent(&self) -> Option<ServoLayoutElement<'ln>> {
+        if let Some(slot) = self.node.assigned_slot_for_layout() {
+            return Some(ServoLayoutElement::from_layout_js(slot.upcast()));
+        }
         let parent = self.parent_node()?;
         if let Some(shadow) = parent.as_shadow_root() {
             return Some(shadow.host());
@@ -389,6 +422,15 @@ impl<'ld> ServoLayoutDocument<'ld> {
         }
     }
 
+    /// The elements of the top layer, from the bottom to the top.
+    pub fn top_layer(&self) -> Vec<ServoLayoutElement<'ld>> {
+        self.document
+            .top_layer()
+            .into_iter()
+            .map(ServoLayoutElement::from_layout_js)
+            .collect()
+    }
+
     pub fn flush_shadow_roots_stylesheets(
         &self,
         device: &Device,
@@ -429,7 +471,7 @@ impl<'le> fmt::Debug for ServoLayoutElement<'le> {
 
 impl<'le> TElement for ServoLayoutElement<'le> {
     type ConcreteNode = ServoLayoutNode<'le>;
-    type TraversalChildrenIterator = DomChildren<Self::ConcreteNode>;
+    type TraversalChildrenIterator = ServoFlatTreeChildren<'le>;
 
     type FontMetricsProvider = ServoMetricsProvider;
 
@@ -438,11 +480,23 @@ impl<'le> TElement for ServoLayoutElement<'le> {
     }
 
     fn traversal_children(&self) -> LayoutIterator<Self::TraversalChildrenIterator> {
-        LayoutIterator(if let Some(shadow) = self.shadow_root() {
-            shadow.as_node().dom_children()
-        } else {
-            self.as_node().dom_children()
-        })
+        if let Some(shadow) = self.shadow_root() {
+            return LayoutIterator(ServoFlatTreeChildren::Children(
+                shadow
+                    .as_node()
+                    .node
+                    .first_child_ref()
+                    .map(ServoLayoutNode::from_layout_js),
+            ));
+        }
+        let node = self.element.upcast::<Node>();
+        let assigned_nodes = node.assigned_nodes_ref();
+        if !assigned_nodes.is_empty() {
+            return LayoutIterator(ServoFlatTreeChildren::AssignedNodes(assigned_nodes.iter()));
+        }
+        LayoutIterator(ServoFlatTreeChildren::Children(
+            node.first_child_ref().map(ServoLayoutNode::from_layout_js),
+        ))
     }
 
     fn is_html_element(&self) -> bool {
@@ -711,6 +765,10 @@ impl<'le> TElement for ServoLayoutElement<'le> {
             .map(ServoShadowRoot::from_layout_js)
     }
 
+    fn implemented_pseudo_element(&self) -> Option<PseudoElement> {
+        self.element.implemented_pseudo_element_for_layout()
+    }
+
     fn local_name(&self) -> &LocalName {
         self.element.local_name()
     }
@@ -797,6 +855,18 @@ impl<'le> ::selectors::Element for ServoLayoutElement<'le> {
         self.containing_shadow().map(|s| s.host())
     }
 
+    fn pseudo_element_originating_element(&self) -> Option<Self> {
+        debug_assert!(self.is_pseudo_element());
+        self.containing_shadow_host()
+    }
+
+    fn assigned_slot(&self) -> Option<Self> {
+        self.element
+            .upcast()
+            .assigned_slot_for_layout()
+            .map(|slot| ServoLayoutElement::from_layout_js(slot.upcast()))
+    }
+
     fn prev_sibling_element(&self) -> Option<ServoLayoutElement<'le>> {
         let mut node = self.as_node();
         while let Some(sibling) = node.prev_sibling() {
@@ -876,15 +946,20 @@ impl<'le> ::selectors::Element for ServoLayoutElement<'le> {
     }
 
     fn is_pseudo_element(&self) -> bool {
-        false
+        self.element
+            .implemented_pseudo_element_for_layout()
+            .is_some()
     }
 
     fn match_pseudo_element(
         &self,
-        _pseudo: &PseudoElement,
+        pseudo: &PseudoElement,
         _context: &mut MatchingContext<Self::Impl>,
     ) -> bool {
-        false
+        self.element
+            .implemented_pseudo_element_for_layout()
+            .as_ref() ==
+            Some(pseudo)
     }
 
     fn match_non_ts_pseudo_class<F>(
@@ -903,6 +978,8 @@ impl<'le> ::selectors::Element for ServoLayoutElement<'le> {
 
             NonTSPseudoClass::Lang(ref lang) => self.match_element_lang(None, &*lang),
 
+            NonTSPseudoClass::State(ref state) => self.element.has_custom_state_for_layout(&*state),
+
             NonTSPseudoClass::ServoNonZeroBorder => {
                 match self
                     .element
@@ -926,6 +1003,7 @@ impl<'le> ::selectors::Element for ServoLayoutElement<'le> {
             NonTSPseudoClass::Disabled |
             NonTSPseudoClass::Checked |
             NonTSPseudoClass::Indeterminate |
+            NonTSPseudoClass::Modal |
             NonTSPseudoClass::ReadWrite |
             NonTSPseudoClass::PlaceholderShown |
             NonTSPseudoClass::Target => self
@@ -1008,13 +1086,13 @@ impl<'a> PartialEq for ServoThreadSafeLayoutNode<'a> {
 impl<'ln> DangerousThreadSafeLayoutNode<'ln> for ServoThreadSafeLayoutNode<'ln> {
     unsafe fn dangerous_first_child(&self) -> Option<Self> {
         self.get_jsmanaged()
-            .first_child_ref()
+            .flat_tree_first_child_ref()
             .map(ServoLayoutNode::from_layout_js)
             .map(Self::new)
     }
     unsafe fn dangerous_next_sibling(&self) -> Option<Self> {
         self.get_jsmanaged()
-            .next_sibling_ref()
+            .flat_tree_next_sibling_ref()
             .map(ServoLayoutNode::from_layout_js)
             .map(Self::new)
     }
@@ -1065,7 +1143,7 @@ impl<'ln> ThreadSafeLayoutNode<'ln> for ServoThreadSafeLayoutNode<'ln> {
     }
 
     fn parent_style(&self) -> Arc<ComputedValues> {
-        let parent = self.node.parent_node().unwrap().as_element().unwrap();
+        let parent = self.node.traversal_parent().unwrap();
         let parent_data = parent.borrow_data().unwrap();
         parent_data.styles.primary().clone()
     }
@@ -1201,6 +1279,15 @@ impl<'ln> ThreadSafeLayoutNode<'ln> for ServoThreadSafeLayoutNode<'ln> {
     }
 }
 
+/// Returns the `::backdrop` of `node` if it has one, since it is iterated
+/// right before `node` itself.
+fn backdrop_or_self<'dom, ConcreteNode>(node: ConcreteNode) -> ConcreteNode
+where
+    ConcreteNode: ThreadSafeLayoutNode<'dom>,
+{
+    node.get_backdrop_pseudo().unwrap_or(node)
+}
+
 pub struct ThreadSafeLayoutNodeChildrenIterator<ConcreteNode> {
     current_node: Option<ConcreteNode>,
     parent_node: ConcreteNode,
@@ -1215,7 +1302,7 @@ where
             PseudoElementType::Normal => parent
                 .get_before_pseudo()
                 .or_else(|| parent.get_details_summary_pseudo())
-                .or_else(|| unsafe { parent.dangerous_first_child() }),
+                .or_else(|| unsafe { parent.dangerous_first_child() }.map(backdrop_or_self)),
             PseudoElementType::DetailsContent | PseudoElementType::DetailsSummary => unsafe {
                 parent.dangerous_first_child()
             },
@@ -1236,7 +1323,9 @@ where
     fn next(&mut self) -> Option<ConcreteNode> {
         use selectors::Element;
         match self.parent_node.get_pseudo_element_type() {
-            PseudoElementType::Before | PseudoElementType::After => None,
+            PseudoElementType::Before | PseudoElementType::After | PseudoElementType::Backdrop => {
+                None
+            },
 
             PseudoElementType::DetailsSummary => {
                 let mut current_node = self.current_node.clone();
@@ -1284,10 +1373,17 @@ where
                         PseudoElementType::Before => self
                             .parent_node
                             .get_details_summary_pseudo()
-                            .or_else(|| unsafe { self.parent_node.dangerous_first_child() })
+                            .or_else(|| {
+                                unsafe { self.parent_node.dangerous_first_child() }
+                                    .map(backdrop_or_self)
+                            })
                             .or_else(|| self.parent_node.get_after_pseudo()),
                         PseudoElementType::Normal => unsafe { node.dangerous_next_sibling() }
+                            .map(backdrop_or_self)
                             .or_else(|| self.parent_node.get_after_pseudo()),
+                        PseudoElementType::Backdrop => node.as_element().map(|element| {
+                            element.with_pseudo(PseudoElementType::Normal).as_node()
+                        }),
                         PseudoElementType::DetailsSummary => {
                             self.parent_node.get_details_content_pseudo()
                         },
@@ -1322,7 +1322,14 @@ impl LayoutThread {
             display_list.wr.dump_serialized_display_list();
         }
 
-        fragment_tree.build_display_list(&mut display_list);
+        let top_layer: Vec<_> = document.map_or(vec![], |document| {
+            document
+                .top_layer()
+                .into_iter()
+                .map(|element| element.as_node().opaque())
+                .collect()
+        });
+        fragment_tree.build_display_list(&mut display_list, &top_layer);
 
         if self.dump_flow_tree {
             fragment_tree.print();
@@ -0,0 +1,165 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The encoders and decoders backing the WebCodecs interfaces.
+//!
+//! The embedder registers a `CodecBackend` at startup with
+//! `set_codec_backend`. Without one, no codec is supported.
+
+use ipc_channel::ipc::IpcSender;
+use std::sync::{Arc, Mutex, RwLock};
+
+/// The kinds of codec a codec string can be checked against.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CodecKind {
+    AudioDecoder,
+    VideoDecoder,
+    VideoEncoder,
+}
+
+#[derive(Debug)]
+pub enum CodecError {
+    /// No backend is registered, or it does not handle the codec.
+    NotSupported,
+    /// The backend failed to set up the codec.
+    Backend(String),
+}
+
+/// A chunk of encoded audio or video data.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EncodedChunk {
+    /// Whether the chunk can be decoded without the preceding ones.
+    pub key: bool,
+    /// The presentation timestamp, in microseconds.
+    pub timestamp: i64,
+    /// The duration, in microseconds.
+    pub duration: Option<u64>,
+    pub data: Vec<u8>,
+}
+
+/// A raw video frame, as packed BGRA pixels.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct VideoFrameData {
+    pub width: u32,
+    pub height: u32,
+    /// The presentation timestamp, in microseconds.
+    pub timestamp: i64,
+    /// The duration, in microseconds.
+    pub duration: Option<u64>,
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EncoderSettings {
+    pub codec: String,
+    pub width: u32,
+    pub height: u32,
+    /// The target bitrate, in bits per second.
+    pub bitrate: Option<u64>,
+    pub framerate: Option<f64>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum DecoderSettings {
+    Audio {
+        codec: String,
+        description: Option<Vec<u8>>,
+        sample_rate: u32,
+        number_of_channels: u32,
+    },
+    Video {
+        codec: String,
+        description: Option<Vec<u8>>,
+        coded_width: Option<u32>,
+        coded_height: Option<u32>,
+    },
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub enum EncoderEvent {
+    Chunk(EncodedChunk),
+    /// All the frames passed to the encoder before the last flush were
+    /// output.
+    Flushed,
+    Error(String),
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub enum DecoderEvent {
+    VideoFrame(VideoFrameData),
+    /// Decoded audio, as planar 32-bit float samples.
+    AudioData {
+        sample_rate: f32,
+        number_of_channels: u32,
+        number_of_frames: u32,
+        /// The presentation timestamp, in microseconds.
+        timestamp: i64,
+        data: Vec<f32>,
+    },
+    /// All the chunks passed to the decoder before the last flush were
+    /// output.
+    Flushed,
+    Error(String),
+}
+
+pub trait Encoder: Send {
+    fn encode(&mut self, frame: VideoFrameData, key_frame: bool);
+    fn flush(&mut self);
+    fn close(&mut self);
+}
+
+pub trait Decoder: Send {
+    fn decode(&mut self, chunk: EncodedChunk);
+    fn flush(&mut self);
+    fn close(&mut self);
+}
+
+pub trait CodecBackend: Send + Sync {
+    fn supports_codec(&self, codec: &str, kind: CodecKind) -> bool;
+    fn create_encoder(
+        &self,
+        settings: EncoderSettings,
+        sender: IpcSender<EncoderEvent>,
+    ) -> Result<Arc<Mutex<dyn Encoder>>, CodecError>;
+    fn create_decoder(
+        &self,
+        settings: DecoderSettings,
+        sender: IpcSender<DecoderEvent>,
+    ) -> Result<Arc<Mutex<dyn Decoder>>, CodecError>;
+}
+
+lazy_static! {
+    static ref CODEC_BACKEND: RwLock<Option<Box<dyn CodecBackend>>> = RwLock::new(None);
+}
+
+/// Registers the backend used by the codecs created in this process.
+pub fn set_codec_backend(backend: Box<dyn CodecBackend>) {
+    *CODEC_BACKEND.write().unwrap() = Some(backend);
+}
+
+pub fn supports_codec(codec: &str, kind: CodecKind) -> bool {
+    CODEC_BACKEND
+        .read()
+        .unwrap()
+        .as_ref()
+        .map_or(false, |backend| backend.supports_codec(codec, kind))
+}
+
+pub fn create_encoder(
+    settings: EncoderSettings,
+    sender: IpcSender<EncoderEvent>,
+) -> Result<Arc<Mutex<dyn Encoder>>, CodecError> {
+    let backend = CODEC_BACKEND.read().unwrap();
+    let backend = backend.as_ref().ok_or(CodecError::NotSupported)?;
+    backend.create_encoder(settings, sender)
+}
+
+pub fn create_decoder(
+    settings: DecoderSettings,
+    sender: IpcSender<DecoderEvent>,
+) -> Result<Arc<Mutex<dyn Decoder>>, CodecError> {
+    let backend = CODEC_BACKEND.read().unwrap();
+    let backend = backend.as_ref().ok_or(CodecError::NotSupported)?;
+    backend.create_decoder(settings, sender)
+}
@@ -0,0 +1,656 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! A `CodecBackend` running each encoder and decoder as a GStreamer
+//! pipeline between an appsrc and an appsink.
+
+use crate::codecs::{
+    CodecBackend, CodecError, CodecKind, Decoder, DecoderEvent, DecoderSettings, EncodedChunk,
+    Encoder, EncoderEvent, EncoderSettings, VideoFrameData,
+};
+use gst::prelude::*;
+use gstreamer as gst;
+use gstreamer_app as gst_app;
+use ipc_channel::ipc::IpcSender;
+use serde::Serialize;
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// The format decoded audio is converted to before being handed out.
+#[cfg(target_endian = "little")]
+const AUDIO_FORMAT: &str = "F32LE";
+#[cfg(target_endian = "big")]
+const AUDIO_FORMAT: &str = "F32BE";
+
+/// Part of a pipeline description, and the elements it needs.
+struct Stage {
+    launch: &'static str,
+    elements: &'static [&'static str],
+}
+
+struct Codec {
+    audio: bool,
+    /// The caps of the encoded stream.
+    caps: &'static str,
+    decoder: Stage,
+    encoder: Option<Stage>,
+    /// What follows the encoder, to output the stream in the expected format.
+    encoder_output: &'static str,
+    /// The name of the encoder property taking the target bitrate, and the
+    /// number of bits per second of its unit.
+    bitrate_property: Option<(&'static str, u64)>,
+}
+
+/// Maps a WebCodecs codec string to the GStreamer elements handling it.
+/// https://www.w3.org/TR/webcodecs-codec-registry/
+fn find_codec(codec: &str) -> Option<Codec> {
+    let codec = if codec == "vp8" {
+        Codec {
+            audio: false,
+            caps: "video/x-vp8",
+            decoder: Stage {
+                launch: "vp8dec",
+                elements: &["vp8dec"],
+            },
+            encoder: Some(Stage {
+                launch: "vp8enc deadline=1",
+                elements: &["vp8enc"],
+            }),
+            encoder_output: "",
+            bitrate_property: Some(("target-bitrate", 1)),
+        }
+    } else if codec.starts_with("vp09.") {
+        Codec {
+            audio: false,
+            caps: "video/x-vp9",
+            decoder: Stage {
+                launch: "vp9dec",
+                elements: &["vp9dec"],
+            },
+            encoder: Some(Stage {
+                launch: "vp9enc deadline=1",
+                elements: &["vp9enc"],
+            }),
+            encoder_output: "",
+            bitrate_property: Some(("target-bitrate", 1)),
+        }
+    } else if codec.starts_with("avc1.") || codec.starts_with("avc3.") {
+        Codec {
+            audio: false,
+            caps: "video/x-h264,alignment=au",
+            decoder: Stage {
+                launch: "h264parse ! avdec_h264",
+                elements: &["h264parse", "avdec_h264"],
+            },
+            // The parameter sets are sent in-band, in Annex B format.
+            encoder: Some(Stage {
+                launch: "x264enc tune=zerolatency speed-preset=ultrafast",
+                elements: &["x264enc", "h264parse"],
+            }),
+            encoder_output: " ! h264parse config-interval=-1 ! \
+                             video/x-h264,stream-format=byte-stream,alignment=au",
+            bitrate_property: Some(("bitrate", 1000)),
+        }
+    } else if codec == "opus" {
+        Codec {
+            audio: true,
+            caps: "audio/x-opus,channel-mapping-family=0",
+            decoder: Stage {
+                launch: "opusdec",
+                elements: &["opusdec"],
+            },
+            encoder: None,
+            encoder_output: "",
+            bitrate_property: None,
+        }
+    } else if codec.starts_with("mp4a.40.") {
+        Codec {
+            audio: true,
+            caps: "audio/mpeg,mpegversion=4",
+            decoder: Stage {
+                launch: "aacparse ! avdec_aac",
+                elements: &["aacparse", "avdec_aac"],
+            },
+            encoder: None,
+            encoder_output: "",
+            bitrate_property: None,
+        }
+    } else if codec == "mp3" {
+        Codec {
+            audio: true,
+            caps: "audio/mpeg,mpegversion=1,layer=3",
+            decoder: Stage {
+                launch: "mpegaudioparse ! avdec_mp3",
+                elements: &["mpegaudioparse", "avdec_mp3"],
+            },
+            encoder: None,
+            encoder_output: "",
+            bitrate_property: None,
+        }
+    } else if codec == "ulaw" {
+        Codec {
+            audio: true,
+            caps: "audio/x-mulaw",
+            decoder: Stage {
+                launch: "mulawdec",
+                elements: &["mulawdec"],
+            },
+            encoder: None,
+            encoder_output: "",
+            bitrate_property: None,
+        }
+    } else if codec == "alaw" {
+        Codec {
+            audio: true,
+            caps: "audio/x-alaw",
+            decoder: Stage {
+                launch: "alawdec",
+                elements: &["alawdec"],
+            },
+            encoder: None,
+            encoder_output: "",
+            bitrate_property: None,
+        }
+    } else {
+        return None;
+    };
+    Some(codec)
+}
+
+fn has_elements(elements: &[&str]) -> bool {
+    elements
+        .iter()
+        .all(|name| gst::ElementFactory::find(name).is_some())
+}
+
+fn backend_error<E: ToString>(error: E) -> CodecError {
+    CodecError::Backend(error.to_string())
+}
+
+pub struct GStreamerCodecBackend;
+
+impl GStreamerCodecBackend {
+    pub fn new() -> Result<GStreamerCodecBackend, String> {
+        gst::init().map_err(|error| error.to_string())?;
+        Ok(GStreamerCodecBackend)
+    }
+}
+
+impl CodecBackend for GStreamerCodecBackend {
+    fn supports_codec(&self, codec: &str, kind: CodecKind) -> bool {
+        let codec = match find_codec(codec) {
+            Some(codec) => codec,
+            None => return false,
+        };
+        match kind {
+            CodecKind::AudioDecoder => {
+                codec.audio &&
+                    has_elements(&["audioconvert"]) &&
+                    has_elements(codec.decoder.elements)
+            },
+            CodecKind::VideoDecoder => {
+                !codec.audio &&
+                    has_elements(&["videoconvert"]) &&
+                    has_elements(codec.decoder.elements)
+            },
+            CodecKind::VideoEncoder => match codec.encoder {
+                Some(ref encoder) if !codec.audio => {
+                    has_elements(&["videoconvert", "videoscale"]) && has_elements(encoder.elements)
+                },
+                _ => false,
+            },
+        }
+    }
+
+    fn create_encoder(
+        &self,
+        settings: EncoderSettings,
+        sender: IpcSender<EncoderEvent>,
+    ) -> Result<Arc<Mutex<dyn Encoder>>, CodecError> {
+        let codec = find_codec(&settings.codec).ok_or(CodecError::NotSupported)?;
+        let encoder = codec.encoder.as_ref().ok_or(CodecError::NotSupported)?;
+        let bitrate = match (settings.bitrate, codec.bitrate_property) {
+            (Some(bitrate), Some((property, unit))) => {
+                format!(" {}={}", property, (bitrate / unit).max(1))
+            },
+            _ => String::new(),
+        };
+        // Only the fixed descriptions above and numbers go into the launch
+        // line, never strings coming from content.
+        let launch = format!(
+            "appsrc name=src format=time ! videoconvert ! videoscale ! \
+             video/x-raw,width={},height={} ! {}{}{} ! appsink name=sink sync=false",
+            settings.width, settings.height, encoder.launch, bitrate, codec.encoder_output,
+        );
+        let pipeline = CodecPipeline::start(
+            &launch,
+            video_frame_caps(settings.width, settings.height)?,
+            Events {
+                sender: Mutex::new(sender),
+                from_sample: |sample, base| encoded_chunk(sample, base).map(EncoderEvent::Chunk),
+                flushed: || EncoderEvent::Flushed,
+                error: EncoderEvent::Error,
+            },
+        )?;
+        Ok(Arc::new(Mutex::new(GStreamerEncoder {
+            pipeline,
+            size: (settings.width, settings.height),
+        })))
+    }
+
+    fn create_decoder(
+        &self,
+        settings: DecoderSettings,
+        sender: IpcSender<DecoderEvent>,
+    ) -> Result<Arc<Mutex<dyn Decoder>>, CodecError> {
+        let (launch, caps, from_sample): (_, _, fn(&gst::Sample, i64) -> Option<DecoderEvent>) =
+            match settings {
+                DecoderSettings::Video {
+                    ref codec,
+                    ref description,
+                    coded_width,
+                    coded_height,
+                } => {
+                    let codec = find_codec(codec)
+                        .filter(|codec| !codec.audio)
+                        .ok_or(CodecError::NotSupported)?;
+                    let mut caps = gst::Caps::from_str(codec.caps).map_err(backend_error)?;
+                    {
+                        let caps = caps.get_mut().unwrap();
+                        if let Some(ref description) = *description {
+                            // An avcC record for H.264, which implies the
+                            // length-prefixed stream format.
+                            if codec.caps.starts_with("video/x-h264") {
+                                caps.set_simple(&[("stream-format", &"avc")]);
+                            }
+                            let buffer = gst::Buffer::from_mut_slice(description.clone());
+                            caps.set_simple(&[("codec_data", &buffer)]);
+                        } else if codec.caps.starts_with("video/x-h264") {
+                            caps.set_simple(&[("stream-format", &"byte-stream")]);
+                        }
+                        if let (Some(width), Some(height)) = (coded_width, coded_height) {
+                            caps.set_simple(&[
+                                ("width", &(width as i32)),
+                                ("height", &(height as i32)),
+                            ]);
+                        }
+                    }
+                    let launch = format!(
+                        "appsrc name=src format=time ! {} ! videoconvert ! \
+                         video/x-raw,format=BGRA ! appsink name=sink sync=false",
+                        codec.decoder.launch,
+                    );
+                    (launch, caps, |sample, base| {
+                        video_frame(sample, base).map(DecoderEvent::VideoFrame)
+                    })
+                },
+                DecoderSettings::Audio {
+                    ref codec,
+                    ref description,
+                    sample_rate,
+                    number_of_channels,
+                } => {
+                    let codec = find_codec(codec)
+                        .filter(|codec| codec.audio)
+                        .ok_or(CodecError::NotSupported)?;
+                    let mut caps = gst::Caps::from_str(codec.caps).map_err(backend_error)?;
+                    {
+                        let caps = caps.get_mut().unwrap();
+                        caps.set_simple(&[
+                            ("rate", &(sample_rate as i32)),
+                            ("channels", &(number_of_channels as i32)),
+                        ]);
+                        if codec.caps.starts_with("audio/mpeg,mpegversion=4") {
+                            // An AudioSpecificConfig means raw AAC frames,
+                            // otherwise they come with ADTS headers.
+                            let format = if description.is_some() { "raw" } else { "adts" };
+                            caps.set_simple(&[("stream-format", &format)]);
+                        }
+                        if let Some(ref description) = *description {
+                            let buffer = gst::Buffer::from_mut_slice(description.clone());
+                            caps.set_simple(&[("codec_data", &buffer)]);
+                        }
+                    }
+                    let launch = format!(
+                        "appsrc name=src format=time ! {} ! audioconvert ! \
+                         audio/x-raw,format={},layout=interleaved ! appsink name=sink sync=false",
+                        codec.decoder.launch, AUDIO_FORMAT,
+                    );
+                    (launch, caps, audio_data)
+                },
+            };
+        let pipeline = CodecPipeline::start(
+            &launch,
+            caps,
+            Events {
+                sender: Mutex::new(sender),
+                from_sample,
+                flushed: || DecoderEvent::Flushed,
+                error: DecoderEvent::Error,
+            },
+        )?;
+        Ok(Arc::new(Mutex::new(GStreamerDecoder { pipeline })))
+    }
+}
+
+struct GStreamerEncoder {
+    pipeline: CodecPipeline,
+    /// The size of the frames the appsrc caps were last set for.
+    size: (u32, u32),
+}
+
+impl Encoder for GStreamerEncoder {
+    fn encode(&mut self, frame: VideoFrameData, key_frame: bool) {
+        // Frames of another size get scaled to the configured one.
+        let caps = if (frame.width, frame.height) != self.size {
+            self.size = (frame.width, frame.height);
+            video_frame_caps(frame.width, frame.height).ok()
+        } else {
+            None
+        };
+        if key_frame {
+            self.pipeline.send(Command::ForceKeyUnit);
+        }
+        self.pipeline
+            .push(frame.data, frame.timestamp, frame.duration, true, caps);
+    }
+
+    fn flush(&mut self) {
+        self.pipeline.send(Command::Flush);
+    }
+
+    fn close(&mut self) {
+        self.pipeline.send(Command::Close);
+    }
+}
+
+struct GStreamerDecoder {
+    pipeline: CodecPipeline,
+}
+
+impl Decoder for GStreamerDecoder {
+    fn decode(&mut self, chunk: EncodedChunk) {
+        self.pipeline
+            .push(chunk.data, chunk.timestamp, chunk.duration, chunk.key, None);
+    }
+
+    fn flush(&mut self) {
+        self.pipeline.send(Command::Flush);
+    }
+
+    fn close(&mut self) {
+        self.pipeline.send(Command::Close);
+    }
+}
+
+fn video_frame_caps(width: u32, height: u32) -> Result<gst::Caps, CodecError> {
+    gst::Caps::from_str(&format!(
+        "video/x-raw,format=BGRA,width={},height={},framerate=0/1",
+        width, height
+    ))
+    .map_err(backend_error)
+}
+
+/// How the samples and the state of a pipeline are reported.
+struct Events<E> {
+    sender: Mutex<IpcSender<E>>,
+    /// Converts a sample of the appsink, given the timestamp the pipeline
+    /// timestamps are relative to.
+    from_sample: fn(&gst::Sample, i64) -> Option<E>,
+    flushed: fn() -> E,
+    error: fn(String) -> E,
+}
+
+impl<E: Serialize> Events<E> {
+    fn send(&self, event: E) {
+        if let Err(error) = self.sender.lock().unwrap().send(event) {
+            warn!("Could not send codec event ({:?})", error);
+        }
+    }
+}
+
+enum Command {
+    /// A buffer for the appsrc, and the caps it should be pushed with if
+    /// they changed.
+    Push(gst::Buffer, Option<gst::Caps>),
+    ForceKeyUnit,
+    Flush,
+    Close,
+}
+
+/// A pipeline fed from its own thread, so that flushing it does not block
+/// the caller.
+struct CodecPipeline {
+    commands: Sender<Command>,
+    /// The timestamp of the first buffer, in microseconds, which the
+    /// pipeline timestamps are relative to since they can't be negative.
+    base: Arc<Mutex<Option<i64>>>,
+}
+
+impl CodecPipeline {
+    fn start<E: Serialize + Send + 'static>(
+        launch: &str,
+        caps: gst::Caps,
+        events: Events<E>,
+    ) -> Result<CodecPipeline, CodecError> {
+        let pipeline = gst::parse_launch(launch)
+            .map_err(backend_error)?
+            .downcast::<gst::Pipeline>()
+            .map_err(|_| CodecError::Backend("Not a pipeline".to_owned()))?;
+        let appsrc = pipeline
+            .get_by_name("src")
+            .and_then(|element| element.downcast::<gst_app::AppSrc>().ok())
+            .ok_or_else(|| CodecError::Backend("No appsrc".to_owned()))?;
+        let appsink = pipeline
+            .get_by_name("sink")
+            .and_then(|element| element.downcast::<gst_app::AppSink>().ok())
+            .ok_or_else(|| CodecError::Backend("No appsink".to_owned()))?;
+        appsrc.set_caps(Some(&caps));
+
+        let events = Arc::new(events);
+        let base = Arc::new(Mutex::new(None));
+
+        let sink_events = events.clone();
+        let sink_base = base.clone();
+        appsink.set_callbacks(
+            gst_app::AppSinkCallbacks::new()
+                .new_sample(move |appsink| {
+                    let sample = appsink.pull_sample().map_err(|_| gst::FlowError::Eos)?;
+                    let base = sink_base.lock().unwrap().unwrap_or(0);
+                    if let Some(event) = (sink_events.from_sample)(&sample, base) {
+                        sink_events.send(event);
+                    }
+                    Ok(gst::FlowSuccess::Ok)
+                })
+                .build(),
+        );
+
+        // The end of each flush, or the error which prevented it.
+        let (flush_sender, flush_receiver) = mpsc::channel();
+        let flush_sender = Mutex::new(flush_sender);
+        let bus_events = events.clone();
+        let bus = pipeline
+            .get_bus()
+            .ok_or_else(|| CodecError::Backend("No bus".to_owned()))?;
+        bus.set_sync_handler(move |_, message| {
+            match message.view() {
+                gst::MessageView::Eos(..) => {
+                    let _ = flush_sender.lock().unwrap().send(Ok(()));
+                },
+                gst::MessageView::Error(error) => {
+                    let error = error.get_error().to_string();
+                    bus_events.send((bus_events.error)(error.clone()));
+                    let _ = flush_sender.lock().unwrap().send(Err(error));
+                },
+                _ => {},
+            }
+            gst::BusSyncReply::Drop
+        });
+
+        pipeline
+            .set_state(gst::State::Playing)
+            .map_err(backend_error)?;
+
+        let (commands, command_receiver) = mpsc::channel();
+        thread::Builder::new()
+            .name("CodecPipeline".to_owned())
+            .spawn(move || run(pipeline, appsrc, command_receiver, flush_receiver, events))
+            .map_err(backend_error)?;
+        Ok(CodecPipeline { commands, base })
+    }
+
+    fn send(&self, command: Command) {
+        let _ = self.commands.send(command);
+    }
+
+    fn push(
+        &self,
+        data: Vec<u8>,
+        timestamp: i64,
+        duration: Option<u64>,
+        key: bool,
+        caps: Option<gst::Caps>,
+    ) {
+        let base = *self.base.lock().unwrap().get_or_insert(timestamp);
+        let mut buffer = gst::Buffer::from_mut_slice(data);
+        {
+            let buffer = buffer.get_mut().unwrap();
+            let pts = (timestamp - base).max(0) as u64;
+            buffer.set_pts(gst::ClockTime::from_useconds(pts));
+            if let Some(duration) = duration {
+                buffer.set_duration(gst::ClockTime::from_useconds(duration));
+            }
+            if !key {
+                buffer.set_flags(gst::BufferFlags::DELTA_UNIT);
+            }
+        }
+        self.send(Command::Push(buffer, caps));
+    }
+}
+
+impl Drop for CodecPipeline {
+    fn drop(&mut self) {
+        self.send(Command::Close);
+    }
+}
+
+fn run<E: Serialize>(
+    pipeline: gst::Pipeline,
+    appsrc: gst_app::AppSrc,
+    commands: Receiver<Command>,
+    flushes: Receiver<Result<(), String>>,
+    events: Arc<Events<E>>,
+) {
+    for command in commands {
+        match command {
+            Command::Push(buffer, caps) => {
+                if let Some(caps) = caps {
+                    appsrc.set_caps(Some(&caps));
+                }
+                if appsrc.push_buffer(buffer).is_err() {
+                    break;
+                }
+            },
+            Command::ForceKeyUnit => {
+                // Handled by the video encoder base class like the event
+                // gst_video_event_new_downstream_force_key_unit creates.
+                let structure = gst::Structure::builder("GstForceKeyUnit")
+                    .field("all-headers", &true)
+                    .build();
+                appsrc.send_event(gst::Event::new_custom_downstream(structure).build());
+            },
+            Command::Flush => {
+                // Drain the pipeline, then restart it for the data which
+                // comes after the flush.
+                let _ = appsrc.end_of_stream();
+                match flushes.recv() {
+                    Ok(Ok(())) => {},
+                    // The error was reported by the bus handler.
+                    Ok(Err(_)) | Err(_) => break,
+                }
+                let _ = pipeline.set_state(gst::State::Null);
+                if let Err(error) = pipeline.set_state(gst::State::Playing) {
+                    events.send((events.error)(error.to_string()));
+                    break;
+                }
+                events.send((events.flushed)());
+            },
+            Command::Close => break,
+        }
+    }
+    let _ = pipeline.set_state(gst::State::Null);
+}
+
+fn buffer_times(buffer: &gst::BufferRef, base: i64) -> (i64, Option<u64>) {
+    let timestamp = buffer
+        .get_pts()
+        .useconds()
+        .map_or(base, |pts| base + pts as i64);
+    (timestamp, buffer.get_duration().useconds())
+}
+
+fn encoded_chunk(sample: &gst::Sample, base: i64) -> Option<EncodedChunk> {
+    let buffer = sample.get_buffer()?;
+    let (timestamp, duration) = buffer_times(buffer, base);
+    let map = buffer.map_readable().ok()?;
+    Some(EncodedChunk {
+        key: !buffer.get_flags().contains(gst::BufferFlags::DELTA_UNIT),
+        timestamp,
+        duration,
+        data: map.as_slice().to_vec(),
+    })
+}
+
+fn video_frame(sample: &gst::Sample, base: i64) -> Option<VideoFrameData> {
+    let structure = sample.get_caps()?.get_structure(0)?;
+    let width = structure.get_some::<i32>("width").ok()? as u32;
+    let height = structure.get_some::<i32>("height").ok()? as u32;
+    let buffer = sample.get_buffer()?;
+    let (timestamp, duration) = buffer_times(buffer, base);
+    let map = buffer.map_readable().ok()?;
+    // BGRA rows are always four-byte aligned, so they are tightly packed.
+    let size = (width * height * 4) as usize;
+    let data = map.as_slice().get(..size)?.to_vec();
+    Some(VideoFrameData {
+        width,
+        height,
+        timestamp,
+        duration,
+        data,
+    })
+}
+
+fn audio_data(sample: &gst::Sample, base: i64) -> Option<DecoderEvent> {
+    let structure = sample.get_caps()?.get_structure(0)?;
+    let sample_rate = structure.get_some::<i32>("rate").ok()?;
+    let channels = structure.get_some::<i32>("channels").ok()? as usize;
+    let buffer = sample.get_buffer()?;
+    let (timestamp, _) = buffer_times(buffer, base);
+    let map = buffer.map_readable().ok()?;
+    let interleaved: Vec<f32> = map
+        .as_slice()
+        .chunks_exact(4)
+        .map(|bytes| f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .collect();
+    let frames = interleaved.len() / channels.max(1);
+    let mut data = Vec::with_capacity(frames * channels);
+    for channel in 0..channels {
+        data.extend(
+            interleaved
+                .iter()
+                .skip(channel)
+                .step_by(channels)
+                .take(frames),
+        );
+    }
+    Some(DecoderEvent::AudioData {
+        sample_rate: sample_rate as f32,
+        number_of_channels: channels as u32,
+        number_of_frames: frames as u32,
+        timestamp,
+        data,
+    })
+}
@@ -11,9 +11,15 @@ extern crate log;
 #[macro_use]
 extern crate serde;
 
+pub mod codecs;
+#[cfg(feature = "codecs-gstreamer")]
+mod gstreamer_codecs;
 mod media_channel;
 mod media_thread;
 
+#[cfg(feature = "codecs-gstreamer")]
+pub use crate::gstreamer_codecs::GStreamerCodecBackend;
+
 pub use crate::media_channel::glplayer_channel;
 use crate::media_channel::{GLPlayerChan, GLPlayerPipeline, GLPlayerReceiver, GLPlayerSender};
 use crate::media_thread::GLPlayerThread;
@@ -184,6 +184,7 @@ impl PipelineNamespace {
     namespace_id_method! {next_service_worker_id, ServiceWorkerId, self, ServiceWorkerIndex}
     namespace_id_method! {next_service_worker_registration_id, ServiceWorkerRegistrationId,
     self, ServiceWorkerRegistrationIndex}
+    namespace_id_method! {next_shared_worker_id, SharedWorkerId, self, SharedWorkerIndex}
     namespace_id_method! {next_blob_id, BlobId, self, BlobIndex}
 }
 
@@ -422,6 +423,27 @@ impl fmt::Display for ServiceWorkerRegistrationId {
     }
 }
 
+namespace_id! {SharedWorkerId, SharedWorkerIndex}
+
+impl SharedWorkerId {
+    pub fn new() -> SharedWorkerId {
+        PIPELINE_NAMESPACE.with(|tls| {
+            let mut namespace = tls.get().expect("No namespace set for this thread!");
+            let next_shared_worker_id = namespace.next_shared_worker_id();
+            tls.set(Some(namespace));
+            next_shared_worker_id
+        })
+    }
+}
+
+impl fmt::Display for SharedWorkerId {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let PipelineNamespaceId(namespace_id) = self.namespace_id;
+        let SharedWorkerIndex(index) = self.index;
+        write!(fmt, "(SharedWorkerId{},{})", namespace_id, index.get())
+    }
+}
+
 namespace_id! {BlobId, BlobIndex}
 
 impl BlobId {
@@ -0,0 +1,412 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Implementation of the origin-partitioned storage behind the
+//! [Cache API](https://w3c.github.io/ServiceWorker/#cache-objects).
+//!
+//! A persistent storage lives in a directory of its own, holding an index of the
+//! caches and their entries in `index.json` and every response body in a separate
+//! file. The index is rewritten after each operation that changes it, once the
+//! bodies it refers to have been written, so that an interrupted write can at worst
+//! leave unreferenced bodies behind.
+
+use crate::resource_thread::{read_json_from_file, write_json_to_file};
+use headers::{HeaderMapExt, Vary};
+use http::{HeaderMap, Method};
+use net_traits::cache_storage::{CacheBatchError, CacheBatchOperation, CacheEntry, CacheId};
+use net_traits::cache_storage::{CacheQueryOptions, CacheSelector, CacheStorageMsg, CachedRequest};
+use servo_url::{ImmutableOrigin, ServoUrl};
+use std::collections::HashMap;
+use std::fs;
+use std::mem;
+use std::path::PathBuf;
+
+const INDEX_FILE: &str = "index.json";
+
+/// An entry of a request response list, whose response body is kept in the body store.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct StoredEntry {
+    /// The entry, with an empty response body.
+    entry: CacheEntry,
+    body_id: u64,
+    body_size: usize,
+}
+
+/// A [name to cache map](https://w3c.github.io/ServiceWorker/#name-to-cache-map) entry.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct NamedCache {
+    name: String,
+    id: CacheId,
+    /// The [request response list](https://w3c.github.io/ServiceWorker/#request-response-list).
+    entries: Vec<StoredEntry>,
+}
+
+/// The caches of every origin, keyed by the serialization of the origin.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+struct Index {
+    next_id: u64,
+    next_body_id: u64,
+    origins: HashMap<String, Vec<NamedCache>>,
+}
+
+/// Where the response bodies are kept.
+enum BodyStore {
+    Memory(HashMap<u64, Vec<u8>>),
+    Disk(PathBuf),
+}
+
+impl BodyStore {
+    fn write(&mut self, id: u64, body: Vec<u8>) -> Result<(), ()> {
+        match *self {
+            BodyStore::Memory(ref mut bodies) => {
+                bodies.insert(id, body);
+                Ok(())
+            },
+            BodyStore::Disk(ref directory) => fs::write(directory.join(id.to_string()), body)
+                .map_err(|error| {
+                    warn!("Could not write a cache storage body: {}", error);
+                }),
+        }
+    }
+
+    fn read(&self, id: u64) -> Vec<u8> {
+        match *self {
+            BodyStore::Memory(ref bodies) => bodies.get(&id).cloned().unwrap_or_default(),
+            BodyStore::Disk(ref directory) => fs::read(directory.join(id.to_string()))
+                .unwrap_or_else(|error| {
+                    warn!("Could not read a cache storage body: {}", error);
+                    vec![]
+                }),
+        }
+    }
+
+    fn remove(&mut self, id: u64) {
+        match *self {
+            BodyStore::Memory(ref mut bodies) => {
+                bodies.remove(&id);
+            },
+            BodyStore::Disk(ref directory) => {
+                let _ = fs::remove_file(directory.join(id.to_string()));
+            },
+        }
+    }
+}
+
+pub struct CacheStorage {
+    index: Index,
+    bodies: BodyStore,
+    /// The number of bytes of response bodies each origin may store.
+    quota: usize,
+}
+
+impl CacheStorage {
+    /// Creates a storage that persists to `directory` if one is given, loading what
+    /// it already contains.
+    pub fn new(directory: Option<PathBuf>, quota: usize) -> CacheStorage {
+        let mut index = Index::default();
+        let bodies = match directory {
+            Some(directory) => match fs::create_dir_all(&directory) {
+                Ok(()) => {
+                    read_json_from_file(&mut index, &directory, INDEX_FILE);
+                    BodyStore::Disk(directory)
+                },
+                Err(error) => {
+                    warn!("Could not create the cache storage directory: {}", error);
+                    BodyStore::Memory(HashMap::new())
+                },
+            },
+            None => BodyStore::Memory(HashMap::new()),
+        };
+        CacheStorage {
+            index,
+            bodies,
+            quota,
+        }
+    }
+
+    pub fn handle_msg(&mut self, msg: CacheStorageMsg) {
+        match msg {
+            CacheStorageMsg::Has(sender, origin, name) => {
+                let _ = sender.send(self.has(&origin, &name));
+            },
+            CacheStorageMsg::Open(sender, origin, name) => {
+                let _ = sender.send(self.open(&origin, name));
+            },
+            CacheStorageMsg::Delete(sender, origin, name) => {
+                let _ = sender.send(self.delete(&origin, &name));
+            },
+            CacheStorageMsg::Keys(sender, origin) => {
+                let _ = sender.send(self.keys(&origin));
+            },
+            CacheStorageMsg::MatchAll(sender, origin, selector, request, options) => {
+                let _ = sender.send(self.match_all(&origin, &selector, request.as_ref(), options));
+            },
+            CacheStorageMsg::BatchOperations(sender, origin, id, operations) => {
+                let _ = sender.send(self.batch_operations(&origin, id, operations));
+            },
+        }
+    }
+
+    fn caches(&self, origin: &ImmutableOrigin) -> &[NamedCache] {
+        self.index
+            .origins
+            .get(&origin.ascii_serialization())
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    /// Writes the index, if this storage is persistent.
+    fn persist(&self) {
+        if let BodyStore::Disk(ref directory) = self.bodies {
+            write_json_to_file(&self.index, directory, INDEX_FILE);
+        }
+    }
+
+    /// <https://w3c.github.io/ServiceWorker/#cache-storage-has>
+    pub fn has(&self, origin: &ImmutableOrigin, name: &str) -> bool {
+        self.caches(origin).iter().any(|cache| cache.name == name)
+    }
+
+    /// <https://w3c.github.io/ServiceWorker/#cache-storage-open>
+    pub fn open(&mut self, origin: &ImmutableOrigin, name: String) -> CacheId {
+        let caches = self
+            .index
+            .origins
+            .entry(origin.ascii_serialization())
+            .or_insert_with(Vec::new);
+        if let Some(cache) = caches.iter().find(|cache| cache.name == name) {
+            return cache.id;
+        }
+        let id = CacheId(self.index.next_id);
+        self.index.next_id += 1;
+        caches.push(NamedCache {
+            name,
+            id,
+            entries: vec![],
+        });
+        self.persist();
+        id
+    }
+
+    /// <https://w3c.github.io/ServiceWorker/#cache-storage-delete>
+    pub fn delete(&mut self, origin: &ImmutableOrigin, name: &str) -> bool {
+        let caches = match self.index.origins.get_mut(&origin.ascii_serialization()) {
+            Some(caches) => caches,
+            None => return false,
+        };
+        let position = match caches.iter().position(|cache| cache.name == name) {
+            Some(position) => position,
+            None => return false,
+        };
+        let cache = caches.remove(position);
+        self.persist();
+        for entry in cache.entries {
+            self.bodies.remove(entry.body_id);
+        }
+        true
+    }
+
+    /// <https://w3c.github.io/ServiceWorker/#cache-storage-keys>
+    pub fn keys(&self, origin: &ImmutableOrigin) -> Vec<String> {
+        self.caches(origin)
+            .iter()
+            .map(|cache| cache.name.clone())
+            .collect()
+    }
+
+    /// Runs [Query Cache](https://w3c.github.io/ServiceWorker/#query-cache) on the
+    /// selected caches, or returns all of their entries if there is no request.
+    pub fn match_all(
+        &self,
+        origin: &ImmutableOrigin,
+        selector: &CacheSelector,
+        request: Option<&CachedRequest>,
+        options: CacheQueryOptions,
+    ) -> Vec<CacheEntry> {
+        self.caches(origin)
+            .iter()
+            .filter(|cache| match *selector {
+                CacheSelector::Id(id) => cache.id == id,
+                CacheSelector::Name(ref name) => cache.name == *name,
+                CacheSelector::All => true,
+            })
+            .flat_map(|cache| query_cache(request, options, &cache.entries))
+            .map(|stored| {
+                let mut entry = stored.entry.clone();
+                entry.response.body = self.bodies.read(stored.body_id);
+                entry
+            })
+            .collect()
+    }
+
+    /// <https://w3c.github.io/ServiceWorker/#batch-cache-operations-algorithm>
+    ///
+    /// Returns whether any entry was removed by a delete operation, or an error if an
+    /// operation would overwrite an entry added by this same batch or the entries of
+    /// the origin would exceed its quota, in which case the cache is left untouched.
+    /// Operations on a cache that has since been deleted are dropped.
+    pub fn batch_operations(
+        &mut self,
+        origin: &ImmutableOrigin,
+        id: CacheId,
+        operations: Vec<CacheBatchOperation>,
+    ) -> Result<bool, CacheBatchError> {
+        let caches = match self.index.origins.get_mut(&origin.ascii_serialization()) {
+            Some(caches) => caches,
+            None => return Ok(false),
+        };
+        let used: usize = caches
+            .iter()
+            .filter(|cache| cache.id != id)
+            .flat_map(|cache| cache.entries.iter())
+            .map(|stored| stored.body_size)
+            .sum();
+        let cache = match caches.iter_mut().find(|cache| cache.id == id) {
+            Some(cache) => cache,
+            None => return Ok(false),
+        };
+
+        // Step 3. The bodies are only written once the batch succeeded.
+        let mut entries = cache.entries.clone();
+        let mut added_items: Vec<StoredEntry> = vec![];
+        let mut added_bodies = vec![];
+        let mut removed_any = false;
+        let mut next_body_id = self.index.next_body_id;
+
+        // Step 5.
+        for operation in operations {
+            match operation {
+                CacheBatchOperation::Delete(request, options) => {
+                    let len = entries.len();
+                    entries
+                        .retain(|stored| !request_matches_cached_item(&request, stored, options));
+                    removed_any |= len != entries.len();
+                },
+                CacheBatchOperation::Put(mut entry) => {
+                    let options = CacheQueryOptions::default();
+                    if !query_cache(Some(&entry.request), options, &added_items).is_empty() {
+                        // Step 5.4.
+                        return Err(CacheBatchError::InvalidState);
+                    }
+                    entries.retain(|stored| {
+                        !request_matches_cached_item(&entry.request, stored, options)
+                    });
+                    let body = mem::replace(&mut entry.response.body, vec![]);
+                    let stored = StoredEntry {
+                        entry,
+                        body_id: next_body_id,
+                        body_size: body.len(),
+                    };
+                    next_body_id += 1;
+                    added_bodies.push((stored.body_id, body));
+                    entries.push(stored.clone());
+                    added_items.push(stored);
+                },
+            }
+        }
+
+        let size: usize = entries.iter().map(|stored| stored.body_size).sum();
+        if used + size > self.quota {
+            return Err(CacheBatchError::QuotaExceeded);
+        }
+
+        // The bodies are written before the index refers to them.
+        let added_ids: Vec<_> = added_bodies.iter().map(|&(body_id, _)| body_id).collect();
+        for (body_id, body) in added_bodies {
+            if self.bodies.write(body_id, body).is_err() {
+                for &body_id in &added_ids {
+                    self.bodies.remove(body_id);
+                }
+                return Err(CacheBatchError::QuotaExceeded);
+            }
+        }
+        let removed_ids: Vec<_> = cache
+            .entries
+            .iter()
+            .map(|stored| stored.body_id)
+            .filter(|body_id| !entries.iter().any(|stored| stored.body_id == *body_id))
+            .collect();
+        cache.entries = entries;
+        self.index.next_body_id = next_body_id;
+        self.persist();
+        for body_id in removed_ids {
+            self.bodies.remove(body_id);
+        }
+        Ok(removed_any)
+    }
+}
+
+/// <https://w3c.github.io/ServiceWorker/#query-cache>
+fn query_cache<'a>(
+    request: Option<&CachedRequest>,
+    options: CacheQueryOptions,
+    entries: &'a [StoredEntry],
+) -> Vec<&'a StoredEntry> {
+    entries
+        .iter()
+        .filter(|stored| match request {
+            Some(request) => request_matches_cached_item(request, stored, options),
+            None => true,
+        })
+        .collect()
+}
+
+/// <https://w3c.github.io/ServiceWorker/#request-matches-cached-item>
+fn request_matches_cached_item(
+    query: &CachedRequest,
+    stored: &StoredEntry,
+    options: CacheQueryOptions,
+) -> bool {
+    let entry = &stored.entry;
+
+    // Step 1.
+    if !options.ignore_method && query.method != Method::GET {
+        return false;
+    }
+
+    // Steps 2-5.
+    if url_for_matching(&query.url, options.ignore_search) !=
+        url_for_matching(&entry.request.url, options.ignore_search)
+    {
+        return false;
+    }
+
+    // Step 6.
+    if options.ignore_vary {
+        return true;
+    }
+    let vary = match entry.response.headers.typed_get::<Vary>() {
+        Some(vary) => vary,
+        None => return true,
+    };
+
+    // Step 7.
+    if vary.is_any() {
+        return false;
+    }
+    vary.iter_strs().all(|name| {
+        name != "*" &&
+            combined_value(&query.headers, name) == combined_value(&entry.request.headers, name)
+    })
+}
+
+/// <https://fetch.spec.whatwg.org/#concept-header-list-get>
+fn combined_value(headers: &HeaderMap, name: &str) -> Option<Vec<u8>> {
+    let mut values = headers.get_all(name).iter();
+    let mut combined = values.next()?.as_bytes().to_vec();
+    for value in values {
+        combined.extend_from_slice(b", ");
+        combined.extend_from_slice(value.as_bytes());
+    }
+    Some(combined)
+}
+
+fn url_for_matching(url: &ServoUrl, ignore_search: bool) -> ServoUrl {
+    let mut url = url.clone();
+    url.set_fragment(None);
+    if ignore_search {
+        url.as_mut_url().set_query(None);
+    }
+    url
+}
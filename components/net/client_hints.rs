@@ -0,0 +1,168 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Implementation of [User-Agent Client Hints](https://wicg.github.io/ua-client-hints/),
+//! delivered with the [Client Hints infrastructure](https://wicg.github.io/client-hints-infrastructure/).
+
+use http::header::{HeaderMap, HeaderName, HeaderValue};
+use net_traits::request::{Origin, Request};
+use servo_url::{ImmutableOrigin, ServoUrl};
+use std::collections::HashMap;
+
+/// The hints sent with every request to a potentially trustworthy URL.
+const LOW_ENTROPY_HINTS: &[&str] = &["sec-ch-ua", "sec-ch-ua-mobile", "sec-ch-ua-platform"];
+
+/// The hints an origin has to opt into with `Accept-CH`.
+const HIGH_ENTROPY_HINTS: &[&str] = &[
+    "sec-ch-ua-arch",
+    "sec-ch-ua-bitness",
+    "sec-ch-ua-full-version-list",
+    "sec-ch-ua-model",
+    "sec-ch-ua-wow64",
+];
+
+/// The version of Servo in its default user agents, for user agents that do not mention Servo.
+const DEFAULT_SERVO_VERSION: &str = "1.0";
+
+/// The [Accept-CH cache](https://wicg.github.io/client-hints-infrastructure/#accept-ch-cache),
+/// keyed by the serialization of the origin.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ClientHintsStore {
+    origins: HashMap<String, Vec<String>>,
+}
+
+impl ClientHintsStore {
+    pub fn new() -> ClientHintsStore {
+        ClientHintsStore::default()
+    }
+
+    /// Replaces the hints the origin of a navigation response opted into with those
+    /// of its `Accept-CH` header, if it has one.
+    pub fn update_from_response(&mut self, url: &ServoUrl, headers: &HeaderMap) {
+        if !url.is_potentially_trustworthy() {
+            return;
+        }
+        let accept_ch = match headers
+            .get("accept-ch")
+            .and_then(|value| value.to_str().ok())
+        {
+            Some(accept_ch) => accept_ch,
+            None => return,
+        };
+        let hints: Vec<String> = accept_ch
+            .split(',')
+            .map(|hint| hint.trim().to_ascii_lowercase())
+            .filter(|hint| HIGH_ENTROPY_HINTS.contains(&hint.as_str()))
+            .collect();
+        let origin = url.origin().ascii_serialization();
+        if hints.is_empty() {
+            self.origins.remove(&origin);
+        } else {
+            self.origins.insert(origin, hints);
+        }
+    }
+
+    /// The hints an origin opted into.
+    pub fn hints(&self, origin: &ImmutableOrigin) -> &[String] {
+        self.origins
+            .get(&origin.ascii_serialization())
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+}
+
+/// Appends the low entropy hints, and the hints the origin of the URL opted into, to a
+/// request to a potentially trustworthy URL. Hints set by the request are kept.
+///
+/// Delegating hints to third parties is not supported, so only navigations and
+/// same-origin requests get the hints an origin opted into.
+pub fn append_client_hints(request: &mut Request, store: &ClientHintsStore, user_agent: &str) {
+    let url = request.current_url();
+    if !url.is_potentially_trustworthy() {
+        return;
+    }
+    let origin = url.origin();
+    let first_party = request.is_navigation_request() ||
+        match request.origin {
+            Origin::Origin(ref request_origin) => *request_origin == origin,
+            Origin::Client => false,
+        };
+    let opted_in_hints = if first_party {
+        store.hints(&origin)
+    } else {
+        &[]
+    };
+    let hints = LOW_ENTROPY_HINTS
+        .iter()
+        .cloned()
+        .chain(opted_in_hints.iter().map(String::as_str));
+    for hint in hints {
+        if request.headers.contains_key(hint) {
+            continue;
+        }
+        let value =
+            hint_value(hint, user_agent).and_then(|value| HeaderValue::from_str(&value).ok());
+        if let Some(value) = value {
+            request
+                .headers
+                .insert(HeaderName::from_bytes(hint.as_bytes()).unwrap(), value);
+        }
+    }
+}
+
+/// The value of a hint, as a structured header.
+///
+/// <https://wicg.github.io/ua-client-hints/#http-ua-hints>
+pub fn hint_value(hint: &str, user_agent: &str) -> Option<String> {
+    let version = user_agent
+        .split_whitespace()
+        .find_map(|token| token.strip_prefix("Servo/"))
+        .unwrap_or(DEFAULT_SERVO_VERSION);
+    let value = match hint {
+        "sec-ch-ua" => {
+            let major_version = version.split('.').next().unwrap_or(version);
+            format!("\"Servo\";v=\"{}\"", major_version)
+        },
+        "sec-ch-ua-full-version-list" => format!("\"Servo\";v=\"{}\"", version),
+        "sec-ch-ua-mobile" if user_agent.contains("Mobile") => "?1".to_owned(),
+        "sec-ch-ua-mobile" | "sec-ch-ua-wow64" => "?0".to_owned(),
+        "sec-ch-ua-platform" => format!("\"{}\"", platform(user_agent)),
+        "sec-ch-ua-arch" => {
+            let arch = match std::env::consts::ARCH {
+                "x86" | "x86_64" => "x86",
+                "arm" | "aarch64" => "arm",
+                _ => "",
+            };
+            format!("\"{}\"", arch)
+        },
+        "sec-ch-ua-bitness" => {
+            let bitness = if cfg!(target_pointer_width = "64") {
+                "64"
+            } else {
+                "32"
+            };
+            format!("\"{}\"", bitness)
+        },
+        "sec-ch-ua-model" => "\"\"".to_owned(),
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// The platform a user agent claims to run on.
+fn platform(user_agent: &str) -> &'static str {
+    if user_agent.contains("Android") {
+        "Android"
+    } else if user_agent.contains("iPhone") || user_agent.contains("iPad") {
+        "iOS"
+    } else if user_agent.contains("Windows") {
+        "Windows"
+    } else if user_agent.contains("Macintosh") {
+        "macOS"
+    } else if user_agent.contains("Linux") {
+        "Linux"
+    } else {
+        "Unknown"
+    }
+}
@@ -3,11 +3,14 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 use crate::hosts::replace_host;
-use hyper::client::connect::{Connect, Destination};
+use crate::private_network_access::{
+    address_space_of_ip, IpAddressSpace, PrivateNetworkAccessError,
+};
+use hyper::client::connect::{Connect, Connected, Destination};
 use hyper::client::HttpConnector as HyperHttpConnector;
 use hyper::rt::Future;
 use hyper::{Body, Client};
-use hyper_openssl::HttpsConnector;
+use hyper_openssl::{HttpsConnector, MaybeHttpsStream};
 use openssl::ex_data::Index;
 use openssl::ssl::{
     Ssl, SslConnector, SslConnectorBuilder, SslContext, SslMethod, SslOptions, SslVerifyMode,
@@ -67,6 +70,7 @@ impl ConnectionCerts {
     }
 }
 
+#[derive(Clone)]
 pub struct HttpConnector {
     inner: HyperHttpConnector,
 }
@@ -94,7 +98,67 @@ impl Connect for HttpConnector {
     }
 }
 
-pub type Connector = HttpsConnector<HttpConnector>;
+/// A connector whose connections fail with a `PrivateNetworkAccessError` when
+/// their remote address is in a more private address space than a limit.
+///
+/// <https://wicg.github.io/private-network-access/#private-network-access-check>
+#[derive(Clone)]
+pub struct Connector {
+    inner: HttpsConnector<HttpConnector>,
+    address_space_limit: Option<IpAddressSpace>,
+}
+
+impl Connect for Connector {
+    type Transport = <HttpsConnector<HttpConnector> as Connect>::Transport;
+    type Error = <HttpsConnector<HttpConnector> as Connect>::Error;
+    type Future =
+        Box<dyn Future<Item = (Self::Transport, Connected), Error = Self::Error> + Send + 'static>;
+
+    fn connect(&self, dest: Destination) -> Self::Future {
+        let address_space_limit = self.address_space_limit;
+        Box::new(
+            self.inner
+                .connect(dest)
+                .and_then(move |(stream, connected)| {
+                    let limit = match address_space_limit {
+                        Some(limit) => limit,
+                        None => return Ok((stream, connected)),
+                    };
+                    // The check happens before anything is sent over the connection.
+                    let remote_address = match stream {
+                        MaybeHttpsStream::Http(ref stream) => stream.peer_addr()?,
+                        MaybeHttpsStream::Https(ref stream) => stream.get_ref().peer_addr()?,
+                    };
+                    let address_space = address_space_of_ip(remote_address.ip());
+                    if address_space < limit {
+                        return Err(PrivateNetworkAccessError(address_space).into());
+                    }
+                    Ok((stream, connected))
+                }),
+        )
+    }
+}
+
+/// The HTTP clients of a network session, which share their TLS configuration
+/// but not their connections.
+pub struct HttpClients {
+    /// The client of requests that may connect to any address.
+    unrestricted: Client<Connector, Body>,
+    /// The clients of requests that must not connect to an address more private
+    /// than a given address space. Nothing is more private than the local one.
+    restricted: HashMap<IpAddressSpace, Client<Connector, Body>>,
+}
+
+impl HttpClients {
+    /// Returns the client of requests that must not connect to an address more
+    /// private than `address_space_limit`.
+    pub fn get(&self, address_space_limit: Option<IpAddressSpace>) -> &Client<Connector, Body> {
+        address_space_limit
+            .and_then(|limit| self.restricted.get(&limit))
+            .unwrap_or(&self.unrestricted)
+    }
+}
+
 pub type TlsConfig = SslConnectorBuilder;
 
 #[derive(Clone)]
@@ -209,9 +273,13 @@ pub fn create_tls_config(
     cfg
 }
 
-pub fn create_http_client<E>(tls_config: TlsConfig, executor: E) -> Client<Connector, Body>
+pub fn create_http_clients<E>(tls_config: TlsConfig, executor: E) -> HttpClients
 where
-    E: Executor<Box<dyn Future<Error = (), Item = ()> + Send + 'static>> + Sync + Send + 'static,
+    E: Executor<Box<dyn Future<Error = (), Item = ()> + Send + 'static>>
+        + Clone
+        + Sync
+        + Send
+        + 'static,
 {
     let mut connector = HttpsConnector::with_connector(HttpConnector::new(), tls_config).unwrap();
     connector.set_callback(|configuration, destination| {
@@ -219,8 +287,20 @@ where
         Ok(())
     });
 
-    Client::builder()
-        .http1_title_case_headers(true)
-        .executor(executor)
-        .build(connector)
+    let create_client = |address_space_limit| {
+        Client::builder()
+            .http1_title_case_headers(true)
+            .executor(executor.clone())
+            .build(Connector {
+                inner: connector.clone(),
+                address_space_limit,
+            })
+    };
+    HttpClients {
+        unrestricted: create_client(None),
+        restricted: [IpAddressSpace::Private, IpAddressSpace::Public]
+            .iter()
+            .map(|&limit| (limit, create_client(Some(limit))))
+            .collect(),
+    }
 }
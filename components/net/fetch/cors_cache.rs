@@ -17,11 +17,13 @@ use time::{self, Timespec};
 
 /// Union type for CORS cache entries
 ///
-/// Each entry might pertain to a header or method
+/// Each entry might pertain to a header or method, or record that the server
+/// allowed [private network requests](https://wicg.github.io/private-network-access/#cors-preflight).
 #[derive(Clone, Debug)]
 pub enum HeaderOrMethod {
     HeaderData(HeaderName),
     MethodData(Method),
+    PrivateNetwork,
 }
 
 impl HeaderOrMethod {
@@ -38,6 +40,13 @@ impl HeaderOrMethod {
             _ => false,
         }
     }
+
+    fn match_private_network(&self) -> bool {
+        match *self {
+            HeaderOrMethod::PrivateNetwork => true,
+            _ => false,
+        }
+    }
 }
 
 /// An entry in the CORS cache
@@ -187,6 +196,36 @@ impl CorsCache {
         }
     }
 
+    /// Returns true if a preflight already allowed private network requests
+    /// for this request
+    pub fn match_private_network(&mut self, request: &Request) -> bool {
+        self.cleanup();
+        self.0
+            .iter()
+            .any(|e| match_headers(e, request) && e.header_or_method.match_private_network())
+    }
+
+    /// Updates max age if an entry allowing private network requests is found.
+    ///
+    /// If not, it will insert an equivalent entry
+    pub fn match_private_network_and_update(&mut self, request: &Request, new_max_age: u32) {
+        self.cleanup();
+        match self
+            .0
+            .iter_mut()
+            .find(|e| match_headers(e, request) && e.header_or_method.match_private_network())
+        {
+            Some(entry) => entry.max_age = new_max_age,
+            None => self.insert(CorsCacheEntry::new(
+                request.origin.clone(),
+                request.current_url(),
+                new_max_age,
+                request.credentials_mode == CredentialsMode::Include,
+                HeaderOrMethod::PrivateNetwork,
+            )),
+        }
+    }
+
     /// Insert an entry
     pub fn insert(&mut self, entry: CorsCacheEntry) {
         self.cleanup();
@@ -7,15 +7,16 @@ use crate::fetch::cors_cache::CorsCache;
 use crate::filemanager_thread::{FileManager, FILE_CHUNK_SIZE};
 use crate::http_loader::{determine_requests_referrer, http_fetch, HttpState};
 use crate::http_loader::{set_default_accept, set_default_accept_language};
+use crate::mixed_content;
 use crate::subresource_integrity::is_response_integrity_valid;
 use content_security_policy as csp;
 use crossbeam_channel::{unbounded, Receiver, Sender};
-use devtools_traits::DevtoolsControlMsg;
+use devtools_traits::{ConsoleMessage, DevtoolsControlMsg, LogLevel, ScriptToDevtoolsControlMsg};
 use headers::{AccessControlExposeHeaders, ContentType, HeaderMapExt, Range};
 use http::header::{self, HeaderMap, HeaderName};
 use hyper::Method;
 use hyper::StatusCode;
-use ipc_channel::ipc::{self, IpcReceiver};
+use ipc_channel::ipc::{self, IpcReceiver, IpcSender};
 use mime::{self, Mime};
 use net_traits::blob_url_store::{parse_blob_url, BlobURLStoreError};
 use net_traits::filemanager_thread::{FileTokenCheck, RelativePos};
@@ -27,7 +28,8 @@ use net_traits::request::{
     RequestMode,
 };
 use net_traits::response::{Response, ResponseBody, ResponseType};
-use net_traits::{FetchTaskTarget, NetworkError, ReferrerPolicy, ResourceFetchTiming};
+use net_traits::{CustomResponseMediator, FetchTaskTarget, NetworkError};
+use net_traits::{ReferrerPolicy, ResourceFetchTiming};
 use net_traits::{ResourceAttribute, ResourceTimeValue, ResourceTimingType};
 use servo_arc::Arc as ServoArc;
 use servo_url::ServoUrl;
@@ -62,6 +64,11 @@ pub struct FetchContext {
     pub file_token: FileTokenCheck,
     pub cancellation_listener: Arc<Mutex<CancellationListener>>,
     pub timing: ServoArc<Mutex<ResourceFetchTiming>>,
+    /// The service worker manager that may [handle](https://w3c.github.io/ServiceWorker/#handle-fetch)
+    /// the request, if the origin of the request or of its client has one.
+    pub sw_manager: Option<IpcSender<CustomResponseMediator>>,
+    /// The URL of the client of the request, if any.
+    pub client_url: Option<ServoUrl>,
 }
 
 pub struct CancellationListener {
@@ -140,13 +147,9 @@ pub fn fetch_with_cors_cache(
     // Step 5.
     // TODO: figure out what a Priority object is.
 
-    // Step 6.
-    // TODO: handle client hints headers.
-
-    // Step 7.
-    if request.is_subresource_request() {
-        // TODO: handle client hints headers.
-    }
+    // Step 6, 7.
+    // The client hints are appended in `http_network_or_cache_fetch`, after the
+    // CORS preflight check, so that they never cause a preflight.
 
     // Step 8.
     main_fetch(request, cache, false, false, target, &mut None, &context);
@@ -176,6 +179,34 @@ pub fn should_request_be_blocked_by_csp(request: &Request) -> csp::CheckResult {
         .unwrap_or(csp::CheckResult::Allowed)
 }
 
+/// Surfaces a [mixed content](https://w3c.github.io/webappsec-mixed-content/) violation
+/// in the log and in the console of the pipeline that made the request.
+fn report_mixed_content(
+    request: &Request,
+    context: &FetchContext,
+    message: String,
+    level: LogLevel,
+) {
+    warn!("{}", message);
+    let (devtools_chan, pipeline_id) = match (&context.devtools_chan, request.pipeline_id) {
+        (Some(devtools_chan), Some(pipeline_id)) => (devtools_chan, pipeline_id),
+        _ => return,
+    };
+    let console_message = ConsoleMessage {
+        message,
+        logLevel: level,
+        filename: context
+            .client_url
+            .as_ref()
+            .map(ServoUrl::to_string)
+            .unwrap_or_default(),
+        lineNumber: 0,
+        columnNumber: 0,
+    };
+    let msg = ScriptToDevtoolsControlMsg::ConsoleAPI(pipeline_id, console_message, None);
+    let _ = devtools_chan.send(DevtoolsControlMsg::FromScript(msg));
+}
+
 /// [Main fetch](https://fetch.spec.whatwg.org/#concept-main-fetch)
 pub fn main_fetch(
     request: &mut Request,
@@ -216,15 +247,40 @@ pub fn main_fetch(
     // TODO: handle request abort.
 
     // Step 4.
-    // TODO: handle upgrade to a potentially secure URL.
+    let insecure_url = request.current_url();
+    mixed_content::upgrade_request_to_potentially_trustworthy_url(request);
+    if mixed_content::upgrade_mixed_content_request(request) {
+        report_mixed_content(
+            request,
+            context,
+            format!(
+                "Mixed Content: the insecure request for '{}' was upgraded to '{}'",
+                insecure_url,
+                request.current_url()
+            ),
+            LogLevel::Warn,
+        );
+    }
 
     // Step 5.
     if should_be_blocked_due_to_bad_port(&request.current_url()) {
         response = Some(Response::network_error(NetworkError::Internal(
             "Request attempted on bad port".into(),
         )));
+    } else if mixed_content::should_request_be_blocked_as_mixed_content(request) {
+        report_mixed_content(
+            request,
+            context,
+            format!(
+                "Mixed Content: the insecure request for '{}' was blocked",
+                request.current_url()
+            ),
+            LogLevel::Error,
+        );
+        response = Some(Response::network_error(NetworkError::Internal(
+            "Blocked as mixed content".into(),
+        )));
     }
-    // TODO: handle blocking as mixed content.
     // TODO: handle blocking by content security policy.
 
     // Step 6
@@ -374,6 +430,8 @@ pub fn main_fetch(
             should_be_blocked_due_to_nosniff(request.destination, &response.headers);
         let should_replace_with_mime_type_error = !response_is_network_error &&
             should_be_blocked_due_to_mime_type(request.destination, &response.headers);
+        let should_replace_with_mixed_content_error = !response_is_network_error &&
+            mixed_content::should_response_be_blocked_as_mixed_content(request, &response);
 
         // Step 15.
         let mut network_error_response = response
@@ -392,10 +450,23 @@ pub fn main_fetch(
         }
 
         // Step 17.
-        // TODO: handle blocking as mixed content.
         // TODO: handle blocking by content security policy.
         let blocked_error_response;
-        let internal_response = if should_replace_with_nosniff_error {
+        let internal_response = if should_replace_with_mixed_content_error {
+            report_mixed_content(
+                request,
+                context,
+                format!(
+                    "Mixed Content: the insecure response from '{}' was blocked",
+                    request.current_url()
+                ),
+                LogLevel::Error,
+            );
+            // Defer rebinding result
+            blocked_error_response =
+                Response::network_error(NetworkError::Internal("Blocked as mixed content".into()));
+            &blocked_error_response
+        } else if should_replace_with_nosniff_error {
             // Defer rebinding result
             blocked_error_response =
                 Response::network_error(NetworkError::Internal("Blocked by nosniff".into()));
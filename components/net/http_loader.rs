@@ -2,7 +2,11 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-use crate::connector::{create_http_client, ConnectionCerts, Connector, ExtraCerts, TlsConfig};
+use crate::cache_storage::CacheStorage;
+use crate::client_hints::{append_client_hints, ClientHintsStore};
+use crate::connector::{
+    create_http_clients, ConnectionCerts, Connector, ExtraCerts, HttpClients, TlsConfig,
+};
 use crate::cookie;
 use crate::cookie_storage::CookieStorage;
 use crate::decoder::Decoder;
@@ -10,6 +14,7 @@ use crate::fetch::cors_cache::CorsCache;
 use crate::fetch::methods::{main_fetch, Data, DoneChannel, FetchContext, Target};
 use crate::hsts::HstsList;
 use crate::http_cache::{CacheKey, HttpCache};
+use crate::private_network_access::{address_space_limit, find_private_network_access_error};
 use crate::resource_thread::AuthCache;
 use crossbeam_channel::{unbounded, Receiver, Sender};
 use devtools_traits::{
@@ -50,7 +55,10 @@ use net_traits::request::{
 use net_traits::request::{CacheMode, CredentialsMode, Destination, Origin};
 use net_traits::request::{ResponseTainting, ServiceWorkersMode};
 use net_traits::response::{HttpsState, Response, ResponseBody, ResponseType};
-use net_traits::{CookieSource, FetchMetadata, NetworkError, ReferrerPolicy};
+use net_traits::ReferrerPolicy;
+use net_traits::{
+    CookieSource, CustomResponse, CustomResponseMediator, FetchMetadata, NetworkError,
+};
 use net_traits::{
     RedirectEndValue, RedirectStartValue, ResourceAttribute, ResourceFetchTiming, ResourceTimeValue,
 };
@@ -66,9 +74,19 @@ use time::{self, Tm};
 use tokio::prelude::{future, Future, Sink, Stream};
 use tokio::runtime::Runtime;
 use tokio::sync::mpsc::{channel, Receiver as TokioReceiver, Sender as TokioSender};
+use tokio::sync::oneshot;
+use tokio::timer::Timeout;
 
 lazy_static! {
     pub static ref HANDLE: Mutex<Option<Runtime>> = Mutex::new(Some(Runtime::new().unwrap()));
+    static ref SEC_FETCH_DEST: HeaderName = HeaderName::from_static("sec-fetch-dest");
+    static ref SEC_FETCH_MODE: HeaderName = HeaderName::from_static("sec-fetch-mode");
+    static ref SEC_FETCH_SITE: HeaderName = HeaderName::from_static("sec-fetch-site");
+    static ref SEC_FETCH_USER: HeaderName = HeaderName::from_static("sec-fetch-user");
+    static ref ACCESS_CONTROL_REQUEST_PRIVATE_NETWORK: HeaderName =
+        HeaderName::from_static("access-control-request-private-network");
+    static ref ACCESS_CONTROL_ALLOW_PRIVATE_NETWORK: HeaderName =
+        HeaderName::from_static("access-control-allow-private-network");
 }
 
 /// The various states an entry of the HttpCache can be in.
@@ -92,7 +110,9 @@ pub struct HttpState {
     pub http_cache_state: Mutex<HashMap<CacheKey, Arc<(Mutex<HttpCacheEntryState>, Condvar)>>>,
     pub auth_cache: RwLock<AuthCache>,
     pub history_states: RwLock<HashMap<HistoryStateId, Vec<u8>>>,
-    pub client: Client<Connector, Body>,
+    pub cache_storage: RwLock<CacheStorage>,
+    pub client_hints: RwLock<ClientHintsStore>,
+    pub clients: HttpClients,
     pub extra_certs: ExtraCerts,
     pub connection_certs: ConnectionCerts,
 }
@@ -104,9 +124,11 @@ impl HttpState {
             cookie_jar: RwLock::new(CookieStorage::new(150)),
             auth_cache: RwLock::new(AuthCache::new()),
             history_states: RwLock::new(HashMap::new()),
+            cache_storage: RwLock::new(CacheStorage::new(None, pref!(dom.caches.quota) as usize)),
+            client_hints: RwLock::new(ClientHintsStore::new()),
             http_cache: RwLock::new(HttpCache::new()),
             http_cache_state: Mutex::new(HashMap::new()),
-            client: create_http_client(
+            clients: create_http_clients(
                 tls_config,
                 HANDLE.lock().unwrap().as_ref().unwrap().executor(),
             ),
@@ -218,6 +240,98 @@ fn strict_origin_when_cross_origin(
     strip_url_for_use_as_referrer(referrer_url, true)
 }
 
+/// Whether the client of a request, represented by its origin, is a secure context.
+fn is_secure_client(request: &Request) -> bool {
+    match request.origin {
+        Origin::Origin(ref origin) => origin.is_potentially_trustworthy(),
+        Origin::Client => false,
+    }
+}
+
+/// <https://html.spec.whatwg.org/multipage/#same-site>
+fn is_same_site(site_a: &ImmutableOrigin, site_b: &ImmutableOrigin) -> bool {
+    is_schemelessy_same_site(site_a, site_b) && site_a.scheme() == site_b.scheme()
+}
+
+/// <https://w3c.github.io/webappsec-fetch-metadata/#abstract-opdef-append-the-fetch-metadata-headers-for-a-request>
+fn append_fetch_metadata_headers(request: &mut Request) {
+    // Step 1.
+    if !request.current_url().is_potentially_trustworthy() {
+        return;
+    }
+
+    // Step 2, https://w3c.github.io/webappsec-fetch-metadata/#abstract-opdef-set-dest
+    let dest = match request.destination {
+        Destination::None => "empty",
+        Destination::Audio => "audio",
+        Destination::AudioWorklet => "audioworklet",
+        Destination::Document => "document",
+        Destination::Embed => "embed",
+        Destination::Font => "font",
+        Destination::Image => "image",
+        Destination::Manifest => "manifest",
+        Destination::Object => "object",
+        Destination::PaintWorklet => "paintworklet",
+        Destination::Report => "report",
+        Destination::Script => "script",
+        Destination::ServiceWorker => "serviceworker",
+        Destination::SharedWorker => "sharedworker",
+        Destination::Style => "style",
+        Destination::Track => "track",
+        Destination::Video => "video",
+        Destination::Worker => "worker",
+        Destination::Xslt => "xslt",
+    };
+    request
+        .headers
+        .insert(SEC_FETCH_DEST.clone(), HeaderValue::from_static(dest));
+
+    // Step 3, https://w3c.github.io/webappsec-fetch-metadata/#abstract-opdef-set-mode
+    let mode = match request.mode {
+        RequestMode::Navigate => "navigate",
+        RequestMode::SameOrigin => "same-origin",
+        RequestMode::NoCors => "no-cors",
+        RequestMode::CorsMode => "cors",
+        RequestMode::WebSocket { .. } => "websocket",
+    };
+    request
+        .headers
+        .insert(SEC_FETCH_MODE.clone(), HeaderValue::from_static(mode));
+
+    // Step 4, https://w3c.github.io/webappsec-fetch-metadata/#abstract-opdef-set-site
+    let site = if request.user_initiated {
+        Some("none")
+    } else if let Origin::Origin(ref origin) = request.origin {
+        let mut site = "same-origin";
+        for url in &request.url_list {
+            let url_origin = url.origin();
+            if url_origin == *origin {
+                continue;
+            }
+            site = "same-site";
+            if !is_same_site(&url_origin, origin) {
+                site = "cross-site";
+                break;
+            }
+        }
+        Some(site)
+    } else {
+        None
+    };
+    if let Some(site) = site {
+        request
+            .headers
+            .insert(SEC_FETCH_SITE.clone(), HeaderValue::from_static(site));
+    }
+
+    // Step 5, https://w3c.github.io/webappsec-fetch-metadata/#abstract-opdef-set-user
+    if request.is_navigation_request() && request.user_initiated {
+        request
+            .headers
+            .insert(SEC_FETCH_USER.clone(), HeaderValue::from_static("?1"));
+    }
+}
+
 /// https://html.spec.whatwg.org/multipage/#schemelessly-same-site
 fn is_schemelessy_same_site(site_a: &ImmutableOrigin, site_b: &ImmutableOrigin) -> bool {
     // Step 1
@@ -704,7 +818,11 @@ fn obtain_response(
                 Ok((Decoder::detect(res), msg))
             })
             .map_err(move |e| {
-                NetworkError::from_hyper_error(&e, connection_certs_clone.remove(host_clone))
+                let cert_bytes = connection_certs_clone.remove(host_clone);
+                match find_private_network_access_error(&e) {
+                    Some(address_space) => NetworkError::PrivateNetworkAccess(address_space),
+                    None => NetworkError::from_hyper_error(&e, cert_bytes),
+                }
             }),
     )
 }
@@ -730,8 +848,8 @@ pub fn http_fetch(
 
     // Step 3
     if request.service_workers_mode == ServiceWorkersMode::All {
-        // TODO: Substep 1
-        // Set response to the result of invoking handle fetch for request.
+        // Substep 1
+        response = handle_fetch(request, context);
 
         // Substep 2
         if let Some(ref res) = response {
@@ -771,7 +889,7 @@ pub fn http_fetch(
 
             // Sub-substep 1
             if method_mismatch || header_mismatch {
-                let preflight_result = cors_preflight_fetch(&request, cache, context);
+                let preflight_result = cors_preflight_fetch(&request, cache, context, false);
                 // Sub-substep 2
                 if let Some(e) = preflight_result.get_network_error() {
                     return Response::network_error(e.clone());
@@ -801,6 +919,37 @@ pub fn http_fetch(
             context,
         );
 
+        // https://wicg.github.io/private-network-access/#private-network-access-check
+        // The connector refused to connect to a more private address space than
+        // the client's. A secure client may ask the server for permission with a
+        // preflight, after which the connection is allowed to reach that space.
+        if let Some(&NetworkError::PrivateNetworkAccess(address_space)) =
+            fetch_result.get_network_error()
+        {
+            if request.target_address_space.is_some() ||
+                request.mode != RequestMode::CorsMode ||
+                !is_secure_client(request)
+            {
+                return Response::network_error(NetworkError::Internal(
+                    "Private network access check failed".into(),
+                ));
+            }
+            request.target_address_space = Some(address_space);
+            if !cache.match_private_network(&*request) {
+                let preflight_result = cors_preflight_fetch(&request, cache, context, true);
+                if let Some(e) = preflight_result.get_network_error() {
+                    return Response::network_error(e.clone());
+                }
+            }
+            fetch_result = http_network_or_cache_fetch(
+                request,
+                authentication_fetch_flag,
+                cors_flag,
+                done_chan,
+                context,
+            );
+        }
+
         // Substep 4
         if cors_flag && cors_check(&request, &fetch_result).is_err() {
             return Response::network_error(NetworkError::Internal("CORS check failed".into()));
@@ -1044,6 +1193,8 @@ pub fn http_redirect_fetch(
 
     // Step 13
     request.url_list.push(location_url);
+    // The new location may be in another address space.
+    request.target_address_space = None;
 
     // Step 14
     if let Some(referrer_policy) = response
@@ -1177,6 +1328,14 @@ fn http_network_or_cache_fetch(
         }
     }
 
+    append_fetch_metadata_headers(http_request);
+
+    // https://fetch.spec.whatwg.org/#concept-fetch step 6, 7
+    if http_request.is_navigation_request() || http_request.is_subresource_request() {
+        let client_hints = context.state.client_hints.read().unwrap();
+        append_client_hints(http_request, &client_hints, &context.user_agent);
+    }
+
     // Step 5.11
     if !http_request.headers.contains_key(header::USER_AGENT) {
         let user_agent = context.user_agent.clone().into_owned();
@@ -1703,7 +1862,7 @@ fn http_network_fetch(
     }
 
     let response_future = obtain_response(
-        &context.state.client,
+        context.state.clients.get(address_space_limit(request)),
         &url,
         &request.method,
         &mut request.headers,
@@ -1930,6 +2089,14 @@ fn http_network_fetch(
         .write()
         .unwrap()
         .update_hsts_list_from_response(&url, &response.headers);
+    if request.is_navigation_request() {
+        context
+            .state
+            .client_hints
+            .write()
+            .unwrap()
+            .update_from_response(&url, &response.headers);
+    }
 
     // TODO these steps
     // Step 16
@@ -1949,11 +2116,107 @@ fn http_network_fetch(
     response
 }
 
+/// [Handle fetch](https://w3c.github.io/ServiceWorker/#handle-fetch): ask the service worker
+/// manager for a response provided by a service worker. Returns `None` if the request should
+/// go to the network.
+fn handle_fetch(request: &Request, context: &FetchContext) -> Option<Response> {
+    let sw_manager = context.sw_manager.as_ref()?;
+
+    // Steps 11 and 12. Requests for workers are not handled.
+    if !request.is_navigation_request() && !request.is_subresource_request() {
+        return None;
+    }
+
+    let origin = match request.origin {
+        Origin::Origin(ref origin) => origin.clone(),
+        Origin::Client => request.current_url().origin(),
+    };
+    let mut request_builder = RequestBuilder::new(request.current_url(), request.referrer.clone())
+        .method(request.method.clone())
+        .headers(request.headers.clone())
+        .destination(request.destination.clone())
+        .mode(request.mode.clone())
+        .credentials_mode(request.credentials_mode)
+        .redirect_mode(request.redirect_mode)
+        .integrity_metadata(request.integrity_metadata.clone())
+        .referrer_policy(request.referrer_policy)
+        .pipeline_id(request.pipeline_id)
+        .origin(origin);
+    request_builder.cache_mode = request.cache_mode;
+
+    let (response_chan, response_port) = ipc::channel().unwrap();
+    let mediator = CustomResponseMediator {
+        response_chan,
+        load_url: request.current_url(),
+        request: request_builder,
+    };
+    if sw_manager.send(mediator).is_err() {
+        return None;
+    }
+    // The answer is awaited on the runtime, like the response of the network.
+    // A service worker which doesn't answer within its lifetime won't answer at
+    // all, in which case the request goes to the network, as it does when the
+    // service worker manager drops the channel.
+    let (answer_sender, answer_receiver) = oneshot::channel();
+    let mut answer_sender = Some(answer_sender);
+    ROUTER.add_route(
+        response_port.to_opaque(),
+        Box::new(move |message| {
+            if let Some(answer_sender) = answer_sender.take() {
+                let _ = answer_sender.send(message.to::<Option<CustomResponse>>());
+            }
+        }),
+    );
+    let timeout = Duration::from_secs(pref!(dom.serviceworker.timeout_seconds) as u64);
+    let (custom_response_sender, custom_response_receiver) = oneshot::channel();
+    HANDLE
+        .lock()
+        .unwrap()
+        .as_mut()
+        .unwrap()
+        .spawn(Timeout::new(answer_receiver, timeout).then(move |answer| {
+            let _ = custom_response_sender.send(answer.ok().and_then(Result::ok));
+            future::ok::<(), ()>(())
+        }));
+    let custom_response = match custom_response_receiver.wait() {
+        Ok(Some(custom_response)) => custom_response?,
+        _ => {
+            warn!(
+                "Service worker did not respond to fetch of {}",
+                request.current_url()
+            );
+            return None;
+        },
+    };
+
+    let response_type = custom_response.response_type;
+    if let ResponseType::Error(error) = response_type {
+        return Some(Response::network_error(error));
+    }
+    let url = custom_response.url.unwrap_or_else(|| request.current_url());
+    let mut response = Response::new(url, ResourceFetchTiming::new(request.timing_type()));
+    let (status, reason) = custom_response.raw_status;
+    response.raw_status = Some((status.as_u16(), reason.as_bytes().to_vec()));
+    response.status = Some((status, reason));
+    response.headers = custom_response.headers;
+    *response.body.lock().unwrap() = ResponseBody::Done(custom_response.body);
+
+    // Responses that script could only see filtered are handed back filtered.
+    Some(match response_type {
+        ResponseType::Basic | ResponseType::Default => response,
+        response_type => response.to_filtered(response_type),
+    })
+}
+
 /// [CORS preflight fetch](https://fetch.spec.whatwg.org#cors-preflight-fetch)
+///
+/// A preflight for a [private network request](https://wicg.github.io/private-network-access/#private-network-request)
+/// also asks the server whether it allows requests from less private networks.
 fn cors_preflight_fetch(
     request: &Request,
     cache: &mut CorsCache,
     context: &FetchContext,
+    private_network_request: bool,
 ) -> Response {
     // Step 1
     let mut preflight = RequestBuilder::new(request.current_url(), request.referrer.clone())
@@ -1993,6 +2256,14 @@ fn cors_preflight_fetch(
             .typed_insert(AccessControlRequestHeaders::from_iter(headers));
     }
 
+    // https://wicg.github.io/private-network-access/#cors-preflight
+    if private_network_request {
+        preflight.headers.insert(
+            ACCESS_CONTROL_REQUEST_PRIVATE_NETWORK.clone(),
+            HeaderValue::from_static("true"),
+        );
+    }
+
     // Step 6
     let response = http_network_or_cache_fetch(&mut preflight, false, false, &mut None, context);
     // Step 7
@@ -2002,6 +2273,18 @@ fn cors_preflight_fetch(
             .as_ref()
             .map_or(false, |(status, _)| status.is_success())
     {
+        // https://wicg.github.io/private-network-access/#cors-preflight
+        if private_network_request &&
+            response
+                .headers
+                .get(&*ACCESS_CONTROL_ALLOW_PRIVATE_NETWORK)
+                .map_or(true, |value| value.as_bytes() != b"true")
+        {
+            return Response::network_error(NetworkError::Internal(
+                "CORS private network check failed".into(),
+            ));
+        }
+
         // Substep 1
         let mut methods = if response
             .headers
@@ -2113,6 +2396,10 @@ fn cors_preflight_fetch(
             cache.match_header_and_update(&*request, &*header_name, max_age);
         }
 
+        if private_network_request {
+            cache.match_private_network_and_update(&*request, max_age);
+        }
+
         // Substep 16
         return response;
     }
@@ -17,6 +17,8 @@ extern crate serde;
 #[macro_use]
 extern crate servo_config;
 
+pub mod cache_storage;
+pub mod client_hints;
 pub mod connector;
 pub mod cookie;
 pub mod cookie_storage;
@@ -29,9 +31,12 @@ pub mod http_cache;
 pub mod http_loader;
 pub mod image_cache;
 pub mod mime_classifier;
+pub mod mixed_content;
+pub mod private_network_access;
 pub mod resource_thread;
 mod storage_thread;
 pub mod subresource_integrity;
+mod websocket_deflate;
 mod websocket_loader;
 /// An implementation of the [Fetch specification](https://fetch.spec.whatwg.org/)
 pub mod fetch {
@@ -43,6 +48,8 @@ pub mod fetch {
 pub mod test {
     pub use crate::hosts::{parse_hostsfile, replace_host_table};
     pub use crate::http_loader::HttpState;
+    pub use crate::websocket_deflate::{is_message_too_big, parse_extensions_header};
+    pub use crate::websocket_deflate::{DeflateDecoder, DeflateEncoder, DeflateParams};
 }
 
 // This dependency gives `build.rs` access to the `DEP_OPENSSL_VERSION_NUMBER` env variable.
@@ -0,0 +1,159 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Implementation of [Mixed Content](https://w3c.github.io/webappsec-mixed-content/)
+//! and [Upgrade Insecure Requests](https://w3c.github.io/webappsec-upgrade-insecure-requests/).
+
+use content_security_policy as csp;
+use net_traits::request::{Destination, Initiator, Origin, Request};
+use net_traits::response::Response;
+use servo_url::ServoUrl;
+use url::Host;
+
+/// <https://w3c.github.io/webappsec-upgrade-insecure-requests/#insecure-requests-policy>
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InsecureRequestsPolicy {
+    DoNotUpgrade,
+    Upgrade,
+}
+
+/// The insecure requests policy set by the `upgrade-insecure-requests` directive
+/// of the enforced policies of a CSP list.
+pub fn insecure_requests_policy(csp_list: Option<&csp::CspList>) -> InsecureRequestsPolicy {
+    let upgrade = csp_list.map_or(false, |csp_list| {
+        csp_list.0.iter().any(|policy| {
+            policy.disposition == csp::PolicyDisposition::Enforce &&
+                policy
+                    .directive_set
+                    .iter()
+                    .any(|directive| directive.name == "upgrade-insecure-requests")
+        })
+    });
+    if upgrade {
+        InsecureRequestsPolicy::Upgrade
+    } else {
+        InsecureRequestsPolicy::DoNotUpgrade
+    }
+}
+
+/// <https://w3c.github.io/webappsec-upgrade-insecure-requests/#upgrade-request>
+///
+/// Returns whether the URL of the request was upgraded.
+pub fn upgrade_request_to_potentially_trustworthy_url(request: &mut Request) -> bool {
+    let origin = match request.origin {
+        Origin::Origin(ref origin) => origin.clone(),
+        Origin::Client => return false,
+    };
+    if insecure_requests_policy(request.csp_list.as_ref()) == InsecureRequestsPolicy::DoNotUpgrade {
+        return false;
+    }
+
+    // Step 1. Top-level navigations are only upgraded within the client's own
+    // host, the only member of its upgrade insecure navigations set.
+    if request.is_navigation_request() {
+        if request.current_url().origin().host() != origin.host() {
+            return false;
+        }
+    }
+
+    // Steps 2-3.
+    upgrade_url(request.current_url_mut())
+}
+
+/// <https://w3c.github.io/webappsec-mixed-content/#upgrade-algorithm>
+///
+/// Returns whether the URL of the request was upgraded.
+pub fn upgrade_mixed_content_request(request: &mut Request) -> bool {
+    let url = request.current_url();
+
+    // Step 1.
+    if is_potentially_trustworthy(&url) {
+        return false;
+    }
+
+    // Step 2.
+    match url.host() {
+        Some(Host::Ipv4(_)) | Some(Host::Ipv6(_)) => return false,
+        _ => {},
+    }
+
+    // Step 3.
+    if !prohibits_mixed_security_contexts(request) {
+        return false;
+    }
+
+    // Step 4.
+    match request.destination {
+        Destination::Audio | Destination::Video => {},
+        Destination::Image if request.initiator != Initiator::ImageSet => {},
+        _ => return false,
+    }
+
+    // Step 5.
+    upgrade_url(request.current_url_mut())
+}
+
+/// <https://w3c.github.io/webappsec-mixed-content/#should-block-fetch>
+pub fn should_request_be_blocked_as_mixed_content(request: &Request) -> bool {
+    // Step 1.
+    if !prohibits_mixed_security_contexts(request) ||
+        is_potentially_trustworthy(&request.current_url()) ||
+        request.destination == Destination::Document
+    {
+        return false;
+    }
+
+    // Step 2.
+    true
+}
+
+/// <https://w3c.github.io/webappsec-mixed-content/#should-block-response>
+pub fn should_response_be_blocked_as_mixed_content(request: &Request, response: &Response) -> bool {
+    // Step 1.
+    if !prohibits_mixed_security_contexts(request) ||
+        response
+            .actual_response()
+            .url()
+            .map_or(true, is_potentially_trustworthy) ||
+        request.destination == Destination::Document
+    {
+        return false;
+    }
+
+    // Step 2.
+    true
+}
+
+/// <https://w3c.github.io/webappsec-mixed-content/#categorize-settings-object>
+///
+/// The client of a request is represented by the origin of the request.
+fn prohibits_mixed_security_contexts(request: &Request) -> bool {
+    // Allowing mixed content is the user agent's choice.
+    if !pref!(network.mixed_content.blocking.enabled) {
+        return false;
+    }
+    match request.origin {
+        // Step 1.
+        Origin::Origin(ref origin) => origin.is_potentially_trustworthy(),
+        // TODO: Step 2, check the ancestors of the client.
+        Origin::Client => false,
+    }
+}
+
+/// Whether a URL is potentially trustworthy, with `blob:` URLs being as trustworthy
+/// as the origin they were created in.
+fn is_potentially_trustworthy(url: &ServoUrl) -> bool {
+    url.is_potentially_trustworthy() || url.origin().is_potentially_trustworthy()
+}
+
+/// Switches an insecure URL to its secure counterpart, keeping any explicit port.
+fn upgrade_url(url: &mut ServoUrl) -> bool {
+    let upgraded_scheme = match url.scheme() {
+        "http" => "https",
+        "ws" => "wss",
+        _ => return false,
+    };
+    url.as_mut_url().set_scheme(upgraded_scheme).unwrap();
+    true
+}
@@ -0,0 +1,133 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Implementation of [Private Network Access](https://wicg.github.io/private-network-access/).
+//!
+//! The address space of the target of a request is that of the remote address of
+//! its connection. The connector checks it once connected, before the request is
+//! sent, and fails the connection when it is more private than the address space
+//! of the client, which the fetch then turns into a preflight or a network error.
+//! The address space of the client is determined from the host of its origin.
+
+use crate::hosts::replace_host;
+pub use net_traits::request::IpAddressSpace;
+use net_traits::request::{Origin, Request, RequestMode};
+use std::error::Error;
+use std::fmt;
+use std::net::{IpAddr, Ipv6Addr};
+use url::Host;
+
+/// <https://wicg.github.io/private-network-access/#determine-the-ip-address-space>
+pub fn ip_address_space(host: Host<&str>) -> IpAddressSpace {
+    let ip = match host {
+        Host::Ipv4(ip) => IpAddr::V4(ip),
+        Host::Ipv6(ip) => IpAddr::V6(ip),
+        Host::Domain(domain) => {
+            if domain == "localhost" || domain.ends_with(".localhost") {
+                return IpAddressSpace::Local;
+            }
+            match replace_host(domain).parse() {
+                Ok(ip) => ip,
+                Err(_) => return IpAddressSpace::Public,
+            }
+        },
+    };
+    address_space_of_ip(ip)
+}
+
+/// <https://wicg.github.io/private-network-access/#determine-the-ip-address-space>
+pub fn address_space_of_ip(ip: IpAddr) -> IpAddressSpace {
+    match ip {
+        IpAddr::V4(ip) if ip.is_loopback() => IpAddressSpace::Local,
+        IpAddr::V4(ip) if ip.is_private() || ip.is_link_local() => IpAddressSpace::Private,
+        IpAddr::V6(ip) if ip.is_loopback() => IpAddressSpace::Local,
+        IpAddr::V6(ip) if is_unique_local(&ip) || is_unicast_link_local(&ip) => {
+            IpAddressSpace::Private
+        },
+        // ::ffff:0:0/96, IPv4 addresses mapped to IPv6 ones.
+        IpAddr::V6(ip) if ip.segments()[..6] == [0, 0, 0, 0, 0, 0xffff] => {
+            address_space_of_ip(IpAddr::V4(ip.to_ipv4().unwrap()))
+        },
+        _ => IpAddressSpace::Public,
+    }
+}
+
+/// <https://wicg.github.io/private-network-access/#private-network-access-check>
+///
+/// Returns the most private address space that the connection of `request` may
+/// reach, or `None` if it may reach any.
+///
+/// Once a preflight allowed the request to reach the address space it was
+/// refused, that is its target address space. Before, it is the address space of
+/// its client, except for no-cors requests of secure contexts, which cannot be
+/// preflighted and are not restricted.
+pub fn address_space_limit(request: &Request) -> Option<IpAddressSpace> {
+    // Navigations are not covered by the specification yet.
+    if !pref!(network.private_network_access.enabled) || request.is_navigation_request() {
+        return None;
+    }
+    if let Some(target_address_space) = request.target_address_space {
+        return Some(target_address_space);
+    }
+    let client_origin = match request.origin {
+        Origin::Origin(ref origin) => origin,
+        Origin::Client => return None,
+    };
+    if client_origin.is_potentially_trustworthy() && request.mode != RequestMode::CorsMode {
+        return None;
+    }
+    let client_address_space = match client_origin.host() {
+        Some(host) => ip_address_space(borrow_host(host)),
+        // Opaque origins are treated as public.
+        None => IpAddressSpace::Public,
+    };
+    Some(client_address_space)
+}
+
+/// The error of a connection whose remote address is in a more private address
+/// space than the connection was allowed to reach.
+#[derive(Debug)]
+pub struct PrivateNetworkAccessError(pub IpAddressSpace);
+
+impl fmt::Display for PrivateNetworkAccessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Private network access to the {:?} address space is not allowed",
+            self.0
+        )
+    }
+}
+
+impl Error for PrivateNetworkAccessError {}
+
+/// Finds a `PrivateNetworkAccessError` among the causes of a connection error.
+pub fn find_private_network_access_error(error: &(dyn Error + 'static)) -> Option<IpAddressSpace> {
+    let mut source = Some(error);
+    while let Some(error) = source {
+        if let Some(error) = error.downcast_ref::<PrivateNetworkAccessError>() {
+            return Some(error.0);
+        }
+        source = error.source();
+    }
+    None
+}
+
+fn borrow_host(host: &Host) -> Host<&str> {
+    match *host {
+        Host::Domain(ref domain) => Host::Domain(domain),
+        Host::Ipv4(ip) => Host::Ipv4(ip),
+        Host::Ipv6(ip) => Host::Ipv6(ip),
+    }
+}
+
+/// fc00::/7
+fn is_unique_local(ip: &Ipv6Addr) -> bool {
+    (ip.segments()[0] & 0xfe00) == 0xfc00
+}
+
+/// fe80::/10
+fn is_unicast_link_local(ip: &Ipv6Addr) -> bool {
+    (ip.segments()[0] & 0xffc0) == 0xfe80
+}
@@ -4,8 +4,10 @@
 
 //! A thread that takes a URL and streams back the binary data.
 
+use crate::cache_storage::CacheStorage;
+use crate::client_hints::ClientHintsStore;
 use crate::connector::{
-    create_http_client, create_tls_config, ConnectionCerts, ExtraCerts, ALPN_H2_H1,
+    create_http_clients, create_tls_config, ConnectionCerts, ExtraCerts, ALPN_H2_H1,
 };
 use crate::cookie;
 use crate::cookie_storage::CookieStorage;
@@ -26,7 +28,7 @@ use ipc_channel::ipc::{self, IpcReceiver, IpcReceiverSet, IpcSender};
 use malloc_size_of::{MallocSizeOf, MallocSizeOfOps};
 use net_traits::blob_url_store::parse_blob_url;
 use net_traits::filemanager_thread::FileTokenCheck;
-use net_traits::request::{Destination, RequestBuilder};
+use net_traits::request::{Destination, Referrer, RequestBuilder};
 use net_traits::response::{Response, ResponseInit};
 use net_traits::storage_thread::StorageThreadMsg;
 use net_traits::DiscardFetch;
@@ -134,10 +136,12 @@ fn create_http_states(
     let mut auth_cache = AuthCache::new();
     let http_cache = HttpCache::new();
     let mut cookie_jar = CookieStorage::new(150);
+    let mut client_hints = ClientHintsStore::new();
     if let Some(config_dir) = config_dir {
         read_json_from_file(&mut auth_cache, config_dir, "auth_cache.json");
         read_json_from_file(&mut hsts_list, config_dir, "hsts_list.json");
         read_json_from_file(&mut cookie_jar, config_dir, "cookie_jar.json");
+        read_json_from_file(&mut client_hints, config_dir, "client_hints.json");
     }
 
     let certs = match certificate_path {
@@ -153,9 +157,14 @@ fn create_http_states(
         cookie_jar: RwLock::new(cookie_jar),
         auth_cache: RwLock::new(auth_cache),
         history_states: RwLock::new(HashMap::new()),
+        cache_storage: RwLock::new(CacheStorage::new(
+            config_dir.map(|config_dir| config_dir.join("cache_storage")),
+            pref!(dom.caches.quota) as usize,
+        )),
+        client_hints: RwLock::new(client_hints),
         http_cache: RwLock::new(http_cache),
         http_cache_state: Mutex::new(HashMap::new()),
-        client: create_http_client(
+        clients: create_http_clients(
             create_tls_config(
                 &certs,
                 ALPN_H2_H1,
@@ -176,9 +185,11 @@ fn create_http_states(
         cookie_jar: RwLock::new(CookieStorage::new(150)),
         auth_cache: RwLock::new(AuthCache::new()),
         history_states: RwLock::new(HashMap::new()),
+        cache_storage: RwLock::new(CacheStorage::new(None, pref!(dom.caches.quota) as usize)),
+        client_hints: RwLock::new(ClientHintsStore::new()),
         http_cache: RwLock::new(HttpCache::new()),
         http_cache_state: Mutex::new(HashMap::new()),
-        client: create_http_client(
+        clients: create_http_clients(
             create_tls_config(
                 &certs,
                 ALPN_H2_H1,
@@ -358,6 +369,9 @@ impl ResourceChannelManager {
                 http_state.http_cache.write().unwrap().clear();
             },
             CoreResourceMsg::ToFileManager(msg) => self.resource_manager.filemanager.handle(msg),
+            CoreResourceMsg::CacheStorage(msg) => {
+                http_state.cache_storage.write().unwrap().handle_msg(msg);
+            },
             CoreResourceMsg::Exit(sender) => {
                 if let Some(ref config_dir) = self.config_dir {
                     match http_state.auth_cache.read() {
@@ -374,6 +388,12 @@ impl ResourceChannelManager {
                         Ok(hsts) => write_json_to_file(&*hsts, config_dir, "hsts_list.json"),
                         Err(_) => warn!("Error writing hsts list to disk"),
                     }
+                    match http_state.client_hints.read() {
+                        Ok(client_hints) => {
+                            write_json_to_file(&*client_hints, config_dir, "client_hints.json")
+                        },
+                        Err(_) => warn!("Error writing client hints to disk"),
+                    }
                 }
                 self.resource_manager.exit();
                 let _ = sender.send(());
@@ -659,6 +679,21 @@ impl CoreResourceManager {
         let mut request = request_builder.build();
         let url = request.current_url();
 
+        // The client of the request is only known through the referrer set by script,
+        // which main fetch later replaces.
+        let client_url = match request.referrer {
+            Referrer::Client(ref client_url) => Some(client_url.clone()),
+            _ => None,
+        };
+        // Navigations are handled by the service workers of the origin navigated to,
+        // other requests by the ones of the origin of their client.
+        let sw_origin = if request.is_navigation_request() {
+            Some(url.origin())
+        } else {
+            client_url.as_ref().map(ServoUrl::origin)
+        };
+        let sw_manager = sw_origin.and_then(|origin| self.sw_managers.get(&origin).cloned());
+
         // In the case of a valid blob URL, acquiring a token granting access to a file,
         // regardless if the URL is revoked after token acquisition.
         //
@@ -693,6 +728,8 @@ impl CoreResourceManager {
                 file_token,
                 cancellation_listener: Arc::new(Mutex::new(CancellationListener::new(cancel_chan))),
                 timing: ServoArc::new(Mutex::new(ResourceFetchTiming::new(request.timing_type()))),
+                sw_manager,
+                client_url,
             };
 
             match res_init_ {
@@ -733,6 +770,7 @@ impl CoreResourceManager {
             action_receiver,
             http_state.clone(),
             self.certificate_path.clone(),
+            self.devtools_chan.clone(),
         );
     }
 }
@@ -0,0 +1,332 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use http::header::{self, HeaderMap, HeaderValue};
+use http::Method;
+use net::cache_storage::CacheStorage;
+use net_traits::cache_storage::{CacheBatchError, CacheBatchOperation, CacheEntry};
+use net_traits::cache_storage::{CacheId, CacheQueryOptions};
+use net_traits::cache_storage::{CacheSelector, CachedRequest, CachedResponse, CachedResponseType};
+use servo_url::{ImmutableOrigin, ServoUrl};
+use std::{env, fs, process};
+
+fn origin() -> ImmutableOrigin {
+    ServoUrl::parse("https://example.com").unwrap().origin()
+}
+
+fn request(url: &str) -> CachedRequest {
+    CachedRequest {
+        url: ServoUrl::parse(url).unwrap(),
+        method: Method::GET,
+        headers: HeaderMap::new(),
+    }
+}
+
+fn entry(request: CachedRequest, body: &[u8]) -> CacheEntry {
+    CacheEntry {
+        response: CachedResponse {
+            response_type: CachedResponseType::Basic,
+            url: Some(request.url.clone()),
+            redirected: false,
+            status: (200, b"OK".to_vec()),
+            headers: HeaderMap::new(),
+            body: body.to_vec(),
+        },
+        request,
+    }
+}
+
+fn bodies(entries: Vec<CacheEntry>) -> Vec<Vec<u8>> {
+    entries
+        .into_iter()
+        .map(|entry| entry.response.body)
+        .collect()
+}
+
+#[test]
+fn test_open_has_delete_and_keys() {
+    let mut storage = CacheStorage::new(None, 1024);
+    let first = storage.open(&origin(), "v1".to_owned());
+    let second = storage.open(&origin(), "v2".to_owned());
+    assert_ne!(first, second);
+    assert_eq!(storage.open(&origin(), "v1".to_owned()), first);
+    assert!(storage.has(&origin(), "v1"));
+    assert_eq!(storage.keys(&origin()), vec!["v1", "v2"]);
+
+    let other = ServoUrl::parse("https://example.org").unwrap().origin();
+    assert!(!storage.has(&other, "v1"));
+    assert!(storage.keys(&other).is_empty());
+
+    assert!(storage.delete(&origin(), "v1"));
+    assert!(!storage.delete(&origin(), "v1"));
+    assert_eq!(storage.keys(&origin()), vec!["v2"]);
+}
+
+#[test]
+fn test_put_replaces_and_matches_ignoring_fragment() {
+    let mut storage = CacheStorage::new(None, 1024);
+    let id = storage.open(&origin(), "v1".to_owned());
+    let operations = vec![
+        CacheBatchOperation::Put(entry(request("https://example.com/a"), b"old")),
+        CacheBatchOperation::Put(entry(request("https://example.com/b"), b"b")),
+    ];
+    assert_eq!(
+        storage.batch_operations(&origin(), id, operations),
+        Ok(false)
+    );
+    let operations = vec![CacheBatchOperation::Put(entry(
+        request("https://example.com/a"),
+        b"new",
+    ))];
+    assert_eq!(
+        storage.batch_operations(&origin(), id, operations),
+        Ok(false)
+    );
+
+    let query = request("https://example.com/a#fragment");
+    let matched = storage.match_all(
+        &origin(),
+        &CacheSelector::Id(id),
+        Some(&query),
+        CacheQueryOptions::default(),
+    );
+    assert_eq!(bodies(matched), vec![b"new".to_vec()]);
+
+    let all = storage.match_all(
+        &origin(),
+        &CacheSelector::All,
+        None,
+        CacheQueryOptions::default(),
+    );
+    assert_eq!(bodies(all), vec![b"b".to_vec(), b"new".to_vec()]);
+}
+
+#[test]
+fn test_query_options() {
+    let mut storage = CacheStorage::new(None, 1024);
+    let id = storage.open(&origin(), "v1".to_owned());
+    let operations = vec![CacheBatchOperation::Put(entry(
+        request("https://example.com/a?x=1"),
+        b"a",
+    ))];
+    storage.batch_operations(&origin(), id, operations).unwrap();
+
+    let selector = CacheSelector::Name("v1".to_owned());
+    let query = request("https://example.com/a?x=2");
+    let options = CacheQueryOptions::default();
+    assert!(storage
+        .match_all(&origin(), &selector, Some(&query), options)
+        .is_empty());
+    let options = CacheQueryOptions {
+        ignore_search: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        storage
+            .match_all(&origin(), &selector, Some(&query), options)
+            .len(),
+        1
+    );
+
+    let mut query = request("https://example.com/a?x=1");
+    query.method = Method::POST;
+    let options = CacheQueryOptions::default();
+    assert!(storage
+        .match_all(&origin(), &selector, Some(&query), options)
+        .is_empty());
+    let options = CacheQueryOptions {
+        ignore_method: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        storage
+            .match_all(&origin(), &selector, Some(&query), options)
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn test_vary() {
+    let mut storage = CacheStorage::new(None, 1024);
+    let id = storage.open(&origin(), "v1".to_owned());
+    let mut cached_request = request("https://example.com/a");
+    cached_request
+        .headers
+        .insert(header::ACCEPT_LANGUAGE, HeaderValue::from_static("en"));
+    let mut cached = entry(cached_request, b"a");
+    cached
+        .response
+        .headers
+        .insert(header::VARY, HeaderValue::from_static("Accept-Language"));
+    let mut any = entry(request("https://example.com/any"), b"any");
+    any.response
+        .headers
+        .insert(header::VARY, HeaderValue::from_static("*"));
+    let operations = vec![
+        CacheBatchOperation::Put(cached),
+        CacheBatchOperation::Put(any),
+    ];
+    storage.batch_operations(&origin(), id, operations).unwrap();
+
+    let selector = CacheSelector::Id(id);
+    let options = CacheQueryOptions::default();
+    let mut query = request("https://example.com/a");
+    query
+        .headers
+        .insert(header::ACCEPT_LANGUAGE, HeaderValue::from_static("fr"));
+    assert!(storage
+        .match_all(&origin(), &selector, Some(&query), options)
+        .is_empty());
+    query
+        .headers
+        .insert(header::ACCEPT_LANGUAGE, HeaderValue::from_static("en"));
+    assert_eq!(
+        storage
+            .match_all(&origin(), &selector, Some(&query), options)
+            .len(),
+        1
+    );
+
+    // Every value of a header is compared, not only the first one.
+    query
+        .headers
+        .append(header::ACCEPT_LANGUAGE, HeaderValue::from_static("fr"));
+    assert!(storage
+        .match_all(&origin(), &selector, Some(&query), options)
+        .is_empty());
+
+    let query = request("https://example.com/any");
+    assert!(storage
+        .match_all(&origin(), &selector, Some(&query), options)
+        .is_empty());
+    let options = CacheQueryOptions {
+        ignore_vary: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        storage
+            .match_all(&origin(), &selector, Some(&query), options)
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn test_batch_operations_are_atomic() {
+    let mut storage = CacheStorage::new(None, 1024);
+    let id = storage.open(&origin(), "v1".to_owned());
+    let operations = vec![CacheBatchOperation::Put(entry(
+        request("https://example.com/a"),
+        b"a",
+    ))];
+    storage.batch_operations(&origin(), id, operations).unwrap();
+
+    // Adding the same request twice in one batch fails and rolls everything back.
+    let operations = vec![
+        CacheBatchOperation::Delete(request("https://example.com/a"), Default::default()),
+        CacheBatchOperation::Put(entry(request("https://example.com/b"), b"b")),
+        CacheBatchOperation::Put(entry(request("https://example.com/b"), b"b")),
+    ];
+    assert_eq!(
+        storage.batch_operations(&origin(), id, operations),
+        Err(CacheBatchError::InvalidState)
+    );
+    let all = storage.match_all(&origin(), &CacheSelector::Id(id), None, Default::default());
+    assert_eq!(bodies(all), vec![b"a".to_vec()]);
+
+    let operations = vec![CacheBatchOperation::Delete(
+        request("https://example.com/a"),
+        Default::default(),
+    )];
+    assert_eq!(
+        storage.batch_operations(&origin(), id, operations),
+        Ok(true)
+    );
+    let operations = vec![CacheBatchOperation::Delete(
+        request("https://example.com/a"),
+        Default::default(),
+    )];
+    assert_eq!(
+        storage.batch_operations(&origin(), id, operations),
+        Ok(false)
+    );
+}
+
+#[test]
+fn test_quota() {
+    let mut storage = CacheStorage::new(None, 4);
+    let first = storage.open(&origin(), "v1".to_owned());
+    let second = storage.open(&origin(), "v2".to_owned());
+    let operations = vec![CacheBatchOperation::Put(entry(
+        request("https://example.com/a"),
+        b"aaa",
+    ))];
+    storage
+        .batch_operations(&origin(), first, operations)
+        .unwrap();
+
+    // The quota is shared by the caches of an origin.
+    let operations = vec![CacheBatchOperation::Put(entry(
+        request("https://example.com/b"),
+        b"bb",
+    ))];
+    assert_eq!(
+        storage.batch_operations(&origin(), second, operations),
+        Err(CacheBatchError::QuotaExceeded)
+    );
+    assert!(storage
+        .match_all(
+            &origin(),
+            &CacheSelector::Id(second),
+            None,
+            Default::default()
+        )
+        .is_empty());
+
+    // Replacing an entry only counts the new body.
+    let operations = vec![CacheBatchOperation::Put(entry(
+        request("https://example.com/a"),
+        b"aaaa",
+    ))];
+    storage
+        .batch_operations(&origin(), first, operations)
+        .unwrap();
+
+    let other = ServoUrl::parse("https://example.org").unwrap().origin();
+    let id = storage.open(&other, "v1".to_owned());
+    let operations = vec![CacheBatchOperation::Put(entry(
+        request("https://example.org/a"),
+        b"aaaa",
+    ))];
+    storage.batch_operations(&other, id, operations).unwrap();
+}
+
+#[test]
+fn test_persistence() {
+    let directory = env::temp_dir().join(format!("servo-cache-storage-{}", process::id()));
+    let id: CacheId;
+    {
+        let mut storage = CacheStorage::new(Some(directory.clone()), 1024);
+        id = storage.open(&origin(), "v1".to_owned());
+        let operations = vec![
+            CacheBatchOperation::Put(entry(request("https://example.com/a"), b"a")),
+            CacheBatchOperation::Put(entry(request("https://example.com/b"), b"b")),
+        ];
+        storage.batch_operations(&origin(), id, operations).unwrap();
+        let operations = vec![CacheBatchOperation::Delete(
+            request("https://example.com/a"),
+            Default::default(),
+        )];
+        storage.batch_operations(&origin(), id, operations).unwrap();
+    }
+
+    // The index and the remaining body are all that is left on disk.
+    assert_eq!(fs::read_dir(&directory).unwrap().count(), 2);
+    let storage = CacheStorage::new(Some(directory.clone()), 1024);
+    assert_eq!(storage.keys(&origin()), vec!["v1"]);
+    let all = storage.match_all(&origin(), &CacheSelector::Id(id), None, Default::default());
+    assert_eq!(bodies(all), vec![b"b".to_vec()]);
+    fs::remove_dir_all(&directory).unwrap();
+}
@@ -9,6 +9,7 @@ use crate::{
     create_embedder_proxy, fetch, make_server, make_ssl_server, new_fetch_context,
     DEFAULT_USER_AGENT,
 };
+use content_security_policy as csp;
 use crossbeam_channel::{unbounded, Sender};
 use devtools_traits::HttpRequest as DevtoolsHttpRequest;
 use devtools_traits::HttpResponse as DevtoolsHttpResponse;
@@ -20,6 +21,7 @@ use http::header::{self, HeaderMap, HeaderName, HeaderValue};
 use http::{Method, StatusCode};
 use hyper::body::Body;
 use hyper::{Request as HyperRequest, Response as HyperResponse};
+use ipc_channel::ipc;
 use mime::{self, Mime};
 use msg::constellation_msg::TEST_PIPELINE_ID;
 use net::connector::{create_tls_config, ConnectionCerts, ExtraCerts, ALPN_H2_H1};
@@ -34,6 +36,7 @@ use net_traits::request::{
     Destination, Origin, RedirectMode, Referrer, Request, RequestBuilder, RequestMode,
 };
 use net_traits::response::{CacheState, HttpsState, Response, ResponseBody, ResponseType};
+use net_traits::{CustomResponse, CustomResponseMediator};
 use net_traits::{
     FetchTaskTarget, IncludeSubdomains, NetworkError, ReferrerPolicy, ResourceFetchTiming,
     ResourceTimingType,
@@ -45,6 +48,7 @@ use std::iter::FromIterator;
 use std::path::Path;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex, Weak};
+use std::thread;
 use std::time::{Duration, SystemTime};
 use uuid::Uuid;
 
@@ -94,6 +98,75 @@ fn test_fetch_on_bad_port_is_network_error() {
     )
 }
 
+#[test]
+fn test_fetch_blocks_mixed_content() {
+    let url = ServoUrl::parse("http://www.example.org/script.js").unwrap();
+    let origin = ServoUrl::parse("https://www.example.org").unwrap().origin();
+    let mut request = Request::new(
+        url,
+        Some(Origin::Origin(origin)),
+        Referrer::NoReferrer,
+        None,
+        HttpsState::None,
+    );
+    request.destination = Destination::Script;
+    let fetch_response = fetch(&mut request, None);
+    assert_eq!(
+        fetch_response.get_network_error(),
+        Some(&NetworkError::Internal("Blocked as mixed content".into()))
+    );
+}
+
+#[test]
+fn test_fetch_upgrades_mixed_content_images() {
+    // The bad port makes the upgraded request fail before reaching the network.
+    let url = ServoUrl::parse("http://www.example.org:6667/image.png").unwrap();
+    let origin = ServoUrl::parse("https://www.example.org").unwrap().origin();
+    let mut request = Request::new(
+        url,
+        Some(Origin::Origin(origin)),
+        Referrer::NoReferrer,
+        None,
+        HttpsState::None,
+    );
+    request.destination = Destination::Image;
+    let fetch_response = fetch(&mut request, None);
+    assert_eq!(request.current_url().scheme(), "https");
+    assert_eq!(
+        fetch_response.get_network_error(),
+        Some(&NetworkError::Internal(
+            "Request attempted on bad port".into()
+        ))
+    );
+}
+
+#[test]
+fn test_fetch_upgrade_insecure_requests() {
+    let url = ServoUrl::parse("http://www.example.org:6667/script.js").unwrap();
+    let origin = ServoUrl::parse("http://www.example.org").unwrap().origin();
+    let mut request = Request::new(
+        url,
+        Some(Origin::Origin(origin)),
+        Referrer::NoReferrer,
+        None,
+        HttpsState::None,
+    );
+    request.destination = Destination::Script;
+    request.csp_list = Some(csp::CspList::parse(
+        "upgrade-insecure-requests",
+        csp::PolicySource::Header,
+        csp::PolicyDisposition::Enforce,
+    ));
+    let fetch_response = fetch(&mut request, None);
+    assert_eq!(request.current_url().scheme(), "https");
+    assert_eq!(
+        fetch_response.get_network_error(),
+        Some(&NetworkError::Internal(
+            "Request attempted on bad port".into()
+        ))
+    );
+}
+
 #[test]
 fn test_fetch_response_body_matches_const_message() {
     static MESSAGE: &'static [u8] = b"Hello World!";
@@ -776,6 +849,8 @@ fn test_fetch_with_hsts() {
         timing: ServoArc::new(Mutex::new(ResourceFetchTiming::new(
             ResourceTimingType::Navigation,
         ))),
+        sw_manager: None,
+        client_url: None,
     };
 
     {
@@ -839,6 +914,8 @@ fn test_load_adds_host_to_hsts_list_when_url_is_https() {
         timing: ServoArc::new(Mutex::new(ResourceFetchTiming::new(
             ResourceTimingType::Navigation,
         ))),
+        sw_manager: None,
+        client_url: None,
     };
 
     let mut request = RequestBuilder::new(url.clone(), Referrer::NoReferrer)
@@ -904,6 +981,8 @@ fn test_fetch_self_signed() {
         timing: ServoArc::new(Mutex::new(ResourceFetchTiming::new(
             ResourceTimingType::Navigation,
         ))),
+        sw_manager: None,
+        client_url: None,
     };
 
     let mut request = RequestBuilder::new(url.clone(), Referrer::NoReferrer)
@@ -1387,6 +1466,17 @@ fn test_fetch_with_devtools() {
 
     headers.typed_insert::<UserAgent>(DEFAULT_USER_AGENT.parse().unwrap());
 
+    headers.insert("sec-fetch-dest", HeaderValue::from_static("empty"));
+    headers.insert("sec-fetch-mode", HeaderValue::from_static("no-cors"));
+    headers.insert("sec-fetch-site", HeaderValue::from_static("same-origin"));
+
+    headers.insert("sec-ch-ua", HeaderValue::from_static("\"Servo\";v=\"1\""));
+    headers.insert("sec-ch-ua-mobile", HeaderValue::from_static("?0"));
+    headers.insert(
+        "sec-ch-ua-platform",
+        HeaderValue::from_static("\"Unknown\""),
+    );
+
     let httprequest = DevtoolsHttpRequest {
         url: url,
         method: Method::GET,
@@ -1419,3 +1509,234 @@ fn test_fetch_with_devtools() {
     assert_eq!(devhttprequest, httprequest);
     assert_eq!(devhttpresponse, httpresponse);
 }
+
+fn fetch_with_service_worker(response: Option<CustomResponse>) -> Response {
+    fetch_with_service_worker_mediator(move |mediator| {
+        mediator.response_chan.send(response).unwrap();
+    })
+}
+
+fn fetch_with_service_worker_mediator<F>(respond: F) -> Response
+where
+    F: FnOnce(CustomResponseMediator) + Send + 'static,
+{
+    let handler = move |_: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
+        *response.body_mut() = b"network".to_vec().into();
+    };
+    let (server, url) = make_server(handler);
+
+    let (sw_manager, mediator_port) = ipc::channel::<CustomResponseMediator>().unwrap();
+    let expected_url = url.clone();
+    thread::spawn(move || {
+        let mediator = mediator_port.recv().unwrap();
+        assert_eq!(mediator.load_url, expected_url);
+        assert_eq!(mediator.request.destination, Destination::Document);
+        respond(mediator);
+    });
+
+    let mut context = new_fetch_context(None, None, None);
+    context.sw_manager = Some(sw_manager);
+    let mut request = RequestBuilder::new(url.clone(), Referrer::NoReferrer)
+        .origin(url.origin())
+        .destination(Destination::Document)
+        .build();
+    let fetch_response = fetch_with_context(&mut request, &mut context);
+    let _ = server.close();
+    fetch_response
+}
+
+#[test]
+fn test_fetch_with_service_worker_response() {
+    let mut headers = HeaderMap::new();
+    headers.typed_insert(ContentType::text());
+    let response = CustomResponse::new(
+        ResponseType::Basic,
+        None,
+        headers,
+        (StatusCode::OK, "OK".to_owned()),
+        b"service worker".to_vec(),
+    );
+    let fetch_response = fetch_with_service_worker(Some(response));
+
+    assert!(!fetch_response.is_network_error());
+    assert_eq!(
+        fetch_response.headers.typed_get::<ContentType>(),
+        Some(ContentType::text())
+    );
+    match *fetch_response.body.lock().unwrap() {
+        ResponseBody::Done(ref body) => assert_eq!(&**body, b"service worker"),
+        _ => panic!(),
+    };
+}
+
+#[test]
+fn test_fetch_with_service_worker_network_error() {
+    let response = CustomResponse::network_error(NetworkError::Internal("failed".to_owned()));
+    let fetch_response = fetch_with_service_worker(Some(response));
+    assert!(fetch_response.is_network_error());
+}
+
+#[test]
+fn test_fetch_with_service_worker_falls_back_to_network() {
+    let fetch_response = fetch_with_service_worker(None);
+
+    assert!(!fetch_response.is_network_error());
+    match *fetch_response.body.lock().unwrap() {
+        ResponseBody::Done(ref body) => assert_eq!(&**body, b"network"),
+        _ => panic!(),
+    };
+}
+
+#[test]
+fn test_fetch_with_service_worker_that_never_responds() {
+    // The service worker goes away without answering.
+    let fetch_response = fetch_with_service_worker_mediator(drop);
+
+    assert!(!fetch_response.is_network_error());
+    match *fetch_response.body.lock().unwrap() {
+        ResponseBody::Done(ref body) => assert_eq!(&**body, b"network"),
+        _ => panic!(),
+    };
+}
+
+#[test]
+fn test_fetch_metadata_headers() {
+    let request_headers = Arc::new(Mutex::new(None));
+    let request_headers_clone = request_headers.clone();
+    let handler = move |request: HyperRequest<Body>, _: &mut HyperResponse<Body>| {
+        *request_headers_clone.lock().unwrap() = Some(request.headers().clone());
+    };
+    let (server, url) = make_server(handler);
+
+    let origin = ServoUrl::parse("https://www.example.com").unwrap().origin();
+    let mut request = Request::new(
+        url,
+        Some(Origin::Origin(origin)),
+        Referrer::NoReferrer,
+        None,
+        HttpsState::None,
+    );
+    request.destination = Destination::Script;
+    let fetch_response = fetch(&mut request, None);
+    let _ = server.close();
+    assert!(!fetch_response.is_network_error());
+
+    let headers = request_headers.lock().unwrap().take().unwrap();
+    assert_eq!(headers.get("sec-fetch-dest").unwrap(), "script");
+    assert_eq!(headers.get("sec-fetch-mode").unwrap(), "no-cors");
+    assert_eq!(headers.get("sec-fetch-site").unwrap(), "cross-site");
+    assert!(!headers.contains_key("sec-fetch-user"));
+}
+
+#[test]
+fn test_fetch_metadata_headers_for_user_initiated_navigation() {
+    let request_headers = Arc::new(Mutex::new(None));
+    let request_headers_clone = request_headers.clone();
+    let handler = move |request: HyperRequest<Body>, _: &mut HyperResponse<Body>| {
+        *request_headers_clone.lock().unwrap() = Some(request.headers().clone());
+    };
+    let (server, url) = make_server(handler);
+
+    let mut request = RequestBuilder::new(url.clone(), Referrer::NoReferrer)
+        .destination(Destination::Document)
+        .mode(RequestMode::Navigate)
+        .origin(url.origin())
+        .user_initiated(true)
+        .build();
+    let fetch_response = fetch(&mut request, None);
+    let _ = server.close();
+    assert!(!fetch_response.is_network_error());
+
+    let headers = request_headers.lock().unwrap().take().unwrap();
+    assert_eq!(headers.get("sec-fetch-dest").unwrap(), "document");
+    assert_eq!(headers.get("sec-fetch-mode").unwrap(), "navigate");
+    assert_eq!(headers.get("sec-fetch-site").unwrap(), "none");
+    assert_eq!(headers.get("sec-fetch-user").unwrap(), "?1");
+}
+
+#[test]
+fn test_fetch_with_client_hints_opt_in() {
+    let request_headers = Arc::new(Mutex::new(None));
+    let request_headers_clone = request_headers.clone();
+    let handler = move |request: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
+        *request_headers_clone.lock().unwrap() = Some(request.headers().clone());
+        response.headers_mut().insert(
+            "accept-ch",
+            HeaderValue::from_static("Sec-CH-UA-Model, Sec-CH-UA-WoW64, Unknown-Hint"),
+        );
+    };
+    let (server, url) = make_server(handler);
+
+    let mut context = new_fetch_context(None, None, None);
+    let navigation = RequestBuilder::new(url.clone(), Referrer::NoReferrer)
+        .destination(Destination::Document)
+        .origin(url.origin());
+
+    let fetch_response = fetch_with_context(&mut navigation.clone().build(), &mut context);
+    assert!(!fetch_response.is_network_error());
+    let headers = request_headers.lock().unwrap().take().unwrap();
+    assert_eq!(headers.get("sec-ch-ua-mobile").unwrap(), "?0");
+    assert!(!headers.contains_key("sec-ch-ua-model"));
+
+    let fetch_response = fetch_with_context(&mut navigation.build(), &mut context);
+    let _ = server.close();
+    assert!(!fetch_response.is_network_error());
+    let headers = request_headers.lock().unwrap().take().unwrap();
+    assert_eq!(headers.get("sec-ch-ua-model").unwrap(), "\"\"");
+    assert_eq!(headers.get("sec-ch-ua-wow64").unwrap(), "?0");
+    assert!(!headers.contains_key("unknown-hint"));
+}
+
+#[test]
+fn test_client_hints_do_not_trigger_cors_preflight() {
+    let preflights = Arc::new(AtomicUsize::new(0));
+    let preflights_clone = preflights.clone();
+    let request_headers = Arc::new(Mutex::new(None));
+    let request_headers_clone = request_headers.clone();
+    let handler = move |request: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
+        if request.method() == Method::OPTIONS {
+            preflights_clone.fetch_add(1, Ordering::SeqCst);
+        } else {
+            *request_headers_clone.lock().unwrap() = Some(request.headers().clone());
+        }
+        response
+            .headers_mut()
+            .typed_insert(AccessControlAllowOrigin::ANY);
+    };
+    let (server, url) = make_server(handler);
+
+    let origin = ImmutableOrigin::new_opaque();
+    let mut request = RequestBuilder::new(url.clone(), Referrer::NoReferrer)
+        .origin(origin)
+        .mode(RequestMode::CorsMode)
+        .build();
+    let fetch_response = fetch(&mut request, None);
+    let _ = server.close();
+    assert!(!fetch_response.is_network_error());
+
+    assert_eq!(preflights.load(Ordering::SeqCst), 0);
+    let headers = request_headers.lock().unwrap().take().unwrap();
+    assert!(headers.contains_key("sec-ch-ua"));
+}
+
+#[test]
+fn test_cors_cache_private_network() {
+    let url = ServoUrl::parse("http://127.0.0.1:8000/").unwrap();
+    let origin = ImmutableOrigin::new_opaque();
+    let request = RequestBuilder::new(url.clone(), Referrer::NoReferrer)
+        .origin(origin)
+        .mode(RequestMode::CorsMode)
+        .build();
+
+    let mut cache = CorsCache::new();
+    assert!(!cache.match_private_network(&request));
+    cache.match_private_network_and_update(&request, 60);
+    assert!(cache.match_private_network(&request));
+    assert!(!cache.match_method(&request, Method::PUT));
+
+    let other = RequestBuilder::new(url.join("other").unwrap(), Referrer::NoReferrer)
+        .origin(ImmutableOrigin::new_opaque())
+        .mode(RequestMode::CorsMode)
+        .build();
+    assert!(!cache.match_private_network(&other));
+}
@@ -155,6 +155,14 @@ fn test_check_default_headers_loaded_in_every_request() {
 
     headers.typed_insert::<UserAgent>(crate::DEFAULT_USER_AGENT.parse().unwrap());
 
+    headers.insert("sec-fetch-dest", HeaderValue::from_static("document"));
+    headers.insert("sec-fetch-mode", HeaderValue::from_static("no-cors"));
+    headers.insert("sec-fetch-site", HeaderValue::from_static("same-origin"));
+
+    headers.insert("sec-ch-ua", HeaderValue::from_static("\"Servo\";v=\"1\""));
+    headers.insert("sec-ch-ua-mobile", HeaderValue::from_static("?0"));
+    headers.insert("sec-ch-ua-platform", HeaderValue::from_static("\"Unknown\""));
+
     *expected_headers.lock().unwrap() = Some(headers.clone());
 
     // Testing for method.GET
@@ -294,6 +302,14 @@ fn test_request_and_response_data_with_network_messages() {
 
     headers.typed_insert::<UserAgent>(crate::DEFAULT_USER_AGENT.parse().unwrap());
 
+    headers.insert("sec-fetch-dest", HeaderValue::from_static("document"));
+    headers.insert("sec-fetch-mode", HeaderValue::from_static("no-cors"));
+    headers.insert("sec-fetch-site", HeaderValue::from_static("cross-site"));
+
+    headers.insert("sec-ch-ua", HeaderValue::from_static("\"Servo\";v=\"1\""));
+    headers.insert("sec-ch-ua-mobile", HeaderValue::from_static("?0"));
+    headers.insert("sec-ch-ua-platform", HeaderValue::from_static("\"Unknown\""));
+
     let httprequest = DevtoolsHttpRequest {
         url: url,
         method: Method::GET,
@@ -7,6 +7,7 @@
 #[macro_use]
 extern crate lazy_static;
 
+mod cache_storage;
 mod cookie;
 mod cookie_http_state;
 mod data_loader;
@@ -17,8 +18,10 @@ mod hsts;
 mod http_cache;
 mod http_loader;
 mod mime_classifier;
+mod private_network_access;
 mod resource_thread;
 mod subresource_integrity;
+mod websocket_deflate;
 
 use crossbeam_channel::{unbounded, Sender};
 use devtools_traits::DevtoolsControlMsg;
@@ -109,6 +112,8 @@ fn new_fetch_context(
         timing: ServoArc::new(Mutex::new(ResourceFetchTiming::new(
             ResourceTimingType::Navigation,
         ))),
+        sw_manager: None,
+        client_url: None,
     }
 }
 impl FetchTaskTarget for FetchResponseCollector {
@@ -0,0 +1,78 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use net::private_network_access::{
+    address_space_limit, address_space_of_ip, ip_address_space, IpAddressSpace,
+};
+use net_traits::request::{Referrer, RequestBuilder, RequestMode};
+use servo_config::set_pref;
+use servo_url::ServoUrl;
+
+#[test]
+fn test_ip_address_space() {
+    let space = |host: &str| {
+        let url = ServoUrl::parse(&format!("http://{}/", host)).unwrap();
+        ip_address_space(url.host().unwrap())
+    };
+    assert_eq!(space("localhost"), IpAddressSpace::Local);
+    assert_eq!(space("127.0.0.1"), IpAddressSpace::Local);
+    assert_eq!(space("[::1]"), IpAddressSpace::Local);
+    assert_eq!(space("10.0.0.1"), IpAddressSpace::Private);
+    assert_eq!(space("192.168.1.1"), IpAddressSpace::Private);
+    assert_eq!(space("[fd00::1]"), IpAddressSpace::Private);
+    assert_eq!(space("8.8.8.8"), IpAddressSpace::Public);
+    assert_eq!(space("servo.org"), IpAddressSpace::Public);
+}
+
+#[test]
+fn test_address_space_of_ip() {
+    let space = |ip: &str| address_space_of_ip(ip.parse().unwrap());
+    assert_eq!(space("127.0.0.1"), IpAddressSpace::Local);
+    assert_eq!(space("::1"), IpAddressSpace::Local);
+    assert_eq!(space("::ffff:127.0.0.1"), IpAddressSpace::Local);
+    assert_eq!(space("169.254.1.1"), IpAddressSpace::Private);
+    assert_eq!(space("fe80::1"), IpAddressSpace::Private);
+    assert_eq!(space("::ffff:192.168.1.1"), IpAddressSpace::Private);
+    assert_eq!(space("2001:db8::1"), IpAddressSpace::Public);
+}
+
+#[test]
+fn test_address_space_limit() {
+    set_pref!(network.private_network_access.enabled, true);
+    let request = |origin: &str, mode: RequestMode| {
+        RequestBuilder::new(
+            ServoUrl::parse("http://192.168.1.1/").unwrap(),
+            Referrer::NoReferrer,
+        )
+        .origin(ServoUrl::parse(origin).unwrap().origin())
+        .mode(mode)
+        .build()
+    };
+    assert_eq!(
+        address_space_limit(&request("http://servo.org", RequestMode::NoCors)),
+        Some(IpAddressSpace::Public)
+    );
+    assert_eq!(
+        address_space_limit(&request("https://servo.org", RequestMode::CorsMode)),
+        Some(IpAddressSpace::Public)
+    );
+    assert_eq!(
+        address_space_limit(&request("http://10.0.0.1", RequestMode::CorsMode)),
+        Some(IpAddressSpace::Private)
+    );
+    // No-cors requests of secure contexts cannot be preflighted.
+    assert_eq!(
+        address_space_limit(&request("https://servo.org", RequestMode::NoCors)),
+        None
+    );
+
+    // A preflight allows the request to reach its target address space.
+    let mut preflighted = request("https://servo.org", RequestMode::CorsMode);
+    preflighted.target_address_space = Some(IpAddressSpace::Private);
+    assert_eq!(
+        address_space_limit(&preflighted),
+        Some(IpAddressSpace::Private)
+    );
+    set_pref!(network.private_network_access.enabled, false);
+}
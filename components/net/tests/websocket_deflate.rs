@@ -0,0 +1,202 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress};
+use net::test::{is_message_too_big, parse_extensions_header};
+use net::test::{DeflateDecoder, DeflateEncoder, DeflateParams};
+
+const HANDSHAKE_HEAD: &[u8] = b"HTTP/1.1 101 Switching Protocols\r\n\
+    Upgrade: websocket\r\n\
+    Connection: Upgrade\r\n\
+    Sec-WebSocket-Extensions: permessage-deflate; server_no_context_takeover\r\n\r\n";
+
+fn compress(compressor: &mut Compress, data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + 64);
+    compressor
+        .compress_vec(data, &mut out, FlushCompress::Sync)
+        .unwrap();
+    // Strip the trailing 0x00 0x00 0xff 0xff as required by RFC 7692.
+    assert_eq!(&out[out.len() - 4..], &[0x00, 0x00, 0xff, 0xff]);
+    out.truncate(out.len() - 4);
+    out
+}
+
+fn masked_frame(first_byte: u8, payload: &[u8], key: [u8; 4]) -> Vec<u8> {
+    assert!(payload.len() < 126);
+    let mut frame = vec![first_byte, 0x80 | payload.len() as u8];
+    frame.extend_from_slice(&key);
+    frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ key[i % 4]));
+    frame
+}
+
+fn frame(first_byte: u8, payload: &[u8]) -> Vec<u8> {
+    assert!(payload.len() < 126);
+    let mut frame = vec![first_byte, payload.len() as u8];
+    frame.extend_from_slice(payload);
+    frame
+}
+
+#[test]
+fn test_parse_extensions_header() {
+    assert_eq!(parse_extensions_header(""), Ok(None));
+    assert_eq!(
+        parse_extensions_header("permessage-deflate"),
+        Ok(Some(DeflateParams::default()))
+    );
+    assert_eq!(
+        parse_extensions_header(
+            "permessage-deflate; server_no_context_takeover; server_max_window_bits=10"
+        ),
+        Ok(Some(DeflateParams {
+            server_no_context_takeover: true,
+            ..DeflateParams::default()
+        }))
+    );
+    assert_eq!(
+        parse_extensions_header(
+            "permessage-deflate; client_no_context_takeover; client_max_window_bits=10"
+        ),
+        Ok(Some(DeflateParams {
+            client_no_context_takeover: true,
+            client_max_window_bits: Some(10),
+            ..DeflateParams::default()
+        }))
+    );
+    assert!(parse_extensions_header("x-webkit-deflate-frame").is_err());
+    assert!(parse_extensions_header("permessage-deflate, permessage-deflate").is_err());
+    assert!(parse_extensions_header("permessage-deflate; server_max_window_bits=16").is_err());
+    assert!(parse_extensions_header("permessage-deflate; unknown_param").is_err());
+}
+
+#[test]
+fn test_deflate_decoder_passes_through_without_extension() {
+    let head = b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\r\n";
+    let mut input = head.to_vec();
+    input.extend(frame(0x81, b"hello"));
+
+    let mut decoder = DeflateDecoder::new(None);
+    let mut output = vec![];
+    decoder.decode(&input, &mut output).unwrap();
+    assert_eq!(output, input);
+    assert_eq!(decoder.negotiated(), None);
+}
+
+#[test]
+fn test_deflate_decoder_inflates_messages() {
+    let mut compressor = Compress::new(Compression::default(), false);
+    let compressed = compress(&mut compressor, b"Hello, Servo!");
+
+    let mut decoder = DeflateDecoder::new(None);
+    let mut output = vec![];
+    decoder.decode(HANDSHAKE_HEAD, &mut output).unwrap();
+    assert_eq!(output, HANDSHAKE_HEAD);
+    assert!(decoder.negotiated().unwrap().server_no_context_takeover);
+
+    // Feed the compressed text frame one byte at a time to exercise buffering.
+    output.clear();
+    for byte in frame(0xc1, &compressed) {
+        decoder.decode(&[byte], &mut output).unwrap();
+    }
+    assert_eq!(output, frame(0x81, b"Hello, Servo!"));
+}
+
+#[test]
+fn test_deflate_decoder_handles_fragments_and_control_frames() {
+    let mut compressor = Compress::new(Compression::default(), false);
+    let compressed = compress(&mut compressor, b"fragmented message");
+    let (first, second) = compressed.split_at(compressed.len() / 2);
+
+    let mut input = HANDSHAKE_HEAD.to_vec();
+    // Text frame with RSV1 and without FIN, a ping, then the final continuation.
+    input.extend(frame(0x41, first));
+    input.extend(frame(0x89, b"ping"));
+    input.extend(frame(0x80, second));
+    // An uncompressed binary message.
+    input.extend(frame(0x82, b"raw"));
+
+    let mut decoder = DeflateDecoder::new(None);
+    let mut output = vec![];
+    decoder.decode(&input, &mut output).unwrap();
+
+    let mut expected = HANDSHAKE_HEAD.to_vec();
+    expected.extend(frame(0x89, b"ping"));
+    expected.extend(frame(0x81, b"fragmented message"));
+    expected.extend(frame(0x82, b"raw"));
+    assert_eq!(output, expected);
+}
+
+#[test]
+fn test_deflate_decoder_rejects_unrequested_extension() {
+    let head = b"HTTP/1.1 101 Switching Protocols\r\n\
+        Sec-WebSocket-Extensions: x-webkit-deflate-frame\r\n\r\n";
+    let mut decoder = DeflateDecoder::new(None);
+    assert!(decoder.decode(head, &mut vec![]).is_err());
+}
+
+#[test]
+fn test_deflate_decoder_limits_message_size() {
+    let mut compressor = Compress::new(Compression::default(), false);
+    let compressed = compress(&mut compressor, &[b'a'; 1000]);
+    assert!(compressed.len() < 100);
+
+    let mut input = HANDSHAKE_HEAD.to_vec();
+    input.extend(frame(0xc1, &compressed));
+    let mut decoder = DeflateDecoder::new(Some(100));
+    let error = decoder.decode(&input, &mut vec![]).unwrap_err();
+    assert!(is_message_too_big(&error));
+}
+
+#[test]
+fn test_deflate_encoder_compresses_messages() {
+    let key = [0x12, 0x34, 0x56, 0x78];
+    let mut decoder = DeflateDecoder::new(None);
+    decoder.decode(HANDSHAKE_HEAD, &mut vec![]).unwrap();
+
+    let mut encoder = DeflateEncoder::new();
+    let mut output = vec![];
+    let message = masked_frame(0x81, b"Hello, Servo! Hello, Servo!", key);
+    encoder
+        .encode(decoder.negotiated(), &message, &mut output)
+        .unwrap();
+
+    // A masked text frame with RSV1 set.
+    assert_eq!(output[0], 0xc1);
+    assert_eq!(output[1] & 0x80, 0x80);
+    assert_eq!(&output[2..6], &key);
+    let mut payload: Vec<u8> = output[6..]
+        .iter()
+        .enumerate()
+        .map(|(i, b)| b ^ key[i % 4])
+        .collect();
+    assert_eq!(payload.len(), (output[1] & 0x7f) as usize);
+    payload.extend_from_slice(&[0x00, 0x00, 0xff, 0xff]);
+
+    let mut inflated = Vec::with_capacity(64);
+    Decompress::new(false)
+        .decompress_vec(&payload, &mut inflated, FlushDecompress::Sync)
+        .unwrap();
+    assert_eq!(inflated, b"Hello, Servo! Hello, Servo!");
+
+    // Control frames are left alone.
+    output.clear();
+    let close = masked_frame(0x88, &[0x03, 0xe8], key);
+    encoder
+        .encode(decoder.negotiated(), &close, &mut output)
+        .unwrap();
+    assert_eq!(output, close);
+}
+
+#[test]
+fn test_deflate_encoder_respects_client_max_window_bits() {
+    let params = DeflateParams {
+        client_max_window_bits: Some(10),
+        ..DeflateParams::default()
+    };
+    let message = masked_frame(0x81, b"hello", [1, 2, 3, 4]);
+    let mut output = vec![];
+    DeflateEncoder::new()
+        .encode(Some(params), &message, &mut output)
+        .unwrap();
+    assert_eq!(output, message);
+}
@@ -0,0 +1,655 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Support for the `permessage-deflate` WebSocket extension
+//! (<https://tools.ietf.org/html/rfc7692>).
+//!
+//! tungstenite treats any frame with a reserved bit set as a protocol error,
+//! so compressed messages are inflated before tungstenite ever sees them.
+//! `DeflateStream` wraps the transport, watches the handshake response for
+//! the negotiated extension parameters and rewrites every compressed message
+//! received from the server into a single uncompressed frame. In the other
+//! direction, the unfragmented data frames written by tungstenite are
+//! compressed before they reach the transport.
+
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+use futures03::ready;
+use std::cmp;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio2::io::{AsyncRead, AsyncWrite};
+
+/// The extension offer sent in the `Sec-WebSocket-Extensions` request header.
+pub const PERMESSAGE_DEFLATE_OFFER: &str = "permessage-deflate; client_max_window_bits";
+
+const PERMESSAGE_DEFLATE: &str = "permessage-deflate";
+
+/// The trailer that the sender strips from every compressed message.
+/// <https://tools.ietf.org/html/rfc7692#section-7.2.2>
+const DEFLATE_TRAILER: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+/// The largest LZ77 window, which is the only one the compressor supports.
+const MAX_WINDOW_BITS: u8 = 15;
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+const FIRST_CONTROL_OPCODE: u8 = 0x8;
+
+/// The parameters agreed on by the server in its extension response.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DeflateParams {
+    /// The server resets its compression context after every message.
+    pub server_no_context_takeover: bool,
+    /// We have to reset our compression context after every message.
+    pub client_no_context_takeover: bool,
+    /// We must not use a window larger than this.
+    pub client_max_window_bits: Option<u8>,
+}
+
+impl DeflateParams {
+    /// Whether outgoing messages can be compressed. The compressor always
+    /// uses the largest window, so it can't honour a smaller limit; sending
+    /// uncompressed messages instead is allowed (RFC 7692, section 6).
+    fn can_compress(&self) -> bool {
+        self.client_max_window_bits
+            .map_or(true, |bits| bits == MAX_WINDOW_BITS)
+    }
+}
+
+/// The error returned while inflating a message which exceeds the maximum
+/// message size, for which the connection has to be closed with status 1009.
+/// <https://tools.ietf.org/html/rfc6455#section-7.4.1>
+#[derive(Debug)]
+struct MessageTooBig;
+
+impl fmt::Display for MessageTooBig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Message too big")
+    }
+}
+
+impl Error for MessageTooBig {}
+
+fn message_too_big() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, MessageTooBig)
+}
+
+/// Whether `error` was returned because a message exceeded the maximum
+/// message size.
+pub fn is_message_too_big(error: &io::Error) -> bool {
+    error
+        .get_ref()
+        .map_or(false, |error| error.is::<MessageTooBig>())
+}
+
+fn parse_window_bits(value: Option<&str>) -> Result<u8, String> {
+    let value = value.ok_or_else(|| "Missing window bits value".to_owned())?;
+    match value.trim_matches('"').parse::<u8>() {
+        Ok(bits) if bits >= 8 && bits <= 15 => Ok(bits),
+        _ => Err(format!("Invalid window bits value: {}", value)),
+    }
+}
+
+/// Parse the value of the `Sec-WebSocket-Extensions` response header.
+///
+/// Returns an error if the server accepted an extension we did not offer,
+/// or answered our offer with invalid parameters, in which case the
+/// connection must be failed.
+/// <https://tools.ietf.org/html/rfc6455#section-4.1>
+pub fn parse_extensions_header(value: &str) -> Result<Option<DeflateParams>, String> {
+    let mut negotiated = None;
+    for extension in value.split(',').filter(|e| !e.trim().is_empty()) {
+        let mut parts = extension.split(';').map(str::trim);
+        let name = parts.next().unwrap_or("");
+        if !name.eq_ignore_ascii_case(PERMESSAGE_DEFLATE) {
+            return Err(format!("Server accepted unrequested extension {}", name));
+        }
+        if negotiated.is_some() {
+            return Err("Server accepted permessage-deflate more than once".to_owned());
+        }
+
+        let mut params = DeflateParams::default();
+        let mut seen = vec![];
+        for param in parts {
+            let mut kv = param.splitn(2, '=').map(str::trim);
+            let key = kv.next().unwrap_or("").to_ascii_lowercase();
+            let value = kv.next();
+            if seen.contains(&key) {
+                return Err(format!("Duplicate permessage-deflate parameter {}", key));
+            }
+            match &*key {
+                "server_no_context_takeover" if value.is_none() => {
+                    params.server_no_context_takeover = true;
+                },
+                "client_no_context_takeover" if value.is_none() => {
+                    params.client_no_context_takeover = true;
+                },
+                "client_max_window_bits" => {
+                    params.client_max_window_bits = Some(parse_window_bits(value)?);
+                },
+                // The inflater always uses the largest window, which fits
+                // whatever window the server restricts itself to.
+                "server_max_window_bits" => {
+                    parse_window_bits(value)?;
+                },
+                _ => return Err(format!("Invalid permessage-deflate parameter {}", param)),
+            }
+            seen.push(key);
+        }
+        negotiated = Some(params);
+    }
+    Ok(negotiated)
+}
+
+/// Find the value of the `Sec-WebSocket-Extensions` header(s) in a raw
+/// HTTP response head. Multiple headers are combined as per RFC 7230.
+fn extensions_from_response_head(head: &[u8]) -> Option<String> {
+    let head = String::from_utf8_lossy(head);
+    let values: Vec<&str> = head
+        .split("\r\n")
+        .skip(1)
+        .filter_map(|line| {
+            let mut parts = line.splitn(2, ':');
+            let name = parts.next()?.trim();
+            let value = parts.next()?.trim();
+            if name.eq_ignore_ascii_case("Sec-WebSocket-Extensions") {
+                Some(value)
+            } else {
+                None
+            }
+        })
+        .collect();
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.join(", "))
+    }
+}
+
+fn invalid_data<E: ToString>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error.to_string())
+}
+
+enum DecoderState {
+    /// Waiting for the end of the HTTP handshake response.
+    Handshake,
+    /// The server did not accept the extension; forward everything untouched.
+    PassThrough,
+    /// Inflating compressed messages with the negotiated parameters.
+    Frames(DeflateParams),
+}
+
+/// A compressed message whose fragments are still being received.
+struct CompressedMessage {
+    opcode: u8,
+    /// The fragments received so far, inflated.
+    data: Vec<u8>,
+}
+
+/// The header length and payload length of the first frame in `bytes`, if
+/// its header was received completely.
+fn frame_lengths(bytes: &[u8]) -> Option<(usize, u64)> {
+    if bytes.len() < 2 {
+        return None;
+    }
+    let masked = bytes[1] & 0x80 != 0;
+    let (length_bytes, payload_len) = match bytes[1] & 0x7f {
+        126 => {
+            if bytes.len() < 4 {
+                return None;
+            }
+            (2, u16::from_be_bytes([bytes[2], bytes[3]]) as u64)
+        },
+        127 => {
+            if bytes.len() < 10 {
+                return None;
+            }
+            let mut length = [0; 8];
+            length.copy_from_slice(&bytes[2..10]);
+            (8, u64::from_be_bytes(length))
+        },
+        length => (0, length as u64),
+    };
+    let header_len = 2 + length_bytes + if masked { 4 } else { 0 };
+    Some((header_len, payload_len))
+}
+
+/// Rewrites the byte stream received from a WebSocket server so that every
+/// compressed message is replaced by an equivalent uncompressed frame.
+pub struct DeflateDecoder {
+    state: DecoderState,
+    pending: Vec<u8>,
+    inflater: Decompress,
+    /// The inflater reached the end of the deflate stream in the middle of
+    /// the current message, and has to be reset at the end of it.
+    stream_ended: bool,
+    message: Option<CompressedMessage>,
+    max_message_size: Option<usize>,
+}
+
+impl DeflateDecoder {
+    pub fn new(max_message_size: Option<usize>) -> DeflateDecoder {
+        DeflateDecoder {
+            state: DecoderState::Handshake,
+            pending: vec![],
+            inflater: Decompress::new(false),
+            stream_ended: false,
+            message: None,
+            max_message_size,
+        }
+    }
+
+    /// The parameters negotiated during the handshake, if the server
+    /// accepted the extension.
+    pub fn negotiated(&self) -> Option<DeflateParams> {
+        match self.state {
+            DecoderState::Frames(params) => Some(params),
+            DecoderState::Handshake | DecoderState::PassThrough => None,
+        }
+    }
+
+    /// Process bytes received from the transport, appending the bytes that
+    /// should be handed to tungstenite to `output`.
+    pub fn decode(&mut self, input: &[u8], output: &mut Vec<u8>) -> io::Result<()> {
+        if let DecoderState::PassThrough = self.state {
+            output.extend_from_slice(input);
+            return Ok(());
+        }
+        self.pending.extend_from_slice(input);
+
+        if let DecoderState::Handshake = self.state {
+            let end = match self.pending.windows(4).position(|w| w == b"\r\n\r\n") {
+                Some(position) => position + 4,
+                None => return Ok(()),
+            };
+            let head: Vec<u8> = self.pending.drain(..end).collect();
+            let switching_protocols = head.starts_with(b"HTTP/1.1 101");
+            let params = match extensions_from_response_head(&head) {
+                Some(ref value) if switching_protocols => {
+                    parse_extensions_header(value).map_err(invalid_data)?
+                },
+                _ => None,
+            };
+            output.extend_from_slice(&head);
+            match params {
+                Some(params) => self.state = DecoderState::Frames(params),
+                None => {
+                    self.state = DecoderState::PassThrough;
+                    output.extend(self.pending.drain(..));
+                    return Ok(());
+                },
+            }
+        }
+
+        while let Some(frame_len) = self.next_frame_length()? {
+            let frame: Vec<u8> = self.pending.drain(..frame_len).collect();
+            self.process_frame(frame, output)?;
+        }
+        Ok(())
+    }
+
+    /// The length of the first frame in `pending`, if it has been
+    /// received completely. Frames which can't fit in a message are
+    /// rejected before they get buffered.
+    fn next_frame_length(&self) -> io::Result<Option<usize>> {
+        let (header_len, payload_len) = match frame_lengths(&self.pending) {
+            Some(lengths) => lengths,
+            None => return Ok(None),
+        };
+        if let Some(max) = self.max_message_size {
+            if payload_len > max as u64 {
+                return Err(message_too_big());
+            }
+        }
+        let frame_len = header_len as u64 + payload_len;
+        if (self.pending.len() as u64) < frame_len {
+            return Ok(None);
+        }
+        Ok(Some(frame_len as usize))
+    }
+
+    fn process_frame(&mut self, frame: Vec<u8>, output: &mut Vec<u8>) -> io::Result<()> {
+        let fin = frame[0] & 0x80 != 0;
+        let rsv1 = frame[0] & 0x40 != 0;
+        let opcode = frame[0] & 0x0f;
+
+        // Control frames are never compressed and may be interleaved
+        // with the fragments of a data message.
+        if opcode >= FIRST_CONTROL_OPCODE {
+            output.extend_from_slice(&frame);
+            return Ok(());
+        }
+
+        let mut message = if opcode == OPCODE_CONTINUATION {
+            if rsv1 {
+                return Err(invalid_data("RSV1 set on a continuation frame"));
+            }
+            match self.message.take() {
+                Some(message) => message,
+                None => {
+                    // Part of an uncompressed message.
+                    output.extend_from_slice(&frame);
+                    return Ok(());
+                },
+            }
+        } else if rsv1 {
+            CompressedMessage {
+                opcode,
+                data: vec![],
+            }
+        } else {
+            self.message = None;
+            output.extend_from_slice(&frame);
+            return Ok(());
+        };
+
+        if frame[1] & 0x80 != 0 {
+            return Err(invalid_data("Server sent a masked frame"));
+        }
+        let (header_len, _) = frame_lengths(&frame).expect("a complete frame");
+        self.inflate(&frame[header_len..], &mut message.data)?;
+
+        if !fin {
+            self.message = Some(message);
+            return Ok(());
+        }
+
+        // <https://tools.ietf.org/html/rfc7692#section-7.2.2>
+        self.inflate(&DEFLATE_TRAILER, &mut message.data)?;
+        let reset = match self.state {
+            DecoderState::Frames(params) => params.server_no_context_takeover,
+            _ => false,
+        };
+        if reset || self.stream_ended {
+            self.inflater.reset(false);
+            self.stream_ended = false;
+        }
+        write_frame_header(output, 0x80 | message.opcode, message.data.len(), None);
+        output.extend_from_slice(&message.data);
+        Ok(())
+    }
+
+    /// Inflate the `input` part of a message, appending it to the part of
+    /// the message inflated so far.
+    fn inflate(&mut self, input: &[u8], out: &mut Vec<u8>) -> io::Result<()> {
+        let max = self.max_message_size.unwrap_or(usize::max_value());
+        let mut consumed = 0;
+        while !self.stream_ended {
+            if out.len() == out.capacity() {
+                // Never allocate more than one byte past the limit, which
+                // is enough to tell that it was exceeded.
+                let room = max.saturating_sub(out.len()).saturating_add(1);
+                out.reserve(cmp::min(cmp::max(out.capacity(), 1024), room));
+            }
+            let (total_in, total_out) = (self.inflater.total_in(), self.inflater.total_out());
+            let status = self
+                .inflater
+                .decompress_vec(&input[consumed..], out, FlushDecompress::Sync)
+                .map_err(invalid_data)?;
+            consumed += (self.inflater.total_in() - total_in) as usize;
+
+            if out.len() > max {
+                return Err(message_too_big());
+            }
+            if status == Status::StreamEnd {
+                // The server ended the deflate stream with a final block,
+                // so the next message starts a fresh one.
+                self.stream_ended = true;
+                break;
+            }
+            if consumed == input.len() && out.len() < out.capacity() {
+                break;
+            }
+            if self.inflater.total_in() == total_in && self.inflater.total_out() == total_out {
+                return Err(invalid_data("Compressed message could not be inflated"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Rewrites the byte stream written by tungstenite so that every
+/// unfragmented data message is compressed.
+pub struct DeflateEncoder {
+    pending: Vec<u8>,
+    compressor: Compress,
+}
+
+impl DeflateEncoder {
+    pub fn new() -> DeflateEncoder {
+        DeflateEncoder {
+            pending: vec![],
+            compressor: Compress::new(Compression::default(), false),
+        }
+    }
+
+    /// Process bytes written by tungstenite, appending the bytes that should
+    /// be sent over the transport to `output`. `params` are the parameters
+    /// negotiated so far, `None` until the handshake succeeded with the
+    /// extension, during which the bytes are forwarded untouched.
+    pub fn encode(
+        &mut self,
+        params: Option<DeflateParams>,
+        input: &[u8],
+        output: &mut Vec<u8>,
+    ) -> io::Result<()> {
+        let params = match params {
+            Some(params) if params.can_compress() => params,
+            _ => {
+                output.extend(self.pending.drain(..));
+                output.extend_from_slice(input);
+                return Ok(());
+            },
+        };
+        self.pending.extend_from_slice(input);
+        while let Some((header_len, payload_len)) = frame_lengths(&self.pending) {
+            let frame_len = header_len + payload_len as usize;
+            if self.pending.len() < frame_len {
+                break;
+            }
+            let frame: Vec<u8> = self.pending.drain(..frame_len).collect();
+            self.process_frame(params, frame, header_len, output)?;
+        }
+        Ok(())
+    }
+
+    fn process_frame(
+        &mut self,
+        params: DeflateParams,
+        mut frame: Vec<u8>,
+        header_len: usize,
+        output: &mut Vec<u8>,
+    ) -> io::Result<()> {
+        let fin = frame[0] & 0x80 != 0;
+        let reserved = frame[0] & 0x70;
+        let opcode = frame[0] & 0x0f;
+        let masked = frame[1] & 0x80 != 0;
+
+        // Fragmented messages are left uncompressed, which saves tracking
+        // them across frames.
+        if !fin || reserved != 0 || (opcode != OPCODE_TEXT && opcode != OPCODE_BINARY) || !masked {
+            output.extend(frame);
+            return Ok(());
+        }
+
+        let mut key = [0; 4];
+        key.copy_from_slice(&frame[header_len - 4..header_len]);
+        let payload = &mut frame[header_len..];
+        apply_mask(payload, key);
+        let mut compressed = self.deflate(payload)?;
+        if params.client_no_context_takeover {
+            self.compressor.reset();
+        }
+
+        apply_mask(&mut compressed, key);
+        write_frame_header(output, 0xc0 | opcode, compressed.len(), Some(key));
+        output.extend(compressed);
+        Ok(())
+    }
+
+    /// <https://tools.ietf.org/html/rfc7692#section-7.2.1>
+    fn deflate(&mut self, data: &[u8]) -> io::Result<Vec<u8>> {
+        let mut out = Vec::with_capacity(data.len() / 2 + 64);
+        let mut consumed = 0;
+        loop {
+            if out.capacity() - out.len() < 64 {
+                out.reserve(cmp::max(out.capacity(), 1024));
+            }
+            let total_in = self.compressor.total_in();
+            self.compressor
+                .compress_vec(&data[consumed..], &mut out, FlushCompress::Sync)
+                .map_err(invalid_data)?;
+            consumed += (self.compressor.total_in() - total_in) as usize;
+            // The flush is complete once all the input was taken and the
+            // compressor did not run out of room.
+            if consumed == data.len() && out.capacity() - out.len() >= 64 {
+                break;
+            }
+        }
+        if out.ends_with(&DEFLATE_TRAILER) {
+            out.truncate(out.len() - DEFLATE_TRAILER.len());
+        }
+        Ok(out)
+    }
+}
+
+fn apply_mask(data: &mut [u8], key: [u8; 4]) {
+    for (i, byte) in data.iter_mut().enumerate() {
+        *byte ^= key[i % 4];
+    }
+}
+
+/// Write the header of an unfragmented frame.
+fn write_frame_header(
+    output: &mut Vec<u8>,
+    first_byte: u8,
+    payload_len: usize,
+    mask: Option<[u8; 4]>,
+) {
+    let mask_bit = if mask.is_some() { 0x80 } else { 0 };
+    output.push(first_byte);
+    if payload_len < 126 {
+        output.push(mask_bit | payload_len as u8);
+    } else if payload_len <= u16::max_value() as usize {
+        output.push(mask_bit | 126);
+        output.extend_from_slice(&(payload_len as u16).to_be_bytes());
+    } else {
+        output.push(mask_bit | 127);
+        output.extend_from_slice(&(payload_len as u64).to_be_bytes());
+    }
+    if let Some(key) = mask {
+        output.extend_from_slice(&key);
+    }
+}
+
+/// A transport wrapper that inflates compressed messages on the read side
+/// and compresses messages on the write side.
+pub struct DeflateStream<S> {
+    inner: S,
+    decoder: DeflateDecoder,
+    decoded: Vec<u8>,
+    read_offset: usize,
+    read_buf: Box<[u8]>,
+    encoder: DeflateEncoder,
+    encoded: Vec<u8>,
+    write_offset: usize,
+}
+
+impl<S> DeflateStream<S> {
+    pub fn new(inner: S, max_message_size: Option<usize>) -> DeflateStream<S> {
+        DeflateStream {
+            inner,
+            decoder: DeflateDecoder::new(max_message_size),
+            decoded: vec![],
+            read_offset: 0,
+            read_buf: vec![0; 16 * 1024].into_boxed_slice(),
+            encoder: DeflateEncoder::new(),
+            encoded: vec![],
+            write_offset: 0,
+        }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for DeflateStream<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        loop {
+            if this.read_offset < this.decoded.len() {
+                let available = &this.decoded[this.read_offset..];
+                let len = cmp::min(buf.len(), available.len());
+                buf[..len].copy_from_slice(&available[..len]);
+                this.read_offset += len;
+                if this.read_offset == this.decoded.len() {
+                    this.decoded.clear();
+                    this.read_offset = 0;
+                }
+                return Poll::Ready(Ok(len));
+            }
+
+            let read = ready!(Pin::new(&mut this.inner).poll_read(cx, &mut this.read_buf[..]))?;
+            if read == 0 {
+                return Poll::Ready(Ok(0));
+            }
+            this.decoder
+                .decode(&this.read_buf[..read], &mut this.decoded)?;
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> DeflateStream<S> {
+    /// Write out the encoded bytes which the transport did not take yet.
+    fn poll_write_encoded(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.write_offset < self.encoded.len() {
+            let written = ready!(
+                Pin::new(&mut self.inner).poll_write(cx, &self.encoded[self.write_offset..])
+            )?;
+            if written == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.write_offset += written;
+        }
+        self.encoded.clear();
+        self.write_offset = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for DeflateStream<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        // Only take more bytes once the previous ones went out, so that
+        // the transport's backpressure still applies.
+        ready!(this.poll_write_encoded(cx))?;
+        let params = this.decoder.negotiated();
+        this.encoder.encode(params, buf, &mut this.encoded)?;
+        // The bytes are taken either way, whatever is left gets written on
+        // the next write or flush.
+        if let Poll::Ready(Err(e)) = this.poll_write_encoded(cx) {
+            return Poll::Ready(Err(e));
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_write_encoded(cx))?;
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_write_encoded(cx))?;
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
@@ -16,23 +16,30 @@ use crate::cookie::Cookie;
 use crate::fetch::methods::should_be_blocked_due_to_bad_port;
 use crate::hosts::replace_host;
 use crate::http_loader::HttpState;
-use async_tungstenite::tokio::{client_async_tls_with_connector_and_config, ConnectStream};
-use async_tungstenite::WebSocketStream;
+use crate::websocket_deflate::{self, DeflateStream, PERMESSAGE_DEFLATE_OFFER};
+use async_tungstenite::tokio::client_async_with_config;
+use crossbeam_channel::Sender;
+use devtools_traits::{ChromeToDevtoolsControlMsg, DevtoolsControlMsg, NetworkEvent};
+use devtools_traits::{WebSocketEvent, WebSocketEventType, WebSocketFrame};
 use embedder_traits::resources::{self, Resource};
 use futures03::future::TryFutureExt;
-use futures03::sink::SinkExt;
-use futures03::stream::StreamExt;
+use futures03::sink::{Sink, SinkExt};
+use futures03::stream::{Stream, StreamExt};
 use http::header::{HeaderMap, HeaderName, HeaderValue};
 use ipc_channel::ipc::{IpcReceiver, IpcSender};
 use ipc_channel::router::ROUTER;
+use msg::constellation_msg::PipelineId;
 use net_traits::request::{RequestBuilder, RequestMode};
 use net_traits::{CookieSource, MessageData};
 use net_traits::{WebSocketDomAction, WebSocketNetworkEvent};
 use openssl::ssl::ConnectConfiguration;
+use servo_config::pref;
 use servo_url::ServoUrl;
 use std::fs;
+use std::io;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
+use tokio2::io::{AsyncRead, AsyncWrite};
 use tokio2::net::TcpStream;
 use tokio2::runtime::Runtime;
 use tokio2::select;
@@ -41,10 +48,15 @@ use tungstenite::error::Error;
 use tungstenite::error::Result as WebSocketResult;
 use tungstenite::handshake::client::{Request, Response};
 use tungstenite::http::header::{self as WSHeader, HeaderValue as WSHeaderValue};
-use tungstenite::protocol::CloseFrame;
+use tungstenite::protocol::frame::coding::CloseCode;
+use tungstenite::protocol::{CloseFrame, WebSocketConfig};
 use tungstenite::Message;
 use url::Url;
 
+/// The close code reported when the connection was closed without a close frame.
+/// <https://tools.ietf.org/html/rfc6455#section-7.1.5>
+const ABNORMAL_CLOSURE: u16 = 1006;
+
 // Websockets get their own tokio runtime that's independent of the one used for
 // HTTP connections, otherwise a large number of websockets could occupy all workers
 // and starve other network traffic.
@@ -53,8 +65,8 @@ lazy_static! {
 }
 
 /// Create a tungstenite Request object for the initial HTTP request.
-/// This request contains `Origin`, `Sec-WebSocket-Protocol`, `Authorization`,
-/// and `Cookie` headers as appropriate.
+/// This request contains `Origin`, `Sec-WebSocket-Protocol`,
+/// `Sec-WebSocket-Extensions`, `Authorization`, and `Cookie` headers as appropriate.
 /// Returns an error if any header values are invalid or tungstenite cannot create
 /// the desired request.
 fn create_request(
@@ -75,6 +87,13 @@ fn create_request(
         );
     }
 
+    if pref!(network.websocket.permessage_deflate.enabled) {
+        headers.insert(
+            "Sec-WebSocket-Extensions",
+            WSHeaderValue::from_static(PERMESSAGE_DEFLATE_OFFER),
+        );
+    }
+
     let mut cookie_jar = http_state.cookie_jar.write().unwrap();
     cookie_jar.remove_expired_cookies_for_url(resource_url);
     if let Some(cookie_list) = cookie_jar.cookies_for_url(resource_url, CookieSource::HTTP) {
@@ -101,12 +120,13 @@ fn create_request(
 /// This ensures that any `Cookie` or HSTS headers are recognized.
 /// Returns an error if the protocol selected by the handshake doesn't
 /// match the list of provided protocols in the original request.
+/// Otherwise returns the protocol and extensions in use, if any.
 fn process_ws_response(
     http_state: &HttpState,
     response: &Response,
     resource_url: &ServoUrl,
     protocols: &[String],
-) -> Result<Option<String>, Error> {
+) -> Result<(Option<String>, Option<String>), Error> {
     trace!("processing websocket http response for {}", resource_url);
     let mut protocol_in_use = None;
     if let Some(protocol_name) = response.headers().get("Sec-WebSocket-Protocol") {
@@ -119,6 +139,14 @@ fn process_ws_response(
         protocol_in_use = Some(protocol_name.to_string());
     }
 
+    // The extension parameters were already validated by the `DeflateStream`
+    // that the handshake response was read through.
+    let extensions_in_use = response
+        .headers()
+        .get("Sec-WebSocket-Extensions")
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_owned());
+
     let mut jar = http_state.cookie_jar.write().unwrap();
     // TODO(eijebong): Replace thise once typed headers settled on a cookie impl
     for cookie in response.headers().get_all(WSHeader::SET_COOKIE) {
@@ -149,7 +177,49 @@ fn process_ws_response(
         .unwrap()
         .update_hsts_list_from_response(resource_url, &headers);
 
-    Ok(protocol_in_use)
+    Ok((protocol_in_use, extensions_in_use))
+}
+
+/// Forwards WebSocket activity to the devtools server, so that it can be
+/// displayed alongside the HTTP requests made by the same pipeline.
+#[derive(Clone)]
+struct DevtoolsReporter {
+    devtools_chan: Sender<DevtoolsControlMsg>,
+    pipeline_id: PipelineId,
+    request_id: String,
+}
+
+impl DevtoolsReporter {
+    fn report(&self, event: WebSocketEventType) {
+        let event = WebSocketEvent {
+            pipeline_id: self.pipeline_id,
+            event,
+        };
+        let msg = ChromeToDevtoolsControlMsg::NetworkEvent(
+            self.request_id.clone(),
+            NetworkEvent::WebSocket(event),
+        );
+        let _ = self.devtools_chan.send(DevtoolsControlMsg::FromChrome(msg));
+    }
+
+    fn report_message(&self, message: &Message, sent: bool) {
+        let frame = match *message {
+            Message::Text(ref text) => WebSocketFrame {
+                opcode: 0x1,
+                payload: text.as_bytes().to_vec(),
+            },
+            Message::Binary(ref data) => WebSocketFrame {
+                opcode: 0x2,
+                payload: data.clone(),
+            },
+            Message::Ping(_) | Message::Pong(_) | Message::Close(_) => return,
+        };
+        if sent {
+            self.report(WebSocketEventType::FrameSent(frame));
+        } else {
+            self.report(WebSocketEventType::FrameReceived(frame));
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -202,11 +272,24 @@ fn setup_dom_listener(
 /// closes the connection or an error occurs. Since this is an async
 /// function that uses the select operation, it will run as a task
 /// on the WS tokio runtime.
-async fn run_ws_loop(
+async fn run_ws_loop<S>(
     mut dom_receiver: UnboundedReceiver<DomMsg>,
     resource_event_sender: IpcSender<WebSocketNetworkEvent>,
-    mut stream: WebSocketStream<ConnectStream>,
-) {
+    mut stream: S,
+    devtools: Option<DevtoolsReporter>,
+) where
+    S: Stream<Item = WebSocketResult<Message>> + Sink<Message, Error = Error> + Unpin,
+{
+    let report_close = |code: Option<u16>, reason: &str, was_clean: bool| {
+        if let Some(ref devtools) = devtools {
+            devtools.report(WebSocketEventType::Closed {
+                code,
+                reason: reason.to_owned(),
+                was_clean,
+            });
+        }
+    };
+
     loop {
         select! {
             dom_msg = dom_receiver.recv() => {
@@ -217,17 +300,21 @@ async fn run_ws_loop(
                 };
                 match dom_msg {
                     DomMsg::Send(m) => {
+                        if let Some(ref devtools) = devtools {
+                            devtools.report_message(&m, true);
+                        }
                         if let Err(e) = stream.send(m).await {
                             warn!("error sending websocket message: {:?}", e);
                         }
                     },
                     DomMsg::Close(frame) => {
-                        if let Err(e) = stream.close(frame.map(|(code, reason)| {
+                        let frame = frame.map(|(code, reason)| {
                             CloseFrame {
                                 code: code.into(),
                                 reason: reason.into(),
                             }
-                        })).await {
+                        });
+                        if let Err(e) = stream.send(Message::Close(frame)).await {
                             warn!("error closing websocket: {:?}", e);
                         }
                     },
@@ -237,17 +324,41 @@ async fn run_ws_loop(
                 trace!("processing WS stream: {:?}", ws_msg);
                 let msg = match ws_msg {
                     Some(Ok(msg)) => msg,
+                    None => {
+                        // The transport went away without a closing handshake.
+                        // https://tools.ietf.org/html/rfc6455#section-7.1.5
+                        debug!("Websocket connection closed without a close frame");
+                        report_close(Some(ABNORMAL_CLOSURE), "", false);
+                        let _ = resource_event_sender.send(WebSocketNetworkEvent::Close {
+                            code: Some(ABNORMAL_CLOSURE),
+                            reason: String::new(),
+                            was_clean: false,
+                        });
+                        break;
+                    },
                     Some(Err(e)) => {
                         warn!("Error in WebSocket communication: {:?}", e);
+                        let too_big = match e {
+                            Error::Capacity(_) => true,
+                            Error::Io(ref e) => websocket_deflate::is_message_too_big(e),
+                            _ => false,
+                        };
+                        if too_big {
+                            // https://tools.ietf.org/html/rfc6455#section-7.4.1
+                            let frame = CloseFrame {
+                                code: CloseCode::Size,
+                                reason: "".into(),
+                            };
+                            let _ = stream.send(Message::Close(Some(frame))).await;
+                        }
+                        report_close(Some(ABNORMAL_CLOSURE), "", false);
                         let _ = resource_event_sender.send(WebSocketNetworkEvent::Fail);
                         break;
                     },
-                    None => {
-                        warn!("Error in WebSocket communication");
-                        let _ = resource_event_sender.send(WebSocketNetworkEvent::Fail);
-                        break;
-                    }
                 };
+                if let Some(ref devtools) = devtools {
+                    devtools.report_message(&msg, false);
+                }
                 match msg {
                     Message::Text(s) => {
                         let message = MessageData::Text(s);
@@ -277,10 +388,12 @@ async fn run_ws_loop(
                             None => ("".into(), None),
                         };
                         debug!("Websocket connection closing due to ({:?}) {}", code, reason);
-                        let _ = resource_event_sender.send(WebSocketNetworkEvent::Close(
+                        report_close(code, &reason, true);
+                        let _ = resource_event_sender.send(WebSocketNetworkEvent::Close {
                             code,
-                            reason.to_string(),
-                        ));
+                            reason: reason.to_string(),
+                            was_clean: true,
+                        });
                         break;
                     }
                 }
@@ -300,6 +413,7 @@ async fn start_websocket(
     client: Request,
     tls_config: ConnectConfiguration,
     dom_action_receiver: IpcReceiver<WebSocketDomAction>,
+    devtools: Option<DevtoolsReporter>,
 ) -> Result<(), Error> {
     trace!("starting WS connection to {}", url);
 
@@ -311,6 +425,7 @@ async fn start_websocket(
         .host()
         .ok_or_else(|| Error::Url("No host string".into()))?;
     let host = replace_host(host_str);
+    let tls_domain = host_str.to_owned();
     let mut net_url =
         Url::parse(&client.uri().to_string()).map_err(|e| Error::Url(e.to_string().into()))?;
     net_url
@@ -326,25 +441,95 @@ async fn start_websocket(
 
     let try_socket = TcpStream::connect((&*domain.to_string(), port)).await;
     let socket = try_socket.map_err(Error::Io)?;
-    let (stream, response) =
-        client_async_tls_with_connector_and_config(client, socket, Some(tls_config), None).await?;
 
-    let protocol_in_use = process_ws_response(&http_state, &response, &url, &protocols)?;
+    let connection = WebSocketConnection {
+        http_state,
+        url,
+        resource_event_sender,
+        protocols,
+        client,
+        dom_receiver,
+        initiated_close,
+        devtools,
+    };
+
+    // Messages are compressed and inflated below tungstenite, which means
+    // that TLS has to be established here rather than by tungstenite itself.
+    if connection.url.scheme() == "wss" {
+        let stream = tokio_openssl2::connect(tls_config, &tls_domain, socket)
+            .await
+            .map_err(|e| Error::Io(io::Error::new(io::ErrorKind::Other, e.to_string())))?;
+        connection.run(stream).await
+    } else {
+        connection.run(socket).await
+    }
+}
 
-    if !initiated_close.load(Ordering::SeqCst) {
-        if resource_event_sender
-            .send(WebSocketNetworkEvent::ConnectionEstablished { protocol_in_use })
+/// The state needed to perform the opening handshake over an established
+/// transport and then run the WS loop.
+struct WebSocketConnection {
+    http_state: Arc<HttpState>,
+    url: ServoUrl,
+    resource_event_sender: IpcSender<WebSocketNetworkEvent>,
+    protocols: Vec<String>,
+    client: Request,
+    dom_receiver: UnboundedReceiver<DomMsg>,
+    initiated_close: Arc<AtomicBool>,
+    devtools: Option<DevtoolsReporter>,
+}
+
+impl WebSocketConnection {
+    async fn run<S>(self, transport: S) -> Result<(), Error>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let config = WebSocketConfig::default();
+        // tungstenite only sees inflated messages, so the size limit has to
+        // be enforced while inflating too.
+        let transport = DeflateStream::new(transport, config.max_message_size);
+        let (stream, response) =
+            client_async_with_config(self.client, transport, Some(config)).await?;
+
+        let (protocol_in_use, extensions_in_use) =
+            process_ws_response(&self.http_state, &response, &self.url, &self.protocols)?;
+
+        if self.initiated_close.load(Ordering::SeqCst) {
+            trace!(
+                "client closed connection for {}, not running loop",
+                self.url
+            );
+            return Ok(());
+        }
+
+        if let Some(ref devtools) = self.devtools {
+            devtools.report(WebSocketEventType::Opened {
+                url: self.url.clone(),
+                protocol: protocol_in_use.clone(),
+                extensions: extensions_in_use.clone(),
+            });
+        }
+
+        if self
+            .resource_event_sender
+            .send(WebSocketNetworkEvent::ConnectionEstablished {
+                protocol_in_use,
+                extensions_in_use,
+            })
             .is_err()
         {
             return Ok(());
         }
 
-        trace!("about to start ws loop for {}", url);
-        run_ws_loop(dom_receiver, resource_event_sender, stream).await;
-    } else {
-        trace!("client closed connection for {}, not running loop", url);
+        trace!("about to start ws loop for {}", self.url);
+        run_ws_loop(
+            self.dom_receiver,
+            self.resource_event_sender,
+            stream,
+            self.devtools,
+        )
+        .await;
+        Ok(())
     }
-    Ok(())
 }
 
 /// Create a new websocket connection for the given request.
@@ -354,6 +539,7 @@ fn connect(
     dom_action_receiver: IpcReceiver<WebSocketDomAction>,
     http_state: Arc<HttpState>,
     certificate_path: Option<String>,
+    devtools_chan: Option<Sender<DevtoolsControlMsg>>,
 ) -> Result<(), String> {
     let protocols = match req_builder.mode {
         RequestMode::WebSocket { protocols } => protocols,
@@ -423,6 +609,15 @@ fn connect(
         Err(e) => return Err(e.to_string()),
     };
 
+    let devtools = match (devtools_chan, req_builder.pipeline_id) {
+        (Some(devtools_chan), Some(pipeline_id)) => Some(DevtoolsReporter {
+            devtools_chan,
+            pipeline_id,
+            request_id: uuid::Uuid::new_v4().to_simple().to_string(),
+        }),
+        _ => None,
+    };
+
     let resource_event_sender2 = resource_event_sender.clone();
     match HANDLE.lock().unwrap().as_mut() {
         Some(handle) => handle.spawn(
@@ -434,6 +629,7 @@ fn connect(
                 client,
                 tls_config,
                 dom_action_receiver,
+                devtools,
             )
             .map_err(move |e| {
                 warn!("Failed to establish a WebSocket connection: {:?}", e);
@@ -452,6 +648,7 @@ pub fn init(
     dom_action_receiver: IpcReceiver<WebSocketDomAction>,
     http_state: Arc<HttpState>,
     certificate_path: Option<String>,
+    devtools_chan: Option<Sender<DevtoolsControlMsg>>,
 ) {
     let resource_event_sender2 = resource_event_sender.clone();
     if let Err(e) = connect(
@@ -460,6 +657,7 @@ pub fn init(
         dom_action_receiver,
         http_state,
         certificate_path,
+        devtools_chan,
     ) {
         warn!("Error starting websocket: {}", e);
         let _ = resource_event_sender2.send(WebSocketNetworkEvent::Fail);
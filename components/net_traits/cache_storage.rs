@@ -0,0 +1,131 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Messages and data types for the [Cache API](https://w3c.github.io/ServiceWorker/#cache-objects)
+//! request/response store that lives in the resource thread.
+
+use http::{HeaderMap, Method};
+use ipc_channel::ipc::IpcSender;
+use servo_url::{ImmutableOrigin, ServoUrl};
+
+/// <https://w3c.github.io/ServiceWorker/#dictdef-cachequeryoptions>
+#[derive(Clone, Copy, Debug, Default, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub struct CacheQueryOptions {
+    pub ignore_search: bool,
+    pub ignore_method: bool,
+    pub ignore_vary: bool,
+}
+
+/// The parts of a request that are kept in a request response list.
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, Serialize)]
+pub struct CachedRequest {
+    pub url: ServoUrl,
+    #[serde(
+        deserialize_with = "::hyper_serde::deserialize",
+        serialize_with = "::hyper_serde::serialize"
+    )]
+    #[ignore_malloc_size_of = "Defined in hyper"]
+    pub method: Method,
+    #[serde(
+        deserialize_with = "::hyper_serde::deserialize",
+        serialize_with = "::hyper_serde::serialize"
+    )]
+    #[ignore_malloc_size_of = "Defined in hyper"]
+    pub headers: HeaderMap,
+}
+
+/// The type of a cached response, mirroring the response types a Response object can expose.
+#[derive(Clone, Copy, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub enum CachedResponseType {
+    Basic,
+    Cors,
+    Default,
+    Error,
+    Opaque,
+    OpaqueRedirect,
+}
+
+/// A response stored in a request response list, with its body fully read.
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, Serialize)]
+pub struct CachedResponse {
+    pub response_type: CachedResponseType,
+    pub url: Option<ServoUrl>,
+    pub redirected: bool,
+    pub status: (u16, Vec<u8>),
+    #[serde(
+        deserialize_with = "::hyper_serde::deserialize",
+        serialize_with = "::hyper_serde::serialize"
+    )]
+    #[ignore_malloc_size_of = "Defined in hyper"]
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+/// An entry of a [request response list](https://w3c.github.io/ServiceWorker/#request-response-list).
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, Serialize)]
+pub struct CacheEntry {
+    pub request: CachedRequest,
+    pub response: CachedResponse,
+}
+
+/// Identifies a single named cache of a cache storage, for as long as it is not deleted.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, MallocSizeOf, PartialEq, Serialize)]
+pub struct CacheId(pub u64);
+
+/// Which caches of an origin a match operation should look into.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum CacheSelector {
+    /// A single cache, as used by `Cache.match()` and `Cache.matchAll()`.
+    Id(CacheId),
+    /// The cache with this name, as used by `CacheStorage.match()` with a `cacheName`.
+    Name(String),
+    /// Every cache, in creation order, as used by `CacheStorage.match()`.
+    All,
+}
+
+/// <https://w3c.github.io/ServiceWorker/#dfn-cache-batch-operation>
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum CacheBatchOperation {
+    Put(CacheEntry),
+    Delete(CachedRequest, CacheQueryOptions),
+}
+
+/// Why the operations of a batch were rolled back.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum CacheBatchError {
+    /// An operation would have overwritten an entry added by the same batch.
+    InvalidState,
+    /// The entries of the origin would have exceeded its quota.
+    QuotaExceeded,
+}
+
+/// Request operations on the cache storage of a particular origin.
+#[derive(Debug, Deserialize, Serialize)]
+pub enum CacheStorageMsg {
+    /// Whether a cache with the given name exists.
+    Has(IpcSender<bool>, ImmutableOrigin, String),
+    /// Returns the cache with the given name, creating it if needed.
+    Open(IpcSender<CacheId>, ImmutableOrigin, String),
+    /// Deletes the cache with the given name, replying whether it existed.
+    Delete(IpcSender<bool>, ImmutableOrigin, String),
+    /// Gets the names of the caches, in creation order.
+    Keys(IpcSender<Vec<String>>, ImmutableOrigin),
+    /// Gets the entries of the selected caches matching a request, or every entry
+    /// of the selected caches if no request is given.
+    MatchAll(
+        IpcSender<Vec<CacheEntry>>,
+        ImmutableOrigin,
+        CacheSelector,
+        Option<CachedRequest>,
+        CacheQueryOptions,
+    ),
+    /// Atomically applies the operations to a cache. Replies with whether a delete
+    /// operation removed an entry, or why the operations were rolled back.
+    BatchOperations(
+        IpcSender<Result<bool, CacheBatchError>>,
+        ImmutableOrigin,
+        CacheId,
+        Vec<CacheBatchOperation>,
+    ),
+}
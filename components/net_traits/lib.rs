@@ -15,9 +15,10 @@ extern crate malloc_size_of_derive;
 #[macro_use]
 extern crate serde;
 
+use crate::cache_storage::CacheStorageMsg;
 use crate::filemanager_thread::FileManagerThreadMsg;
-use crate::request::{Request, RequestBuilder};
-use crate::response::{HttpsState, Response, ResponseInit};
+use crate::request::{IpAddressSpace, Request, RequestBuilder};
+use crate::response::{HttpsState, Response, ResponseInit, ResponseType};
 use crate::storage_thread::StorageThreadMsg;
 use cookie::Cookie;
 use headers::{ContentType, HeaderMapExt, ReferrerPolicy as ReferrerPolicyHeader};
@@ -36,6 +37,7 @@ use time::precise_time_ns;
 use webrender_api::{ImageData, ImageDescriptor, ImageKey};
 
 pub mod blob_url_store;
+pub mod cache_storage;
 pub mod filemanager_thread;
 pub mod image_cache;
 pub mod pub_domains;
@@ -68,8 +70,13 @@ pub enum LoadContext {
     CacheManifest,
 }
 
+/// A response provided by a service worker through `FetchEvent.respondWith()`.
 #[derive(Clone, Debug, Deserialize, MallocSizeOf, Serialize)]
 pub struct CustomResponse {
+    /// The type of the response. An error type makes the fetch fail with that error.
+    pub response_type: ResponseType,
+    /// The URL of the response, if it is not the URL of the request.
+    pub url: Option<ServoUrl>,
     #[ignore_malloc_size_of = "Defined in hyper"]
     #[serde(
         deserialize_with = "::hyper_serde::deserialize",
@@ -87,22 +94,41 @@ pub struct CustomResponse {
 
 impl CustomResponse {
     pub fn new(
+        response_type: ResponseType,
+        url: Option<ServoUrl>,
         headers: HeaderMap,
         raw_status: (StatusCode, String),
         body: Vec<u8>,
     ) -> CustomResponse {
         CustomResponse {
+            response_type: response_type,
+            url: url,
             headers: headers,
             raw_status: raw_status,
             body: body,
         }
     }
+
+    pub fn network_error(error: NetworkError) -> CustomResponse {
+        CustomResponse::new(
+            ResponseType::Error(error),
+            None,
+            HeaderMap::new(),
+            (StatusCode::OK, String::new()),
+            vec![],
+        )
+    }
 }
 
+/// A request for a service worker to [handle a fetch](https://w3c.github.io/ServiceWorker/#handle-fetch).
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct CustomResponseMediator {
+    /// Receives the response of the service worker, or `None` to fall back to the network.
     pub response_chan: IpcSender<Option<CustomResponse>>,
     pub load_url: ServoUrl,
+    /// The request being fetched, without its body. Its pipeline is its client,
+    /// whose controller handles it unless it is a navigation.
+    pub request: RequestBuilder,
 }
 
 /// [Policies](https://w3c.github.io/webappsec-referrer-policy/#referrer-policy-states)
@@ -410,9 +436,18 @@ pub enum WebSocketDomAction {
 
 #[derive(Debug, Deserialize, Serialize)]
 pub enum WebSocketNetworkEvent {
-    ConnectionEstablished { protocol_in_use: Option<String> },
+    ConnectionEstablished {
+        protocol_in_use: Option<String>,
+        extensions_in_use: Option<String>,
+    },
     MessageReceived(MessageData),
-    Close(Option<u16>, String),
+    /// The connection was closed, either through the closing handshake or
+    /// because the underlying transport went away without one.
+    Close {
+        code: Option<u16>,
+        reason: String,
+        was_clean: bool,
+    },
     Fail,
 }
 
@@ -469,6 +504,8 @@ pub enum CoreResourceMsg {
     NetworkMediator(IpcSender<CustomResponseMediator>, ImmutableOrigin),
     /// Message forwarded to file manager's handler
     ToFileManager(FileManagerThreadMsg),
+    /// Operations on the Cache API storage
+    CacheStorage(CacheStorageMsg),
     /// Break the load handler loop, send a reply when done cleaning up local resources
     /// and exit
     Exit(IpcSender<()>),
@@ -754,6 +791,10 @@ pub enum NetworkError {
     LoadCancelled,
     /// SSL validation error that has to be handled in the HTML parser
     SslValidation(String, Vec<u8>),
+    /// The connection of a request reached a more private address space than
+    /// the request was allowed to, see
+    /// <https://wicg.github.io/private-network-access/#private-network-access-check>.
+    PrivateNetworkAccess(IpAddressSpace),
 }
 
 impl NetworkError {
@@ -114,6 +114,18 @@ pub enum CorsSettings {
 }
 
 /// [Parser Metadata](https://fetch.spec.whatwg.org/#concept-request-parser-metadata)
+/// <https://wicg.github.io/private-network-access/#ip-address-space-section>
+///
+/// Address spaces are ordered from the most to the least private.
+#[derive(
+    Clone, Copy, Debug, Deserialize, Eq, Hash, MallocSizeOf, Ord, PartialEq, PartialOrd, Serialize,
+)]
+pub enum IpAddressSpace {
+    Local,
+    Private,
+    Public,
+}
+
 #[derive(Clone, Copy, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
 pub enum ParserMetadata {
     Default,
@@ -251,6 +263,9 @@ pub struct RequestBuilder {
     pub parser_metadata: ParserMetadata,
     pub initiator: Initiator,
     pub https_state: HttpsState,
+    /// Whether this is a navigation explicitly caused by the user through the
+    /// user agent, rather than by content.
+    pub user_initiated: bool,
 }
 
 impl RequestBuilder {
@@ -280,6 +295,7 @@ impl RequestBuilder {
             initiator: Initiator::None,
             csp_list: None,
             https_state: HttpsState::None,
+            user_initiated: false,
         }
     }
 
@@ -308,6 +324,14 @@ impl RequestBuilder {
         self
     }
 
+    pub fn service_workers_mode(
+        mut self,
+        service_workers_mode: ServiceWorkersMode,
+    ) -> RequestBuilder {
+        self.service_workers_mode = service_workers_mode;
+        self
+    }
+
     pub fn destination(mut self, destination: Destination) -> RequestBuilder {
         self.destination = destination;
         self
@@ -373,6 +397,11 @@ impl RequestBuilder {
         self
     }
 
+    pub fn user_initiated(mut self, user_initiated: bool) -> RequestBuilder {
+        self.user_initiated = user_initiated;
+        self
+    }
+
     pub fn build(self) -> Request {
         let mut request = Request::new(
             self.url.clone(),
@@ -405,6 +434,7 @@ impl RequestBuilder {
         request.integrity_metadata = self.integrity_metadata;
         request.parser_metadata = self.parser_metadata;
         request.csp_list = self.csp_list;
+        request.user_initiated = self.user_initiated;
         request
     }
 }
@@ -478,6 +508,11 @@ pub struct Request {
     #[ignore_malloc_size_of = "Defined in rust-content-security-policy"]
     pub csp_list: Option<CspList>,
     pub https_state: HttpsState,
+    /// Whether this is a navigation explicitly caused by the user through the
+    /// user agent, rather than by content.
+    pub user_initiated: bool,
+    /// <https://wicg.github.io/private-network-access/#request-target-ip-address-space>
+    pub target_address_space: Option<IpAddressSpace>,
 }
 
 impl Request {
@@ -518,6 +553,8 @@ impl Request {
             response_tainting: ResponseTainting::Basic,
             csp_list: None,
             https_state: https_state,
+            user_initiated: false,
+            target_address_space: None,
         }
     }
 
@@ -14,21 +14,27 @@ use time;
 #[derive(Clone, Copy, Debug, JSTraceable, MallocSizeOf)]
 pub(crate) struct AnimationTimeline {
     current_value: f64,
+    origin_value: f64,
 }
 
 impl AnimationTimeline {
     /// Creates a new "normal" timeline, i.e., a "Current" mode timer.
     #[inline]
     pub fn new() -> Self {
+        let current_value = time::precise_time_s();
         Self {
-            current_value: time::precise_time_s(),
+            current_value,
+            origin_value: current_value,
         }
     }
 
     /// Creates a new "test mode" timeline, with initial time 0.
     #[inline]
     pub fn new_for_testing() -> Self {
-        Self { current_value: 0. }
+        Self {
+            current_value: 0.,
+            origin_value: 0.,
+        }
     }
 
     /// Returns the current value of the timeline in seconds.
@@ -36,6 +42,12 @@ impl AnimationTimeline {
         self.current_value
     }
 
+    /// Returns the value of the timeline in seconds when it was created, which is
+    /// the zero time of the document's default `DocumentTimeline`.
+    pub fn origin_value(&self) -> f64 {
+        self.origin_value
+    }
+
     /// Updates the value of the `AnimationTimeline` to the current clock time.
     pub fn update(&mut self) {
         self.current_value = time::precise_time_s();
@@ -6,6 +6,7 @@
 
 //! The set of animations for a document.
 
+use crate::dom::animation::Animation as DOMAnimation;
 use crate::dom::animationevent::AnimationEvent;
 use crate::dom::bindings::cell::DomRefCell;
 use crate::dom::bindings::codegen::Bindings::AnimationEventBinding::AnimationEventInit;
@@ -47,6 +48,13 @@ pub(crate) struct Animations {
 
     /// A list of pending animation-related events.
     pending_events: DomRefCell<Vec<TransitionOrAnimationEvent>>,
+
+    /// The animations created from script that are relevant, in the order that
+    /// they were created.
+    script_animations: DomRefCell<Vec<Dom<DOMAnimation>>>,
+
+    /// The identifier to give to the next animation created from script.
+    next_script_animation_id: Cell<u64>,
 }
 
 impl Animations {
@@ -56,6 +64,8 @@ impl Animations {
             have_running_animations: Cell::new(false),
             rooted_nodes: Default::default(),
             pending_events: Default::default(),
+            script_animations: Default::default(),
+            next_script_animation_id: Cell::new(0),
         }
     }
 
@@ -63,6 +73,43 @@ impl Animations {
         self.sets.sets.write().clear();
         self.rooted_nodes.borrow_mut().clear();
         self.pending_events.borrow_mut().clear();
+        self.script_animations.borrow_mut().clear();
+    }
+
+    /// Get an identifier for a new animation created from script.
+    pub(crate) fn next_script_animation_id(&self) -> u64 {
+        let id = self.next_script_animation_id.get();
+        self.next_script_animation_id.set(id + 1);
+        id
+    }
+
+    /// Start tracking an animation created from script, if it is not already
+    /// tracked, so that it is ticked with the timeline.
+    pub(crate) fn add_script_animation(&self, animation: &DOMAnimation) {
+        let mut script_animations = self.script_animations.borrow_mut();
+        if !script_animations.iter().any(|other| &**other == animation) {
+            script_animations.push(Dom::from_ref(animation));
+        }
+    }
+
+    /// Stop tracking an animation created from script.
+    pub(crate) fn remove_script_animation(&self, animation: &DOMAnimation) {
+        self.script_animations
+            .borrow_mut()
+            .retain(|other| &**other != animation);
+    }
+
+    /// The animations created from script that are tracked, sorted by the order
+    /// in which they were created.
+    pub(crate) fn script_animations(&self) -> Vec<DomRoot<DOMAnimation>> {
+        let mut animations: Vec<_> = self
+            .script_animations
+            .borrow()
+            .iter()
+            .map(|animation| DomRoot::from_ref(&**animation))
+            .collect();
+        animations.sort_by_key(|animation| animation.style_id());
+        animations
     }
 
     pub(crate) fn mark_animating_nodes_as_dirty(&self) {
@@ -74,6 +121,12 @@ impl Animations {
     }
 
     pub(crate) fn update_for_new_timeline_value(&self, window: &Window, now: f64) {
+        // Animations created from script update their entries in the animation sets
+        // themselves, so they must be ticked before the sets are locked.
+        for animation in self.script_animations() {
+            animation.tick();
+        }
+
         let pipeline_id = window.pipeline_id();
         let mut sets = self.sets.sets.write();
 
@@ -285,6 +338,10 @@ impl Animations {
             animation.is_new = false;
         }
 
+        for animation in set.script_animations.iter_mut() {
+            animation.is_new = false;
+        }
+
         for transition in set.transitions.iter_mut() {
             if transition.is_new {
                 self.add_transition_event(
@@ -316,7 +373,10 @@ impl Animations {
             }
 
             if set.animations.iter().any(|animation| animation.is_new) ||
-                set.transitions.iter().any(|transition| transition.is_new)
+                set.transitions.iter().any(|transition| transition.is_new) ||
+                set.script_animations
+                    .iter()
+                    .any(|animation| animation.is_new)
             {
                 let address = UntrustedNodeAddress(opaque_node.0 as *const c_void);
                 unsafe {
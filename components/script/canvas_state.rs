@@ -242,6 +242,7 @@ impl CanvasState {
                 image.same_origin(GlobalScope::entry().origin())
             },
             CanvasImageSource::CSSStyleValue(_) => true,
+            CanvasImageSource::VideoFrame(_) => true,
         }
     }
 
@@ -430,6 +431,13 @@ impl CanvasState {
                     htmlcanvas, url, None, sx, sy, sw, sh, dx, dy, dw, dh,
                 )
             },
+            CanvasImageSource::VideoFrame(ref frame) => {
+                // https://html.spec.whatwg.org/multipage/#check-the-usability-of-the-image-argument
+                let (image_data, image_size) = frame.get_bgra_data().ok_or(Error::InvalidState)?;
+                self.draw_image_data(
+                    htmlcanvas, image_data, image_size, sx, sy, sw, sh, dx, dy, dw, dh,
+                )
+            },
         };
 
         if result.is_ok() && !self.is_origin_clean(image) {
@@ -571,9 +579,29 @@ impl CanvasState {
         dh: Option<f64>,
     ) -> ErrorResult {
         debug!("Fetching image {}.", url);
-        let (mut image_data, image_size) = self
+        let (image_data, image_size) = self
             .fetch_image_data(url, cors_setting)
             .ok_or(Error::InvalidState)?;
+        self.draw_image_data(
+            canvas, image_data, image_size, sx, sy, sw, sh, dx, dy, dw, dh,
+        )
+    }
+
+    /// Draws the given non-premultiplied BGRA pixels.
+    fn draw_image_data(
+        &self,
+        canvas: Option<&HTMLCanvasElement>,
+        mut image_data: Vec<u8>,
+        image_size: Size2D<u32>,
+        sx: f64,
+        sy: f64,
+        sw: Option<f64>,
+        sh: Option<f64>,
+        dx: f64,
+        dy: f64,
+        dw: Option<f64>,
+        dh: Option<f64>,
+    ) -> ErrorResult {
         pixels::rgba8_premultiply_inplace(&mut image_data);
         let image_size = image_size.to_f64();
 
@@ -925,6 +953,9 @@ impl CanvasState {
                 .get_url(self.base_url.clone())
                 .and_then(|url| self.fetch_image_data(url, None))
                 .ok_or(Error::InvalidState)?,
+            CanvasImageSource::VideoFrame(ref frame) => {
+                frame.get_bgra_data().ok_or(Error::InvalidState)?
+            },
         };
 
         if repetition.is_empty() {
@@ -0,0 +1,343 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::AbortSignalBinding::AbortSignalMethods;
+use crate::dom::bindings::codegen::Bindings::EventListenerBinding::EventListener;
+use crate::dom::bindings::codegen::Bindings::EventTargetBinding::EventListenerOptions;
+use crate::dom::bindings::conversions::ToJSValConvertible;
+use crate::dom::bindings::error::{Error, ErrorResult};
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::refcounted::Trusted;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject};
+use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::bindings::str::DOMString;
+use crate::dom::domexception::{DOMErrorName, DOMException};
+use crate::dom::eventtarget::EventTarget;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::readablestream::PipeTo;
+use crate::dom::response::Response;
+use crate::fetch::{self, FetchCanceller};
+use crate::realms::enter_realm;
+use crate::script_runtime::JSContext;
+use crate::timers::OneshotTimerCallback;
+use dom_struct::dom_struct;
+use euclid::Length;
+use js::jsapi::{ExceptionStackBehavior, Heap};
+use js::jsval::{JSVal, UndefinedValue};
+use js::rust::wrappers::JS_SetPendingException;
+use js::rust::{HandleValue, MutableHandleValue};
+use std::cell::Cell;
+use std::mem;
+use std::rc::Rc;
+
+/// <https://dom.spec.whatwg.org/#abortsignal-abort-algorithms>
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+pub enum AbortAlgorithm {
+    /// Removes an event listener that was added with this signal.
+    RemoveEventListener {
+        target: Dom<EventTarget>,
+        ty: DOMString,
+        #[ignore_malloc_size_of = "Rc"]
+        listener: Rc<EventListener>,
+        capture: bool,
+    },
+    /// Aborts an ongoing `fetch()` call.
+    Fetch {
+        #[ignore_malloc_size_of = "Rc"]
+        promise: Rc<Promise>,
+        response: Dom<Response>,
+        canceller: FetchCanceller,
+    },
+    /// Aborts an ongoing `pipeTo()` call.
+    StreamPipe(#[ignore_malloc_size_of = "Rc"] Rc<PipeTo>),
+}
+
+impl AbortAlgorithm {
+    fn run(self, reason: HandleValue) {
+        match self {
+            AbortAlgorithm::RemoveEventListener {
+                target,
+                ty,
+                listener,
+                capture,
+            } => {
+                target.remove_event_listener(ty, Some(listener), EventListenerOptions { capture });
+            },
+            AbortAlgorithm::Fetch {
+                promise,
+                response,
+                mut canceller,
+            } => {
+                canceller.cancel();
+                fetch::abort_fetch_call(&promise, Some(&response), reason);
+            },
+            AbortAlgorithm::StreamPipe(pipe) => pipe.abort(reason),
+        }
+    }
+}
+
+#[dom_struct]
+pub struct AbortSignal {
+    eventtarget: EventTarget,
+    /// <https://dom.spec.whatwg.org/#abortsignal-abort-reason>
+    #[ignore_malloc_size_of = "Defined in rust-mozjs"]
+    abort_reason: Heap<JSVal>,
+    /// <https://dom.spec.whatwg.org/#abortsignal-abort-algorithms>
+    abort_algorithms: DomRefCell<Vec<AbortAlgorithm>>,
+    /// <https://dom.spec.whatwg.org/#abortsignal-dependent>
+    dependent: Cell<bool>,
+    /// <https://dom.spec.whatwg.org/#abortsignal-source-signals>
+    source_signals: DomRefCell<Vec<Dom<AbortSignal>>>,
+    /// <https://dom.spec.whatwg.org/#abortsignal-dependent-signals>
+    ///
+    /// These are strong references, released once this signal is aborted.
+    dependent_signals: DomRefCell<Vec<Dom<AbortSignal>>>,
+}
+
+impl AbortSignal {
+    #[allow(unrooted_must_root)]
+    fn new_inherited() -> AbortSignal {
+        AbortSignal {
+            eventtarget: EventTarget::new_inherited(),
+            abort_reason: Heap::default(),
+            abort_algorithms: DomRefCell::new(vec![]),
+            dependent: Cell::new(false),
+            source_signals: DomRefCell::new(vec![]),
+            dependent_signals: DomRefCell::new(vec![]),
+        }
+    }
+
+    pub fn new(global: &GlobalScope) -> DomRoot<AbortSignal> {
+        reflect_dom_object(Box::new(AbortSignal::new_inherited()), global)
+    }
+
+    /// <https://dom.spec.whatwg.org/#dom-abortsignal-abort>
+    #[allow(non_snake_case)]
+    pub fn Abort(
+        _cx: JSContext,
+        global: &GlobalScope,
+        reason: HandleValue,
+    ) -> DomRoot<AbortSignal> {
+        // Step 1.
+        let signal = AbortSignal::new(global);
+
+        // Step 2.
+        signal.set_abort_reason(reason);
+
+        // Step 3.
+        signal
+    }
+
+    /// <https://dom.spec.whatwg.org/#dom-abortsignal-timeout>
+    #[allow(non_snake_case)]
+    pub fn Timeout(global: &GlobalScope, milliseconds: u64) -> DomRoot<AbortSignal> {
+        // Step 1.
+        let signal = AbortSignal::new(global);
+
+        // Steps 2-3.
+        let callback = OneshotTimerCallback::AbortSignalTimeout(AbortSignalTimeoutCallback {
+            signal: Trusted::new(&*signal),
+        });
+        global.schedule_callback(callback, Length::new(milliseconds));
+
+        // Step 4.
+        signal
+    }
+
+    /// <https://dom.spec.whatwg.org/#dom-abortsignal-any>
+    #[allow(non_snake_case)]
+    pub fn Any(global: &GlobalScope, signals: Vec<DomRoot<AbortSignal>>) -> DomRoot<AbortSignal> {
+        AbortSignal::create_dependent(global, &signals)
+    }
+
+    /// <https://dom.spec.whatwg.org/#create-a-dependent-abort-signal>
+    pub fn create_dependent(
+        global: &GlobalScope,
+        signals: &[DomRoot<AbortSignal>],
+    ) -> DomRoot<AbortSignal> {
+        // Step 1.
+        let result_signal = AbortSignal::new(global);
+
+        // Step 2.
+        if let Some(signal) = signals.iter().find(|signal| signal.aborted()) {
+            result_signal.abort_reason.set(signal.abort_reason.get());
+            return result_signal;
+        }
+
+        // Step 3.
+        result_signal.dependent.set(true);
+
+        // Step 4.
+        for signal in signals {
+            if !signal.dependent.get() {
+                // Step 4.1.
+                result_signal.add_source_signal(signal);
+            } else {
+                // Step 4.2.
+                for source_signal in signal.source_signals.borrow().iter() {
+                    result_signal.add_source_signal(source_signal);
+                }
+            }
+        }
+
+        // Step 5.
+        result_signal
+    }
+
+    #[allow(unrooted_must_root)]
+    fn add_source_signal(&self, source_signal: &AbortSignal) {
+        let source = Dom::from_ref(source_signal);
+        let mut source_signals = self.source_signals.borrow_mut();
+        if !source_signals.contains(&source) {
+            source_signals.push(source);
+            source_signal
+                .dependent_signals
+                .borrow_mut()
+                .push(Dom::from_ref(self));
+        }
+    }
+
+    /// <https://dom.spec.whatwg.org/#abortsignal-aborted>
+    pub fn aborted(&self) -> bool {
+        !self.abort_reason.get().is_undefined()
+    }
+
+    /// The abort reason, which is undefined while the signal is not aborted.
+    pub fn abort_reason(&self) -> JSVal {
+        self.abort_reason.get()
+    }
+
+    /// <https://dom.spec.whatwg.org/#abortsignal-add>
+    pub fn add(&self, algorithm: AbortAlgorithm) {
+        // Step 1.
+        if self.aborted() {
+            return;
+        }
+
+        // Step 2.
+        self.abort_algorithms.borrow_mut().push(algorithm);
+    }
+
+    /// <https://dom.spec.whatwg.org/#abortsignal-signal-abort>
+    #[allow(unrooted_must_root)]
+    pub fn signal_abort(&self, reason: HandleValue) {
+        // Step 1.
+        if self.aborted() {
+            return;
+        }
+
+        // Step 2.
+        self.set_abort_reason(reason);
+
+        // Steps 3-4.
+        let dependent_signals_to_abort: Vec<DomRoot<AbortSignal>> = self
+            .dependent_signals
+            .borrow_mut()
+            .drain(..)
+            .filter(|dependent_signal| !dependent_signal.aborted())
+            .map(|dependent_signal| {
+                dependent_signal.abort_reason.set(self.abort_reason.get());
+                DomRoot::from_ref(&*dependent_signal)
+            })
+            .collect();
+
+        // Step 5.
+        self.run_abort_steps();
+
+        // Step 6.
+        for dependent_signal in dependent_signals_to_abort {
+            dependent_signal.run_abort_steps();
+        }
+    }
+
+    /// Sets the abort reason, which defaults to a new "AbortError" `DOMException`.
+    #[allow(unsafe_code)]
+    fn set_abort_reason(&self, reason: HandleValue) {
+        if !reason.is_undefined() {
+            self.abort_reason.set(reason.get());
+            return;
+        }
+        let global = self.global();
+        let cx = global.get_cx();
+        rooted!(in(*cx) let mut exception = UndefinedValue());
+        new_exception(&global, DOMErrorName::AbortError, exception.handle_mut());
+        self.abort_reason.set(exception.get());
+    }
+
+    /// <https://dom.spec.whatwg.org/#run-the-abort-steps>
+    #[allow(unrooted_must_root)]
+    fn run_abort_steps(&self) {
+        let cx = self.global().get_cx();
+        rooted!(in(*cx) let reason = self.abort_reason.get());
+
+        // Steps 1-2.
+        let algorithms = mem::replace(&mut *self.abort_algorithms.borrow_mut(), vec![]);
+        for algorithm in algorithms {
+            algorithm.run(reason.handle());
+        }
+
+        // Step 3.
+        self.upcast::<EventTarget>().fire_event(atom!("abort"));
+    }
+}
+
+impl AbortSignalMethods for AbortSignal {
+    /// <https://dom.spec.whatwg.org/#dom-abortsignal-aborted>
+    fn Aborted(&self) -> bool {
+        self.aborted()
+    }
+
+    /// <https://dom.spec.whatwg.org/#dom-abortsignal-reason>
+    fn Reason(&self, _cx: JSContext) -> JSVal {
+        self.abort_reason.get()
+    }
+
+    /// <https://dom.spec.whatwg.org/#dom-abortsignal-throwifaborted>
+    #[allow(unsafe_code)]
+    fn ThrowIfAborted(&self) -> ErrorResult {
+        if !self.aborted() {
+            return Ok(());
+        }
+        let cx = self.global().get_cx();
+        rooted!(in(*cx) let reason = self.abort_reason.get());
+        unsafe {
+            JS_SetPendingException(*cx, reason.handle(), ExceptionStackBehavior::Capture);
+        }
+        Err(Error::JSFailed)
+    }
+
+    // https://dom.spec.whatwg.org/#dom-abortsignal-onabort
+    event_handler!(abort, GetOnabort, SetOnabort);
+}
+
+/// Creates a `DOMException` in the realm of a global.
+#[allow(unsafe_code)]
+fn new_exception(global: &GlobalScope, name: DOMErrorName, rval: MutableHandleValue) {
+    let cx = global.get_cx();
+    let _ac = enter_realm(global);
+    unsafe { DOMException::new(global, name).to_jsval(*cx, rval) };
+}
+
+/// Aborts a signal created with `AbortSignal.timeout()`.
+#[derive(JSTraceable, MallocSizeOf)]
+pub struct AbortSignalTimeoutCallback {
+    #[ignore_malloc_size_of = "Because it is non-owning"]
+    signal: Trusted<AbortSignal>,
+}
+
+impl AbortSignalTimeoutCallback {
+    /// <https://dom.spec.whatwg.org/#dom-abortsignal-timeout>
+    pub fn invoke(self) {
+        // Step 3.1.
+        let signal = self.signal.root();
+        let global = signal.global();
+        let cx = global.get_cx();
+        rooted!(in(*cx) let mut reason = UndefinedValue());
+        new_exception(&global, DOMErrorName::TimeoutError, reason.handle_mut());
+        signal.signal_abort(reason.handle());
+    }
+}
@@ -0,0 +1,895 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::animationeffect::{AnimationEffect, AnimationEffectPhase};
+use crate::dom::animationplaybackevent::AnimationPlaybackEvent;
+use crate::dom::animationtimeline::AnimationTimeline;
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::AnimationBinding::{
+    AnimationMethods, AnimationPlayState,
+};
+use crate::dom::bindings::codegen::Bindings::AnimationPlaybackEventBinding::AnimationPlaybackEventInit;
+use crate::dom::bindings::codegen::Bindings::DocumentBinding::DocumentMethods;
+use crate::dom::bindings::codegen::Bindings::EventBinding::EventInit;
+use crate::dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
+use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::num::Finite;
+use crate::dom::bindings::refcounted::Trusted;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject};
+use crate::dom::bindings::root::{DomRoot, MutNullableDom};
+use crate::dom::bindings::str::DOMString;
+use crate::dom::element::Element;
+use crate::dom::event::Event;
+use crate::dom::eventtarget::EventTarget;
+use crate::dom::keyframeeffect::KeyframeEffect;
+use crate::dom::node::{Node, NodeDamage};
+use crate::dom::promise::Promise;
+use crate::dom::window::Window;
+use crate::task_source::TaskSource;
+use dom_struct::dom_struct;
+use servo_atoms::Atom;
+use std::cell::Cell;
+use std::rc::Rc;
+use style::animation::{AnimationSetKey, ScriptAnimation};
+
+/// A task that runs the next time that an animation is ready.
+///
+/// <https://drafts.csswg.org/web-animations/#pending-play-task>
+#[derive(Clone, Copy, Debug, JSTraceable, MallocSizeOf, PartialEq)]
+enum PendingTask {
+    Play,
+    Pause,
+}
+
+// https://drafts.csswg.org/web-animations/#the-animation-interface
+#[dom_struct]
+pub struct Animation {
+    eventtarget: EventTarget,
+    id: DomRefCell<DOMString>,
+    effect: MutNullableDom<AnimationEffect>,
+    timeline: MutNullableDom<AnimationTimeline>,
+    /// All of the times of this animation are in milliseconds.
+    start_time: Cell<Option<f64>>,
+    hold_time: Cell<Option<f64>>,
+    previous_current_time: Cell<Option<f64>>,
+    playback_rate: Cell<f64>,
+    pending_playback_rate: Cell<Option<f64>>,
+    pending_task: Cell<Option<PendingTask>>,
+    #[ignore_malloc_size_of = "Rc"]
+    ready_promise: DomRefCell<Rc<Promise>>,
+    #[ignore_malloc_size_of = "Rc"]
+    finished_promise: DomRefCell<Rc<Promise>>,
+    /// The identifier of this animation in the animation sets of layout, which is
+    /// also the order in which animations were created.
+    style_id: u64,
+    /// The element whose animation set currently contains this animation.
+    animating_element: MutNullableDom<Element>,
+}
+
+impl Animation {
+    fn new_inherited(window: &Window, style_id: u64) -> Animation {
+        Animation {
+            eventtarget: EventTarget::new_inherited(),
+            id: DomRefCell::new(DOMString::new()),
+            effect: Default::default(),
+            timeline: Default::default(),
+            start_time: Cell::new(None),
+            hold_time: Cell::new(None),
+            previous_current_time: Cell::new(None),
+            playback_rate: Cell::new(1.),
+            pending_playback_rate: Cell::new(None),
+            pending_task: Cell::new(None),
+            ready_promise: DomRefCell::new(Promise::new(window.upcast())),
+            finished_promise: DomRefCell::new(Promise::new(window.upcast())),
+            style_id,
+            animating_element: Default::default(),
+        }
+    }
+
+    pub fn new(
+        window: &Window,
+        effect: Option<&AnimationEffect>,
+        timeline: Option<&AnimationTimeline>,
+    ) -> DomRoot<Animation> {
+        let style_id = window.Document().animations().next_script_animation_id();
+        let animation =
+            reflect_dom_object(Box::new(Animation::new_inherited(window, style_id)), window);
+        animation.ready_promise.borrow().resolve_native(&*animation);
+        animation.timeline.set(timeline);
+        animation.SetEffect(effect);
+        animation
+    }
+
+    #[allow(non_snake_case)]
+    pub fn Constructor(
+        window: &Window,
+        effect: Option<&AnimationEffect>,
+        timeline: Option<Option<&AnimationTimeline>>,
+    ) -> Fallible<DomRoot<Animation>> {
+        let animation = match timeline {
+            Some(timeline) => Animation::new(window, effect, timeline),
+            None => {
+                let timeline = window.Document().Timeline();
+                Animation::new(window, effect, Some(timeline.upcast()))
+            },
+        };
+        Ok(animation)
+    }
+
+    pub fn style_id(&self) -> u64 {
+        self.style_id
+    }
+
+    pub fn set_id(&self, id: DOMString) {
+        *self.id.borrow_mut() = id;
+    }
+
+    pub fn playback_rate(&self) -> f64 {
+        self.playback_rate.get()
+    }
+
+    /// The target of the keyframe effect of this animation, if any.
+    pub fn target(&self) -> Option<DomRoot<Element>> {
+        self.effect
+            .get()
+            .and_then(|effect| effect.downcast::<KeyframeEffect>().and_then(|e| e.target()))
+    }
+
+    fn timeline_time(&self) -> Option<f64> {
+        self.timeline
+            .get()
+            .and_then(|timeline| timeline.current_time())
+    }
+
+    fn effect_end(&self) -> f64 {
+        self.effect.get().map_or(0., |effect| effect.end_time())
+    }
+
+    fn effective_playback_rate(&self) -> f64 {
+        self.pending_playback_rate
+            .get()
+            .unwrap_or(self.playback_rate.get())
+    }
+
+    /// <https://drafts.csswg.org/web-animations/#apply-any-pending-playback-rate>
+    fn apply_pending_playback_rate(&self) {
+        if let Some(rate) = self.pending_playback_rate.take() {
+            self.playback_rate.set(rate);
+        }
+    }
+
+    fn resolve_ready_promise(&self) {
+        self.ready_promise.borrow().resolve_native(self);
+    }
+
+    /// <https://drafts.csswg.org/web-animations/#animation-current-time>
+    pub fn current_time(&self) -> Option<f64> {
+        if let Some(hold_time) = self.hold_time.get() {
+            return Some(hold_time);
+        }
+        self.unheld_current_time()
+    }
+
+    fn unheld_current_time(&self) -> Option<f64> {
+        match (self.timeline_time(), self.start_time.get()) {
+            (Some(timeline_time), Some(start_time)) => {
+                Some((timeline_time - start_time) * self.playback_rate.get())
+            },
+            _ => None,
+        }
+    }
+
+    /// <https://drafts.csswg.org/web-animations/#play-states>
+    fn play_state(&self) -> AnimationPlayState {
+        let current_time = self.current_time();
+        if current_time.is_none() &&
+            self.start_time.get().is_none() &&
+            self.pending_task.get().is_none()
+        {
+            return AnimationPlayState::Idle;
+        }
+        if self.pending_task.get() == Some(PendingTask::Pause) ||
+            (self.start_time.get().is_none() &&
+                self.pending_task.get() != Some(PendingTask::Play))
+        {
+            return AnimationPlayState::Paused;
+        }
+        if let Some(current_time) = current_time {
+            let rate = self.effective_playback_rate();
+            if (rate > 0. && current_time >= self.effect_end()) || (rate < 0. && current_time <= 0.)
+            {
+                return AnimationPlayState::Finished;
+            }
+        }
+        AnimationPlayState::Running
+    }
+
+    /// <https://drafts.csswg.org/web-animations/#silently-set-the-current-time>
+    fn silently_set_current_time(&self, seek_time: Option<f64>) -> ErrorResult {
+        // Step 1.
+        let seek_time = match seek_time {
+            Some(seek_time) => seek_time,
+            None if self.current_time().is_some() => {
+                return Err(Error::Type(
+                    "the current time of a running animation can't be cleared".to_owned(),
+                ));
+            },
+            None => return Ok(()),
+        };
+
+        // Step 2.
+        let timeline_time = self.timeline_time();
+        match timeline_time {
+            Some(timeline_time)
+                if self.hold_time.get().is_none() &&
+                    self.start_time.get().is_some() &&
+                    self.playback_rate.get() != 0. =>
+            {
+                self.start_time
+                    .set(Some(timeline_time - seek_time / self.playback_rate.get()));
+            }
+            _ => self.hold_time.set(Some(seek_time)),
+        }
+
+        // Step 3.
+        if timeline_time.is_none() {
+            self.start_time.set(None);
+        }
+
+        // Step 4.
+        self.previous_current_time.set(None);
+        Ok(())
+    }
+
+    /// <https://drafts.csswg.org/web-animations/#setting-the-current-time-of-an-animation>
+    fn set_current_time(&self, seek_time: Option<f64>) -> ErrorResult {
+        // Step 1.
+        self.silently_set_current_time(seek_time)?;
+
+        // Step 2.
+        if self.pending_task.get() == Some(PendingTask::Pause) {
+            self.hold_time.set(seek_time);
+            self.apply_pending_playback_rate();
+            self.start_time.set(None);
+            self.pending_task.set(None);
+            self.resolve_ready_promise();
+        }
+
+        // Step 3.
+        self.update_finished_state(true, false);
+        Ok(())
+    }
+
+    /// <https://drafts.csswg.org/web-animations/#playing-an-animation-section>
+    fn play_with_auto_rewind(&self, auto_rewind: bool) -> ErrorResult {
+        // Steps 1-3.
+        let aborted_pause = self.pending_task.get() == Some(PendingTask::Pause);
+        let mut has_pending_ready_promise = false;
+        let mut seek_time = None;
+
+        // Step 4.
+        if auto_rewind {
+            let rate = self.effective_playback_rate();
+            let current_time = self.current_time();
+            let end = self.effect_end();
+            if rate >= 0. && current_time.map_or(true, |time| time < 0. || time >= end) {
+                seek_time = Some(0.);
+            } else if rate < 0. && current_time.map_or(true, |time| time <= 0. || time > end) {
+                if end.is_infinite() {
+                    return Err(Error::InvalidState);
+                }
+                seek_time = Some(end);
+            }
+        }
+
+        // Steps 5-6.
+        if seek_time.is_some() {
+            self.hold_time.set(seek_time);
+        }
+        if self.hold_time.get().is_some() {
+            self.start_time.set(None);
+        }
+
+        // Step 7.
+        if self.pending_task.take().is_some() {
+            has_pending_ready_promise = true;
+        }
+
+        // Step 8.
+        if self.hold_time.get().is_none() &&
+            seek_time.is_none() &&
+            !aborted_pause &&
+            self.pending_playback_rate.get().is_none()
+        {
+            return Ok(());
+        }
+
+        // Step 9.
+        if !has_pending_ready_promise {
+            *self.ready_promise.borrow_mut() = Promise::new(&self.global());
+        }
+
+        // Steps 10-11.
+        self.pending_task.set(Some(PendingTask::Play));
+        self.update_finished_state(false, false);
+        Ok(())
+    }
+
+    /// <https://drafts.csswg.org/web-animations/#pausing-an-animation-section>
+    fn pause(&self) -> ErrorResult {
+        // Steps 1-2.
+        if self.pending_task.get() == Some(PendingTask::Pause) ||
+            self.play_state() == AnimationPlayState::Paused
+        {
+            return Ok(());
+        }
+
+        // Steps 3-5.
+        if self.current_time().is_none() {
+            if self.playback_rate.get() >= 0. {
+                self.hold_time.set(Some(0.));
+            } else {
+                let end = self.effect_end();
+                if end.is_infinite() {
+                    return Err(Error::InvalidState);
+                }
+                self.hold_time.set(Some(end));
+            }
+        }
+
+        // Steps 6-8.
+        let has_pending_ready_promise = self.pending_task.take() == Some(PendingTask::Play);
+        if !has_pending_ready_promise {
+            *self.ready_promise.borrow_mut() = Promise::new(&self.global());
+        }
+
+        // Steps 9-10.
+        self.pending_task.set(Some(PendingTask::Pause));
+        self.update_finished_state(false, false);
+        Ok(())
+    }
+
+    /// Run the pending play or pause task of this animation, if any, once the
+    /// timeline has a time to start from.
+    ///
+    /// <https://drafts.csswg.org/web-animations/#pending-play-task>
+    /// <https://drafts.csswg.org/web-animations/#pending-pause-task>
+    fn run_pending_task(&self) {
+        let ready_time = match self.timeline_time() {
+            Some(ready_time) => ready_time,
+            None => return,
+        };
+        match self.pending_task.take() {
+            Some(PendingTask::Play) => {
+                if let Some(hold_time) = self.hold_time.get() {
+                    self.apply_pending_playback_rate();
+                    let rate = self.playback_rate.get();
+                    if rate == 0. {
+                        self.start_time.set(Some(ready_time));
+                    } else {
+                        self.start_time.set(Some(ready_time - hold_time / rate));
+                        self.hold_time.set(None);
+                    }
+                } else if let Some(start_time) = self.start_time.get() {
+                    let current_time_to_match =
+                        (ready_time - start_time) * self.playback_rate.get();
+                    self.apply_pending_playback_rate();
+                    let rate = self.playback_rate.get();
+                    if rate == 0. {
+                        self.hold_time.set(Some(current_time_to_match));
+                    } else {
+                        self.start_time
+                            .set(Some(ready_time - current_time_to_match / rate));
+                    }
+                }
+            },
+            Some(PendingTask::Pause) => {
+                if let (Some(start_time), None) = (self.start_time.get(), self.hold_time.get()) {
+                    self.hold_time
+                        .set(Some((ready_time - start_time) * self.playback_rate.get()));
+                }
+                self.apply_pending_playback_rate();
+                self.start_time.set(None);
+            },
+            None => return,
+        }
+        self.resolve_ready_promise();
+        self.update_finished_state(false, false);
+    }
+
+    /// <https://drafts.csswg.org/web-animations/#update-an-animations-finished-state>
+    fn update_finished_state(&self, did_seek: bool, synchronously_notify: bool) {
+        // Step 1.
+        let unconstrained_current_time = if did_seek {
+            self.current_time()
+        } else {
+            self.unheld_current_time()
+        };
+
+        // Step 2.
+        if let (Some(unconstrained_current_time), Some(_), None) = (
+            unconstrained_current_time,
+            self.start_time.get(),
+            self.pending_task.get(),
+        ) {
+            let rate = self.playback_rate.get();
+            let end = self.effect_end();
+            let previous_current_time = self.previous_current_time.get();
+            if rate > 0. && unconstrained_current_time >= end {
+                self.hold_time.set(Some(match previous_current_time {
+                    _ if did_seek => unconstrained_current_time,
+                    Some(previous) => previous.max(end),
+                    None => end,
+                }));
+            } else if rate < 0. && unconstrained_current_time <= 0. {
+                self.hold_time.set(Some(match previous_current_time {
+                    _ if did_seek => unconstrained_current_time,
+                    Some(previous) => previous.min(0.),
+                    None => 0.,
+                }));
+            } else if rate != 0. {
+                if let Some(timeline_time) = self.timeline_time() {
+                    if let (true, Some(hold_time)) = (did_seek, self.hold_time.get()) {
+                        self.start_time.set(Some(timeline_time - hold_time / rate));
+                    }
+                    self.hold_time.set(None);
+                }
+            }
+        }
+
+        // Step 3.
+        self.previous_current_time.set(self.current_time());
+
+        // Steps 4-6.
+        let finished = self.play_state() == AnimationPlayState::Finished;
+        let finished_promise_settled = self.finished_promise.borrow().is_fulfilled();
+        if finished && !finished_promise_settled {
+            if synchronously_notify {
+                self.notify_finished();
+            } else {
+                let window = self.global();
+                let window = window.as_window();
+                let this = Trusted::new(self);
+                let _ = window.task_manager().dom_manipulation_task_source().queue(
+                    task!(animation_finish_notification: move || {
+                        let this = this.root();
+                        if !this.finished_promise.borrow().is_fulfilled() {
+                            this.notify_finished();
+                        }
+                    }),
+                    window.upcast(),
+                );
+            }
+        } else if !finished && finished_promise_settled {
+            *self.finished_promise.borrow_mut() = Promise::new(&self.global());
+        }
+
+        self.update_style(false);
+    }
+
+    /// <https://drafts.csswg.org/web-animations/#finish-notification-steps>
+    fn notify_finished(&self) {
+        if self.play_state() != AnimationPlayState::Finished {
+            return;
+        }
+        self.finished_promise.borrow().resolve_native(self);
+        self.queue_playback_event(atom!("finish"), self.current_time());
+    }
+
+    fn queue_playback_event(&self, type_: Atom, current_time: Option<f64>) {
+        let window = self.global();
+        let window = window.as_window();
+        let init = AnimationPlaybackEventInit {
+            parent: EventInit::empty(),
+            currentTime: current_time.and_then(Finite::new),
+            timelineTime: self.timeline_time().and_then(Finite::new),
+        };
+        let this = Trusted::new(self);
+        let event = Trusted::new(&*AnimationPlaybackEvent::new(window, type_, &init));
+        let _ = window.task_manager().dom_manipulation_task_source().queue(
+            task!(animation_playback_event: move || {
+                let event = event.root();
+                event.upcast::<Event>().fire(this.root().upcast());
+            }),
+            window.upcast(),
+        );
+    }
+
+    /// Whether this animation is relevant, and so is returned by `getAnimations()`.
+    ///
+    /// <https://drafts.csswg.org/web-animations/#relevant-animations-section>
+    pub fn is_relevant(&self) -> bool {
+        let effect = match self.effect.get() {
+            Some(effect) => effect,
+            None => return false,
+        };
+        let play_state = self.play_state();
+        if play_state == AnimationPlayState::Idle {
+            return false;
+        }
+        let timing = effect.computed_timing();
+        let backwards = self.playback_rate.get() < 0.;
+        let current = play_state != AnimationPlayState::Finished ||
+            timing.phase == AnimationEffectPhase::Active ||
+            (timing.phase == AnimationEffectPhase::Before && !backwards) ||
+            (timing.phase == AnimationEffectPhase::After && backwards);
+        current || timing.progress.is_some()
+    }
+
+    /// Run the pending tasks of this animation and update its style for a new
+    /// value of its timeline.
+    pub fn tick(&self) {
+        self.run_pending_task();
+        self.update_finished_state(false, false);
+    }
+
+    /// Called when the timing of the effect of this animation changes.
+    pub fn effect_timing_changed(&self) {
+        self.update_finished_state(false, false);
+    }
+
+    /// Called when the keyframes, target or composite operation of the effect of
+    /// this animation change.
+    pub fn effect_keyframes_changed(&self) {
+        self.update_style(true);
+    }
+
+    /// Reflect the state of this animation in the animation set of its target, so
+    /// that its values are applied the next time that the target is styled.
+    fn update_style(&self, keyframes_changed: bool) {
+        let window = self.global();
+        let document = window.as_window().Document();
+        let effect = self
+            .effect
+            .get()
+            .and_then(|effect| DomRoot::downcast::<KeyframeEffect>(effect));
+        let target = effect.as_ref().and_then(|effect| effect.target());
+        let relevant = target.is_some() && self.is_relevant();
+
+        let animations = document.animations();
+        let old_target = self.animating_element.get();
+        if let Some(old_target) = old_target.as_ref() {
+            if !relevant || target.as_deref() != Some(&**old_target) {
+                let node = old_target.upcast::<Node>();
+                let key = AnimationSetKey::new_for_non_pseudo(node.to_opaque());
+                if let Some(set) = animations.sets.sets.write().get_mut(&key) {
+                    set.script_animations
+                        .retain(|animation| animation.id != self.style_id);
+                }
+                node.dirty(NodeDamage::NodeStyleDamaged);
+                self.animating_element.set(None);
+            }
+        }
+
+        let (effect, target) = match (effect, target) {
+            (Some(effect), Some(target)) if relevant => (effect, target),
+            _ => {
+                animations.remove_script_animation(self);
+                return;
+            },
+        };
+        animations.add_script_animation(self);
+
+        let timing = effect.upcast::<AnimationEffect>().computed_timing();
+        let running =
+            self.pending_task.get().is_some() || self.play_state() == AnimationPlayState::Running;
+        let node = target.upcast::<Node>();
+        let key = AnimationSetKey::new_for_non_pseudo(node.to_opaque());
+        let mut sets = animations.sets.sets.write();
+        let set = sets.entry(key).or_default();
+        let index = match set
+            .script_animations
+            .iter()
+            .position(|animation| animation.id == self.style_id)
+        {
+            Some(index) => {
+                if keyframes_changed {
+                    set.script_animations[index].set_keyframes(effect.keyframes_animation());
+                }
+                index
+            },
+            None => {
+                set.script_animations.push(ScriptAnimation::new(
+                    self.style_id,
+                    effect.keyframes_animation(),
+                    effect.style_composite(),
+                ));
+                set.script_animations.len() - 1
+            },
+        };
+
+        let animation = &mut set.script_animations[index];
+        let composite = effect.style_composite();
+        let duration = effect.upcast::<AnimationEffect>().duration_in_seconds();
+        let changed = keyframes_changed ||
+            old_target.is_none() ||
+            animation.progress != timing.progress ||
+            animation.composite != composite ||
+            animation.running != running;
+        animation.progress = timing.progress;
+        animation.composite = composite;
+        animation.duration = duration;
+        animation.running = running;
+        drop(sets);
+
+        self.animating_element.set(Some(&target));
+        if changed {
+            node.dirty(NodeDamage::NodeStyleDamaged);
+        }
+    }
+}
+
+impl AnimationMethods for Animation {
+    // https://drafts.csswg.org/web-animations/#dom-animation-id
+    fn Id(&self) -> DOMString {
+        self.id.borrow().clone()
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animation-id
+    fn SetId(&self, id: DOMString) {
+        self.set_id(id);
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animation-effect
+    fn GetEffect(&self) -> Option<DomRoot<AnimationEffect>> {
+        self.effect.get()
+    }
+
+    // https://drafts.csswg.org/web-animations/#setting-the-associated-effect
+    fn SetEffect(&self, effect: Option<&AnimationEffect>) {
+        let old_effect = self.effect.get();
+        if old_effect.as_deref() == effect {
+            return;
+        }
+
+        if let Some(old_effect) = old_effect {
+            old_effect.set_animation(None);
+        }
+        if let Some(effect) = effect {
+            if let Some(previous_animation) = effect.animation() {
+                previous_animation.SetEffect(None);
+            }
+            effect.set_animation(Some(self));
+        }
+        self.effect.set(effect);
+        self.update_finished_state(false, false);
+        self.update_style(true);
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animation-timeline
+    fn GetTimeline(&self) -> Option<DomRoot<AnimationTimeline>> {
+        self.timeline.get()
+    }
+
+    // https://drafts.csswg.org/web-animations/#setting-the-timeline
+    fn SetTimeline(&self, timeline: Option<&AnimationTimeline>) {
+        if self.timeline.get().as_deref() == timeline {
+            return;
+        }
+        self.timeline.set(timeline);
+        if self.start_time.get().is_some() {
+            self.hold_time.set(None);
+        }
+        self.update_finished_state(false, false);
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animation-starttime
+    fn GetStartTime(&self) -> Option<Finite<f64>> {
+        self.start_time.get().and_then(Finite::new)
+    }
+
+    // https://drafts.csswg.org/web-animations/#set-the-start-time
+    fn SetStartTime(&self, start_time: Option<Finite<f64>>) {
+        let start_time = start_time.map(|time| *time);
+
+        // Steps 1-2.
+        if self.timeline_time().is_none() && start_time.is_some() {
+            self.hold_time.set(None);
+        }
+
+        // Steps 3-5.
+        let previous_current_time = self.current_time();
+        self.apply_pending_playback_rate();
+        self.start_time.set(start_time);
+
+        // Step 6.
+        if start_time.is_some() {
+            if self.playback_rate.get() != 0. {
+                self.hold_time.set(None);
+            } else {
+                self.hold_time.set(previous_current_time);
+            }
+        }
+
+        // Step 7.
+        if self.pending_task.take().is_some() {
+            self.resolve_ready_promise();
+        }
+
+        // Step 8.
+        self.update_finished_state(true, false);
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animation-currenttime
+    fn GetCurrentTime(&self) -> Option<Finite<f64>> {
+        self.current_time().and_then(Finite::new)
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animation-currenttime
+    fn SetCurrentTime(&self, current_time: Option<Finite<f64>>) -> ErrorResult {
+        self.set_current_time(current_time.map(|time| *time))
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animation-playbackrate
+    fn PlaybackRate(&self) -> Finite<f64> {
+        Finite::wrap(self.playback_rate.get())
+    }
+
+    // https://drafts.csswg.org/web-animations/#set-the-playback-rate
+    fn SetPlaybackRate(&self, playback_rate: Finite<f64>) {
+        self.pending_playback_rate.set(None);
+        let previous_time = self.current_time();
+        self.playback_rate.set(*playback_rate);
+        if previous_time.is_some() {
+            let _ = self.set_current_time(previous_time);
+        }
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animation-playstate
+    fn PlayState(&self) -> AnimationPlayState {
+        self.play_state()
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animation-pending
+    fn Pending(&self) -> bool {
+        self.pending_task.get().is_some()
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animation-ready
+    fn Ready(&self) -> Rc<Promise> {
+        self.ready_promise.borrow().clone()
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animation-finished
+    fn Finished(&self) -> Rc<Promise> {
+        self.finished_promise.borrow().clone()
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animation-onfinish
+    event_handler!(finish, GetOnfinish, SetOnfinish);
+
+    // https://drafts.csswg.org/web-animations/#dom-animation-oncancel
+    event_handler!(cancel, GetOncancel, SetOncancel);
+
+    // https://drafts.csswg.org/web-animations/#canceling-an-animation-section
+    fn Cancel(&self) {
+        if self.play_state() != AnimationPlayState::Idle {
+            // Step 1.1: reset pending tasks.
+            if self.pending_task.take().is_some() {
+                self.apply_pending_playback_rate();
+                let ready_promise = self.ready_promise.borrow().clone();
+                ready_promise.reject_error(Error::Abort);
+                ready_promise.set_promise_is_handled();
+                let new_ready_promise = Promise::new(&self.global());
+                new_ready_promise.resolve_native(self);
+                *self.ready_promise.borrow_mut() = new_ready_promise;
+            }
+
+            // Steps 1.2-1.4.
+            let finished_promise = self.finished_promise.borrow().clone();
+            finished_promise.reject_error(Error::Abort);
+            finished_promise.set_promise_is_handled();
+            *self.finished_promise.borrow_mut() = Promise::new(&self.global());
+            self.queue_playback_event(atom!("cancel"), None);
+        }
+
+        // Steps 2-3.
+        self.hold_time.set(None);
+        self.start_time.set(None);
+        self.update_style(false);
+    }
+
+    // https://drafts.csswg.org/web-animations/#finishing-an-animation-section
+    fn Finish(&self) -> ErrorResult {
+        // Step 1.
+        let rate = self.effective_playback_rate();
+        let end = self.effect_end();
+        if rate == 0. || (rate > 0. && end.is_infinite()) {
+            return Err(Error::InvalidState);
+        }
+
+        // Steps 2-4.
+        self.apply_pending_playback_rate();
+        let limit = if rate > 0. { end } else { 0. };
+        self.silently_set_current_time(Some(limit))?;
+
+        // Step 5.
+        if let (None, Some(timeline_time)) = (self.start_time.get(), self.timeline_time()) {
+            self.start_time
+                .set(Some(timeline_time - limit / self.playback_rate.get()));
+        }
+
+        // Steps 6-7.
+        if self.start_time.get().is_some() {
+            match self.pending_task.take() {
+                Some(PendingTask::Pause) => {
+                    self.hold_time.set(None);
+                    self.resolve_ready_promise();
+                },
+                Some(PendingTask::Play) => self.resolve_ready_promise(),
+                None => {},
+            }
+        }
+
+        // Step 8.
+        self.update_finished_state(true, true);
+        Ok(())
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animation-play
+    fn Play(&self) -> ErrorResult {
+        self.play_with_auto_rewind(true)
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animation-pause
+    fn Pause(&self) -> ErrorResult {
+        self.pause()
+    }
+
+    // https://drafts.csswg.org/web-animations/#seamlessly-update-the-playback-rate
+    fn UpdatePlaybackRate(&self, playback_rate: Finite<f64>) {
+        // Steps 1-3.
+        self.pending_playback_rate.set(Some(*playback_rate));
+        let previous_play_state = self.play_state();
+        if self.pending_task.get().is_some() {
+            return;
+        }
+
+        // Step 4.
+        match previous_play_state {
+            AnimationPlayState::Idle | AnimationPlayState::Paused => {
+                self.apply_pending_playback_rate();
+            },
+            AnimationPlayState::Finished => {
+                let timeline_time = self.timeline_time();
+                if *playback_rate == 0. {
+                    self.start_time.set(timeline_time);
+                } else if let (Some(timeline_time), Some(start_time)) =
+                    (timeline_time, self.start_time.get())
+                {
+                    let unconstrained_current_time =
+                        (timeline_time - start_time) * self.playback_rate.get();
+                    self.start_time.set(Some(
+                        timeline_time - unconstrained_current_time / *playback_rate,
+                    ));
+                }
+                self.apply_pending_playback_rate();
+                self.update_finished_state(false, false);
+            },
+            AnimationPlayState::Running => {
+                let _ = self.play_with_auto_rewind(false);
+            },
+        }
+    }
+
+    // https://drafts.csswg.org/web-animations/#reversing-an-animation-section
+    fn Reverse(&self) -> ErrorResult {
+        // Step 1.
+        if self.timeline_time().is_none() {
+            return Err(Error::InvalidState);
+        }
+
+        // Steps 2-4.
+        let original_pending_playback_rate = self.pending_playback_rate.get();
+        self.pending_playback_rate
+            .set(Some(-self.effective_playback_rate()));
+        if let Err(error) = self.play_with_auto_rewind(true) {
+            self.pending_playback_rate
+                .set(original_pending_playback_rate);
+            return Err(error);
+        }
+        Ok(())
+    }
+}
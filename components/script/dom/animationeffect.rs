@@ -0,0 +1,471 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::animation::Animation;
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::AnimationEffectBinding::{
+    AnimationEffectMethods, ComputedEffectTiming, EffectTiming, FillMode, OptionalEffectTiming,
+    PlaybackDirection,
+};
+use crate::dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
+use crate::dom::bindings::codegen::UnionTypes::UnrestrictedDoubleOrString;
+use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
+use crate::dom::bindings::num::Finite;
+use crate::dom::bindings::reflector::{DomObject, Reflector};
+use crate::dom::bindings::root::{DomRoot, MutNullableDom};
+use crate::dom::bindings::str::DOMString;
+use crate::dom::window::Window;
+use cssparser::{Parser, ParserInput};
+use dom_struct::dom_struct;
+use style::animation::timing_function_output;
+use style::context::QuirksMode;
+use style::parser::{Parse, ParserContext};
+use style::stylesheets::CssRuleType;
+use style::values::computed::TimingFunction;
+use style::values::generics::easing::{TimingFunction as GenericTimingFunction, TimingKeyword};
+use style::values::specified::easing::TimingFunction as SpecifiedTimingFunction;
+use style_traits::ParsingMode;
+
+/// The phase of an animation effect.
+///
+/// <https://drafts.csswg.org/web-animations/#animation-effect-phases-and-states>
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AnimationEffectPhase {
+    Before,
+    Active,
+    After,
+    Idle,
+}
+
+/// The timing of an animation effect computed at its current local time.
+///
+/// <https://drafts.csswg.org/web-animations/#calculating-computed-timing>
+pub struct ComputedTiming {
+    pub end_time: f64,
+    pub active_duration: f64,
+    pub local_time: Option<f64>,
+    pub phase: AnimationEffectPhase,
+    /// The transformed progress through the current iteration.
+    pub progress: Option<f64>,
+    pub current_iteration: Option<f64>,
+}
+
+/// The timing properties of an animation effect. Times are in milliseconds.
+///
+/// <https://drafts.csswg.org/web-animations/#the-effecttiming-dictionaries>
+#[derive(Clone, JSTraceable, MallocSizeOf)]
+pub struct Timing {
+    delay: f64,
+    end_delay: f64,
+    fill: FillMode,
+    iteration_start: f64,
+    iterations: f64,
+    /// The iteration duration, or `None` if it is `auto`.
+    duration: Option<f64>,
+    direction: PlaybackDirection,
+    easing: DOMString,
+    timing_function: TimingFunction,
+}
+
+impl Default for Timing {
+    fn default() -> Timing {
+        Timing {
+            delay: 0.,
+            end_delay: 0.,
+            fill: FillMode::Auto,
+            iteration_start: 0.,
+            iterations: 1.,
+            duration: None,
+            direction: PlaybackDirection::Normal,
+            easing: DOMString::from("linear"),
+            timing_function: GenericTimingFunction::Keyword(TimingKeyword::Linear),
+        }
+    }
+}
+
+impl Timing {
+    /// A timing with the given iteration duration, as used when the options of an
+    /// effect are just a number.
+    pub fn from_duration(duration: f64) -> Fallible<Timing> {
+        Ok(Timing {
+            duration: validate_duration(&UnrestrictedDoubleOrString::UnrestrictedDouble(duration))?,
+            ..Default::default()
+        })
+    }
+
+    pub fn from_effect_timing(window: &Window, timing: &EffectTiming) -> Fallible<Timing> {
+        validate_iteration_start(*timing.iterationStart)?;
+        validate_iterations(timing.iterations)?;
+        let duration = match timing.duration {
+            Some(ref duration) => validate_duration(duration)?,
+            None => None,
+        };
+        Ok(Timing {
+            delay: *timing.delay,
+            end_delay: *timing.endDelay,
+            fill: timing.fill,
+            iteration_start: *timing.iterationStart,
+            iterations: timing.iterations,
+            duration,
+            direction: timing.direction,
+            easing: timing.easing.clone(),
+            timing_function: parse_easing(window, &timing.easing)?,
+        })
+    }
+
+    /// <https://drafts.csswg.org/web-animations/#update-the-timing-properties-of-an-animation-effect>
+    pub fn update(&mut self, window: &Window, timing: &OptionalEffectTiming) -> ErrorResult {
+        // Step 1. Validate all of the new values before changing anything.
+        if let Some(iteration_start) = timing.iterationStart {
+            validate_iteration_start(*iteration_start)?;
+        }
+        if let Some(iterations) = timing.iterations {
+            validate_iterations(iterations)?;
+        }
+        let duration = match timing.duration {
+            Some(ref duration) => Some(validate_duration(duration)?),
+            None => None,
+        };
+        let timing_function = match timing.easing {
+            Some(ref easing) => Some(parse_easing(window, easing)?),
+            None => None,
+        };
+
+        // Step 2.
+        if let Some(delay) = timing.delay {
+            self.delay = *delay;
+        }
+        if let Some(end_delay) = timing.endDelay {
+            self.end_delay = *end_delay;
+        }
+        if let Some(fill) = timing.fill {
+            self.fill = fill;
+        }
+        if let Some(iteration_start) = timing.iterationStart {
+            self.iteration_start = *iteration_start;
+        }
+        if let Some(iterations) = timing.iterations {
+            self.iterations = iterations;
+        }
+        if let Some(duration) = duration {
+            self.duration = duration;
+        }
+        if let Some(direction) = timing.direction {
+            self.direction = direction;
+        }
+        if let (Some(easing), Some(timing_function)) = (timing.easing.as_ref(), timing_function) {
+            self.easing = easing.clone();
+            self.timing_function = timing_function;
+        }
+        Ok(())
+    }
+
+    fn to_effect_timing(&self) -> EffectTiming {
+        EffectTiming {
+            delay: Finite::wrap(self.delay),
+            endDelay: Finite::wrap(self.end_delay),
+            fill: self.fill,
+            iterationStart: Finite::wrap(self.iteration_start),
+            iterations: self.iterations,
+            duration: Some(match self.duration {
+                Some(duration) => UnrestrictedDoubleOrString::UnrestrictedDouble(duration),
+                None => UnrestrictedDoubleOrString::String(DOMString::from("auto")),
+            }),
+            direction: self.direction,
+            easing: self.easing.clone(),
+        }
+    }
+
+    /// The iteration duration in milliseconds, where `auto` is zero for keyframe effects.
+    fn iteration_duration(&self) -> f64 {
+        self.duration.unwrap_or(0.)
+    }
+
+    /// <https://drafts.csswg.org/web-animations/#active-duration>
+    fn active_duration(&self) -> f64 {
+        let duration = self.iteration_duration();
+        if duration == 0. || self.iterations == 0. {
+            return 0.;
+        }
+        duration * self.iterations
+    }
+
+    /// <https://drafts.csswg.org/web-animations/#end-time>
+    pub fn end_time(&self) -> f64 {
+        (self.delay + self.active_duration() + self.end_delay).max(0.)
+    }
+
+    fn fills_backwards(&self) -> bool {
+        self.fill == FillMode::Backwards || self.fill == FillMode::Both
+    }
+
+    fn fills_forwards(&self) -> bool {
+        self.fill == FillMode::Forwards || self.fill == FillMode::Both
+    }
+
+    /// <https://drafts.csswg.org/web-animations/#calculating-computed-timing>
+    pub fn computed_timing(&self, local_time: Option<f64>, playback_rate: f64) -> ComputedTiming {
+        let active_duration = self.active_duration();
+        let end_time = self.end_time();
+        let mut timing = ComputedTiming {
+            end_time,
+            active_duration,
+            local_time,
+            phase: AnimationEffectPhase::Idle,
+            progress: None,
+            current_iteration: None,
+        };
+
+        let local_time = match local_time {
+            Some(local_time) => local_time,
+            None => return timing,
+        };
+
+        // https://drafts.csswg.org/web-animations/#animation-effect-phases-and-states
+        let backwards = playback_rate < 0.;
+        let before_active_boundary = self.delay.min(end_time).max(0.);
+        let active_after_boundary = (self.delay + active_duration).min(end_time).max(0.);
+        timing.phase = if local_time < before_active_boundary ||
+            (backwards && local_time == before_active_boundary)
+        {
+            AnimationEffectPhase::Before
+        } else if local_time > active_after_boundary ||
+            (!backwards && local_time == active_after_boundary)
+        {
+            AnimationEffectPhase::After
+        } else {
+            AnimationEffectPhase::Active
+        };
+
+        // https://drafts.csswg.org/web-animations/#calculating-the-active-time
+        let active_time = match timing.phase {
+            AnimationEffectPhase::Before if self.fills_backwards() => {
+                (local_time - self.delay).max(0.)
+            },
+            AnimationEffectPhase::Active => local_time - self.delay,
+            AnimationEffectPhase::After if self.fills_forwards() => {
+                (local_time - self.delay).min(active_duration).max(0.)
+            },
+            _ => return timing,
+        };
+
+        // https://drafts.csswg.org/web-animations/#calculating-the-overall-progress
+        let duration = self.iteration_duration();
+        let overall_progress = if duration == 0. {
+            match timing.phase {
+                AnimationEffectPhase::Before => 0.,
+                _ => self.iterations,
+            }
+        } else {
+            active_time / duration
+        } + self.iteration_start;
+
+        // https://drafts.csswg.org/web-animations/#calculating-the-simple-iteration-progress
+        let mut simple_iteration_progress = if overall_progress.is_infinite() {
+            self.iteration_start % 1.
+        } else {
+            overall_progress % 1.
+        };
+        if simple_iteration_progress == 0. &&
+            timing.phase != AnimationEffectPhase::Before &&
+            active_time == active_duration &&
+            self.iterations != 0.
+        {
+            simple_iteration_progress = 1.;
+        }
+
+        // https://drafts.csswg.org/web-animations/#calculating-the-current-iteration
+        let current_iteration =
+            if timing.phase == AnimationEffectPhase::After && self.iterations.is_infinite() {
+                std::f64::INFINITY
+            } else if simple_iteration_progress == 1. {
+                overall_progress.floor() - 1.
+            } else {
+                overall_progress.floor()
+            };
+
+        // https://drafts.csswg.org/web-animations/#calculating-the-directed-progress
+        let even_iteration = current_iteration.is_infinite() || current_iteration % 2. == 0.;
+        let forwards = match self.direction {
+            PlaybackDirection::Normal => true,
+            PlaybackDirection::Reverse => false,
+            PlaybackDirection::Alternate => even_iteration,
+            PlaybackDirection::Alternate_reverse => !even_iteration,
+        };
+        let directed_progress = if forwards {
+            simple_iteration_progress
+        } else {
+            1. - simple_iteration_progress
+        };
+
+        // https://drafts.csswg.org/web-animations/#calculating-the-transformed-progress
+        let transformed_progress = timing_function_output(
+            &self.timing_function,
+            directed_progress,
+            (duration / 1000.).max(1.),
+        );
+
+        timing.progress = Some(transformed_progress);
+        timing.current_iteration = Some(current_iteration);
+        timing
+    }
+
+    /// The iteration duration in seconds.
+    pub fn duration_in_seconds(&self) -> f64 {
+        self.iteration_duration() / 1000.
+    }
+}
+
+fn validate_iteration_start(iteration_start: f64) -> ErrorResult {
+    if iteration_start < 0. {
+        return Err(Error::Type(
+            "iterationStart must not be negative".to_owned(),
+        ));
+    }
+    Ok(())
+}
+
+fn validate_iterations(iterations: f64) -> ErrorResult {
+    if iterations.is_nan() || iterations < 0. {
+        return Err(Error::Type(
+            "iterations must be a non-negative number".to_owned(),
+        ));
+    }
+    Ok(())
+}
+
+fn validate_duration(duration: &UnrestrictedDoubleOrString) -> Fallible<Option<f64>> {
+    match *duration {
+        UnrestrictedDoubleOrString::UnrestrictedDouble(duration) => {
+            if duration.is_nan() || duration < 0. {
+                return Err(Error::Type(
+                    "duration must be a non-negative number".to_owned(),
+                ));
+            }
+            Ok(Some(duration))
+        },
+        UnrestrictedDoubleOrString::String(ref duration) => {
+            if &**duration != "auto" {
+                return Err(Error::Type(
+                    "duration must be a number or 'auto'".to_owned(),
+                ));
+            }
+            Ok(None)
+        },
+    }
+}
+
+/// Parse an easing function.
+///
+/// <https://drafts.csswg.org/web-animations/#parsing-an-easing-function>
+pub fn parse_easing(window: &Window, easing: &DOMString) -> Fallible<TimingFunction> {
+    let url = window.Document().url();
+    let context = ParserContext::new_for_cssom(
+        &url,
+        Some(CssRuleType::Style),
+        ParsingMode::DEFAULT,
+        QuirksMode::NoQuirks,
+        None,
+        None,
+    );
+    let mut input = ParserInput::new(&easing);
+    let mut parser = Parser::new(&mut input);
+    parser
+        .parse_entirely(|input| SpecifiedTimingFunction::parse(&context, input))
+        .map(|timing_function| timing_function.to_computed_value_without_context())
+        .map_err(|_| Error::Type(format!("'{}' is not a valid easing", easing)))
+}
+
+// https://drafts.csswg.org/web-animations/#the-animationeffect-interface
+#[dom_struct]
+pub struct AnimationEffect {
+    reflector_: Reflector,
+    timing: DomRefCell<Timing>,
+    animation: MutNullableDom<Animation>,
+}
+
+impl AnimationEffect {
+    pub fn new_inherited(timing: Timing) -> AnimationEffect {
+        AnimationEffect {
+            reflector_: Reflector::new(),
+            timing: DomRefCell::new(timing),
+            animation: Default::default(),
+        }
+    }
+
+    /// The animation this effect is associated with, if any.
+    pub fn animation(&self) -> Option<DomRoot<Animation>> {
+        self.animation.get()
+    }
+
+    pub fn set_animation(&self, animation: Option<&Animation>) {
+        self.animation.set(animation);
+    }
+
+    /// <https://drafts.csswg.org/web-animations/#end-time>
+    pub fn end_time(&self) -> f64 {
+        self.timing.borrow().end_time()
+    }
+
+    /// The iteration duration in seconds.
+    pub fn duration_in_seconds(&self) -> f64 {
+        self.timing.borrow().duration_in_seconds()
+    }
+
+    /// The timing of this effect at the current time of its animation.
+    pub fn computed_timing(&self) -> ComputedTiming {
+        let animation = self.animation.get();
+        let local_time = animation
+            .as_ref()
+            .and_then(|animation| animation.current_time());
+        let playback_rate = animation.map_or(1., |animation| animation.playback_rate());
+        self.timing
+            .borrow()
+            .computed_timing(local_time, playback_rate)
+    }
+}
+
+impl AnimationEffectMethods for AnimationEffect {
+    // https://drafts.csswg.org/web-animations/#dom-animationeffect-gettiming
+    fn GetTiming(&self) -> EffectTiming {
+        self.timing.borrow().to_effect_timing()
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animationeffect-getcomputedtiming
+    fn GetComputedTiming(&self) -> ComputedEffectTiming {
+        let timing = self.timing.borrow();
+        let mut parent = timing.to_effect_timing();
+        // The computed duration of an `auto` iteration duration is zero.
+        parent.duration = Some(UnrestrictedDoubleOrString::UnrestrictedDouble(
+            timing.iteration_duration(),
+        ));
+        // The computed fill mode of `auto` is `none`.
+        if parent.fill == FillMode::Auto {
+            parent.fill = FillMode::None;
+        }
+        drop(timing);
+
+        let computed_timing = self.computed_timing();
+        ComputedEffectTiming {
+            parent,
+            endTime: Some(computed_timing.end_time),
+            activeDuration: Some(computed_timing.active_duration),
+            localTime: Some(computed_timing.local_time.map(Finite::wrap)),
+            progress: Some(computed_timing.progress.map(Finite::wrap)),
+            currentIteration: Some(computed_timing.current_iteration),
+        }
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animationeffect-updatetiming
+    fn UpdateTiming(&self, timing: &OptionalEffectTiming) -> ErrorResult {
+        let global = self.global();
+        self.timing
+            .borrow_mut()
+            .update(global.as_window(), timing)?;
+        if let Some(animation) = self.animation.get() {
+            animation.effect_timing_changed();
+        }
+        Ok(())
+    }
+}
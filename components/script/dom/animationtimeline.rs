@@ -0,0 +1,40 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::AnimationTimelineBinding::AnimationTimelineMethods;
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::num::Finite;
+use crate::dom::bindings::reflector::Reflector;
+use crate::dom::documenttimeline::DocumentTimeline;
+use dom_struct::dom_struct;
+
+// https://drafts.csswg.org/web-animations/#the-animationtimeline-interface
+#[dom_struct]
+pub struct AnimationTimeline {
+    reflector_: Reflector,
+}
+
+impl AnimationTimeline {
+    pub fn new_inherited() -> AnimationTimeline {
+        AnimationTimeline {
+            reflector_: Reflector::new(),
+        }
+    }
+
+    /// The current time of this timeline in milliseconds, or `None` if the
+    /// timeline is inactive.
+    ///
+    /// <https://drafts.csswg.org/web-animations/#timeline-current-time>
+    pub fn current_time(&self) -> Option<f64> {
+        self.downcast::<DocumentTimeline>()
+            .and_then(|timeline| timeline.current_time())
+    }
+}
+
+impl AnimationTimelineMethods for AnimationTimeline {
+    // https://drafts.csswg.org/web-animations/#dom-animationtimeline-currenttime
+    fn GetCurrentTime(&self) -> Option<Finite<f64>> {
+        self.current_time().map(Finite::wrap)
+    }
+}
@@ -31,7 +31,6 @@ use crate::dom::window::Window;
 use crate::realms::InRealm;
 use crate::task_source::TaskSource;
 use dom_struct::dom_struct;
-use msg::constellation_msg::PipelineId;
 use servo_media::audio::context::{LatencyCategory, ProcessingState, RealTimeAudioContextOptions};
 use std::rc::Rc;
 
@@ -48,12 +47,16 @@ pub struct AudioContext {
 impl AudioContext {
     #[allow(unrooted_must_root)]
     // https://webaudio.github.io/web-audio-api/#AudioContext-constructors
-    fn new_inherited(options: &AudioContextOptions, pipeline_id: PipelineId) -> AudioContext {
+    fn new_inherited(window: &Window, options: &AudioContextOptions) -> AudioContext {
+        let pipeline_id = window.pipeline_id();
         // Steps 1-3.
-        let context = BaseAudioContext::new_inherited(
+        let mut context = BaseAudioContext::new_inherited(
             BaseAudioContextOptions::AudioContext(options.into()),
             pipeline_id,
         );
+        if let Some(mixer) = window.audio_output_capture() {
+            context.capture_audio_output(pipeline_id, mixer, window.get_player_context());
+        }
 
         // Step 4.1.
         let latency_hint = match options.latencyHint {
@@ -80,8 +83,7 @@ impl AudioContext {
 
     #[allow(unrooted_must_root)]
     pub fn new(window: &Window, options: &AudioContextOptions) -> DomRoot<AudioContext> {
-        let pipeline_id = window.pipeline_id();
-        let context = AudioContext::new_inherited(options, pipeline_id);
+        let context = AudioContext::new_inherited(window, options);
         let context = reflect_dom_object(Box::new(context), window);
         context.resume();
         context
@@ -0,0 +1,359 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::AudioDataBinding::{
+    AudioDataCopyToOptions, AudioDataInit, AudioDataMethods, AudioSampleFormat,
+};
+use crate::dom::bindings::codegen::UnionTypes::ArrayBufferViewOrArrayBuffer;
+use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
+use crate::dom::bindings::num::Finite;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::bindings::trace::RootedTraceableBox;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::webcodecs;
+use dom_struct::dom_struct;
+use std::sync::Arc;
+
+/// https://w3c.github.io/webcodecs/#audiodata-interface
+#[dom_struct]
+pub struct AudioData {
+    reflector_: Reflector,
+    /// https://w3c.github.io/webcodecs/#dom-audiodata-resource-reference-slot
+    ///
+    /// The samples in `format`, shared with the clones of this object.
+    /// `None` once closed.
+    #[ignore_malloc_size_of = "Arc"]
+    resource: DomRefCell<Option<Arc<Vec<u8>>>>,
+    format: AudioSampleFormat,
+    sample_rate: f32,
+    number_of_frames: u32,
+    number_of_channels: u32,
+    timestamp: i64,
+}
+
+impl AudioData {
+    fn new_inherited(
+        resource: Arc<Vec<u8>>,
+        format: AudioSampleFormat,
+        sample_rate: f32,
+        number_of_frames: u32,
+        number_of_channels: u32,
+        timestamp: i64,
+    ) -> AudioData {
+        AudioData {
+            reflector_: Reflector::new(),
+            resource: DomRefCell::new(Some(resource)),
+            format,
+            sample_rate,
+            number_of_frames,
+            number_of_channels,
+            timestamp,
+        }
+    }
+
+    fn new(
+        global: &GlobalScope,
+        resource: Arc<Vec<u8>>,
+        format: AudioSampleFormat,
+        sample_rate: f32,
+        number_of_frames: u32,
+        number_of_channels: u32,
+        timestamp: i64,
+    ) -> DomRoot<AudioData> {
+        reflect_dom_object(
+            Box::new(AudioData::new_inherited(
+                resource,
+                format,
+                sample_rate,
+                number_of_frames,
+                number_of_channels,
+                timestamp,
+            )),
+            global,
+        )
+    }
+
+    /// Wraps the planar samples produced by a media backend decoder.
+    pub fn new_from_decoded(
+        global: &GlobalScope,
+        sample_rate: f32,
+        number_of_frames: u32,
+        number_of_channels: u32,
+        timestamp: i64,
+        samples: Vec<f32>,
+    ) -> DomRoot<AudioData> {
+        let data = samples
+            .iter()
+            .flat_map(|sample| sample.to_ne_bytes().to_vec())
+            .collect();
+        AudioData::new(
+            global,
+            Arc::new(data),
+            AudioSampleFormat::F32_planar,
+            sample_rate,
+            number_of_frames,
+            number_of_channels,
+            timestamp,
+        )
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-audiodata-audiodata
+    #[allow(non_snake_case)]
+    pub fn Constructor(
+        global: &GlobalScope,
+        init: RootedTraceableBox<AudioDataInit>,
+    ) -> Fallible<DomRoot<AudioData>> {
+        // Step 1.
+        // https://w3c.github.io/webcodecs/#valid-audiodatainit
+        if *init.sampleRate <= 0. {
+            return Err(Error::Type("sampleRate must be positive".to_owned()));
+        }
+        if init.numberOfFrames == 0 || init.numberOfChannels == 0 {
+            return Err(Error::Type(
+                "numberOfFrames and numberOfChannels must not be zero".to_owned(),
+            ));
+        }
+        let size = init.numberOfFrames as usize *
+            init.numberOfChannels as usize *
+            bytes_per_sample(init.format);
+        let mut data = webcodecs::buffer_source_to_vec(&init.data);
+        if data.len() < size {
+            return Err(Error::Type(
+                "The buffer is too small for the given frames".to_owned(),
+            ));
+        }
+        data.truncate(size);
+
+        // Steps 2-4.
+        Ok(AudioData::new(
+            global,
+            Arc::new(data),
+            init.format,
+            *init.sampleRate,
+            init.numberOfFrames,
+            init.numberOfChannels,
+            init.timestamp,
+        ))
+    }
+
+    fn resource(&self) -> Option<Arc<Vec<u8>>> {
+        self.resource.borrow().clone()
+    }
+
+    fn is_closed(&self) -> bool {
+        self.resource.borrow().is_none()
+    }
+
+    /// https://w3c.github.io/webcodecs/#compute-copy-element-count
+    ///
+    /// Returns the format to copy to and the number of frames to copy.
+    fn copy_frame_count(
+        &self,
+        options: &AudioDataCopyToOptions,
+    ) -> Fallible<(AudioSampleFormat, usize)> {
+        // Step 1.
+        let dest_format = options.format.unwrap_or(self.format);
+
+        // Steps 2-3.
+        if is_planar(dest_format) {
+            if options.planeIndex >= self.number_of_channels {
+                return Err(Error::Range("planeIndex is out of range".to_owned()));
+            }
+        } else if options.planeIndex > 0 {
+            return Err(Error::Range("planeIndex is out of range".to_owned()));
+        }
+
+        // Step 4.
+        if dest_format != self.format && dest_format != AudioSampleFormat::F32_planar {
+            return Err(Error::NotSupported);
+        }
+
+        // Steps 5-7.
+        if options.frameOffset >= self.number_of_frames {
+            return Err(Error::Range("frameOffset is out of range".to_owned()));
+        }
+        let mut copy_frame_count = self.number_of_frames - options.frameOffset;
+
+        // Step 8.
+        if let Some(frame_count) = options.frameCount {
+            if frame_count > copy_frame_count {
+                return Err(Error::Range("frameCount is out of range".to_owned()));
+            }
+            copy_frame_count = frame_count;
+        }
+
+        Ok((dest_format, copy_frame_count as usize))
+    }
+
+    /// Returns the value of a single sample, normalized to [-1, 1].
+    fn sample_at(&self, data: &[u8], channel: usize, frame: usize) -> f32 {
+        let index = if is_planar(self.format) {
+            channel * self.number_of_frames as usize + frame
+        } else {
+            frame * self.number_of_channels as usize + channel
+        };
+        let size = bytes_per_sample(self.format);
+        let bytes = &data[index * size..(index + 1) * size];
+        match self.format {
+            AudioSampleFormat::U8 | AudioSampleFormat::U8_planar => (bytes[0] as f32 - 128.) / 128.,
+            AudioSampleFormat::S16 | AudioSampleFormat::S16_planar => {
+                i16::from_ne_bytes([bytes[0], bytes[1]]) as f32 / 32768.
+            },
+            AudioSampleFormat::S32 | AudioSampleFormat::S32_planar => {
+                i32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32 / 2147483648.
+            },
+            AudioSampleFormat::F32 | AudioSampleFormat::F32_planar => {
+                f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+            },
+        }
+    }
+}
+
+impl AudioDataMethods for AudioData {
+    /// https://w3c.github.io/webcodecs/#dom-audiodata-format
+    fn GetFormat(&self) -> Option<AudioSampleFormat> {
+        if self.is_closed() {
+            return None;
+        }
+        Some(self.format)
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-audiodata-samplerate
+    fn SampleRate(&self) -> Finite<f32> {
+        if self.is_closed() {
+            return Finite::wrap(0.);
+        }
+        Finite::wrap(self.sample_rate)
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-audiodata-numberofframes
+    fn NumberOfFrames(&self) -> u32 {
+        if self.is_closed() {
+            return 0;
+        }
+        self.number_of_frames
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-audiodata-numberofchannels
+    fn NumberOfChannels(&self) -> u32 {
+        if self.is_closed() {
+            return 0;
+        }
+        self.number_of_channels
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-audiodata-duration
+    fn Duration(&self) -> u64 {
+        if self.is_closed() {
+            return 0;
+        }
+        (self.number_of_frames as f64 * 1_000_000. / self.sample_rate as f64) as u64
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-audiodata-timestamp
+    fn Timestamp(&self) -> i64 {
+        self.timestamp
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-audiodata-allocationsize
+    fn AllocationSize(&self, options: &AudioDataCopyToOptions) -> Fallible<u32> {
+        // Step 1.
+        if self.is_closed() {
+            return Err(Error::InvalidState);
+        }
+        // Steps 2-5.
+        let (dest_format, frame_count) = self.copy_frame_count(options)?;
+        let mut element_count = frame_count;
+        if !is_planar(dest_format) {
+            element_count *= self.number_of_channels as usize;
+        }
+        Ok((element_count * bytes_per_sample(dest_format)) as u32)
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-audiodata-copyto
+    fn CopyTo(
+        &self,
+        mut destination: ArrayBufferViewOrArrayBuffer,
+        options: &AudioDataCopyToOptions,
+    ) -> ErrorResult {
+        // Step 1.
+        let data = self.resource().ok_or(Error::InvalidState)?;
+
+        // Steps 2-4.
+        let (dest_format, frame_count) = self.copy_frame_count(options)?;
+        let frame_offset = options.frameOffset as usize;
+        let plane = options.planeIndex as usize;
+
+        // Step 5.
+        let copy = if dest_format == self.format {
+            let size = bytes_per_sample(self.format);
+            let (start, length) = if is_planar(self.format) {
+                (
+                    (plane * self.number_of_frames as usize + frame_offset) * size,
+                    frame_count * size,
+                )
+            } else {
+                let channels = self.number_of_channels as usize;
+                (
+                    frame_offset * channels * size,
+                    frame_count * channels * size,
+                )
+            };
+            data[start..start + length].to_vec()
+        } else {
+            // Only the conversion to f32-planar is supported.
+            (frame_offset..frame_offset + frame_count)
+                .flat_map(|frame| self.sample_at(&data, plane, frame).to_ne_bytes().to_vec())
+                .collect()
+        };
+        webcodecs::copy_to_buffer_source(&mut destination, &copy)
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-audiodata-clone
+    fn Clone(&self) -> Fallible<DomRoot<AudioData>> {
+        // Step 1.
+        let resource = self.resource().ok_or(Error::InvalidState)?;
+        // Step 2.
+        Ok(AudioData::new(
+            &self.global(),
+            resource,
+            self.format,
+            self.sample_rate,
+            self.number_of_frames,
+            self.number_of_channels,
+            self.timestamp,
+        ))
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-audiodata-close
+    fn Close(&self) {
+        self.resource.borrow_mut().take();
+    }
+}
+
+fn is_planar(format: AudioSampleFormat) -> bool {
+    match format {
+        AudioSampleFormat::U8_planar |
+        AudioSampleFormat::S16_planar |
+        AudioSampleFormat::S32_planar |
+        AudioSampleFormat::F32_planar => true,
+        AudioSampleFormat::U8 |
+        AudioSampleFormat::S16 |
+        AudioSampleFormat::S32 |
+        AudioSampleFormat::F32 => false,
+    }
+}
+
+fn bytes_per_sample(format: AudioSampleFormat) -> usize {
+    match format {
+        AudioSampleFormat::U8 | AudioSampleFormat::U8_planar => 1,
+        AudioSampleFormat::S16 | AudioSampleFormat::S16_planar => 2,
+        AudioSampleFormat::S32 |
+        AudioSampleFormat::S32_planar |
+        AudioSampleFormat::F32 |
+        AudioSampleFormat::F32_planar => 4,
+    }
+}
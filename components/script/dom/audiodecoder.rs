@@ -0,0 +1,376 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::audiodata::AudioData;
+use crate::dom::bindings::callback::ExceptionHandling;
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::AudioDecoderBinding::{
+    AudioDataOutputCallback, AudioDecoderConfig, AudioDecoderInit, AudioDecoderMethods,
+    AudioDecoderSupport,
+};
+use crate::dom::bindings::codegen::Bindings::WebCodecsBinding::{
+    CodecState, WebCodecsErrorCallback,
+};
+use crate::dom::bindings::error::{Error, ErrorResult};
+use crate::dom::bindings::refcounted::Trusted;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::bindings::trace::RootedTraceableBox;
+use crate::dom::domexception::{DOMErrorName, DOMException};
+use crate::dom::encodedaudiochunk::EncodedAudioChunk;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::webcodecs;
+use crate::task_source::{TaskSource, TaskSourceName};
+use dom_struct::dom_struct;
+use ipc_channel::ipc;
+use ipc_channel::router::ROUTER;
+use media::codecs::{self, CodecKind, Decoder, DecoderEvent, DecoderSettings};
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+/// https://w3c.github.io/webcodecs/#audiodecoder-interface
+#[dom_struct]
+pub struct AudioDecoder {
+    reflector_: Reflector,
+    /// https://w3c.github.io/webcodecs/#dom-audiodecoder-output-callback-slot
+    #[ignore_malloc_size_of = "Rc"]
+    output_callback: Rc<AudioDataOutputCallback>,
+    /// https://w3c.github.io/webcodecs/#dom-audiodecoder-error-callback-slot
+    #[ignore_malloc_size_of = "Rc"]
+    error_callback: Rc<WebCodecsErrorCallback>,
+    /// https://w3c.github.io/webcodecs/#dom-audiodecoder-state
+    state: Cell<CodecState>,
+    /// https://w3c.github.io/webcodecs/#dom-audiodecoder-decodequeuesize
+    decode_queue_size: Cell<u32>,
+    /// https://w3c.github.io/webcodecs/#dom-audiodecoder-key-chunk-required-slot
+    key_chunk_required: Cell<bool>,
+    /// https://w3c.github.io/webcodecs/#dom-audiodecoder-pending-flush-promises-slot
+    #[ignore_malloc_size_of = "promises are hard"]
+    pending_flush_promises: DomRefCell<VecDeque<Rc<Promise>>>,
+    /// Bumped whenever the decoder is reset, so that the queued control
+    /// messages and the backend events of the previous configuration are
+    /// dropped.
+    generation: Cell<u32>,
+    #[ignore_malloc_size_of = "Arc"]
+    decoder: DomRefCell<Option<Arc<Mutex<dyn Decoder>>>>,
+}
+
+impl AudioDecoder {
+    fn new_inherited(init: &AudioDecoderInit) -> AudioDecoder {
+        AudioDecoder {
+            reflector_: Reflector::new(),
+            output_callback: init.output.clone(),
+            error_callback: init.error.clone(),
+            state: Cell::new(CodecState::Unconfigured),
+            decode_queue_size: Cell::new(0),
+            key_chunk_required: Cell::new(true),
+            pending_flush_promises: DomRefCell::new(VecDeque::new()),
+            generation: Cell::new(0),
+            decoder: DomRefCell::new(None),
+        }
+    }
+
+    fn new(global: &GlobalScope, init: &AudioDecoderInit) -> DomRoot<AudioDecoder> {
+        reflect_dom_object(Box::new(AudioDecoder::new_inherited(init)), global)
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-audiodecoder-audiodecoder
+    #[allow(non_snake_case)]
+    pub fn Constructor(global: &GlobalScope, init: &AudioDecoderInit) -> DomRoot<AudioDecoder> {
+        AudioDecoder::new(global, init)
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-audiodecoder-isconfigsupported
+    #[allow(non_snake_case)]
+    pub fn IsConfigSupported(
+        global: &GlobalScope,
+        config: RootedTraceableBox<AudioDecoderConfig>,
+    ) -> Rc<Promise> {
+        let promise = Promise::new(global);
+
+        // Step 1.
+        if !is_valid_config(&config) {
+            promise.reject_error(Error::Type("Invalid AudioDecoderConfig".to_owned()));
+            return promise;
+        }
+
+        // Steps 2-3.
+        let supported = codecs::supports_codec(&config.codec, CodecKind::AudioDecoder);
+        // The description is not echoed back, it is only meaningful to the
+        // decoder which was configured with it.
+        let echoed_config = RootedTraceableBox::new(AudioDecoderConfig {
+            codec: config.codec.clone(),
+            sampleRate: config.sampleRate,
+            numberOfChannels: config.numberOfChannels,
+            description: None,
+        });
+        let mut support = AudioDecoderSupport::empty();
+        support.supported = Some(supported);
+        support.config = Some(echoed_config);
+        promise.resolve_native(&*support);
+        promise
+    }
+
+    fn create_decoder(&self, settings: DecoderSettings) -> Result<Arc<Mutex<dyn Decoder>>, ()> {
+        let global = self.global();
+        let (sender, receiver) = ipc::channel::<DecoderEvent>().unwrap();
+
+        let decoder = codecs::create_decoder(settings, sender)
+            .map_err(|error| warn!("Could not create audio decoder {:?}", error))?;
+
+        let this = Trusted::new(self);
+        let generation = self.generation.get();
+        let task_source = global.dom_manipulation_task_source();
+        let canceller = global.task_canceller(TaskSourceName::DOMManipulation);
+        ROUTER.add_route(
+            receiver.to_opaque(),
+            Box::new(move |message| {
+                let event = message.to().unwrap();
+                let this = this.clone();
+                let _ = task_source.queue_with_canceller(
+                    task!(handle_audio_decoder_event: move || {
+                        this.root().handle_decoder_event(generation, event);
+                    }),
+                    &canceller,
+                );
+            }),
+        );
+
+        Ok(decoder)
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-audiodecoder-configure
+    /// Step 5, run as a control message.
+    fn configure_backend(&self, supported: bool, settings: DecoderSettings) {
+        if let Some(decoder) = self.decoder.borrow_mut().take() {
+            decoder.lock().unwrap().close();
+        }
+        // Step 5.1.
+        if !supported {
+            return self.close_with_error(DOMErrorName::NotSupportedError);
+        }
+        // Steps 5.2-5.3.
+        match self.create_decoder(settings) {
+            Ok(decoder) => *self.decoder.borrow_mut() = Some(decoder),
+            Err(()) => self.close_with_error(DOMErrorName::NotSupportedError),
+        }
+    }
+
+    fn handle_decoder_event(&self, generation: u32, event: DecoderEvent) {
+        if generation != self.generation.get() {
+            return;
+        }
+        match event {
+            DecoderEvent::AudioData {
+                sample_rate,
+                number_of_channels,
+                number_of_frames,
+                timestamp,
+                data,
+            } => {
+                // https://w3c.github.io/webcodecs/#output-audiodata
+                let data = AudioData::new_from_decoded(
+                    &self.global(),
+                    sample_rate,
+                    number_of_frames,
+                    number_of_channels,
+                    timestamp,
+                    data,
+                );
+                let _ = self
+                    .output_callback
+                    .Call__(&data, ExceptionHandling::Report);
+            },
+            DecoderEvent::VideoFrame(_) => {
+                warn!("Audio decoder produced a video frame");
+            },
+            DecoderEvent::Flushed => {
+                // https://w3c.github.io/webcodecs/#dom-audiodecoder-flush
+                // Step 5.
+                if let Some(promise) = self.pending_flush_promises.borrow_mut().pop_front() {
+                    promise.resolve_native(&());
+                }
+            },
+            DecoderEvent::Error(message) => {
+                warn!("Audio decoding failed: {}", message);
+                self.close_with_error(DOMErrorName::EncodingError);
+            },
+        }
+    }
+
+    /// Queues a task to run `f` once the preceding control messages ran,
+    /// unless the decoder is reset in the meantime.
+    /// https://w3c.github.io/webcodecs/#enqueues-a-control-message
+    fn queue_control_message<F>(&self, f: F)
+    where
+        F: FnOnce(&AudioDecoder) + Send + 'static,
+    {
+        let global = self.global();
+        let this = Trusted::new(self);
+        let generation = self.generation.get();
+        let _ = global.dom_manipulation_task_source().queue(
+            task!(audio_decoder_control_message: move || {
+                let this = this.root();
+                if this.generation.get() == generation {
+                    f(&*this);
+                }
+            }),
+            &global,
+        );
+    }
+
+    /// https://w3c.github.io/webcodecs/#reset-audiodecoder
+    fn reset(&self, exception: DOMErrorName) -> ErrorResult {
+        // Step 1.
+        if self.state.get() == CodecState::Closed {
+            return Err(Error::InvalidState);
+        }
+        // Steps 2-6.
+        self.state.set(CodecState::Unconfigured);
+        if let Some(decoder) = self.decoder.borrow_mut().take() {
+            decoder.lock().unwrap().close();
+        }
+        self.generation.set(self.generation.get() + 1);
+        self.key_chunk_required.set(true);
+        self.decode_queue_size.set(0);
+        // Step 7.
+        let global = self.global();
+        for promise in self.pending_flush_promises.borrow_mut().drain(..) {
+            promise.reject_native(&DOMException::new(&global, exception));
+        }
+        Ok(())
+    }
+
+    /// https://w3c.github.io/webcodecs/#close-audiodecoder
+    fn close_with_error(&self, exception: DOMErrorName) {
+        // Step 1.
+        if self.reset(exception).is_err() {
+            return;
+        }
+        // Step 2.
+        self.state.set(CodecState::Closed);
+        // Step 4.
+        if exception != DOMErrorName::AbortError {
+            let error = DOMException::new(&self.global(), exception);
+            let _ = self
+                .error_callback
+                .Call__(&error, ExceptionHandling::Report);
+        }
+    }
+}
+
+impl AudioDecoderMethods for AudioDecoder {
+    /// https://w3c.github.io/webcodecs/#dom-audiodecoder-state
+    fn State(&self) -> CodecState {
+        self.state.get()
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-audiodecoder-decodequeuesize
+    fn DecodeQueueSize(&self) -> u32 {
+        self.decode_queue_size.get()
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-audiodecoder-configure
+    fn Configure(&self, config: RootedTraceableBox<AudioDecoderConfig>) -> ErrorResult {
+        // Step 1.
+        if !is_valid_config(&config) {
+            return Err(Error::Type("Invalid AudioDecoderConfig".to_owned()));
+        }
+        // Step 2.
+        if self.state.get() == CodecState::Closed {
+            return Err(Error::InvalidState);
+        }
+        // Steps 3-4.
+        self.state.set(CodecState::Configured);
+        self.key_chunk_required.set(true);
+
+        // Step 5.
+        let settings = DecoderSettings::Audio {
+            codec: config.codec.to_string(),
+            description: config
+                .description
+                .as_ref()
+                .map(webcodecs::buffer_source_to_vec),
+            sample_rate: config.sampleRate,
+            number_of_channels: config.numberOfChannels,
+        };
+        let supported = codecs::supports_codec(&config.codec, CodecKind::AudioDecoder);
+        self.queue_control_message(move |decoder| decoder.configure_backend(supported, settings));
+        Ok(())
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-audiodecoder-decode
+    fn Decode(&self, chunk: &EncodedAudioChunk) -> ErrorResult {
+        // Step 1.
+        if self.state.get() != CodecState::Configured {
+            return Err(Error::InvalidState);
+        }
+        // Step 2.
+        if self.key_chunk_required.get() {
+            if !chunk.is_key() {
+                return Err(Error::Data);
+            }
+            self.key_chunk_required.set(false);
+        }
+        // Step 3.
+        self.decode_queue_size.set(self.decode_queue_size.get() + 1);
+        // Step 4.
+        let chunk = chunk.to_backend_chunk();
+        self.queue_control_message(move |decoder| {
+            decoder
+                .decode_queue_size
+                .set(decoder.decode_queue_size.get() - 1);
+            if let Some(ref backend) = *decoder.decoder.borrow() {
+                backend.lock().unwrap().decode(chunk);
+            }
+        });
+        Ok(())
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-audiodecoder-flush
+    fn Flush(&self) -> Rc<Promise> {
+        let promise = Promise::new(&self.global());
+        // Step 1.
+        if self.state.get() != CodecState::Configured {
+            promise.reject_error(Error::InvalidState);
+            return promise;
+        }
+        // Steps 2-4.
+        self.key_chunk_required.set(true);
+        self.pending_flush_promises
+            .borrow_mut()
+            .push_back(promise.clone());
+        self.queue_control_message(|decoder| {
+            if let Some(ref backend) = *decoder.decoder.borrow() {
+                backend.lock().unwrap().flush();
+            }
+        });
+        promise
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-audiodecoder-reset
+    fn Reset(&self) -> ErrorResult {
+        self.reset(DOMErrorName::AbortError)
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-audiodecoder-close
+    fn Close(&self) -> ErrorResult {
+        if self.state.get() == CodecState::Closed {
+            return Err(Error::InvalidState);
+        }
+        self.close_with_error(DOMErrorName::AbortError);
+        Ok(())
+    }
+}
+
+/// https://w3c.github.io/webcodecs/#valid-audiodecoderconfig
+fn is_valid_config(config: &AudioDecoderConfig) -> bool {
+    // Steps 1-3.
+    webcodecs::is_valid_codec_string(&config.codec) &&
+        config.sampleRate != 0 &&
+        config.numberOfChannels != 0
+}
@@ -273,6 +273,12 @@ impl AudioNodeMethods for AudioNode {
                     return Err(Error::NotSupported);
                 }
             },
+            EventTargetTypeId::AudioNode(AudioNodeTypeId::ConvolverNode) |
+            EventTargetTypeId::AudioNode(AudioNodeTypeId::DynamicsCompressorNode) => {
+                if value > 2 {
+                    return Err(Error::NotSupported);
+                }
+            },
             EventTargetTypeId::AudioNode(AudioNodeTypeId::ChannelMergerNode) => {
                 return Err(Error::InvalidState);
             },
@@ -324,6 +330,12 @@ impl AudioNodeMethods for AudioNode {
                     return Err(Error::NotSupported);
                 }
             },
+            EventTargetTypeId::AudioNode(AudioNodeTypeId::ConvolverNode) |
+            EventTargetTypeId::AudioNode(AudioNodeTypeId::DynamicsCompressorNode) => {
+                if value == ChannelCountMode::Max {
+                    return Err(Error::NotSupported);
+                }
+            },
             EventTargetTypeId::AudioNode(AudioNodeTypeId::ChannelMergerNode) => {
                 return Err(Error::InvalidState);
             },
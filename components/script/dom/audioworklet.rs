@@ -0,0 +1,52 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::audioworkletglobalscope::ParamDescriptor;
+use crate::dom::bindings::reflector::reflect_dom_object;
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::window::Window;
+use crate::dom::worklet::{Worklet, WorkletExecutor, WorkletId};
+use crate::dom::workletglobalscope::WorkletGlobalScopeType;
+use crate::script_thread::ScriptThread;
+use dom_struct::dom_struct;
+
+/// <https://webaudio.github.io/web-audio-api/#audioworklet>
+#[dom_struct]
+pub struct AudioWorklet {
+    worklet: Worklet,
+}
+
+impl AudioWorklet {
+    fn new_inherited(window: &Window) -> AudioWorklet {
+        AudioWorklet {
+            worklet: Worklet::new_inherited(window, WorkletGlobalScopeType::Audio),
+        }
+    }
+
+    pub fn new(window: &Window, sample_rate: f32) -> DomRoot<AudioWorklet> {
+        let worklet = reflect_dom_object(Box::new(AudioWorklet::new_inherited(window)), window);
+        // The worklet global scopes are created when the first module is
+        // added, and read the sample rate of their context from the registry.
+        ScriptThread::worklet_thread_pool()
+            .audio_worklet_registry()
+            .add_worklet(worklet.worklet_id(), sample_rate);
+        worklet
+    }
+
+    pub fn worklet_id(&self) -> WorkletId {
+        self.worklet.worklet_id()
+    }
+
+    /// An executor to schedule tasks on the global scopes of this worklet.
+    pub fn executor(&self) -> WorkletExecutor {
+        ScriptThread::worklet_thread_pool().executor(self.worklet_id())
+    }
+
+    /// <https://webaudio.github.io/web-audio-api/#dom-baseaudiocontext-node-name-to-parameter-descriptor-map>
+    pub fn processor_parameter_descriptors(&self, name: &str) -> Option<Vec<ParamDescriptor>> {
+        ScriptThread::worklet_thread_pool()
+            .audio_worklet_registry()
+            .processor(self.worklet_id(), name)
+    }
+}
@@ -0,0 +1,629 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::callback::CallbackContainer;
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::AudioParamBinding::AutomationRate;
+use crate::dom::bindings::codegen::Bindings::AudioWorkletGlobalScopeBinding;
+use crate::dom::bindings::codegen::Bindings::AudioWorkletGlobalScopeBinding::AudioParamDescriptor;
+use crate::dom::bindings::codegen::Bindings::AudioWorkletGlobalScopeBinding::AudioWorkletGlobalScopeMethods;
+use crate::dom::bindings::codegen::Bindings::VoidFunctionBinding::VoidFunction;
+use crate::dom::bindings::conversions::{get_property, get_property_jsval};
+use crate::dom::bindings::error::{report_pending_exception, Error, Fallible};
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::num::Finite;
+use crate::dom::bindings::reflector::DomObject;
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::bindings::str::DOMString;
+use crate::dom::bindings::structuredclone;
+use crate::dom::bindings::utils::set_dictionary_property;
+use crate::dom::worklet::{WorkletExecutor, WorkletId};
+use crate::dom::workletglobalscope::WorkletGlobalScope;
+use crate::dom::workletglobalscope::WorkletGlobalScopeInit;
+use crate::realms::{AlreadyInRealm, InRealm};
+use crate::script_runtime::JSContext;
+use crossbeam_channel::Sender;
+use dom_struct::dom_struct;
+use js::jsapi::{HandleValueArray, Heap, IsCallable, IsConstructor, JSAutoRealm, JSObject};
+use js::jsapi::{JS_ClearPendingException, JS_DefineElement, JS_IsExceptionPending};
+use js::jsapi::{JS_NewPlainObject, NewArrayObject1, JSPROP_ENUMERATE};
+use js::jsval::{JSVal, ObjectValue, UndefinedValue};
+use js::rust::jsapi_wrapped::{JS_GetArrayLength, JS_GetElement};
+use js::rust::wrappers::{Call, Construct1, JS_SetPrototype};
+use js::rust::{HandleValue, MutableHandleValue, Runtime, ToBoolean};
+use js::typedarray::{CreateWith, Float32Array};
+use msg::constellation_msg::PipelineId;
+use parking_lot::Mutex;
+use script_traits::StructuredSerializedData;
+use servo_media::audio::worklet_node::ProcessQuantum;
+use servo_url::ServoUrl;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::ptr;
+use std::rc::Rc;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// <https://webaudio.github.io/web-audio-api/#audioworkletglobalscope>
+#[dom_struct]
+pub struct AudioWorkletGlobalScope {
+    /// The worklet global for this object
+    worklet_global: WorkletGlobalScope,
+    /// The processors registered by the global scopes of this worklet
+    #[ignore_malloc_size_of = "Arc"]
+    registry: AudioWorkletRegistry,
+    worklet_id: WorkletId,
+    /// <https://webaudio.github.io/web-audio-api/#dom-audioworkletglobalscope-samplerate>
+    sample_rate: f32,
+    /// <https://webaudio.github.io/web-audio-api/#dom-audioworkletglobalscope-currentframe>
+    current_frame: Cell<u64>,
+    /// <https://webaudio.github.io/web-audio-api/#node-name-to-processor-constructor-map>
+    processor_definitions: DomRefCell<HashMap<String, Box<ProcessorDefinition>>>,
+    /// The processor instances created in this global, keyed by the id
+    /// shared with their AudioWorkletNode.
+    #[ignore_malloc_size_of = "mozjs"]
+    processors: DomRefCell<HashMap<AudioWorkletProcessorId, Box<Heap<JSVal>>>>,
+    /// <https://webaudio.github.io/web-audio-api/#pending-processor-construction-data>
+    processor_construction_pending: Cell<bool>,
+}
+
+impl AudioWorkletGlobalScope {
+    #[allow(unsafe_code)]
+    pub fn new(
+        runtime: &Runtime,
+        pipeline_id: PipelineId,
+        base_url: ServoUrl,
+        executor: WorkletExecutor,
+        init: &WorkletGlobalScopeInit,
+    ) -> DomRoot<AudioWorkletGlobalScope> {
+        debug!(
+            "Creating audio worklet global scope for pipeline {}.",
+            pipeline_id
+        );
+        let worklet_id = executor.worklet_id();
+        let registry = init.audio_worklet_registry.clone();
+        let sample_rate = registry.sample_rate(worklet_id);
+        let global = Box::new(AudioWorkletGlobalScope {
+            worklet_global: WorkletGlobalScope::new_inherited(
+                pipeline_id,
+                base_url,
+                executor,
+                init,
+            ),
+            registry,
+            worklet_id,
+            sample_rate,
+            current_frame: Cell::new(0),
+            processor_definitions: Default::default(),
+            processors: Default::default(),
+            processor_construction_pending: Cell::new(false),
+        });
+        unsafe { AudioWorkletGlobalScopeBinding::Wrap(JSContext::from_ptr(runtime.cx()), global) }
+    }
+
+    pub fn perform_a_worklet_task(&self, task: AudioWorkletTask) {
+        match task {
+            AudioWorkletTask::Process {
+                processor,
+                quantum,
+                sender,
+            } => {
+                let result = self.process(&processor, quantum);
+                let _ = sender.send(result);
+            },
+            AudioWorkletTask::DestroyProcessor(id) => {
+                self.processors.borrow_mut().remove(&id);
+            },
+        }
+    }
+
+    /// Consume the pending processor construction data, returning whether
+    /// there was any.
+    pub fn take_processor_construction_pending(&self) -> bool {
+        self.processor_construction_pending.replace(false)
+    }
+
+    /// Run one render quantum through a processor, constructing the
+    /// processor first if this global doesn't have an instance yet.
+    #[allow(unsafe_code)]
+    fn process(
+        &self,
+        init: &AudioWorkletProcessorInit,
+        mut quantum: ProcessQuantum,
+    ) -> ProcessResult {
+        self.current_frame.set(quantum.current_frame);
+
+        let cx = self.worklet_global.get_cx();
+        let _ac = JSAutoRealm::new(*cx, self.reflector().get_jsobject().get());
+
+        rooted!(in(*cx) let mut processor = UndefinedValue());
+        if self.processor(init, processor.handle_mut()).is_err() {
+            return ProcessResult::failed(quantum, &init.name);
+        }
+        rooted!(in(*cx) let processor_object = processor.to_object());
+
+        // https://webaudio.github.io/web-audio-api/#rendering-loop
+        // The process function is looked up on every call.
+        rooted!(in(*cx) let mut process_function = UndefinedValue());
+        let callable = unsafe {
+            get_property_jsval(
+                *cx,
+                processor_object.handle(),
+                "process",
+                process_function.handle_mut(),
+            )
+            .is_ok() &&
+                process_function.is_object() &&
+                IsCallable(process_function.to_object())
+        };
+        if !callable {
+            debug!("Audio worklet processor {} has no process().", init.name);
+            unsafe { JS_ClearPendingException(*cx) };
+            return ProcessResult::failed(quantum, &init.name);
+        }
+
+        rooted!(in(*cx) let mut inputs = UndefinedValue());
+        rooted!(in(*cx) let mut outputs = UndefinedValue());
+        rooted!(in(*cx) let mut parameters = UndefinedValue());
+        let parameter_names = match self.processor_definitions.borrow().get(&init.name) {
+            Some(definition) => definition.parameter_names.clone(),
+            None => vec![],
+        };
+        unsafe {
+            ports_to_js(cx, &quantum.inputs, inputs.handle_mut());
+            ports_to_js(cx, &quantum.outputs, outputs.handle_mut());
+            rooted!(in(*cx) let object = JS_NewPlainObject(*cx));
+            for (name, values) in parameter_names.iter().zip(quantum.parameters.iter()) {
+                rooted!(in(*cx) let mut array = ptr::null_mut::<JSObject>());
+                let _ = Float32Array::create(*cx, CreateWith::Slice(values), array.handle_mut());
+                rooted!(in(*cx) let value = ObjectValue(array.get()));
+                let _ = set_dictionary_property(*cx, object.handle(), name, value.handle());
+            }
+            parameters.set(ObjectValue(object.get()));
+        }
+
+        let args_slice = [inputs.get(), outputs.get(), parameters.get()];
+        let args = unsafe { HandleValueArray::from_rooted_slice(&args_slice) };
+        rooted!(in(*cx) let mut result = UndefinedValue());
+        unsafe {
+            Call(
+                *cx,
+                processor.handle(),
+                process_function.handle(),
+                &args,
+                result.handle_mut(),
+            );
+        }
+
+        if unsafe { JS_IsExceptionPending(*cx) } {
+            debug!("Audio worklet processor {} threw an exception.", init.name);
+            self.report_exception(cx);
+            self.processors.borrow_mut().remove(&init.id);
+            return ProcessResult::failed(quantum, &init.name);
+        }
+
+        let keep_alive = unsafe { ToBoolean(result.handle()) };
+        unsafe { read_ports_from_js(cx, outputs.handle(), &mut quantum.outputs) };
+        ProcessResult {
+            quantum,
+            keep_alive,
+            error: None,
+        }
+    }
+
+    /// Get the processor instance for `init`, constructing it if needed.
+    /// <https://webaudio.github.io/web-audio-api/#instantiation-of-AudioWorkletNode-and-AudioWorkletProcessor>
+    #[allow(unsafe_code)]
+    fn processor(
+        &self,
+        init: &AudioWorkletProcessorInit,
+        mut rval: MutableHandleValue,
+    ) -> Result<(), ()> {
+        if let Some(processor) = self.processors.borrow().get(&init.id) {
+            rval.set(processor.get());
+            return Ok(());
+        }
+
+        let cx = self.worklet_global.get_cx();
+        rooted!(in(*cx) let mut constructor = UndefinedValue());
+        match self.processor_definitions.borrow().get(&init.name) {
+            Some(definition) => constructor.set(definition.constructor.get()),
+            None => {
+                warn!(
+                    "Constructing unregistered audio worklet processor {}.",
+                    init.name
+                );
+                return Err(());
+            },
+        }
+
+        // Step 1-2.
+        rooted!(in(*cx) let mut options = UndefinedValue());
+        structuredclone::read(
+            self.upcast(),
+            init.options.clone_for_broadcast(),
+            options.handle_mut(),
+        )?;
+
+        // Steps 3-5.
+        self.processor_construction_pending.set(true);
+        let args_slice = [options.get()];
+        let args = unsafe { HandleValueArray::from_rooted_slice(&args_slice) };
+        rooted!(in(*cx) let mut instance = ptr::null_mut::<JSObject>());
+        unsafe {
+            Construct1(*cx, constructor.handle(), &args, instance.handle_mut());
+        }
+        self.processor_construction_pending.set(false);
+        if unsafe { JS_IsExceptionPending(*cx) } || instance.is_null() {
+            debug!("Audio worklet processor {} constructor threw.", init.name);
+            self.report_exception(cx);
+            return Err(());
+        }
+
+        // The bindings don't pass new.target to interface constructors, so
+        // the instance was created with the AudioWorkletProcessor prototype
+        // rather than the one of the registered subclass.
+        rooted!(in(*cx) let mut prototype = UndefinedValue());
+        rooted!(in(*cx) let constructor_object = constructor.to_object());
+        unsafe {
+            if get_property_jsval(
+                *cx,
+                constructor_object.handle(),
+                "prototype",
+                prototype.handle_mut(),
+            )
+            .is_ok() &&
+                prototype.is_object()
+            {
+                rooted!(in(*cx) let prototype = prototype.to_object());
+                JS_SetPrototype(*cx, instance.handle(), prototype.handle());
+            }
+        }
+
+        rval.set(ObjectValue(instance.get()));
+        let heap = Box::new(Heap::default());
+        heap.set(rval.get());
+        self.processors.borrow_mut().insert(init.id, heap);
+        Ok(())
+    }
+
+    #[allow(unsafe_code)]
+    fn report_exception(&self, cx: JSContext) {
+        let in_realm_proof = AlreadyInRealm::assert_for_cx(cx);
+        unsafe {
+            report_pending_exception(*cx, false, InRealm::Already(&in_realm_proof));
+        }
+    }
+}
+
+impl AudioWorkletGlobalScopeMethods for AudioWorkletGlobalScope {
+    #[allow(unsafe_code)]
+    #[allow(unrooted_must_root)]
+    /// <https://webaudio.github.io/web-audio-api/#dom-audioworkletglobalscope-registerprocessor>
+    fn RegisterProcessor(&self, name: DOMString, processor_ctor: Rc<VoidFunction>) -> Fallible<()> {
+        let name = String::from(name);
+        let cx = self.worklet_global.get_cx();
+        rooted!(in(*cx) let ctor_obj = processor_ctor.callback_holder().get());
+        rooted!(in(*cx) let ctor_val = ObjectValue(ctor_obj.get()));
+
+        debug!("Registering audio worklet processor {}.", name);
+
+        // Step 1.
+        if name.is_empty() {
+            return Err(Error::NotSupported);
+        }
+
+        // Step 2.
+        if self.processor_definitions.borrow().contains_key(&name) {
+            return Err(Error::NotSupported);
+        }
+
+        // Step 3.
+        if unsafe { !IsConstructor(ctor_obj.get()) } {
+            return Err(Error::Type(String::from("Not a constructor.")));
+        }
+
+        // Steps 4-5.
+        rooted!(in(*cx) let mut prototype = UndefinedValue());
+        unsafe {
+            get_property_jsval(*cx, ctor_obj.handle(), "prototype", prototype.handle_mut())?;
+        }
+        if !prototype.is_object() {
+            return Err(Error::Type(String::from("Prototype is not an object.")));
+        }
+
+        // Step 6.
+        let descriptors: Vec<AudioParamDescriptor> =
+            unsafe { get_property(*cx, ctor_obj.handle(), "parameterDescriptors", ()) }?
+                .unwrap_or_default();
+
+        // Step 7.
+        let mut parameters: Vec<ParamDescriptor> = Vec::with_capacity(descriptors.len());
+        for descriptor in &descriptors {
+            let name = String::from(descriptor.name.clone());
+            if parameters.iter().any(|parameter| parameter.name == name) {
+                return Err(Error::NotSupported);
+            }
+            let (default, min, max) = (
+                *descriptor.defaultValue,
+                *descriptor.minValue,
+                *descriptor.maxValue,
+            );
+            if default < min || default > max {
+                return Err(Error::InvalidState);
+            }
+            parameters.push(ParamDescriptor {
+                name,
+                default_value: default,
+                min_value: min,
+                max_value: max,
+                automation_rate: descriptor.automationRate,
+            });
+        }
+
+        // Step 8.
+        let definition = ProcessorDefinition::new(
+            ctor_val.handle(),
+            parameters
+                .iter()
+                .map(|parameter| parameter.name.clone())
+                .collect(),
+        );
+        self.processor_definitions
+            .borrow_mut()
+            .insert(name.clone(), definition);
+
+        // Step 9. Every worklet thread evaluates the module, so the
+        // registration may already be known to the script thread.
+        self.registry
+            .register_processor(self.worklet_id, name, parameters);
+
+        Ok(())
+    }
+
+    /// <https://webaudio.github.io/web-audio-api/#dom-audioworkletglobalscope-currentframe>
+    fn CurrentFrame(&self) -> u64 {
+        self.current_frame.get()
+    }
+
+    /// <https://webaudio.github.io/web-audio-api/#dom-audioworkletglobalscope-currenttime>
+    fn CurrentTime(&self) -> Finite<f64> {
+        Finite::wrap(self.current_frame.get() as f64 / self.sample_rate as f64)
+    }
+
+    /// <https://webaudio.github.io/web-audio-api/#dom-audioworkletglobalscope-samplerate>
+    fn SampleRate(&self) -> Finite<f32> {
+        Finite::wrap(self.sample_rate)
+    }
+}
+
+/// Create a JS array with, for each input or output, an array of one
+/// Float32Array per channel.
+#[allow(unsafe_code)]
+unsafe fn ports_to_js(cx: JSContext, ports: &[Vec<Vec<f32>>], mut rval: MutableHandleValue) {
+    rooted!(in(*cx) let js_ports = NewArrayObject1(*cx, ports.len()));
+    for (index, channels) in ports.iter().enumerate() {
+        rooted!(in(*cx) let js_channels = NewArrayObject1(*cx, channels.len()));
+        for (channel_index, channel) in channels.iter().enumerate() {
+            rooted!(in(*cx) let mut array = ptr::null_mut::<JSObject>());
+            let _ = Float32Array::create(*cx, CreateWith::Slice(channel), array.handle_mut());
+            rooted!(in(*cx) let value = ObjectValue(array.get()));
+            JS_DefineElement(
+                *cx,
+                js_channels.handle().into(),
+                channel_index as u32,
+                value.handle().into(),
+                JSPROP_ENUMERATE as u32,
+            );
+        }
+        rooted!(in(*cx) let value = ObjectValue(js_channels.get()));
+        JS_DefineElement(
+            *cx,
+            js_ports.handle().into(),
+            index as u32,
+            value.handle().into(),
+            JSPROP_ENUMERATE as u32,
+        );
+    }
+    rval.set(ObjectValue(js_ports.get()));
+}
+
+/// Copy the samples written by `process()` back out of the JS outputs.
+/// Channels that were replaced by something other than a Float32Array of
+/// the right length are left silent.
+#[allow(unsafe_code)]
+unsafe fn read_ports_from_js(cx: JSContext, js_ports: HandleValue, ports: &mut [Vec<Vec<f32>>]) {
+    rooted!(in(*cx) let js_ports = js_ports.to_object());
+    for (index, channels) in ports.iter_mut().enumerate() {
+        for channel in channels.iter_mut() {
+            for sample in channel.iter_mut() {
+                *sample = 0.;
+            }
+        }
+        rooted!(in(*cx) let mut js_channels = UndefinedValue());
+        if !JS_GetElement(
+            *cx,
+            js_ports.handle(),
+            index as u32,
+            &mut js_channels.handle_mut(),
+        ) || !js_channels.is_object()
+        {
+            continue;
+        }
+        rooted!(in(*cx) let js_channels = js_channels.to_object());
+        let mut length = 0;
+        if !JS_GetArrayLength(*cx, js_channels.handle(), &mut length) {
+            continue;
+        }
+        for (channel_index, channel) in channels.iter_mut().enumerate().take(length as usize) {
+            rooted!(in(*cx) let mut js_channel = UndefinedValue());
+            if !JS_GetElement(
+                *cx,
+                js_channels.handle(),
+                channel_index as u32,
+                &mut js_channel.handle_mut(),
+            ) || !js_channel.is_object()
+            {
+                continue;
+            }
+            typedarray!(in(*cx) let array: Float32Array = js_channel.to_object());
+            if let Ok(array) = array {
+                let samples = array.as_slice();
+                if samples.len() == channel.len() {
+                    channel.copy_from_slice(samples);
+                }
+            }
+        }
+    }
+    JS_ClearPendingException(*cx);
+}
+
+/// A processor constructor registered with `registerProcessor()`.
+/// This type is dangerous, because it contains an unboxed `Heap<JSVal>`
+/// value, which can't be moved.
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+struct ProcessorDefinition {
+    #[ignore_malloc_size_of = "mozjs"]
+    constructor: Heap<JSVal>,
+    /// The names of the parameter descriptors, in the order their values
+    /// are rendered in.
+    parameter_names: Vec<String>,
+}
+
+impl ProcessorDefinition {
+    fn new(constructor: HandleValue, parameter_names: Vec<String>) -> Box<ProcessorDefinition> {
+        let result = Box::new(ProcessorDefinition {
+            constructor: Heap::default(),
+            parameter_names,
+        });
+        result.constructor.set(constructor.get());
+        result
+    }
+}
+
+/// A unique id for an AudioWorkletProcessor, shared with its node.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct AudioWorkletProcessorId(Uuid);
+
+unsafe_no_jsmanaged_fields!(AudioWorkletProcessorId);
+
+impl AudioWorkletProcessorId {
+    pub fn new() -> AudioWorkletProcessorId {
+        AudioWorkletProcessorId(Uuid::new_v4())
+    }
+}
+
+/// What a worklet global needs to construct the processor of a node.
+pub struct AudioWorkletProcessorInit {
+    pub id: AudioWorkletProcessorId,
+    pub name: String,
+    /// The serialized AudioWorkletNodeOptions the node was created with.
+    pub options: StructuredSerializedData,
+}
+
+/// A parameter descriptor, as registered by `registerProcessor()`.
+#[derive(Clone, Debug)]
+pub struct ParamDescriptor {
+    pub name: String,
+    pub default_value: f32,
+    pub min_value: f32,
+    pub max_value: f32,
+    pub automation_rate: AutomationRate,
+}
+
+#[derive(Default)]
+struct AudioWorkletState {
+    sample_rate: f32,
+    /// <https://webaudio.github.io/web-audio-api/#dom-baseaudiocontext-node-name-to-parameter-descriptor-map>
+    processors: HashMap<String, Vec<ParamDescriptor>>,
+}
+
+/// The state of each audio worklet which is shared between the script
+/// thread and the worklet threads.
+#[derive(Clone, Default)]
+pub struct AudioWorkletRegistry(Arc<Mutex<HashMap<WorkletId, AudioWorkletState>>>);
+
+unsafe_no_jsmanaged_fields!(AudioWorkletRegistry);
+
+impl AudioWorkletRegistry {
+    pub fn add_worklet(&self, worklet_id: WorkletId, sample_rate: f32) {
+        self.0.lock().insert(
+            worklet_id,
+            AudioWorkletState {
+                sample_rate,
+                processors: HashMap::new(),
+            },
+        );
+    }
+
+    pub fn remove_worklet(&self, worklet_id: WorkletId) {
+        self.0.lock().remove(&worklet_id);
+    }
+
+    fn sample_rate(&self, worklet_id: WorkletId) -> f32 {
+        self.0
+            .lock()
+            .get(&worklet_id)
+            .map_or(0., |state| state.sample_rate)
+    }
+
+    fn register_processor(
+        &self,
+        worklet_id: WorkletId,
+        name: String,
+        parameters: Vec<ParamDescriptor>,
+    ) {
+        if let Some(state) = self.0.lock().get_mut(&worklet_id) {
+            state.processors.entry(name).or_insert(parameters);
+        }
+    }
+
+    /// The parameter descriptors of a registered processor.
+    pub fn processor(&self, worklet_id: WorkletId, name: &str) -> Option<Vec<ParamDescriptor>> {
+        self.0
+            .lock()
+            .get(&worklet_id)
+            .and_then(|state| state.processors.get(name).cloned())
+    }
+}
+
+/// The outcome of running a render quantum through a processor.
+pub struct ProcessResult {
+    /// The quantum, with the outputs written by the processor.
+    pub quantum: ProcessQuantum,
+    /// <https://webaudio.github.io/web-audio-api/#active-source>
+    pub keep_alive: bool,
+    /// Set if the processor couldn't be constructed or threw.
+    pub error: Option<String>,
+}
+
+impl ProcessResult {
+    fn failed(mut quantum: ProcessQuantum, name: &str) -> ProcessResult {
+        for channel in quantum
+            .outputs
+            .iter_mut()
+            .flat_map(|output| output.iter_mut())
+        {
+            for sample in channel.iter_mut() {
+                *sample = 0.;
+            }
+        }
+        ProcessResult {
+            quantum,
+            keep_alive: false,
+            error: Some(format!("AudioWorkletProcessor {} failed", name)),
+        }
+    }
+}
+
+/// Tasks which can be performed by audio worklets.
+pub enum AudioWorkletTask {
+    Process {
+        processor: Arc<AudioWorkletProcessorInit>,
+        quantum: ProcessQuantum,
+        sender: Sender<ProcessResult>,
+    },
+    DestroyProcessor(AudioWorkletProcessorId),
+}
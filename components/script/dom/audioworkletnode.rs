@@ -0,0 +1,395 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::audionode::{AudioNode, MAX_CHANNEL_COUNT};
+use crate::dom::audioparam::AudioParam;
+use crate::dom::audioparammap::AudioParamMap;
+use crate::dom::audioworkletglobalscope::{AudioWorkletProcessorId, AudioWorkletProcessorInit};
+use crate::dom::audioworkletglobalscope::{AudioWorkletTask, ProcessResult};
+use crate::dom::baseaudiocontext::BaseAudioContext;
+use crate::dom::bindings::codegen::Bindings::AudioNodeBinding::{
+    ChannelCountMode, ChannelInterpretation,
+};
+use crate::dom::bindings::codegen::Bindings::AudioWorkletNodeBinding::AudioWorkletNodeMethods;
+use crate::dom::bindings::codegen::Bindings::AudioWorkletNodeBinding::AudioWorkletNodeOptions;
+use crate::dom::bindings::codegen::Bindings::BaseAudioContextBinding::BaseAudioContextMethods;
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::refcounted::Trusted;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject};
+use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::bindings::str::DOMString;
+use crate::dom::bindings::structuredclone;
+use crate::dom::bindings::trace::RootedTraceableBox;
+use crate::dom::errorevent::ErrorEvent;
+use crate::dom::event::{Event, EventBubbles, EventCancelable};
+use crate::dom::eventtarget::EventTarget;
+use crate::dom::offlineaudiocontext::OfflineAudioContext;
+use crate::dom::window::Window;
+use crate::dom::worklet::WorkletExecutor;
+use crate::dom::workletglobalscope::WorkletTask;
+use crate::realms::enter_realm;
+use crate::task_source::TaskSource;
+use crossbeam_channel::{unbounded, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use dom_struct::dom_struct;
+use ipc_channel::ipc::{self, IpcReceiver};
+use ipc_channel::router::ROUTER;
+use js::conversions::ToJSValConvertible;
+use js::jsval::UndefinedValue;
+use js::rust::HandleValue;
+use servo_media::audio::node::AudioNodeInit;
+use servo_media::audio::param::ParamType;
+use servo_media::audio::worklet_node::{
+    AudioWorkletNodeOptions as ServoMediaAudioWorkletNodeOptions, ProcessQuantum, WorkletProcessor,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[dom_struct]
+pub struct AudioWorkletNode {
+    node: AudioNode,
+    parameters: Dom<AudioParamMap>,
+}
+
+impl AudioWorkletNode {
+    #[allow(unsafe_code)]
+    #[allow(unrooted_must_root)]
+    pub fn new_inherited(
+        window: &Window,
+        context: &BaseAudioContext,
+        name: DOMString,
+        options: &AudioWorkletNodeOptions,
+    ) -> Fallible<(AudioWorkletNode, IpcReceiver<String>)> {
+        // Step 1.
+        if options.numberOfInputs == 0 && options.numberOfOutputs == 0 {
+            return Err(Error::NotSupported);
+        }
+
+        // Step 2.
+        let output_channel_count = match options.outputChannelCount {
+            Some(ref counts) => {
+                if counts
+                    .iter()
+                    .any(|&count| count == 0 || count > MAX_CHANNEL_COUNT)
+                {
+                    return Err(Error::NotSupported);
+                }
+                if counts.len() != options.numberOfOutputs as usize {
+                    return Err(Error::IndexSize);
+                }
+                counts.clone()
+            },
+            None => vec![1; options.numberOfOutputs as usize],
+        };
+
+        // Step 3.
+        let worklet = context.AudioWorklet();
+        let descriptors = match worklet.processor_parameter_descriptors(&name) {
+            Some(descriptors) => descriptors,
+            None => return Err(Error::InvalidState),
+        };
+
+        // Steps 4-7.
+        let processor_options = {
+            let cx = window.get_cx();
+            let _ac = enter_realm(window);
+            rooted!(in(*cx) let mut value = UndefinedValue());
+            unsafe { options.to_jsval(*cx, value.handle_mut()) };
+            structuredclone::write(cx, value.handle(), None)?
+        };
+
+        let node_options =
+            options
+                .parent
+                .unwrap_or(2, ChannelCountMode::Max, ChannelInterpretation::Speakers);
+
+        // Initial values for the parameters, in the order of the descriptors.
+        let initial_values: Vec<f32> = descriptors
+            .iter()
+            .map(|descriptor| {
+                options
+                    .parameterData
+                    .as_ref()
+                    .and_then(|data| data.get(&*descriptor.name))
+                    .map_or(descriptor.default_value, |value| **value as f32)
+            })
+            .collect();
+
+        let (send, rcv) = ipc::channel().unwrap();
+        let bridge = AudioWorkletProcessorBridge::new(
+            worklet.executor(),
+            Arc::new(AudioWorkletProcessorInit {
+                id: AudioWorkletProcessorId::new(),
+                name: String::from(name),
+                options: processor_options,
+            }),
+            context.is::<OfflineAudioContext>(),
+            Box::new(move |error| {
+                let _ = send.send(error);
+            }),
+        );
+
+        let node = AudioNode::new_inherited(
+            AudioNodeInit::AudioWorkletNode(ServoMediaAudioWorkletNodeOptions {
+                number_of_inputs: options.numberOfInputs,
+                number_of_outputs: options.numberOfOutputs,
+                output_channel_count,
+                parameters: initial_values.clone(),
+                processor: Box::new(bridge),
+            }),
+            context,
+            node_options,
+            options.numberOfInputs,
+            options.numberOfOutputs,
+        )?;
+
+        // Step 8.
+        let node_id = node.node_id();
+        let params: Vec<(DOMString, DomRoot<AudioParam>)> = descriptors
+            .iter()
+            .zip(initial_values)
+            .enumerate()
+            .map(|(index, (descriptor, value))| {
+                (
+                    DOMString::from(descriptor.name.clone()),
+                    AudioParam::new(
+                        window,
+                        context,
+                        node_id,
+                        ParamType::Custom(index),
+                        descriptor.automation_rate,
+                        value,
+                        descriptor.min_value,
+                        descriptor.max_value,
+                    ),
+                )
+            })
+            .collect();
+        let parameters = AudioParamMap::new(window, &params);
+
+        Ok((
+            AudioWorkletNode {
+                node,
+                parameters: Dom::from_ref(&parameters),
+            },
+            rcv,
+        ))
+    }
+
+    #[allow(unrooted_must_root)]
+    pub fn new(
+        window: &Window,
+        context: &BaseAudioContext,
+        name: DOMString,
+        options: &AudioWorkletNodeOptions,
+    ) -> Fallible<DomRoot<AudioWorkletNode>> {
+        let (node, recv) = AudioWorkletNode::new_inherited(window, context, name, options)?;
+        let object = reflect_dom_object(Box::new(node), window);
+        let (source, canceller) = window
+            .task_manager()
+            .dom_manipulation_task_source_with_canceller();
+        let this = Trusted::new(&*object);
+
+        ROUTER.add_route(
+            recv.to_opaque(),
+            Box::new(move |error| {
+                let this = this.clone();
+                let _ = source.queue_with_canceller(
+                    task!(fire_processor_error: move || {
+                        let this = this.root();
+                        this.fire_processor_error(error.to().unwrap());
+                    }),
+                    &canceller,
+                );
+            }),
+        );
+        Ok(object)
+    }
+
+    /// <https://webaudio.github.io/web-audio-api/#dom-audioworkletnode-audioworkletnode>
+    #[allow(non_snake_case)]
+    pub fn Constructor(
+        window: &Window,
+        context: &BaseAudioContext,
+        name: DOMString,
+        options: RootedTraceableBox<AudioWorkletNodeOptions>,
+    ) -> Fallible<DomRoot<AudioWorkletNode>> {
+        AudioWorkletNode::new(window, context, name, &options)
+    }
+
+    /// <https://webaudio.github.io/web-audio-api/#dom-audioworkletnode-onprocessorerror>
+    fn fire_processor_error(&self, message: String) {
+        let global = self.global();
+        let event = ErrorEvent::new(
+            &global,
+            atom!("processorerror"),
+            EventBubbles::DoesNotBubble,
+            EventCancelable::NotCancelable,
+            message.into(),
+            DOMString::new(),
+            0,
+            0,
+            HandleValue::null(),
+        );
+        event.upcast::<Event>().fire(self.upcast::<EventTarget>());
+    }
+}
+
+impl AudioWorkletNodeMethods for AudioWorkletNode {
+    /// <https://webaudio.github.io/web-audio-api/#dom-audioworkletnode-parameters>
+    fn Parameters(&self) -> DomRoot<AudioParamMap> {
+        DomRoot::from_ref(&self.parameters)
+    }
+
+    // https://webaudio.github.io/web-audio-api/#dom-audioworkletnode-onprocessorerror
+    event_handler!(processorerror, GetOnprocessorerror, SetOnprocessorerror);
+}
+
+/// The processor of an AudioWorkletNode, as seen by the rendering thread.
+///
+/// Render quanta are handed over to the primary worklet thread, which runs
+/// the `process()` method of the AudioWorkletProcessor. An offline context
+/// waits for the result. A realtime context must never block the rendering
+/// thread, so it renders the result of the previous quantum instead, and
+/// outputs silence if the worklet hasn't finished it yet.
+struct AudioWorkletProcessorBridge {
+    executor: WorkletExecutor,
+    processor: Arc<AudioWorkletProcessorInit>,
+    sender: Sender<ProcessResult>,
+    receiver: Receiver<ProcessResult>,
+    offline: bool,
+    /// Whether a quantum has been sent to the worklet and not received back.
+    pending: bool,
+    /// A quantum which can be reused for the next hand over, to avoid
+    /// allocating on the rendering thread.
+    spare: Option<ProcessQuantum>,
+    /// <https://webaudio.github.io/web-audio-api/#active-source>
+    keep_alive: bool,
+    /// Set once the processor has failed, after which it outputs silence.
+    failed: bool,
+    on_error: Box<dyn Fn(String) + Send>,
+}
+
+impl AudioWorkletProcessorBridge {
+    fn new(
+        executor: WorkletExecutor,
+        processor: Arc<AudioWorkletProcessorInit>,
+        offline: bool,
+        on_error: Box<dyn Fn(String) + Send>,
+    ) -> AudioWorkletProcessorBridge {
+        let (sender, receiver) = unbounded();
+        AudioWorkletProcessorBridge {
+            executor,
+            processor,
+            sender,
+            receiver,
+            offline,
+            pending: false,
+            spare: None,
+            keep_alive: true,
+            failed: false,
+            on_error,
+        }
+    }
+
+    fn send(&mut self, quantum: &ProcessQuantum) {
+        let mut copy = self.spare.take().unwrap_or_default();
+        copy.clone_from(quantum);
+        silence(&mut copy);
+        self.executor
+            .schedule_a_worklet_task(WorkletTask::Audio(AudioWorkletTask::Process {
+                processor: self.processor.clone(),
+                quantum: copy,
+                sender: self.sender.clone(),
+            }));
+        self.pending = true;
+    }
+
+    fn receive(&mut self, result: ProcessResult, quantum: &mut ProcessQuantum) {
+        self.pending = false;
+        if let Some(error) = result.error {
+            self.fail(error, quantum);
+            return;
+        }
+        quantum.outputs.clone_from(&result.quantum.outputs);
+        self.keep_alive = result.keep_alive;
+        self.spare = Some(result.quantum);
+    }
+
+    fn fail(&mut self, error: String, quantum: &mut ProcessQuantum) {
+        self.failed = true;
+        self.keep_alive = false;
+        silence(quantum);
+        (self.on_error)(error);
+    }
+}
+
+impl WorkletProcessor for AudioWorkletProcessorBridge {
+    fn process(&mut self, quantum: &mut ProcessQuantum) -> bool {
+        if self.failed {
+            silence(quantum);
+            return false;
+        }
+
+        if self.offline {
+            self.send(quantum);
+            let timeout = Duration::from_millis(pref!(dom.worklet.timeout_ms) as u64);
+            match self.receiver.recv_timeout(timeout) {
+                Ok(result) => self.receive(result, quantum),
+                Err(RecvTimeoutError::Timeout) => {
+                    self.fail("AudioWorkletProcessor timed out".into(), quantum)
+                },
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.fail("AudioWorklet has been destroyed".into(), quantum)
+                },
+            }
+            return self.keep_alive;
+        }
+
+        if self.pending {
+            match self.receiver.try_recv() {
+                Ok(result) => self.receive(result, quantum),
+                Err(TryRecvError::Empty) => {
+                    // The worklet is still busy with the previous quantum,
+                    // so this one is dropped.
+                    silence(quantum);
+                    return self.keep_alive;
+                },
+                Err(TryRecvError::Disconnected) => {
+                    self.fail("AudioWorklet has been destroyed".into(), quantum)
+                },
+            }
+        } else {
+            silence(quantum);
+        }
+        if !self.failed {
+            // The inputs of this quantum are rendered with the next one.
+            self.send(quantum);
+        }
+        self.keep_alive
+    }
+}
+
+impl Drop for AudioWorkletProcessorBridge {
+    fn drop(&mut self) {
+        // TODO: processors live in the global scope of the primary worklet
+        // thread, so a processor is lost, and constructed anew, if the
+        // worklet threads swap roles.
+        self.executor.schedule_a_worklet_task(WorkletTask::Audio(
+            AudioWorkletTask::DestroyProcessor(self.processor.id),
+        ));
+    }
+}
+
+fn silence(quantum: &mut ProcessQuantum) {
+    for channel in quantum
+        .outputs
+        .iter_mut()
+        .flat_map(|output| output.iter_mut())
+    {
+        for sample in channel.iter_mut() {
+            *sample = 0.;
+        }
+    }
+}
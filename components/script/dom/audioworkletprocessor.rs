@@ -0,0 +1,48 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::audioworkletglobalscope::AudioWorkletGlobalScope;
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::reflector::{reflect_dom_object, Reflector};
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::globalscope::GlobalScope;
+use dom_struct::dom_struct;
+
+/// <https://webaudio.github.io/web-audio-api/#audioworkletprocessor>
+#[dom_struct]
+pub struct AudioWorkletProcessor {
+    reflector_: Reflector,
+}
+
+impl AudioWorkletProcessor {
+    fn new_inherited() -> AudioWorkletProcessor {
+        AudioWorkletProcessor {
+            reflector_: Reflector::new(),
+        }
+    }
+
+    fn new(global: &GlobalScope) -> DomRoot<AudioWorkletProcessor> {
+        reflect_dom_object(Box::new(AudioWorkletProcessor::new_inherited()), global)
+    }
+
+    /// <https://webaudio.github.io/web-audio-api/#dom-audioworkletprocessor-audioworkletprocessor>
+    #[allow(non_snake_case)]
+    pub fn Constructor(global: &GlobalScope) -> Fallible<DomRoot<AudioWorkletProcessor>> {
+        // Steps 1-3. Processors can only be created by the audio worklet
+        // while it is constructing the processor of an AudioWorkletNode.
+        let pending = global
+            .downcast::<AudioWorkletGlobalScope>()
+            .map_or(false, |global| global.take_processor_construction_pending());
+        if !pending {
+            return Err(Error::Type(
+                "AudioWorkletProcessor can only be constructed by an AudioWorkletNode".into(),
+            ));
+        }
+
+        // Steps 4-6. The processor options are passed to the constructor by
+        // the audio worklet global scope, which also keeps the instance.
+        Ok(AudioWorkletProcessor::new(global))
+    }
+}
@@ -8,6 +8,7 @@ use crate::dom::audiobuffersourcenode::AudioBufferSourceNode;
 use crate::dom::audiodestinationnode::AudioDestinationNode;
 use crate::dom::audiolistener::AudioListener;
 use crate::dom::audionode::MAX_CHANNEL_COUNT;
+use crate::dom::audioworklet::AudioWorklet;
 use crate::dom::bindings::callback::ExceptionHandling;
 use crate::dom::bindings::cell::DomRefCell;
 use crate::dom::bindings::codegen::Bindings::AnalyserNodeBinding::AnalyserOptions;
@@ -24,10 +25,15 @@ use crate::dom::bindings::codegen::Bindings::BiquadFilterNodeBinding::BiquadFilt
 use crate::dom::bindings::codegen::Bindings::ChannelMergerNodeBinding::ChannelMergerOptions;
 use crate::dom::bindings::codegen::Bindings::ChannelSplitterNodeBinding::ChannelSplitterOptions;
 use crate::dom::bindings::codegen::Bindings::ConstantSourceNodeBinding::ConstantSourceOptions;
+use crate::dom::bindings::codegen::Bindings::ConvolverNodeBinding::ConvolverOptions;
+use crate::dom::bindings::codegen::Bindings::DelayNodeBinding::DelayOptions;
+use crate::dom::bindings::codegen::Bindings::DynamicsCompressorNodeBinding::DynamicsCompressorOptions;
 use crate::dom::bindings::codegen::Bindings::GainNodeBinding::GainOptions;
+use crate::dom::bindings::codegen::Bindings::IIRFilterNodeBinding::IIRFilterOptions;
 use crate::dom::bindings::codegen::Bindings::OscillatorNodeBinding::OscillatorOptions;
 use crate::dom::bindings::codegen::Bindings::PannerNodeBinding::PannerOptions;
 use crate::dom::bindings::codegen::Bindings::StereoPannerNodeBinding::StereoPannerOptions;
+use crate::dom::bindings::codegen::Bindings::WaveShaperNodeBinding::WaveShaperOptions;
 use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
 use crate::dom::bindings::inheritance::Castable;
 use crate::dom::bindings::num::Finite;
@@ -38,24 +44,41 @@ use crate::dom::biquadfilternode::BiquadFilterNode;
 use crate::dom::channelmergernode::ChannelMergerNode;
 use crate::dom::channelsplitternode::ChannelSplitterNode;
 use crate::dom::constantsourcenode::ConstantSourceNode;
+use crate::dom::convolvernode::ConvolverNode;
+use crate::dom::delaynode::DelayNode;
 use crate::dom::domexception::{DOMErrorName, DOMException};
+use crate::dom::dynamicscompressornode::DynamicsCompressorNode;
 use crate::dom::eventtarget::EventTarget;
 use crate::dom::gainnode::GainNode;
+use crate::dom::htmlmediaelement::MediaAudioOutputRenderer;
+use crate::dom::iirfilternode::IIRFilterNode;
 use crate::dom::oscillatornode::OscillatorNode;
 use crate::dom::pannernode::PannerNode;
 use crate::dom::promise::Promise;
 use crate::dom::stereopannernode::StereoPannerNode;
+use crate::dom::waveshapernode::WaveShaperNode;
 use crate::dom::window::Window;
 use crate::realms::InRealm;
 use crate::task_source::TaskSource;
 use dom_struct::dom_struct;
+use ipc_channel::ipc::{self, IpcSender};
+use ipc_channel::router::ROUTER;
 use js::rust::CustomAutoRooterGuard;
 use js::typedarray::ArrayBuffer;
+use media::WindowGLContext;
 use msg::constellation_msg::PipelineId;
+use script_traits::AudioOutputMsg;
 use servo_media::audio::context::{AudioContext, AudioContextOptions, ProcessingState};
 use servo_media::audio::context::{OfflineAudioContextOptions, RealTimeAudioContextOptions};
 use servo_media::audio::decoder::AudioDecoderCallbacks;
 use servo_media::audio::graph::NodeId;
+use servo_media::audio::node::{AudioNodeInit, ChannelCountMode as ServoMediaChannelCountMode};
+use servo_media::audio::node::{
+    ChannelInfo, ChannelInterpretation as ServoMediaChannelInterpretation,
+};
+use servo_media::player::audio::AudioRenderer;
+use servo_media::player::{Player, PlayerEvent, StreamType};
+use servo_media::streams::MediaStreamType;
 use servo_media::{ClientContextId, ServoMedia};
 use std::cell::Cell;
 use std::collections::hash_map::Entry;
@@ -78,6 +101,17 @@ struct DecodeResolver {
     pub error_callback: Option<Rc<DecodeErrorCallback>>,
 }
 
+/// Sends the output of a realtime context to the audio output mixer, while the
+/// embedder captures the audio output of the browser.
+#[derive(JSTraceable)]
+struct AudioOutputCapture {
+    /// The node connected to instead of the destination of the context, which
+    /// outputs to a media stream.
+    node: NodeId,
+    /// The player rendering the media stream to the mixer.
+    player: Arc<Mutex<dyn Player>>,
+}
+
 #[dom_struct]
 pub struct BaseAudioContext {
     eventtarget: EventTarget,
@@ -86,6 +120,8 @@ pub struct BaseAudioContext {
     /// https://webaudio.github.io/web-audio-api/#dom-baseaudiocontext-destination
     destination: MutNullableDom<AudioDestinationNode>,
     listener: MutNullableDom<AudioListener>,
+    /// https://webaudio.github.io/web-audio-api/#dom-baseaudiocontext-audioworklet
+    audio_worklet: MutNullableDom<AudioWorklet>,
     /// Resume promises which are soon to be fulfilled by a queued task.
     #[ignore_malloc_size_of = "promises are hard"]
     in_flight_resume_promises_queue: DomRefCell<VecDeque<(Box<[Rc<Promise>]>, ErrorResult)>>,
@@ -103,6 +139,8 @@ pub struct BaseAudioContext {
     /// been "closed()".
     state: Cell<AudioContextState>,
     channel_count: u32,
+    #[ignore_malloc_size_of = "servo_media"]
+    audio_output_capture: Option<AudioOutputCapture>,
 }
 
 impl BaseAudioContext {
@@ -127,17 +165,69 @@ impl BaseAudioContext {
                 .create_audio_context(&client_context_id, options.into()),
             destination: Default::default(),
             listener: Default::default(),
+            audio_worklet: Default::default(),
             in_flight_resume_promises_queue: Default::default(),
             pending_resume_promises: Default::default(),
             decode_resolvers: Default::default(),
             sample_rate,
             state: Cell::new(AudioContextState::Suspended),
             channel_count: channel_count.into(),
+            audio_output_capture: None,
         };
 
         context
     }
 
+    /// Send the output of this context to the audio output `mixer` instead of
+    /// the default audio sink. This must happen before the destination node of
+    /// the context gets created.
+    pub fn capture_audio_output(
+        &mut self,
+        pipeline_id: PipelineId,
+        mixer: IpcSender<AudioOutputMsg>,
+        player_context: WindowGLContext,
+    ) {
+        let media = ServoMedia::get().unwrap();
+        let (socket, stream_id) = media.create_stream_and_socket(MediaStreamType::Audio);
+        let node = self.audio_context_impl.lock().unwrap().create_node(
+            AudioNodeInit::MediaStreamDestinationNode(socket),
+            ChannelInfo {
+                count: 2,
+                mode: ServoMediaChannelCountMode::Explicit,
+                interpretation: ServoMediaChannelInterpretation::Speakers,
+            },
+        );
+
+        // The player only reports events about the playback of the stream.
+        let (event_sender, event_receiver) = ipc::channel::<PlayerEvent>().unwrap();
+        ROUTER.add_route(event_receiver.to_opaque(), Box::new(|_| {}));
+
+        let renderer = MediaAudioOutputRenderer::new(pipeline_id, mixer, Some(self.sample_rate));
+        let client_context_id =
+            ClientContextId::build(pipeline_id.namespace_id.0, pipeline_id.index.0.get());
+        let player = media.create_player(
+            &client_context_id,
+            StreamType::Stream,
+            event_sender,
+            None,
+            Some(Arc::new(Mutex::new(renderer)) as Arc<Mutex<dyn AudioRenderer>>),
+            Box::new(player_context),
+        );
+        let result = {
+            let player = player.lock().unwrap();
+            player
+                .set_stream(&stream_id, true)
+                .and_then(|_| player.play())
+        };
+        if let Err(e) = result {
+            return warn!(
+                "Could not capture the output of an audio context ({:?}).",
+                e
+            );
+        }
+        self.audio_output_capture = Some(AudioOutputCapture { node, player });
+    }
+
     /// Tells whether this is an OfflineAudioContext or not.
     pub fn is_offline(&self) -> bool {
         false
@@ -148,7 +238,10 @@ impl BaseAudioContext {
     }
 
     pub fn destination_node(&self) -> NodeId {
-        self.audio_context_impl.lock().unwrap().dest_node()
+        match self.audio_output_capture {
+            Some(ref capture) => capture.node,
+            None => self.audio_context_impl.lock().unwrap().dest_node(),
+        }
     }
 
     pub fn listener(&self) -> NodeId {
@@ -331,6 +424,14 @@ impl BaseAudioContextMethods for BaseAudioContext {
         self.listener.or_init(|| AudioListener::new(&window, self))
     }
 
+    /// https://webaudio.github.io/web-audio-api/#dom-baseaudiocontext-audioworklet
+    fn AudioWorklet(&self) -> DomRoot<AudioWorklet> {
+        let global = self.global();
+        let window = global.as_window();
+        self.audio_worklet
+            .or_init(|| AudioWorklet::new(&window, self.sample_rate))
+    }
+
     // https://webaudio.github.io/web-audio-api/#dom-baseaudiocontext-onstatechange
     event_handler!(statechange, GetOnstatechange, SetOnstatechange);
 
@@ -348,6 +449,13 @@ impl BaseAudioContextMethods for BaseAudioContext {
         GainNode::new(&self.global().as_window(), &self, &GainOptions::empty())
     }
 
+    /// https://webaudio.github.io/web-audio-api/#dom-baseaudiocontext-createdelay
+    fn CreateDelay(&self, max_delay_time: Finite<f64>) -> Fallible<DomRoot<DelayNode>> {
+        let mut opts = DelayOptions::empty();
+        opts.maxDelayTime = max_delay_time;
+        DelayNode::new(&self.global().as_window(), &self, &opts)
+    }
+
     /// https://webaudio.github.io/web-audio-api/#dom-baseaudiocontext-createpanner
     fn CreatePanner(&self) -> Fallible<DomRoot<PannerNode>> {
         PannerNode::new(&self.global().as_window(), &self, &PannerOptions::empty())
@@ -367,6 +475,29 @@ impl BaseAudioContextMethods for BaseAudioContext {
         )
     }
 
+    /// https://webaudio.github.io/web-audio-api/#dom-baseaudiocontext-createiirfilter
+    fn CreateIIRFilter(
+        &self,
+        feedforward: Vec<Finite<f64>>,
+        feedback: Vec<Finite<f64>>,
+    ) -> Fallible<DomRoot<IIRFilterNode>> {
+        let opts = IIRFilterOptions {
+            parent: AudioNodeOptions::empty(),
+            feedforward,
+            feedback,
+        };
+        IIRFilterNode::new(&self.global().as_window(), &self, &opts)
+    }
+
+    /// https://webaudio.github.io/web-audio-api/#dom-baseaudiocontext-createwaveshaper
+    fn CreateWaveShaper(&self) -> Fallible<DomRoot<WaveShaperNode>> {
+        WaveShaperNode::new(
+            &self.global().as_window(),
+            &self,
+            &WaveShaperOptions::empty(),
+        )
+    }
+
     /// https://webaudio.github.io/web-audio-api/#dom-baseaudiocontext-createstereopanner
     fn CreateStereoPanner(&self) -> Fallible<DomRoot<StereoPannerNode>> {
         StereoPannerNode::new(
@@ -385,6 +516,24 @@ impl BaseAudioContextMethods for BaseAudioContext {
         )
     }
 
+    /// https://webaudio.github.io/web-audio-api/#dom-baseaudiocontext-createconvolver
+    fn CreateConvolver(&self) -> Fallible<DomRoot<ConvolverNode>> {
+        ConvolverNode::new(
+            &self.global().as_window(),
+            &self,
+            &ConvolverOptions::empty(),
+        )
+    }
+
+    /// https://webaudio.github.io/web-audio-api/#dom-baseaudiocontext-createdynamicscompressor
+    fn CreateDynamicsCompressor(&self) -> Fallible<DomRoot<DynamicsCompressorNode>> {
+        DynamicsCompressorNode::new(
+            &self.global().as_window(),
+            &self,
+            &DynamicsCompressorOptions::empty(),
+        )
+    }
+
     /// https://webaudio.github.io/web-audio-api/#dom-baseaudiocontext-createchannelmerger
     fn CreateChannelMerger(&self, count: u32) -> Fallible<DomRoot<ChannelMergerNode>> {
         let mut opts = ChannelMergerOptions::empty();
@@ -20,7 +20,7 @@ use js::jsapi::{AddRawValueRoot, IsCallable, JSObject};
 use js::jsapi::{EnterRealm, LeaveRealm, Realm, RemoveRawValueRoot};
 use js::jsval::{JSVal, ObjectValue, UndefinedValue};
 use js::rust::wrappers::{JS_GetProperty, JS_WrapObject};
-use js::rust::{MutableHandleObject, Runtime};
+use js::rust::{HandleObject, MutableHandleObject, Runtime};
 use std::default::Default;
 use std::ffi::CString;
 use std::mem::drop;
@@ -205,9 +205,31 @@ impl CallbackInterface {
     }
 }
 
+/// A value that can be the `this` object of a callback: either a DOM object,
+/// or a plain JS object, such as a streams underlying source.
+pub trait ThisReflector {
+    fn jsobject(&self) -> *mut JSObject;
+}
+
+impl<T: DomObject> ThisReflector for T {
+    fn jsobject(&self) -> *mut JSObject {
+        self.reflector().get_jsobject().get()
+    }
+}
+
+impl<'a> ThisReflector for HandleObject<'a> {
+    fn jsobject(&self) -> *mut JSObject {
+        self.get()
+    }
+}
+
 /// Wraps the reflector for `p` into the realm of `cx`.
-pub fn wrap_call_this_object<T: DomObject>(cx: JSContext, p: &T, mut rval: MutableHandleObject) {
-    rval.set(p.reflector().get_jsobject().get());
+pub fn wrap_call_this_object<T: ThisReflector>(
+    cx: JSContext,
+    p: &T,
+    mut rval: MutableHandleObject,
+) {
+    rval.set(p.jsobject());
     assert!(!rval.get().is_null());
 
     unsafe {
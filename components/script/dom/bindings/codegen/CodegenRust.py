@@ -906,41 +906,7 @@ def getJSToNativeConversionInfo(type, descriptorProvider, failureCode=None,
 
         return handleOptional(templateBody, declType, handleDefault("None"))
 
-    if type.isReadableStream():
-        assert not isEnforceRange and not isClamp
-
-        if failureCode is None:
-            unwrapFailureCode = '''throw_type_error(*cx, "This object is not \
-                    an instance of ReadableStream.");\n'''
-        else:
-            unwrapFailureCode = failureCode
-
-        templateBody = fill(
-            """
-            {
-                use crate::realms::{AlreadyInRealm, InRealm};
-                let in_realm_proof = AlreadyInRealm::assert_for_cx(cx);
-                match ReadableStream::from_js(cx, $${val}.get().to_object(), InRealm::Already(&in_realm_proof)) {
-                    Ok(val) => val,
-                    Err(()) => {
-                    $*{failureCode}
-                    }
-                }
-
-            }
-            """,
-            failureCode=unwrapFailureCode + "\n",
-        )
-
-        templateBody = wrapObjectTemplate(templateBody, "None",
-                                          isDefinitelyObject, type, failureCode)
-
-        declType = CGGeneric("DomRoot<ReadableStream>")
-
-        return handleOptional(templateBody, declType,
-                              handleDefault("None"))
-
-    elif type.isSpiderMonkeyInterface():
+    if type.isSpiderMonkeyInterface():
         raise TypeError("Can't handle SpiderMonkey interface arguments other than typed arrays yet")
 
     if type.isDOMString():
@@ -4543,9 +4509,6 @@ def getUnionTypeTemplateVars(type, descriptorProvider):
     elif type.isObject():
         name = type.name
         typeName = "Heap<*mut JSObject>"
-    elif type.isReadableStream():
-        name = type.name
-        typeName = "DomRoot<ReadableStream>"
     elif is_typed_array(type):
         name = type.name
         typeName = "typedarray::Heap" + name
@@ -6196,6 +6159,7 @@ def generate_imports(config, cgthings, descriptors, callbacks=None, dictionaries
         'crate::dom::bindings::callback::CallbackObject',
         'crate::dom::bindings::callback::ExceptionHandling',
         'crate::dom::bindings::callback::wrap_call_this_object',
+        'crate::dom::bindings::callback::ThisReflector',
         'crate::dom::bindings::conversions::ConversionBehavior',
         'crate::dom::bindings::conversions::ConversionResult',
         'crate::dom::bindings::conversions::DOM_OBJECT_SLOT',
@@ -7133,7 +7097,7 @@ class CGCallback(CGClass):
               })
         return [ClassMethod(method.name + '_', method.returnType, args,
                             bodyInHeader=True,
-                            templateArgs=["T: DomObject"],
+                            templateArgs=["T: ThisReflector"],
                             body=bodyWithThis,
                             visibility='pub'),
                 ClassMethod(method.name + '__', method.returnType, argsWithoutThis,
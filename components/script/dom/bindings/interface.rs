@@ -138,7 +138,7 @@ pub unsafe fn create_global_object(
     let mut options = RealmOptions::default();
     options.creationOptions_.traceGlobal_ = Some(trace);
     options.creationOptions_.sharedMemoryAndAtomics_ = false;
-    options.creationOptions_.streams_ = true;
+    options.creationOptions_.streams_ = false;
 
     rval.set(JS_NewGlobalObject(
         *cx,
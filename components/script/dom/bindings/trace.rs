@@ -86,6 +86,7 @@ use js::rust::{GCMethods, Handle, Runtime};
 use js::typedarray::TypedArray;
 use js::typedarray::TypedArrayElement;
 use malloc_size_of::{MallocSizeOf, MallocSizeOfOps};
+use media::codecs::{Decoder, Encoder};
 use media::WindowGLContext;
 use metrics::{InteractiveMetrics, InteractiveWindow};
 use mime::Mime;
@@ -93,7 +94,8 @@ use msg::constellation_msg::{
     BlobId, BroadcastChannelRouterId, BrowsingContextId, HistoryStateId, MessagePortId,
     MessagePortRouterId, PipelineId, TopLevelBrowsingContextId,
 };
-use msg::constellation_msg::{ServiceWorkerId, ServiceWorkerRegistrationId};
+use msg::constellation_msg::{ServiceWorkerId, ServiceWorkerRegistrationId, SharedWorkerId};
+use net_traits::cache_storage::{CacheEntry, CacheId, CachedRequest};
 use net_traits::filemanager_thread::RelativePos;
 use net_traits::image::base::{Image, ImageMetadata};
 use net_traits::image_cache::{ImageCache, PendingImageId};
@@ -162,6 +164,7 @@ use style::stylesheets::keyframes_rule::Keyframe;
 use style::stylesheets::{CssRules, FontFaceRule, KeyframesRule, MediaRule, Stylesheet};
 use style::stylesheets::{ImportRule, NamespaceRule, StyleRule, SupportsRule, ViewportRule};
 use style::stylist::CascadeData;
+use style::values::computed::TimingFunction;
 use style::values::specified::Length;
 use tendril::fmt::UTF8;
 use tendril::stream::LossyDecoder;
@@ -197,6 +200,7 @@ unsafe_no_jsmanaged_fields!(MessagePortRouterId);
 
 unsafe_no_jsmanaged_fields!(ServiceWorkerId);
 unsafe_no_jsmanaged_fields!(ServiceWorkerRegistrationId);
+unsafe_no_jsmanaged_fields!(SharedWorkerId);
 
 unsafe_no_jsmanaged_fields!(BroadcastChannelRouterId);
 
@@ -556,6 +560,7 @@ unsafe_no_jsmanaged_fields!(UntrustedNodeAddress, OpaqueNode);
 unsafe_no_jsmanaged_fields!(LengthOrPercentageOrAuto);
 unsafe_no_jsmanaged_fields!(RGBA);
 unsafe_no_jsmanaged_fields!(StorageType);
+unsafe_no_jsmanaged_fields!(CacheEntry, CacheId, CachedRequest);
 unsafe_no_jsmanaged_fields!(CanvasGradientStop, LinearGradientStyle, RadialGradientStyle);
 unsafe_no_jsmanaged_fields!(LineCapStyle, LineJoinStyle, CompositionOrBlending);
 unsafe_no_jsmanaged_fields!(TextAlign, TextBaseline, Direction);
@@ -565,6 +570,7 @@ unsafe_no_jsmanaged_fields!(TimeProfilerChan);
 unsafe_no_jsmanaged_fields!(MemProfilerChan);
 unsafe_no_jsmanaged_fields!(PseudoElement);
 unsafe_no_jsmanaged_fields!(Length);
+unsafe_no_jsmanaged_fields!(TimingFunction);
 unsafe_no_jsmanaged_fields!(ElementSelectorFlags);
 unsafe_no_jsmanaged_fields!(ElementState);
 unsafe_no_jsmanaged_fields!(DOMString);
@@ -658,6 +664,7 @@ unsafe_no_jsmanaged_fields!(Arc<Mutex<AudioContext>>);
 unsafe_no_jsmanaged_fields!(NodeId);
 unsafe_no_jsmanaged_fields!(AnalysisEngine, DistanceModel, PanningModel, ParamType);
 unsafe_no_jsmanaged_fields!(Arc<Mutex<dyn Player>>);
+unsafe_no_jsmanaged_fields!(Arc<Mutex<dyn Decoder>>, Arc<Mutex<dyn Encoder>>);
 unsafe_no_jsmanaged_fields!(WebRtcController);
 unsafe_no_jsmanaged_fields!(MediaStreamId, MediaStreamType);
 unsafe_no_jsmanaged_fields!(Mutex<MediaFrameRenderer>);
@@ -0,0 +1,110 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::ByteLengthQueuingStrategyBinding::ByteLengthQueuingStrategyMethods;
+use crate::dom::bindings::codegen::Bindings::FunctionBinding::Function;
+use crate::dom::bindings::codegen::Bindings::QueuingStrategyBinding::QueuingStrategyInit;
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::globalscope::GlobalScope;
+use crate::realms::enter_realm;
+use dom_struct::dom_struct;
+use js::jsapi::{CallArgs, JSContext, JS_GetFunctionObject, JS_NewFunction};
+use js::jsval::{JSVal, UndefinedValue};
+use js::rust::wrappers::JS_GetProperty;
+use js::rust::{HandleValue, ToObject};
+use std::rc::Rc;
+
+#[dom_struct]
+pub struct ByteLengthQueuingStrategy {
+    reflector_: Reflector,
+    high_water_mark: f64,
+}
+
+impl ByteLengthQueuingStrategy {
+    fn new_inherited(high_water_mark: f64) -> ByteLengthQueuingStrategy {
+        ByteLengthQueuingStrategy {
+            reflector_: Reflector::new(),
+            high_water_mark,
+        }
+    }
+
+    pub fn new(global: &GlobalScope, high_water_mark: f64) -> DomRoot<ByteLengthQueuingStrategy> {
+        reflect_dom_object(
+            Box::new(ByteLengthQueuingStrategy::new_inherited(high_water_mark)),
+            global,
+        )
+    }
+
+    /// <https://streams.spec.whatwg.org/#blqs-constructor>
+    #[allow(non_snake_case)]
+    pub fn Constructor(
+        global: &GlobalScope,
+        init: &QueuingStrategyInit,
+    ) -> DomRoot<ByteLengthQueuingStrategy> {
+        ByteLengthQueuingStrategy::new(global, init.highWaterMark)
+    }
+}
+
+impl ByteLengthQueuingStrategyMethods for ByteLengthQueuingStrategy {
+    /// <https://streams.spec.whatwg.org/#blqs-high-water-mark>
+    fn HighWaterMark(&self) -> f64 {
+        self.high_water_mark
+    }
+
+    /// <https://streams.spec.whatwg.org/#blqs-size>
+    #[allow(unsafe_code)]
+    fn Size(&self) -> Fallible<Rc<Function>> {
+        let global = self.global();
+        if let Some(size) = global.get_byte_length_queuing_strategy_size() {
+            return Ok(size);
+        }
+
+        // https://streams.spec.whatwg.org/#byte-length-queuing-strategy-size-function
+        let cx = global.get_cx();
+        let _ac = enter_realm(&*global);
+        let size = unsafe {
+            let fun = JS_NewFunction(
+                *cx,
+                Some(byte_length_queuing_strategy_size),
+                1,
+                0,
+                b"size\0".as_ptr() as *const _,
+            );
+            if fun.is_null() {
+                return Err(Error::JSFailed);
+            }
+            Function::new(cx, JS_GetFunctionObject(fun))
+        };
+        global.set_byte_length_queuing_strategy_size(size.clone());
+        Ok(size)
+    }
+}
+
+/// Returns the `byteLength` of a chunk.
+#[allow(unsafe_code)]
+unsafe extern "C" fn byte_length_queuing_strategy_size(
+    cx: *mut JSContext,
+    argc: u32,
+    vp: *mut JSVal,
+) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let chunk = HandleValue::from_raw(args.get(0));
+    rooted!(in(cx) let object = ToObject(cx, chunk));
+    if object.is_null() {
+        return false;
+    }
+    rooted!(in(cx) let mut byte_length = UndefinedValue());
+    if !JS_GetProperty(
+        cx,
+        object.handle(),
+        b"byteLength\0".as_ptr() as *const _,
+        byte_length.handle_mut(),
+    ) {
+        return false;
+    }
+    *args.rval() = byte_length.get();
+    true
+}
@@ -0,0 +1,705 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::body::{consume_body, BodyType};
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::CacheBinding::{CacheMethods, CacheQueryOptions};
+use crate::dom::bindings::codegen::Bindings::RequestBinding::{
+    RequestInfo, RequestInit, RequestMethods,
+};
+use crate::dom::bindings::codegen::Bindings::ResponseBinding::{
+    ResponseMethods, ResponseType as DOMResponseType,
+};
+use crate::dom::bindings::conversions::root_from_handlevalue;
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::refcounted::TrustedPromise;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::headers::Guard;
+use crate::dom::promise::Promise;
+use crate::dom::promisenativehandler::{Callback, PromiseNativeHandler};
+use crate::dom::request::Request;
+use crate::dom::response::Response;
+use crate::fetch;
+use crate::realms::InRealm;
+use crate::script_runtime::JSContext as SafeJSContext;
+use crate::task::TaskCanceller;
+use crate::task_source::networking::NetworkingTaskSource;
+use crate::task_source::{TaskSource, TaskSourceName};
+use dom_struct::dom_struct;
+use headers::{HeaderMapExt, Vary};
+use http::Method;
+use hyper_serde::Serde;
+use ipc_channel::ipc::IpcSender;
+use ipc_channel::router::ROUTER;
+use js::rust::HandleValue;
+use js::typedarray::ArrayBuffer;
+use net_traits::cache_storage::CacheQueryOptions as NetCacheQueryOptions;
+use net_traits::cache_storage::{CacheBatchError, CacheBatchOperation, CacheEntry};
+use net_traits::cache_storage::{CacheId, CacheSelector};
+use net_traits::cache_storage::{
+    CacheStorageMsg, CachedRequest, CachedResponse, CachedResponseType,
+};
+use net_traits::CoreResourceMsg;
+use profile_traits::ipc;
+use serde::{Deserialize, Serialize};
+use servo_url::{ImmutableOrigin, ServoUrl};
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// <https://w3c.github.io/ServiceWorker/#cache-interface>
+#[dom_struct]
+pub struct Cache {
+    reflector_: Reflector,
+    /// The name to cache map entry holding the request response list of this cache.
+    id: CacheId,
+}
+
+impl Cache {
+    fn new_inherited(id: CacheId) -> Cache {
+        Cache {
+            reflector_: Reflector::new(),
+            id: id,
+        }
+    }
+
+    pub fn new(global: &GlobalScope, id: CacheId) -> DomRoot<Cache> {
+        reflect_dom_object(Box::new(Cache::new_inherited(id)), global)
+    }
+
+    /// The steps shared by `match()`, `matchAll()` and `keys()`, which settle `promise`
+    /// with `on_entries` once the matching entries are known.
+    fn query<F>(
+        &self,
+        request: Option<RequestInfo>,
+        options: &CacheQueryOptions,
+        promise: &Rc<Promise>,
+        on_entries: F,
+    ) where
+        F: FnOnce(&Promise, Vec<CacheEntry>) + Send + 'static,
+    {
+        let global = self.global();
+        let options: NetCacheQueryOptions = options.into();
+        let request = match request {
+            Some(request) => {
+                let request = match request_from_info(&global, request) {
+                    Ok(request) => request,
+                    Err(error) => return promise.reject_error(error),
+                };
+                let cached = cached_request(&request);
+                if cached.method != Method::GET && !options.ignore_method {
+                    return on_entries(promise, vec![]);
+                }
+                Some(cached)
+            },
+            None => None,
+        };
+        match_all(
+            &global,
+            promise,
+            CacheSelector::Id(self.id),
+            request,
+            options,
+            on_entries,
+        );
+    }
+
+    /// <https://w3c.github.io/ServiceWorker/#batch-cache-operations-algorithm>
+    ///
+    /// Settles `promise` with `on_success` if the operations succeeded, passing whether
+    /// any entry was removed, or rejects it otherwise.
+    fn batch_operations<F>(
+        &self,
+        promise: &Rc<Promise>,
+        operations: Vec<CacheBatchOperation>,
+        on_success: F,
+    ) where
+        F: FnOnce(&Promise, bool) + Send + 'static,
+    {
+        let id = self.id;
+        send_cache_storage_msg(
+            &self.global(),
+            promise,
+            |sender, origin| CacheStorageMsg::BatchOperations(sender, origin, id, operations),
+            |promise, result| match result {
+                Ok(removed_any) => on_success(promise, removed_any),
+                Err(CacheBatchError::InvalidState) => promise.reject_error(Error::InvalidState),
+                Err(CacheBatchError::QuotaExceeded) => promise.reject_error(Error::QuotaExceeded),
+            },
+        );
+    }
+}
+
+impl CacheMethods for Cache {
+    // https://w3c.github.io/ServiceWorker/#cache-match
+    fn Match(
+        &self,
+        request: RequestInfo,
+        options: &CacheQueryOptions,
+        comp: InRealm,
+    ) -> Rc<Promise> {
+        let promise = Promise::new_in_current_realm(&self.global(), comp);
+        self.query(
+            Some(request),
+            options,
+            &promise,
+            |promise, entries| match entries.first() {
+                Some(entry) => {
+                    promise.resolve_native(&response_from_entry(&promise.global(), entry))
+                },
+                None => promise.resolve_native(&()),
+            },
+        );
+        promise
+    }
+
+    // https://w3c.github.io/ServiceWorker/#cache-matchall
+    fn MatchAll(
+        &self,
+        request: Option<RequestInfo>,
+        options: &CacheQueryOptions,
+        comp: InRealm,
+    ) -> Rc<Promise> {
+        let promise = Promise::new_in_current_realm(&self.global(), comp);
+        self.query(request, options, &promise, |promise, entries| {
+            let global = promise.global();
+            let responses: Vec<_> = entries
+                .iter()
+                .map(|entry| response_from_entry(&global, entry))
+                .collect();
+            promise.resolve_native(&responses);
+        });
+        promise
+    }
+
+    // https://w3c.github.io/ServiceWorker/#cache-add
+    fn Add(&self, request: RequestInfo, comp: InRealm) -> Rc<Promise> {
+        self.AddAll(vec![request], comp)
+    }
+
+    // https://w3c.github.io/ServiceWorker/#cache-addAll
+    fn AddAll(&self, requests: Vec<RequestInfo>, comp: InRealm) -> Rc<Promise> {
+        let global = self.global();
+        let promise = Promise::new_in_current_realm(&global, comp);
+
+        // Steps 1-3.
+        let mut request_objects = vec![];
+        for request in requests {
+            let request = match request_from_info(&global, request) {
+                Ok(request) => request,
+                Err(error) => {
+                    promise.reject_error(error);
+                    return promise;
+                },
+            };
+            let cached = cached_request(&request);
+            if !is_http_scheme(&cached.url) || cached.method != Method::GET {
+                promise.reject_error(Error::Type(
+                    "Only GET requests to http(s) URLs can be cached".to_owned(),
+                ));
+                return promise;
+            }
+            request_objects.push((cached, request));
+        }
+
+        let pending = PendingPuts::new(self, &promise, request_objects.len());
+        if request_objects.is_empty() {
+            pending.resolve_if_complete();
+            return promise;
+        }
+
+        // Steps 4-5.
+        for (index, (cached, request)) in request_objects.into_iter().enumerate() {
+            let fetch_promise = fetch::Fetch(
+                &global,
+                RequestInfo::Request(request),
+                RequestInit::empty(),
+                comp,
+            );
+            let handler = PromiseNativeHandler::new(
+                &global,
+                Some(Box::new(FetchedResponseHandler {
+                    pending: pending.clone(),
+                    request: cached,
+                    index,
+                })),
+                Some(Box::new(PutRejectionHandler {
+                    pending: pending.clone(),
+                })),
+            );
+            fetch_promise.append_native_handler(&handler, comp);
+        }
+        promise
+    }
+
+    // https://w3c.github.io/ServiceWorker/#cache-put
+    fn Put(&self, request: RequestInfo, response: &Response, comp: InRealm) -> Rc<Promise> {
+        let global = self.global();
+        let promise = Promise::new_in_current_realm(&global, comp);
+
+        // Steps 1-4.
+        let request = match request_from_info(&global, request) {
+            Ok(request) => request,
+            Err(error) => {
+                promise.reject_error(error);
+                return promise;
+            },
+        };
+        let cached = cached_request(&request);
+        if !is_http_scheme(&cached.url) || cached.method != Method::GET {
+            promise.reject_error(Error::Type(
+                "Only GET requests to http(s) URLs can be cached".to_owned(),
+            ));
+            return promise;
+        }
+
+        // Steps 5-8.
+        if let Err(error) = check_response(response, false) {
+            promise.reject_error(error);
+            return promise;
+        }
+        if response.BodyUsed() {
+            promise.reject_error(Error::Type("Response body is already used".to_owned()));
+            return promise;
+        }
+
+        // Steps 9-13.
+        let pending = PendingPuts::new(self, &promise, 1);
+        read_response(&pending, 0, cached, DomRoot::from_ref(response), comp);
+        promise
+    }
+
+    // https://w3c.github.io/ServiceWorker/#cache-delete
+    fn Delete(
+        &self,
+        request: RequestInfo,
+        options: &CacheQueryOptions,
+        comp: InRealm,
+    ) -> Rc<Promise> {
+        let global = self.global();
+        let promise = Promise::new_in_current_realm(&global, comp);
+        let options: NetCacheQueryOptions = options.into();
+        let request = match request_from_info(&global, request) {
+            Ok(request) => cached_request(&request),
+            Err(error) => {
+                promise.reject_error(error);
+                return promise;
+            },
+        };
+        if request.method != Method::GET && !options.ignore_method {
+            promise.resolve_native(&false);
+            return promise;
+        }
+        let operations = vec![CacheBatchOperation::Delete(request, options)];
+        self.batch_operations(&promise, operations, |promise, deleted| {
+            promise.resolve_native(&deleted)
+        });
+        promise
+    }
+
+    // https://w3c.github.io/ServiceWorker/#cache-keys
+    fn Keys(
+        &self,
+        request: Option<RequestInfo>,
+        options: &CacheQueryOptions,
+        comp: InRealm,
+    ) -> Rc<Promise> {
+        let promise = Promise::new_in_current_realm(&self.global(), comp);
+        self.query(request, options, &promise, |promise, entries| {
+            let global = promise.global();
+            let requests: Vec<_> = entries
+                .into_iter()
+                .map(|entry| {
+                    let request = entry.request;
+                    Request::new_with_parts(&global, request.url, request.method, request.headers)
+                })
+                .collect();
+            promise.resolve_native(&requests);
+        });
+        promise
+    }
+}
+
+impl<'a> From<&'a CacheQueryOptions> for NetCacheQueryOptions {
+    fn from(options: &'a CacheQueryOptions) -> NetCacheQueryOptions {
+        NetCacheQueryOptions {
+            ignore_search: options.ignoreSearch,
+            ignore_method: options.ignoreMethod,
+            ignore_vary: options.ignoreVary,
+        }
+    }
+}
+
+/// Runs query cache on the selected caches of the origin of the global, and settles
+/// `promise` with `on_entries` once the matching entries are known.
+pub fn match_all<F>(
+    global: &GlobalScope,
+    promise: &Rc<Promise>,
+    selector: CacheSelector,
+    request: Option<CachedRequest>,
+    options: NetCacheQueryOptions,
+    on_entries: F,
+) where
+    F: FnOnce(&Promise, Vec<CacheEntry>) + Send + 'static,
+{
+    send_cache_storage_msg(
+        global,
+        promise,
+        |sender, origin| CacheStorageMsg::MatchAll(sender, origin, selector, request, options),
+        on_entries,
+    );
+}
+
+/// Sends a message to the cache storage of the origin of the global, and settles
+/// `promise` with `on_reply` in a networking task once the resource thread replied.
+/// The promise is rejected if the resource thread cannot be reached or goes away
+/// without replying.
+pub fn send_cache_storage_msg<T, M, F>(
+    global: &GlobalScope,
+    promise: &Rc<Promise>,
+    msg: M,
+    on_reply: F,
+) where
+    T: for<'de> Deserialize<'de> + Serialize + Send + 'static,
+    M: FnOnce(IpcSender<T>, ImmutableOrigin) -> CacheStorageMsg,
+    F: FnOnce(&Promise, T) + Send + 'static,
+{
+    let (sender, receiver) = match ipc::channel(global.time_profiler_chan().clone()) {
+        Ok(channel) => channel,
+        Err(_) => return promise.reject_error(Error::InvalidState),
+    };
+    let msg = msg(sender, global.origin().immutable().clone());
+    if global
+        .core_resource_thread()
+        .send(CoreResourceMsg::CacheStorage(msg))
+        .is_err()
+    {
+        return promise.reject_error(Error::InvalidState);
+    }
+    let mut reply = CacheStorageReply {
+        promise: Some(TrustedPromise::new(promise.clone())),
+        on_reply: Some(on_reply),
+        task_source: global.networking_task_source(),
+        canceller: global.task_canceller(TaskSourceName::Networking),
+    };
+    ROUTER.add_route(
+        receiver.to_opaque(),
+        Box::new(move |message| reply.settle(message.to::<T>().ok())),
+    );
+}
+
+/// The pending reply of the cache storage to a message, which rejects its promise if
+/// it is dropped before being settled, as the router does when the resource thread
+/// drops its sender.
+struct CacheStorageReply<F> {
+    promise: Option<TrustedPromise>,
+    on_reply: Option<F>,
+    task_source: NetworkingTaskSource,
+    canceller: TaskCanceller,
+}
+
+impl<F> CacheStorageReply<F> {
+    fn settle<T>(&mut self, reply: Option<T>)
+    where
+        T: Send + 'static,
+        F: FnOnce(&Promise, T) + Send + 'static,
+    {
+        let (promise, on_reply) = match (self.promise.take(), self.on_reply.take()) {
+            (Some(promise), Some(on_reply)) => (promise, on_reply),
+            _ => return,
+        };
+        let _ = match reply {
+            Some(reply) => self.task_source.queue_with_canceller(
+                task!(cache_storage_reply: move || {
+                    on_reply(&promise.root(), reply);
+                }),
+                &self.canceller,
+            ),
+            None => self
+                .task_source
+                .queue_with_canceller(promise.reject_task(Error::InvalidState), &self.canceller),
+        };
+    }
+}
+
+impl<F> Drop for CacheStorageReply<F> {
+    fn drop(&mut self) {
+        if let Some(promise) = self.promise.take() {
+            let _ = self
+                .task_source
+                .queue_with_canceller(promise.reject_task(Error::InvalidState), &self.canceller);
+        }
+    }
+}
+
+/// Returns the Request object for a `RequestInfo`, creating one for strings.
+pub fn request_from_info(global: &GlobalScope, info: RequestInfo) -> Fallible<DomRoot<Request>> {
+    match info {
+        RequestInfo::Request(request) => Ok(request),
+        info => Request::Constructor(global, info, RequestInit::empty()),
+    }
+}
+
+pub fn cached_request(request: &Request) -> CachedRequest {
+    let net_request = request.get_request();
+    CachedRequest {
+        url: net_request.url(),
+        method: net_request.method,
+        headers: request.Headers().get_headers_list(),
+    }
+}
+
+/// Creates a new Response object holding a copy of a cached response.
+pub fn response_from_entry(global: &GlobalScope, entry: &CacheEntry) -> DomRoot<Response> {
+    let cached = &entry.response;
+    let response = Response::new(global);
+    response.set_type(cached.response_type.into());
+    if let Some(ref url) = cached.url {
+        response.set_final_url(url.clone());
+    }
+    response.set_redirected(cached.redirected);
+    match cached.response_type {
+        CachedResponseType::Error |
+        CachedResponseType::Opaque |
+        CachedResponseType::OpaqueRedirect => {},
+        _ => {
+            response.set_raw_status(Some(cached.status.clone()));
+            response.set_headers(Some(Serde(cached.headers.clone())));
+        },
+    }
+    response.Headers().set_guard(Guard::Immutable);
+    response.stream_chunk(cached.body.clone());
+    response.finish();
+    response
+}
+
+fn is_http_scheme(url: &ServoUrl) -> bool {
+    url.scheme() == "http" || url.scheme() == "https"
+}
+
+/// Checks that a response can be stored in a cache. `Cache.addAll()` additionally
+/// requires an ok status.
+fn check_response(response: &Response, require_ok: bool) -> Fallible<()> {
+    if require_ok && (response.Type() == DOMResponseType::Error || !response.Ok()) {
+        return Err(Error::Type("Response is not ok".to_owned()));
+    }
+    if response.Status() == 206 {
+        return Err(Error::Type(
+            "Partial responses can not be cached".to_owned(),
+        ));
+    }
+    let headers = response.Headers().get_headers_list();
+    if let Some(vary) = headers.typed_get::<Vary>() {
+        if vary.is_any() || vary.iter_strs().any(|name| name == "*") {
+            return Err(Error::Type(
+                "Responses varying on every header can not be cached".to_owned(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The state shared by the promise handlers of a `Cache.put()` or `Cache.addAll()`
+/// call while the bodies of the responses are read.
+#[derive(JSTraceable, MallocSizeOf)]
+struct PendingPuts {
+    cache: DomRoot<Cache>,
+    #[ignore_malloc_size_of = "Rc are hard"]
+    result_promise: Rc<Promise>,
+    entries: DomRefCell<Vec<Option<CacheEntry>>>,
+    failed: Cell<bool>,
+}
+
+impl PendingPuts {
+    fn new(cache: &Cache, result_promise: &Rc<Promise>, len: usize) -> Rc<PendingPuts> {
+        Rc::new(PendingPuts {
+            cache: DomRoot::from_ref(cache),
+            result_promise: result_promise.clone(),
+            entries: DomRefCell::new(vec![None; len]),
+            failed: Cell::new(false),
+        })
+    }
+
+    fn fail(&self, cx: SafeJSContext, reason: HandleValue) {
+        if !self.failed.replace(true) {
+            self.result_promise.reject(cx, reason);
+        }
+    }
+
+    fn fail_with_error(&self, error: Error) {
+        if !self.failed.replace(true) {
+            self.result_promise.reject_error(error);
+        }
+    }
+
+    /// Writes all entries in a single batch once every body has been read.
+    fn resolve_if_complete(&self) {
+        if self.failed.get() {
+            return;
+        }
+        let operations = {
+            let entries = self.entries.borrow();
+            if entries.iter().any(Option::is_none) {
+                return;
+            }
+            entries
+                .iter()
+                .map(|entry| CacheBatchOperation::Put(entry.clone().unwrap()))
+                .collect()
+        };
+        self.cache
+            .batch_operations(&self.result_promise, operations, |promise, _| {
+                promise.resolve_native(&())
+            });
+    }
+}
+
+/// Reads the body of a response and records it as the entry at `index` of a pending put.
+fn read_response(
+    pending: &Rc<PendingPuts>,
+    index: usize,
+    request: CachedRequest,
+    response: DomRoot<Response>,
+    comp: InRealm,
+) {
+    let global = response.global();
+    let body_promise = consume_body(&*response, BodyType::ArrayBuffer);
+    let handler = PromiseNativeHandler::new(
+        &global,
+        Some(Box::new(ResponseBodyHandler {
+            pending: pending.clone(),
+            request,
+            response,
+            index,
+        })),
+        Some(Box::new(PutRejectionHandler {
+            pending: pending.clone(),
+        })),
+    );
+    body_promise.append_native_handler(&handler, comp);
+}
+
+/// Handles the responses fetched by `Cache.addAll()`.
+#[derive(JSTraceable, MallocSizeOf)]
+struct FetchedResponseHandler {
+    #[ignore_malloc_size_of = "Rc are hard"]
+    pending: Rc<PendingPuts>,
+    request: CachedRequest,
+    index: usize,
+}
+
+impl Callback for FetchedResponseHandler {
+    /// Steps 5.7 of <https://w3c.github.io/ServiceWorker/#cache-addAll>
+    fn callback(&self, cx: SafeJSContext, v: HandleValue, realm: InRealm) {
+        if self.pending.failed.get() {
+            return;
+        }
+        let response = match root_from_handlevalue::<Response>(v, *cx) {
+            Ok(response) => response,
+            Err(()) => {
+                return self
+                    .pending
+                    .fail_with_error(Error::Type("Not a Response".to_owned()))
+            },
+        };
+        if let Err(error) = check_response(&response, true) {
+            return self.pending.fail_with_error(error);
+        }
+        read_response(
+            &self.pending,
+            self.index,
+            self.request.clone(),
+            response,
+            realm,
+        );
+    }
+}
+
+/// Stores a response once its body has been read.
+#[derive(JSTraceable, MallocSizeOf)]
+struct ResponseBodyHandler {
+    #[ignore_malloc_size_of = "Rc are hard"]
+    pending: Rc<PendingPuts>,
+    request: CachedRequest,
+    response: DomRoot<Response>,
+    index: usize,
+}
+
+impl Callback for ResponseBodyHandler {
+    #[allow(unsafe_code)]
+    fn callback(&self, cx: SafeJSContext, v: HandleValue, _realm: InRealm) {
+        if self.pending.failed.get() {
+            return;
+        }
+        if !v.is_object() {
+            return self
+                .pending
+                .fail_with_error(Error::Type("Could not read the response body".to_owned()));
+        }
+        typedarray!(in(*cx) let buffer: ArrayBuffer = v.to_object());
+        let body = match buffer {
+            Ok(buffer) => buffer.to_vec(),
+            Err(()) => {
+                return self
+                    .pending
+                    .fail_with_error(Error::Type("Could not read the response body".to_owned()));
+            },
+        };
+        let response = &self.response;
+        let url = ServoUrl::parse(&response.Url()).ok();
+        let entry = CacheEntry {
+            request: self.request.clone(),
+            response: CachedResponse {
+                response_type: response.Type().into(),
+                url,
+                redirected: response.Redirected(),
+                status: (response.Status(), response.StatusText().into()),
+                headers: response.Headers().get_headers_list(),
+                body,
+            },
+        };
+        self.pending.entries.borrow_mut()[self.index] = Some(entry);
+        self.pending.resolve_if_complete();
+    }
+}
+
+/// Rejects a pending `Cache.put()` or `Cache.addAll()` when a fetch or body read fails.
+#[derive(JSTraceable, MallocSizeOf)]
+struct PutRejectionHandler {
+    #[ignore_malloc_size_of = "Rc are hard"]
+    pending: Rc<PendingPuts>,
+}
+
+impl Callback for PutRejectionHandler {
+    fn callback(&self, cx: SafeJSContext, v: HandleValue, _realm: InRealm) {
+        self.pending.fail(cx, v);
+    }
+}
+
+impl Into<CachedResponseType> for DOMResponseType {
+    fn into(self) -> CachedResponseType {
+        match self {
+            DOMResponseType::Basic => CachedResponseType::Basic,
+            DOMResponseType::Cors => CachedResponseType::Cors,
+            DOMResponseType::Default => CachedResponseType::Default,
+            DOMResponseType::Error => CachedResponseType::Error,
+            DOMResponseType::Opaque => CachedResponseType::Opaque,
+            DOMResponseType::Opaqueredirect => CachedResponseType::OpaqueRedirect,
+        }
+    }
+}
+
+impl Into<DOMResponseType> for CachedResponseType {
+    fn into(self) -> DOMResponseType {
+        match self {
+            CachedResponseType::Basic => DOMResponseType::Basic,
+            CachedResponseType::Cors => DOMResponseType::Cors,
+            CachedResponseType::Default => DOMResponseType::Default,
+            CachedResponseType::Error => DOMResponseType::Error,
+            CachedResponseType::Opaque => DOMResponseType::Opaque,
+            CachedResponseType::OpaqueRedirect => DOMResponseType::Opaqueredirect,
+        }
+    }
+}
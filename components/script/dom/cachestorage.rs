@@ -0,0 +1,154 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::CacheStorageBinding::{
+    CacheStorageMethods, MultiCacheQueryOptions,
+};
+use crate::dom::bindings::codegen::Bindings::RequestBinding::RequestInfo;
+use crate::dom::bindings::error::Error;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::bindings::str::DOMString;
+use crate::dom::cache::{self, Cache};
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::realms::InRealm;
+use dom_struct::dom_struct;
+use http::Method;
+use ipc_channel::ipc::IpcSender;
+use net_traits::cache_storage::{CacheQueryOptions, CacheSelector, CacheStorageMsg};
+use serde::{Deserialize, Serialize};
+use servo_url::ImmutableOrigin;
+use std::rc::Rc;
+
+/// <https://w3c.github.io/ServiceWorker/#cachestorage-interface>
+#[dom_struct]
+pub struct CacheStorage {
+    reflector_: Reflector,
+}
+
+impl CacheStorage {
+    fn new_inherited() -> CacheStorage {
+        CacheStorage {
+            reflector_: Reflector::new(),
+        }
+    }
+
+    pub fn new(global: &GlobalScope) -> DomRoot<CacheStorage> {
+        reflect_dom_object(Box::new(CacheStorage::new_inherited()), global)
+    }
+
+    /// Sends a message to the cache storage of the origin of this global, and settles
+    /// `promise` with `on_reply` once it replied. Rejects the promise for opaque
+    /// origins, which have no cache storage.
+    fn send<T, M, F>(&self, promise: &Rc<Promise>, msg: M, on_reply: F)
+    where
+        T: for<'de> Deserialize<'de> + Serialize + Send + 'static,
+        M: FnOnce(IpcSender<T>, ImmutableOrigin) -> CacheStorageMsg,
+        F: FnOnce(&Promise, T) + Send + 'static,
+    {
+        let global = self.global();
+        if !global.origin().is_tuple() {
+            return promise.reject_error(Error::Security);
+        }
+        cache::send_cache_storage_msg(&global, promise, msg, on_reply);
+    }
+}
+
+impl CacheStorageMethods for CacheStorage {
+    // https://w3c.github.io/ServiceWorker/#cache-storage-match
+    fn Match(
+        &self,
+        request: RequestInfo,
+        options: &MultiCacheQueryOptions,
+        comp: InRealm,
+    ) -> Rc<Promise> {
+        let global = self.global();
+        let promise = Promise::new_in_current_realm(&global, comp);
+        if !global.origin().is_tuple() {
+            promise.reject_error(Error::Security);
+            return promise;
+        }
+        let query_options: CacheQueryOptions = (&options.parent).into();
+        let request = match cache::request_from_info(&global, request) {
+            Ok(request) => cache::cached_request(&request),
+            Err(error) => {
+                promise.reject_error(error);
+                return promise;
+            },
+        };
+        if request.method != Method::GET && !query_options.ignore_method {
+            promise.resolve_native(&());
+            return promise;
+        }
+        let selector = match options.cacheName {
+            Some(ref name) => CacheSelector::Name(name.to_string()),
+            None => CacheSelector::All,
+        };
+        cache::match_all(
+            &global,
+            &promise,
+            selector,
+            Some(request),
+            query_options,
+            |promise, entries| match entries.first() {
+                Some(entry) => {
+                    promise.resolve_native(&cache::response_from_entry(&promise.global(), entry))
+                },
+                None => promise.resolve_native(&()),
+            },
+        );
+        promise
+    }
+
+    // https://w3c.github.io/ServiceWorker/#cache-storage-has
+    fn Has(&self, cache_name: DOMString, comp: InRealm) -> Rc<Promise> {
+        let promise = Promise::new_in_current_realm(&self.global(), comp);
+        let name = String::from(cache_name);
+        self.send(
+            &promise,
+            |sender, origin| CacheStorageMsg::Has(sender, origin, name),
+            |promise, has| promise.resolve_native(&has),
+        );
+        promise
+    }
+
+    // https://w3c.github.io/ServiceWorker/#cache-storage-open
+    fn Open(&self, cache_name: DOMString, comp: InRealm) -> Rc<Promise> {
+        let promise = Promise::new_in_current_realm(&self.global(), comp);
+        let name = String::from(cache_name);
+        self.send(
+            &promise,
+            |sender, origin| CacheStorageMsg::Open(sender, origin, name),
+            |promise, id| promise.resolve_native(&Cache::new(&promise.global(), id)),
+        );
+        promise
+    }
+
+    // https://w3c.github.io/ServiceWorker/#cache-storage-delete
+    fn Delete(&self, cache_name: DOMString, comp: InRealm) -> Rc<Promise> {
+        let promise = Promise::new_in_current_realm(&self.global(), comp);
+        let name = String::from(cache_name);
+        self.send(
+            &promise,
+            |sender, origin| CacheStorageMsg::Delete(sender, origin, name),
+            |promise, deleted| promise.resolve_native(&deleted),
+        );
+        promise
+    }
+
+    // https://w3c.github.io/ServiceWorker/#cache-storage-keys
+    fn Keys(&self, comp: InRealm) -> Rc<Promise> {
+        let promise = Promise::new_in_current_realm(&self.global(), comp);
+        self.send(
+            &promise,
+            CacheStorageMsg::Keys,
+            |promise, keys: Vec<String>| {
+                let keys: Vec<DOMString> = keys.into_iter().map(DOMString::from).collect();
+                promise.resolve_native(&keys);
+            },
+        );
+        promise
+    }
+}
@@ -0,0 +1,185 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::CompressionStreamBinding::{
+    CompressionFormat, CompressionStreamMethods,
+};
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::readablestream::ReadableStream;
+use crate::dom::transformstream::TransformStream;
+use crate::dom::transformstreamdefaultcontroller::{
+    buffer_source_bytes, TransformStreamDefaultController, TransformerType,
+};
+use crate::dom::writablestream::WritableStream;
+use crate::script_runtime::JSContext;
+use dom_struct::dom_struct;
+use flate2::write::{DeflateEncoder, GzEncoder, ZlibEncoder};
+use flate2::Compression;
+use js::rust::HandleValue;
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::mem;
+
+/// <https://wicg.github.io/compression/#compressionstream-context>
+///
+/// The compressed bytes are written to a buffer, which is emptied into a
+/// chunk after each write.
+enum Compressor {
+    /// "deflate", the ZLIB format.
+    Deflate(ZlibEncoder<Vec<u8>>),
+    /// "deflate-raw", the DEFLATE format.
+    DeflateRaw(DeflateEncoder<Vec<u8>>),
+    /// "gzip", the GZIP format.
+    Gzip(GzEncoder<Vec<u8>>),
+}
+
+unsafe_no_jsmanaged_fields!(Compressor);
+
+impl Compressor {
+    fn new(format: CompressionFormat) -> Compressor {
+        match format {
+            CompressionFormat::Deflate => {
+                Compressor::Deflate(ZlibEncoder::new(Vec::new(), Compression::default()))
+            },
+            CompressionFormat::Deflate_raw => {
+                Compressor::DeflateRaw(DeflateEncoder::new(Vec::new(), Compression::default()))
+            },
+            CompressionFormat::Gzip => {
+                Compressor::Gzip(GzEncoder::new(Vec::new(), Compression::default()))
+            },
+        }
+    }
+
+    fn write(&mut self, input: &[u8]) -> io::Result<Vec<u8>> {
+        match *self {
+            Compressor::Deflate(ref mut encoder) => {
+                encoder.write_all(input)?;
+                Ok(mem::replace(encoder.get_mut(), Vec::new()))
+            },
+            Compressor::DeflateRaw(ref mut encoder) => {
+                encoder.write_all(input)?;
+                Ok(mem::replace(encoder.get_mut(), Vec::new()))
+            },
+            Compressor::Gzip(ref mut encoder) => {
+                encoder.write_all(input)?;
+                Ok(mem::replace(encoder.get_mut(), Vec::new()))
+            },
+        }
+    }
+
+    fn finish(&mut self) -> io::Result<Vec<u8>> {
+        match *self {
+            Compressor::Deflate(ref mut encoder) => {
+                encoder.try_finish()?;
+                Ok(mem::replace(encoder.get_mut(), Vec::new()))
+            },
+            Compressor::DeflateRaw(ref mut encoder) => {
+                encoder.try_finish()?;
+                Ok(mem::replace(encoder.get_mut(), Vec::new()))
+            },
+            Compressor::Gzip(ref mut encoder) => {
+                encoder.try_finish()?;
+                Ok(mem::replace(encoder.get_mut(), Vec::new()))
+            },
+        }
+    }
+}
+
+/// The state of the transformer of a `CompressionStream`.
+#[derive(JSTraceable, MallocSizeOf)]
+pub struct CompressionTransformer {
+    #[ignore_malloc_size_of = "Defined in flate2"]
+    compressor: RefCell<Compressor>,
+}
+
+impl CompressionTransformer {
+    /// <https://wicg.github.io/compression/#compress-and-enqueue-a-chunk>
+    pub fn transform(
+        &self,
+        cx: JSContext,
+        controller: &TransformStreamDefaultController,
+        chunk: HandleValue,
+    ) -> Fallible<()> {
+        // Step 1.
+        let input = buffer_source_bytes(chunk)?;
+
+        // Steps 2-5.
+        let output = self
+            .compressor
+            .borrow_mut()
+            .write(&input)
+            .map_err(|_| Error::Type("Compression failed".to_owned()))?;
+        if output.is_empty() {
+            return Ok(());
+        }
+        controller.enqueue_bytes(cx, &output)
+    }
+
+    /// <https://wicg.github.io/compression/#compress-flush-and-enqueue>
+    pub fn flush(
+        &self,
+        cx: JSContext,
+        controller: &TransformStreamDefaultController,
+    ) -> Fallible<()> {
+        let output = self
+            .compressor
+            .borrow_mut()
+            .finish()
+            .map_err(|_| Error::Type("Compression failed".to_owned()))?;
+        if output.is_empty() {
+            return Ok(());
+        }
+        controller.enqueue_bytes(cx, &output)
+    }
+}
+
+/// <https://wicg.github.io/compression/#compression-stream>
+#[dom_struct]
+pub struct CompressionStream {
+    reflector_: Reflector,
+    /// <https://streams.spec.whatwg.org/#generictransformstream-transform>
+    transform: Dom<TransformStream>,
+}
+
+impl CompressionStream {
+    fn new_inherited(transform: &TransformStream) -> CompressionStream {
+        CompressionStream {
+            reflector_: Reflector::new(),
+            transform: Dom::from_ref(transform),
+        }
+    }
+
+    /// <https://wicg.github.io/compression/#dom-compressionstream-compressionstream>
+    #[allow(non_snake_case, unrooted_must_root)]
+    pub fn Constructor(
+        global: &GlobalScope,
+        format: CompressionFormat,
+    ) -> Fallible<DomRoot<CompressionStream>> {
+        let transform = TransformStream::new_with_transformer(
+            global,
+            TransformerType::Compression(CompressionTransformer {
+                compressor: RefCell::new(Compressor::new(format)),
+            }),
+        )?;
+        Ok(reflect_dom_object(
+            Box::new(CompressionStream::new_inherited(&transform)),
+            global,
+        ))
+    }
+}
+
+impl CompressionStreamMethods for CompressionStream {
+    /// <https://streams.spec.whatwg.org/#dom-generictransformstream-readable>
+    fn Readable(&self) -> DomRoot<ReadableStream> {
+        self.transform.get_readable()
+    }
+
+    /// <https://streams.spec.whatwg.org/#dom-generictransformstream-writable>
+    fn Writable(&self) -> DomRoot<WritableStream> {
+        self.transform.get_writable()
+    }
+}
@@ -0,0 +1,136 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::audiobuffer::AudioBuffer;
+use crate::dom::audionode::AudioNode;
+use crate::dom::baseaudiocontext::BaseAudioContext;
+use crate::dom::bindings::codegen::Bindings::AudioBufferBinding::AudioBufferMethods;
+use crate::dom::bindings::codegen::Bindings::AudioNodeBinding::AudioNodeMethods;
+use crate::dom::bindings::codegen::Bindings::AudioNodeBinding::{
+    ChannelCountMode, ChannelInterpretation,
+};
+use crate::dom::bindings::codegen::Bindings::BaseAudioContextBinding::BaseAudioContextMethods;
+use crate::dom::bindings::codegen::Bindings::ConvolverNodeBinding::ConvolverNodeMethods;
+use crate::dom::bindings::codegen::Bindings::ConvolverNodeBinding::ConvolverOptions;
+use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
+use crate::dom::bindings::reflector::reflect_dom_object;
+use crate::dom::bindings::root::{DomRoot, MutNullableDom};
+use crate::dom::window::Window;
+use dom_struct::dom_struct;
+use servo_media::audio::convolver_node::{ConvolverNodeMessage, ConvolverNodeOptions};
+use servo_media::audio::node::{AudioNodeInit, AudioNodeMessage};
+use std::cell::Cell;
+
+#[dom_struct]
+pub struct ConvolverNode {
+    node: AudioNode,
+    buffer: MutNullableDom<AudioBuffer>,
+    normalize: Cell<bool>,
+}
+
+impl ConvolverNode {
+    #[allow(unrooted_must_root)]
+    pub fn new_inherited(
+        _: &Window,
+        context: &BaseAudioContext,
+        options: &ConvolverOptions,
+    ) -> Fallible<ConvolverNode> {
+        let node_options = options.parent.unwrap_or(
+            2,
+            ChannelCountMode::Clamped_max,
+            ChannelInterpretation::Speakers,
+        );
+        if node_options.mode == ChannelCountMode::Max {
+            return Err(Error::NotSupported);
+        }
+        if node_options.count > 2 || node_options.count == 0 {
+            return Err(Error::NotSupported);
+        }
+        let normalize = !options.disableNormalization;
+        let node = AudioNode::new_inherited(
+            AudioNodeInit::ConvolverNode(ConvolverNodeOptions {
+                buffer: None,
+                normalize,
+            }),
+            context,
+            node_options,
+            1, // inputs
+            1, // outputs
+        )?;
+        let node = ConvolverNode {
+            node,
+            buffer: Default::default(),
+            normalize: Cell::new(normalize),
+        };
+        if let Some(Some(ref buffer)) = options.buffer {
+            node.SetBuffer(Some(&**buffer))?;
+        }
+        Ok(node)
+    }
+
+    #[allow(unrooted_must_root)]
+    pub fn new(
+        window: &Window,
+        context: &BaseAudioContext,
+        options: &ConvolverOptions,
+    ) -> Fallible<DomRoot<ConvolverNode>> {
+        let node = ConvolverNode::new_inherited(window, context, options)?;
+        Ok(reflect_dom_object(Box::new(node), window))
+    }
+
+    #[allow(non_snake_case)]
+    pub fn Constructor(
+        window: &Window,
+        context: &BaseAudioContext,
+        options: &ConvolverOptions,
+    ) -> Fallible<DomRoot<ConvolverNode>> {
+        ConvolverNode::new(window, context, options)
+    }
+}
+
+impl ConvolverNodeMethods for ConvolverNode {
+    // https://webaudio.github.io/web-audio-api/#dom-convolvernode-buffer
+    fn GetBuffer(&self) -> Option<DomRoot<AudioBuffer>> {
+        self.buffer.get()
+    }
+
+    // https://webaudio.github.io/web-audio-api/#dom-convolvernode-buffer
+    fn SetBuffer(&self, new_buffer: Option<&AudioBuffer>) -> ErrorResult {
+        if let Some(buffer) = new_buffer {
+            // The impulse response must be mono, stereo or four channel
+            // "true" stereo, and match the sample rate of the context.
+            match buffer.NumberOfChannels() {
+                1 | 2 | 4 => (),
+                _ => return Err(Error::NotSupported),
+            }
+            if *buffer.SampleRate() != *self.node.Context().SampleRate() {
+                return Err(Error::NotSupported);
+            }
+        }
+
+        self.buffer.set(new_buffer);
+
+        // The normalization scale is computed from the impulse response at
+        // the time the buffer is set, so `normalize` only applies to the
+        // buffers set after it changes.
+        let channels = new_buffer.and_then(|buffer| (*buffer.get_channels()).clone());
+        self.node.message(AudioNodeMessage::ConvolverNode(
+            ConvolverNodeMessage::SetBuffer {
+                buffer: channels,
+                normalize: self.normalize.get(),
+            },
+        ));
+        Ok(())
+    }
+
+    // https://webaudio.github.io/web-audio-api/#dom-convolvernode-normalize
+    fn Normalize(&self) -> bool {
+        self.normalize.get()
+    }
+
+    // https://webaudio.github.io/web-audio-api/#dom-convolvernode-normalize
+    fn SetNormalize(&self, normalize: bool) {
+        self.normalize.set(normalize);
+    }
+}
@@ -0,0 +1,94 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::CountQueuingStrategyBinding::CountQueuingStrategyMethods;
+use crate::dom::bindings::codegen::Bindings::FunctionBinding::Function;
+use crate::dom::bindings::codegen::Bindings::QueuingStrategyBinding::QueuingStrategyInit;
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::globalscope::GlobalScope;
+use crate::realms::enter_realm;
+use dom_struct::dom_struct;
+use js::jsapi::{CallArgs, JSContext, JS_GetFunctionObject, JS_NewFunction};
+use js::jsval::{Int32Value, JSVal};
+use std::rc::Rc;
+
+#[dom_struct]
+pub struct CountQueuingStrategy {
+    reflector_: Reflector,
+    high_water_mark: f64,
+}
+
+impl CountQueuingStrategy {
+    fn new_inherited(high_water_mark: f64) -> CountQueuingStrategy {
+        CountQueuingStrategy {
+            reflector_: Reflector::new(),
+            high_water_mark,
+        }
+    }
+
+    pub fn new(global: &GlobalScope, high_water_mark: f64) -> DomRoot<CountQueuingStrategy> {
+        reflect_dom_object(
+            Box::new(CountQueuingStrategy::new_inherited(high_water_mark)),
+            global,
+        )
+    }
+
+    /// <https://streams.spec.whatwg.org/#cqs-constructor>
+    #[allow(non_snake_case)]
+    pub fn Constructor(
+        global: &GlobalScope,
+        init: &QueuingStrategyInit,
+    ) -> DomRoot<CountQueuingStrategy> {
+        CountQueuingStrategy::new(global, init.highWaterMark)
+    }
+}
+
+impl CountQueuingStrategyMethods for CountQueuingStrategy {
+    /// <https://streams.spec.whatwg.org/#cqs-high-water-mark>
+    fn HighWaterMark(&self) -> f64 {
+        self.high_water_mark
+    }
+
+    /// <https://streams.spec.whatwg.org/#cqs-size>
+    #[allow(unsafe_code)]
+    fn Size(&self) -> Fallible<Rc<Function>> {
+        let global = self.global();
+        if let Some(size) = global.get_count_queuing_strategy_size() {
+            return Ok(size);
+        }
+
+        // https://streams.spec.whatwg.org/#count-queuing-strategy-size-function
+        let cx = global.get_cx();
+        let _ac = enter_realm(&*global);
+        let size = unsafe {
+            let fun = JS_NewFunction(
+                *cx,
+                Some(count_queuing_strategy_size),
+                0,
+                0,
+                b"size\0".as_ptr() as *const _,
+            );
+            if fun.is_null() {
+                return Err(Error::JSFailed);
+            }
+            Function::new(cx, JS_GetFunctionObject(fun))
+        };
+        global.set_count_queuing_strategy_size(size.clone());
+        Ok(size)
+    }
+}
+
+/// Returns 1, whatever the chunk.
+#[allow(unsafe_code)]
+unsafe extern "C" fn count_queuing_strategy_size(
+    _cx: *mut JSContext,
+    argc: u32,
+    vp: *mut JSVal,
+) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    *args.rval() = Int32Value(1);
+    true
+}
@@ -5,9 +5,11 @@
 use crate::dom::bindings::cell::DomRefCell;
 use crate::dom::bindings::codegen::Bindings::CryptoBinding::CryptoMethods;
 use crate::dom::bindings::error::{Error, Fallible};
-use crate::dom::bindings::reflector::{reflect_dom_object, Reflector};
-use crate::dom::bindings::root::DomRoot;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{DomRoot, MutNullableDom};
+use crate::dom::bindings::str::DOMString;
 use crate::dom::globalscope::GlobalScope;
+use crate::dom::subtlecrypto::SubtleCrypto;
 use crate::script_runtime::JSContext;
 use dom_struct::dom_struct;
 use js::jsapi::JSObject;
@@ -16,6 +18,7 @@ use js::rust::CustomAutoRooterGuard;
 use js::typedarray::ArrayBufferView;
 use servo_rand::{RngCore, ServoRng};
 use std::ptr::NonNull;
+use uuid::Uuid;
 
 unsafe_no_jsmanaged_fields!(ServoRng);
 
@@ -25,6 +28,7 @@ pub struct Crypto {
     reflector_: Reflector,
     #[ignore_malloc_size_of = "Defined in rand"]
     rng: DomRefCell<ServoRng>,
+    subtle: MutNullableDom<SubtleCrypto>,
 }
 
 impl Crypto {
@@ -32,6 +36,7 @@ impl Crypto {
         Crypto {
             reflector_: Reflector::new(),
             rng: DomRefCell::new(ServoRng::new()),
+            subtle: MutNullableDom::default(),
         }
     }
 
@@ -41,6 +46,11 @@ impl Crypto {
 }
 
 impl CryptoMethods for Crypto {
+    // https://w3c.github.io/webcrypto/#dfn-Crypto-attribute-subtle
+    fn Subtle(&self) -> DomRoot<SubtleCrypto> {
+        self.subtle.or_init(|| SubtleCrypto::new(&self.global()))
+    }
+
     #[allow(unsafe_code)]
     // https://dvcs.w3.org/hg/webcrypto-api/raw-file/tip/spec/Overview.html#Crypto-method-getRandomValues
     fn GetRandomValues(
@@ -62,6 +72,11 @@ impl CryptoMethods for Crypto {
 
         unsafe { Ok(NonNull::new_unchecked(*input.underlying_object())) }
     }
+
+    // https://w3c.github.io/webcrypto/#Crypto-method-randomUUID
+    fn RandomUUID(&self) -> DOMString {
+        DOMString::from(Uuid::new_v4().to_hyphenated().to_string())
+    }
 }
 
 fn is_integer_buffer(array_type: Type) -> bool {
@@ -0,0 +1,232 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::CryptoKeyBinding::{
+    CryptoKeyMethods, KeyType, KeyUsage,
+};
+use crate::dom::bindings::reflector::{reflect_dom_object, Reflector};
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::bindings::utils::set_dictionary_property;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::subtlecrypto::{CryptoAlgorithm, NamedCurve};
+use crate::realms::enter_realm;
+use crate::script_runtime::JSContext;
+use dom_struct::dom_struct;
+use js::conversions::ToJSValConvertible;
+use js::jsapi::{Heap, JSObject, JS_NewPlainObject};
+use js::jsval::{ObjectValue, UndefinedValue};
+use js::rust::{HandleObject, MutableHandleObject};
+use js::typedarray::{CreateWith, Uint8Array};
+use openssl::pkey::{PKey, Private, Public};
+use std::ptr::{self, NonNull};
+
+/// <https://w3c.github.io/webcrypto/#dfn-CryptoKey-slot-handle>
+#[derive(Clone)]
+pub enum KeyHandle {
+    /// The raw bytes of an AES, HMAC, HKDF or PBKDF2 key.
+    Secret(Vec<u8>),
+    /// The private half of an RSA or EC key pair.
+    Private(PKey<Private>),
+    /// The public half of an RSA or EC key pair.
+    Public(PKey<Public>),
+}
+
+/// The `[[algorithm]]` internal slot of a key, from which its `algorithm`
+/// attribute is created.
+#[derive(Clone, Debug, PartialEq)]
+pub enum KeyAlgorithm {
+    /// <https://w3c.github.io/webcrypto/#AesKeyAlgorithm-dictionary>
+    Aes { name: CryptoAlgorithm, length: u16 },
+    /// <https://w3c.github.io/webcrypto/#HmacKeyAlgorithm-dictionary>
+    Hmac { hash: CryptoAlgorithm, length: u32 },
+    /// <https://w3c.github.io/webcrypto/#RsaHashedKeyAlgorithm-dictionary>
+    RsaHashed {
+        name: CryptoAlgorithm,
+        modulus_length: u32,
+        public_exponent: Vec<u8>,
+        hash: CryptoAlgorithm,
+    },
+    /// <https://w3c.github.io/webcrypto/#EcKeyAlgorithm-dictionary>
+    Ec {
+        name: CryptoAlgorithm,
+        named_curve: NamedCurve,
+    },
+    /// <https://w3c.github.io/webcrypto/#key-algorithm-dictionary>, used by
+    /// the key derivation algorithms.
+    Plain(CryptoAlgorithm),
+}
+
+impl KeyAlgorithm {
+    pub fn name(&self) -> CryptoAlgorithm {
+        match *self {
+            KeyAlgorithm::Aes { name, .. } |
+            KeyAlgorithm::RsaHashed { name, .. } |
+            KeyAlgorithm::Ec { name, .. } |
+            KeyAlgorithm::Plain(name) => name,
+            KeyAlgorithm::Hmac { .. } => CryptoAlgorithm::Hmac,
+        }
+    }
+
+    /// Creates the dictionary object exposed as the `algorithm` attribute.
+    #[allow(unsafe_code)]
+    fn to_object(&self, cx: JSContext, mut rval: MutableHandleObject) {
+        unsafe {
+            rval.set(JS_NewPlainObject(*cx));
+            assert!(!rval.is_null());
+            set_string_property(cx, rval.handle(), "name", self.name().as_str());
+            match *self {
+                KeyAlgorithm::Aes { length, .. } => {
+                    set_property(cx, rval.handle(), "length", &length);
+                },
+                KeyAlgorithm::Hmac { hash, length } => {
+                    set_hash_property(cx, rval.handle(), hash);
+                    set_property(cx, rval.handle(), "length", &length);
+                },
+                KeyAlgorithm::RsaHashed {
+                    modulus_length,
+                    ref public_exponent,
+                    hash,
+                    ..
+                } => {
+                    set_property(cx, rval.handle(), "modulusLength", &modulus_length);
+                    rooted!(in(*cx) let mut exponent = ptr::null_mut::<JSObject>());
+                    assert!(Uint8Array::create(
+                        *cx,
+                        CreateWith::Slice(public_exponent),
+                        exponent.handle_mut()
+                    )
+                    .is_ok());
+                    rooted!(in(*cx) let exponent = ObjectValue(exponent.get()));
+                    let _ = set_dictionary_property(
+                        *cx,
+                        rval.handle(),
+                        "publicExponent",
+                        exponent.handle(),
+                    );
+                    set_hash_property(cx, rval.handle(), hash);
+                },
+                KeyAlgorithm::Ec { named_curve, .. } => {
+                    set_string_property(cx, rval.handle(), "namedCurve", named_curve.as_str());
+                },
+                KeyAlgorithm::Plain(_) => {},
+            }
+        }
+    }
+}
+
+#[allow(unsafe_code)]
+unsafe fn set_property<T: ToJSValConvertible>(
+    cx: JSContext,
+    object: HandleObject,
+    property: &str,
+    value: &T,
+) {
+    rooted!(in(*cx) let mut value_ = UndefinedValue());
+    value.to_jsval(*cx, value_.handle_mut());
+    let _ = set_dictionary_property(*cx, object, property, value_.handle());
+}
+
+#[allow(unsafe_code)]
+unsafe fn set_string_property(cx: JSContext, object: HandleObject, property: &str, value: &str) {
+    set_property(cx, object, property, &value.to_owned());
+}
+
+/// Sets the `hash` member of a key algorithm dictionary, which is itself a
+/// `KeyAlgorithm` dictionary.
+#[allow(unsafe_code)]
+unsafe fn set_hash_property(cx: JSContext, object: HandleObject, hash: CryptoAlgorithm) {
+    rooted!(in(*cx) let mut hash_object = ptr::null_mut::<JSObject>());
+    KeyAlgorithm::Plain(hash).to_object(cx, hash_object.handle_mut());
+    rooted!(in(*cx) let hash_value = ObjectValue(hash_object.get()));
+    let _ = set_dictionary_property(*cx, object, "hash", hash_value.handle());
+}
+
+/// The internal slots of a key, which can be sent to the thread performing
+/// the cryptographic operations.
+#[derive(Clone)]
+pub struct KeyData {
+    /// <https://w3c.github.io/webcrypto/#dfn-CryptoKey-slot-type>
+    pub key_type: KeyType,
+    /// <https://w3c.github.io/webcrypto/#dfn-CryptoKey-slot-extractable>
+    pub extractable: bool,
+    /// <https://w3c.github.io/webcrypto/#dfn-CryptoKey-slot-algorithm>
+    pub algorithm: KeyAlgorithm,
+    /// <https://w3c.github.io/webcrypto/#dfn-CryptoKey-slot-usages>
+    pub usages: Vec<KeyUsage>,
+    /// <https://w3c.github.io/webcrypto/#dfn-CryptoKey-slot-handle>
+    pub handle: KeyHandle,
+}
+
+unsafe_no_jsmanaged_fields!(KeyData);
+
+/// <https://w3c.github.io/webcrypto/#cryptokey-interface>
+#[dom_struct]
+pub struct CryptoKey {
+    reflector_: Reflector,
+    #[ignore_malloc_size_of = "Defined in openssl"]
+    data: KeyData,
+    /// <https://w3c.github.io/webcrypto/#dfn-CryptoKey-slot-algorithm_cached>
+    #[ignore_malloc_size_of = "Defined in rust-mozjs"]
+    algorithm_cached: Heap<*mut JSObject>,
+    /// <https://w3c.github.io/webcrypto/#dfn-CryptoKey-slot-usages_cached>
+    #[ignore_malloc_size_of = "Defined in rust-mozjs"]
+    usages_cached: Heap<*mut JSObject>,
+}
+
+impl CryptoKey {
+    fn new_inherited(data: KeyData) -> CryptoKey {
+        CryptoKey {
+            reflector_: Reflector::new(),
+            data,
+            algorithm_cached: Heap::default(),
+            usages_cached: Heap::default(),
+        }
+    }
+
+    #[allow(unsafe_code)]
+    pub fn new(global: &GlobalScope, data: KeyData) -> DomRoot<CryptoKey> {
+        let key = reflect_dom_object(Box::new(CryptoKey::new_inherited(data)), global);
+
+        let cx = global.get_cx();
+        let _ac = enter_realm(global);
+        rooted!(in(*cx) let mut algorithm = ptr::null_mut::<JSObject>());
+        key.data.algorithm.to_object(cx, algorithm.handle_mut());
+        key.algorithm_cached.set(algorithm.get());
+
+        rooted!(in(*cx) let mut usages = UndefinedValue());
+        unsafe { key.data.usages.to_jsval(*cx, usages.handle_mut()) };
+        key.usages_cached.set(usages.to_object());
+
+        key
+    }
+
+    /// Returns a copy of the internal slots of this key.
+    pub fn data(&self) -> KeyData {
+        self.data.clone()
+    }
+}
+
+impl CryptoKeyMethods for CryptoKey {
+    /// <https://w3c.github.io/webcrypto/#dom-cryptokey-type>
+    fn Type(&self) -> KeyType {
+        self.data.key_type
+    }
+
+    /// <https://w3c.github.io/webcrypto/#dom-cryptokey-extractable>
+    fn Extractable(&self) -> bool {
+        self.data.extractable
+    }
+
+    #[allow(unsafe_code)]
+    /// <https://w3c.github.io/webcrypto/#dom-cryptokey-algorithm>
+    fn Algorithm(&self, _cx: JSContext) -> NonNull<JSObject> {
+        unsafe { NonNull::new_unchecked(self.algorithm_cached.get()) }
+    }
+
+    #[allow(unsafe_code)]
+    /// <https://w3c.github.io/webcrypto/#dom-cryptokey-usages>
+    fn Usages(&self, _cx: JSContext) -> NonNull<JSObject> {
+        unsafe { NonNull::new_unchecked(self.usages_cached.get()) }
+    }
+}
@@ -26,6 +26,7 @@ use crate::dom::domexception::{DOMErrorName, DOMException};
 use crate::dom::element::Element;
 use crate::dom::globalscope::GlobalScope;
 use crate::dom::htmlelement::HTMLElement;
+use crate::dom::htmlformelement::{FormControl, HTMLFormElement};
 use crate::dom::node::{document_from_node, window_from_node, Node, ShadowIncluding};
 use crate::dom::promise::Promise;
 use crate::dom::window::Window;
@@ -39,7 +40,7 @@ use js::conversions::ToJSValConvertible;
 use js::glue::UnwrapObjectStatic;
 use js::jsapi::{HandleValueArray, Heap, IsCallable, IsConstructor};
 use js::jsapi::{JSAutoRealm, JSObject};
-use js::jsval::{JSVal, NullValue, ObjectValue, UndefinedValue};
+use js::jsval::{BooleanValue, JSVal, NullValue, ObjectValue, UndefinedValue};
 use js::rust::wrappers::{Construct1, JS_GetProperty, SameValue};
 use js::rust::{HandleObject, HandleValue, MutableHandleValue};
 use std::cell::Cell;
@@ -55,6 +56,7 @@ pub enum CustomElementState {
     Undefined,
     Failed,
     Uncustomized,
+    Precustomized,
     Custom,
 }
 
@@ -174,39 +176,93 @@ impl CustomElementRegistry {
             disconnected_callback: get_callback(cx, prototype, b"disconnectedCallback\0")?,
             adopted_callback: get_callback(cx, prototype, b"adoptedCallback\0")?,
             attribute_changed_callback: get_callback(cx, prototype, b"attributeChangedCallback\0")?,
+            form_associated_callback: None,
+            form_reset_callback: None,
+            form_disabled_callback: None,
         })
     }
 
     /// <https://html.spec.whatwg.org/multipage/#dom-customelementregistry-define>
-    /// Step 10.6
+    /// Step 10.13
     #[allow(unsafe_code)]
-    fn get_observed_attributes(&self, constructor: HandleObject) -> Fallible<Vec<DOMString>> {
+    unsafe fn add_form_associated_callbacks(
+        &self,
+        prototype: HandleObject,
+        callbacks: &mut LifecycleCallbacks,
+    ) -> ErrorResult {
+        let cx = self.window.get_cx();
+
+        callbacks.form_associated_callback =
+            get_callback(cx, prototype, b"formAssociatedCallback\0")?;
+        callbacks.form_reset_callback = get_callback(cx, prototype, b"formResetCallback\0")?;
+        callbacks.form_disabled_callback = get_callback(cx, prototype, b"formDisabledCallback\0")?;
+        // formStateRestoreCallback isn't read, as form state restoration isn't
+        // implemented.
+
+        Ok(())
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#dom-customelementregistry-define>
+    /// Steps 10.6 and 10.8: reads a static `sequence<DOMString>` property,
+    /// such as `observedAttributes` or `disabledFeatures`, from the constructor.
+    #[allow(unsafe_code)]
+    fn get_string_sequence(
+        &self,
+        constructor: HandleObject,
+        name: &[u8],
+    ) -> Fallible<Vec<DOMString>> {
         let cx = self.window.get_cx();
-        rooted!(in(*cx) let mut observed_attributes = UndefinedValue());
+        rooted!(in(*cx) let mut iterable = UndefinedValue());
         if unsafe {
             !JS_GetProperty(
                 *cx,
                 constructor,
-                b"observedAttributes\0".as_ptr() as *const _,
-                observed_attributes.handle_mut(),
+                name.as_ptr() as *const _,
+                iterable.handle_mut(),
             )
         } {
             return Err(Error::JSFailed);
         }
 
-        if observed_attributes.is_undefined() {
+        if iterable.is_undefined() {
             return Ok(Vec::new());
         }
 
         let conversion = unsafe {
             FromJSValConvertible::from_jsval(
                 *cx,
-                observed_attributes.handle(),
+                iterable.handle(),
                 StringificationBehavior::Default,
             )
         };
         match conversion {
-            Ok(ConversionResult::Success(attributes)) => Ok(attributes),
+            Ok(ConversionResult::Success(strings)) => Ok(strings),
+            Ok(ConversionResult::Failure(error)) => Err(Error::Type(error.into())),
+            _ => Err(Error::JSFailed),
+        }
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#dom-customelementregistry-define>
+    /// Steps 10.11-10.12
+    #[allow(unsafe_code)]
+    fn get_form_associated_value(&self, constructor: HandleObject) -> Fallible<bool> {
+        let cx = self.window.get_cx();
+        rooted!(in(*cx) let mut form_associated_value = UndefinedValue());
+        if unsafe {
+            !JS_GetProperty(
+                *cx,
+                constructor,
+                b"formAssociated\0".as_ptr() as *const _,
+                form_associated_value.handle_mut(),
+            )
+        } {
+            return Err(Error::JSFailed);
+        }
+
+        let conversion =
+            unsafe { FromJSValConvertible::from_jsval(*cx, form_associated_value.handle(), ()) };
+        match conversion {
+            Ok(ConversionResult::Success(form_associated)) => Ok(form_associated),
             Ok(ConversionResult::Failure(error)) => Err(Error::Type(error.into())),
             _ => Err(Error::JSFailed),
         }
@@ -334,7 +390,7 @@ impl CustomElementRegistryMethods for CustomElementRegistry {
 
         // Steps 10.3 - 10.4
         rooted!(in(*cx) let proto_object = prototype.to_object());
-        let callbacks = {
+        let mut callbacks = {
             let _ac = JSAutoRealm::new(*cx, proto_object.get());
             match unsafe { self.get_callbacks(proto_object.handle()) } {
                 Ok(callbacks) => callbacks,
@@ -348,7 +404,7 @@ impl CustomElementRegistryMethods for CustomElementRegistry {
         // Step 10.5 - 10.6
         let observed_attributes = if callbacks.attribute_changed_callback.is_some() {
             let _ac = JSAutoRealm::new(*cx, constructor.get());
-            match self.get_observed_attributes(constructor.handle()) {
+            match self.get_string_sequence(constructor.handle(), b"observedAttributes\0") {
                 Ok(attributes) => attributes,
                 Err(error) => {
                     self.element_definition_is_running.set(false);
@@ -359,6 +415,48 @@ impl CustomElementRegistryMethods for CustomElementRegistry {
             Vec::new()
         };
 
+        // Steps 10.7-10.10
+        let (disable_internals, disable_shadow) = {
+            let _ac = JSAutoRealm::new(*cx, constructor.get());
+            match self.get_string_sequence(constructor.handle(), b"disabledFeatures\0") {
+                Ok(disabled_features) => (
+                    disabled_features
+                        .iter()
+                        .any(|feature| &**feature == "internals"),
+                    disabled_features
+                        .iter()
+                        .any(|feature| &**feature == "shadow"),
+                ),
+                Err(error) => {
+                    self.element_definition_is_running.set(false);
+                    return Err(error);
+                },
+            }
+        };
+
+        // Steps 10.11-10.12
+        let form_associated = {
+            let _ac = JSAutoRealm::new(*cx, constructor.get());
+            match self.get_form_associated_value(constructor.handle()) {
+                Ok(form_associated) => form_associated,
+                Err(error) => {
+                    self.element_definition_is_running.set(false);
+                    return Err(error);
+                },
+            }
+        };
+
+        // Step 10.13
+        if form_associated {
+            let _ac = JSAutoRealm::new(*cx, proto_object.get());
+            if let Err(error) =
+                unsafe { self.add_form_associated_callbacks(proto_object.handle(), &mut callbacks) }
+            {
+                self.element_definition_is_running.set(false);
+                return Err(error);
+            }
+        }
+
         self.element_definition_is_running.set(false);
 
         // Step 11
@@ -368,6 +466,9 @@ impl CustomElementRegistryMethods for CustomElementRegistry {
             constructor_,
             observed_attributes,
             callbacks,
+            form_associated,
+            disable_internals,
+            disable_shadow,
         ));
 
         // Step 12
@@ -487,6 +588,15 @@ pub struct LifecycleCallbacks {
 
     #[ignore_malloc_size_of = "Rc"]
     attribute_changed_callback: Option<Rc<Function>>,
+
+    #[ignore_malloc_size_of = "Rc"]
+    form_associated_callback: Option<Rc<Function>>,
+
+    #[ignore_malloc_size_of = "Rc"]
+    form_reset_callback: Option<Rc<Function>>,
+
+    #[ignore_malloc_size_of = "Rc"]
+    form_disabled_callback: Option<Rc<Function>>,
 }
 
 #[derive(Clone, JSTraceable, MallocSizeOf)]
@@ -510,6 +620,15 @@ pub struct CustomElementDefinition {
     pub callbacks: LifecycleCallbacks,
 
     pub construction_stack: DomRefCell<Vec<ConstructionStackEntry>>,
+
+    /// <https://html.spec.whatwg.org/multipage/#concept-custom-element-definition-form-associated>
+    pub form_associated: bool,
+
+    /// <https://html.spec.whatwg.org/multipage/#concept-custom-element-definition-disable-internals>
+    pub disable_internals: bool,
+
+    /// <https://html.spec.whatwg.org/multipage/#concept-custom-element-definition-disable-shadow>
+    pub disable_shadow: bool,
 }
 
 impl CustomElementDefinition {
@@ -519,6 +638,9 @@ impl CustomElementDefinition {
         constructor: Rc<CustomElementConstructor>,
         observed_attributes: Vec<DOMString>,
         callbacks: LifecycleCallbacks,
+        form_associated: bool,
+        disable_internals: bool,
+        disable_shadow: bool,
     ) -> CustomElementDefinition {
         CustomElementDefinition {
             name: name,
@@ -527,6 +649,9 @@ impl CustomElementDefinition {
             observed_attributes: observed_attributes,
             callbacks: callbacks,
             construction_stack: Default::default(),
+            form_associated: form_associated,
+            disable_internals: disable_internals,
+            disable_shadow: disable_shadow,
         }
     }
 
@@ -647,7 +772,7 @@ pub fn upgrade_element(definition: Rc<CustomElementDefinition>, element: &Elemen
         .push(ConstructionStackEntry::Element(DomRoot::from_ref(element)));
 
     // Steps 7-8, successful case
-    let result = run_upgrade_constructor(&definition.constructor, element);
+    let result = run_upgrade_constructor(&definition, element);
 
     // "regardless of whether the above steps threw an exception" step
     definition.construction_stack.borrow_mut().pop();
@@ -655,12 +780,15 @@ pub fn upgrade_element(definition: Rc<CustomElementDefinition>, element: &Elemen
     // Step 8 exception handling
     if let Err(error) = result {
         // Step 8.exception.1
-        element.clear_custom_element_definition();
+        element.set_custom_element_state(CustomElementState::Failed);
 
         // Step 8.exception.2
-        element.clear_reaction_queue();
+        element.clear_custom_element_definition();
 
         // Step 8.exception.3
+        element.clear_reaction_queue();
+
+        // Step 8.exception.4
         let global = GlobalScope::current().expect("No current global");
         let cx = global.get_cx();
         unsafe {
@@ -672,7 +800,14 @@ pub fn upgrade_element(definition: Rc<CustomElementDefinition>, element: &Elemen
         return;
     }
 
-    // TODO Step 9: "If element is a form-associated custom element..."
+    // Step 9
+    if element.is_form_associated_custom_element() {
+        let html_element = element.downcast::<HTMLElement>().unwrap();
+        // Step 9.1. The formAssociatedCallback is enqueued when the form owner changes.
+        html_element.reset_form_owner();
+        // Step 9.2. The formDisabledCallback is enqueued when the disabled state changes.
+        html_element.update_form_associated_disabled_state();
+    }
 
     // Step 10
     element.set_custom_element_state(CustomElementState::Custom);
@@ -681,10 +816,8 @@ pub fn upgrade_element(definition: Rc<CustomElementDefinition>, element: &Elemen
 /// <https://html.spec.whatwg.org/multipage/#concept-upgrade-an-element>
 /// Steps 8.1-8.3
 #[allow(unsafe_code)]
-fn run_upgrade_constructor(
-    constructor: &Rc<CustomElementConstructor>,
-    element: &Element,
-) -> ErrorResult {
+fn run_upgrade_constructor(definition: &CustomElementDefinition, element: &Element) -> ErrorResult {
+    let constructor = &definition.constructor;
     let window = window_from_node(element);
     let cx = window.get_cx();
     rooted!(in(*cx) let constructor_val = ObjectValue(constructor.callback()));
@@ -694,12 +827,18 @@ fn run_upgrade_constructor(
     }
     rooted!(in(*cx) let mut construct_result = ptr::null_mut::<JSObject>());
     {
-        // Step 8.1 TODO when shadow DOM exists
+        // Step 8.1
+        if definition.disable_shadow && element.shadow_root().is_some() {
+            return Err(Error::NotSupported);
+        }
+
+        // Step 8.2
+        element.set_custom_element_state(CustomElementState::Precustomized);
 
         // Go into the constructor's realm
         let _ac = JSAutoRealm::new(*cx, constructor.callback());
         let args = HandleValueArray::new();
-        // Step 8.2
+        // Step 8.3
         if unsafe {
             !Construct1(
                 *cx,
@@ -719,7 +858,7 @@ fn run_upgrade_constructor(
                 .perform_a_microtask_checkpoint();
         }
 
-        // Step 8.3
+        // Step 8.4
         let mut same = false;
         rooted!(in(*cx) let construct_result_val = ObjectValue(construct_result.get()));
         if unsafe {
@@ -792,6 +931,9 @@ pub enum CallbackReaction {
     Disconnected,
     Adopted(DomRoot<Document>, DomRoot<Document>),
     AttributeChanged(LocalName, Option<DOMString>, Option<DOMString>, Namespace),
+    FormAssociated(Option<DomRoot<HTMLFormElement>>),
+    FormReset,
+    FormDisabled(bool),
 }
 
 /// <https://html.spec.whatwg.org/multipage/#processing-the-backup-element-queue>
@@ -959,6 +1101,24 @@ impl CustomElementReactionStack {
                     args,
                 )
             },
+            CallbackReaction::FormAssociated(ref form) => {
+                let args = vec![Heap::default()];
+                match *form {
+                    Some(ref form) => {
+                        args[0].set(ObjectValue(form.reflector().get_jsobject().get()))
+                    },
+                    None => args[0].set(NullValue()),
+                }
+                (definition.callbacks.form_associated_callback.clone(), args)
+            },
+            CallbackReaction::FormReset => {
+                (definition.callbacks.form_reset_callback.clone(), Vec::new())
+            },
+            CallbackReaction::FormDisabled(disabled) => {
+                let args = vec![Heap::default()];
+                args[0].set(BooleanValue(disabled));
+                (definition.callbacks.form_disabled_callback.clone(), args)
+            },
         };
 
         // Step 3
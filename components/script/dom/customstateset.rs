@@ -0,0 +1,115 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::CustomStateSetBinding::CustomStateSetMethods;
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::reflector::{reflect_dom_object, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot, LayoutDom};
+use crate::dom::bindings::str::DOMString;
+use crate::dom::element::Element;
+use crate::dom::node::{document_from_node, window_from_node, Node};
+use dom_struct::dom_struct;
+use servo_atoms::Atom;
+use style::invalidation::element::restyle_hints::RestyleHint;
+
+/// <https://html.spec.whatwg.org/multipage/#customstateset>
+#[dom_struct]
+pub struct CustomStateSet {
+    reflector_: Reflector,
+    element: Dom<Element>,
+    /// The states in the set, in insertion order.
+    states: DomRefCell<Vec<Atom>>,
+}
+
+impl CustomStateSet {
+    fn new_inherited(element: &Element) -> CustomStateSet {
+        CustomStateSet {
+            reflector_: Reflector::new(),
+            element: Dom::from_ref(element),
+            states: DomRefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn new(element: &Element) -> DomRoot<CustomStateSet> {
+        let window = window_from_node(element);
+        reflect_dom_object(Box::new(CustomStateSet::new_inherited(element)), &*window)
+    }
+
+    pub fn has_state(&self, state: &str) -> bool {
+        self.states.borrow().iter().any(|s| &**s == state)
+    }
+
+    /// Custom states are not part of the element snapshots used for selector
+    /// invalidation, so restyle the whole subtree of the element when they change.
+    fn states_changed(&self) {
+        if !self.element.upcast::<Node>().is_connected() {
+            return;
+        }
+        let document = document_from_node(&*self.element);
+        document
+            .ensure_pending_restyle(&self.element)
+            .hint
+            .insert(RestyleHint::restyle_subtree());
+    }
+}
+
+impl CustomStateSetMethods for CustomStateSet {
+    // https://html.spec.whatwg.org/multipage/#customstateset
+    fn Size(&self) -> u32 {
+        self.states.borrow().len() as u32
+    }
+
+    // https://html.spec.whatwg.org/multipage/#customstateset
+    fn Has(&self, value: DOMString) -> bool {
+        self.has_state(&value)
+    }
+
+    // https://html.spec.whatwg.org/multipage/#customstateset
+    fn Add(&self, value: DOMString) {
+        if self.has_state(&value) {
+            return;
+        }
+        self.states.borrow_mut().push(Atom::from(value));
+        self.states_changed();
+    }
+
+    // https://html.spec.whatwg.org/multipage/#customstateset
+    fn Delete(&self, value: DOMString) -> bool {
+        let index = match self.states.borrow().iter().position(|s| **s == *value) {
+            Some(index) => index,
+            None => return false,
+        };
+        self.states.borrow_mut().remove(index);
+        self.states_changed();
+        true
+    }
+
+    // https://html.spec.whatwg.org/multipage/#customstateset
+    fn Clear(&self) {
+        if self.states.borrow().is_empty() {
+            return;
+        }
+        self.states.borrow_mut().clear();
+        self.states_changed();
+    }
+}
+
+pub trait LayoutCustomStateSetHelpers {
+    fn has_state_for_layout(self, state: &str) -> bool;
+}
+
+impl LayoutCustomStateSetHelpers for LayoutDom<'_, CustomStateSet> {
+    #[inline]
+    #[allow(unsafe_code)]
+    fn has_state_for_layout(self, state: &str) -> bool {
+        unsafe {
+            self.unsafe_get()
+                .states
+                .borrow_for_layout()
+                .iter()
+                .any(|s| &**s == state)
+        }
+    }
+}
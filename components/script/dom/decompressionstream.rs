@@ -0,0 +1,228 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::CompressionStreamBinding::CompressionFormat;
+use crate::dom::bindings::codegen::Bindings::DecompressionStreamBinding::DecompressionStreamMethods;
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::readablestream::ReadableStream;
+use crate::dom::transformstream::TransformStream;
+use crate::dom::transformstreamdefaultcontroller::{
+    buffer_source_bytes, TransformStreamDefaultController, TransformerType,
+};
+use crate::dom::writablestream::WritableStream;
+use crate::script_runtime::JSContext;
+use dom_struct::dom_struct;
+use flate2::write::GzDecoder;
+use flate2::{Decompress, FlushDecompress, Status};
+use js::rust::HandleValue;
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::mem;
+
+/// <https://wicg.github.io/compression/#decompressionstream-context>
+enum Decompressor {
+    /// "deflate" and "deflate-raw", inflated directly so that the end of the
+    /// compressed data can be detected.
+    Inflate {
+        decompress: Decompress,
+        /// Whether the end of the compressed data has been reached.
+        ended: bool,
+    },
+    /// "gzip", whose decoder writes the decompressed bytes to a buffer that is
+    /// emptied into a chunk after each write.
+    Gzip(GzDecoder<Vec<u8>>),
+}
+
+unsafe_no_jsmanaged_fields!(Decompressor);
+
+impl Decompressor {
+    fn new(format: CompressionFormat) -> Decompressor {
+        match format {
+            CompressionFormat::Deflate => Decompressor::Inflate {
+                decompress: Decompress::new(true),
+                ended: false,
+            },
+            CompressionFormat::Deflate_raw => Decompressor::Inflate {
+                decompress: Decompress::new(false),
+                ended: false,
+            },
+            CompressionFormat::Gzip => Decompressor::Gzip(GzDecoder::new(Vec::new())),
+        }
+    }
+
+    fn write(&mut self, input: &[u8]) -> io::Result<Vec<u8>> {
+        match *self {
+            Decompressor::Inflate {
+                ref mut decompress,
+                ref mut ended,
+            } => inflate(decompress, ended, input),
+            Decompressor::Gzip(ref mut decoder) => {
+                // Bytes after the end of the member make `write_all` fail.
+                decoder.write_all(input)?;
+                Ok(mem::replace(decoder.get_mut(), Vec::new()))
+            },
+        }
+    }
+
+    fn finish(&mut self) -> io::Result<Vec<u8>> {
+        match *self {
+            Decompressor::Inflate {
+                ref mut decompress,
+                ref mut ended,
+            } => {
+                let output = inflate(decompress, ended, &[])?;
+                if !*ended {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "The compressed data was truncated",
+                    ));
+                }
+                Ok(output)
+            },
+            Decompressor::Gzip(ref mut decoder) => {
+                // This fails if the trailer is missing or does not match.
+                decoder.try_finish()?;
+                Ok(mem::replace(decoder.get_mut(), Vec::new()))
+            },
+        }
+    }
+}
+
+/// Inflates `input`, failing if any of it comes after the end of the
+/// compressed data.
+fn inflate(decompress: &mut Decompress, ended: &mut bool, mut input: &[u8]) -> io::Result<Vec<u8>> {
+    let mut output = Vec::new();
+    loop {
+        if *ended {
+            if !input.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Junk found after the end of the compressed data",
+                ));
+            }
+            return Ok(output);
+        }
+        output.reserve(OUTPUT_CHUNK_SIZE);
+        let (before_in, before_out) = (decompress.total_in(), decompress.total_out());
+        let status = decompress.decompress_vec(input, &mut output, FlushDecompress::None)?;
+        input = &input[(decompress.total_in() - before_in) as usize..];
+        match status {
+            Status::StreamEnd => *ended = true,
+            Status::Ok | Status::BufError => {
+                // Stop once all the input is consumed and the output buffer
+                // was not filled, as there is nothing left to flush.
+                if input.is_empty() && output.len() < output.capacity() {
+                    return Ok(output);
+                }
+                if decompress.total_in() == before_in && decompress.total_out() == before_out {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "The compressed data is corrupt",
+                    ));
+                }
+            },
+        }
+    }
+}
+
+/// The amount of space reserved for decompressed bytes on each call to zlib.
+const OUTPUT_CHUNK_SIZE: usize = 32 * 1024;
+
+/// The state of the transformer of a `DecompressionStream`.
+#[derive(JSTraceable, MallocSizeOf)]
+pub struct DecompressionTransformer {
+    #[ignore_malloc_size_of = "Defined in flate2"]
+    decompressor: RefCell<Decompressor>,
+}
+
+impl DecompressionTransformer {
+    /// <https://wicg.github.io/compression/#decompress-and-enqueue-a-chunk>
+    pub fn transform(
+        &self,
+        cx: JSContext,
+        controller: &TransformStreamDefaultController,
+        chunk: HandleValue,
+    ) -> Fallible<()> {
+        // Step 1.
+        let input = buffer_source_bytes(chunk)?;
+
+        // Steps 2-5.
+        let output = self
+            .decompressor
+            .borrow_mut()
+            .write(&input)
+            .map_err(|_| Error::Type("Decompression failed".to_owned()))?;
+        if output.is_empty() {
+            return Ok(());
+        }
+        controller.enqueue_bytes(cx, &output)
+    }
+
+    /// <https://wicg.github.io/compression/#decompress-flush-and-enqueue>
+    pub fn flush(
+        &self,
+        cx: JSContext,
+        controller: &TransformStreamDefaultController,
+    ) -> Fallible<()> {
+        let output = self
+            .decompressor
+            .borrow_mut()
+            .finish()
+            .map_err(|_| Error::Type("Decompression failed".to_owned()))?;
+        if output.is_empty() {
+            return Ok(());
+        }
+        controller.enqueue_bytes(cx, &output)
+    }
+}
+
+/// <https://wicg.github.io/compression/#decompression-stream>
+#[dom_struct]
+pub struct DecompressionStream {
+    reflector_: Reflector,
+    /// <https://streams.spec.whatwg.org/#generictransformstream-transform>
+    transform: Dom<TransformStream>,
+}
+
+impl DecompressionStream {
+    fn new_inherited(transform: &TransformStream) -> DecompressionStream {
+        DecompressionStream {
+            reflector_: Reflector::new(),
+            transform: Dom::from_ref(transform),
+        }
+    }
+
+    /// <https://wicg.github.io/compression/#dom-decompressionstream-decompressionstream>
+    #[allow(non_snake_case, unrooted_must_root)]
+    pub fn Constructor(
+        global: &GlobalScope,
+        format: CompressionFormat,
+    ) -> Fallible<DomRoot<DecompressionStream>> {
+        let transform = TransformStream::new_with_transformer(
+            global,
+            TransformerType::Decompression(DecompressionTransformer {
+                decompressor: RefCell::new(Decompressor::new(format)),
+            }),
+        )?;
+        Ok(reflect_dom_object(
+            Box::new(DecompressionStream::new_inherited(&transform)),
+            global,
+        ))
+    }
+}
+
+impl DecompressionStreamMethods for DecompressionStream {
+    /// <https://streams.spec.whatwg.org/#dom-generictransformstream-readable>
+    fn Readable(&self) -> DomRoot<ReadableStream> {
+        self.transform.get_readable()
+    }
+
+    /// <https://streams.spec.whatwg.org/#dom-generictransformstream-writable>
+    fn Writable(&self) -> DomRoot<WritableStream> {
+        self.transform.get_writable()
+    }
+}
@@ -13,6 +13,7 @@ use crate::dom::bindings::codegen::Bindings::MessagePortBinding::PostMessageOpti
 use crate::dom::bindings::codegen::Bindings::WorkerBinding::WorkerType;
 use crate::dom::bindings::error::{ErrorInfo, ErrorResult};
 use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::refcounted::Trusted;
 use crate::dom::bindings::reflector::DomObject;
 use crate::dom::bindings::root::{DomRoot, RootCollection, ThreadLocalStackRoots};
 use crate::dom::bindings::str::DOMString;
@@ -28,6 +29,7 @@ use crate::dom::worker::{TrustedWorkerAddress, Worker};
 use crate::dom::workerglobalscope::WorkerGlobalScope;
 use crate::fetch::load_whole_resource;
 use crate::realms::{enter_realm, AlreadyInRealm, InRealm};
+use crate::script_module::{fetch_external_module_script, ModuleOwner, ScriptFetchOptions};
 use crate::script_runtime::ScriptThreadEventCategory::WorkerEvent;
 use crate::script_runtime::{
     new_child_runtime, CommonScriptMsg, ContextForRequestInterrupt, JSContext as SafeJSContext,
@@ -320,6 +322,7 @@ impl DedicatedWorkerGlobalScope {
         worker_load_origin: WorkerScriptLoadOrigin,
         worker_name: String,
         worker_type: WorkerType,
+        credentials_mode: CredentialsMode,
         closing: Arc<AtomicBool>,
         image_cache: Arc<dyn ImageCache>,
         browsing_context: Option<BrowsingContextId>,
@@ -410,41 +413,64 @@ impl DedicatedWorkerGlobalScope {
 
                 global_scope.set_https_state(current_global_https_state);
 
-                let (metadata, bytes) = match load_whole_resource(
-                    request,
-                    &global_scope.resource_threads().sender(),
-                    &global_scope,
-                ) {
-                    Err(_) => {
-                        println!("error loading script {}", serialized_worker_url);
-                        parent_sender
-                            .send(CommonScriptMsg::Task(
-                                WorkerEvent,
-                                Box::new(SimpleWorkerErrorHandler::new(worker)),
-                                Some(pipeline_id),
-                                TaskSourceName::DOMManipulation,
-                            ))
-                            .unwrap();
-                        return;
-                    },
-                    Ok((metadata, bytes)) => (metadata, bytes),
-                };
-                scope.set_url(metadata.final_url);
-                global_scope.set_https_state(metadata.https_state);
-                let source = String::from_utf8_lossy(&bytes);
-
                 unsafe {
                     // Handle interrupt requests
                     JS_AddInterruptCallback(*scope.get_cx(), Some(interrupt_callback));
                 }
 
-                if scope.is_closing() {
-                    return;
-                }
-
-                {
-                    let _ar = AutoWorkerReset::new(&global, worker.clone());
-                    scope.execute_script(DOMString::from(source));
+                match worker_type {
+                    WorkerType::Classic => {
+                        let (metadata, bytes) = match load_whole_resource(
+                            request,
+                            &global_scope.resource_threads().sender(),
+                            &global_scope,
+                        ) {
+                            Err(_) => {
+                                println!("error loading script {}", serialized_worker_url);
+                                parent_sender
+                                    .send(CommonScriptMsg::Task(
+                                        WorkerEvent,
+                                        Box::new(SimpleWorkerErrorHandler::new(worker)),
+                                        Some(pipeline_id),
+                                        TaskSourceName::DOMManipulation,
+                                    ))
+                                    .unwrap();
+                                return;
+                            },
+                            Ok((metadata, bytes)) => (metadata, bytes),
+                        };
+                        scope.set_url(metadata.final_url);
+                        global_scope.set_https_state(metadata.https_state);
+                        let source = String::from_utf8_lossy(&bytes);
+
+                        if scope.is_closing() {
+                            return;
+                        }
+
+                        {
+                            let _ar = AutoWorkerReset::new(&global, worker.clone());
+                            scope.execute_script(DOMString::from(source));
+                        }
+                    },
+                    WorkerType::Module => {
+                        // https://html.spec.whatwg.org/multipage/#fetch-a-module-worker-script-tree
+                        // The module script runs once the module graph has been fetched,
+                        // from within the worker event loop.
+                        let options = ScriptFetchOptions {
+                            credentials_mode,
+                            referrer_policy,
+                            ..ScriptFetchOptions::default_classic_script(&global_scope)
+                        };
+                        let worker_url = scope.get_url().clone();
+
+                        let _ar = AutoWorkerReset::new(&global, worker.clone());
+                        fetch_external_module_script(
+                            ModuleOwner::Worker(Trusted::new(scope)),
+                            worker_url,
+                            Destination::Worker,
+                            options,
+                        );
+                    },
                 }
 
                 let reporter_name = format!("dedicated-worker-reporter-{}", random::<u64>());
@@ -545,6 +571,21 @@ impl DedicatedWorkerGlobalScope {
         true
     }
 
+    /// Queue a simple `error` event at the Worker object, used when the worker's
+    /// module script graph couldn't be fetched.
+    pub fn forward_simple_error_at_worker(&self) {
+        let worker = self.worker.borrow().as_ref().unwrap().clone();
+        let pipeline_id = self.upcast::<GlobalScope>().pipeline_id();
+        self.parent_sender
+            .send(CommonScriptMsg::Task(
+                WorkerEvent,
+                Box::new(SimpleWorkerErrorHandler::new(worker)),
+                Some(pipeline_id),
+                TaskSourceName::DOMManipulation,
+            ))
+            .unwrap();
+    }
+
     // https://html.spec.whatwg.org/multipage/#runtime-script-errors-2
     #[allow(unsafe_code)]
     pub fn forward_error_to_worker_object(&self, error_info: ErrorInfo) {
@@ -0,0 +1,105 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::audionode::AudioNode;
+use crate::dom::audioparam::AudioParam;
+use crate::dom::baseaudiocontext::BaseAudioContext;
+use crate::dom::bindings::codegen::Bindings::AudioNodeBinding::{
+    ChannelCountMode, ChannelInterpretation,
+};
+use crate::dom::bindings::codegen::Bindings::AudioParamBinding::AutomationRate;
+use crate::dom::bindings::codegen::Bindings::DelayNodeBinding::DelayNodeMethods;
+use crate::dom::bindings::codegen::Bindings::DelayNodeBinding::DelayOptions;
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::reflector::reflect_dom_object;
+use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::window::Window;
+use dom_struct::dom_struct;
+use servo_media::audio::delay_node::DelayNodeOptions;
+use servo_media::audio::node::AudioNodeInit;
+use servo_media::audio::param::ParamType;
+
+/// The largest `maxDelayTime` a DelayNode may be created with, in seconds.
+const MAX_DELAY_TIME: f64 = 180.;
+
+#[dom_struct]
+pub struct DelayNode {
+    node: AudioNode,
+    delay_time: Dom<AudioParam>,
+}
+
+impl DelayNode {
+    #[allow(unrooted_must_root)]
+    pub fn new_inherited(
+        window: &Window,
+        context: &BaseAudioContext,
+        options: &DelayOptions,
+    ) -> Fallible<DelayNode> {
+        let node_options =
+            options
+                .parent
+                .unwrap_or(2, ChannelCountMode::Max, ChannelInterpretation::Speakers);
+        // https://webaudio.github.io/web-audio-api/#dom-delayoptions-maxdelaytime
+        let max_delay_time = *options.maxDelayTime;
+        if max_delay_time <= 0. || max_delay_time >= MAX_DELAY_TIME {
+            return Err(Error::NotSupported);
+        }
+        let node = AudioNode::new_inherited(
+            AudioNodeInit::DelayNode(options.into()),
+            context,
+            node_options,
+            1, // inputs
+            1, // outputs
+        )?;
+        let delay_time = AudioParam::new(
+            window,
+            context,
+            node.node_id(),
+            ParamType::DelayTime,
+            AutomationRate::A_rate,
+            *options.delayTime as f32, // default value
+            0.,                        // min value
+            max_delay_time as f32,     // max value
+        );
+        Ok(DelayNode {
+            node,
+            delay_time: Dom::from_ref(&delay_time),
+        })
+    }
+
+    #[allow(unrooted_must_root)]
+    pub fn new(
+        window: &Window,
+        context: &BaseAudioContext,
+        options: &DelayOptions,
+    ) -> Fallible<DomRoot<DelayNode>> {
+        let node = DelayNode::new_inherited(window, context, options)?;
+        Ok(reflect_dom_object(Box::new(node), window))
+    }
+
+    #[allow(non_snake_case)]
+    pub fn Constructor(
+        window: &Window,
+        context: &BaseAudioContext,
+        options: &DelayOptions,
+    ) -> Fallible<DomRoot<DelayNode>> {
+        DelayNode::new(window, context, options)
+    }
+}
+
+impl DelayNodeMethods for DelayNode {
+    // https://webaudio.github.io/web-audio-api/#dom-delaynode-delaytime
+    fn DelayTime(&self) -> DomRoot<AudioParam> {
+        DomRoot::from_ref(&self.delay_time)
+    }
+}
+
+impl<'a> From<&'a DelayOptions> for DelayNodeOptions {
+    fn from(options: &'a DelayOptions) -> Self {
+        Self {
+            max_delay_time: *options.maxDelayTime,
+            delay_time: *options.delayTime as f32,
+        }
+    }
+}
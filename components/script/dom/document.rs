@@ -5,6 +5,7 @@
 use crate::animation_timeline::AnimationTimeline;
 use crate::animations::Animations;
 use crate::document_loader::{DocumentLoader, LoadType};
+use crate::dom::animation::Animation;
 use crate::dom::attr::Attr;
 use crate::dom::beforeunloadevent::BeforeUnloadEvent;
 use crate::dom::bindings::callback::ExceptionHandling;
@@ -46,6 +47,7 @@ use crate::dom::customelementregistry::CustomElementDefinition;
 use crate::dom::customevent::CustomEvent;
 use crate::dom::documentfragment::DocumentFragment;
 use crate::dom::documentorshadowroot::{DocumentOrShadowRoot, StyleSheetInDocument};
+use crate::dom::documenttimeline::DocumentTimeline;
 use crate::dom::documenttype::DocumentType;
 use crate::dom::domimplementation::DOMImplementation;
 use crate::dom::element::CustomElementCreationMode;
@@ -63,6 +65,7 @@ use crate::dom::htmlareaelement::HTMLAreaElement;
 use crate::dom::htmlbaseelement::HTMLBaseElement;
 use crate::dom::htmlbodyelement::HTMLBodyElement;
 use crate::dom::htmlcollection::{CollectionFilter, HTMLCollection};
+use crate::dom::htmldialogelement::HTMLDialogElement;
 use crate::dom::htmlelement::HTMLElement;
 use crate::dom::htmlembedelement::HTMLEmbedElement;
 use crate::dom::htmlformelement::{FormControl, FormControlElementHelpers, HTMLFormElement};
@@ -74,6 +77,7 @@ use crate::dom::htmlinputelement::HTMLInputElement;
 use crate::dom::htmlscriptelement::{HTMLScriptElement, ScriptResult};
 use crate::dom::htmltextareaelement::HTMLTextAreaElement;
 use crate::dom::htmltitleelement::HTMLTitleElement;
+use crate::dom::htmlvideoelement::HTMLVideoElement;
 use crate::dom::keyboardevent::KeyboardEvent;
 use crate::dom::location::Location;
 use crate::dom::messageevent::MessageEvent;
@@ -83,6 +87,7 @@ use crate::dom::node::{Node, NodeDamage, NodeFlags, ShadowIncluding};
 use crate::dom::nodeiterator::NodeIterator;
 use crate::dom::nodelist::NodeList;
 use crate::dom::pagetransitionevent::PageTransitionEvent;
+use crate::dom::pictureinpicturewindow::PictureInPictureWindow;
 use crate::dom::processinginstruction::ProcessingInstruction;
 use crate::dom::promise::Promise;
 use crate::dom::range::Range;
@@ -102,6 +107,7 @@ use crate::dom::webglrenderingcontext::WebGLRenderingContext;
 use crate::dom::wheelevent::WheelEvent;
 use crate::dom::window::{ReflowReason, Window};
 use crate::dom::windowproxy::WindowProxy;
+use crate::editing::{self, EditingCommand, EditingHistory};
 use crate::fetch::FetchCanceller;
 use crate::realms::{AlreadyInRealm, InRealm};
 use crate::script_runtime::JSContext;
@@ -116,7 +122,7 @@ use content_security_policy::{self as csp, CspList};
 use cookie::Cookie;
 use devtools_traits::ScriptToDevtoolsControlMsg;
 use dom_struct::dom_struct;
-use embedder_traits::EmbedderMsg;
+use embedder_traits::{EmbedderMsg, PictureInPictureRequest};
 use encoding_rs::{Encoding, UTF_8};
 use euclid::default::{Point2D, Rect, Size2D};
 use html5ever::{LocalName, Namespace, QualName};
@@ -143,6 +149,7 @@ use profile_traits::time::{TimerMetadata, TimerMetadataFrameType, TimerMetadataR
 use ref_slice::ref_slice;
 use script_layout_interface::message::{Msg, PendingRestyle, ReflowGoal};
 use script_layout_interface::TrustedNodeAddress;
+use script_traits::PictureInPictureAction;
 use script_traits::{AnimationState, DocumentActivity, MouseButton, MouseEventType};
 use script_traits::{
     MsDuration, ScriptMsg, TouchEventType, TouchId, UntrustedNodeAddress, WheelDelta,
@@ -356,6 +363,15 @@ pub struct Document {
     dom_count: Cell<u32>,
     /// Entry node for fullscreen.
     fullscreen_element: MutNullableDom<Element>,
+    /// <https://fullscreen.spec.whatwg.org/#top-layer>
+    top_layer: DomRefCell<Vec<Dom<Element>>>,
+    /// The topmost modal dialog of `top_layer`, which is kept up to date when
+    /// the top layer changes so that hit testing doesn't have to look for it.
+    blocking_modal_dialog: MutNullableDom<HTMLDialogElement>,
+    /// <https://w3c.github.io/picture-in-picture/#dom-documentorshadowroot-pictureinpictureelement>
+    picture_in_picture_element: MutNullableDom<HTMLVideoElement>,
+    /// The window of the current picture-in-picture session, if any.
+    picture_in_picture_window: MutNullableDom<PictureInPictureWindow>,
     /// Map from ID to set of form control elements that have that ID as
     /// their 'form' content attribute. Used to reset form controls
     /// whenever any element with the same ID as the form attribute
@@ -406,9 +422,18 @@ pub struct Document {
     csp_list: DomRefCell<Option<CspList>>,
     /// https://w3c.github.io/slection-api/#dfn-selection
     selection: MutNullableDom<Selection>,
+    /// https://html.spec.whatwg.org/multipage/#designMode
+    design_mode: Cell<bool>,
+    /// The undo and redo stacks of the editing hosts of this document.
+    editing_history: EditingHistory,
+    /// The text nodes layout currently paints a selection or caret in.
+    text_selected_for_layout: DomRefCell<Vec<Dom<Text>>>,
     /// A timeline for animations which is used for synchronizing animations.
     /// https://drafts.csswg.org/web-animations/#timeline
     animation_timeline: DomRefCell<AnimationTimeline>,
+    /// The default timeline of this document, as exposed to script.
+    /// https://drafts.csswg.org/web-animations/#the-documents-default-timeline
+    timeline: MutNullableDom<DocumentTimeline>,
     /// Animations for this Document
     animations: DomRefCell<Animations>,
     /// The nearest inclusive ancestors to all the nodes that require a restyle.
@@ -478,7 +503,9 @@ impl Document {
             return;
         }
 
-        let parent = match node.inclusive_ancestors(ShadowIncluding::Yes).nth(1) {
+        // Dirty bits are propagated along the flat tree, since that is what the
+        // style traversal walks.
+        let parent = match node.inclusive_flat_tree_ancestors().nth(1) {
             Some(parent) => parent,
             None => {
                 // There is no parent so this is the Document node, so we
@@ -490,10 +517,7 @@ impl Document {
                 if let Some(dirty_root) = self.dirty_root.get() {
                     // There was an existing dirty root so we mark its
                     // ancestors as dirty until the document element.
-                    for ancestor in dirty_root
-                        .upcast::<Node>()
-                        .inclusive_ancestors(ShadowIncluding::Yes)
-                    {
+                    for ancestor in dirty_root.upcast::<Node>().inclusive_flat_tree_ancestors() {
                         if ancestor.is::<Element>() {
                             ancestor.set_flag(NodeFlags::HAS_DIRTY_DESCENDANTS, true);
                         }
@@ -546,10 +570,7 @@ impl Document {
             Some(root) => root,
         };
 
-        for ancestor in element
-            .upcast::<Node>()
-            .inclusive_ancestors(ShadowIncluding::Yes)
-        {
+        for ancestor in element.upcast::<Node>().inclusive_flat_tree_ancestors() {
             if ancestor.get_flag(NodeFlags::HAS_DIRTY_DESCENDANTS) {
                 return;
             }
@@ -560,14 +581,17 @@ impl Document {
 
         let new_dirty_root = element
             .upcast::<Node>()
-            .common_ancestor(dirty_root.upcast(), ShadowIncluding::Yes)
+            .inclusive_flat_tree_ancestors()
+            .find(|ancestor| {
+                dirty_root
+                    .upcast::<Node>()
+                    .inclusive_flat_tree_ancestors()
+                    .any(|node| node == *ancestor)
+            })
             .expect("Couldn't find common ancestor");
 
         let mut has_dirty_descendants = true;
-        for ancestor in dirty_root
-            .upcast::<Node>()
-            .inclusive_ancestors(ShadowIncluding::Yes)
-        {
+        for ancestor in dirty_root.upcast::<Node>().inclusive_flat_tree_ancestors() {
             ancestor.set_flag(NodeFlags::HAS_DIRTY_DESCENDANTS, has_dirty_descendants);
             has_dirty_descendants &= *ancestor != *new_dirty_root;
         }
@@ -1068,7 +1092,9 @@ impl Document {
         if implicit_transaction {
             self.begin_focus_transaction();
         }
-        if elem.map_or(true, |e| e.is_focusable_area()) {
+        if elem.map_or(true, |e| {
+            e.is_focusable_area() && !e.upcast::<Node>().is_inert()
+        }) {
             *self.focus_transaction.borrow_mut() =
                 FocusTransaction::InTransaction(elem.map(Dom::from_ref));
         }
@@ -1135,7 +1161,11 @@ impl Document {
                     DeviceIntRect::from_untyped(&rect),
                 ));
             }
+
+            editing::focus_editing_host(self, elem);
         }
+
+        editing::update_selection_for_layout(self);
     }
 
     /// Handles any updates when the document's title has changed.
@@ -1202,8 +1232,9 @@ impl Document {
         };
         debug!("{}: at {:?}", mouse_event_type_string, client_point);
 
-        let el = node_address.and_then(|address| {
-            let node = unsafe { node::from_untrusted_node_address(js_runtime, address) };
+        let hit_node = node_address
+            .map(|address| unsafe { node::from_untrusted_node_address(js_runtime, address) });
+        let el = hit_node.as_ref().and_then(|node| {
             node.inclusive_ancestors(ShadowIncluding::No)
                 .filter_map(DomRoot::downcast::<Element>)
                 .next()
@@ -1215,6 +1246,11 @@ impl Document {
 
         let node = el.upcast::<Node>();
         debug!("{} on {:?}", mouse_event_type_string, node.debug_str());
+        // Inert nodes don't receive user interaction events.
+        // https://html.spec.whatwg.org/multipage/#inert
+        if node.is_inert() {
+            return;
+        }
         // Prevent click event if form control element is disabled.
         if let MouseEventType::Click = mouse_event_type {
             if el.click_event_filter_by_disabled_state() {
@@ -1222,7 +1258,9 @@ impl Document {
             }
 
             self.begin_focus_transaction();
-            self.request_focus(Some(&*el), FocusType::Element);
+            // Clicking anywhere in an editing host focuses it.
+            let focus_target = editing::editing_host(node).unwrap_or_else(|| el.clone());
+            self.request_focus(Some(&*focus_target), FocusType::Element);
         }
 
         // https://w3c.github.io/uievents/#event-type-click
@@ -1255,10 +1293,11 @@ impl Document {
         event.set_trusted(true);
         // https://html.spec.whatwg.org/multipage/#run-authentic-click-activation-steps
         let activatable = el.as_maybe_activatable();
+        let mut status = EventStatus::NotCanceled;
         match mouse_event_type {
             MouseEventType::Click => {
                 el.set_click_in_progress(true);
-                event.fire(node.upcast());
+                status = event.fire(node.upcast());
                 el.set_click_in_progress(false);
             },
             MouseEventType::MouseDown => {
@@ -1281,6 +1320,11 @@ impl Document {
 
         if let MouseEventType::Click = mouse_event_type {
             self.commit_focus_transaction(FocusType::Element);
+            if status == EventStatus::NotCanceled {
+                if let Some(hit_node) = hit_node {
+                    editing::place_caret(self, &hit_node, point_in_node);
+                }
+            }
             self.maybe_fire_dblclick(client_point, node, pressed_mouse_buttons);
         }
 
@@ -1777,6 +1821,19 @@ impl Document {
             let msg = EmbedderMsg::Keyboard(keyboard_event.clone());
             self.send_to_embedder(msg);
 
+            // https://html.spec.whatwg.org/multipage/#cancel-dialog
+            if keyboard_event.key == Key::Escape && keyboard_event.state == KeyState::Down {
+                if let Some(dialog) = self.blocking_modal_dialog() {
+                    dialog.cancel();
+                }
+            }
+
+            if keyboard_event.state == KeyState::Down {
+                if let Some(node) = target.downcast::<Node>() {
+                    editing::handle_keydown(self, node, &keyboard_event);
+                }
+            }
+
             // This behavior is unspecced
             // We are supposed to dispatch synthetic click activation for Space and/or Return,
             // however *when* we do it is up to us.
@@ -2948,6 +3005,7 @@ pub trait LayoutDocumentHelpers<'dom> {
     fn quirks_mode(self) -> QuirksMode;
     fn style_shared_lock(self) -> &'dom StyleSharedRwLock;
     fn shadow_roots(self) -> Vec<LayoutDom<'dom, ShadowRoot>>;
+    fn top_layer(self) -> Vec<LayoutDom<'dom, Element>>;
     fn shadow_roots_styles_changed(self) -> bool;
     unsafe fn flush_shadow_roots_stylesheets(self);
 }
@@ -2995,6 +3053,18 @@ impl<'dom> LayoutDocumentHelpers<'dom> for LayoutDom<'dom, Document> {
         }
     }
 
+    #[inline]
+    fn top_layer(self) -> Vec<LayoutDom<'dom, Element>> {
+        unsafe {
+            self.unsafe_get()
+                .top_layer
+                .borrow_for_layout()
+                .iter()
+                .map(|element| element.to_layout())
+                .collect()
+        }
+    }
+
     #[inline]
     fn shadow_roots_styles_changed(self) -> bool {
         unsafe { self.unsafe_get().shadow_roots_styles_changed.get() }
@@ -3200,6 +3270,10 @@ impl Document {
             spurious_animation_frames: Cell::new(0),
             dom_count: Cell::new(1),
             fullscreen_element: MutNullableDom::new(None),
+            top_layer: DomRefCell::new(vec![]),
+            blocking_modal_dialog: MutNullableDom::new(None),
+            picture_in_picture_element: MutNullableDom::new(None),
+            picture_in_picture_window: MutNullableDom::new(None),
             form_id_listener_map: Default::default(),
             interactive_time: DomRefCell::new(interactive_time),
             tti_window: DomRefCell::new(InteractiveWindow::new()),
@@ -3221,11 +3295,15 @@ impl Document {
             dirty_webgpu_contexts: DomRefCell::new(HashMap::new()),
             csp_list: DomRefCell::new(None),
             selection: MutNullableDom::new(None),
+            design_mode: Cell::new(false),
+            editing_history: EditingHistory::default(),
+            text_selected_for_layout: DomRefCell::new(vec![]),
             animation_timeline: if pref!(layout.animations.test.enabled) {
                 DomRefCell::new(AnimationTimeline::new_for_testing())
             } else {
                 DomRefCell::new(AnimationTimeline::new())
             },
+            timeline: Default::default(),
             animations: DomRefCell::new(Animations::new()),
             dirty_root: Default::default(),
         }
@@ -3726,10 +3804,111 @@ impl Document {
         promise
     }
 
+    /// <https://fullscreen.spec.whatwg.org/#top-layer-add>
+    pub fn add_to_top_layer(&self, element: &Element) {
+        {
+            let mut top_layer = self.top_layer.borrow_mut();
+            top_layer.retain(|candidate| &**candidate != element);
+            top_layer.push(Dom::from_ref(element));
+        }
+        self.update_blocking_modal_dialog();
+    }
+
+    /// <https://fullscreen.spec.whatwg.org/#top-layer-remove>
+    pub fn remove_from_top_layer(&self, element: &Element) {
+        self.top_layer
+            .borrow_mut()
+            .retain(|candidate| &**candidate != element);
+        self.update_blocking_modal_dialog();
+    }
+
+    fn update_blocking_modal_dialog(&self) {
+        let dialog = self
+            .top_layer
+            .borrow()
+            .iter()
+            .rev()
+            .filter_map(|element| element.downcast::<HTMLDialogElement>())
+            .find(|dialog| dialog.is_modal())
+            .map(DomRoot::from_ref);
+        self.blocking_modal_dialog.set(dialog.as_deref());
+    }
+
+    /// The topmost modal dialog in the top layer, if any, which blocks the
+    /// rest of the document.
+    ///
+    /// <https://html.spec.whatwg.org/multipage/#blocked-by-a-modal-dialog>
+    pub fn blocking_modal_dialog(&self) -> Option<DomRoot<HTMLDialogElement>> {
+        self.blocking_modal_dialog.get()
+    }
+
+    pub fn design_mode(&self) -> bool {
+        self.design_mode.get()
+    }
+
+    pub fn editing_history(&self) -> &EditingHistory {
+        &self.editing_history
+    }
+
+    pub fn text_selected_for_layout(&self) -> &DomRefCell<Vec<Dom<Text>>> {
+        &self.text_selected_for_layout
+    }
+
     pub fn set_fullscreen_element(&self, element: Option<&Element>) {
         self.fullscreen_element.set(element);
     }
 
+    pub fn picture_in_picture_element(&self) -> Option<DomRoot<HTMLVideoElement>> {
+        self.picture_in_picture_element.get()
+    }
+
+    pub fn picture_in_picture_window(&self) -> Option<DomRoot<PictureInPictureWindow>> {
+        self.picture_in_picture_window.get()
+    }
+
+    pub fn set_picture_in_picture(
+        &self,
+        session: Option<(&HTMLVideoElement, &PictureInPictureWindow)>,
+    ) {
+        self.picture_in_picture_element.set(session.map(|s| s.0));
+        self.picture_in_picture_window.set(session.map(|s| s.1));
+    }
+
+    /// <https://w3c.github.io/picture-in-picture/#exit-picture-in-picture-algorithm>
+    pub fn exit_picture_in_picture(&self) -> ErrorResult {
+        // Step 1.
+        let video = match self.picture_in_picture_element.get() {
+            Some(video) => video,
+            None => return Err(Error::InvalidState),
+        };
+        let window = self.picture_in_picture_window.get().unwrap();
+
+        // Step 2.
+        window.close();
+        video.send_picture_in_picture_request(PictureInPictureRequest::Exit);
+
+        // Step 3.
+        self.set_picture_in_picture(None);
+
+        // Step 4.
+        video.queue_picture_in_picture_event(atom!("leavepictureinpicture"), &window);
+        Ok(())
+    }
+
+    /// Handle a change made by the user to the picture-in-picture surface.
+    pub fn handle_picture_in_picture_action(&self, action: PictureInPictureAction) {
+        match action {
+            PictureInPictureAction::Resize(size) => {
+                if let Some(window) = self.picture_in_picture_window.get() {
+                    window.resize(Size2D::new(size.width, size.height));
+                }
+            },
+            PictureInPictureAction::Close => {
+                let _ = self.exit_picture_in_picture();
+            },
+        }
+    }
+
     pub fn get_allow_fullscreen(&self) -> bool {
         // https://html.spec.whatwg.org/multipage/#allowed-to-use
         match self.browsing_context() {
@@ -3967,6 +4146,12 @@ impl Document {
         self.animation_timeline.borrow().current_value()
     }
 
+    /// The value of the animation timeline when this document was created, which is
+    /// the zero time of its default `DocumentTimeline`.
+    pub(crate) fn animation_timeline_origin_value(&self) -> f64 {
+        self.animation_timeline.borrow().origin_value()
+    }
+
     pub(crate) fn animations(&self) -> Ref<Animations> {
         self.animations.borrow()
     }
@@ -4009,9 +4194,72 @@ impl ProfilerMetadataFactory for Document {
 }
 
 impl DocumentMethods for Document {
-    // https://w3c.github.io/editing/ActiveDocuments/execCommand.html#querycommandsupported()
-    fn QueryCommandSupported(&self, _command: DOMString) -> bool {
-        false
+    // https://w3c.github.io/editing/docs/execCommand/#execcommand()
+    fn ExecCommand(&self, command_id: DOMString, _show_ui: bool, value: DOMString) -> bool {
+        match EditingCommand::from_name(&command_id) {
+            Some(command) => editing::exec_command(self, command, value),
+            None => false,
+        }
+    }
+
+    // https://w3c.github.io/editing/docs/execCommand/#querycommandenabled()
+    fn QueryCommandEnabled(&self, command_id: DOMString) -> bool {
+        EditingCommand::from_name(&command_id).map_or(false, |command| {
+            editing::query_command_enabled(self, command)
+        })
+    }
+
+    // https://w3c.github.io/editing/docs/execCommand/#querycommandindeterm()
+    fn QueryCommandIndeterm(&self, command_id: DOMString) -> bool {
+        EditingCommand::from_name(&command_id).map_or(false, |command| {
+            editing::query_command_indeterm(self, command)
+        })
+    }
+
+    // https://w3c.github.io/editing/docs/execCommand/#querycommandstate()
+    fn QueryCommandState(&self, command_id: DOMString) -> bool {
+        EditingCommand::from_name(&command_id)
+            .map_or(false, |command| editing::query_command_state(self, command))
+    }
+
+    // https://w3c.github.io/editing/docs/execCommand/#querycommandsupported()
+    fn QueryCommandSupported(&self, command_id: DOMString) -> bool {
+        EditingCommand::from_name(&command_id).is_some()
+    }
+
+    // https://w3c.github.io/editing/docs/execCommand/#querycommandvalue()
+    fn QueryCommandValue(&self, command_id: DOMString) -> DOMString {
+        match EditingCommand::from_name(&command_id) {
+            Some(command @ EditingCommand::Bold) | Some(command @ EditingCommand::Italic) => {
+                DOMString::from(editing::query_command_state(self, command).to_string())
+            },
+            _ => DOMString::new(),
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-document-designmode
+    fn DesignMode(&self) -> DOMString {
+        DOMString::from(if self.design_mode.get() { "on" } else { "off" })
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-document-designmode
+    fn SetDesignMode(&self, value: DOMString) {
+        let design_mode = match_ignore_ascii_case! { &*value,
+            "on" => true,
+            "off" => false,
+            _ => return,
+        };
+        if design_mode == self.design_mode.get() {
+            return;
+        }
+        self.design_mode.set(design_mode);
+        if design_mode {
+            if let (Some(selection), Some(root)) = (self.GetSelection(), self.GetDocumentElement())
+            {
+                let _ = selection.Collapse(Some(root.upcast()), 0);
+            }
+        }
+        editing::update_selection_for_layout(self);
     }
 
     // https://drafts.csswg.org/cssom/#dom-document-stylesheets
@@ -5187,6 +5435,40 @@ impl DocumentMethods for Document {
         self.exit_fullscreen()
     }
 
+    // https://w3c.github.io/picture-in-picture/#dom-document-pictureinpictureenabled
+    fn PictureInPictureEnabled(&self) -> bool {
+        true
+    }
+
+    // https://w3c.github.io/picture-in-picture/#dom-documentorshadowroot-pictureinpictureelement
+    fn GetPictureInPictureElement(&self) -> Option<DomRoot<Element>> {
+        // TODO ShadowRoot
+        self.picture_in_picture_element
+            .get()
+            .map(|video| DomRoot::from_ref(video.upcast::<Element>()))
+    }
+
+    // https://w3c.github.io/picture-in-picture/#dom-document-exitpictureinpicture
+    fn ExitPictureInPicture(&self) -> Rc<Promise> {
+        let promise = Promise::new(&self.global());
+        if let Err(error) = self.exit_picture_in_picture() {
+            promise.reject_error(error);
+            return promise;
+        }
+        let trusted_promise = TrustedPromise::new(promise.clone());
+        let _ = self
+            .window
+            .task_manager()
+            .media_element_task_source()
+            .queue(
+                task!(resolve_exit_picture_in_picture: move || {
+                    trusted_promise.root().resolve_native(&());
+                }),
+                self.window.upcast(),
+            );
+        promise
+    }
+
     // check-tidy: no specs after this line
     // Servo only API to get an instance of the controls of a specific
     // media element matching the given id.
@@ -5205,6 +5487,26 @@ impl DocumentMethods for Document {
             None
         }
     }
+
+    // https://drafts.csswg.org/web-animations/#dom-document-timeline
+    fn Timeline(&self) -> DomRoot<DocumentTimeline> {
+        self.timeline
+            .or_init(|| DocumentTimeline::new(&self.window, 0.))
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-documentorshadowroot-getanimations
+    fn GetAnimations(&self) -> Vec<DomRoot<Animation>> {
+        self.animations()
+            .script_animations()
+            .into_iter()
+            .filter(|animation| {
+                animation.is_relevant() &&
+                    animation
+                        .target()
+                        .map_or(false, |target| target.upcast::<Node>().is_connected())
+            })
+            .collect()
+    }
 }
 
 fn update_with_current_time_ms(marker: &Cell<u64>) {
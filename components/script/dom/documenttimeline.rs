@@ -0,0 +1,65 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::animationtimeline::AnimationTimeline;
+use crate::dom::bindings::codegen::Bindings::DocumentTimelineBinding::DocumentTimelineOptions;
+use crate::dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
+use crate::dom::bindings::reflector::reflect_dom_object;
+use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::document::Document;
+use crate::dom::window::Window;
+use dom_struct::dom_struct;
+
+// https://drafts.csswg.org/web-animations/#the-documenttimeline-interface
+#[dom_struct]
+pub struct DocumentTimeline {
+    timeline: AnimationTimeline,
+    document: Dom<Document>,
+    /// The zero time of this timeline in milliseconds, relative to the zero time
+    /// of the default document timeline.
+    origin_time: f64,
+}
+
+impl DocumentTimeline {
+    fn new_inherited(document: &Document, origin_time: f64) -> DocumentTimeline {
+        DocumentTimeline {
+            timeline: AnimationTimeline::new_inherited(),
+            document: Dom::from_ref(document),
+            origin_time,
+        }
+    }
+
+    pub fn new(window: &Window, origin_time: f64) -> DomRoot<DocumentTimeline> {
+        reflect_dom_object(
+            Box::new(DocumentTimeline::new_inherited(
+                &window.Document(),
+                origin_time,
+            )),
+            window,
+        )
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-documenttimeline-documenttimeline
+    #[allow(non_snake_case)]
+    pub fn Constructor(
+        window: &Window,
+        options: &DocumentTimelineOptions,
+    ) -> DomRoot<DocumentTimeline> {
+        DocumentTimeline::new(window, *options.originTime)
+    }
+
+    /// The current time of this timeline in milliseconds. Document timelines are
+    /// inactive while their document is not fully active.
+    ///
+    /// <https://drafts.csswg.org/web-animations/#ref-for-timeline-current-time>
+    pub fn current_time(&self) -> Option<f64> {
+        if !self.document.is_fully_active() {
+            return None;
+        }
+
+        let elapsed = self.document.current_animation_timeline_value() -
+            self.document.animation_timeline_origin_value();
+        Some(elapsed * 1000. - self.origin_time)
+    }
+}
@@ -37,7 +37,10 @@ pub enum DOMErrorName {
     InvalidNodeTypeError = DOMExceptionConstants::INVALID_NODE_TYPE_ERR,
     DataCloneError = DOMExceptionConstants::DATA_CLONE_ERR,
     NotReadableError,
+    DataError,
     OperationError,
+    NotAllowedError,
+    EncodingError,
 }
 
 impl DOMErrorName {
@@ -66,7 +69,10 @@ impl DOMErrorName {
             "InvalidNodeTypeError" => Some(DOMErrorName::InvalidNodeTypeError),
             "DataCloneError" => Some(DOMErrorName::DataCloneError),
             "NotReadableError" => Some(DOMErrorName::NotReadableError),
+            "DataError" => Some(DOMErrorName::DataError),
             "OperationError" => Some(DOMErrorName::OperationError),
+            "NotAllowedError" => Some(DOMErrorName::NotAllowedError),
+            "EncodingError" => Some(DOMErrorName::EncodingError),
             _ => None,
         }
     }
@@ -111,9 +117,14 @@ impl DOMException {
             },
             DOMErrorName::DataCloneError => "The object can not be cloned.",
             DOMErrorName::NotReadableError => "The I/O read operation failed.",
+            DOMErrorName::DataError => "The provided data is inadequate.",
             DOMErrorName::OperationError => {
                 "The operation failed for an operation-specific reason."
             },
+            DOMErrorName::NotAllowedError => {
+                "The request is not allowed by the user agent or the platform in the current context."
+            },
+            DOMErrorName::EncodingError => "The encoding or decoding operation failed.",
         };
 
         (
@@ -5,18 +5,26 @@
 //! Element nodes.
 
 use crate::dom::activation::Activatable;
+use crate::dom::animation::Animation;
 use crate::dom::attr::{Attr, AttrHelpersForLayout};
 use crate::dom::bindings::cell::{ref_filter_map, DomRefCell, Ref, RefMut};
+use crate::dom::bindings::codegen::Bindings::AnimatableBinding::GetAnimationsOptions;
+use crate::dom::bindings::codegen::Bindings::AnimationBinding::AnimationMethods;
 use crate::dom::bindings::codegen::Bindings::AttrBinding::AttrMethods;
 use crate::dom::bindings::codegen::Bindings::DocumentBinding::DocumentMethods;
-use crate::dom::bindings::codegen::Bindings::ElementBinding::ElementMethods;
+use crate::dom::bindings::codegen::Bindings::ElementBinding::{ElementMethods, ShadowRootInit};
 use crate::dom::bindings::codegen::Bindings::FunctionBinding::Function;
 use crate::dom::bindings::codegen::Bindings::HTMLTemplateElementBinding::HTMLTemplateElementMethods;
 use crate::dom::bindings::codegen::Bindings::NodeBinding::NodeMethods;
 use crate::dom::bindings::codegen::Bindings::ShadowRootBinding::ShadowRootBinding::ShadowRootMethods;
+use crate::dom::bindings::codegen::Bindings::ShadowRootBinding::ShadowRootMode;
 use crate::dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
 use crate::dom::bindings::codegen::Bindings::WindowBinding::{ScrollBehavior, ScrollToOptions};
 use crate::dom::bindings::codegen::UnionTypes::NodeOrString;
+use crate::dom::bindings::codegen::UnionTypes::StringStringRecordSequenceOrStringStringOrStringSequenceRecord as KeyframesInit;
+use crate::dom::bindings::codegen::UnionTypes::{
+    UnrestrictedDoubleOrKeyframeAnimationOptions, UnrestrictedDoubleOrKeyframeEffectOptions,
+};
 use crate::dom::bindings::conversions::DerivedFrom;
 use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
 use crate::dom::bindings::inheritance::{Castable, ElementTypeId, HTMLElementTypeId, NodeTypeId};
@@ -31,12 +39,15 @@ use crate::dom::bindings::xmlname::{
 use crate::dom::characterdata::CharacterData;
 use crate::dom::create::create_element;
 use crate::dom::customelementregistry::{
-    CallbackReaction, CustomElementDefinition, CustomElementReaction, CustomElementState,
+    is_valid_custom_element_name, CallbackReaction, CustomElementDefinition, CustomElementReaction,
+    CustomElementState,
 };
+use crate::dom::customstateset::{CustomStateSet, LayoutCustomStateSetHelpers};
 use crate::dom::document::{determine_policy_for_token, Document, LayoutDocumentHelpers};
 use crate::dom::documentfragment::DocumentFragment;
 use crate::dom::domrect::DOMRect;
 use crate::dom::domtokenlist::DOMTokenList;
+use crate::dom::elementinternals::ElementInternals;
 use crate::dom::eventtarget::EventTarget;
 use crate::dom::htmlanchorelement::HTMLAnchorElement;
 use crate::dom::htmlbodyelement::{HTMLBodyElement, HTMLBodyElementLayoutHelpers};
@@ -46,7 +57,7 @@ use crate::dom::htmlcollection::HTMLCollection;
 use crate::dom::htmlelement::HTMLElement;
 use crate::dom::htmlfieldsetelement::HTMLFieldSetElement;
 use crate::dom::htmlfontelement::{HTMLFontElement, HTMLFontElementLayoutHelpers};
-use crate::dom::htmlformelement::FormControlElementHelpers;
+use crate::dom::htmlformelement::{FormControlElementHelpers, HTMLFormElement};
 use crate::dom::htmlhrelement::{HTMLHRElement, HTMLHRLayoutHelpers};
 use crate::dom::htmliframeelement::{HTMLIFrameElement, HTMLIFrameElementLayoutMethods};
 use crate::dom::htmlimageelement::{HTMLImageElement, LayoutHTMLImageElementHelpers};
@@ -54,10 +65,12 @@ use crate::dom::htmlinputelement::{HTMLInputElement, LayoutHTMLInputElementHelpe
 use crate::dom::htmllabelelement::HTMLLabelElement;
 use crate::dom::htmllegendelement::HTMLLegendElement;
 use crate::dom::htmllinkelement::HTMLLinkElement;
+use crate::dom::htmlmediaelement::HTMLMediaElement;
 use crate::dom::htmlobjectelement::HTMLObjectElement;
 use crate::dom::htmloptgroupelement::HTMLOptGroupElement;
 use crate::dom::htmloutputelement::HTMLOutputElement;
 use crate::dom::htmlselectelement::HTMLSelectElement;
+use crate::dom::htmlslotelement::HTMLSlotElement;
 use crate::dom::htmlstyleelement::HTMLStyleElement;
 use crate::dom::htmltablecellelement::{HTMLTableCellElement, HTMLTableCellElementLayoutHelpers};
 use crate::dom::htmltableelement::{HTMLTableElement, HTMLTableElementLayoutHelpers};
@@ -67,6 +80,7 @@ use crate::dom::htmltablesectionelement::{
 };
 use crate::dom::htmltemplateelement::HTMLTemplateElement;
 use crate::dom::htmltextareaelement::{HTMLTextAreaElement, LayoutHTMLTextAreaElementHelpers};
+use crate::dom::keyframeeffect::KeyframeEffect;
 use crate::dom::mutationobserver::{Mutation, MutationObserver};
 use crate::dom::namednodemap::NamedNodeMap;
 use crate::dom::node::{document_from_node, window_from_node};
@@ -76,11 +90,12 @@ use crate::dom::nodelist::NodeList;
 use crate::dom::promise::Promise;
 use crate::dom::raredata::ElementRareData;
 use crate::dom::servoparser::ServoParser;
-use crate::dom::shadowroot::{IsUserAgentWidget, ShadowRoot};
+use crate::dom::shadowroot::{IsUserAgentWidget, LayoutShadowRootHelpers, ShadowRoot};
 use crate::dom::text::Text;
 use crate::dom::validation::Validatable;
 use crate::dom::virtualmethods::{vtable_for, VirtualMethods};
 use crate::dom::window::ReflowReason;
+use crate::editing;
 use crate::script_thread::ScriptThread;
 use crate::stylesheet_loader::StylesheetOwner;
 use crate::task::TaskOnce;
@@ -354,6 +369,59 @@ impl Element {
         self.ensure_rare_data().custom_element_definition = None;
     }
 
+    /// <https://html.spec.whatwg.org/multipage/#form-associated-custom-element>
+    pub fn is_form_associated_custom_element(&self) -> bool {
+        self.get_custom_element_definition()
+            .map_or(false, |definition| {
+                definition.is_autonomous() && definition.form_associated
+            })
+    }
+
+    pub fn get_element_internals(&self) -> Option<DomRoot<ElementInternals>> {
+        self.rare_data()
+            .as_ref()?
+            .element_internals
+            .as_ref()
+            .map(|internals| DomRoot::from_ref(&**internals))
+    }
+
+    pub fn set_element_internals(&self, internals: &ElementInternals) {
+        self.ensure_rare_data().element_internals = Some(Dom::from_ref(internals));
+    }
+
+    pub fn custom_element_form_owner(&self) -> Option<DomRoot<HTMLFormElement>> {
+        self.rare_data()
+            .as_ref()?
+            .form_owner
+            .as_ref()
+            .map(|form| DomRoot::from_ref(&**form))
+    }
+
+    pub fn set_custom_element_form_owner(&self, form: Option<&HTMLFormElement>) {
+        self.ensure_rare_data().form_owner = form.map(Dom::from_ref);
+    }
+
+    pub fn custom_state_set(&self) -> DomRoot<CustomStateSet> {
+        if let Some(ref custom_state_set) = self
+            .rare_data()
+            .as_ref()
+            .and_then(|rare_data| rare_data.custom_state_set.as_ref())
+        {
+            return DomRoot::from_ref(&**custom_state_set);
+        }
+        let custom_state_set = CustomStateSet::new(self);
+        self.ensure_rare_data().custom_state_set = Some(Dom::from_ref(&*custom_state_set));
+        custom_state_set
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#selector-custom>
+    pub fn has_custom_state(&self, state: &str) -> bool {
+        self.rare_data()
+            .as_ref()
+            .and_then(|rare_data| rare_data.custom_state_set.as_ref())
+            .map_or(false, |custom_state_set| custom_state_set.has_state(state))
+    }
+
     pub fn push_callback_reaction(&self, function: Rc<Function>, args: Box<[Heap<JSVal>]>) {
         self.ensure_rare_data()
             .custom_element_reaction_queue
@@ -447,7 +515,7 @@ impl Element {
         })
     }
 
-    fn shadow_root(&self) -> Option<DomRoot<ShadowRoot>> {
+    pub fn shadow_root(&self) -> Option<DomRoot<ShadowRoot>> {
         self.rare_data()
             .as_ref()?
             .shadow_root
@@ -460,9 +528,11 @@ impl Element {
     }
 
     /// https://dom.spec.whatwg.org/#dom-element-attachshadow
-    /// XXX This is not exposed to web content yet. It is meant to be used
-    ///     for UA widgets only.
-    pub fn attach_shadow(&self, is_ua_widget: IsUserAgentWidget) -> Fallible<DomRoot<ShadowRoot>> {
+    pub fn attach_shadow(
+        &self,
+        is_ua_widget: IsUserAgentWidget,
+        mode: ShadowRootMode,
+    ) -> Fallible<DomRoot<ShadowRoot>> {
         // Step 1.
         if self.namespace != ns!(html) {
             return Err(Error::NotSupported);
@@ -490,16 +560,29 @@ impl Element {
             &local_name!("span") => {},
             &local_name!("video") | &local_name!("audio")
                 if is_ua_widget == IsUserAgentWidget::Yes => {},
+            local_name if is_valid_custom_element_name(local_name) => {},
             _ => return Err(Error::NotSupported),
         };
 
         // Step 3.
+        if is_valid_custom_element_name(self.local_name()) || self.get_is().is_some() {
+            let definition = self.node.owner_doc().lookup_custom_element_definition(
+                self.namespace(),
+                self.local_name(),
+                self.get_is().as_ref(),
+            );
+            if definition.map_or(false, |definition| definition.disable_shadow) {
+                return Err(Error::NotSupported);
+            }
+        }
+
+        // Step 4.
         if self.is_shadow_host() {
             return Err(Error::InvalidState);
         }
 
-        // Steps 4, 5 and 6.
-        let shadow_root = ShadowRoot::new(self, &*self.node.owner_doc());
+        // Steps 5, 6 and 7.
+        let shadow_root = ShadowRoot::new(self, &*self.node.owner_doc(), mode);
         self.ensure_rare_data().shadow_root = Some(Dom::from_ref(&*shadow_root));
         shadow_root
             .upcast::<Node>()
@@ -587,6 +670,8 @@ pub trait LayoutElementHelpers<'dom> {
     fn has_selector_flags(self, flags: ElementSelectorFlags) -> bool;
     /// The shadow root this element is a host of.
     fn get_shadow_root_for_layout(self) -> Option<LayoutDom<'dom, ShadowRoot>>;
+    /// Whether this element's custom state set contains `state`.
+    fn has_custom_state_for_layout(self, state: &str) -> bool;
     fn get_attr_for_layout(
         self,
         namespace: &Namespace,
@@ -594,6 +679,9 @@ pub trait LayoutElementHelpers<'dom> {
     ) -> Option<&'dom AttrValue>;
     fn get_attr_val_for_layout(self, namespace: &Namespace, name: &LocalName) -> Option<&'dom str>;
     fn get_attr_vals_for_layout(self, name: &LocalName) -> Vec<&'dom AttrValue>;
+    /// The pseudo-element this element implements, if it is part of the
+    /// user agent shadow tree of a media element.
+    fn implemented_pseudo_element_for_layout(self) -> Option<PseudoElement>;
 }
 
 impl<'dom> LayoutDom<'dom, Element> {
@@ -1055,6 +1143,21 @@ impl<'dom> LayoutElementHelpers<'dom> for LayoutDom<'dom, Element> {
         }
     }
 
+    #[inline]
+    #[allow(unsafe_code)]
+    fn has_custom_state_for_layout(self, state: &str) -> bool {
+        unsafe {
+            self.unsafe_get()
+                .rare_data
+                .borrow_for_layout()
+                .as_ref()
+                .and_then(|rare_data| rare_data.custom_state_set.as_ref())
+                .map_or(false, |custom_state_set| {
+                    custom_state_set.to_layout().has_state_for_layout(state)
+                })
+        }
+    }
+
     #[inline]
     fn get_attr_for_layout(
         self,
@@ -1082,6 +1185,18 @@ impl<'dom> LayoutElementHelpers<'dom> for LayoutDom<'dom, Element> {
             })
             .collect()
     }
+
+    #[inline]
+    fn implemented_pseudo_element_for_layout(self) -> Option<PseudoElement> {
+        let shadow_root = self.upcast::<Node>().containing_shadow_root_for_layout()?;
+        if !shadow_root.get_host_for_layout().is::<HTMLMediaElement>() {
+            return None;
+        }
+        if self.has_class_for_layout(&atom!("cue"), CaseSensitivity::CaseSensitive) {
+            return Some(PseudoElement::Cue);
+        }
+        None
+    }
 }
 
 impl Element {
@@ -1305,6 +1420,12 @@ impl Element {
             return true;
         }
 
+        // Editing hosts are focusable.
+        // https://html.spec.whatwg.org/multipage/#focusable-area
+        if editing::is_editing_host(self) {
+            return true;
+        }
+
         // <a>, <input>, <select>, and <textrea> are inherently focusable.
         match node.type_id() {
             NodeTypeId::Element(ElementTypeId::HTMLElement(
@@ -1956,6 +2077,16 @@ impl ElementMethods for Element {
             .or_init(|| DOMTokenList::new(self, &local_name!("class"), None))
     }
 
+    // https://dom.spec.whatwg.org/#dom-element-slot
+    fn Slot(&self) -> DOMString {
+        self.get_string_attribute(&local_name!("slot"))
+    }
+
+    // https://dom.spec.whatwg.org/#dom-element-slot
+    fn SetSlot(&self, slot: DOMString) {
+        self.set_string_attribute(&local_name!("slot"), slot);
+    }
+
     // https://dom.spec.whatwg.org/#dom-element-attributes
     fn Attributes(&self) -> DomRoot<NamedNodeMap> {
         self.attr_list
@@ -2790,8 +2921,69 @@ impl ElementMethods for Element {
     // XXX Hidden under dom.shadowdom.enabled pref. Only exposed to be able
     //     to test partial Shadow DOM support for UA widgets.
     // https://dom.spec.whatwg.org/#dom-element-attachshadow
-    fn AttachShadow(&self) -> Fallible<DomRoot<ShadowRoot>> {
-        self.attach_shadow(IsUserAgentWidget::No)
+    fn AttachShadow(&self, init: &ShadowRootInit) -> Fallible<DomRoot<ShadowRoot>> {
+        self.attach_shadow(IsUserAgentWidget::No, init.mode)
+    }
+
+    // https://dom.spec.whatwg.org/#dom-element-shadowroot
+    fn GetShadowRoot(&self) -> Option<DomRoot<ShadowRoot>> {
+        self.shadow_root()
+            .filter(|shadow_root| shadow_root.Mode() == ShadowRootMode::Open)
+    }
+
+    // https://dom.spec.whatwg.org/#dom-slotable-assignedslot
+    fn GetAssignedSlot(&self) -> Option<DomRoot<HTMLSlotElement>> {
+        self.upcast::<Node>().find_a_slot(true)
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animatable-animate
+    fn Animate(
+        &self,
+        keyframes: Option<KeyframesInit>,
+        options: UnrestrictedDoubleOrKeyframeAnimationOptions,
+    ) -> Fallible<DomRoot<Animation>> {
+        let (options, id) = match options {
+            UnrestrictedDoubleOrKeyframeAnimationOptions::UnrestrictedDouble(duration) => (
+                UnrestrictedDoubleOrKeyframeEffectOptions::UnrestrictedDouble(duration),
+                DOMString::new(),
+            ),
+            UnrestrictedDoubleOrKeyframeAnimationOptions::KeyframeAnimationOptions(options) => (
+                UnrestrictedDoubleOrKeyframeEffectOptions::KeyframeEffectOptions(options.parent),
+                options.id,
+            ),
+        };
+
+        // Steps 1-2.
+        let window = window_from_node(self);
+        let effect =
+            KeyframeEffect::new_with_options(&window, Some(self), keyframes.as_ref(), &options)?;
+
+        // Steps 3-7.
+        let timeline = document_from_node(self).Timeline();
+        let animation = Animation::new(&window, Some(effect.upcast()), Some(timeline.upcast()));
+        animation.set_id(id);
+        animation.Play()?;
+        Ok(animation)
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animatable-getanimations
+    fn GetAnimations(&self, options: &GetAnimationsOptions) -> Vec<DomRoot<Animation>> {
+        document_from_node(self)
+            .animations()
+            .script_animations()
+            .into_iter()
+            .filter(|animation| {
+                animation.is_relevant() &&
+                    animation.target().map_or(false, |target| {
+                        if options.subtree {
+                            self.upcast::<Node>()
+                                .is_inclusive_ancestor_of(target.upcast::<Node>())
+                        } else {
+                            &*target == self
+                        }
+                    })
+            })
+            .collect()
     }
 }
 
@@ -2934,6 +3126,13 @@ impl VirtualMethods for Element {
                     }
                 }
             },
+            &local_name!("slot") if attr.namespace() == &ns!() => {
+                // https://dom.spec.whatwg.org/#slotable-name
+                if let Some(slot) = node.assigned_slot() {
+                    slot.assign_slottables();
+                }
+                node.assign_a_slot();
+            },
             _ => {
                 // FIXME(emilio): This is pretty dubious, and should be done in
                 // the relevant super-classes.
@@ -3207,6 +3406,8 @@ impl<'a> SelectorsElement for DomRoot<Element> {
             // storing separate <ident> or <string>s for each language tag.
             NonTSPseudoClass::Lang(ref lang) => extended_filtering(&*self.get_lang(), &*lang),
 
+            NonTSPseudoClass::State(ref state) => self.has_custom_state(&*state),
+
             NonTSPseudoClass::ReadOnly => !Element::state(self).contains(pseudo_class.state_flag()),
 
             NonTSPseudoClass::Active |
@@ -3218,6 +3419,7 @@ impl<'a> SelectorsElement for DomRoot<Element> {
             NonTSPseudoClass::Disabled |
             NonTSPseudoClass::Checked |
             NonTSPseudoClass::Indeterminate |
+            NonTSPseudoClass::Modal |
             NonTSPseudoClass::ReadWrite |
             NonTSPseudoClass::PlaceholderShown |
             NonTSPseudoClass::Target => Element::state(self).contains(pseudo_class.state_flag()),
@@ -3381,6 +3583,15 @@ impl Element {
                 let element = self.downcast::<HTMLOutputElement>().unwrap();
                 Some(element as &dyn Validatable)
             },
+            // Form-associated custom elements only take part in constraint
+            // validation once they have attached their internals.
+            NodeTypeId::Element(ElementTypeId::HTMLElement(HTMLElementTypeId::HTMLElement))
+                if self.is_form_associated_custom_element() &&
+                    self.get_element_internals().is_some() =>
+            {
+                let element = self.downcast::<HTMLElement>().unwrap();
+                Some(element as &dyn Validatable)
+            }
             _ => None,
         };
         element
@@ -3540,10 +3751,20 @@ impl Element {
 
 impl Element {
     pub fn check_ancestors_disabled_state_for_form_control(&self) {
-        let node = self.upcast::<Node>();
         if self.disabled_state() {
             return;
         }
+        if self.is_disabled_by_fieldset_ancestor() {
+            self.set_disabled_state(true);
+            self.set_enabled_state(false);
+        }
+    }
+
+    /// Whether this element is a descendant of a disabled fieldset, and not a
+    /// descendant of that fieldset's first legend.
+    /// <https://html.spec.whatwg.org/multipage/#concept-fe-disabled>
+    pub fn is_disabled_by_fieldset_ancestor(&self) -> bool {
+        let node = self.upcast::<Node>();
         for ancestor in node.ancestors() {
             if !ancestor.is::<HTMLFieldSetElement>() {
                 continue;
@@ -3552,9 +3773,7 @@ impl Element {
                 continue;
             }
             if ancestor.is_parent_of(node) {
-                self.set_disabled_state(true);
-                self.set_enabled_state(false);
-                return;
+                return true;
             }
             if let Some(ref legend) = ancestor.children().find(|n| n.is::<HTMLLegendElement>()) {
                 // XXXabinader: should we save previous ancestor to avoid this iteration?
@@ -3562,10 +3781,9 @@ impl Element {
                     continue;
                 }
             }
-            self.set_disabled_state(true);
-            self.set_enabled_state(false);
-            return;
+            return true;
         }
+        false
     }
 
     pub fn check_parent_disabled_state_for_option(&self) {
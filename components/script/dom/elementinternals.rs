@@ -0,0 +1,307 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::ElementInternalsBinding::{
+    ElementInternalsMethods, ValidityStateFlags,
+};
+use crate::dom::bindings::codegen::UnionTypes::FileOrUSVStringOrFormData;
+use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::reflector::{reflect_dom_object, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
+use crate::dom::bindings::str::DOMString;
+use crate::dom::customstateset::CustomStateSet;
+use crate::dom::element::Element;
+use crate::dom::file::File;
+use crate::dom::htmlelement::HTMLElement;
+use crate::dom::htmlformelement::{FormControl, FormDatum, FormDatumValue, HTMLFormElement};
+use crate::dom::node::{window_from_node, Node, ShadowIncluding};
+use crate::dom::nodelist::NodeList;
+use crate::dom::shadowroot::ShadowRoot;
+use crate::dom::validation::Validatable;
+use crate::dom::validitystate::{ValidationFlags, ValidityState};
+use dom_struct::dom_struct;
+use std::cell::Cell;
+
+/// The value set by `setFormValue()`.
+/// <https://html.spec.whatwg.org/multipage/#face-submission-value>
+#[derive(Clone, JSTraceable, MallocSizeOf)]
+enum SubmissionValue {
+    None,
+    File(DomRoot<File>),
+    String(DOMString),
+    FormData(Vec<FormDatum>),
+}
+
+impl From<Option<FileOrUSVStringOrFormData>> for SubmissionValue {
+    fn from(value: Option<FileOrUSVStringOrFormData>) -> SubmissionValue {
+        match value {
+            None => SubmissionValue::None,
+            Some(FileOrUSVStringOrFormData::File(file)) => SubmissionValue::File(file),
+            Some(FileOrUSVStringOrFormData::USVString(string)) => {
+                SubmissionValue::String(DOMString::from(string.0))
+            },
+            // Submission values and states hold a copy of the form data's entry list.
+            Some(FileOrUSVStringOrFormData::FormData(form_data)) => {
+                SubmissionValue::FormData(form_data.datums())
+            },
+        }
+    }
+}
+
+/// <https://html.spec.whatwg.org/multipage/#elementinternals>
+#[dom_struct]
+pub struct ElementInternals {
+    reflector_: Reflector,
+    /// <https://html.spec.whatwg.org/multipage/#internals-target>
+    target_element: Dom<HTMLElement>,
+    /// <https://html.spec.whatwg.org/multipage/#face-submission-value>
+    submission_value: DomRefCell<SubmissionValue>,
+    /// <https://html.spec.whatwg.org/multipage/#face-state>
+    state: DomRefCell<SubmissionValue>,
+    /// The validity flags set through `setValidity()`.
+    validity_flags: Cell<ValidationFlags>,
+    /// <https://html.spec.whatwg.org/multipage/#face-validation-message>
+    validation_message: DomRefCell<DOMString>,
+    validity: MutNullableDom<ValidityState>,
+    labels_node_list: MutNullableDom<NodeList>,
+}
+
+impl ElementInternals {
+    fn new_inherited(target_element: &HTMLElement) -> ElementInternals {
+        ElementInternals {
+            reflector_: Reflector::new(),
+            target_element: Dom::from_ref(target_element),
+            submission_value: DomRefCell::new(SubmissionValue::None),
+            state: DomRefCell::new(SubmissionValue::None),
+            validity_flags: Cell::new(ValidationFlags::empty()),
+            validation_message: DomRefCell::new(DOMString::new()),
+            validity: Default::default(),
+            labels_node_list: Default::default(),
+        }
+    }
+
+    pub fn new(target_element: &HTMLElement) -> DomRoot<ElementInternals> {
+        let window = window_from_node(target_element);
+        reflect_dom_object(
+            Box::new(ElementInternals::new_inherited(target_element)),
+            &*window,
+        )
+    }
+
+    fn is_target_form_associated(&self) -> bool {
+        self.target_element
+            .upcast::<Element>()
+            .is_form_associated_custom_element()
+    }
+
+    pub fn validity_state(&self) -> DomRoot<ValidityState> {
+        self.validity.or_init(|| {
+            let window = window_from_node(&*self.target_element);
+            ValidityState::new(&window, self.target_element.upcast())
+        })
+    }
+
+    pub fn validity_flags(&self) -> ValidationFlags {
+        self.validity_flags.get()
+    }
+
+    pub fn validation_message(&self) -> DOMString {
+        self.validation_message.borrow().clone()
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#face-entry-construction>
+    pub fn append_entries(&self, entry_list: &mut Vec<FormDatum>) {
+        let name = self
+            .target_element
+            .upcast::<Element>()
+            .get_string_attribute(&local_name!("name"));
+        match *self.submission_value.borrow() {
+            // Step 1
+            SubmissionValue::FormData(ref datums) => entry_list.extend(datums.iter().cloned()),
+            // Step 2
+            SubmissionValue::File(ref file) if !name.is_empty() => entry_list.push(FormDatum {
+                ty: DOMString::from("file"),
+                name: name,
+                value: FormDatumValue::File(file.clone()),
+            }),
+            SubmissionValue::String(ref string) if !name.is_empty() => entry_list.push(FormDatum {
+                ty: DOMString::from("string"),
+                name: name,
+                value: FormDatumValue::String(string.clone()),
+            }),
+            _ => {},
+        }
+    }
+}
+
+/// Converts the dictionary passed to `setValidity()` to a set of validation flags.
+fn validation_flags_from_dictionary(flags: &ValidityStateFlags) -> ValidationFlags {
+    let mut result = ValidationFlags::empty();
+    result.set(ValidationFlags::VALUE_MISSING, flags.valueMissing);
+    result.set(ValidationFlags::TYPE_MISMATCH, flags.typeMismatch);
+    result.set(ValidationFlags::PATTERN_MISMATCH, flags.patternMismatch);
+    result.set(ValidationFlags::TOO_LONG, flags.tooLong);
+    result.set(ValidationFlags::TOO_SHORT, flags.tooShort);
+    result.set(ValidationFlags::RANGE_UNDERFLOW, flags.rangeUnderflow);
+    result.set(ValidationFlags::RANGE_OVERFLOW, flags.rangeOverflow);
+    result.set(ValidationFlags::STEP_MISMATCH, flags.stepMismatch);
+    result.set(ValidationFlags::BAD_INPUT, flags.badInput);
+    result.set(ValidationFlags::CUSTOM_ERROR, flags.customError);
+    result
+}
+
+impl ElementInternalsMethods for ElementInternals {
+    // https://html.spec.whatwg.org/multipage/#dom-elementinternals-shadowroot
+    fn GetShadowRoot(&self) -> Option<DomRoot<ShadowRoot>> {
+        self.target_element.upcast::<Element>().shadow_root()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-elementinternals-setformvalue
+    fn SetFormValue(
+        &self,
+        value: Option<FileOrUSVStringOrFormData>,
+        state: Option<Option<FileOrUSVStringOrFormData>>,
+    ) -> ErrorResult {
+        // Steps 1-2
+        if !self.is_target_form_associated() {
+            return Err(Error::NotSupported);
+        }
+
+        // Step 3
+        let submission_value = SubmissionValue::from(value);
+
+        // Steps 4-5
+        *self.state.borrow_mut() = match state {
+            Some(state) => SubmissionValue::from(state),
+            None => submission_value.clone(),
+        };
+        *self.submission_value.borrow_mut() = submission_value;
+        Ok(())
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-elementinternals-form
+    fn GetForm(&self) -> Fallible<Option<DomRoot<HTMLFormElement>>> {
+        if !self.is_target_form_associated() {
+            return Err(Error::NotSupported);
+        }
+        Ok(self.target_element.form_owner())
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-elementinternals-setvalidity
+    fn SetValidity(
+        &self,
+        flags: &ValidityStateFlags,
+        message: Option<DOMString>,
+        anchor: Option<&HTMLElement>,
+    ) -> ErrorResult {
+        // Steps 1-2
+        if !self.is_target_form_associated() {
+            return Err(Error::NotSupported);
+        }
+
+        // Step 3
+        let flags = validation_flags_from_dictionary(flags);
+        let message = message.unwrap_or_default();
+        if !flags.is_empty() && message.is_empty() {
+            return Err(Error::Type(
+                "A validation message is required when setting validity flags".to_owned(),
+            ));
+        }
+
+        // Step 4
+        self.validity_flags.set(flags);
+
+        // Step 5
+        *self.validation_message.borrow_mut() = if flags.is_empty() {
+            DOMString::new()
+        } else {
+            message
+        };
+
+        // Step 6
+        let custom_error_message = if flags.contains(ValidationFlags::CUSTOM_ERROR) {
+            self.validation_message()
+        } else {
+            DOMString::new()
+        };
+        self.validity_state()
+            .set_custom_error_message(custom_error_message);
+
+        // Step 7. The validation anchor is only used to position the validation
+        // message, which we never display, so it isn't stored.
+        if let Some(anchor) = anchor {
+            let target = self.target_element.upcast::<Node>();
+            let is_descendant = anchor
+                .upcast::<Node>()
+                .inclusive_ancestors(ShadowIncluding::Yes)
+                .skip(1)
+                .any(|ancestor| &*ancestor == target);
+            if !is_descendant {
+                return Err(Error::NotFound);
+            }
+        }
+
+        Ok(())
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-elementinternals-willvalidate
+    fn WillValidate(&self) -> Fallible<bool> {
+        if !self.is_target_form_associated() {
+            return Err(Error::NotSupported);
+        }
+        Ok(self.target_element.is_instance_validatable())
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-elementinternals-validity
+    fn Validity(&self) -> Fallible<DomRoot<ValidityState>> {
+        if !self.is_target_form_associated() {
+            return Err(Error::NotSupported);
+        }
+        Ok(self.validity_state())
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-elementinternals-validationmessage
+    fn ValidationMessage(&self) -> Fallible<DOMString> {
+        if !self.is_target_form_associated() {
+            return Err(Error::NotSupported);
+        }
+        Ok(self.target_element.validation_message())
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-elementinternals-checkvalidity
+    fn CheckValidity(&self) -> Fallible<bool> {
+        if !self.is_target_form_associated() {
+            return Err(Error::NotSupported);
+        }
+        Ok(self.target_element.check_validity())
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-elementinternals-reportvalidity
+    fn ReportValidity(&self) -> Fallible<bool> {
+        if !self.is_target_form_associated() {
+            return Err(Error::NotSupported);
+        }
+        Ok(self.target_element.report_validity())
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-elementinternals-labels
+    fn Labels(&self) -> Fallible<DomRoot<NodeList>> {
+        if !self.is_target_form_associated() {
+            return Err(Error::NotSupported);
+        }
+        Ok(self.labels_node_list.or_init(|| {
+            NodeList::new_labels_list(
+                &window_from_node(&*self.target_element),
+                &*self.target_element,
+            )
+        }))
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-elementinternals-states
+    fn States(&self) -> DomRoot<CustomStateSet> {
+        self.target_element.upcast::<Element>().custom_state_set()
+    }
+}
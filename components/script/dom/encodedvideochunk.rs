@@ -0,0 +1,127 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::EncodedVideoChunkBinding::{
+    EncodedVideoChunkInit, EncodedVideoChunkMethods, EncodedVideoChunkType,
+};
+use crate::dom::bindings::codegen::UnionTypes::ArrayBufferViewOrArrayBuffer;
+use crate::dom::bindings::error::ErrorResult;
+use crate::dom::bindings::reflector::{reflect_dom_object, Reflector};
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::bindings::trace::RootedTraceableBox;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::webcodecs;
+use dom_struct::dom_struct;
+use media::codecs::EncodedChunk;
+
+/// https://w3c.github.io/webcodecs/#encodedvideochunk-interface
+#[dom_struct]
+pub struct EncodedVideoChunk {
+    reflector_: Reflector,
+    type_: EncodedVideoChunkType,
+    timestamp: i64,
+    duration: Option<u64>,
+    data: Vec<u8>,
+}
+
+impl EncodedVideoChunk {
+    fn new_inherited(
+        type_: EncodedVideoChunkType,
+        timestamp: i64,
+        duration: Option<u64>,
+        data: Vec<u8>,
+    ) -> EncodedVideoChunk {
+        EncodedVideoChunk {
+            reflector_: Reflector::new(),
+            type_,
+            timestamp,
+            duration,
+            data,
+        }
+    }
+
+    pub fn new(
+        global: &GlobalScope,
+        type_: EncodedVideoChunkType,
+        timestamp: i64,
+        duration: Option<u64>,
+        data: Vec<u8>,
+    ) -> DomRoot<EncodedVideoChunk> {
+        reflect_dom_object(
+            Box::new(EncodedVideoChunk::new_inherited(
+                type_, timestamp, duration, data,
+            )),
+            global,
+        )
+    }
+
+    /// Wraps a chunk produced by a media backend encoder.
+    pub fn new_from_encoded(
+        global: &GlobalScope,
+        chunk: EncodedChunk,
+    ) -> DomRoot<EncodedVideoChunk> {
+        let type_ = if chunk.key {
+            EncodedVideoChunkType::Key
+        } else {
+            EncodedVideoChunkType::Delta
+        };
+        EncodedVideoChunk::new(global, type_, chunk.timestamp, chunk.duration, chunk.data)
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-encodedvideochunk-encodedvideochunk
+    #[allow(non_snake_case)]
+    pub fn Constructor(
+        global: &GlobalScope,
+        init: RootedTraceableBox<EncodedVideoChunkInit>,
+    ) -> DomRoot<EncodedVideoChunk> {
+        EncodedVideoChunk::new(
+            global,
+            init.type_,
+            init.timestamp,
+            init.duration,
+            webcodecs::buffer_source_to_vec(&init.data),
+        )
+    }
+
+    pub fn is_key(&self) -> bool {
+        self.type_ == EncodedVideoChunkType::Key
+    }
+
+    /// The chunk in the form the media backend decoders take.
+    pub fn to_backend_chunk(&self) -> EncodedChunk {
+        EncodedChunk {
+            key: self.is_key(),
+            timestamp: self.timestamp,
+            duration: self.duration,
+            data: self.data.clone(),
+        }
+    }
+}
+
+impl EncodedVideoChunkMethods for EncodedVideoChunk {
+    /// https://w3c.github.io/webcodecs/#dom-encodedvideochunk-type
+    fn Type(&self) -> EncodedVideoChunkType {
+        self.type_
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-encodedvideochunk-timestamp
+    fn Timestamp(&self) -> i64 {
+        self.timestamp
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-encodedvideochunk-duration
+    fn GetDuration(&self) -> Option<u64> {
+        self.duration
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-encodedvideochunk-bytelength
+    fn ByteLength(&self) -> u32 {
+        self.data.len() as u32
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-encodedvideochunk-copyto
+    fn CopyTo(&self, mut destination: ArrayBufferViewOrArrayBuffer) -> ErrorResult {
+        webcodecs::copy_to_buffer_source(&mut destination, &self.data)
+    }
+}
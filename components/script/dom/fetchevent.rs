@@ -0,0 +1,346 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::body::{consume_body, BodyMixin, BodyType};
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::EventBinding::EventMethods;
+use crate::dom::bindings::codegen::Bindings::FetchEventBinding::{
+    FetchEventInit, FetchEventMethods,
+};
+use crate::dom::bindings::codegen::Bindings::ResponseBinding::{
+    ResponseMethods, ResponseType as DOMResponseType,
+};
+use crate::dom::bindings::conversions::root_from_handlevalue;
+use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject};
+use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::bindings::str::DOMString;
+use crate::dom::event::{Event, EventStatus};
+use crate::dom::extendableevent::ExtendableEvent;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::promisenativehandler::{Callback, PromiseNativeHandler};
+use crate::dom::request::Request;
+use crate::dom::response::Response;
+use crate::dom::serviceworkerglobalscope::ServiceWorkerGlobalScope;
+use crate::realms::InRealm;
+use crate::script_runtime::JSContext as SafeJSContext;
+use dom_struct::dom_struct;
+use http::StatusCode;
+use ipc_channel::ipc::IpcSender;
+use js::rust::HandleValue;
+use js::typedarray::ArrayBuffer;
+use net_traits::response::ResponseType;
+use net_traits::{CustomResponse, NetworkError};
+use servo_atoms::Atom;
+use servo_url::ServoUrl;
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// <https://w3c.github.io/ServiceWorker/#fetchevent-interface>
+#[dom_struct]
+pub struct FetchEvent {
+    event: ExtendableEvent,
+    request: Dom<Request>,
+    #[ignore_malloc_size_of = "Rc"]
+    preload_response: Rc<Promise>,
+    client_id: DOMString,
+    resulting_client_id: DOMString,
+    replaces_client_id: DOMString,
+    #[ignore_malloc_size_of = "Rc"]
+    handled: Rc<Promise>,
+    /// <https://w3c.github.io/ServiceWorker/#fetchevent-respond-with-entered-flag>
+    respond_with_entered: Cell<bool>,
+    /// The channel handle fetch waits on, for events fired by the user agent.
+    #[ignore_malloc_size_of = "Channels are hard"]
+    response_chan: DomRefCell<Option<IpcSender<Option<CustomResponse>>>>,
+}
+
+impl FetchEvent {
+    #[allow(unrooted_must_root)]
+    fn new_inherited(
+        request: &Request,
+        preload_response: Rc<Promise>,
+        client_id: DOMString,
+        resulting_client_id: DOMString,
+        replaces_client_id: DOMString,
+        handled: Rc<Promise>,
+        response_chan: Option<IpcSender<Option<CustomResponse>>>,
+    ) -> FetchEvent {
+        FetchEvent {
+            event: ExtendableEvent::new_inherited(),
+            request: Dom::from_ref(request),
+            preload_response,
+            client_id,
+            resulting_client_id,
+            replaces_client_id,
+            handled,
+            respond_with_entered: Cell::new(false),
+            response_chan: DomRefCell::new(response_chan),
+        }
+    }
+
+    #[allow(unrooted_must_root)]
+    pub fn new(
+        global: &GlobalScope,
+        type_: Atom,
+        bubbles: bool,
+        cancelable: bool,
+        request: &Request,
+        preload_response: Rc<Promise>,
+        client_id: DOMString,
+        resulting_client_id: DOMString,
+        replaces_client_id: DOMString,
+        handled: Rc<Promise>,
+        response_chan: Option<IpcSender<Option<CustomResponse>>>,
+    ) -> DomRoot<FetchEvent> {
+        let ev = reflect_dom_object(
+            Box::new(FetchEvent::new_inherited(
+                request,
+                preload_response,
+                client_id,
+                resulting_client_id,
+                replaces_client_id,
+                handled,
+                response_chan,
+            )),
+            global,
+        );
+        {
+            let event = ev.upcast::<Event>();
+            event.init_event(type_, bubbles, cancelable);
+        }
+        ev
+    }
+
+    #[allow(non_snake_case)]
+    pub fn Constructor(
+        worker: &ServiceWorkerGlobalScope,
+        type_: DOMString,
+        init: &FetchEventInit,
+    ) -> Fallible<DomRoot<FetchEvent>> {
+        let global = worker.upcast::<GlobalScope>();
+        let preload_response = init.preloadResponse.clone().unwrap_or_else(|| {
+            let promise = Promise::new(global);
+            promise.resolve_native(&());
+            promise
+        });
+        let handled = init.handled.clone().unwrap_or_else(|| Promise::new(global));
+        Ok(FetchEvent::new(
+            global,
+            Atom::from(type_),
+            init.parent.parent.bubbles,
+            init.parent.parent.cancelable,
+            &init.request,
+            preload_response,
+            init.clientId.clone(),
+            init.resultingClientId.clone(),
+            init.replacesClientId.clone(),
+            handled,
+            None,
+        ))
+    }
+
+    /// Steps of <https://w3c.github.io/ServiceWorker/#handle-fetch> run once the event has
+    /// been dispatched: without a call to `respondWith()`, the request goes to the network,
+    /// unless the event was canceled.
+    pub fn finish_dispatch(&self, status: EventStatus) {
+        if self.respond_with_entered.get() {
+            return;
+        }
+        match status {
+            EventStatus::Canceled => self.respond(Some(CustomResponse::network_error(
+                NetworkError::Internal("Fetch event canceled".to_owned()),
+            ))),
+            EventStatus::NotCanceled => self.respond(None),
+        }
+    }
+
+    /// Replies to handle fetch, with `None` falling back to the network.
+    /// Events constructed by script have no one to reply to.
+    fn respond(&self, response: Option<CustomResponse>) {
+        let response_chan = match self.response_chan.borrow_mut().take() {
+            Some(response_chan) => response_chan,
+            None => return,
+        };
+        let is_error = match response {
+            Some(CustomResponse {
+                response_type: ResponseType::Error(..),
+                ..
+            }) => true,
+            _ => false,
+        };
+        let _ = response_chan.send(response);
+        if is_error {
+            self.handled.reject_error(Error::Network);
+        } else {
+            self.handled.resolve_native(&());
+        }
+    }
+
+    fn respond_with_error(&self) {
+        self.respond(Some(CustomResponse::network_error(NetworkError::Internal(
+            "Fetch event responded with an invalid response".to_owned(),
+        ))));
+    }
+}
+
+impl FetchEventMethods for FetchEvent {
+    // https://w3c.github.io/ServiceWorker/#fetch-event-request
+    fn Request(&self) -> DomRoot<Request> {
+        DomRoot::from_ref(&*self.request)
+    }
+
+    // https://w3c.github.io/ServiceWorker/#fetch-event-preloadresponse
+    fn PreloadResponse(&self) -> Rc<Promise> {
+        self.preload_response.clone()
+    }
+
+    // https://w3c.github.io/ServiceWorker/#fetch-event-clientid
+    fn ClientId(&self) -> DOMString {
+        self.client_id.clone()
+    }
+
+    // https://w3c.github.io/ServiceWorker/#fetch-event-resultingclientid
+    fn ResultingClientId(&self) -> DOMString {
+        self.resulting_client_id.clone()
+    }
+
+    // https://w3c.github.io/ServiceWorker/#fetch-event-replacesclientid
+    fn ReplacesClientId(&self) -> DOMString {
+        self.replaces_client_id.clone()
+    }
+
+    // https://w3c.github.io/ServiceWorker/#fetch-event-handled
+    fn Handled(&self) -> Rc<Promise> {
+        self.handled.clone()
+    }
+
+    // https://w3c.github.io/ServiceWorker/#fetch-event-respondwith
+    fn RespondWith(&self, r: &Promise, comp: InRealm) -> ErrorResult {
+        let event = self.upcast::<Event>();
+
+        // Step 2.
+        if !event.dispatching() {
+            return Err(Error::InvalidState);
+        }
+
+        // Step 3.
+        if self.respond_with_entered.get() {
+            return Err(Error::InvalidState);
+        }
+
+        // TODO: Step 4, add r to the extend lifetime promises.
+
+        // Step 5.
+        event.StopImmediatePropagation();
+
+        // Step 6.
+        self.respond_with_entered.set(true);
+
+        // Step 8.
+        let this = DomRoot::from_ref(self);
+        let handler = PromiseNativeHandler::new(
+            &self.global(),
+            Some(Box::new(RespondWithHandler {
+                event: this.clone(),
+            })),
+            Some(Box::new(RespondWithRejectionHandler { event: this })),
+        );
+        r.append_native_handler(&handler, comp);
+        Ok(())
+    }
+
+    // https://dom.spec.whatwg.org/#dom-event-istrusted
+    fn IsTrusted(&self) -> bool {
+        self.event.IsTrusted()
+    }
+}
+
+/// Reads the body of the response passed to `respondWith()`.
+#[derive(JSTraceable, MallocSizeOf)]
+struct RespondWithHandler {
+    event: DomRoot<FetchEvent>,
+}
+
+impl Callback for RespondWithHandler {
+    /// Step 8.3 of <https://w3c.github.io/ServiceWorker/#fetch-event-respondwith>
+    fn callback(&self, cx: SafeJSContext, v: HandleValue, realm: InRealm) {
+        let response = match root_from_handlevalue::<Response>(v, *cx) {
+            Ok(response) => response,
+            Err(()) => return self.event.respond_with_error(),
+        };
+        if response.is_disturbed() || response.is_locked() {
+            return self.event.respond_with_error();
+        }
+        let body_promise = consume_body(&*response, BodyType::ArrayBuffer);
+        let handler = PromiseNativeHandler::new(
+            &self.event.global(),
+            Some(Box::new(ResponseBodyHandler {
+                event: self.event.clone(),
+                response,
+            })),
+            Some(Box::new(RespondWithRejectionHandler {
+                event: self.event.clone(),
+            })),
+        );
+        body_promise.append_native_handler(&handler, realm);
+    }
+}
+
+/// Sends the response passed to `respondWith()` once its body has been read.
+#[derive(JSTraceable, MallocSizeOf)]
+struct ResponseBodyHandler {
+    event: DomRoot<FetchEvent>,
+    response: DomRoot<Response>,
+}
+
+impl Callback for ResponseBodyHandler {
+    #[allow(unsafe_code)]
+    fn callback(&self, cx: SafeJSContext, v: HandleValue, _realm: InRealm) {
+        if !v.is_object() {
+            return self.event.respond_with_error();
+        }
+        typedarray!(in(*cx) let buffer: ArrayBuffer = v.to_object());
+        let body = match buffer {
+            Ok(buffer) => buffer.to_vec(),
+            Err(()) => return self.event.respond_with_error(),
+        };
+        let response = &self.response;
+        let response_type = match response.Type() {
+            DOMResponseType::Basic => ResponseType::Basic,
+            DOMResponseType::Cors => ResponseType::Cors,
+            DOMResponseType::Default => ResponseType::Default,
+            DOMResponseType::Error => return self.event.respond_with_error(),
+            DOMResponseType::Opaque => ResponseType::Opaque,
+            DOMResponseType::Opaqueredirect => ResponseType::OpaqueRedirect,
+        };
+        // Filtered responses have a status of 0, which is not kept in the network process.
+        let status = StatusCode::from_u16(response.Status()).unwrap_or(StatusCode::OK);
+        self.event.respond(Some(CustomResponse::new(
+            response_type,
+            ServoUrl::parse(&response.Url()).ok(),
+            response.Headers().get_headers_list(),
+            (
+                status,
+                String::from_utf8_lossy(&response.StatusText()).into_owned(),
+            ),
+            body,
+        )));
+    }
+}
+
+/// Makes the fetch fail when the promise passed to `respondWith()` is rejected,
+/// or the body of its response can not be read.
+#[derive(JSTraceable, MallocSizeOf)]
+struct RespondWithRejectionHandler {
+    event: DomRoot<FetchEvent>,
+}
+
+impl Callback for RespondWithRejectionHandler {
+    fn callback(&self, _cx: SafeJSContext, _v: HandleValue, _realm: InRealm) {
+        self.event.respond_with_error();
+    }
+}
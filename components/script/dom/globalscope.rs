@@ -5,6 +5,7 @@
 use crate::dom::bindings::cell::{DomRefCell, RefMut};
 use crate::dom::bindings::codegen::Bindings::BroadcastChannelBinding::BroadcastChannelMethods;
 use crate::dom::bindings::codegen::Bindings::EventSourceBinding::EventSourceBinding::EventSourceMethods;
+use crate::dom::bindings::codegen::Bindings::FunctionBinding::Function;
 use crate::dom::bindings::codegen::Bindings::ImageBitmapBinding::{
     ImageBitmapOptions, ImageBitmapSource,
 };
@@ -25,6 +26,7 @@ use crate::dom::bindings::utils::to_frozen_array;
 use crate::dom::bindings::weakref::{DOMTracker, WeakRef};
 use crate::dom::blob::Blob;
 use crate::dom::broadcastchannel::BroadcastChannel;
+use crate::dom::cachestorage::CacheStorage;
 use crate::dom::crypto::Crypto;
 use crate::dom::dedicatedworkerglobalscope::{
     DedicatedWorkerControlMsg, DedicatedWorkerGlobalScope,
@@ -38,6 +40,7 @@ use crate::dom::gpudevice::GPUDevice;
 use crate::dom::htmlscriptelement::{ScriptId, SourceCode};
 use crate::dom::identityhub::Identities;
 use crate::dom::imagebitmap::ImageBitmap;
+use crate::dom::mediasource::MediaSource;
 use crate::dom::messageevent::MessageEvent;
 use crate::dom::messageport::MessagePort;
 use crate::dom::paintworkletglobalscope::PaintWorkletGlobalScope;
@@ -52,7 +55,7 @@ use crate::dom::workerglobalscope::WorkerGlobalScope;
 use crate::dom::workletglobalscope::WorkletGlobalScope;
 use crate::microtask::{Microtask, MicrotaskQueue, UserMicrotask};
 use crate::realms::{enter_realm, AlreadyInRealm, InRealm};
-use crate::script_module::{DynamicModuleList, ModuleTree};
+use crate::script_module::{DynamicModuleList, ImportMap, ModuleTree};
 use crate::script_module::{ModuleScript, ScriptFetchOptions};
 use crate::script_runtime::{
     CommonScriptMsg, ContextForRequestInterrupt, JSContext as SafeJSContext, ScriptChan, ScriptPort,
@@ -175,6 +178,7 @@ impl Drop for AutoCloseWorker {
 pub struct GlobalScope {
     eventtarget: EventTarget,
     crypto: MutNullableDom<Crypto>,
+    caches: MutNullableDom<CacheStorage>,
 
     /// The message-port router id for this global, if it is managing ports.
     message_port_state: DomRefCell<MessagePortState>,
@@ -210,6 +214,10 @@ pub struct GlobalScope {
     #[ignore_malloc_size_of = "mozjs"]
     inline_module_map: DomRefCell<HashMap<ScriptId, Rc<ModuleTree>>>,
 
+    /// <https://html.spec.whatwg.org/multipage/#concept-global-import-map>
+    #[ignore_malloc_size_of = "Defined in indexmap"]
+    import_map: DomRefCell<ImportMap>,
+
     /// For providing instructions to an optional devtools server.
     #[ignore_malloc_size_of = "channels are hard"]
     devtools_chan: Option<IpcSender<ScriptToDevtoolsControlMsg>>,
@@ -315,6 +323,18 @@ pub struct GlobalScope {
 
     /// Is considered in a secure context
     inherited_secure_context: Option<bool>,
+
+    /// <https://streams.spec.whatwg.org/#count-queuing-strategy-size-function>
+    #[ignore_malloc_size_of = "Rc<T> is hard"]
+    count_queuing_strategy_size_function: DomRefCell<Option<Rc<Function>>>,
+
+    /// <https://streams.spec.whatwg.org/#byte-length-queuing-strategy-size-function>
+    #[ignore_malloc_size_of = "Rc<T> is hard"]
+    byte_length_queuing_strategy_size_function: DomRefCell<Option<Rc<Function>>>,
+
+    /// The media sources with an object URL created by this global.
+    /// <https://w3c.github.io/media-source/#dom-url-createobjecturl>
+    media_source_urls: DomRefCell<HashMap<ServoUrl, Dom<MediaSource>>>,
 }
 
 /// A wrapper for glue-code between the ipc router and the event-loop.
@@ -738,6 +758,7 @@ impl GlobalScope {
             blob_state: DomRefCell::new(BlobState::UnManaged),
             eventtarget: EventTarget::new_inherited(),
             crypto: Default::default(),
+            caches: Default::default(),
             registration_map: DomRefCell::new(HashMap::new()),
             worker_map: DomRefCell::new(HashMap::new()),
             pipeline_id,
@@ -745,6 +766,7 @@ impl GlobalScope {
             console_timers: DomRefCell::new(Default::default()),
             module_map: DomRefCell::new(Default::default()),
             inline_module_map: DomRefCell::new(Default::default()),
+            import_map: DomRefCell::new(Default::default()),
             devtools_chan,
             mem_profiler_chan,
             time_profiler_chan,
@@ -771,6 +793,9 @@ impl GlobalScope {
             console_group_stack: DomRefCell::new(Vec::new()),
             dynamic_modules: DomRefCell::new(DynamicModuleList::new()),
             inherited_secure_context,
+            count_queuing_strategy_size_function: DomRefCell::new(None),
+            byte_length_queuing_strategy_size_function: DomRefCell::new(None),
+            media_source_urls: DomRefCell::new(HashMap::new()),
         }
     }
 
@@ -2216,6 +2241,14 @@ impl GlobalScope {
         &self.inline_module_map
     }
 
+    pub fn set_import_map(&self, import_map: ImportMap) {
+        *self.import_map.borrow_mut() = import_map;
+    }
+
+    pub fn get_import_map(&self) -> &DomRefCell<ImportMap> {
+        &self.import_map
+    }
+
     #[allow(unsafe_code)]
     pub fn get_cx(&self) -> SafeJSContext {
         unsafe { SafeJSContext::from_ptr(Runtime::get()) }
@@ -2225,6 +2258,10 @@ impl GlobalScope {
         self.crypto.or_init(|| Crypto::new(self))
     }
 
+    pub fn caches(&self) -> DomRoot<CacheStorage> {
+        self.caches.or_init(|| CacheStorage::new(self))
+    }
+
     pub fn live_devtools_updates(&self) -> bool {
         self.devtools_wants_updates.get()
     }
@@ -2993,6 +3030,24 @@ impl GlobalScope {
         frozen_types
     }
 
+    pub fn get_count_queuing_strategy_size(&self) -> Option<Rc<Function>> {
+        self.count_queuing_strategy_size_function.borrow().clone()
+    }
+
+    pub fn set_count_queuing_strategy_size(&self, function: Rc<Function>) {
+        *self.count_queuing_strategy_size_function.borrow_mut() = Some(function);
+    }
+
+    pub fn get_byte_length_queuing_strategy_size(&self) -> Option<Rc<Function>> {
+        self.byte_length_queuing_strategy_size_function
+            .borrow()
+            .clone()
+    }
+
+    pub fn set_byte_length_queuing_strategy_size(&self, function: Rc<Function>) {
+        *self.byte_length_queuing_strategy_size_function.borrow_mut() = Some(function);
+    }
+
     pub fn is_headless(&self) -> bool {
         self.is_headless
     }
@@ -3045,6 +3100,26 @@ impl GlobalScope {
         let _ = self.gpu_devices.borrow_mut().remove(&device);
     }
 
+    pub fn add_media_source_url(&self, url: ServoUrl, media_source: &MediaSource) {
+        self.media_source_urls
+            .borrow_mut()
+            .insert(url, Dom::from_ref(media_source));
+    }
+
+    /// Remove the media source an object URL refers to, returning whether
+    /// there was one.
+    pub fn remove_media_source_url(&self, url: &ServoUrl) -> bool {
+        self.media_source_urls.borrow_mut().remove(url).is_some()
+    }
+
+    /// The media source an object URL refers to, if any.
+    pub fn media_source_for_url(&self, url: &ServoUrl) -> Option<DomRoot<MediaSource>> {
+        self.media_source_urls
+            .borrow()
+            .get(url)
+            .map(|media_source| DomRoot::from_ref(&**media_source))
+    }
+
     pub fn handle_wgpu_msg(
         &self,
         device: WebGPUDevice,
@@ -2,23 +2,33 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use crate::dom::attr::Attr;
 use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::EventBinding::EventMethods;
 use crate::dom::bindings::codegen::Bindings::HTMLDialogElementBinding::HTMLDialogElementMethods;
+use crate::dom::bindings::error::{Error, ErrorResult};
 use crate::dom::bindings::inheritance::Castable;
 use crate::dom::bindings::root::DomRoot;
 use crate::dom::bindings::str::DOMString;
-use crate::dom::document::Document;
-use crate::dom::element::Element;
+use crate::dom::document::{Document, FocusType};
+use crate::dom::element::{AttributeMutation, Element};
 use crate::dom::eventtarget::EventTarget;
 use crate::dom::htmlelement::HTMLElement;
-use crate::dom::node::{window_from_node, Node};
+use crate::dom::node::{
+    document_from_node, window_from_node, Node, ShadowIncluding, UnbindContext,
+};
+use crate::dom::virtualmethods::VirtualMethods;
 use dom_struct::dom_struct;
 use html5ever::{LocalName, Prefix};
+use std::cell::Cell;
+use style::element_state::ElementState;
 
 #[dom_struct]
 pub struct HTMLDialogElement {
     htmlelement: HTMLElement,
     return_value: DomRefCell<DOMString>,
+    /// <https://html.spec.whatwg.org/multipage/#is-modal>
+    is_modal: Cell<bool>,
 }
 
 impl HTMLDialogElement {
@@ -30,6 +40,7 @@ impl HTMLDialogElement {
         HTMLDialogElement {
             htmlelement: HTMLElement::new_inherited(local_name, prefix, document),
             return_value: DomRefCell::new(DOMString::new()),
+            is_modal: Cell::new(false),
         }
     }
 
@@ -46,6 +57,60 @@ impl HTMLDialogElement {
             document,
         )
     }
+
+    pub fn is_modal(&self) -> bool {
+        self.is_modal.get()
+    }
+
+    fn set_modal(&self, is_modal: bool) {
+        self.is_modal.set(is_modal);
+        self.upcast::<Element>()
+            .set_state(ElementState::IN_MODAL_DIALOG_STATE, is_modal);
+    }
+
+    /// Removes this dialog from the top layer of its document, if it is a
+    /// modal dialog.
+    fn stop_being_modal(&self) {
+        if !self.is_modal() {
+            return;
+        }
+        self.set_modal(false);
+        document_from_node(self).remove_from_top_layer(self.upcast());
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#dialog-focusing-steps>
+    fn run_focusing_steps(&self) {
+        // Step 1.
+        let descendants = || {
+            self.upcast::<Node>()
+                .traverse_preorder(ShadowIncluding::No)
+                .skip(1)
+                .filter_map(DomRoot::downcast::<Element>)
+                .filter(|element| !element.upcast::<Node>().is_inert())
+        };
+        let control = descendants()
+            .find(|element| element.has_attribute(&local_name!("autofocus")))
+            .or_else(|| descendants().find(|element| element.is_focusable_area()));
+
+        // Step 2.
+        let control = match control {
+            Some(control) => control,
+            None => DomRoot::from_ref(self.upcast::<Element>()),
+        };
+
+        // Step 3.
+        document_from_node(self).request_focus(Some(&*control), FocusType::Element);
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#cancel-dialog>
+    pub fn cancel(&self) {
+        let event = self
+            .upcast::<EventTarget>()
+            .fire_cancelable_event(atom!("cancel"));
+        if !event.DefaultPrevented() {
+            self.Close(None);
+        }
+    }
 }
 
 impl HTMLDialogElementMethods for HTMLDialogElement {
@@ -66,6 +131,48 @@ impl HTMLDialogElementMethods for HTMLDialogElement {
         *self.return_value.borrow_mut() = return_value;
     }
 
+    // https://html.spec.whatwg.org/multipage/#dom-dialog-show
+    fn Show(&self) {
+        let element = self.upcast::<Element>();
+
+        // Step 1.
+        if element.has_attribute(&local_name!("open")) {
+            return;
+        }
+
+        // Step 2.
+        element.set_bool_attribute(&local_name!("open"), true);
+
+        // Step 3.
+        self.run_focusing_steps();
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-dialog-showmodal
+    fn ShowModal(&self) -> ErrorResult {
+        let element = self.upcast::<Element>();
+
+        // Step 1.
+        if element.has_attribute(&local_name!("open")) {
+            return Err(Error::InvalidState);
+        }
+
+        // Step 2.
+        if !self.upcast::<Node>().is_connected() {
+            return Err(Error::InvalidState);
+        }
+
+        // Step 3.
+        element.set_bool_attribute(&local_name!("open"), true);
+
+        // Steps 4-6.
+        self.set_modal(true);
+        document_from_node(self).add_to_top_layer(element);
+
+        // Step 7.
+        self.run_focusing_steps();
+        Ok(())
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-dialog-close
     fn Close(&self, return_value: Option<DOMString>) {
         let element = self.upcast::<Element>();
@@ -85,7 +192,8 @@ impl HTMLDialogElementMethods for HTMLDialogElement {
             *self.return_value.borrow_mut() = new_value;
         }
 
-        // TODO: Step 4 implement pending dialog stack removal
+        // Step 4
+        self.stop_being_modal();
 
         // Step 5
         win.task_manager()
@@ -93,3 +201,31 @@ impl HTMLDialogElementMethods for HTMLDialogElement {
             .queue_simple_event(target, atom!("close"), &win);
     }
 }
+
+impl VirtualMethods for HTMLDialogElement {
+    fn super_type(&self) -> Option<&dyn VirtualMethods> {
+        Some(self.upcast::<HTMLElement>() as &dyn VirtualMethods)
+    }
+
+    fn attribute_mutated(&self, attr: &Attr, mutation: AttributeMutation) {
+        self.super_type().unwrap().attribute_mutated(attr, mutation);
+
+        // A dialog whose open attribute is removed without closing it stops
+        // blocking its document.
+        match (attr.local_name(), mutation) {
+            (&local_name!("open"), AttributeMutation::Removed) => self.stop_being_modal(),
+            _ => {},
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/#the-dialog-element:html-element-removing-steps
+    fn unbind_from_tree(&self, context: &UnbindContext) {
+        if let Some(s) = self.super_type() {
+            s.unbind_from_tree(context);
+        }
+
+        if context.tree_connected {
+            self.stop_being_modal();
+        }
+    }
+}
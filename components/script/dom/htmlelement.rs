@@ -10,30 +10,37 @@ use crate::dom::bindings::codegen::Bindings::HTMLElementBinding::HTMLElementMeth
 use crate::dom::bindings::codegen::Bindings::HTMLLabelElementBinding::HTMLLabelElementMethods;
 use crate::dom::bindings::codegen::Bindings::NodeBinding::NodeBinding::NodeMethods;
 use crate::dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
-use crate::dom::bindings::error::{Error, ErrorResult};
+use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
 use crate::dom::bindings::inheritance::Castable;
 use crate::dom::bindings::inheritance::{ElementTypeId, HTMLElementTypeId, NodeTypeId};
 use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
 use crate::dom::bindings::str::DOMString;
 use crate::dom::cssstyledeclaration::{CSSModificationAccess, CSSStyleDeclaration, CSSStyleOwner};
+use crate::dom::customelementregistry::{CallbackReaction, CustomElementState};
 use crate::dom::document::{Document, FocusType};
 use crate::dom::documentfragment::DocumentFragment;
 use crate::dom::domstringmap::DOMStringMap;
 use crate::dom::element::{AttributeMutation, Element};
+use crate::dom::elementinternals::ElementInternals;
 use crate::dom::event::Event;
 use crate::dom::eventtarget::EventTarget;
 use crate::dom::htmlbodyelement::HTMLBodyElement;
 use crate::dom::htmlbrelement::HTMLBRElement;
 use crate::dom::htmldetailselement::HTMLDetailsElement;
+use crate::dom::htmlformelement::{FormControl, HTMLFormElement};
 use crate::dom::htmlframesetelement::HTMLFrameSetElement;
 use crate::dom::htmlhtmlelement::HTMLHtmlElement;
 use crate::dom::htmlinputelement::{HTMLInputElement, InputType};
 use crate::dom::htmllabelelement::HTMLLabelElement;
 use crate::dom::htmltextareaelement::HTMLTextAreaElement;
 use crate::dom::node::{document_from_node, window_from_node};
-use crate::dom::node::{Node, ShadowIncluding};
+use crate::dom::node::{BindContext, Node, ShadowIncluding, UnbindContext};
 use crate::dom::text::Text;
+use crate::dom::validation::{is_barred_by_datalist_ancestor, Validatable};
+use crate::dom::validitystate::{ValidationFlags, ValidityState};
 use crate::dom::virtualmethods::VirtualMethods;
+use crate::editing;
+use crate::script_thread::ScriptThread;
 use dom_struct::dom_struct;
 use html5ever::{LocalName, Prefix};
 use script_layout_interface::message::QueryMsg;
@@ -43,6 +50,16 @@ use std::rc::Rc;
 use style::attr::AttrValue;
 use style::element_state::*;
 
+/// The state of an element's `contenteditable` attribute.
+///
+/// https://html.spec.whatwg.org/multipage/#attr-contenteditable
+#[derive(Clone, Copy, PartialEq)]
+pub enum ContentEditableState {
+    True,
+    False,
+    Inherit,
+}
+
 #[dom_struct]
 pub struct HTMLElement {
     element: Element,
@@ -523,23 +540,75 @@ impl HTMLElementMethods for HTMLElement {
 
     // https://html.spec.whatwg.org/multipage/#dom-contenteditable
     fn ContentEditable(&self) -> DOMString {
-        // TODO: https://github.com/servo/servo/issues/12776
-        self.upcast::<Element>()
-            .get_attribute(&ns!(), &local_name!("contenteditable"))
-            .map(|attr| DOMString::from(&**attr.value()))
-            .unwrap_or_else(|| DOMString::from("inherit"))
+        match self.content_editable_state() {
+            ContentEditableState::True => DOMString::from("true"),
+            ContentEditableState::False => DOMString::from("false"),
+            ContentEditableState::Inherit => DOMString::from("inherit"),
+        }
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-contenteditable
-    fn SetContentEditable(&self, _: DOMString) {
-        // TODO: https://github.com/servo/servo/issues/12776
-        warn!("The contentEditable attribute is not implemented yet");
+    fn SetContentEditable(&self, value: DOMString) -> ErrorResult {
+        let element = self.upcast::<Element>();
+        let value = match_ignore_ascii_case! { &*value,
+            "inherit" => {
+                element.remove_attribute(&ns!(), &local_name!("contenteditable"));
+                return Ok(());
+            },
+            "true" => "true",
+            "false" => "false",
+            _ => return Err(Error::Syntax),
+        };
+        element.set_string_attribute(&local_name!("contenteditable"), DOMString::from(value));
+        Ok(())
     }
 
-    // https://html.spec.whatwg.org/multipage/#dom-contenteditable
+    // https://html.spec.whatwg.org/multipage/#dom-iscontenteditable
     fn IsContentEditable(&self) -> bool {
-        // TODO: https://github.com/servo/servo/issues/12776
-        false
+        editing::editing_host(self.upcast()).is_some()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-attachinternals
+    fn AttachInternals(&self) -> Fallible<DomRoot<ElementInternals>> {
+        let element = self.upcast::<Element>();
+
+        // Step 1
+        if element.get_is().is_some() {
+            return Err(Error::NotSupported);
+        }
+
+        // Steps 2-3
+        let definition = match document_from_node(self).lookup_custom_element_definition(
+            element.namespace(),
+            element.local_name(),
+            None,
+        ) {
+            Some(definition) => definition,
+            None => return Err(Error::NotSupported),
+        };
+
+        // Step 4
+        if definition.disable_internals {
+            return Err(Error::NotSupported);
+        }
+
+        // Step 5
+        if element.get_element_internals().is_some() {
+            return Err(Error::NotSupported);
+        }
+
+        // Step 6
+        match element.get_custom_element_state() {
+            CustomElementState::Precustomized | CustomElementState::Custom => {},
+            _ => return Err(Error::NotSupported),
+        }
+
+        // Step 7
+        let internals = ElementInternals::new(self);
+        element.set_element_internals(&internals);
+
+        // Step 8
+        Ok(internals)
     }
 }
 
@@ -615,6 +684,25 @@ fn to_camel_case(name: &str) -> Option<DOMString> {
 }
 
 impl HTMLElement {
+    // https://html.spec.whatwg.org/multipage/#attr-contenteditable
+    pub fn content_editable_state(&self) -> ContentEditableState {
+        let value = self
+            .upcast::<Element>()
+            .get_attribute(&ns!(), &local_name!("contenteditable"));
+        let value = match value {
+            Some(attr) => attr,
+            None => return ContentEditableState::Inherit,
+        };
+        let value = value.value();
+        if value.is_empty() || value.eq_ignore_ascii_case("true") {
+            ContentEditableState::True
+        } else if value.eq_ignore_ascii_case("false") {
+            ContentEditableState::False
+        } else {
+            ContentEditableState::Inherit
+        }
+    }
+
     pub fn set_custom_attr(&self, name: DOMString, value: DOMString) -> ErrorResult {
         if name
             .chars()
@@ -659,6 +747,9 @@ impl HTMLElement {
                 HTMLElementTypeId::HTMLProgressElement |
                 HTMLElementTypeId::HTMLSelectElement |
                 HTMLElementTypeId::HTMLTextAreaElement => true,
+                HTMLElementTypeId::HTMLElement => {
+                    self.upcast::<Element>().is_form_associated_custom_element()
+                },
                 _ => false,
             },
             _ => false,
@@ -682,12 +773,40 @@ impl HTMLElement {
                 HTMLElementTypeId::HTMLOutputElement |
                 HTMLElementTypeId::HTMLSelectElement |
                 HTMLElementTypeId::HTMLTextAreaElement => true,
+                HTMLElementTypeId::HTMLElement => {
+                    self.upcast::<Element>().is_form_associated_custom_element()
+                },
                 _ => false,
             },
             _ => false,
         }
     }
 
+    /// Updates the disabled state of a form-associated custom element, and
+    /// enqueues its `formDisabledCallback` if the state changed.
+    /// <https://html.spec.whatwg.org/multipage/#concept-fe-disabled>
+    pub fn update_form_associated_disabled_state(&self) {
+        let element = self.upcast::<Element>();
+        let disabled = element.has_attribute(&local_name!("disabled")) ||
+            element.is_disabled_by_fieldset_ancestor();
+        let was_disabled = element.disabled_state();
+        element.set_disabled_state(disabled);
+        element.set_enabled_state(!disabled);
+        if disabled != was_disabled {
+            ScriptThread::enqueue_callback_reaction(
+                element,
+                CallbackReaction::FormDisabled(disabled),
+                None,
+            );
+        }
+    }
+
+    fn element_internals(&self) -> DomRoot<ElementInternals> {
+        self.upcast::<Element>()
+            .get_element_internals()
+            .expect("Form-associated custom element without attached internals")
+    }
+
     pub fn supported_prop_names_custom_attr(&self) -> Vec<DOMString> {
         let element = self.upcast::<Element>();
         element
@@ -850,10 +969,38 @@ impl VirtualMethods for HTMLElement {
                     DOMString::from(&**attr.value()),
                 );
             },
+            (&local_name!("disabled"), _)
+                if self.upcast::<Element>().is_form_associated_custom_element() =>
+            {
+                self.update_form_associated_disabled_state();
+            }
+            (&local_name!("form"), mutation)
+                if self.upcast::<Element>().is_form_associated_custom_element() =>
+            {
+                self.form_attribute_mutated(mutation);
+            }
             _ => {},
         }
     }
 
+    fn bind_to_tree(&self, context: &BindContext) {
+        if let Some(ref s) = self.super_type() {
+            s.bind_to_tree(context);
+        }
+
+        if self.upcast::<Element>().is_form_associated_custom_element() {
+            self.update_form_associated_disabled_state();
+        }
+    }
+
+    fn unbind_from_tree(&self, context: &UnbindContext) {
+        self.super_type().unwrap().unbind_from_tree(context);
+
+        if self.upcast::<Element>().is_form_associated_custom_element() {
+            self.update_form_associated_disabled_state();
+        }
+    }
+
     fn parse_plain_attribute(&self, name: &LocalName, value: DOMString) -> AttrValue {
         match name {
             &local_name!("itemprop") => AttrValue::from_serialized_tokenlist(value.into()),
@@ -872,7 +1019,7 @@ impl Activatable for HTMLElement {
     }
 
     fn is_instance_activatable(&self) -> bool {
-        self.as_element().local_name() == &local_name!("summary")
+        self.upcast::<Element>().local_name() == &local_name!("summary")
     }
 
     // Basically used to make the HTMLSummaryElement activatable (which has no IDL definition)
@@ -880,3 +1027,61 @@ impl Activatable for HTMLElement {
         self.summary_activation_behavior();
     }
 }
+
+impl FormControl for HTMLElement {
+    fn form_owner(&self) -> Option<DomRoot<HTMLFormElement>> {
+        self.upcast::<Element>().custom_element_form_owner()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#reset-the-form-owner
+    // Form-associated custom elements are told about the new form owner
+    // through their formAssociatedCallback.
+    fn set_form_owner(&self, form: Option<&HTMLFormElement>) {
+        let element = self.upcast::<Element>();
+        if element.custom_element_form_owner().as_deref() == form {
+            return;
+        }
+        element.set_custom_element_form_owner(form);
+        ScriptThread::enqueue_callback_reaction(
+            element,
+            CallbackReaction::FormAssociated(form.map(DomRoot::from_ref)),
+            None,
+        );
+    }
+
+    fn to_element<'a>(&'a self) -> &'a Element {
+        self.upcast::<Element>()
+    }
+}
+
+impl Validatable for HTMLElement {
+    fn as_element(&self) -> &Element {
+        self.upcast()
+    }
+
+    fn validity_state(&self) -> DomRoot<ValidityState> {
+        self.element_internals().validity_state()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#candidate-for-constraint-validation
+    fn is_instance_validatable(&self) -> bool {
+        let element = self.upcast::<Element>();
+        element.is_form_associated_custom_element() &&
+            !element.disabled_state() &&
+            !element.has_attribute(&local_name!("readonly")) &&
+            !is_barred_by_datalist_ancestor(self.upcast())
+    }
+
+    fn perform_validation(&self, validate_flags: ValidationFlags) -> ValidationFlags {
+        self.element_internals().validity_flags() & validate_flags
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-validationmessage
+    fn validation_message(&self) -> DOMString {
+        if self.is_instance_validatable() && !self.validate(ValidationFlags::all()).is_empty() {
+            self.element_internals().validation_message()
+        } else {
+            DOMString::new()
+        }
+    }
+}
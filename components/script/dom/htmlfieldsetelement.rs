@@ -174,21 +174,39 @@ impl VirtualMethods for HTMLFieldSetElement {
                             NodeTypeId::Element(ElementTypeId::HTMLElement(
                                 HTMLElementTypeId::HTMLTextAreaElement,
                             )) => true,
+                            NodeTypeId::Element(ElementTypeId::HTMLElement(
+                                HTMLElementTypeId::HTMLElement,
+                            )) => descendant
+                                .downcast::<Element>()
+                                .unwrap()
+                                .is_form_associated_custom_element(),
                             _ => false,
                         })
                 });
                 if disabled_state {
                     for field in fields {
                         let el = field.downcast::<Element>().unwrap();
-                        el.set_disabled_state(true);
-                        el.set_enabled_state(false);
+                        if el.is_form_associated_custom_element() {
+                            el.downcast::<HTMLElement>()
+                                .unwrap()
+                                .update_form_associated_disabled_state();
+                        } else {
+                            el.set_disabled_state(true);
+                            el.set_enabled_state(false);
+                        }
                         el.update_sequentially_focusable_status();
                     }
                 } else {
                     for field in fields {
                         let el = field.downcast::<Element>().unwrap();
-                        el.check_disabled_attribute();
-                        el.check_ancestors_disabled_state_for_form_control();
+                        if el.is_form_associated_custom_element() {
+                            el.downcast::<HTMLElement>()
+                                .unwrap()
+                                .update_form_associated_disabled_state();
+                        } else {
+                            el.check_disabled_attribute();
+                            el.check_ancestors_disabled_state_for_form_control();
+                        }
                         el.update_sequentially_focusable_status();
                     }
                 }
@@ -23,6 +23,7 @@ use crate::dom::bindings::reflector::DomObject;
 use crate::dom::bindings::root::{Dom, DomOnceCell, DomRoot, MutNullableDom};
 use crate::dom::bindings::str::DOMString;
 use crate::dom::blob::Blob;
+use crate::dom::customelementregistry::CallbackReaction;
 use crate::dom::document::Document;
 use crate::dom::domtokenlist::DOMTokenList;
 use crate::dom::element::{AttributeMutation, Element};
@@ -56,6 +57,7 @@ use crate::dom::submitevent::SubmitEvent;
 use crate::dom::validitystate::ValidationFlags;
 use crate::dom::virtualmethods::VirtualMethods;
 use crate::dom::window::Window;
+use crate::script_thread::ScriptThread;
 use crate::task_source::TaskSource;
 use dom_struct::dom_struct;
 use encoding_rs::{Encoding, UTF_8};
@@ -355,6 +357,11 @@ impl HTMLFormElementMethods for HTMLFormElement {
                         HTMLElementTypeId::HTMLTextAreaElement => {
                             elem.downcast::<HTMLTextAreaElement>().unwrap().form_owner()
                         },
+                        HTMLElementTypeId::HTMLElement
+                            if elem.is_form_associated_custom_element() =>
+                        {
+                            elem.downcast::<HTMLElement>().unwrap().form_owner()
+                        },
                         _ => {
                             debug_assert!(
                                 !elem.downcast::<HTMLElement>().unwrap().is_listed_element() ||
@@ -1125,6 +1132,15 @@ impl HTMLFormElement {
                             });
                         }
                     },
+                    HTMLElementTypeId::HTMLElement => {
+                        // Form-associated custom elements submit the
+                        // value set through their internals.
+                        let element = child.downcast::<Element>().unwrap();
+                        if let Some(internals) = element.get_element_internals() {
+                            internals.append_entries(&mut data_set);
+                        }
+                        continue;
+                    },
                     _ => (),
                 }
             }
@@ -1282,6 +1298,14 @@ impl HTMLFormElement {
                 )) => {
                     child.downcast::<HTMLOutputElement>().unwrap().reset();
                 },
+                // https://html.spec.whatwg.org/multipage/#form-associated-custom-element
+                NodeTypeId::Element(ElementTypeId::HTMLElement(HTMLElementTypeId::HTMLElement)) => {
+                    ScriptThread::enqueue_callback_reaction(
+                        child.downcast::<Element>().unwrap(),
+                        CallbackReaction::FormReset,
+                        None,
+                    );
+                },
                 _ => {},
             }
         }
@@ -1734,6 +1758,11 @@ impl FormControlElementHelpers for Element {
             NodeTypeId::Element(ElementTypeId::HTMLElement(
                 HTMLElementTypeId::HTMLTextAreaElement,
             )) => Some(self.downcast::<HTMLTextAreaElement>().unwrap() as &dyn FormControl),
+            NodeTypeId::Element(ElementTypeId::HTMLElement(HTMLElementTypeId::HTMLElement))
+                if self.is_form_associated_custom_element() =>
+            {
+                Some(self.downcast::<HTMLElement>().unwrap() as &dyn FormControl)
+            },
             _ => None,
         }
     }
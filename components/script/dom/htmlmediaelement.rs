@@ -16,7 +16,12 @@ use crate::dom::bindings::codegen::Bindings::MediaErrorBinding::MediaErrorConsta
 use crate::dom::bindings::codegen::Bindings::MediaErrorBinding::MediaErrorMethods;
 use crate::dom::bindings::codegen::Bindings::NavigatorBinding::NavigatorBinding::NavigatorMethods;
 use crate::dom::bindings::codegen::Bindings::NodeBinding::NodeBinding::NodeMethods;
-use crate::dom::bindings::codegen::Bindings::TextTrackBinding::{TextTrackKind, TextTrackMode};
+use crate::dom::bindings::codegen::Bindings::ShadowRootBinding::ShadowRootMode;
+use crate::dom::bindings::codegen::Bindings::TextTrackBinding::{
+    TextTrackKind, TextTrackMethods, TextTrackMode,
+};
+use crate::dom::bindings::codegen::Bindings::VTTCueBinding::{DirectionSetting, VTTCueMethods};
+use crate::dom::bindings::codegen::Bindings::VTTRegionBinding::VTTRegionMethods;
 use crate::dom::bindings::codegen::Bindings::WindowBinding::WindowBinding::WindowMethods;
 use crate::dom::bindings::codegen::InheritTypes::{ElementTypeId, HTMLElementTypeId};
 use crate::dom::bindings::codegen::InheritTypes::{HTMLMediaElementTypeId, NodeTypeId};
@@ -39,6 +44,7 @@ use crate::dom::element::{AttributeMutation, Element, ElementCreator};
 use crate::dom::event::Event;
 use crate::dom::eventtarget::EventTarget;
 use crate::dom::globalscope::GlobalScope;
+use crate::dom::htmldivelement::HTMLDivElement;
 use crate::dom::htmlelement::HTMLElement;
 use crate::dom::htmlscriptelement::HTMLScriptElement;
 use crate::dom::htmlsourceelement::HTMLSourceElement;
@@ -46,12 +52,14 @@ use crate::dom::htmlstyleelement::HTMLStyleElement;
 use crate::dom::htmlvideoelement::HTMLVideoElement;
 use crate::dom::mediaerror::MediaError;
 use crate::dom::mediafragmentparser::MediaFragmentParser;
+use crate::dom::mediasource::MediaSource;
 use crate::dom::mediastream::MediaStream;
 use crate::dom::node::{document_from_node, window_from_node, Node, NodeDamage, UnbindContext};
 use crate::dom::performanceresourcetiming::InitiatorType;
 use crate::dom::promise::Promise;
-use crate::dom::shadowroot::IsUserAgentWidget;
+use crate::dom::shadowroot::{IsUserAgentWidget, ShadowRoot};
 use crate::dom::texttrack::TextTrack;
+use crate::dom::texttrackcue::TextTrackCue;
 use crate::dom::texttracklist::TextTrackList;
 use crate::dom::timeranges::{TimeRanges, TimeRangesContainer};
 use crate::dom::trackevent::TrackEvent;
@@ -59,6 +67,8 @@ use crate::dom::url::URL;
 use crate::dom::videotrack::VideoTrack;
 use crate::dom::videotracklist::VideoTrackList;
 use crate::dom::virtualmethods::VirtualMethods;
+use crate::dom::vttcue::{VTTCue, LINE_HEIGHT};
+use crate::dom::vttregion::VTTRegion;
 use crate::fetch::{create_a_potential_cors_request, FetchCanceller};
 use crate::microtask::{Microtask, MicrotaskRunnable};
 use crate::network_listener::{self, NetworkListener, PreInvoke, ResourceTimingListener};
@@ -72,16 +82,17 @@ use euclid::default::Size2D;
 use headers::{ContentLength, ContentRange, HeaderMapExt};
 use html5ever::{LocalName, Prefix};
 use http::header::{self, HeaderMap, HeaderValue};
-use ipc_channel::ipc;
+use ipc_channel::ipc::{self, IpcSender};
 use ipc_channel::router::ROUTER;
 use media::{glplayer_channel, GLPlayerMsg, GLPlayerMsgForward, WindowGLContext};
+use msg::constellation_msg::PipelineId;
 use net_traits::image::base::Image;
 use net_traits::image_cache::ImageResponse;
 use net_traits::request::Destination;
 use net_traits::{CoreResourceMsg, FetchChannels, FetchMetadata, FetchResponseListener, Metadata};
 use net_traits::{NetworkError, ResourceFetchTiming, ResourceTimingType};
 use script_layout_interface::HTMLMediaData;
-use script_traits::{ImageUpdate, WebrenderIpcSender};
+use script_traits::{AudioOutputMsg, AudioOutputSourceId, ImageUpdate, WebrenderIpcSender};
 use servo_config::pref;
 use servo_media::player::audio::AudioRenderer;
 use servo_media::player::video::{VideoFrame, VideoFrameRenderer};
@@ -89,11 +100,14 @@ use servo_media::player::{PlaybackState, Player, PlayerError, PlayerEvent, SeekL
 use servo_media::{ClientContextId, ServoMedia, SupportsMediaType};
 use servo_url::ServoUrl;
 use std::cell::Cell;
+use std::cmp::Ordering;
 use std::collections::VecDeque;
 use std::f64;
 use std::mem;
 use std::rc::Rc;
+use std::sync::atomic::{self, AtomicU64};
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 use time::{self, Duration, Timespec};
 use webrender_api::ImageKey;
 use webrender_api::{ExternalImageData, ExternalImageId, ExternalImageType, TextureTarget};
@@ -277,6 +291,160 @@ impl VideoFrameRenderer for MediaFrameRenderer {
     }
 }
 
+/// The sample rates a media player's audio output is snapped to when it has
+/// to be worked out from the rate at which samples arrive.
+const COMMON_SAMPLE_RATES: [f32; 10] = [
+    8000., 11025., 16000., 22050., 24000., 32000., 44100., 48000., 88200., 96000.,
+];
+
+/// How long samples are counted for at least to work out the sample rate of a
+/// media player's audio output.
+const SAMPLE_RATE_ESTIMATION_MS: u128 = 500;
+
+/// How long samples are counted for at most, after which the common sample
+/// rate closest to the measured one is used even if it is not that close.
+const MAX_SAMPLE_RATE_ESTIMATION_MS: u128 = 2000;
+
+/// How far, relatively, the measured sample rate may be from a common one to
+/// be snapped to it.
+const SAMPLE_RATE_TOLERANCE: f32 = 0.05;
+
+/// Gaps between samples longer than this, such as the ones of pauses and
+/// seeks, aren't counted when working out the sample rate.
+const MAX_SAMPLE_GAP_MS: u128 = 100;
+
+/// The samples counted to work out the sample rate of a media player's audio
+/// output.
+struct SampleRateEstimation {
+    /// When the last samples of the first channel arrived.
+    last: Instant,
+    /// The time during which samples kept arriving, in seconds.
+    elapsed: f32,
+    /// The samples of the first channel that arrived during that time.
+    count: usize,
+}
+
+/// Sends the audio output of a media player or an audio context to the audio
+/// output mixer, while the embedder captures the audio output of the browser.
+pub(crate) struct MediaAudioOutputRenderer {
+    source: AudioOutputSourceId,
+    mixer: IpcSender<AudioOutputMsg>,
+    /// The sample rate of the samples, once known.
+    sample_rate: Option<f32>,
+    /// The samples counted so far, while the sample rate is not known.
+    estimation: Option<SampleRateEstimation>,
+    /// The samples received while the sample rate is not known.
+    pending: Vec<(u32, Vec<f32>)>,
+}
+
+impl MediaAudioOutputRenderer {
+    /// Without a `sample_rate`, it is worked out from the rate at which the
+    /// samples arrive.
+    ///
+    /// FIXME: Media players negotiate the sample rate of their output, but
+    /// servo-media's `AudioRenderer` is only given the samples, so the rate of
+    /// the output of a media player has to be measured until servo-media
+    /// passes it along.
+    pub(crate) fn new(
+        pipeline_id: PipelineId,
+        mixer: IpcSender<AudioOutputMsg>,
+        sample_rate: Option<f32>,
+    ) -> Self {
+        static NEXT_SOURCE_ID: AtomicU64 = AtomicU64::new(0);
+        let source_id = NEXT_SOURCE_ID.fetch_add(1, atomic::Ordering::Relaxed);
+        Self {
+            source: AudioOutputSourceId(pipeline_id, source_id),
+            mixer,
+            sample_rate,
+            estimation: None,
+            pending: vec![],
+        }
+    }
+
+    fn estimate_sample_rate(&mut self, channel: u32, len: usize) -> Option<f32> {
+        if channel != 0 {
+            return None;
+        }
+        let now = Instant::now();
+        let estimation = match self.estimation {
+            // The first samples may be delivered ahead of time, so only the
+            // samples arriving after them are counted.
+            None => {
+                self.estimation = Some(SampleRateEstimation {
+                    last: now,
+                    elapsed: 0.,
+                    count: 0,
+                });
+                return None;
+            },
+            Some(ref mut estimation) => estimation,
+        };
+        let gap = now.duration_since(estimation.last);
+        estimation.last = now;
+        if gap.as_millis() > MAX_SAMPLE_GAP_MS {
+            // Playback stalled, the samples may have been produced any time
+            // during the gap.
+            return None;
+        }
+        estimation.elapsed += gap.as_secs_f32();
+        estimation.count += len;
+
+        let elapsed_ms = (estimation.elapsed * 1000.) as u128;
+        if elapsed_ms < SAMPLE_RATE_ESTIMATION_MS {
+            return None;
+        }
+        let measured = estimation.count as f32 / estimation.elapsed;
+        let closest = COMMON_SAMPLE_RATES.iter().cloned().min_by(|a, b| {
+            (a - measured)
+                .abs()
+                .partial_cmp(&(b - measured).abs())
+                .unwrap_or(Ordering::Equal)
+        })?;
+        if (closest - measured).abs() > closest * SAMPLE_RATE_TOLERANCE &&
+            elapsed_ms < MAX_SAMPLE_RATE_ESTIMATION_MS
+        {
+            return None;
+        }
+        Some(closest)
+    }
+}
+
+impl AudioRenderer for MediaAudioOutputRenderer {
+    fn render(&mut self, sample: Box<dyn AsRef<[f32]>>, channel: u32) {
+        let samples = (*sample).as_ref().to_vec();
+        if self.sample_rate.is_none() {
+            self.sample_rate = self.estimate_sample_rate(channel, samples.len());
+            match self.sample_rate {
+                Some(sample_rate) => {
+                    for (channel, samples) in self.pending.drain(..) {
+                        let _ = self.mixer.send(AudioOutputMsg::Samples(
+                            self.source,
+                            sample_rate,
+                            channel,
+                            samples,
+                        ));
+                    }
+                },
+                None => return self.pending.push((channel, samples)),
+            }
+        }
+        if let Some(sample_rate) = self.sample_rate {
+            let _ = self.mixer.send(AudioOutputMsg::Samples(
+                self.source,
+                sample_rate,
+                channel,
+                samples,
+            ));
+        }
+    }
+}
+
+impl Drop for MediaAudioOutputRenderer {
+    fn drop(&mut self) {
+        let _ = self.mixer.send(AudioOutputMsg::RemoveSource(self.source));
+    }
+}
+
 #[unrooted_must_root_lint::must_root]
 #[derive(JSTraceable, MallocSizeOf)]
 enum SrcObject {
@@ -357,6 +525,12 @@ pub struct HTMLMediaElement {
     /// URL of the media resource, if the resource is set through the src_object attribute and it
     /// is a blob.
     blob_url: DomRefCell<Option<ServoUrl>>,
+    /// The media source attached to this element, if the media resource is one.
+    /// <https://w3c.github.io/media-source/#mediasource-attach>
+    media_source: MutNullableDom<MediaSource>,
+    /// The position of the last seek, from which the data of a media source is
+    /// pushed again when the player asks for it.
+    media_source_seek_position: Cell<f64>,
     /// https://html.spec.whatwg.org/multipage/#dom-media-played
     #[ignore_malloc_size_of = "Rc"]
     played: DomRefCell<TimeRangesContainer>,
@@ -366,6 +540,13 @@ pub struct HTMLMediaElement {
     video_tracks_list: MutNullableDom<VideoTrackList>,
     /// https://html.spec.whatwg.org/multipage/#dom-media-texttracks
     text_tracks_list: MutNullableDom<TextTrackList>,
+    /// The current playback position the last time the text track cues were
+    /// updated during normal playback, from which missed cues are found.
+    /// <https://html.spec.whatwg.org/multipage/#time-marches-on>
+    last_text_track_cues_update: Cell<Option<f64>>,
+    /// The box over the video viewport holding the rendered text track cues.
+    /// <https://w3c.github.io/webvtt/#rules-for-updating-the-display-of-webvtt-text-tracks>
+    text_track_display: MutNullableDom<HTMLDivElement>,
     /// Time of last timeupdate notification.
     #[ignore_malloc_size_of = "Defined in time"]
     next_timeupdate_event: Cell<Timespec>,
@@ -436,10 +617,14 @@ impl HTMLMediaElement {
             seeking: Cell::new(false),
             resource_url: DomRefCell::new(None),
             blob_url: DomRefCell::new(None),
+            media_source: Default::default(),
+            media_source_seek_position: Cell::new(0.),
             played: DomRefCell::new(TimeRangesContainer::new()),
             audio_tracks_list: Default::default(),
             video_tracks_list: Default::default(),
             text_tracks_list: Default::default(),
+            last_text_track_cues_update: Cell::new(None),
+            text_track_display: Default::default(),
             next_timeupdate_event: Cell::new(time::get_time() + Duration::milliseconds(250)),
             current_fetch_context: DomRefCell::new(None),
             id: Cell::new(0),
@@ -489,6 +674,11 @@ impl HTMLMediaElement {
 
     /// https://html.spec.whatwg.org/multipage/#time-marches-on
     fn time_marches_on(&self) {
+        // Steps 1 to 5 and 7 to 15.
+        if self.update_text_track_cues() {
+            self.update_text_track_rendering();
+        }
+
         // Step 6.
         if time::get_time() > self.next_timeupdate_event.get() {
             let window = window_from_node(self);
@@ -501,6 +691,327 @@ impl HTMLMediaElement {
         }
     }
 
+    /// Called when text tracks are added to or removed from this element, or
+    /// when the mode or the cues of one of them change.
+    pub fn text_tracks_changed(&self) {
+        self.update_text_track_cues();
+        self.update_text_track_rendering();
+    }
+
+    /// Update the active flags of the text track cues and fire their events.
+    /// Returns whether the active cues changed.
+    ///
+    /// <https://html.spec.whatwg.org/multipage/#time-marches-on>
+    fn update_text_track_cues(&self) -> bool {
+        let tracks = match self.text_tracks_list.get() {
+            Some(tracks) => tracks,
+            None => return false,
+        };
+        let current_position = self.playback_position.get();
+        let normal_playback = !self.Paused() && !self.seeking.get();
+
+        // Steps 3 and 4.
+        let last_position = self
+            .last_text_track_cues_update
+            .replace(Some(current_position))
+            .filter(|last_position| normal_playback && *last_position <= current_position);
+
+        // Steps 1, 2 and 4, iterating over the cues in text track cue order.
+        let mut current_cues = vec![];
+        let mut other_cues = vec![];
+        let mut missed_cues = vec![];
+        let mut active_cues = vec![];
+        for index in 0..tracks.len() {
+            let track = tracks.item(index).unwrap();
+            if track.Mode() == TextTrackMode::Disabled {
+                track.set_active_cues(&[]);
+                continue;
+            }
+            active_cues.extend(track.active_cues());
+            for cue in track.get_cues().cues() {
+                let (start_time, end_time) = (cue.start_time(), cue.end_time());
+                let order = current_cues.len() + other_cues.len();
+                if start_time <= current_position && end_time > current_position {
+                    current_cues.push((order, track.clone(), cue));
+                    continue;
+                }
+                if let Some(last_position) = last_position {
+                    if start_time >= last_position && end_time <= current_position {
+                        missed_cues.push(order);
+                    }
+                }
+                other_cues.push((order, track.clone(), cue));
+            }
+        }
+        let is_active = |cue: &DomRoot<TextTrackCue>| active_cues.contains(cue);
+
+        // Step 7.
+        if current_cues.iter().all(|(_, _, cue)| is_active(cue)) &&
+            !other_cues.iter().any(|(_, _, cue)| is_active(cue)) &&
+            missed_cues.is_empty()
+        {
+            return false;
+        }
+
+        // Step 8.
+        if normal_playback &&
+            other_cues.iter().any(|(order, _, cue)| {
+                cue.pause_on_exit() && (is_active(cue) || missed_cues.contains(order))
+            })
+        {
+            self.internal_pause_steps();
+        }
+
+        // Step 9.
+        let mut events = vec![];
+        for (order, track, cue) in &other_cues {
+            let missed = missed_cues.contains(order);
+            if missed {
+                events.push((cue.start_time(), *order, atom!("enter"), track, cue));
+            }
+            if missed || is_active(cue) {
+                let time = cue.start_time().max(cue.end_time());
+                events.push((time, *order, atom!("exit"), track, cue));
+            }
+        }
+        for (order, track, cue) in &current_cues {
+            if !is_active(cue) {
+                events.push((cue.start_time(), *order, atom!("enter"), track, cue));
+            }
+        }
+
+        // Step 10. The enter event of a cue is prepared before its exit
+        // event, and the sort is stable.
+        events.sort_by(|a, b| {
+            a.0.partial_cmp(&b.0)
+                .unwrap_or(Ordering::Equal)
+                .then(a.1.cmp(&b.1))
+        });
+
+        // Step 11.
+        let window = window_from_node(self);
+        let task_source = window.task_manager().media_element_task_source();
+        let mut affected_tracks: Vec<&DomRoot<TextTrack>> = vec![];
+        for (_, _, name, track, cue) in &events {
+            task_source.queue_simple_event(cue.upcast(), name.clone(), &window);
+            if !affected_tracks.contains(track) {
+                affected_tracks.push(*track);
+            }
+        }
+
+        // Steps 12 and 13.
+        for index in 0..tracks.len() {
+            let track = tracks.item(index).unwrap();
+            if !affected_tracks.contains(&&track) {
+                continue;
+            }
+            task_source.queue_simple_event(track.upcast(), atom!("cuechange"), &window);
+            if let Some(track_element) = track.track_element() {
+                task_source.queue_simple_event(track_element.upcast(), atom!("cuechange"), &window);
+            }
+        }
+
+        // Step 14.
+        for index in 0..tracks.len() {
+            let track = tracks.item(index).unwrap();
+            if track.Mode() == TextTrackMode::Disabled {
+                continue;
+            }
+            let track_cues: Vec<&TextTrackCue> = current_cues
+                .iter()
+                .filter(|(_, cue_track, _)| *cue_track == track)
+                .map(|(_, _, cue)| &**cue)
+                .collect();
+            track.set_active_cues(&track_cues);
+        }
+
+        true
+    }
+
+    /// <https://w3c.github.io/webvtt/#rules-for-updating-the-display-of-webvtt-text-tracks>
+    fn update_text_track_rendering(&self) {
+        if !self.is::<HTMLVideoElement>() {
+            return;
+        }
+
+        // Steps 3 and 4. The cues of showing subtitles and captions tracks,
+        // with the number of showing tracks before their own.
+        let mut cues = vec![];
+        let mut showing_tracks = 0;
+        if let Some(tracks) = self.text_tracks_list.get() {
+            for index in 0..tracks.len() {
+                let track = tracks.item(index).unwrap();
+                if track.Mode() != TextTrackMode::Showing {
+                    continue;
+                }
+                match track.Kind() {
+                    TextTrackKind::Subtitles | TextTrackKind::Captions => {
+                        for cue in track.active_cues() {
+                            if let Some(cue) = cue.downcast::<VTTCue>() {
+                                cues.push((showing_tracks, DomRoot::from_ref(cue)));
+                            }
+                        }
+                    },
+                    _ => {},
+                }
+                showing_tracks += 1;
+            }
+        }
+
+        let content_box = if cues.is_empty() {
+            None
+        } else {
+            self.upcast::<Node>().bounding_content_box()
+        };
+        let content_box = match content_box {
+            Some(content_box) => content_box,
+            None => {
+                if let Some(display) = self.text_track_display.get() {
+                    display.upcast::<Node>().SetTextContent(None);
+                }
+                return;
+            },
+        };
+
+        // Step 1. The display is absolutely positioned at the static position
+        // it would have right after the video, and moved back over it.
+        let display = self.text_track_display();
+        let display_node = display.upcast::<Node>();
+        display_node.SetTextContent(None);
+        let (width, height) = (
+            content_box.size.width.to_f32_px(),
+            content_box.size.height.to_f32_px(),
+        );
+        display.upcast::<Element>().set_string_attribute(
+            &local_name!("style"),
+            DOMString::from(format!(
+                "position: absolute; display: inline; vertical-align: top; \
+                 margin-left: -{}px; width: {}px; height: {}px; overflow: hidden; \
+                 font-size: {}px; line-height: {}; pointer-events: none;",
+                width,
+                width,
+                height,
+                height * 0.05,
+                LINE_HEIGHT,
+            )),
+        );
+
+        // Step 5.
+        let document = document_from_node(self);
+        let mut stacked_cues: Vec<(DirectionSetting, Vec<DomRoot<Element>>)> = vec![];
+        let mut region_cues: Vec<(DomRoot<VTTRegion>, Vec<DomRoot<Element>>)> = vec![];
+        for (showing_tracks_before, cue) in cues {
+            if let Some(region) = cue.rendered_region() {
+                let cue_box = cue.create_cue_box(&document, None);
+                match region_cues.iter_mut().find(|(r, _)| *r == region) {
+                    Some((_, boxes)) => boxes.push(cue_box),
+                    None => region_cues.push((region, vec![cue_box])),
+                }
+            } else if cue.has_automatic_line() {
+                let cue_box = cue.create_cue_box(&document, None);
+                let direction = cue.Vertical();
+                match stacked_cues.iter_mut().find(|(d, _)| *d == direction) {
+                    Some((_, boxes)) => boxes.push(cue_box),
+                    None => stacked_cues.push((direction, vec![cue_box])),
+                }
+            } else {
+                let line = cue.computed_line(showing_tracks_before);
+                let cue_box = cue.create_cue_box(&document, Some(line));
+                display_node.AppendChild(cue_box.upcast()).unwrap();
+            }
+        }
+
+        // Cues with an automatic line are stacked from the end of the video
+        // viewport, the first one being the closest to it, which avoids
+        // overlapping boxes.
+        for (direction, boxes) in stacked_cues {
+            let style = match direction {
+                DirectionSetting::_empty => "position: absolute; left: 0; bottom: 0; width: 100%;",
+                DirectionSetting::Rl => {
+                    "position: absolute; top: 0; left: 0; height: 100%; writing-mode: vertical-rl;"
+                },
+                DirectionSetting::Lr => {
+                    "position: absolute; top: 0; right: 0; height: 100%; writing-mode: vertical-lr;"
+                },
+            };
+            let stack = HTMLDivElement::new(local_name!("div"), None, &document);
+            stack
+                .upcast::<Element>()
+                .set_string_attribute(&local_name!("style"), DOMString::from(style));
+            for cue_box in boxes.iter().rev() {
+                stack
+                    .upcast::<Node>()
+                    .AppendChild(cue_box.upcast())
+                    .unwrap();
+            }
+            display_node.AppendChild(stack.upcast()).unwrap();
+        }
+
+        // https://w3c.github.io/webvtt/#webvtt-region
+        for (region, boxes) in region_cues {
+            let width = *region.Width();
+            let region_height = region.Lines() as f64 * LINE_HEIGHT;
+            let region_box = HTMLDivElement::new(local_name!("div"), None, &document);
+            region_box.upcast::<Element>().set_string_attribute(
+                &local_name!("style"),
+                DOMString::from(format!(
+                    "position: absolute; overflow: hidden; width: {}%; height: {}em; \
+                     left: {}%; top: calc({}% - {}em);",
+                    width,
+                    region_height,
+                    *region.ViewportAnchorX() - *region.RegionAnchorX() * width / 100.,
+                    *region.ViewportAnchorY(),
+                    *region.RegionAnchorY() * region_height / 100.,
+                )),
+            );
+            // The most recent cues are at the bottom of the region, and the
+            // older ones overflow at its top.
+            let lines = HTMLDivElement::new(local_name!("div"), None, &document);
+            lines.upcast::<Element>().set_string_attribute(
+                &local_name!("style"),
+                DOMString::from("position: absolute; left: 0; bottom: 0; width: 100%;"),
+            );
+            for cue_box in boxes {
+                lines
+                    .upcast::<Node>()
+                    .AppendChild(cue_box.upcast())
+                    .unwrap();
+            }
+            region_box
+                .upcast::<Node>()
+                .AppendChild(lines.upcast())
+                .unwrap();
+            display_node.AppendChild(region_box.upcast()).unwrap();
+        }
+    }
+
+    /// The text track display, created in the user agent shadow root before
+    /// the media controls if needed.
+    fn text_track_display(&self) -> DomRoot<HTMLDivElement> {
+        if let Some(display) = self.text_track_display.get() {
+            return display;
+        }
+        let display = HTMLDivElement::new(local_name!("div"), None, &document_from_node(self));
+        let shadow_root = self.user_agent_shadow_root();
+        let shadow_root = shadow_root.upcast::<Node>();
+        shadow_root
+            .InsertBefore(display.upcast(), shadow_root.GetFirstChild().as_deref())
+            .unwrap();
+        self.text_track_display.set(Some(&display));
+        display
+    }
+
+    /// The shadow root holding the media controls and the text track
+    /// display, attached the first time one of them is rendered.
+    fn user_agent_shadow_root(&self) -> DomRoot<ShadowRoot> {
+        let element = self.upcast::<Element>();
+        element.shadow_root().unwrap_or_else(|| {
+            element
+                .attach_shadow(IsUserAgentWidget::Yes, ShadowRootMode::Closed)
+                .unwrap()
+        })
+    }
+
     /// <https://html.spec.whatwg.org/multipage/#internal-pause-steps>
     fn internal_pause_steps(&self) {
         // Step 1.
@@ -914,6 +1425,17 @@ impl HTMLMediaElement {
         // Step 4.
         match resource {
             Resource::Url(url) => {
+                // https://w3c.github.io/media-source/#mediasource-attach
+                if let Some(media_source) = self.global().media_source_for_url(&url) {
+                    if !media_source.attach(self) {
+                        self.queue_dedicated_media_source_failure_steps();
+                        return;
+                    }
+                    *self.current_fetch_context.borrow_mut() = None;
+                    self.media_source.set(Some(&media_source));
+                    return;
+                }
+
                 // Step 4.remote.1.
                 if self.Preload() == "none" && !self.autoplaying.get() {
                     // Step 4.remote.1.1.
@@ -1110,7 +1632,9 @@ impl HTMLMediaElement {
             }
 
             // Step 6.3.
-            // FIXME(nox): Detach MediaSource media provider object.
+            if let Some(media_source) = self.media_source.take() {
+                media_source.detach();
+            }
 
             // Step 6.4.
             self.AudioTracks().clear();
@@ -1246,6 +1770,7 @@ impl HTMLMediaElement {
         // Step 4.
         // The flag will be cleared when the media engine tells us the seek was done.
         self.seeking.set(true);
+        self.last_text_track_cues_update.set(None);
 
         // Step 5.
         // XXX(ferjm) The rest of the steps should be run in parallel, so seeking cancelation
@@ -1277,9 +1802,14 @@ impl HTMLMediaElement {
         task_source.queue_simple_event(self.upcast(), atom!("seeking"), &window);
 
         // Step 11.
+        self.media_source_seek_position.set(time);
         if let Some(ref player) = *self.player.borrow() {
             if let Err(e) = player.lock().unwrap().seek(time) {
                 eprintln!("Seek error {:?}", e);
+            } else if let Some(media_source) = self.media_source.get() {
+                // The player drops the data it was pushed, and asks for the
+                // data at the seek position with a `SeekData` event.
+                media_source.flush();
             }
         }
 
@@ -1351,9 +1881,26 @@ impl HTMLMediaElement {
             HTMLMediaElementTypeId::HTMLVideoElement => Some(self.video_renderer.clone()),
         };
 
-        let audio_renderer = self.audio_renderer.borrow().as_ref().map(|r| r.clone());
-
         let pipeline_id = window.pipeline_id();
+
+        // While the embedder captures the audio output of the browser, audio
+        // which isn't routed to an audio context is sent to the mixer instead
+        // of being played.
+        let audio_renderer = self
+            .audio_renderer
+            .borrow()
+            .as_ref()
+            .map(|r| r.clone())
+            .or_else(|| {
+                window.audio_output_capture().map(|mixer| {
+                    Arc::new(Mutex::new(MediaAudioOutputRenderer::new(
+                        pipeline_id,
+                        mixer,
+                        None,
+                    ))) as Arc<Mutex<dyn AudioRenderer>>
+                })
+            });
+
         let client_context_id =
             ClientContextId::build(pipeline_id.namespace_id.0, pipeline_id.index.0.get());
         let player = ServoMedia::get().unwrap().create_player(
@@ -1551,6 +2098,9 @@ impl HTMLMediaElement {
             },
             PlayerEvent::VideoFrameUpdated => {
                 self.upcast::<Node>().dirty(NodeDamage::OtherNodeDamage);
+                if let Some(video) = self.downcast::<HTMLVideoElement>() {
+                    video.update_picture_in_picture_frame();
+                }
             },
             PlayerEvent::MetadataUpdated(ref metadata) => {
                 // https://html.spec.whatwg.org/multipage/#media-data-processing-steps-list
@@ -1675,16 +2225,24 @@ impl HTMLMediaElement {
                 self.playback_position.set(0.);
 
                 // Step 4.
-                let previous_duration = self.duration.get();
-                if let Some(duration) = metadata.duration {
-                    self.duration.set(duration.as_secs() as f64);
-                } else {
-                    self.duration.set(f64::INFINITY);
-                }
-                if previous_duration != self.duration.get() {
-                    let window = window_from_node(self);
-                    let task_source = window.task_manager().media_element_task_source();
-                    task_source.queue_simple_event(self.upcast(), atom!("durationchange"), &window);
+                // The duration of a media source is set through its source
+                // buffers instead.
+                if self.media_source.get().is_none() {
+                    let previous_duration = self.duration.get();
+                    if let Some(duration) = metadata.duration {
+                        self.duration.set(duration.as_secs() as f64);
+                    } else {
+                        self.duration.set(f64::INFINITY);
+                    }
+                    if previous_duration != self.duration.get() {
+                        let window = window_from_node(self);
+                        let task_source = window.task_manager().media_element_task_source();
+                        task_source.queue_simple_event(
+                            self.upcast(),
+                            atom!("durationchange"),
+                            &window,
+                        );
+                    }
                 }
 
                 // Step 5.
@@ -1803,6 +2361,13 @@ impl HTMLMediaElement {
                 ));
             },
             PlayerEvent::SeekData(p, ref seek_lock) => {
+                // The data of a media source isn't addressed by byte offset,
+                // so it is pushed again from the seek position instead.
+                if let Some(media_source) = self.media_source.get() {
+                    media_source.push_from(self.media_source_seek_position.get());
+                    seek_lock.clone().unlock(/* successful seek */ true);
+                    return;
+                }
                 self.fetch_request(Some(p), Some(seek_lock.clone()));
             },
             PlayerEvent::SeekDone(_) => {
@@ -1857,12 +2422,18 @@ impl HTMLMediaElement {
 
     fn render_controls(&self) {
         let element = self.htmlelement.upcast::<Element>();
-        if self.ready_state.get() < ReadyState::HaveMetadata || element.is_shadow_host() {
+        // The controls are rendered if the shadow root holds anything besides
+        // the text track display.
+        let has_controls = element.shadow_root().map_or(false, |shadow_root| {
+            shadow_root.upcast::<Node>().children_count() >
+                self.text_track_display.get().map_or(0, |_| 1)
+        });
+        if self.ready_state.get() < ReadyState::HaveMetadata || has_controls {
             // Bail out if we have no metadata yet or
             // if we are already showing the controls.
             return;
         }
-        let shadow_root = element.attach_shadow(IsUserAgentWidget::Yes).unwrap();
+        let shadow_root = self.user_agent_shadow_root();
         let document = document_from_node(self);
         let script = HTMLScriptElement::new(
             local_name!("script"),
@@ -1924,6 +2495,12 @@ impl HTMLMediaElement {
         }
     }
 
+    /// The WebRender image the video frames are currently rendered to, along
+    /// with the size of the frames.
+    pub fn get_current_frame_image(&self) -> Option<(ImageKey, i32, i32)> {
+        self.video_renderer.lock().unwrap().current_frame
+    }
+
     /// By default the audio is rendered through the audio sink automatically
     /// selected by the servo-media Player instance. However, in some cases, like
     /// the WebAudio MediaElementAudioSourceNode, we need to set a custom audio
@@ -1951,6 +2528,61 @@ impl HTMLMediaElement {
         self.duration.set(duration);
     }
 
+    /// https://html.spec.whatwg.org/multipage/#durationChange
+    pub fn change_duration(&self, duration: f64) {
+        let previous_duration = self.duration.get();
+        if previous_duration == duration || (previous_duration.is_nan() && duration.is_nan()) {
+            return;
+        }
+        self.duration.set(duration);
+
+        let window = window_from_node(self);
+        let task_source = window.task_manager().media_element_task_source();
+        task_source.queue_simple_event(self.upcast(), atom!("durationchange"), &window);
+
+        if self.playback_position.get() > duration {
+            self.seek(duration, /* approximate_for_speed */ false);
+        }
+    }
+
+    /// Push the data of a segment appended to the attached media source to
+    /// the player.
+    pub fn push_media_source_data(&self, data: Vec<u8>) {
+        if let Some(ref player) = *self.player.borrow() {
+            // The data stays buffered in the source buffer, so it can be
+            // pushed again after a seek if the player drops it.
+            if let Err(e) = player.lock().unwrap().push_data(data) {
+                warn!("Could not push media source data to player {:?}", e);
+            }
+        }
+    }
+
+    /// Signal the player that the attached media source has no more data.
+    pub fn end_media_source_stream(&self) {
+        if let Some(ref player) = *self.player.borrow() {
+            if let Err(e) = player.lock().unwrap().end_of_stream() {
+                warn!("Could not signal EOS to player {:?}", e);
+            }
+        }
+    }
+
+    /// Steps 3.network and 3.decode of the end of stream algorithm, run when
+    /// the attached media source ends with an error.
+    /// <https://w3c.github.io/media-source/#end-of-stream-algorithm>
+    pub fn media_source_failed(&self, code: u16) {
+        if self.ready_state.get() == ReadyState::HaveNothing {
+            self.queue_dedicated_media_source_failure_steps();
+            return;
+        }
+
+        // https://html.spec.whatwg.org/multipage/#media-data-processing-steps-list
+        self.error
+            .set(Some(&*MediaError::new(&*window_from_node(self), code)));
+        self.network_state.set(NetworkState::Idle);
+        self.delay_load_event(false);
+        self.upcast::<EventTarget>().fire_event(atom!("error"));
+    }
+
     pub fn reset(&self) {
         if let Some(ref player) = *self.player.borrow() {
             if let Err(e) = player.lock().unwrap().stop() {
@@ -2324,6 +2956,11 @@ impl HTMLMediaElementMethods for HTMLMediaElement {
 
     // https://html.spec.whatwg.org/multipage/#dom-media-buffered
     fn Buffered(&self) -> DomRoot<TimeRanges> {
+        // https://w3c.github.io/media-source/#htmlmediaelement-extensions-buffered
+        if let Some(media_source) = self.media_source.get() {
+            return TimeRanges::new(self.global().as_window(), media_source.buffered());
+        }
+
         let mut buffered = TimeRangesContainer::new();
         if let Some(ref player) = *self.player.borrow() {
             if let Ok(ranges) = player.lock().unwrap().buffered() {
@@ -2353,7 +2990,7 @@ impl HTMLMediaElementMethods for HTMLMediaElement {
     fn TextTracks(&self) -> DomRoot<TextTrackList> {
         let window = window_from_node(self);
         self.text_tracks_list
-            .or_init(|| TextTrackList::new(&window, &[]))
+            .or_init(|| TextTrackList::new(&window, &[], Some(self)))
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-media-addtexttrack
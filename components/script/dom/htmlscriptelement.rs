@@ -31,6 +31,7 @@ use crate::network_listener::{self, NetworkListener, PreInvoke, ResourceTimingLi
 use crate::realms::enter_realm;
 use crate::script_module::fetch_inline_module_script;
 use crate::script_module::{fetch_external_module_script, ModuleOwner, ScriptFetchOptions};
+use crate::script_module::{parse_an_import_map_string, register_import_map};
 use crate::task::TaskCanceller;
 use crate::task_source::dom_manipulation::DOMManipulationTaskSource;
 use crate::task_source::TaskSource;
@@ -236,6 +237,7 @@ pub static SCRIPT_JS_MIMES: StaticStringVec = &[
 pub enum ScriptType {
     Classic,
     Module,
+    ImportMap,
 }
 
 #[derive(JSTraceable, MallocSizeOf)]
@@ -681,7 +683,7 @@ impl HTMLScriptElement {
 
         // Step 19.
         let module_credentials_mode = match script_type {
-            ScriptType::Classic => CredentialsMode::CredentialsSameOrigin,
+            ScriptType::Classic | ScriptType::ImportMap => CredentialsMode::CredentialsSameOrigin,
             ScriptType::Module => reflect_cross_origin_attribute(element).map_or(
                 CredentialsMode::CredentialsSameOrigin,
                 |attr| match &*attr {
@@ -782,6 +784,11 @@ impl HTMLScriptElement {
                         ExternalScriptKind::Asap => doc.add_asap_script(self),
                     }
                 },
+                ScriptType::ImportMap => {
+                    // Import maps can't be fetched from an external file.
+                    self.queue_error_event();
+                    return;
+                },
                 ScriptType::Module => {
                     fetch_external_module_script(
                         ModuleOwner::Window(Trusted::new(self)),
@@ -828,6 +835,16 @@ impl HTMLScriptElement {
                         self.execute(result);
                     }
                 },
+                ScriptType::ImportMap => {
+                    let window = window_from_node(self);
+                    if !window.import_maps_allowed() {
+                        self.queue_error_event();
+                        return;
+                    }
+                    window.set_import_maps_allowed(false);
+
+                    self.execute(result);
+                },
                 ScriptType::Module => {
                     // We should add inline module script elements
                     // into those vectors in case that there's no
@@ -1014,6 +1031,7 @@ impl HTMLScriptElement {
         match script.type_ {
             ScriptType::Classic => document.set_current_script(Some(self)),
             ScriptType::Module => document.set_current_script(None),
+            ScriptType::ImportMap => (),
         }
 
         match script.type_ {
@@ -1025,6 +1043,10 @@ impl HTMLScriptElement {
                 assert!(document.GetCurrentScript().is_none());
                 self.run_a_module_script(&script, false);
             },
+            ScriptType::ImportMap => {
+                let result = parse_an_import_map_string(&script.text(), &script.url);
+                register_import_map(&self.global(), result);
+            },
         }
 
         // Step 5.
@@ -1184,6 +1206,10 @@ impl HTMLScriptElement {
                     return Some(ScriptType::Module);
                 }
 
+                if &***ty == String::from("importmap") {
+                    return Some(ScriptType::ImportMap);
+                }
+
                 if SCRIPT_JS_MIMES
                     .contains(&ty.to_ascii_lowercase().trim_matches(HTML_SPACE_CHARACTERS))
                 {
@@ -0,0 +1,251 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::attr::Attr;
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::HTMLSlotElementBinding::{
+    AssignedNodesOptions, HTMLSlotElementMethods,
+};
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::root::{Dom, DomRoot, LayoutDom};
+use crate::dom::bindings::str::DOMString;
+use crate::dom::document::Document;
+use crate::dom::element::{AttributeMutation, Element};
+use crate::dom::htmlelement::HTMLElement;
+use crate::dom::mutationobserver::MutationObserver;
+use crate::dom::node::{Node, NodeDamage, ShadowIncluding};
+use crate::dom::shadowroot::ShadowRoot;
+use crate::dom::virtualmethods::VirtualMethods;
+use crate::script_thread::ScriptThread;
+use dom_struct::dom_struct;
+use html5ever::{LocalName, Prefix};
+
+#[dom_struct]
+pub struct HTMLSlotElement {
+    htmlelement: HTMLElement,
+    /// <https://dom.spec.whatwg.org/#slot-assigned-nodes>
+    assigned_nodes: DomRefCell<Vec<Dom<Node>>>,
+}
+
+impl HTMLSlotElement {
+    fn new_inherited(
+        local_name: LocalName,
+        prefix: Option<Prefix>,
+        document: &Document,
+    ) -> HTMLSlotElement {
+        HTMLSlotElement {
+            htmlelement: HTMLElement::new_inherited(local_name, prefix, document),
+            assigned_nodes: DomRefCell::new(vec![]),
+        }
+    }
+
+    #[allow(unrooted_must_root)]
+    pub fn new(
+        local_name: LocalName,
+        prefix: Option<Prefix>,
+        document: &Document,
+    ) -> DomRoot<HTMLSlotElement> {
+        Node::reflect_node(
+            Box::new(HTMLSlotElement::new_inherited(local_name, prefix, document)),
+            document,
+        )
+    }
+
+    pub fn assigned_nodes(&self) -> Vec<DomRoot<Node>> {
+        self.assigned_nodes
+            .borrow()
+            .iter()
+            .map(|node| DomRoot::from_ref(&**node))
+            .collect()
+    }
+
+    /// Whether the root of this slot is a shadow root.
+    pub fn is_in_shadow_tree_root(&self) -> bool {
+        self.upcast::<Node>()
+            .inclusive_ancestors(ShadowIncluding::No)
+            .last()
+            .map_or(false, |root| root.is::<ShadowRoot>())
+    }
+
+    /// <https://dom.spec.whatwg.org/#find-slotables>
+    fn find_slottables(&self) -> Vec<DomRoot<Node>> {
+        // Step 1.
+        let mut result = vec![];
+        // Step 2.
+        let root = self
+            .upcast::<Node>()
+            .inclusive_ancestors(ShadowIncluding::No)
+            .last()
+            .unwrap();
+        let shadow_root = match root.downcast::<ShadowRoot>() {
+            Some(shadow_root) => shadow_root,
+            None => return result,
+        };
+        // Step 3.
+        let host = shadow_root.Host();
+        // Step 4.
+        for slottable in host
+            .upcast::<Node>()
+            .children()
+            .filter(|child| child.is_slottable())
+        {
+            // Steps 4.1-2.
+            if slottable
+                .find_a_slot(false)
+                .map_or(false, |slot| &*slot == self)
+            {
+                result.push(slottable);
+            }
+        }
+        // Step 5.
+        result
+    }
+
+    /// <https://dom.spec.whatwg.org/#find-flattened-slotables>
+    fn find_flattened_slottables(&self) -> Vec<DomRoot<Node>> {
+        // Step 1.
+        let mut result = vec![];
+        // Step 2.
+        if !self.is_in_shadow_tree_root() {
+            return result;
+        }
+        // Step 3.
+        let mut slottables = self.find_slottables();
+        // Step 4.
+        if slottables.is_empty() {
+            slottables = self
+                .upcast::<Node>()
+                .children()
+                .filter(|child| child.is_slottable())
+                .collect();
+        }
+        // Step 5.
+        for node in slottables {
+            match node.downcast::<HTMLSlotElement>() {
+                // Step 5.1.
+                Some(slot) if slot.is_in_shadow_tree_root() => {
+                    result.extend(slot.find_flattened_slottables());
+                },
+                // Step 5.2.
+                _ => result.push(node),
+            }
+        }
+        // Step 6.
+        result
+    }
+
+    /// <https://dom.spec.whatwg.org/#assign-slotables>
+    pub fn assign_slottables(&self) {
+        // Step 1.
+        let slottables = self.find_slottables();
+        // Step 2.
+        let changed = {
+            let assigned_nodes = self.assigned_nodes.borrow();
+            assigned_nodes.len() != slottables.len() ||
+                assigned_nodes
+                    .iter()
+                    .zip(slottables.iter())
+                    .any(|(assigned, slottable)| **assigned != **slottable)
+        };
+        if !changed {
+            return;
+        }
+        self.signal_a_slot_change();
+
+        // Nodes that are no longer assigned to this slot need to forget about it,
+        // unless they were assigned to another slot in the meantime.
+        for node in self.assigned_nodes() {
+            if node.assigned_slot().map_or(false, |slot| &*slot == self) {
+                node.set_assigned_slot(None);
+            }
+        }
+        // Step 3.
+        *self.assigned_nodes.borrow_mut() = slottables
+            .iter()
+            .map(|slottable| Dom::from_ref(&**slottable))
+            .collect();
+        // Step 4.
+        for (index, slottable) in slottables.iter().enumerate() {
+            slottable.set_assigned_slot(Some((self, index)));
+        }
+
+        // The flat tree changed, so the slot needs to be laid out again.
+        self.upcast::<Node>().dirty(NodeDamage::OtherNodeDamage);
+    }
+
+    /// <https://dom.spec.whatwg.org/#assign-slotables-for-a-tree>
+    pub fn assign_slottables_for_a_tree(root: &Node) {
+        for node in root.traverse_preorder(ShadowIncluding::No) {
+            if let Some(slot) = node.downcast::<HTMLSlotElement>() {
+                slot.assign_slottables();
+            }
+        }
+    }
+
+    /// <https://dom.spec.whatwg.org/#signal-a-slot-change>
+    pub fn signal_a_slot_change(&self) {
+        // Step 1.
+        ScriptThread::add_signal_slot(self);
+        // Step 2.
+        MutationObserver::queue_mutation_observer_microtask();
+    }
+}
+
+impl HTMLSlotElementMethods for HTMLSlotElement {
+    // https://html.spec.whatwg.org/multipage/#dom-slot-name
+    make_getter!(Name, "name");
+
+    // https://html.spec.whatwg.org/multipage/#dom-slot-name
+    make_setter!(SetName, "name");
+
+    // https://html.spec.whatwg.org/multipage/#dom-slot-assignednodes
+    fn AssignedNodes(&self, options: &AssignedNodesOptions) -> Vec<DomRoot<Node>> {
+        if options.flatten {
+            self.find_flattened_slottables()
+        } else {
+            self.assigned_nodes()
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-slot-assignedelements
+    fn AssignedElements(&self, options: &AssignedNodesOptions) -> Vec<DomRoot<Element>> {
+        self.AssignedNodes(options)
+            .into_iter()
+            .filter_map(DomRoot::downcast::<Element>)
+            .collect()
+    }
+}
+
+impl VirtualMethods for HTMLSlotElement {
+    fn super_type(&self) -> Option<&dyn VirtualMethods> {
+        Some(self.upcast::<HTMLElement>() as &dyn VirtualMethods)
+    }
+
+    /// <https://dom.spec.whatwg.org/#slot-name>
+    fn attribute_mutated(&self, attr: &Attr, mutation: AttributeMutation) {
+        self.super_type().unwrap().attribute_mutated(attr, mutation);
+        if attr.local_name() == &local_name!("name") && attr.namespace() == &ns!() {
+            let root = self
+                .upcast::<Node>()
+                .inclusive_ancestors(ShadowIncluding::No)
+                .last()
+                .unwrap();
+            if root.is::<ShadowRoot>() {
+                HTMLSlotElement::assign_slottables_for_a_tree(&root);
+            }
+        }
+    }
+}
+
+pub trait LayoutHTMLSlotElementHelpers<'dom> {
+    fn assigned_nodes_for_layout(self) -> &'dom [LayoutDom<'dom, Node>];
+}
+
+impl<'dom> LayoutHTMLSlotElementHelpers<'dom> for LayoutDom<'dom, HTMLSlotElement> {
+    #[inline]
+    #[allow(unsafe_code)]
+    fn assigned_nodes_for_layout(self) -> &'dom [LayoutDom<'dom, Node>] {
+        unsafe { LayoutDom::to_layout_slice(self.unsafe_get().assigned_nodes.borrow_for_layout()) }
+    }
+}
@@ -2,23 +2,54 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use crate::document_loader::{LoadBlocker, LoadType};
+use crate::dom::attr::Attr;
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::HTMLMediaElementBinding::HTMLMediaElementMethods;
 use crate::dom::bindings::codegen::Bindings::HTMLTrackElementBinding::{
     HTMLTrackElementConstants, HTMLTrackElementMethods,
 };
+use crate::dom::bindings::codegen::Bindings::NodeBinding::NodeMethods;
+use crate::dom::bindings::codegen::Bindings::TextTrackBinding::{
+    TextTrackKind, TextTrackMethods, TextTrackMode,
+};
 use crate::dom::bindings::inheritance::Castable;
-use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::bindings::refcounted::Trusted;
+use crate::dom::bindings::reflector::DomObject;
+use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
 use crate::dom::bindings::str::{DOMString, USVString};
 use crate::dom::document::Document;
-use crate::dom::element::Element;
+use crate::dom::element::{cors_setting_for_element, AttributeMutation, Element};
+use crate::dom::globalscope::GlobalScope;
 use crate::dom::htmlelement::HTMLElement;
-use crate::dom::node::Node;
+use crate::dom::htmlmediaelement::HTMLMediaElement;
+use crate::dom::node::{document_from_node, window_from_node, BindContext, Node, UnbindContext};
+use crate::dom::performanceresourcetiming::InitiatorType;
 use crate::dom::texttrack::TextTrack;
+use crate::dom::texttrackcue::TextTrackCue;
+use crate::dom::virtualmethods::VirtualMethods;
+use crate::dom::vttcue::VTTCue;
+use crate::dom::vttregion::VTTRegion;
+use crate::dom::webvttparser;
+use crate::fetch::create_a_potential_cors_request;
+use crate::network_listener::{self, NetworkListener, PreInvoke, ResourceTimingListener};
+use crate::task_source::TaskSource;
 use dom_struct::dom_struct;
 use html5ever::{LocalName, Prefix};
+use ipc_channel::ipc;
+use ipc_channel::router::ROUTER;
+use net_traits::request::Destination;
+use net_traits::{
+    CoreResourceMsg, FetchChannels, FetchMetadata, FetchResponseListener, NetworkError,
+};
+use net_traits::{ResourceFetchTiming, ResourceTimingType};
+use servo_config::pref;
+use servo_url::ServoUrl;
+use std::cell::Cell;
+use std::sync::{Arc, Mutex};
 
 #[derive(Clone, Copy, JSTraceable, MallocSizeOf, PartialEq)]
 #[repr(u16)]
-#[allow(unused)]
 pub enum ReadyState {
     None = HTMLTrackElementConstants::NONE,
     Loading = HTMLTrackElementConstants::LOADING,
@@ -29,8 +60,17 @@ pub enum ReadyState {
 #[dom_struct]
 pub struct HTMLTrackElement {
     htmlelement: HTMLElement,
-    ready_state: ReadyState,
+    /// <https://html.spec.whatwg.org/multipage/#text-track-readiness-state>
+    ready_state: Cell<ReadyState>,
     track: Dom<TextTrack>,
+    /// The media element this element is a child of, whose list of text
+    /// tracks holds the text track of this element.
+    media_element: MutNullableDom<HTMLMediaElement>,
+    /// Incremented whenever the text track is loaded again, to ignore the
+    /// responses of outdated requests.
+    generation_id: Cell<u32>,
+    /// Blocks the load event of the document while the text track is fetched.
+    load_blocker: DomRefCell<Option<LoadBlocker>>,
 }
 
 impl HTMLTrackElement {
@@ -42,8 +82,11 @@ impl HTMLTrackElement {
     ) -> HTMLTrackElement {
         HTMLTrackElement {
             htmlelement: HTMLElement::new_inherited(local_name, prefix, document),
-            ready_state: ReadyState::None,
+            ready_state: Cell::new(ReadyState::None),
             track: Dom::from_ref(&track),
+            media_element: Default::default(),
+            generation_id: Cell::new(0),
+            load_blocker: DomRefCell::new(None),
         }
     }
 
@@ -61,12 +104,212 @@ impl HTMLTrackElement {
             Default::default(),
             None,
         );
-        Node::reflect_node(
+        let element = Node::reflect_node(
             Box::new(HTMLTrackElement::new_inherited(
                 local_name, prefix, document, &track,
             )),
             document,
+        );
+        track.set_track_element(&element);
+        element
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#start-the-track-processing-model>
+    pub fn start_track_processing(&self) {
+        if !pref!(dom.webvtt.enabled) {
+            return;
+        }
+
+        // Step 1.
+        if self.ready_state.get() != ReadyState::None {
+            return;
+        }
+
+        // Step 2.
+        if self.track.Mode() == TextTrackMode::Disabled {
+            return;
+        }
+
+        // Step 3.
+        let media_element = match self.media_element.get() {
+            Some(media_element) => media_element,
+            None => return,
+        };
+
+        // Step 5.
+        self.ready_state.set(ReadyState::Loading);
+        self.generation_id.set(self.generation_id.get() + 1);
+
+        // Steps 8 and 9.
+        let document = document_from_node(self);
+        let src = self
+            .upcast::<Element>()
+            .get_string_attribute(&local_name!("src"));
+        let url = match document.base_url().join(&src) {
+            Ok(url) if !src.is_empty() => url,
+            _ => return self.track_processing_done(false),
+        };
+
+        // Step 10.
+        let request = create_a_potential_cors_request(
+            url.clone(),
+            Destination::Track,
+            cors_setting_for_element(media_element.upcast()),
+            None,
+            self.global().get_referrer(),
         )
+        .origin(document.origin().immutable().clone())
+        .pipeline_id(Some(self.global().pipeline_id()))
+        .referrer_policy(document.get_referrer_policy());
+
+        let mut blocker = self.load_blocker.borrow_mut();
+        LoadBlocker::terminate(&mut *blocker);
+        *blocker = Some(LoadBlocker::new(&document, LoadType::Media));
+
+        let context = Arc::new(Mutex::new(TrackFetchContext {
+            elem: Trusted::new(self),
+            generation_id: self.generation_id.get(),
+            data: vec![],
+            status: Ok(()),
+            resource_timing: ResourceFetchTiming::new(ResourceTimingType::Resource),
+            url,
+        }));
+        let (action_sender, action_receiver) = ipc::channel().unwrap();
+        let (task_source, canceller) = window_from_node(self)
+            .task_manager()
+            .networking_task_source_with_canceller();
+        let listener = NetworkListener {
+            context,
+            task_source,
+            canceller: Some(canceller),
+        };
+        ROUTER.add_route(
+            action_receiver.to_opaque(),
+            Box::new(move |message| {
+                listener.notify_fetch(message.to().unwrap());
+            }),
+        );
+        self.global()
+            .core_resource_thread()
+            .send(CoreResourceMsg::Fetch(
+                request,
+                FetchChannels::ResponseMsg(action_sender, None),
+            ))
+            .unwrap();
+    }
+
+    /// Parse the fetched text track resource and replace the cues of the
+    /// text track with the ones it contains.
+    ///
+    /// <https://w3c.github.io/webvtt/#webvtt-parser-algorithm>
+    fn process_track_resource(&self, data: &[u8]) -> bool {
+        let file = match webvttparser::parse(&String::from_utf8_lossy(data)) {
+            Ok(file) => file,
+            Err(webvttparser::ParseError(error)) => {
+                warn!("Could not parse WebVTT file: {}", error);
+                return false;
+            },
+        };
+
+        let global = self.global();
+        let regions: Vec<_> = file
+            .regions
+            .iter()
+            .map(|region| (region.id.as_str(), VTTRegion::from_webvtt(&global, region)))
+            .collect();
+        let cues: Vec<_> = file
+            .cues
+            .iter()
+            .map(|cue| {
+                let region = cue.region.as_ref().and_then(|id| {
+                    regions
+                        .iter()
+                        .find(|(region_id, _)| *region_id == id.as_str())
+                        .map(|(_, region)| &**region)
+                });
+                VTTCue::from_webvtt(&global, cue, region)
+            })
+            .collect();
+        let cues: Vec<&TextTrackCue> = cues.iter().map(|cue| cue.upcast()).collect();
+        self.track.reset_cues(&cues);
+        true
+    }
+
+    /// Set the readiness state of the text track once its resource was
+    /// loaded, or failed to, and fire the corresponding event.
+    ///
+    /// <https://html.spec.whatwg.org/multipage/#start-the-track-processing-model>
+    fn track_processing_done(&self, success: bool) {
+        LoadBlocker::terminate(&mut *self.load_blocker.borrow_mut());
+        let (ready_state, event) = if success {
+            (ReadyState::Loaded, atom!("load"))
+        } else {
+            (ReadyState::Error, atom!("error"))
+        };
+        self.ready_state.set(ready_state);
+        let window = window_from_node(self);
+        window
+            .task_manager()
+            .dom_manipulation_task_source()
+            .queue_simple_event(self.upcast(), event, &window);
+    }
+
+    /// The kind of the text track, from the `kind` attribute.
+    fn kind(&self) -> TextTrackKind {
+        match &*self.Kind() {
+            "captions" => TextTrackKind::Captions,
+            "descriptions" => TextTrackKind::Descriptions,
+            "chapters" => TextTrackKind::Chapters,
+            "metadata" => TextTrackKind::Metadata,
+            _ => TextTrackKind::Subtitles,
+        }
+    }
+
+    /// Add the text track to the list of text tracks of the media element
+    /// this element was inserted in.
+    ///
+    /// <https://html.spec.whatwg.org/multipage/#sourcing-out-of-band-text-tracks>
+    fn attach_to_media_element(&self, media_element: &HTMLMediaElement) {
+        self.media_element.set(Some(media_element));
+        let tracks = media_element.TextTracks();
+        tracks.add(&self.track);
+
+        // A track moved from another media element keeps its mode.
+        if self.track.Mode() != TextTrackMode::Disabled {
+            return self.start_track_processing();
+        }
+
+        // https://html.spec.whatwg.org/multipage/#honor-user-preferences-for-automatic-text-track-selection
+        if !self.Default() {
+            return;
+        }
+        let kind = self.kind();
+        let showing_track_of_same_kind = (0..tracks.len())
+            .filter_map(|index| tracks.item(index))
+            .any(|track| track.Mode() == TextTrackMode::Showing && track.Kind() == kind);
+        match kind {
+            TextTrackKind::Subtitles | TextTrackKind::Captions if !showing_track_of_same_kind => {
+                self.track.SetMode(TextTrackMode::Showing)
+            },
+            TextTrackKind::Chapters | TextTrackKind::Metadata => {
+                self.track.SetMode(TextTrackMode::Hidden)
+            },
+            _ => {},
+        }
+    }
+
+    /// Remove the text track from the list of text tracks of the media
+    /// element this element was a child of.
+    fn detach_from_media_element(&self) {
+        let media_element = match self.media_element.get() {
+            Some(media_element) => media_element,
+            None => return,
+        };
+        self.media_element.set(None);
+        let tracks = media_element.TextTracks();
+        if let Some(index) = tracks.find(&self.track) {
+            tracks.remove(index);
+        }
     }
 }
 
@@ -125,7 +368,7 @@ impl HTMLTrackElementMethods for HTMLTrackElement {
 
     // https://html.spec.whatwg.org/multipage/#dom-track-readystate
     fn ReadyState(&self) -> u16 {
-        self.ready_state as u16
+        self.ready_state.get() as u16
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-track-track
@@ -133,3 +376,145 @@ impl HTMLTrackElementMethods for HTMLTrackElement {
         DomRoot::from_ref(&*self.track)
     }
 }
+
+impl VirtualMethods for HTMLTrackElement {
+    fn super_type(&self) -> Option<&dyn VirtualMethods> {
+        Some(self.upcast::<HTMLElement>() as &dyn VirtualMethods)
+    }
+
+    fn attribute_mutated(&self, attr: &Attr, mutation: AttributeMutation) {
+        self.super_type().unwrap().attribute_mutated(attr, mutation);
+
+        let value = DOMString::from(
+            mutation
+                .new_value(attr)
+                .map_or(String::new(), |value| String::from(&**value)),
+        );
+        match attr.local_name() {
+            &local_name!("kind") => {
+                self.track.set_kind(self.kind());
+                self.track.cues_changed();
+            },
+            &local_name!("label") => self.track.set_label(value),
+            &local_name!("srclang") => self.track.set_language(value),
+            &local_name!("id") => self.track.set_id(value),
+            // https://html.spec.whatwg.org/multipage/#sourcing-out-of-band-text-tracks:attr-track-src
+            &local_name!("src") => {
+                self.ready_state.set(ReadyState::None);
+                self.track.reset_cues(&[]);
+                self.start_track_processing();
+            },
+            _ => {},
+        }
+    }
+
+    fn bind_to_tree(&self, context: &BindContext) {
+        if let Some(ref s) = self.super_type() {
+            s.bind_to_tree(context);
+        }
+
+        let parent = self.upcast::<Node>().GetParentNode();
+        let media_element = parent
+            .as_ref()
+            .and_then(|parent| parent.downcast::<HTMLMediaElement>());
+        if let Some(media_element) = media_element {
+            if self.media_element != Some(media_element) {
+                self.detach_from_media_element();
+                self.attach_to_media_element(media_element);
+            }
+        }
+    }
+
+    fn unbind_from_tree(&self, context: &UnbindContext) {
+        self.super_type().unwrap().unbind_from_tree(context);
+
+        let parent = self.upcast::<Node>().GetParentNode();
+        let is_media_element_child = match (parent, self.media_element.get()) {
+            (Some(parent), Some(media_element)) => parent == DomRoot::upcast(media_element),
+            _ => false,
+        };
+        if !is_media_element_child {
+            self.detach_from_media_element();
+        }
+    }
+}
+
+struct TrackFetchContext {
+    /// The element that initiated the request.
+    elem: Trusted<HTMLTrackElement>,
+    /// The generation of the element when this fetch started.
+    generation_id: u32,
+    /// The response body received to date.
+    data: Vec<u8>,
+    /// Whether the response is a successful one so far.
+    status: Result<(), NetworkError>,
+    /// Timing data for this resource.
+    resource_timing: ResourceFetchTiming,
+    /// Url for the resource.
+    url: ServoUrl,
+}
+
+impl FetchResponseListener for TrackFetchContext {
+    fn process_request_body(&mut self) {}
+
+    fn process_request_eof(&mut self) {}
+
+    fn process_response(&mut self, metadata: Result<FetchMetadata, NetworkError>) {
+        let metadata = metadata.map(|meta| match meta {
+            FetchMetadata::Unfiltered(m) => m,
+            FetchMetadata::Filtered { unsafe_, .. } => unsafe_,
+        });
+
+        self.status = metadata.and_then(|metadata| match metadata.status {
+            Some((status, _)) if status < 200 || status >= 300 => Err(NetworkError::Internal(
+                format!("HTTP error code {}", status),
+            )),
+            _ => Ok(()),
+        });
+    }
+
+    fn process_response_chunk(&mut self, mut chunk: Vec<u8>) {
+        if self.status.is_ok() {
+            self.data.append(&mut chunk);
+        }
+    }
+
+    fn process_response_eof(&mut self, response: Result<ResourceFetchTiming, NetworkError>) {
+        let elem = self.elem.root();
+        let success =
+            response.is_ok() && self.status.is_ok() && elem.process_track_resource(&self.data);
+        elem.track_processing_done(success);
+    }
+
+    fn resource_timing_mut(&mut self) -> &mut ResourceFetchTiming {
+        &mut self.resource_timing
+    }
+
+    fn resource_timing(&self) -> &ResourceFetchTiming {
+        &self.resource_timing
+    }
+
+    fn submit_resource_timing(&mut self) {
+        network_listener::submit_timing(self)
+    }
+}
+
+impl ResourceTimingListener for TrackFetchContext {
+    fn resource_timing_information(&self) -> (InitiatorType, ServoUrl) {
+        (
+            InitiatorType::LocalName(String::from("track")),
+            self.url.clone(),
+        )
+    }
+
+    fn resource_timing_global(&self) -> DomRoot<GlobalScope> {
+        document_from_node(&*self.elem.root()).global()
+    }
+}
+
+impl PreInvoke for TrackFetchContext {
+    fn should_invoke(&self) -> bool {
+        // Ignore the responses of a request superseded by a new one.
+        self.elem.root().generation_id.get() == self.generation_id
+    }
+}
@@ -5,23 +5,33 @@
 use crate::document_loader::{LoadBlocker, LoadType};
 use crate::dom::attr::Attr;
 use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::HTMLMediaElementBinding::HTMLMediaElementMethods;
 use crate::dom::bindings::codegen::Bindings::HTMLVideoElementBinding::HTMLVideoElementMethods;
+use crate::dom::bindings::codegen::Bindings::VideoTrackListBinding::VideoTrackListMethods;
+use crate::dom::bindings::error::Error;
 use crate::dom::bindings::inheritance::Castable;
-use crate::dom::bindings::refcounted::Trusted;
+use crate::dom::bindings::refcounted::{Trusted, TrustedPromise};
 use crate::dom::bindings::reflector::DomObject;
 use crate::dom::bindings::root::DomRoot;
 use crate::dom::bindings::str::DOMString;
 use crate::dom::document::Document;
 use crate::dom::element::{AttributeMutation, Element};
+use crate::dom::event::{Event, EventBubbles, EventCancelable};
+use crate::dom::eventtarget::EventTarget;
 use crate::dom::globalscope::GlobalScope;
 use crate::dom::htmlmediaelement::{HTMLMediaElement, ReadyState};
-use crate::dom::node::{document_from_node, window_from_node, Node};
+use crate::dom::node::{document_from_node, window_from_node, Node, UnbindContext};
 use crate::dom::performanceresourcetiming::InitiatorType;
+use crate::dom::pictureinpictureevent::PictureInPictureEvent;
+use crate::dom::pictureinpicturewindow::PictureInPictureWindow;
+use crate::dom::promise::Promise;
 use crate::dom::virtualmethods::VirtualMethods;
 use crate::fetch::FetchCanceller;
 use crate::image_listener::{generate_cache_listener_for_element, ImageCacheListener};
 use crate::network_listener::{self, NetworkListener, PreInvoke, ResourceTimingListener};
+use crate::task_source::{TaskSource, TaskSourceName};
 use dom_struct::dom_struct;
+use embedder_traits::{PictureInPictureFrame, PictureInPictureRequest};
 use euclid::default::Size2D;
 use html5ever::{LocalName, Prefix};
 use ipc_channel::ipc;
@@ -35,14 +45,25 @@ use net_traits::{
     CoreResourceMsg, FetchChannels, FetchMetadata, FetchResponseListener, FetchResponseMsg,
 };
 use net_traits::{NetworkError, ResourceFetchTiming, ResourceTimingType};
+use script_traits::ScriptMsg;
+use servo_atoms::Atom;
+use servo_config::pref;
 use servo_media::player::video::VideoFrame;
 use servo_url::ServoUrl;
 use std::cell::Cell;
+use std::rc::Rc;
 use std::sync::{Arc, Mutex};
+use webrender_api::units::DeviceIntSize;
+use webrender_api::ImageKey;
 
 const DEFAULT_WIDTH: u32 = 300;
 const DEFAULT_HEIGHT: u32 = 150;
 
+// TODO change this to local_name! when html5ever updates
+fn disable_picture_in_picture_name() -> LocalName {
+    LocalName::from("disablepictureinpicture")
+}
+
 #[dom_struct]
 pub struct HTMLVideoElement {
     htmlmediaelement: HTMLMediaElement,
@@ -60,6 +81,10 @@ pub struct HTMLVideoElement {
     /// A copy of the last frame
     #[ignore_malloc_size_of = "VideoFrame"]
     last_frame: DomRefCell<Option<VideoFrame>>,
+    /// The image last reported to the embedder while this video is shown in
+    /// picture-in-picture.
+    #[ignore_malloc_size_of = "Defined in webrender_api"]
+    picture_in_picture_image: Cell<Option<ImageKey>>,
 }
 
 impl HTMLVideoElement {
@@ -76,6 +101,7 @@ impl HTMLVideoElement {
             poster_frame_canceller: DomRefCell::new(Default::default()),
             load_blocker: Default::default(),
             last_frame: Default::default(),
+            picture_in_picture_image: Cell::new(None),
         }
     }
 
@@ -134,6 +160,108 @@ impl HTMLVideoElement {
         }
     }
 
+    fn is_picture_in_picture_element(&self) -> bool {
+        document_from_node(self)
+            .picture_in_picture_element()
+            .map_or(false, |element| &*element == self)
+    }
+
+    /// The frames of this video, as shown in a picture-in-picture surface.
+    fn picture_in_picture_frame(&self) -> PictureInPictureFrame {
+        match self.htmlmediaelement.get_current_frame_image() {
+            Some((image_key, width, height)) => PictureInPictureFrame {
+                image_key: Some(image_key),
+                size: DeviceIntSize::new(width, height),
+            },
+            None => PictureInPictureFrame {
+                image_key: None,
+                size: DeviceIntSize::new(
+                    self.video_width.get() as i32,
+                    self.video_height.get() as i32,
+                ),
+            },
+        }
+    }
+
+    pub fn send_picture_in_picture_request(&self, request: PictureInPictureRequest) {
+        window_from_node(self).send_to_constellation(ScriptMsg::PictureInPicture(request));
+    }
+
+    /// Lets the embedder know when the video starts rendering to another
+    /// image while it is shown in picture-in-picture.
+    pub fn update_picture_in_picture_frame(&self) {
+        if !self.is_picture_in_picture_element() {
+            return;
+        }
+        let frame = self.picture_in_picture_frame();
+        if frame.image_key == self.picture_in_picture_image.get() {
+            return;
+        }
+        self.picture_in_picture_image.set(frame.image_key);
+        self.send_picture_in_picture_request(PictureInPictureRequest::UpdateFrame(frame));
+    }
+
+    /// Queues a task to fire an `enterpictureinpicture` or
+    /// `leavepictureinpicture` event at this video.
+    pub fn queue_picture_in_picture_event(&self, name: Atom, window: &PictureInPictureWindow) {
+        let this = Trusted::new(self);
+        let picture_in_picture_window = Trusted::new(window);
+        let window = window_from_node(self);
+        let _ = window.task_manager().media_element_task_source().queue(
+            task!(fire_picture_in_picture_event: move || {
+                let this = this.root();
+                let event = PictureInPictureEvent::new(
+                    &this.global(),
+                    name,
+                    EventBubbles::Bubbles,
+                    EventCancelable::NotCancelable,
+                    &picture_in_picture_window.root(),
+                );
+                event.upcast::<Event>().fire(this.upcast::<EventTarget>());
+            }),
+            window.upcast(),
+        );
+    }
+
+    /// https://w3c.github.io/picture-in-picture/#request-pip
+    /// Steps 8 onwards, once the embedder answered the request for a surface.
+    fn enter_picture_in_picture(&self, size: Option<DeviceIntSize>, promise: Rc<Promise>) {
+        let size = match size {
+            Some(size) => size,
+            None => return promise.reject_error(Error::NotAllowed),
+        };
+
+        // The video may have left the document while the embedder was busy.
+        if !self.upcast::<Node>().is_connected() {
+            self.send_picture_in_picture_request(PictureInPictureRequest::Exit);
+            return promise.reject_error(Error::InvalidState);
+        }
+
+        // Steps 8-9.
+        let window = window_from_node(self);
+        let picture_in_picture_window =
+            PictureInPictureWindow::new(&window, Size2D::new(size.width, size.height));
+        document_from_node(self).set_picture_in_picture(Some((self, &picture_in_picture_window)));
+        self.picture_in_picture_image
+            .set(self.picture_in_picture_frame().image_key);
+
+        // Step 11.
+        self.queue_picture_in_picture_event(
+            atom!("enterpictureinpicture"),
+            &picture_in_picture_window,
+        );
+
+        // Resolve the promise after the event fired.
+        let promise = TrustedPromise::new(promise);
+        let picture_in_picture_window = Trusted::new(&*picture_in_picture_window);
+        let _ = window.task_manager().media_element_task_source().queue(
+            task!(resolve_request_picture_in_picture: move || {
+                promise.root().resolve_native(&*picture_in_picture_window.root());
+            }),
+            window.upcast(),
+        );
+    }
+
     /// https://html.spec.whatwg.org/multipage/#poster-frame
     fn fetch_poster_frame(&self, poster_url: &str) {
         // Step 1.
@@ -266,6 +394,109 @@ impl HTMLVideoElementMethods for HTMLVideoElement {
     // For testing purposes only. This is not an event from
     // https://html.spec.whatwg.org/multipage/#dom-video-poster
     event_handler!(postershown, GetOnpostershown, SetOnpostershown);
+
+    // https://w3c.github.io/picture-in-picture/#dom-htmlvideoelement-requestpictureinpicture
+    fn RequestPictureInPicture(&self) -> Rc<Promise> {
+        let document = document_from_node(self);
+        let promise = Promise::new(&document.global());
+
+        // Steps 1-2 are covered by the preference gating this API.
+
+        // Step 3.
+        if self.htmlmediaelement.get_ready_state() == ReadyState::HaveNothing {
+            promise.reject_error(Error::InvalidState);
+            return promise;
+        }
+
+        // Step 4.
+        if self.htmlmediaelement.VideoTracks().Length() == 0 {
+            promise.reject_error(Error::InvalidState);
+            return promise;
+        }
+
+        // Step 5.
+        if self.DisablePictureInPicture() {
+            promise.reject_error(Error::InvalidState);
+            return promise;
+        }
+
+        // TODO: Step 6. Servo does not track transient user activation yet.
+
+        // Step 7.
+        if self.is_picture_in_picture_element() {
+            let window = document.picture_in_picture_window().unwrap();
+            promise.resolve_native(&*window);
+            return promise;
+        }
+
+        // Only one video of the document is shown in picture-in-picture.
+        let _ = document.exit_picture_in_picture();
+
+        if pref!(dom.picture_in_picture.test) {
+            // For tests we don't ask the embedder for a surface, and pretend
+            // that it opened one of the size of the video.
+            let size = self.picture_in_picture_frame().size;
+            self.enter_picture_in_picture(Some(size), promise.clone());
+            return promise;
+        }
+
+        let (sender, receiver) = ipc::channel().unwrap();
+        let window = window_from_node(self);
+        let task_source = window.task_manager().media_element_task_source();
+        let canceller = window
+            .task_manager()
+            .task_canceller(TaskSourceName::MediaElement);
+        let this = Trusted::new(self);
+        let mut trusted_promise = Some(TrustedPromise::new(promise.clone()));
+        ROUTER.add_route(
+            receiver.to_opaque(),
+            Box::new(move |message| {
+                let size: Option<DeviceIntSize> = message.to().unwrap();
+                let this = this.clone();
+                let promise = match trusted_promise.take() {
+                    Some(promise) => promise,
+                    None => return,
+                };
+                let _ = task_source.queue_with_canceller(
+                    task!(picture_in_picture_response: move || {
+                        this.root().enter_picture_in_picture(size, promise.root());
+                    }),
+                    &canceller,
+                );
+            }),
+        );
+        self.send_picture_in_picture_request(PictureInPictureRequest::Enter(
+            self.picture_in_picture_frame(),
+            sender,
+        ));
+        promise
+    }
+
+    // https://w3c.github.io/picture-in-picture/#dom-htmlvideoelement-onenterpictureinpicture
+    event_handler!(
+        enterpictureinpicture,
+        GetOnenterpictureinpicture,
+        SetOnenterpictureinpicture
+    );
+
+    // https://w3c.github.io/picture-in-picture/#dom-htmlvideoelement-onleavepictureinpicture
+    event_handler!(
+        leavepictureinpicture,
+        GetOnleavepictureinpicture,
+        SetOnleavepictureinpicture
+    );
+
+    // https://w3c.github.io/picture-in-picture/#dom-htmlvideoelement-disablepictureinpicture
+    fn DisablePictureInPicture(&self) -> bool {
+        self.upcast::<Element>()
+            .has_attribute(&disable_picture_in_picture_name())
+    }
+
+    // https://w3c.github.io/picture-in-picture/#dom-htmlvideoelement-disablepictureinpicture
+    fn SetDisablePictureInPicture(&self, value: bool) {
+        self.upcast::<Element>()
+            .set_bool_attribute(&disable_picture_in_picture_name(), value)
+    }
 }
 
 impl VirtualMethods for HTMLVideoElement {
@@ -281,10 +512,25 @@ impl VirtualMethods for HTMLVideoElement {
                 &local_name!("poster") => {
                     self.fetch_poster_frame(&new_value);
                 },
+                // https://w3c.github.io/picture-in-picture/#disable-pip
+                name if *name == disable_picture_in_picture_name() => {
+                    if self.is_picture_in_picture_element() {
+                        let _ = document_from_node(self).exit_picture_in_picture();
+                    }
+                },
                 _ => (),
             };
         }
     }
+
+    fn unbind_from_tree(&self, context: &UnbindContext) {
+        self.super_type().unwrap().unbind_from_tree(context);
+
+        // https://w3c.github.io/picture-in-picture/#remove-video
+        if context.tree_connected && self.is_picture_in_picture_element() {
+            let _ = document_from_node(self).exit_picture_in_picture();
+        }
+    }
 }
 
 impl ImageCacheListener for HTMLVideoElement {
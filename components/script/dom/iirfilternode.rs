@@ -0,0 +1,156 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::audionode::AudioNode;
+use crate::dom::baseaudiocontext::BaseAudioContext;
+use crate::dom::bindings::codegen::Bindings::AudioNodeBinding::{
+    ChannelCountMode, ChannelInterpretation,
+};
+use crate::dom::bindings::codegen::Bindings::BaseAudioContextBinding::BaseAudioContextMethods;
+use crate::dom::bindings::codegen::Bindings::IIRFilterNodeBinding::IIRFilterNodeMethods;
+use crate::dom::bindings::codegen::Bindings::IIRFilterNodeBinding::IIRFilterOptions;
+use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
+use crate::dom::bindings::reflector::reflect_dom_object;
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::window::Window;
+use dom_struct::dom_struct;
+use js::rust::CustomAutoRooterGuard;
+use js::typedarray::Float32Array;
+use servo_media::audio::iir_filter_node::IIRFilterNodeOptions;
+use servo_media::audio::node::AudioNodeInit;
+use std::f64::consts::PI;
+
+/// The maximum number of feedforward and feedback coefficients.
+const MAX_COEFFICIENTS: usize = 20;
+
+#[dom_struct]
+pub struct IIRFilterNode {
+    node: AudioNode,
+    feedforward: Vec<f64>,
+    feedback: Vec<f64>,
+    sample_rate: f32,
+}
+
+impl IIRFilterNode {
+    #[allow(unrooted_must_root)]
+    pub fn new_inherited(
+        _: &Window,
+        context: &BaseAudioContext,
+        options: &IIRFilterOptions,
+    ) -> Fallible<IIRFilterNode> {
+        let node_options =
+            options
+                .parent
+                .unwrap_or(2, ChannelCountMode::Max, ChannelInterpretation::Speakers);
+        let feedforward: Vec<f64> = options.feedforward.iter().map(|c| **c).collect();
+        let feedback: Vec<f64> = options.feedback.iter().map(|c| **c).collect();
+
+        // https://webaudio.github.io/web-audio-api/#dom-iirfilteroptions-feedforward
+        if feedforward.is_empty() || feedforward.len() > MAX_COEFFICIENTS {
+            return Err(Error::NotSupported);
+        }
+        if feedforward.iter().all(|c| *c == 0.) {
+            return Err(Error::InvalidState);
+        }
+        // https://webaudio.github.io/web-audio-api/#dom-iirfilteroptions-feedback
+        if feedback.is_empty() || feedback.len() > MAX_COEFFICIENTS {
+            return Err(Error::NotSupported);
+        }
+        if feedback[0] == 0. {
+            return Err(Error::InvalidState);
+        }
+
+        let node = AudioNode::new_inherited(
+            AudioNodeInit::IIRFilterNode(IIRFilterNodeOptions {
+                feedforward: feedforward.clone(),
+                feedback: feedback.clone(),
+            }),
+            context,
+            node_options,
+            1, // inputs
+            1, // outputs
+        )?;
+        Ok(IIRFilterNode {
+            node,
+            feedforward,
+            feedback,
+            sample_rate: *context.SampleRate(),
+        })
+    }
+
+    #[allow(unrooted_must_root)]
+    pub fn new(
+        window: &Window,
+        context: &BaseAudioContext,
+        options: &IIRFilterOptions,
+    ) -> Fallible<DomRoot<IIRFilterNode>> {
+        let node = IIRFilterNode::new_inherited(window, context, options)?;
+        Ok(reflect_dom_object(Box::new(node), window))
+    }
+
+    #[allow(non_snake_case)]
+    pub fn Constructor(
+        window: &Window,
+        context: &BaseAudioContext,
+        options: &IIRFilterOptions,
+    ) -> Fallible<DomRoot<IIRFilterNode>> {
+        IIRFilterNode::new(window, context, options)
+    }
+
+    /// Evaluate the transfer function at the given frequency, returning its
+    /// magnitude and phase.
+    fn frequency_response(&self, frequency: f64) -> (f64, f64) {
+        // z^-k = e^(-jωk), with ω the normalized angular frequency.
+        let omega = 2. * PI * frequency / self.sample_rate as f64;
+        let evaluate = |coefficients: &[f64]| {
+            coefficients
+                .iter()
+                .enumerate()
+                .fold((0., 0.), |(re, im), (k, c)| {
+                    let angle = -omega * k as f64;
+                    (re + c * angle.cos(), im + c * angle.sin())
+                })
+        };
+        let (num_re, num_im) = evaluate(&self.feedforward);
+        let (den_re, den_im) = evaluate(&self.feedback);
+        let den_norm = den_re * den_re + den_im * den_im;
+        let re = (num_re * den_re + num_im * den_im) / den_norm;
+        let im = (num_im * den_re - num_re * den_im) / den_norm;
+        (re.hypot(im), im.atan2(re))
+    }
+}
+
+impl IIRFilterNodeMethods for IIRFilterNode {
+    #[allow(unsafe_code)]
+    /// https://webaudio.github.io/web-audio-api/#dom-iirfilternode-getfrequencyresponse
+    fn GetFrequencyResponse(
+        &self,
+        frequency_hz: CustomAutoRooterGuard<Float32Array>,
+        mut mag_response: CustomAutoRooterGuard<Float32Array>,
+        mut phase_response: CustomAutoRooterGuard<Float32Array>,
+    ) -> ErrorResult {
+        let frequencies = frequency_hz.to_vec();
+        // Invariant to maintain: No JS code that may touch the arrays should
+        // run whilst we're writing to them
+        let magnitudes = unsafe { mag_response.as_mut_slice() };
+        let phases = unsafe { phase_response.as_mut_slice() };
+        if magnitudes.len() != frequencies.len() || phases.len() != frequencies.len() {
+            return Err(Error::InvalidAccess);
+        }
+
+        let nyquist = self.sample_rate as f64 / 2.;
+        for (i, frequency) in frequencies.iter().enumerate() {
+            let frequency = *frequency as f64;
+            if frequency < 0. || frequency > nyquist || frequency.is_nan() {
+                magnitudes[i] = f32::NAN;
+                phases[i] = f32::NAN;
+                continue;
+            }
+            let (magnitude, phase) = self.frequency_response(frequency);
+            magnitudes[i] = magnitude as f32;
+            phases[i] = phase as f32;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,396 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::animationeffect::{parse_easing, AnimationEffect, Timing};
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::KeyframeEffectBinding::{
+    CompositeOperation, KeyframeEffectMethods,
+};
+use crate::dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
+use crate::dom::bindings::codegen::UnionTypes::StringOrStringSequence;
+use crate::dom::bindings::codegen::UnionTypes::StringStringRecordSequenceOrStringStringOrStringSequenceRecord as KeyframesInit;
+use crate::dom::bindings::codegen::UnionTypes::UnrestrictedDoubleOrKeyframeEffectOptions;
+use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject};
+use crate::dom::bindings::root::{DomRoot, MutNullableDom};
+use crate::dom::bindings::str::DOMString;
+use crate::dom::element::Element;
+use crate::dom::window::Window;
+use cssparser::SourceLocation;
+use dom_struct::dom_struct;
+use servo_arc::Arc;
+use std::cell::Cell;
+use style::animation::CompositeOperation as StyleCompositeOperation;
+use style::properties::{
+    parse_one_declaration_into, Importance, PropertyDeclarationBlock, PropertyId,
+    SourcePropertyDeclaration,
+};
+use style::shared_lock::Locked;
+use style::stylesheets::keyframes_rule::{
+    Keyframe, KeyframePercentage, KeyframeSelector, KeyframesAnimation,
+};
+use style_traits::ParsingMode;
+
+/// A keyframe as specified from script, before its offset is computed.
+///
+/// <https://drafts.csswg.org/web-animations/#processing-a-keyframes-argument>
+struct ProcessedKeyframe {
+    offset: Option<f64>,
+    easing: DOMString,
+    values: Vec<(PropertyId, DOMString)>,
+}
+
+impl ProcessedKeyframe {
+    fn new(offset: Option<f64>) -> ProcessedKeyframe {
+        ProcessedKeyframe {
+            offset,
+            easing: DOMString::from("linear"),
+            values: vec![],
+        }
+    }
+}
+
+/// Convert the name of a keyframe member, which is the IDL attribute name of a
+/// property, to a property.
+///
+/// <https://drafts.csswg.org/web-animations/#animation-property-name-to-idl-attribute-name>
+fn property_for_member_name(name: &str) -> Option<PropertyId> {
+    if name.contains('-') {
+        return None;
+    }
+    if name == "cssFloat" {
+        return PropertyId::parse_enabled_for_all_content("float").ok();
+    }
+    let mut property = String::with_capacity(name.len());
+    for character in name.chars() {
+        if character.is_ascii_uppercase() {
+            property.push('-');
+            property.push(character.to_ascii_lowercase());
+        } else {
+            property.push(character);
+        }
+    }
+    PropertyId::parse_enabled_for_all_content(&property).ok()
+}
+
+fn parse_offset(offset: &DOMString) -> Fallible<Option<f64>> {
+    if &**offset == "null" {
+        return Ok(None);
+    }
+    match offset.parse::<f64>() {
+        Ok(offset) if offset >= 0. && offset <= 1. => Ok(Some(offset)),
+        _ => Err(Error::Type(
+            "keyframe offsets must be numbers between 0 and 1".to_owned(),
+        )),
+    }
+}
+
+/// <https://drafts.csswg.org/web-animations/#processing-a-keyframes-argument>
+fn process_keyframes(keyframes: &KeyframesInit) -> Fallible<Vec<ProcessedKeyframe>> {
+    match *keyframes {
+        KeyframesInit::StringStringRecordSequence(ref keyframes) => {
+            let mut processed = Vec::with_capacity(keyframes.len());
+            let mut previous_offset = 0.;
+            for keyframe in keyframes {
+                let offset = match keyframe.get("offset") {
+                    Some(offset) => parse_offset(offset)?,
+                    None => None,
+                };
+                if let Some(offset) = offset {
+                    if offset < previous_offset {
+                        return Err(Error::Type(
+                            "keyframe offsets must be loosely sorted".to_owned(),
+                        ));
+                    }
+                    previous_offset = offset;
+                }
+
+                let mut result = ProcessedKeyframe::new(offset);
+                for (name, value) in keyframe.iter() {
+                    match &**name {
+                        "offset" | "composite" => {},
+                        "easing" => result.easing = value.clone(),
+                        name => {
+                            if let Some(property) = property_for_member_name(name) {
+                                result.values.push((property, value.clone()));
+                            }
+                        },
+                    }
+                }
+                processed.push(result);
+            }
+            Ok(processed)
+        },
+        KeyframesInit::StringStringOrStringSequenceRecord(ref keyframes) => {
+            let mut processed: Vec<ProcessedKeyframe> = vec![];
+            let mut easings = vec![];
+            for (name, values) in keyframes.iter() {
+                let values = match *values {
+                    StringOrStringSequence::String(ref value) => vec![value.clone()],
+                    StringOrStringSequence::StringSequence(ref values) => values.clone(),
+                };
+                let property = match &**name {
+                    "easing" => {
+                        easings = values;
+                        continue;
+                    },
+                    // Per-keyframe offsets and composite operations are not
+                    // supported for property-indexed keyframes.
+                    "offset" | "composite" => continue,
+                    name => match property_for_member_name(name) {
+                        Some(property) => property,
+                        None => continue,
+                    },
+                };
+
+                // A single value is the value of the last keyframe, otherwise the
+                // values are evenly spaced.
+                let count = values.len();
+                for (index, value) in values.into_iter().enumerate() {
+                    let offset = if count == 1 {
+                        1.
+                    } else {
+                        index as f64 / (count - 1) as f64
+                    };
+                    let position = processed
+                        .iter()
+                        .position(|keyframe| keyframe.offset.map_or(false, |o| o >= offset));
+                    let keyframe = match position {
+                        Some(position) if processed[position].offset == Some(offset) => {
+                            &mut processed[position]
+                        },
+                        Some(position) => {
+                            processed.insert(position, ProcessedKeyframe::new(Some(offset)));
+                            &mut processed[position]
+                        },
+                        None => {
+                            processed.push(ProcessedKeyframe::new(Some(offset)));
+                            processed.last_mut().unwrap()
+                        },
+                    };
+                    keyframe.values.push((property.clone(), value));
+                }
+            }
+
+            if !easings.is_empty() {
+                for (keyframe, easing) in processed.iter_mut().zip(easings.iter().cycle()) {
+                    keyframe.easing = easing.clone();
+                }
+            }
+            Ok(processed)
+        },
+    }
+}
+
+/// <https://drafts.csswg.org/web-animations/#compute-missing-keyframe-offsets>
+fn compute_missing_offsets(keyframes: &mut [ProcessedKeyframe]) {
+    let count = keyframes.len();
+    if count == 0 {
+        return;
+    }
+    if count > 1 && keyframes[0].offset.is_none() {
+        keyframes[0].offset = Some(0.);
+    }
+    if keyframes[count - 1].offset.is_none() {
+        keyframes[count - 1].offset = Some(1.);
+    }
+
+    let mut previous = 0;
+    for index in 1..count {
+        let offset = match keyframes[index].offset {
+            Some(offset) => offset,
+            None => continue,
+        };
+        let start = keyframes[previous].offset.unwrap_or(0.);
+        let steps = (index - previous) as f64;
+        for missing in previous + 1..index {
+            let step = (missing - previous) as f64;
+            keyframes[missing].offset = Some(start + (offset - start) * step / steps);
+        }
+        previous = index;
+    }
+}
+
+// https://drafts.csswg.org/web-animations/#the-keyframeeffect-interface
+#[dom_struct]
+pub struct KeyframeEffect {
+    effect: AnimationEffect,
+    target: MutNullableDom<Element>,
+    #[ignore_malloc_size_of = "Arc"]
+    keyframes: DomRefCell<Vec<Arc<Locked<Keyframe>>>>,
+    composite: Cell<CompositeOperation>,
+}
+
+impl KeyframeEffect {
+    fn new_inherited(
+        target: Option<&Element>,
+        timing: Timing,
+        composite: CompositeOperation,
+    ) -> KeyframeEffect {
+        KeyframeEffect {
+            effect: AnimationEffect::new_inherited(timing),
+            target: MutNullableDom::new(target),
+            keyframes: DomRefCell::new(vec![]),
+            composite: Cell::new(composite),
+        }
+    }
+
+    pub fn new(
+        window: &Window,
+        target: Option<&Element>,
+        timing: Timing,
+        composite: CompositeOperation,
+    ) -> DomRoot<KeyframeEffect> {
+        reflect_dom_object(
+            Box::new(KeyframeEffect::new_inherited(target, timing, composite)),
+            window,
+        )
+    }
+
+    /// Create a keyframe effect given the options of `KeyframeEffect()` or
+    /// `Element.animate()`.
+    pub fn new_with_options(
+        window: &Window,
+        target: Option<&Element>,
+        keyframes: Option<&KeyframesInit>,
+        options: &UnrestrictedDoubleOrKeyframeEffectOptions,
+    ) -> Fallible<DomRoot<KeyframeEffect>> {
+        let (timing, composite) = match *options {
+            UnrestrictedDoubleOrKeyframeEffectOptions::UnrestrictedDouble(duration) => (
+                Timing::from_duration(duration)?,
+                CompositeOperation::Replace,
+            ),
+            UnrestrictedDoubleOrKeyframeEffectOptions::KeyframeEffectOptions(ref options) => (
+                Timing::from_effect_timing(window, &options.parent)?,
+                options.composite,
+            ),
+        };
+        let effect = KeyframeEffect::new(window, target, timing, composite);
+        effect.set_keyframes(window, keyframes)?;
+        Ok(effect)
+    }
+
+    pub fn target(&self) -> Option<DomRoot<Element>> {
+        self.target.get()
+    }
+
+    /// The keyframes of this effect, ready to be computed for its target.
+    pub fn keyframes_animation(&self) -> KeyframesAnimation {
+        let window = self.global();
+        let document = window.as_window().Document();
+        let lock = document.style_shared_lock();
+        KeyframesAnimation::from_keyframes(&self.keyframes.borrow(), None, &lock.read())
+    }
+
+    pub fn style_composite(&self) -> StyleCompositeOperation {
+        match self.composite.get() {
+            CompositeOperation::Replace => StyleCompositeOperation::Replace,
+            CompositeOperation::Add => StyleCompositeOperation::Add,
+            CompositeOperation::Accumulate => StyleCompositeOperation::Accumulate,
+        }
+    }
+
+    fn set_keyframes(&self, window: &Window, keyframes: Option<&KeyframesInit>) -> ErrorResult {
+        let mut processed = match keyframes {
+            Some(keyframes) => process_keyframes(keyframes)?,
+            None => vec![],
+        };
+        for keyframe in &processed {
+            parse_easing(window, &keyframe.easing)?;
+        }
+        compute_missing_offsets(&mut processed);
+
+        let document = window.Document();
+        let url = document.base_url();
+        let quirks_mode = document.quirks_mode();
+        let lock = document.style_shared_lock();
+        let keyframes = processed
+            .into_iter()
+            .map(|keyframe| {
+                let mut block = PropertyDeclarationBlock::new();
+                let mut values = keyframe.values;
+                if &*keyframe.easing != "linear" {
+                    if let Ok(id) =
+                        PropertyId::parse_enabled_for_all_content("animation-timing-function")
+                    {
+                        values.push((id, keyframe.easing));
+                    }
+                }
+                for (id, value) in values {
+                    // Invalid property values are ignored.
+                    let mut declarations = SourcePropertyDeclaration::new();
+                    if parse_one_declaration_into(
+                        &mut declarations,
+                        id,
+                        &value,
+                        &url,
+                        window.css_error_reporter(),
+                        ParsingMode::DEFAULT,
+                        quirks_mode,
+                    )
+                    .is_ok()
+                    {
+                        block.extend(declarations.drain(), Importance::Normal);
+                    }
+                }
+
+                let offset = keyframe.offset.unwrap_or(1.);
+                Arc::new(lock.wrap(Keyframe {
+                    selector: KeyframeSelector::new(vec![KeyframePercentage::new(offset as f32)]),
+                    block: Arc::new(lock.wrap(block)),
+                    source_location: SourceLocation { line: 0, column: 0 },
+                }))
+            })
+            .collect();
+        *self.keyframes.borrow_mut() = keyframes;
+        Ok(())
+    }
+
+    fn notify_animation(&self) {
+        if let Some(animation) = self.effect.animation() {
+            animation.effect_keyframes_changed();
+        }
+    }
+
+    #[allow(non_snake_case)]
+    pub fn Constructor(
+        window: &Window,
+        target: Option<&Element>,
+        keyframes: Option<KeyframesInit>,
+        options: UnrestrictedDoubleOrKeyframeEffectOptions,
+    ) -> Fallible<DomRoot<KeyframeEffect>> {
+        KeyframeEffect::new_with_options(window, target, keyframes.as_ref(), &options)
+    }
+}
+
+impl KeyframeEffectMethods for KeyframeEffect {
+    // https://drafts.csswg.org/web-animations/#dom-keyframeeffect-target
+    fn GetTarget(&self) -> Option<DomRoot<Element>> {
+        self.target.get()
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-keyframeeffect-target
+    fn SetTarget(&self, target: Option<&Element>) {
+        self.target.set(target);
+        self.notify_animation();
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-keyframeeffect-composite
+    fn Composite(&self) -> CompositeOperation {
+        self.composite.get()
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-keyframeeffect-composite
+    fn SetComposite(&self, composite: CompositeOperation) {
+        self.composite.set(composite);
+        self.notify_animation();
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-keyframeeffect-setkeyframes
+    fn SetKeyframes(&self, keyframes: Option<KeyframesInit>) -> ErrorResult {
+        self.set_keyframes(self.global().as_window(), keyframes.as_ref())?;
+        self.notify_animation();
+        Ok(())
+    }
+}
@@ -2,11 +2,17 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::MediaDeviceInfoBinding::MediaDeviceKind;
 use crate::dom::bindings::codegen::Bindings::MediaDevicesBinding::MediaDevicesMethods;
 use crate::dom::bindings::codegen::Bindings::MediaDevicesBinding::MediaStreamConstraints;
+use crate::dom::bindings::codegen::Bindings::PermissionStatusBinding::{
+    PermissionName, PermissionState,
+};
 use crate::dom::bindings::codegen::UnionTypes::BooleanOrMediaTrackConstraints;
 use crate::dom::bindings::codegen::UnionTypes::ClampedUnsignedLongOrConstrainULongRange as ConstrainULong;
 use crate::dom::bindings::codegen::UnionTypes::DoubleOrConstrainDoubleRange as ConstrainDouble;
+use crate::dom::bindings::error::Error;
 use crate::dom::bindings::reflector::reflect_dom_object;
 use crate::dom::bindings::reflector::DomObject;
 use crate::dom::bindings::root::DomRoot;
@@ -15,29 +21,262 @@ use crate::dom::globalscope::GlobalScope;
 use crate::dom::mediadeviceinfo::MediaDeviceInfo;
 use crate::dom::mediastream::MediaStream;
 use crate::dom::mediastreamtrack::MediaStreamTrack;
+use crate::dom::permissions::prompt_user_from_embedder;
 use crate::dom::promise::Promise;
 use crate::realms::{AlreadyInRealm, InRealm};
 use dom_struct::dom_struct;
+use embedder_traits::PermissionPrompt;
+use servo_config::pref;
+use servo_media::audio::buffer_source_node::AudioBuffer as ServoMediaAudioBuffer;
+use servo_media::audio::buffer_source_node::{
+    AudioBufferSourceNodeMessage, AudioBufferSourceNodeOptions,
+};
+use servo_media::audio::context::{AudioContext, AudioContextOptions};
+use servo_media::audio::decoder::AudioDecoderCallbacks;
+use servo_media::audio::node::{AudioNodeInit, AudioNodeMessage, ChannelInfo};
+use servo_media::audio::node::{
+    AudioScheduledSourceNodeMessage, ChannelCountMode, ChannelInterpretation,
+};
 use servo_media::streams::capture::{Constrain, ConstrainRange, MediaTrackConstraintSet};
+use servo_media::streams::registry::MediaStreamId;
 use servo_media::streams::MediaStreamType;
-use servo_media::ServoMedia;
+use servo_media::{ClientContextId, ServoMedia};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
 use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+/// The group all fake capture devices are reported under.
+const FAKE_DEVICE_GROUP_ID: &str = "fake-capture-devices";
 
 #[dom_struct]
 pub struct MediaDevices {
     eventtarget: EventTarget,
+    /// Audio graphs playing the `media.fake_devices.source` file into fake
+    /// microphone tracks. They have to outlive the tracks they feed.
+    #[ignore_malloc_size_of = "servo_media"]
+    fake_audio_contexts: DomRefCell<Vec<Arc<Mutex<AudioContext>>>>,
 }
 
 impl MediaDevices {
     pub fn new_inherited() -> MediaDevices {
         MediaDevices {
             eventtarget: EventTarget::new_inherited(),
+            fake_audio_contexts: Default::default(),
         }
     }
 
     pub fn new(global: &GlobalScope) -> DomRoot<MediaDevices> {
         reflect_dom_object(Box::new(MediaDevices::new_inherited()), global)
     }
+
+    /// https://w3c.github.io/mediacapture-main/#dfn-request-permission-to-use
+    ///
+    /// The embedder is asked through `EmbedderMsg::PromptPermission` unless the
+    /// `media.testing.capture_permission` preference answers on its behalf.
+    /// The outcome is remembered for the lifetime of the global, so later
+    /// requests and `enumerateDevices` see the same decision.
+    fn request_permission(&self, name: PermissionName) -> bool {
+        let global = self.global();
+        if let Some(state) = global
+            .permission_state_invocation_results()
+            .borrow()
+            .get(&name.to_string())
+        {
+            if *state != PermissionState::Prompt {
+                return *state == PermissionState::Granted;
+            }
+        }
+
+        let state = match pref!(media.testing.capture_permission).as_str() {
+            "granted" => PermissionState::Granted,
+            "denied" => PermissionState::Denied,
+            _ => prompt_user_from_embedder(PermissionPrompt::Request(name.into()), &global),
+        };
+        global
+            .permission_state_invocation_results()
+            .borrow_mut()
+            .insert(name.to_string(), state);
+        state == PermissionState::Granted
+    }
+
+    /// Whether labels of devices of the given kind may be exposed, i.e. whether
+    /// the document has been granted access to that kind of device.
+    fn may_expose_labels(&self, kind: MediaDeviceKind) -> bool {
+        let name = match kind {
+            MediaDeviceKind::Audioinput | MediaDeviceKind::Audiooutput => {
+                PermissionName::Microphone
+            },
+            MediaDeviceKind::Videoinput => PermissionName::Camera,
+        };
+        self.global()
+            .permission_state_invocation_results()
+            .borrow()
+            .get(&name.to_string()) ==
+            Some(&PermissionState::Granted)
+    }
+
+    fn create_audio_input(&self, constraints: MediaTrackConstraintSet) -> Option<MediaStreamId> {
+        let media = ServoMedia::get().unwrap();
+        if !pref!(media.fake_devices.enabled) {
+            return media.create_audioinput_stream(constraints);
+        }
+        match fake_source_file() {
+            Some(path) => self.create_file_audio_stream(path),
+            // The backend test source plays a sine tone.
+            None => Some(media.create_audiostream()),
+        }
+    }
+
+    fn create_video_input(&self, constraints: MediaTrackConstraintSet) -> Option<MediaStreamId> {
+        let media = ServoMedia::get().unwrap();
+        if !pref!(media.fake_devices.enabled) {
+            return media.create_videoinput_stream(constraints);
+        }
+        if fake_source_file().is_some() {
+            // XXX The media backend cannot turn a player into a MediaStream yet,
+            // so only the audio of a fake source file is played back.
+            warn!("Fake video capture from a file is not supported, using the test pattern.");
+        }
+        Some(media.create_videostream())
+    }
+
+    /// Creates an audio stream looping over the decoded contents of `path`.
+    ///
+    /// The file is decoded into a buffer source node of a private audio graph
+    /// whose output feeds the stream.
+    fn create_file_audio_stream(&self, path: PathBuf) -> Option<MediaStreamId> {
+        let data = match fs::read(&path) {
+            Ok(data) => data,
+            Err(e) => {
+                warn!("Could not read fake capture source {:?} ({:?}).", path, e);
+                return None;
+            },
+        };
+
+        let media = ServoMedia::get().unwrap();
+        let pipeline_id = self.global().pipeline_id();
+        let client_context_id =
+            ClientContextId::build(pipeline_id.namespace_id.0, pipeline_id.index.0.get());
+        let context = media.create_audio_context(
+            &client_context_id,
+            AudioContextOptions::RealTimeAudioContext(Default::default()),
+        );
+        let (socket, stream_id) = media.create_stream_and_socket(MediaStreamType::Audio);
+
+        let channel_info = || ChannelInfo {
+            count: 2,
+            mode: ChannelCountMode::Explicit,
+            interpretation: ChannelInterpretation::Speakers,
+        };
+        let source = {
+            let mut context = context.lock().unwrap();
+            let source = context.create_node(
+                AudioNodeInit::AudioBufferSourceNode(AudioBufferSourceNodeOptions {
+                    buffer: None,
+                    detune: 0.,
+                    loop_enabled: true,
+                    loop_end: None,
+                    loop_start: None,
+                    playback_rate: 1.,
+                }),
+                channel_info(),
+            );
+            let destination = context.create_node(
+                AudioNodeInit::MediaStreamDestinationNode(socket),
+                channel_info(),
+            );
+            context.connect_ports(source.output(0), destination.input(0));
+            source
+        };
+
+        // servo-media reports the channel position of each decoded chunk,
+        // which is not necessarily the index of the channel.
+        let decoded_audio = Arc::new(Mutex::new(Vec::<Vec<f32>>::new()));
+        let decoded_audio_ = decoded_audio.clone();
+        let decoded_audio__ = decoded_audio.clone();
+        let channels = Arc::new(Mutex::new(HashMap::new()));
+        let context_ = context.clone();
+        let callbacks = AudioDecoderCallbacks::new()
+            .ready(move |channel_count| {
+                decoded_audio
+                    .lock()
+                    .unwrap()
+                    .resize(channel_count as usize, Vec::new());
+            })
+            .progress(move |buffer, channel_pos_mask| {
+                let mut decoded_audio = decoded_audio_.lock().unwrap();
+                let mut channels = channels.lock().unwrap();
+                let next = channels.len();
+                let channel = *channels.entry(channel_pos_mask).or_insert(next);
+                if let Some(data) = decoded_audio.get_mut(channel) {
+                    data.extend_from_slice((*buffer).as_ref());
+                }
+            })
+            .eos(move || {
+                let decoded_audio = decoded_audio__.lock().unwrap();
+                let length = decoded_audio.first().map_or(0, |channel| channel.len());
+                let mut buffer =
+                    ServoMediaAudioBuffer::new(decoded_audio.len() as u8, length, 44100.);
+                for (i, channel) in decoded_audio.iter().enumerate() {
+                    buffer.buffers[i] = channel.clone();
+                }
+                let mut context = context_.lock().unwrap();
+                context.message_node(
+                    source,
+                    AudioNodeMessage::AudioBufferSourceNode(
+                        AudioBufferSourceNodeMessage::SetBuffer(Some(buffer)),
+                    ),
+                );
+                context.message_node(
+                    source,
+                    AudioNodeMessage::AudioScheduledSourceNode(
+                        AudioScheduledSourceNodeMessage::Start(0.),
+                    ),
+                );
+            })
+            .error(move |error| {
+                warn!("Could not decode fake capture source ({:?}).", error);
+            })
+            .build();
+
+        context.lock().unwrap().decode_audio_data(data, callbacks);
+        if let Err(e) = context.lock().unwrap().resume() {
+            warn!("Could not start fake capture source ({:?}).", e);
+            return None;
+        }
+        self.fake_audio_contexts.borrow_mut().push(context);
+        Some(stream_id)
+    }
+
+    /// The devices reported while `media.fake_devices.enabled` is set, one per
+    /// kind of fake capture source.
+    fn fake_devices(&self) -> Vec<DomRoot<MediaDeviceInfo>> {
+        let audio_label = match fake_source_file() {
+            Some(path) => format!("Fake microphone ({})", path.display()),
+            None => "Fake microphone (tone)".to_owned(),
+        };
+        [
+            ("fake-audioinput", MediaDeviceKind::Audioinput, audio_label),
+            (
+                "fake-videoinput",
+                MediaDeviceKind::Videoinput,
+                "Fake camera (test pattern)".to_owned(),
+            ),
+        ]
+        .iter()
+        .map(|(device_id, kind, label)| {
+            let kind = *kind;
+            let label = if self.may_expose_labels(kind) {
+                &**label
+            } else {
+                ""
+            };
+            MediaDeviceInfo::new(&self.global(), device_id, kind, label, FAKE_DEVICE_GROUP_ID)
+        })
+        .collect()
+    }
 }
 
 impl MediaDevicesMethods for MediaDevices {
@@ -45,16 +284,25 @@ impl MediaDevicesMethods for MediaDevices {
     #[allow(unsafe_code)]
     fn GetUserMedia(&self, constraints: &MediaStreamConstraints, comp: InRealm) -> Rc<Promise> {
         let p = Promise::new_in_current_realm(&self.global(), comp);
-        let media = ServoMedia::get().unwrap();
+        let audio = convert_constraints(&constraints.audio);
+        let video = convert_constraints(&constraints.video);
+
+        if (audio.is_some() && !self.request_permission(PermissionName::Microphone)) ||
+            (video.is_some() && !self.request_permission(PermissionName::Camera))
+        {
+            p.reject_error(Error::NotAllowed);
+            return p;
+        }
+
         let stream = MediaStream::new(&self.global());
-        if let Some(constraints) = convert_constraints(&constraints.audio) {
-            if let Some(audio) = media.create_audioinput_stream(constraints) {
+        if let Some(constraints) = audio {
+            if let Some(audio) = self.create_audio_input(constraints) {
                 let track = MediaStreamTrack::new(&self.global(), audio, MediaStreamType::Audio);
                 stream.add_track(&track);
             }
         }
-        if let Some(constraints) = convert_constraints(&constraints.video) {
-            if let Some(video) = media.create_videoinput_stream(constraints) {
+        if let Some(constraints) = video {
+            if let Some(video) = self.create_video_input(constraints) {
                 let track = MediaStreamTrack::new(&self.global(), video, MediaStreamType::Video);
                 stream.add_track(&track);
             }
@@ -76,23 +324,27 @@ impl MediaDevicesMethods for MediaDevices {
         // XXX Steps 2.1 - 2.4
 
         // Step 2.5
-        let media = ServoMedia::get().unwrap();
-        let device_monitor = media.get_device_monitor();
-        let result_list = match device_monitor.enumerate_devices() {
-            Ok(devices) => devices
-                .iter()
-                .map(|device| {
-                    // XXX The media backend has no way to group devices yet.
-                    MediaDeviceInfo::new(
-                        &self.global(),
-                        &device.device_id,
-                        device.kind.into(),
-                        &device.label,
-                        "",
-                    )
-                })
-                .collect(),
-            Err(_) => Vec::new(),
+        let result_list = if pref!(media.fake_devices.enabled) {
+            self.fake_devices()
+        } else {
+            let media = ServoMedia::get().unwrap();
+            let device_monitor = media.get_device_monitor();
+            match device_monitor.enumerate_devices() {
+                Ok(devices) => devices
+                    .iter()
+                    .map(|device| {
+                        let kind = device.kind.into();
+                        let label = if self.may_expose_labels(kind) {
+                            &*device.label
+                        } else {
+                            ""
+                        };
+                        // XXX The media backend has no way to group devices yet.
+                        MediaDeviceInfo::new(&self.global(), &device.device_id, kind, label, "")
+                    })
+                    .collect(),
+                Err(_) => Vec::new(),
+            }
         };
 
         p.resolve_native(&result_list);
@@ -102,6 +354,17 @@ impl MediaDevicesMethods for MediaDevices {
     }
 }
 
+/// The local file fake capture devices play back, if `media.fake_devices.source`
+/// names one rather than the synthetic `pattern` source.
+fn fake_source_file() -> Option<PathBuf> {
+    let source = pref!(media.fake_devices.source);
+    if source.is_empty() || source == "pattern" {
+        None
+    } else {
+        Some(PathBuf::from(source))
+    }
+}
+
 fn convert_constraints(js: &BooleanOrMediaTrackConstraints) -> Option<MediaTrackConstraintSet> {
     match js {
         BooleanOrMediaTrackConstraints::Boolean(false) => None,
@@ -95,6 +95,7 @@ impl MediaQueryListMethods for MediaQueryList {
             AddEventListenerOptions {
                 parent: EventListenerOptions { capture: false },
                 once: false,
+                signal: None,
             },
         );
     }
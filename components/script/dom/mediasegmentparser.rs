@@ -0,0 +1,822 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Splitting of the bytes appended to a `SourceBuffer` into initialization
+//! and media segments.
+//!
+//! <https://w3c.github.io/mse-byte-stream-format-registry/>
+
+use std::collections::HashMap;
+
+/// <https://w3c.github.io/mse-byte-stream-format-registry/#registry>
+#[derive(Clone, Copy, Debug, Eq, JSTraceable, MallocSizeOf, PartialEq)]
+pub enum ByteStreamFormat {
+    /// <https://w3c.github.io/mse-byte-stream-format-isobmff/>
+    IsoBmff,
+    /// <https://w3c.github.io/mse-byte-stream-format-webm/>
+    WebM,
+}
+
+impl ByteStreamFormat {
+    /// The byte stream format of a MIME type essence, if it is supported.
+    pub fn from_mime_type(essence: &str) -> Option<ByteStreamFormat> {
+        match essence {
+            "audio/mp4" | "video/mp4" => Some(ByteStreamFormat::IsoBmff),
+            "audio/webm" | "video/webm" => Some(ByteStreamFormat::WebM),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Segment {
+    /// <https://w3c.github.io/media-source/#init-segment>
+    Initialization(InitializationSegment),
+    /// <https://w3c.github.io/media-source/#media-segment>
+    Media(MediaSegment),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct InitializationSegment {
+    pub data: Vec<u8>,
+    /// The duration of the presentation in seconds, if the segment has one.
+    pub duration: Option<f64>,
+}
+
+#[derive(Clone, Debug, JSTraceable, MallocSizeOf, PartialEq)]
+pub struct MediaSegment {
+    pub data: Vec<u8>,
+    /// The presentation start time of the coded frames, in seconds.
+    pub start: f64,
+    /// The presentation end time of the coded frames, in seconds.
+    pub end: f64,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ParseError(pub &'static str);
+
+/// `Ok(None)` means that more data is needed.
+type ParseResult<T> = Result<Option<T>, ParseError>;
+
+/// <https://w3c.github.io/media-source/#segment-parser-loop>
+#[derive(JSTraceable, MallocSizeOf)]
+pub struct SegmentParser {
+    format: ByteStreamFormat,
+    /// <https://w3c.github.io/media-source/#sourcebuffer-input-buffer>
+    input: Vec<u8>,
+    /// The number of bytes of the input buffer already split into segments.
+    position: usize,
+    /// <https://w3c.github.io/media-source/#sourcebuffer-first-initialization-segment-received-flag>
+    initialization_received: bool,
+    /// Whether no more data will be appended, so that elements of unknown
+    /// size end with the input buffer.
+    end_of_stream: bool,
+    iso_bmff: IsoBmffState,
+    webm: WebMState,
+}
+
+impl SegmentParser {
+    pub fn new(format: ByteStreamFormat) -> SegmentParser {
+        SegmentParser {
+            format,
+            input: vec![],
+            position: 0,
+            initialization_received: false,
+            end_of_stream: false,
+            iso_bmff: Default::default(),
+            webm: Default::default(),
+        }
+    }
+
+    pub fn format(&self) -> ByteStreamFormat {
+        self.format
+    }
+
+    /// The number of bytes waiting in the input buffer.
+    pub fn pending_len(&self) -> usize {
+        self.input.len() - self.position
+    }
+
+    pub fn append(&mut self, data: &[u8]) {
+        self.input.extend_from_slice(data);
+    }
+
+    /// Return the segments completed by the data appended so far.
+    pub fn parse(&mut self) -> Result<Vec<Segment>, ParseError> {
+        let mut segments = vec![];
+        let result = match self.format {
+            ByteStreamFormat::IsoBmff => self.parse_iso_bmff(&mut segments),
+            ByteStreamFormat::WebM => self.parse_webm(&mut segments),
+        };
+        self.input.drain(..self.position);
+        self.position = 0;
+        result.map(|_| segments)
+    }
+
+    /// Return the segments completed by the data appended so far, knowing
+    /// that no more data will follow.
+    pub fn parse_end_of_stream(&mut self) -> Result<Vec<Segment>, ParseError> {
+        self.end_of_stream = true;
+        let result = self.parse();
+        self.end_of_stream = false;
+        result
+    }
+
+    /// <https://w3c.github.io/media-source/#sourcebuffer-reset-parser-state>
+    pub fn reset(&mut self) {
+        self.input.clear();
+        self.position = 0;
+        self.iso_bmff.pending_initialization = None;
+        self.iso_bmff.pending_media.clear();
+        self.iso_bmff.pending_fragment = None;
+        self.webm.pending_initialization = None;
+    }
+
+    fn parse_iso_bmff(&mut self, segments: &mut Vec<Segment>) -> Result<(), ParseError> {
+        loop {
+            let data = &self.input[self.position..];
+            let (box_type, header_size, size) = match read_box_header(data)? {
+                Some(header) => header,
+                None => return Ok(()),
+            };
+            if data.len() < size {
+                return Ok(());
+            }
+            let data = &data[..size];
+            let state = &mut self.iso_bmff;
+            match &box_type {
+                b"ftyp" => {
+                    if state.pending_fragment.is_some() || !state.pending_media.is_empty() {
+                        return Err(ParseError("initialization segment inside a media segment"));
+                    }
+                    state.pending_initialization = Some(data.to_vec());
+                },
+                b"moov" => {
+                    let duration = state.parse_moov(&data[header_size..])?;
+                    let mut initialization =
+                        state.pending_initialization.take().unwrap_or_default();
+                    initialization.extend_from_slice(data);
+                    segments.push(Segment::Initialization(InitializationSegment {
+                        data: initialization,
+                        duration,
+                    }));
+                    self.initialization_received = true;
+                },
+                b"moof" => {
+                    if !self.initialization_received {
+                        return Err(ParseError("media segment before an initialization segment"));
+                    }
+                    if state.pending_fragment.is_some() {
+                        return Err(ParseError("movie fragment without media data"));
+                    }
+                    state.pending_fragment = Some(state.parse_moof(&data[header_size..])?);
+                    state.pending_media.extend_from_slice(data);
+                },
+                b"mdat" => {
+                    let (start, end) = match state.pending_fragment.take() {
+                        Some(range) => range,
+                        None => return Err(ParseError("media data without a movie fragment")),
+                    };
+                    state.pending_media.extend_from_slice(data);
+                    segments.push(Segment::Media(MediaSegment {
+                        data: std::mem::replace(&mut state.pending_media, vec![]),
+                        start,
+                        end,
+                    }));
+                },
+                _ => {
+                    // Boxes such as `free`, `styp` or `sidx` are kept with
+                    // the segment they precede or belong to.
+                    if let Some(ref mut initialization) = state.pending_initialization {
+                        initialization.extend_from_slice(data);
+                    } else if self.initialization_received {
+                        state.pending_media.extend_from_slice(data);
+                    }
+                },
+            }
+            self.position += size;
+        }
+    }
+
+    fn parse_webm(&mut self, segments: &mut Vec<Segment>) -> Result<(), ParseError> {
+        loop {
+            let data = &self.input[self.position..];
+            let (id, header_size, size) = match read_element_header(data)? {
+                Some(header) => header,
+                None => return Ok(()),
+            };
+            let state = &mut self.webm;
+
+            if id == SEGMENT {
+                // Only the header of the Segment element is consumed, its
+                // children are parsed as top-level elements.
+                match state.pending_initialization {
+                    Some(ref mut initialization) => {
+                        initialization.extend_from_slice(&data[..header_size])
+                    },
+                    None => return Err(ParseError("Segment element without an EBML header")),
+                }
+                self.position += header_size;
+                continue;
+            }
+
+            let length = match size {
+                Some(size) => match (size as usize).checked_add(header_size) {
+                    Some(length) if length <= data.len() => length,
+                    Some(_) => return Ok(()),
+                    None => return Err(ParseError("invalid element size")),
+                },
+                None if id == CLUSTER => {
+                    match find_cluster_end(&data[header_size..], self.end_of_stream)? {
+                        Some(length) => header_size + length,
+                        None => return Ok(()),
+                    }
+                },
+                None => return Err(ParseError("element of unknown size")),
+            };
+            let data = &data[..length];
+
+            match id {
+                EBML_HEADER => state.pending_initialization = Some(data.to_vec()),
+                INFO => {
+                    state.parse_info(&data[header_size..])?;
+                    if let Some(ref mut initialization) = state.pending_initialization {
+                        initialization.extend_from_slice(data);
+                    }
+                },
+                TRACKS => {
+                    let mut initialization = match state.pending_initialization.take() {
+                        Some(initialization) => initialization,
+                        None => return Err(ParseError("Tracks element without an EBML header")),
+                    };
+                    initialization.extend_from_slice(data);
+                    segments.push(Segment::Initialization(InitializationSegment {
+                        data: initialization,
+                        duration: state.duration,
+                    }));
+                    self.initialization_received = true;
+                },
+                CLUSTER => {
+                    if !self.initialization_received {
+                        return Err(ParseError("media segment before an initialization segment"));
+                    }
+                    let (start, end) = state.parse_cluster(&data[header_size..])?;
+                    segments.push(Segment::Media(MediaSegment {
+                        data: data.to_vec(),
+                        start,
+                        end,
+                    }));
+                },
+                _ => {
+                    if let Some(ref mut initialization) = state.pending_initialization {
+                        initialization.extend_from_slice(data);
+                    }
+                },
+            }
+            self.position += length;
+        }
+    }
+}
+
+/// A cursor over the content of a complete box or element, where running out
+/// of data is an error.
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Reader<'a> {
+        Reader { data, position: 0 }
+    }
+
+    fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], ParseError> {
+        if self.data.len() - self.position < length {
+            return Err(ParseError("truncated box"));
+        }
+        let bytes = &self.data[self.position..self.position + length];
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, length: usize) -> Result<(), ParseError> {
+        self.read_bytes(length).map(|_| ())
+    }
+
+    fn read_u8(&mut self) -> Result<u8, ParseError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_u32(&mut self) -> Result<u32, ParseError> {
+        let bytes = self.read_bytes(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, ParseError> {
+        Ok((self.read_u32()? as u64) << 32 | self.read_u32()? as u64)
+    }
+
+    /// Read the version and flags of a full box.
+    fn read_version_and_flags(&mut self) -> Result<(u8, u32), ParseError> {
+        let version = self.read_u8()?;
+        let flags = self.read_bytes(3)?;
+        Ok((
+            version,
+            (flags[0] as u32) << 16 | (flags[1] as u32) << 8 | flags[2] as u32,
+        ))
+    }
+
+    /// Read a value that is 64 bits wide in version 1 of a full box, and 32
+    /// bits wide otherwise.
+    fn read_versioned(&mut self, version: u8) -> Result<u64, ParseError> {
+        if version == 1 {
+            self.read_u64()
+        } else {
+            self.read_u32().map(|value| value as u64)
+        }
+    }
+}
+
+/// Read the header of a box, returning its type, the size of the header and
+/// the size of the whole box.
+fn read_box_header(data: &[u8]) -> ParseResult<([u8; 4], usize, usize)> {
+    if data.len() < 8 {
+        return Ok(None);
+    }
+    let mut reader = Reader::new(data);
+    let size = reader.read_u32()? as u64;
+    let mut box_type = [0; 4];
+    box_type.copy_from_slice(reader.read_bytes(4)?);
+    let (header_size, size) = match size {
+        0 => return Err(ParseError("box extending to the end of the stream")),
+        1 if data.len() < 16 => return Ok(None),
+        1 => (16, reader.read_u64()?),
+        size => (8, size),
+    };
+    if size < header_size as u64 || size > usize::max_value() as u64 {
+        return Err(ParseError("invalid box size"));
+    }
+    Ok(Some((box_type, header_size, size as usize)))
+}
+
+/// Split the content of a box into its children, returning the type and
+/// content of each.
+fn child_boxes(data: &[u8]) -> Result<Vec<([u8; 4], &[u8])>, ParseError> {
+    let mut children = vec![];
+    let mut position = 0;
+    while position < data.len() {
+        match read_box_header(&data[position..])? {
+            Some((box_type, header_size, size)) if size <= data.len() - position => {
+                children.push((box_type, &data[position + header_size..position + size]));
+                position += size;
+            },
+            _ => return Err(ParseError("truncated box")),
+        }
+    }
+    Ok(children)
+}
+
+#[derive(Default, JSTraceable, MallocSizeOf)]
+struct IsoBmffState {
+    /// The timescale of each track, by track ID.
+    timescales: HashMap<u32, u32>,
+    /// The default sample duration of each track, by track ID.
+    default_sample_durations: HashMap<u32, u32>,
+    /// The boxes of an initialization segment waiting for its `moov` box.
+    pending_initialization: Option<Vec<u8>>,
+    /// The boxes of a media segment waiting for its `mdat` box.
+    pending_media: Vec<u8>,
+    /// The time range of the last `moof` box, waiting for its `mdat` box.
+    pending_fragment: Option<(f64, f64)>,
+}
+
+impl IsoBmffState {
+    /// Parse a `moov` box, returning the duration of the presentation.
+    fn parse_moov(&mut self, moov: &[u8]) -> Result<Option<f64>, ParseError> {
+        let mut timescale = 0;
+        let mut duration = None;
+        let mut fragment_duration = None;
+        for (box_type, content) in child_boxes(moov)? {
+            match &box_type {
+                b"mvhd" => {
+                    let mut reader = Reader::new(content);
+                    let (version, _) = reader.read_version_and_flags()?;
+                    // Creation and modification times.
+                    reader.skip(if version == 1 { 16 } else { 8 })?;
+                    timescale = reader.read_u32()?;
+                    duration = Some(reader.read_versioned(version)?);
+                },
+                b"trak" => self.parse_trak(content)?,
+                b"mvex" => {
+                    for (box_type, content) in child_boxes(content)? {
+                        let mut reader = Reader::new(content);
+                        match &box_type {
+                            b"mehd" => {
+                                let (version, _) = reader.read_version_and_flags()?;
+                                fragment_duration = Some(reader.read_versioned(version)?);
+                            },
+                            b"trex" => {
+                                reader.read_version_and_flags()?;
+                                let track_id = reader.read_u32()?;
+                                // Default sample description index.
+                                reader.skip(4)?;
+                                let default_sample_duration = reader.read_u32()?;
+                                self.default_sample_durations
+                                    .insert(track_id, default_sample_duration);
+                            },
+                            _ => {},
+                        }
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        // An unknown duration is either zero or all ones.
+        let duration = fragment_duration
+            .or(duration)
+            .filter(|&duration| duration != 0 && duration != u32::max_value() as u64)
+            .filter(|&duration| duration != u64::max_value());
+        Ok(match duration {
+            Some(duration) if timescale != 0 => Some(duration as f64 / timescale as f64),
+            _ => None,
+        })
+    }
+
+    fn parse_trak(&mut self, trak: &[u8]) -> Result<(), ParseError> {
+        let mut track_id = None;
+        let mut timescale = None;
+        for (box_type, content) in child_boxes(trak)? {
+            match &box_type {
+                b"tkhd" => {
+                    let mut reader = Reader::new(content);
+                    let (version, _) = reader.read_version_and_flags()?;
+                    reader.skip(if version == 1 { 16 } else { 8 })?;
+                    track_id = Some(reader.read_u32()?);
+                },
+                b"mdia" => {
+                    for (box_type, content) in child_boxes(content)? {
+                        if &box_type == b"mdhd" {
+                            let mut reader = Reader::new(content);
+                            let (version, _) = reader.read_version_and_flags()?;
+                            reader.skip(if version == 1 { 16 } else { 8 })?;
+                            timescale = Some(reader.read_u32()?);
+                        }
+                    }
+                },
+                _ => {},
+            }
+        }
+        match (track_id, timescale) {
+            (Some(track_id), Some(timescale)) if timescale != 0 => {
+                self.timescales.insert(track_id, timescale);
+                Ok(())
+            },
+            _ => Err(ParseError("track without an ID or a timescale")),
+        }
+    }
+
+    /// Parse a `moof` box, returning the presentation time range of its
+    /// samples in seconds.
+    fn parse_moof(&self, moof: &[u8]) -> Result<(f64, f64), ParseError> {
+        let mut range: Option<(f64, f64)> = None;
+        for (box_type, traf) in child_boxes(moof)? {
+            if &box_type != b"traf" {
+                continue;
+            }
+
+            let mut track_id = None;
+            let mut default_duration = None;
+            let mut decode_time = 0;
+            let mut runs = vec![];
+            for (box_type, content) in child_boxes(traf)? {
+                let mut reader = Reader::new(content);
+                match &box_type {
+                    b"tfhd" => {
+                        let (_, flags) = reader.read_version_and_flags()?;
+                        track_id = Some(reader.read_u32()?);
+                        if flags & 0x1 != 0 {
+                            // Base data offset.
+                            reader.skip(8)?;
+                        }
+                        if flags & 0x2 != 0 {
+                            // Sample description index.
+                            reader.skip(4)?;
+                        }
+                        if flags & 0x8 != 0 {
+                            default_duration = Some(reader.read_u32()?);
+                        }
+                    },
+                    b"tfdt" => {
+                        let (version, _) = reader.read_version_and_flags()?;
+                        decode_time = reader.read_versioned(version)? as i64;
+                    },
+                    b"trun" => runs.push(content),
+                    _ => {},
+                }
+            }
+
+            let track_id = track_id.ok_or(ParseError("track fragment without a header"))?;
+            let timescale = *self
+                .timescales
+                .get(&track_id)
+                .ok_or(ParseError("track fragment for an unknown track"))?
+                as f64;
+            let default_duration = default_duration
+                .or_else(|| self.default_sample_durations.get(&track_id).cloned())
+                .unwrap_or(0);
+
+            for run in runs {
+                let mut reader = Reader::new(run);
+                let (version, flags) = reader.read_version_and_flags()?;
+                let sample_count = reader.read_u32()?;
+                if flags & 0x1 != 0 {
+                    // Data offset.
+                    reader.skip(4)?;
+                }
+                if flags & 0x4 != 0 {
+                    // First sample flags.
+                    reader.skip(4)?;
+                }
+                for _ in 0..sample_count {
+                    let duration = if flags & 0x100 != 0 {
+                        reader.read_u32()?
+                    } else {
+                        default_duration
+                    } as i64;
+                    if flags & 0x200 != 0 {
+                        // Sample size.
+                        reader.skip(4)?;
+                    }
+                    if flags & 0x400 != 0 {
+                        // Sample flags.
+                        reader.skip(4)?;
+                    }
+                    let composition_offset = if flags & 0x800 == 0 {
+                        0
+                    } else if version == 0 {
+                        reader.read_u32()? as i64
+                    } else {
+                        reader.read_u32()? as i32 as i64
+                    };
+
+                    let start = (decode_time + composition_offset) as f64 / timescale;
+                    let end = (decode_time + composition_offset + duration) as f64 / timescale;
+                    range = Some(match range {
+                        Some((range_start, range_end)) => {
+                            (range_start.min(start), range_end.max(end))
+                        },
+                        None => (start, end),
+                    });
+                    decode_time += duration;
+                }
+            }
+        }
+        Ok(range.unwrap_or((0., 0.)))
+    }
+}
+
+const EBML_HEADER: u32 = 0x1A45_DFA3;
+const SEGMENT: u32 = 0x1853_8067;
+const SEEK_HEAD: u32 = 0x114D_9B74;
+const INFO: u32 = 0x1549_A966;
+const TIMECODE_SCALE: u32 = 0x2A_D7B1;
+const DURATION: u32 = 0x4489;
+const TRACKS: u32 = 0x1654_AE6B;
+const CLUSTER: u32 = 0x1F43_B675;
+const TIMECODE: u32 = 0xE7;
+const SIMPLE_BLOCK: u32 = 0xA3;
+const BLOCK_GROUP: u32 = 0xA0;
+const BLOCK: u32 = 0xA1;
+const BLOCK_DURATION: u32 = 0x9B;
+const CUES: u32 = 0x1C53_BB6B;
+const CHAPTERS: u32 = 0x1043_A770;
+const TAGS: u32 = 0x1254_C367;
+const ATTACHMENTS: u32 = 0x1941_A469;
+
+/// Whether an element is a child of the Segment element, which ends a
+/// Cluster element of unknown size.
+fn is_top_level_element(id: u32) -> bool {
+    match id {
+        EBML_HEADER | SEGMENT | SEEK_HEAD | INFO | TRACKS | CLUSTER | CUES | CHAPTERS | TAGS |
+        ATTACHMENTS => true,
+        _ => false,
+    }
+}
+
+/// Read a variable size integer, returning its value and length. Element IDs
+/// keep their length marker, while sizes don't.
+fn read_vint(data: &[u8], keep_marker: bool, max_length: usize) -> ParseResult<(u64, usize)> {
+    let first = match data.first() {
+        Some(&first) => first,
+        None => return Ok(None),
+    };
+    let length = first.leading_zeros() as usize + 1;
+    if length > max_length {
+        return Err(ParseError("invalid variable size integer"));
+    }
+    if data.len() < length {
+        return Ok(None);
+    }
+    let mut value = if keep_marker {
+        first as u64
+    } else {
+        first as u64 & ((1 << (8 - length)) - 1)
+    };
+    for &byte in &data[1..length] {
+        value = value << 8 | byte as u64;
+    }
+    Ok(Some((value, length)))
+}
+
+/// Read the header of an element, returning its ID, the size of the header
+/// and the size of its content, which is `None` when unknown.
+fn read_element_header(data: &[u8]) -> ParseResult<(u32, usize, Option<u64>)> {
+    let (id, id_length) = match read_vint(data, true, 4)? {
+        Some(id) => id,
+        None => return Ok(None),
+    };
+    let (size, size_length) = match read_vint(&data[id_length..], false, 8)? {
+        Some(size) => size,
+        None => return Ok(None),
+    };
+    let unknown_size = (1 << (7 * size_length)) - 1;
+    Ok(Some((
+        id as u32,
+        id_length + size_length,
+        if size == unknown_size {
+            None
+        } else {
+            Some(size)
+        },
+    )))
+}
+
+/// Split the content of an element into its children, returning the ID and
+/// content of each.
+fn child_elements(data: &[u8]) -> Result<Vec<(u32, &[u8])>, ParseError> {
+    let mut children = vec![];
+    let mut position = 0;
+    while position < data.len() {
+        let (id, header_size, size) = match read_element_header(&data[position..])? {
+            Some((id, header_size, Some(size))) => (id, header_size, size),
+            Some(_) => return Err(ParseError("element of unknown size")),
+            None => return Err(ParseError("truncated element")),
+        };
+        if size > (data.len() - position - header_size) as u64 {
+            return Err(ParseError("truncated element"));
+        }
+        let start = position + header_size;
+        position = start + size as usize;
+        children.push((id, &data[start..position]));
+    }
+    Ok(children)
+}
+
+/// Find the length of the content of a Cluster element of unknown size.
+fn find_cluster_end(data: &[u8], end_of_stream: bool) -> ParseResult<usize> {
+    let mut position = 0;
+    loop {
+        let (id, header_size, size) = match read_element_header(&data[position..])? {
+            Some(header) => header,
+            None if end_of_stream && position == data.len() => return Ok(Some(position)),
+            None => return Ok(None),
+        };
+        if is_top_level_element(id) {
+            return Ok(Some(position));
+        }
+        let size = size.ok_or(ParseError("element of unknown size"))?;
+        if size > (data.len() - position - header_size) as u64 {
+            return Ok(None);
+        }
+        position += header_size + size as usize;
+    }
+}
+
+fn read_uint(data: &[u8]) -> Result<u64, ParseError> {
+    if data.len() > 8 {
+        return Err(ParseError("invalid unsigned integer"));
+    }
+    Ok(data.iter().fold(0, |value, &byte| value << 8 | byte as u64))
+}
+
+fn read_float(data: &[u8]) -> Result<f64, ParseError> {
+    match data.len() {
+        0 => Ok(0.),
+        4 => Ok(f32::from_bits(read_uint(data)? as u32) as f64),
+        8 => Ok(f64::from_bits(read_uint(data)?)),
+        _ => Err(ParseError("invalid float")),
+    }
+}
+
+#[derive(JSTraceable, MallocSizeOf)]
+struct WebMState {
+    /// The duration of a tick, in nanoseconds.
+    timecode_scale: u64,
+    /// The duration of the presentation in seconds, from the Info element.
+    duration: Option<f64>,
+    /// The elements of an initialization segment waiting for its Tracks
+    /// element.
+    pending_initialization: Option<Vec<u8>>,
+}
+
+impl Default for WebMState {
+    fn default() -> WebMState {
+        WebMState {
+            timecode_scale: 1_000_000,
+            duration: None,
+            pending_initialization: None,
+        }
+    }
+}
+
+impl WebMState {
+    fn seconds(&self, ticks: f64) -> f64 {
+        ticks * self.timecode_scale as f64 / 1e9
+    }
+
+    fn parse_info(&mut self, info: &[u8]) -> Result<(), ParseError> {
+        let mut duration = None;
+        for (id, content) in child_elements(info)? {
+            match id {
+                TIMECODE_SCALE => self.timecode_scale = read_uint(content)?,
+                DURATION => duration = Some(read_float(content)?),
+                _ => {},
+            }
+        }
+        if self.timecode_scale == 0 {
+            return Err(ParseError("invalid timecode scale"));
+        }
+        self.duration = duration.map(|duration| self.seconds(duration));
+        Ok(())
+    }
+
+    /// Parse the content of a Cluster element, returning the presentation
+    /// time range of its blocks in seconds.
+    fn parse_cluster(&self, cluster: &[u8]) -> Result<(f64, f64), ParseError> {
+        let mut timecode = None;
+        // The track number, timecode and duration of each block.
+        let mut blocks = vec![];
+        for (id, content) in child_elements(cluster)? {
+            match id {
+                TIMECODE => timecode = Some(read_uint(content)? as i64),
+                SIMPLE_BLOCK => blocks.push(parse_block(content, None)?),
+                BLOCK_GROUP => {
+                    let mut block = None;
+                    let mut duration = None;
+                    for (id, content) in child_elements(content)? {
+                        match id {
+                            BLOCK => block = Some(content),
+                            BLOCK_DURATION => duration = Some(read_uint(content)? as i64),
+                            _ => {},
+                        }
+                    }
+                    if let Some(block) = block {
+                        blocks.push(parse_block(block, duration)?);
+                    }
+                },
+                _ => {},
+            }
+        }
+        let timecode = timecode.ok_or(ParseError("cluster without a timecode"))?;
+
+        let mut range: Option<(i64, i64)> = None;
+        for &(track, relative_timecode, duration) in &blocks {
+            // Blocks without a duration last until the next block of their
+            // track, or as long as the previous one for the last block.
+            let duration = duration.unwrap_or_else(|| {
+                let same_track = blocks.iter().filter(|block| block.0 == track);
+                let next = same_track
+                    .clone()
+                    .skip_while(|block| block.1 <= relative_timecode)
+                    .next();
+                let previous = same_track.rev().find(|block| block.1 < relative_timecode);
+                match (previous, next) {
+                    (_, Some(next)) => next.1 - relative_timecode,
+                    (Some(previous), None) => relative_timecode - previous.1,
+                    (None, None) => 0,
+                }
+            });
+            let start = timecode + relative_timecode;
+            let end = start + duration;
+            range = Some(match range {
+                Some((range_start, range_end)) => (range_start.min(start), range_end.max(end)),
+                None => (start, end),
+            });
+        }
+        let (start, end) = range.unwrap_or((timecode, timecode));
+        Ok((self.seconds(start as f64), self.seconds(end as f64)))
+    }
+}
+
+/// Parse the header of a Block or SimpleBlock element, returning its track
+/// number, its timecode relative to its cluster and its duration.
+fn parse_block(data: &[u8], duration: Option<i64>) -> Result<(u64, i64, Option<i64>), ParseError> {
+    let (track, length) = read_vint(data, false, 8)?.ok_or(ParseError("truncated block"))?;
+    if data.len() < length + 2 {
+        return Err(ParseError("truncated block"));
+    }
+    let relative_timecode = i16::from_be_bytes([data[length], data[length + 1]]) as i64;
+    Ok((track, relative_timecode, duration))
+}
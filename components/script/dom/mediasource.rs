@@ -0,0 +1,438 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::MediaErrorBinding::MediaErrorConstants::*;
+use crate::dom::bindings::codegen::Bindings::MediaSourceBinding::{
+    EndOfStreamError, MediaSourceMethods, ReadyState,
+};
+use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject};
+use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
+use crate::dom::bindings::str::DOMString;
+use crate::dom::eventtarget::EventTarget;
+use crate::dom::htmlmediaelement::HTMLMediaElement;
+use crate::dom::mediasegmentparser::ByteStreamFormat;
+use crate::dom::sourcebuffer::SourceBuffer;
+use crate::dom::sourcebufferlist::SourceBufferList;
+use crate::dom::timeranges::TimeRangesContainer;
+use crate::dom::window::Window;
+use crate::task_source::TaskSource;
+use dom_struct::dom_struct;
+use mime::Mime;
+use servo_atoms::Atom;
+use servo_media::{ServoMedia, SupportsMediaType};
+use std::cell::Cell;
+use std::f64;
+
+#[dom_struct]
+pub struct MediaSource {
+    eventtarget: EventTarget,
+    /// <https://w3c.github.io/media-source/#dom-mediasource-sourcebuffers>
+    source_buffers: Dom<SourceBufferList>,
+    /// <https://w3c.github.io/media-source/#dom-mediasource-activesourcebuffers>
+    active_source_buffers: Dom<SourceBufferList>,
+    /// <https://w3c.github.io/media-source/#dom-mediasource-readystate>
+    ready_state: Cell<ReadyState>,
+    /// <https://w3c.github.io/media-source/#dom-mediasource-duration>
+    duration: Cell<f64>,
+    /// The media element this media source is attached to, if any.
+    media_element: MutNullableDom<HTMLMediaElement>,
+}
+
+impl MediaSource {
+    fn new_inherited(window: &Window) -> MediaSource {
+        MediaSource {
+            eventtarget: EventTarget::new_inherited(),
+            source_buffers: Dom::from_ref(&*SourceBufferList::new(window)),
+            active_source_buffers: Dom::from_ref(&*SourceBufferList::new(window)),
+            ready_state: Cell::new(ReadyState::Closed),
+            duration: Cell::new(f64::NAN),
+            media_element: Default::default(),
+        }
+    }
+
+    pub fn new(window: &Window) -> DomRoot<MediaSource> {
+        reflect_dom_object(Box::new(MediaSource::new_inherited(window)), window)
+    }
+
+    // https://w3c.github.io/media-source/#dom-mediasource-constructor
+    pub fn Constructor(window: &Window) -> DomRoot<MediaSource> {
+        MediaSource::new(window)
+    }
+
+    // https://w3c.github.io/media-source/#dom-mediasource-istypesupported
+    pub fn IsTypeSupported(_window: &Window, type_: DOMString) -> bool {
+        supported_format(&type_).is_some()
+    }
+
+    pub fn ready_state(&self) -> ReadyState {
+        self.ready_state.get()
+    }
+
+    pub fn media_element(&self) -> Option<DomRoot<HTMLMediaElement>> {
+        self.media_element.get()
+    }
+
+    pub fn active_source_buffers(&self) -> &SourceBufferList {
+        &self.active_source_buffers
+    }
+
+    /// Attach this media source to a media element, returning false if it is
+    /// already attached to one.
+    /// <https://w3c.github.io/media-source/#mediasource-attach>
+    pub fn attach(&self, media_element: &HTMLMediaElement) -> bool {
+        if self.ready_state.get() != ReadyState::Closed {
+            return false;
+        }
+        self.media_element.set(Some(media_element));
+        self.ready_state.set(ReadyState::Open);
+        self.queue_event(atom!("sourceopen"));
+        true
+    }
+
+    /// <https://w3c.github.io/media-source/#mediasource-detach>
+    pub fn detach(&self) {
+        // Step 1.
+        self.ready_state.set(ReadyState::Closed);
+
+        // Step 2.
+        self.duration.set(f64::NAN);
+
+        // Steps 3-4.
+        self.active_source_buffers.clear();
+
+        // Steps 5-6.
+        for buffer in self.source_buffers.buffers() {
+            buffer.detach();
+        }
+        self.source_buffers.clear();
+
+        // Step 7.
+        self.queue_event(atom!("sourceclose"));
+
+        self.media_element.set(None);
+    }
+
+    /// Set the ready state to "open" if it is "ended", as done before
+    /// appending or removing data.
+    pub fn open_if_ended(&self) {
+        if self.ready_state.get() == ReadyState::Ended {
+            self.ready_state.set(ReadyState::Open);
+            self.queue_event(atom!("sourceopen"));
+        }
+    }
+
+    /// <https://w3c.github.io/media-source/#duration-change-algorithm>
+    pub fn change_duration(&self, new_duration: f64) -> ErrorResult {
+        // Step 1.
+        if self.duration.get() == new_duration {
+            return Ok(());
+        }
+
+        // Step 2.
+        if new_duration < self.highest_end_time() {
+            return Err(Error::InvalidState);
+        }
+
+        // Steps 3-4.
+        self.duration.set(new_duration);
+
+        // Steps 5-7.
+        if let Some(media_element) = self.media_element.get() {
+            media_element.change_duration(new_duration);
+        }
+        Ok(())
+    }
+
+    /// <https://w3c.github.io/media-source/#end-of-stream-algorithm>
+    pub fn end_of_stream(&self, error: Option<EndOfStreamError>) {
+        // Step 1.
+        self.ready_state.set(ReadyState::Ended);
+
+        // Step 2.
+        self.queue_event(atom!("sourceended"));
+
+        // Step 3.
+        let media_element = match self.media_element.get() {
+            Some(media_element) => media_element,
+            None => return,
+        };
+        match error {
+            None => {
+                let _ = self.change_duration(self.highest_end_time());
+                media_element.end_media_source_stream();
+            },
+            Some(EndOfStreamError::Network) => media_element.media_source_failed(MEDIA_ERR_NETWORK),
+            Some(EndOfStreamError::Decode) => media_element.media_source_failed(MEDIA_ERR_DECODE),
+        }
+    }
+
+    /// Hold back the data appended to the source buffers while the media
+    /// element's player seeks.
+    pub fn flush(&self) {
+        for buffer in self.source_buffers.buffers() {
+            buffer.flush();
+        }
+    }
+
+    /// Push the data needed to resume playback at `time` to the media
+    /// element's player again, after it seeked.
+    pub fn push_from(&self, time: f64) {
+        for buffer in self.active_source_buffers.buffers() {
+            buffer.push_from(time);
+        }
+    }
+
+    /// <https://w3c.github.io/media-source/#htmlmediaelement-extensions-buffered>
+    pub fn buffered(&self) -> TimeRangesContainer {
+        let buffers = self.active_source_buffers.buffers();
+        let mut container = TimeRangesContainer::new();
+
+        // Step 2.1.
+        if buffers.is_empty() {
+            return container;
+        }
+
+        // Steps 2.2-2.3.
+        let highest_end_time = self.highest_end_time();
+        let mut intersection = vec![(0., highest_end_time)];
+
+        // Step 2.4.
+        for buffer in buffers {
+            let mut ranges = buffer.buffered_ranges();
+            if self.ready_state.get() == ReadyState::Ended {
+                if let Some(last) = ranges.last_mut() {
+                    last.1 = highest_end_time;
+                }
+            }
+            intersection = intersect_ranges(&intersection, &ranges);
+        }
+
+        for (start, end) in intersection {
+            let _ = container.add(start, end);
+        }
+        container
+    }
+
+    /// The highest end time of the buffered ranges of the active source
+    /// buffers.
+    fn highest_end_time(&self) -> f64 {
+        self.active_source_buffers
+            .buffers()
+            .iter()
+            .filter_map(|buffer| buffer.buffered_ranges().last().map(|range| range.1))
+            .fold(0., f64::max)
+    }
+
+    fn queue_event(&self, name: Atom) {
+        let window = self.global();
+        let window = window.as_window();
+        window
+            .task_manager()
+            .media_element_task_source()
+            .queue_simple_event(self.upcast(), name, window);
+    }
+}
+
+impl MediaSourceMethods for MediaSource {
+    // https://w3c.github.io/media-source/#dom-mediasource-sourcebuffers
+    fn SourceBuffers(&self) -> DomRoot<SourceBufferList> {
+        DomRoot::from_ref(&*self.source_buffers)
+    }
+
+    // https://w3c.github.io/media-source/#dom-mediasource-activesourcebuffers
+    fn ActiveSourceBuffers(&self) -> DomRoot<SourceBufferList> {
+        DomRoot::from_ref(&*self.active_source_buffers)
+    }
+
+    // https://w3c.github.io/media-source/#dom-mediasource-readystate
+    fn ReadyState(&self) -> ReadyState {
+        self.ready_state.get()
+    }
+
+    // https://w3c.github.io/media-source/#dom-mediasource-duration
+    fn Duration(&self) -> f64 {
+        if self.ready_state.get() == ReadyState::Closed {
+            return f64::NAN;
+        }
+        self.duration.get()
+    }
+
+    // https://w3c.github.io/media-source/#dom-mediasource-duration
+    fn SetDuration(&self, value: f64) -> ErrorResult {
+        // Step 1.
+        if value < 0. || value.is_nan() {
+            return Err(Error::Type("Duration must be a positive number".to_owned()));
+        }
+
+        // Step 2.
+        if self.ready_state.get() != ReadyState::Open {
+            return Err(Error::InvalidState);
+        }
+
+        // Step 3.
+        if self
+            .source_buffers
+            .buffers()
+            .iter()
+            .any(|b| b.is_updating())
+        {
+            return Err(Error::InvalidState);
+        }
+
+        // Step 4.
+        self.change_duration(value)
+    }
+
+    // https://w3c.github.io/media-source/#dom-mediasource-onsourceopen
+    event_handler!(sourceopen, GetOnsourceopen, SetOnsourceopen);
+
+    // https://w3c.github.io/media-source/#dom-mediasource-onsourceended
+    event_handler!(sourceended, GetOnsourceended, SetOnsourceended);
+
+    // https://w3c.github.io/media-source/#dom-mediasource-onsourceclose
+    event_handler!(sourceclose, GetOnsourceclose, SetOnsourceclose);
+
+    // https://w3c.github.io/media-source/#dom-mediasource-addsourcebuffer
+    fn AddSourceBuffer(&self, type_: DOMString) -> Fallible<DomRoot<SourceBuffer>> {
+        // Step 1.
+        if type_.is_empty() {
+            return Err(Error::Type("Empty MIME type".to_owned()));
+        }
+
+        // Step 2.
+        let format = supported_format(&type_).ok_or(Error::NotSupported)?;
+
+        // Step 3.
+        // The player is fed a single byte stream, so there can only be one
+        // source buffer, as reported by `isTypeSupported` for types with
+        // only audio or only video codecs.
+        if !self.source_buffers.is_empty() {
+            return Err(Error::QuotaExceeded);
+        }
+
+        // Step 4.
+        if self.ready_state.get() != ReadyState::Open {
+            return Err(Error::InvalidState);
+        }
+
+        // Steps 5-7.
+        let buffer = SourceBuffer::new(self.global().as_window(), self, format);
+        self.source_buffers.add(&buffer);
+        Ok(buffer)
+    }
+
+    // https://w3c.github.io/media-source/#dom-mediasource-removesourcebuffer
+    fn RemoveSourceBuffer(&self, source_buffer: &SourceBuffer) -> ErrorResult {
+        // Step 1.
+        if !self.source_buffers.contains(source_buffer) {
+            return Err(Error::NotFound);
+        }
+
+        // Step 2.
+        source_buffer.abort_buffer_append();
+
+        // Steps 3-5.
+        // Audio, video and text tracks aren't supported.
+
+        // Step 6.
+        self.active_source_buffers.remove(source_buffer);
+
+        // Step 7.
+        self.source_buffers.remove(source_buffer);
+
+        // Step 8.
+        source_buffer.detach();
+        Ok(())
+    }
+
+    // https://w3c.github.io/media-source/#dom-mediasource-endofstream
+    fn EndOfStream(&self, error: Option<EndOfStreamError>) -> ErrorResult {
+        // Step 1.
+        if self.ready_state.get() != ReadyState::Open {
+            return Err(Error::InvalidState);
+        }
+
+        // Step 2.
+        if self
+            .source_buffers
+            .buffers()
+            .iter()
+            .any(|b| b.is_updating())
+        {
+            return Err(Error::InvalidState);
+        }
+
+        // Step 3.
+        self.end_of_stream(error);
+        Ok(())
+    }
+}
+
+/// Codecs of audio tracks, by the part of their name before the first dot.
+const AUDIO_CODECS: &[&str] = &["mp4a", "opus", "vorbis", "flac", "mp3", "ac-3", "ec-3"];
+
+/// Codecs of video tracks, by the part of their name before the first dot.
+const VIDEO_CODECS: &[&str] = &[
+    "avc1", "avc3", "hev1", "hvc1", "vp8", "vp9", "vp09", "av01", "theora",
+];
+
+/// The byte stream format of a MIME type, if both the segment parser and the
+/// media backend support it.
+fn supported_format(type_: &str) -> Option<ByteStreamFormat> {
+    let mime = type_.parse::<Mime>().ok()?;
+    let format = ByteStreamFormat::from_mime_type(mime.essence_str())?;
+    if !has_audio_and_video_codecs(&mime) {
+        return None;
+    }
+    match ServoMedia::get().unwrap().can_play_type(type_) {
+        SupportsMediaType::No => None,
+        _ => Some(format),
+    }
+}
+
+/// Whether the codecs of a MIME type don't describe only audio tracks or only
+/// video tracks. The player is fed the byte stream of a single source buffer,
+/// so such a presentation would need another source buffer for the other kind
+/// of track, which `addSourceBuffer` refuses.
+fn has_audio_and_video_codecs(mime: &Mime) -> bool {
+    let codecs = match mime.get_param("codecs") {
+        Some(codecs) => codecs,
+        None => return true,
+    };
+    let (mut audio, mut video) = (false, false);
+    for codec in codecs.as_str().trim_matches('"').split(',') {
+        let codec = codec.trim();
+        let family = codec.split('.').next().unwrap_or(codec);
+        if AUDIO_CODECS.contains(&family) {
+            audio = true;
+        } else if VIDEO_CODECS.contains(&family) {
+            video = true;
+        } else {
+            // Leave codecs we don't know about to the media backend.
+            return true;
+        }
+    }
+    audio && video
+}
+
+/// The intersection of two lists of sorted and disjoint time ranges.
+fn intersect_ranges(a: &[(f64, f64)], b: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let mut intersection = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        let start = a[i].0.max(b[j].0);
+        let end = a[i].1.min(b[j].1);
+        if start < end {
+            intersection.push((start, end));
+        }
+        if a[i].1 < b[j].1 {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    intersection
+}
@@ -209,22 +209,35 @@ pub mod types {
     include!(concat!(env!("OUT_DIR"), "/InterfaceTypes.rs"));
 }
 
+pub mod abortcontroller;
+pub mod abortsignal;
 pub mod abstractworker;
 pub mod abstractworkerglobalscope;
 pub mod activation;
 pub mod analysernode;
+pub mod animation;
+pub mod animationeffect;
 pub mod animationevent;
+pub mod animationplaybackevent;
+pub mod animationtimeline;
 pub mod attr;
 pub mod audiobuffer;
 pub mod audiobuffersourcenode;
 pub mod audiocontext;
+pub mod audiodata;
+pub mod audiodecoder;
 pub mod audiodestinationnode;
 pub mod audiolistener;
 pub mod audionode;
 pub mod audioparam;
+pub mod audioparammap;
 pub mod audioscheduledsourcenode;
 pub mod audiotrack;
 pub mod audiotracklist;
+pub mod audioworklet;
+pub mod audioworkletglobalscope;
+pub mod audioworkletnode;
+pub mod audioworkletprocessor;
 pub mod baseaudiocontext;
 pub mod beforeunloadevent;
 pub mod bindings;
@@ -241,6 +254,9 @@ pub mod bluetoothremotegattserver;
 pub mod bluetoothremotegattservice;
 pub mod bluetoothuuid;
 pub mod broadcastchannel;
+pub mod bytelengthqueuingstrategy;
+pub mod cache;
+pub mod cachestorage;
 pub mod canvasgradient;
 pub mod canvaspattern;
 pub mod canvasrenderingcontext2d;
@@ -252,10 +268,14 @@ pub mod client;
 pub mod closeevent;
 pub mod comment;
 pub mod compositionevent;
+pub mod compressionstream;
 pub mod console;
 pub mod constantsourcenode;
+pub mod convolvernode;
+pub mod countqueuingstrategy;
 mod create;
 pub mod crypto;
+pub mod cryptokey;
 pub mod css;
 pub mod cssconditionrule;
 pub mod cssfontfacerule;
@@ -275,12 +295,16 @@ pub mod csssupportsrule;
 pub mod cssviewportrule;
 pub mod customelementregistry;
 pub mod customevent;
+pub mod customstateset;
+pub mod decompressionstream;
 pub mod dedicatedworkerglobalscope;
+pub mod delaynode;
 pub mod dissimilaroriginlocation;
 pub mod dissimilaroriginwindow;
 pub mod document;
 pub mod documentfragment;
 pub mod documentorshadowroot;
+pub mod documenttimeline;
 pub mod documenttype;
 pub mod domexception;
 pub mod domimplementation;
@@ -296,7 +320,11 @@ pub mod domstringlist;
 pub mod domstringmap;
 pub mod domtokenlist;
 pub mod dynamicmoduleowner;
+pub mod dynamicscompressornode;
 pub mod element;
+pub mod elementinternals;
+pub mod encodedaudiochunk;
+pub mod encodedvideochunk;
 pub mod errorevent;
 pub mod event;
 pub mod eventsource;
@@ -305,6 +333,7 @@ pub mod extendableevent;
 pub mod extendablemessageevent;
 pub mod fakexrdevice;
 pub mod fakexrinputcontroller;
+pub mod fetchevent;
 pub mod file;
 pub mod filelist;
 pub mod filereader;
@@ -409,6 +438,7 @@ pub mod htmlprogresselement;
 pub mod htmlquoteelement;
 pub mod htmlscriptelement;
 pub mod htmlselectelement;
+pub mod htmlslotelement;
 pub mod htmlsourceelement;
 pub mod htmlspanelement;
 pub mod htmlstyleelement;
@@ -427,10 +457,12 @@ pub mod htmlulistelement;
 pub mod htmlunknownelement;
 pub mod htmlvideoelement;
 pub mod identityhub;
+pub mod iirfilternode;
 pub mod imagebitmap;
 pub mod imagedata;
 pub mod inputevent;
 pub mod keyboardevent;
+pub mod keyframeeffect;
 pub mod location;
 pub mod mediadeviceinfo;
 pub mod mediadevices;
@@ -441,7 +473,9 @@ pub mod medialist;
 pub mod mediametadata;
 pub mod mediaquerylist;
 pub mod mediaquerylistevent;
+pub mod mediasegmentparser;
 pub mod mediasession;
+pub mod mediasource;
 pub mod mediastream;
 pub mod mediastreamaudiodestinationnode;
 pub mod mediastreamaudiosourcenode;
@@ -484,6 +518,8 @@ pub mod performancepainttiming;
 pub mod performanceresourcetiming;
 pub mod permissions;
 pub mod permissionstatus;
+pub mod pictureinpictureevent;
+pub mod pictureinpicturewindow;
 pub mod plugin;
 pub mod pluginarray;
 pub mod popstateevent;
@@ -492,10 +528,16 @@ pub mod progressevent;
 pub mod promise;
 pub mod promisenativehandler;
 pub mod promiserejectionevent;
+pub mod queuingstrategy;
 pub mod radionodelist;
 pub mod range;
 pub mod raredata;
+pub mod readablebytestreamcontroller;
 pub mod readablestream;
+pub mod readablestreambyobreader;
+pub mod readablestreambyobrequest;
+pub mod readablestreamdefaultcontroller;
+pub mod readablestreamdefaultreader;
 pub mod request;
 pub mod response;
 pub mod rtcdatachannel;
@@ -505,9 +547,11 @@ pub mod rtcerrorevent;
 pub mod rtcicecandidate;
 pub mod rtcpeerconnection;
 pub mod rtcpeerconnectioniceevent;
+pub(crate) mod rtcrtpreceiver;
 pub(crate) mod rtcrtpsender;
 pub(crate) mod rtcrtptransceiver;
 pub mod rtcsessiondescription;
+pub mod rtcstatsreport;
 pub mod rtctrackevent;
 pub mod screen;
 pub mod selection;
@@ -517,6 +561,10 @@ pub mod serviceworkerglobalscope;
 pub mod serviceworkerregistration;
 pub mod servoparser;
 pub mod shadowroot;
+pub mod sharedworker;
+pub mod sharedworkerglobalscope;
+pub mod sourcebuffer;
+pub mod sourcebufferlist;
 pub mod stereopannernode;
 pub mod storage;
 pub mod storageevent;
@@ -524,6 +572,7 @@ pub mod stylepropertymapreadonly;
 pub mod stylesheet;
 pub mod stylesheetlist;
 pub mod submitevent;
+pub mod subtlecrypto;
 pub mod svgelement;
 pub mod svggraphicselement;
 pub mod svgsvgelement;
@@ -537,7 +586,9 @@ pub mod testworkletglobalscope;
 pub mod text;
 pub mod textcontrol;
 pub mod textdecoder;
+pub mod textdecoderstream;
 pub mod textencoder;
+pub mod textencoderstream;
 pub mod textmetrics;
 pub mod texttrack;
 pub mod texttrackcue;
@@ -548,9 +599,12 @@ pub mod touch;
 pub mod touchevent;
 pub mod touchlist;
 pub mod trackevent;
+pub mod transformstream;
+pub mod transformstreamdefaultcontroller;
 pub mod transitionevent;
 pub mod treewalker;
 pub mod uievent;
+pub mod underlyingsourcecontainer;
 pub mod url;
 pub mod urlhelper;
 pub mod urlsearchparams;
@@ -559,11 +613,16 @@ pub mod validation;
 pub mod validitystate;
 pub mod values;
 pub mod vertexarrayobject;
+pub mod videodecoder;
+pub mod videoencoder;
+pub mod videoframe;
 pub mod videotrack;
 pub mod videotracklist;
 pub mod virtualmethods;
 pub mod vttcue;
 pub mod vttregion;
+pub mod waveshapernode;
+pub(crate) mod webcodecs;
 pub mod webgl2renderingcontext;
 pub mod webgl_extensions;
 pub mod webgl_validations;
@@ -586,6 +645,7 @@ pub mod webgluniformlocation;
 pub mod webglvertexarrayobject;
 pub mod webglvertexarrayobjectoes;
 pub mod websocket;
+pub mod webvttparser;
 pub mod wheelevent;
 pub mod window;
 pub mod windowproxy;
@@ -595,6 +655,9 @@ pub mod workerlocation;
 pub mod workernavigator;
 pub mod worklet;
 pub mod workletglobalscope;
+pub mod writablestream;
+pub mod writablestreamdefaultcontroller;
+pub mod writablestreamdefaultwriter;
 pub mod xmldocument;
 pub mod xmlhttprequest;
 pub mod xmlhttprequesteventtarget;
@@ -8,12 +8,15 @@ use crate::dom::bindings::codegen::Bindings::MutationObserverBinding::MutationCa
 use crate::dom::bindings::codegen::Bindings::MutationObserverBinding::MutationObserverBinding::MutationObserverMethods;
 use crate::dom::bindings::codegen::Bindings::MutationObserverBinding::MutationObserverInit;
 use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::inheritance::Castable;
 use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
 use crate::dom::bindings::root::DomRoot;
 use crate::dom::bindings::str::DOMString;
+use crate::dom::eventtarget::EventTarget;
 use crate::dom::mutationrecord::MutationRecord;
 use crate::dom::node::{Node, ShadowIncluding};
 use crate::dom::window::Window;
+use crate::editing;
 use crate::microtask::Microtask;
 use crate::script_thread::ScriptThread;
 use dom_struct::dom_struct;
@@ -107,7 +110,8 @@ impl MutationObserver {
         ScriptThread::set_mutation_observer_microtask_queued(false);
         // Step 2
         let notify_list = ScriptThread::get_mutation_observers();
-        // TODO: steps 3-4 (slots)
+        // Steps 3-4
+        let signal_list = ScriptThread::take_signal_slots();
         // Step 5
         for mo in &notify_list {
             let queue: Vec<DomRoot<MutationRecord>> = mo.record_queue.borrow().clone();
@@ -119,11 +123,16 @@ impl MutationObserver {
                     .Call_(&**mo, queue, &**mo, ExceptionHandling::Report);
             }
         }
-        // TODO: Step 6 (slot signals)
+        // Step 6
+        for slot in signal_list {
+            slot.upcast::<EventTarget>()
+                .fire_bubbling_event(atom!("slotchange"));
+        }
     }
 
     /// <https://dom.spec.whatwg.org/#queueing-a-mutation-record>
     pub fn queue_a_mutation_record(target: &Node, attr_type: Mutation) {
+        editing::record_mutation(target, &attr_type);
         if !target.global().as_window().get_exists_mut_observer() {
             return;
         }
@@ -12,12 +12,14 @@ use crate::dom::bindings::codegen::Bindings::CharacterDataBinding::CharacterData
 use crate::dom::bindings::codegen::Bindings::DocumentBinding::DocumentMethods;
 use crate::dom::bindings::codegen::Bindings::ElementBinding::ElementMethods;
 use crate::dom::bindings::codegen::Bindings::HTMLCollectionBinding::HTMLCollectionMethods;
+use crate::dom::bindings::codegen::Bindings::HTMLSlotElementBinding::HTMLSlotElementMethods;
 use crate::dom::bindings::codegen::Bindings::NodeBinding::{
     GetRootNodeOptions, NodeConstants, NodeMethods,
 };
 use crate::dom::bindings::codegen::Bindings::NodeListBinding::NodeListMethods;
 use crate::dom::bindings::codegen::Bindings::ProcessingInstructionBinding::ProcessingInstructionMethods;
 use crate::dom::bindings::codegen::Bindings::ShadowRootBinding::ShadowRootBinding::ShadowRootMethods;
+use crate::dom::bindings::codegen::Bindings::ShadowRootBinding::ShadowRootMode;
 use crate::dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
 use crate::dom::bindings::codegen::InheritTypes::DocumentFragmentTypeId;
 use crate::dom::bindings::codegen::UnionTypes::NodeOrString;
@@ -49,6 +51,7 @@ use crate::dom::htmlinputelement::{HTMLInputElement, LayoutHTMLInputElementHelpe
 use crate::dom::htmllinkelement::HTMLLinkElement;
 use crate::dom::htmlmediaelement::{HTMLMediaElement, LayoutHTMLMediaElementHelpers};
 use crate::dom::htmlmetaelement::HTMLMetaElement;
+use crate::dom::htmlslotelement::{HTMLSlotElement, LayoutHTMLSlotElementHelpers};
 use crate::dom::htmlstyleelement::HTMLStyleElement;
 use crate::dom::htmltextareaelement::{HTMLTextAreaElement, LayoutHTMLTextAreaElementHelpers};
 use crate::dom::mouseevent::MouseEvent;
@@ -60,7 +63,7 @@ use crate::dom::raredata::NodeRareData;
 use crate::dom::shadowroot::{LayoutShadowRootHelpers, ShadowRoot};
 use crate::dom::stylesheetlist::StyleSheetListOwner;
 use crate::dom::svgsvgelement::{LayoutSVGSVGElementHelpers, SVGSVGElement};
-use crate::dom::text::Text;
+use crate::dom::text::{LayoutTextHelpers, Text};
 use crate::dom::virtualmethods::{vtable_for, VirtualMethods};
 use crate::dom::window::Window;
 use crate::script_thread::ScriptThread;
@@ -733,6 +736,19 @@ impl Node {
             .any(|ancestor| &*ancestor == self)
     }
 
+    /// Whether this node is inert because its document is blocked by a modal
+    /// dialog that does not contain it.
+    ///
+    /// <https://html.spec.whatwg.org/multipage/#inert>
+    pub fn is_inert(&self) -> bool {
+        match self.owner_doc().blocking_modal_dialog() {
+            Some(dialog) => !dialog
+                .upcast::<Node>()
+                .is_shadow_including_inclusive_ancestor_of(self),
+            None => false,
+        }
+    }
+
     pub fn following_siblings(&self) -> impl Iterator<Item = DomRoot<Node>> {
         SimpleNodeIterator {
             current: self.GetNextSibling(),
@@ -1021,6 +1037,24 @@ impl Node {
         }
     }
 
+    /// Iterates over the inclusive ancestors of this node in the flat tree, where
+    /// the parent of an assigned node is its assigned slot.
+    /// https://drafts.csswg.org/css-scoping/#flat-tree
+    pub fn inclusive_flat_tree_ancestors(&self) -> impl Iterator<Item = DomRoot<Node>> {
+        SimpleNodeIterator {
+            current: Some(DomRoot::from_ref(self)),
+            next_node: |n| {
+                if let Some(slot) = n.assigned_slot() {
+                    return Some(DomRoot::upcast(slot));
+                }
+                if let Some(shadow_root) = n.downcast::<ShadowRoot>() {
+                    return Some(DomRoot::from_ref(shadow_root.Host().upcast::<Node>()));
+                }
+                n.GetParentNode()
+            },
+        }
+    }
+
     pub fn owner_doc(&self) -> DomRoot<Document> {
         self.owner_doc.get().unwrap()
     }
@@ -1041,6 +1075,62 @@ impl Node {
         self.ensure_rare_data().containing_shadow_root = shadow_root.map(Dom::from_ref);
     }
 
+    /// <https://dom.spec.whatwg.org/#concept-slotable>
+    pub fn is_slottable(&self) -> bool {
+        self.is::<Element>() || self.is::<Text>()
+    }
+
+    /// <https://dom.spec.whatwg.org/#slotable-assigned-slot>
+    pub fn assigned_slot(&self) -> Option<DomRoot<HTMLSlotElement>> {
+        self.rare_data()
+            .as_ref()?
+            .assigned_slot
+            .as_ref()
+            .map(|slot| DomRoot::from_ref(&**slot))
+    }
+
+    /// Sets the assigned slot of this node, along with the index of this node
+    /// in the assigned nodes of that slot.
+    pub fn set_assigned_slot(&self, slot: Option<(&HTMLSlotElement, usize)>) {
+        if slot.is_none() && self.rare_data().is_none() {
+            return;
+        }
+        let mut rare_data = self.ensure_rare_data();
+        rare_data.assigned_slot = slot.map(|(slot, _)| Dom::from_ref(slot));
+        rare_data.assigned_slot_index = slot.map_or(0, |(_, index)| index);
+    }
+
+    /// <https://dom.spec.whatwg.org/#find-a-slot>
+    pub fn find_a_slot(&self, open: bool) -> Option<DomRoot<HTMLSlotElement>> {
+        // Step 1.
+        let parent = self.GetParentNode()?;
+        // Step 2.
+        let shadow_root = parent.downcast::<Element>()?.shadow_root()?;
+        // Step 3.
+        if open && shadow_root.Mode() != ShadowRootMode::Open {
+            return None;
+        }
+        // Step 4. Manual slot assignment is not supported, so we skip step 5.
+        let name = match self.downcast::<Element>() {
+            Some(element) => element.get_string_attribute(&local_name!("slot")),
+            None => DOMString::new(),
+        };
+        shadow_root
+            .upcast::<Node>()
+            .traverse_preorder(ShadowIncluding::No)
+            .filter_map(DomRoot::downcast::<HTMLSlotElement>)
+            .find(|slot| slot.Name() == name)
+    }
+
+    /// <https://dom.spec.whatwg.org/#assign-a-slot>
+    pub fn assign_a_slot(&self) {
+        // Step 1.
+        if let Some(slot) = self.find_a_slot(false) {
+            // Step 2.
+            slot.assign_slottables();
+        }
+    }
+
     pub fn is_in_html_doc(&self) -> bool {
         self.owner_doc().is_html_document()
     }
@@ -1349,6 +1439,11 @@ pub trait LayoutNodeHelpers<'dom> {
 
     fn owner_doc_for_layout(self) -> LayoutDom<'dom, Document>;
     fn containing_shadow_root_for_layout(self) -> Option<LayoutDom<'dom, ShadowRoot>>;
+    fn assigned_slot_for_layout(self) -> Option<LayoutDom<'dom, HTMLSlotElement>>;
+    fn assigned_slot_index_for_layout(self) -> usize;
+    fn assigned_nodes_ref(self) -> &'dom [LayoutDom<'dom, Node>];
+    fn flat_tree_first_child_ref(self) -> Option<LayoutDom<'dom, Node>>;
+    fn flat_tree_next_sibling_ref(self) -> Option<LayoutDom<'dom, Node>>;
 
     fn is_element_for_layout(self) -> bool;
     unsafe fn get_flag(self, flag: NodeFlags) -> bool;
@@ -1448,6 +1543,62 @@ impl<'dom> LayoutNodeHelpers<'dom> for LayoutDom<'dom, Node> {
         }
     }
 
+    #[inline]
+    #[allow(unsafe_code)]
+    fn assigned_slot_for_layout(self) -> Option<LayoutDom<'dom, HTMLSlotElement>> {
+        unsafe {
+            self.unsafe_get()
+                .rare_data
+                .borrow_for_layout()
+                .as_ref()?
+                .assigned_slot
+                .as_ref()
+                .map(|slot| slot.to_layout())
+        }
+    }
+
+    #[inline]
+    #[allow(unsafe_code)]
+    fn assigned_slot_index_for_layout(self) -> usize {
+        unsafe {
+            self.unsafe_get()
+                .rare_data
+                .borrow_for_layout()
+                .as_ref()
+                .map_or(0, |rare_data| rare_data.assigned_slot_index)
+        }
+    }
+
+    /// The nodes assigned to this node if it is a slot, in tree order.
+    #[inline]
+    fn assigned_nodes_ref(self) -> &'dom [LayoutDom<'dom, Node>] {
+        match self.downcast::<HTMLSlotElement>() {
+            Some(slot) => slot.assigned_nodes_for_layout(),
+            None => &[],
+        }
+    }
+
+    /// The first child of this node in the flat tree, ignoring shadow roots.
+    /// For a slot, this is its first assigned node if it has any.
+    #[inline]
+    fn flat_tree_first_child_ref(self) -> Option<LayoutDom<'dom, Node>> {
+        self.assigned_nodes_ref()
+            .first()
+            .cloned()
+            .or_else(|| self.first_child_ref())
+    }
+
+    /// The next sibling of this node in the flat tree, ignoring shadow roots.
+    /// For an assigned node, this is the next node assigned to the same slot.
+    #[inline]
+    fn flat_tree_next_sibling_ref(self) -> Option<LayoutDom<'dom, Node>> {
+        if let Some(slot) = self.assigned_slot_for_layout() {
+            let index = self.assigned_slot_index_for_layout();
+            return slot.assigned_nodes_for_layout().get(index + 1).cloned();
+        }
+        self.next_sibling_ref()
+    }
+
     // FIXME(nox): get_flag/set_flag (especially the latter) are not safe because
     // they mutate stuff while values of this type can be used from multiple
     // threads at once, this should be revisited.
@@ -1539,6 +1690,10 @@ impl<'dom> LayoutNodeHelpers<'dom> for LayoutDom<'dom, Node> {
             return input.selection_for_layout();
         }
 
+        if let Some(text) = self.downcast::<Text>() {
+            return text.selection_for_layout();
+        }
+
         None
     }
 
@@ -2078,6 +2233,27 @@ impl Node {
         for kid in new_nodes {
             // Step 7.1.
             parent.add_child(*kid, child);
+            // Step 7.4.
+            if kid.is_slottable() &&
+                parent
+                    .downcast::<Element>()
+                    .map_or(false, |parent| parent.is_shadow_host())
+            {
+                kid.assign_a_slot();
+            }
+            // Step 7.5.
+            if let Some(slot) = parent.downcast::<HTMLSlotElement>() {
+                if slot.is_in_shadow_tree_root() && slot.assigned_nodes().is_empty() {
+                    slot.signal_a_slot_change();
+                }
+            }
+            // Step 7.6.
+            // Only slots in a shadow tree have slottables, so other trees can't
+            // be affected by this insertion.
+            let root = kid.inclusive_ancestors(ShadowIncluding::No).last().unwrap();
+            if root.is::<ShadowRoot>() {
+                HTMLSlotElement::assign_slottables_for_a_tree(&root);
+            }
             // Step 7.7.
             for descendant in kid
                 .traverse_preorder(ShadowIncluding::Yes)
@@ -2216,8 +2392,35 @@ impl Node {
         let old_previous_sibling = node.GetPreviousSibling();
         // Step 8.
         let old_next_sibling = node.GetNextSibling();
+        let assigned_slot = node.assigned_slot();
         // Steps 9-10 are handled in unbind_from_tree.
         parent.remove_child(node, cached_index);
+        // If node is assigned, run assign slottables for node's assigned slot.
+        if let Some(slot) = assigned_slot {
+            slot.assign_slottables();
+        }
+        // If parent is a slot in a shadow tree whose assigned nodes is empty,
+        // signal a slot change for parent.
+        if let Some(slot) = parent.downcast::<HTMLSlotElement>() {
+            if slot.is_in_shadow_tree_root() && slot.assigned_nodes().is_empty() {
+                slot.signal_a_slot_change();
+            }
+        }
+        // If node has an inclusive descendant that is a slot, run assign
+        // slottables for a tree with parent's root and with node.
+        if node
+            .traverse_preorder(ShadowIncluding::No)
+            .any(|descendant| descendant.is::<HTMLSlotElement>())
+        {
+            let root = parent
+                .inclusive_ancestors(ShadowIncluding::No)
+                .last()
+                .unwrap();
+            if root.is::<ShadowRoot>() {
+                HTMLSlotElement::assign_slottables_for_a_tree(&root);
+            }
+            HTMLSlotElement::assign_slottables_for_a_tree(node);
+        }
         // Step 11. transient registered observers
         // Step 12.
         if let SuppressObserver::Unsuppressed = suppress_observers {
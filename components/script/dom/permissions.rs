@@ -351,7 +351,7 @@ fn allowed_in_nonsecure_contexts(permission_name: &PermissionName) -> bool {
     }
 }
 
-fn prompt_user_from_embedder(prompt: PermissionPrompt, gs: &GlobalScope) -> PermissionState {
+pub fn prompt_user_from_embedder(prompt: PermissionPrompt, gs: &GlobalScope) -> PermissionState {
     let (sender, receiver) = ipc::channel().expect("Failed to create IPC channel!");
     gs.send_to_embedder(EmbedderMsg::PromptPermission(prompt, sender));
 
@@ -242,6 +242,18 @@ impl Promise {
         obj
     }
 
+    /// <https://webidl.spec.whatwg.org/#mark-a-promise-as-handled>
+    ///
+    /// Adding a reaction that does nothing keeps a rejection of the promise
+    /// from being reported as unhandled.
+    pub fn set_promise_is_handled(&self) {
+        let global = self.global();
+        let handler = PromiseNativeHandler::new(&global, None, None);
+        let realm = enter_realm(&*global);
+        let comp = InRealm::Entered(&realm);
+        self.append_native_handler(&handler, comp);
+    }
+
     #[allow(unsafe_code)]
     pub fn append_native_handler(&self, handler: &PromiseNativeHandler, _comp: InRealm) {
         let _ais = AutoEntryScript::new(&*handler.global());
@@ -0,0 +1,161 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Queuing strategies and the queue-with-sizes abstract operations shared by
+//! the controllers of readable and writable streams.
+
+use crate::dom::bindings::callback::ExceptionHandling;
+use crate::dom::bindings::codegen::Bindings::QueuingStrategyBinding::{
+    QueuingStrategy, QueuingStrategySize,
+};
+use crate::dom::bindings::error::{Error, Fallible};
+use js::jsapi::Heap;
+use js::jsval::JSVal;
+use js::rust::{HandleValue, MutableHandleValue};
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+/// <https://streams.spec.whatwg.org/#validate-and-normalize-high-water-mark>
+pub fn extract_high_water_mark(strategy: &QueuingStrategy, default_hwm: f64) -> Fallible<f64> {
+    // Step 1.
+    let high_water_mark = match strategy.highWaterMark {
+        Some(high_water_mark) => high_water_mark,
+        None => return Ok(default_hwm),
+    };
+
+    // Step 3.
+    if high_water_mark.is_nan() || high_water_mark < 0. {
+        return Err(Error::Range(
+            "The high water mark must be a non-negative number".to_owned(),
+        ));
+    }
+
+    // Step 4.
+    Ok(high_water_mark)
+}
+
+/// <https://streams.spec.whatwg.org/#make-size-algorithm-from-size-function>
+pub fn extract_size_algorithm(strategy: &QueuingStrategy) -> SizeAlgorithm {
+    match strategy.size {
+        Some(ref size) => SizeAlgorithm::Js(size.clone()),
+        None => SizeAlgorithm::ReturnOne,
+    }
+}
+
+/// The algorithm a stream uses to compute the size of a chunk.
+#[derive(JSTraceable, MallocSizeOf)]
+pub enum SizeAlgorithm {
+    /// Every chunk has a size of one.
+    ReturnOne,
+    /// The `size` function of a queuing strategy.
+    Js(#[ignore_malloc_size_of = "Rc is hard"] Rc<QueuingStrategySize>),
+}
+
+impl SizeAlgorithm {
+    pub fn size(&self, chunk: HandleValue) -> Fallible<f64> {
+        match *self {
+            SizeAlgorithm::ReturnOne => Ok(1.),
+            SizeAlgorithm::Js(ref size) => size.Call__(chunk, ExceptionHandling::Rethrow),
+        }
+    }
+}
+
+/// A value in the queue of a stream controller.
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+pub enum QueueEntry {
+    /// <https://streams.spec.whatwg.org/#value-with-size>
+    Value {
+        #[ignore_malloc_size_of = "Defined in rust-mozjs"]
+        value: Box<Heap<JSVal>>,
+        size: f64,
+    },
+    /// The close sentinel of the queue of a writable stream controller.
+    CloseSentinel,
+}
+
+/// <https://streams.spec.whatwg.org/#queue-with-sizes>
+#[derive(Default, JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+pub struct QueueWithSizes {
+    queue: VecDeque<QueueEntry>,
+    total_size: f64,
+}
+
+impl QueueWithSizes {
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    pub fn total_size(&self) -> f64 {
+        self.total_size
+    }
+
+    /// <https://streams.spec.whatwg.org/#dequeue-value>
+    ///
+    /// Returns whether the dequeued entry was the close sentinel.
+    pub fn dequeue_value(&mut self, mut rval: MutableHandleValue) -> bool {
+        // Steps 1-3.
+        let entry = self
+            .queue
+            .pop_front()
+            .expect("Dequeued a value from an empty queue");
+        match entry {
+            QueueEntry::Value { value, size } => {
+                // Steps 4-5.
+                self.total_size -= size;
+                if self.total_size < 0. {
+                    self.total_size = 0.;
+                }
+
+                // Step 6.
+                rval.set(value.get());
+                false
+            },
+            QueueEntry::CloseSentinel => true,
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#enqueue-value-with-size>
+    pub fn enqueue_value_with_size(&mut self, value: HandleValue, size: f64) -> Fallible<()> {
+        // Steps 3-4.
+        if !size.is_finite() || size < 0. {
+            return Err(Error::Range(
+                "The size of a chunk must be a finite, non-negative number".to_owned(),
+            ));
+        }
+
+        // Steps 5-6.
+        self.queue.push_back(QueueEntry::Value {
+            value: Heap::boxed(value.get()),
+            size,
+        });
+        self.total_size += size;
+        Ok(())
+    }
+
+    /// Appends the close sentinel, which has a size of zero.
+    pub fn enqueue_close_sentinel(&mut self) {
+        self.queue.push_back(QueueEntry::CloseSentinel);
+    }
+
+    /// <https://streams.spec.whatwg.org/#peek-queue-value>
+    ///
+    /// Returns whether the entry at the front of the queue is the close sentinel.
+    pub fn peek_queue_value(&self, mut rval: MutableHandleValue) -> bool {
+        match *self.queue.front().expect("Peeked at an empty queue") {
+            QueueEntry::Value { ref value, .. } => {
+                rval.set(value.get());
+                false
+            },
+            QueueEntry::CloseSentinel => true,
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#reset-queue>
+    pub fn reset(&mut self) {
+        self.queue.clear();
+        self.total_size = 0.;
+    }
+}
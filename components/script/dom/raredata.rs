@@ -6,6 +6,10 @@ use crate::dom::bindings::root::Dom;
 use crate::dom::customelementregistry::{
     CustomElementDefinition, CustomElementReaction, CustomElementState,
 };
+use crate::dom::customstateset::CustomStateSet;
+use crate::dom::elementinternals::ElementInternals;
+use crate::dom::htmlformelement::HTMLFormElement;
+use crate::dom::htmlslotelement::HTMLSlotElement;
 use crate::dom::mutationobserver::RegisteredObserver;
 use crate::dom::node::UniqueId;
 use crate::dom::shadowroot::ShadowRoot;
@@ -28,6 +32,10 @@ pub struct NodeRareData {
     pub mutation_observers: Vec<RegisteredObserver>,
     /// Lazily-generated Unique Id for this node.
     pub unique_id: Option<UniqueId>,
+    /// <https://dom.spec.whatwg.org/#slotable-assigned-slot>
+    pub assigned_slot: Option<Dom<HTMLSlotElement>>,
+    /// The index of the node in the assigned nodes of its assigned slot.
+    pub assigned_slot_index: usize,
 }
 
 #[derive(Default, JSTraceable, MallocSizeOf)]
@@ -35,8 +43,6 @@ pub struct NodeRareData {
 pub struct ElementRareData {
     /// https://dom.spec.whatwg.org/#dom-element-shadowroot
     /// The ShadowRoot this element is host of.
-    /// XXX This is currently not exposed to web content. Only for
-    ///     internal use.
     pub shadow_root: Option<Dom<ShadowRoot>>,
     /// <https://html.spec.whatwg.org/multipage/#custom-element-reaction-queue>
     pub custom_element_reaction_queue: Vec<CustomElementReaction>,
@@ -45,6 +51,12 @@ pub struct ElementRareData {
     pub custom_element_definition: Option<Rc<CustomElementDefinition>>,
     /// <https://dom.spec.whatwg.org/#concept-element-custom-element-state>
     pub custom_element_state: CustomElementState,
+    /// <https://html.spec.whatwg.org/multipage/#attached-internals>
+    pub element_internals: Option<Dom<ElementInternals>>,
+    /// <https://html.spec.whatwg.org/multipage/#custom-state-set>
+    pub custom_state_set: Option<Dom<CustomStateSet>>,
+    /// The form owner of a form-associated custom element.
+    pub form_owner: Option<Dom<HTMLFormElement>>,
     /// The "name" content attribute; not used as frequently as id, but used
     /// in named getter loops so it's worth looking up quickly when present
     pub name_attribute: Option<Atom>,
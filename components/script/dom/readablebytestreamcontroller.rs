@@ -0,0 +1,1042 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::ReadableByteStreamControllerBinding::ReadableByteStreamControllerMethods;
+use crate::dom::bindings::codegen::UnionTypes::ReadableStreamDefaultControllerOrReadableByteStreamController as ReadableStreamController;
+use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{DomRoot, MutNullableDom};
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::promisenativehandler::{Callback, PromiseNativeHandler};
+use crate::dom::readablestream::{new_type_error, ReadableStream, ReadableStreamState};
+use crate::dom::readablestreambyobreader::ReadIntoRequest;
+use crate::dom::readablestreambyobrequest::ReadableStreamBYOBRequest;
+use crate::dom::readablestreamdefaultreader::ReadRequest;
+use crate::dom::underlyingsourcecontainer::UnderlyingSourceType;
+use crate::realms::{enter_realm, InRealm};
+use crate::script_runtime::JSContext;
+use dom_struct::dom_struct;
+use js::jsapi::{Heap, JSObject, JS_NewDataView, Type};
+use js::jsval::{ObjectValue, UndefinedValue};
+use js::rust::{CustomAutoRooterGuard, HandleValue, MutableHandleObject};
+use js::typedarray::{
+    ArrayBuffer, ArrayBufferView, ClampedU8, CreateWith, Float32, Float64, Int16, Int32, Int8,
+    TypedArray, TypedArrayElementCreator, Uint16, Uint32, Uint8,
+};
+use std::cell::Cell;
+use std::cmp;
+use std::collections::VecDeque;
+use std::ptr;
+use std::rc::Rc;
+
+/// <https://streams.spec.whatwg.org/#pull-into-descriptor-reader-type>
+#[derive(Clone, Copy, JSTraceable, MallocSizeOf, PartialEq)]
+enum ReaderType {
+    Default,
+    Byob,
+    None,
+}
+
+/// <https://streams.spec.whatwg.org/#pull-into-descriptor>
+///
+/// Bytes are copied into the view the descriptor was created for, rather than
+/// into a transferred buffer.
+#[derive(JSTraceable, MallocSizeOf)]
+struct PullIntoDescriptor {
+    /// The view the bytes are written into.
+    #[ignore_malloc_size_of = "Defined in rust-mozjs"]
+    view: Box<Heap<*mut JSObject>>,
+    /// <https://streams.spec.whatwg.org/#pull-into-descriptor-byte-length>
+    byte_length: usize,
+    /// <https://streams.spec.whatwg.org/#pull-into-descriptor-bytes-filled>
+    bytes_filled: usize,
+    /// <https://streams.spec.whatwg.org/#pull-into-descriptor-minimum-fill>
+    minimum_fill: usize,
+    /// <https://streams.spec.whatwg.org/#pull-into-descriptor-element-size>
+    element_size: usize,
+    /// <https://streams.spec.whatwg.org/#pull-into-descriptor-reader-type>
+    reader_type: ReaderType,
+}
+
+/// <https://streams.spec.whatwg.org/#rbs-controller-class>
+#[dom_struct]
+pub struct ReadableByteStreamController {
+    reflector_: Reflector,
+    /// <https://streams.spec.whatwg.org/#readablebytestreamcontroller-stream>
+    stream: MutNullableDom<ReadableStream>,
+    /// The source of the pull and cancel algorithms.
+    #[ignore_malloc_size_of = "Rc is hard"]
+    underlying_source: Rc<UnderlyingSourceType>,
+    /// <https://streams.spec.whatwg.org/#readablebytestreamcontroller-autoallocatechunksize>
+    auto_allocate_chunk_size: Option<u64>,
+    /// <https://streams.spec.whatwg.org/#readablebytestreamcontroller-byobrequest>
+    byob_request: MutNullableDom<ReadableStreamBYOBRequest>,
+    /// <https://streams.spec.whatwg.org/#readablebytestreamcontroller-closerequested>
+    close_requested: Cell<bool>,
+    /// <https://streams.spec.whatwg.org/#readablebytestreamcontroller-pullagain>
+    pull_again: Cell<bool>,
+    /// <https://streams.spec.whatwg.org/#readablebytestreamcontroller-pulling>
+    pulling: Cell<bool>,
+    /// <https://streams.spec.whatwg.org/#readablebytestreamcontroller-started>
+    started: Cell<bool>,
+    /// <https://streams.spec.whatwg.org/#readablebytestreamcontroller-queue>
+    queue: DomRefCell<VecDeque<Vec<u8>>>,
+    /// <https://streams.spec.whatwg.org/#readablebytestreamcontroller-queuetotalsize>
+    queue_total_size: Cell<usize>,
+    /// <https://streams.spec.whatwg.org/#readablebytestreamcontroller-strategyhwm>
+    strategy_hwm: f64,
+    /// <https://streams.spec.whatwg.org/#readablebytestreamcontroller-pendingpullintos>
+    pending_pull_intos: DomRefCell<VecDeque<PullIntoDescriptor>>,
+}
+
+impl ReadableByteStreamController {
+    #[allow(unrooted_must_root)]
+    fn new_inherited(
+        underlying_source: UnderlyingSourceType,
+        auto_allocate_chunk_size: Option<u64>,
+        strategy_hwm: f64,
+    ) -> ReadableByteStreamController {
+        ReadableByteStreamController {
+            reflector_: Reflector::new(),
+            stream: MutNullableDom::new(None),
+            underlying_source: Rc::new(underlying_source),
+            auto_allocate_chunk_size,
+            byob_request: MutNullableDom::new(None),
+            close_requested: Cell::new(false),
+            pull_again: Cell::new(false),
+            pulling: Cell::new(false),
+            started: Cell::new(false),
+            queue: DomRefCell::new(VecDeque::new()),
+            queue_total_size: Cell::new(0),
+            strategy_hwm,
+            pending_pull_intos: DomRefCell::new(VecDeque::new()),
+        }
+    }
+
+    #[allow(unrooted_must_root)]
+    fn new(
+        global: &GlobalScope,
+        underlying_source: UnderlyingSourceType,
+        auto_allocate_chunk_size: Option<u64>,
+        strategy_hwm: f64,
+    ) -> DomRoot<ReadableByteStreamController> {
+        reflect_dom_object(
+            Box::new(ReadableByteStreamController::new_inherited(
+                underlying_source,
+                auto_allocate_chunk_size,
+                strategy_hwm,
+            )),
+            global,
+        )
+    }
+
+    /// <https://streams.spec.whatwg.org/#set-up-readable-byte-stream-controller-from-underlying-source>
+    /// and <https://streams.spec.whatwg.org/#set-up-readable-byte-stream-controller>.
+    #[allow(unrooted_must_root)]
+    pub fn set_up_from_underlying_source(
+        stream: &ReadableStream,
+        underlying_source: UnderlyingSourceType,
+        auto_allocate_chunk_size: Option<u64>,
+        high_water_mark: f64,
+    ) -> Fallible<()> {
+        // Step 9 of set up from underlying source.
+        if auto_allocate_chunk_size == Some(0) {
+            return Err(Error::Type(
+                "autoAllocateChunkSize must be greater than zero".to_owned(),
+            ));
+        }
+
+        let global = stream.global();
+        let cx = global.get_cx();
+
+        // Steps 1-13.
+        let controller = ReadableByteStreamController::new(
+            &global,
+            underlying_source,
+            auto_allocate_chunk_size,
+            high_water_mark,
+        );
+        controller.stream.set(Some(stream));
+
+        // Step 14.
+        stream.set_byte_controller(&controller);
+
+        // Step 15.
+        rooted!(in(*cx) let mut start_result = UndefinedValue());
+        controller.underlying_source.start(
+            cx,
+            ReadableStreamController::ReadableByteStreamController(controller.clone()),
+            start_result.handle_mut(),
+        )?;
+
+        // Step 16.
+        let start_promise = Promise::new_resolved(&global, cx, start_result.handle())?;
+
+        // Steps 17-18.
+        controller.react(&start_promise, ControllerStep::Start);
+        Ok(())
+    }
+
+    fn react(&self, promise: &Promise, step: ControllerStep) {
+        let global = self.global();
+        let handler = PromiseNativeHandler::new(
+            &global,
+            Some(Box::new(ControllerPromiseHandler {
+                controller: DomRoot::from_ref(self),
+                step,
+                fulfilled: true,
+            })),
+            Some(Box::new(ControllerPromiseHandler {
+                controller: DomRoot::from_ref(self),
+                step,
+                fulfilled: false,
+            })),
+        );
+        let realm = enter_realm(&*global);
+        let comp = InRealm::Entered(&realm);
+        promise.append_native_handler(&handler, comp);
+    }
+
+    fn stream(&self) -> DomRoot<ReadableStream> {
+        self.stream.get().expect("Controller should have a stream")
+    }
+
+    /// Does the underlying source have all its data in memory?
+    pub fn source_in_memory(&self) -> bool {
+        self.underlying_source.in_memory()
+    }
+
+    /// A copy of the bytes in the queue.
+    pub fn get_queued_bytes(&self) -> Vec<u8> {
+        self.queue.borrow().iter().flatten().cloned().collect()
+    }
+
+    /// Enqueues bytes provided by a native source.
+    pub fn enqueue_native(&self, bytes: Vec<u8>) {
+        if self.close_requested.get() || !self.stream().is_readable() {
+            return;
+        }
+        self.enqueue(bytes);
+    }
+
+    /// Closes a stream fed by a native source.
+    pub fn close_native(&self) {
+        if self.close_requested.get() || !self.stream().is_readable() {
+            return;
+        }
+        let _ = self.close();
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-call-pull-if-needed>
+    fn call_pull_if_needed(&self) {
+        // Steps 1-2.
+        if !self.should_call_pull() {
+            return;
+        }
+
+        // Step 3.
+        if self.pulling.get() {
+            self.pull_again.set(true);
+            return;
+        }
+
+        // Steps 4-5.
+        self.pulling.set(true);
+
+        // Steps 6-8.
+        let pull_promise = self.underlying_source.pull(
+            &self.global(),
+            ReadableStreamController::ReadableByteStreamController(DomRoot::from_ref(self)),
+        );
+        self.react(&pull_promise, ControllerStep::Pull);
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-should-call-pull>
+    fn should_call_pull(&self) -> bool {
+        // Steps 1-4.
+        let stream = self.stream();
+        if !stream.is_readable() || self.close_requested.get() || !self.started.get() {
+            return false;
+        }
+
+        // Step 5.
+        if stream.has_default_reader() && stream.get_num_read_requests() > 0 {
+            return true;
+        }
+
+        // Step 6.
+        if stream.has_byob_reader() && stream.get_num_read_into_requests() > 0 {
+            return true;
+        }
+
+        // Steps 7-10.
+        self.get_desired_size().unwrap_or(0.) > 0.
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-get-desired-size>
+    fn get_desired_size(&self) -> Option<f64> {
+        match self.stream().state() {
+            ReadableStreamState::Errored => None,
+            ReadableStreamState::Closed => Some(0.),
+            ReadableStreamState::Readable => {
+                Some(self.strategy_hwm - self.queue_total_size.get() as f64)
+            },
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-enqueue-chunk-to-queue>
+    fn enqueue_chunk_to_queue(&self, bytes: Vec<u8>) {
+        self.queue_total_size
+            .set(self.queue_total_size.get() + bytes.len());
+        self.queue.borrow_mut().push_back(bytes);
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-reset-queue>
+    fn reset_queue(&self) {
+        self.queue.borrow_mut().clear();
+        self.queue_total_size.set(0);
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-clear-pending-pull-intos>
+    fn clear_pending_pull_intos(&self) {
+        self.invalidate_byob_request();
+        self.pending_pull_intos.borrow_mut().clear();
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-invalidate-byob-request>
+    fn invalidate_byob_request(&self) {
+        if let Some(byob_request) = self.byob_request.get() {
+            byob_request.invalidate();
+            self.byob_request.set(None);
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-handle-queue-drain>
+    fn handle_queue_drain(&self) {
+        if self.queue_total_size.get() == 0 && self.close_requested.get() {
+            self.stream().close();
+        } else {
+            self.call_pull_if_needed();
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-enqueue>
+    fn enqueue(&self, bytes: Vec<u8>) {
+        let stream = self.stream();
+        let cx = self.global().get_cx();
+
+        // Step 9.
+        let first_reader_type = self
+            .pending_pull_intos
+            .borrow()
+            .front()
+            .map(|descriptor| descriptor.reader_type);
+        if let Some(reader_type) = first_reader_type {
+            // Step 9.2.
+            self.invalidate_byob_request();
+
+            // Step 9.4.
+            if reader_type == ReaderType::None {
+                self.enqueue_detached_pull_into_to_queue();
+            }
+        }
+
+        if stream.has_default_reader() {
+            // Step 10.1.
+            self.process_read_requests_using_queue();
+
+            if stream.get_num_read_requests() == 0 {
+                // Step 10.2.
+                self.enqueue_chunk_to_queue(bytes);
+            } else {
+                // Step 10.3.1.
+                if self.pending_pull_intos.borrow().front().is_some() {
+                    self.pending_pull_intos.borrow_mut().pop_front();
+                }
+
+                // Steps 10.3.2-10.3.3.
+                rooted!(in(*cx) let mut view = ptr::null_mut::<JSObject>());
+                create_view(cx, Type::Uint8, &bytes, view.handle_mut());
+                rooted!(in(*cx) let chunk = ObjectValue(view.get()));
+                stream.fulfill_read_request(chunk.handle(), false);
+            }
+        } else if stream.has_byob_reader() {
+            // Steps 11.1-11.2.
+            self.enqueue_chunk_to_queue(bytes);
+            let filled_pull_intos = self.process_pull_into_descriptors_using_queue();
+            for descriptor in filled_pull_intos {
+                self.commit_pull_into_descriptor(descriptor);
+            }
+        } else {
+            // Step 12.
+            self.enqueue_chunk_to_queue(bytes);
+        }
+
+        // Step 13.
+        self.call_pull_if_needed();
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-enqueue-detached-pull-into-queue>
+    fn enqueue_detached_pull_into_to_queue(&self) {
+        let descriptor = self
+            .pending_pull_intos
+            .borrow_mut()
+            .pop_front()
+            .expect("There should be a pending pull-into");
+        if descriptor.bytes_filled > 0 {
+            let cx = self.global().get_cx();
+            let bytes = view_bytes(cx, descriptor.view.get());
+            self.enqueue_chunk_to_queue(bytes[..descriptor.bytes_filled].to_vec());
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#abstract-opdef-readablebytestreamcontrollerprocessreadrequestsusingqueue>
+    fn process_read_requests_using_queue(&self) {
+        let stream = self.stream();
+        while stream.get_num_read_requests() > 0 && self.queue_total_size.get() > 0 {
+            let read_request = stream
+                .get_default_reader()
+                .expect("Stream should have a default reader")
+                .take_first_read_request();
+            self.fill_read_request_from_queue(read_request);
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#abstract-opdef-readablebytestreamcontrollerfillreadrequestfromqueue>
+    fn fill_read_request_from_queue(&self, read_request: ReadRequest) {
+        // Steps 1-3.
+        let entry = self
+            .queue
+            .borrow_mut()
+            .pop_front()
+            .expect("The queue should not be empty");
+        self.queue_total_size
+            .set(self.queue_total_size.get() - entry.len());
+
+        // Step 4.
+        self.handle_queue_drain();
+
+        // Steps 5-6.
+        let cx = self.global().get_cx();
+        rooted!(in(*cx) let mut view = ptr::null_mut::<JSObject>());
+        create_view(cx, Type::Uint8, &entry, view.handle_mut());
+        rooted!(in(*cx) let chunk = ObjectValue(view.get()));
+        read_request.chunk_steps(chunk.handle());
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-process-pull-into-descriptors-using-queue>
+    ///
+    /// Returns the descriptors that were filled, which are to be committed.
+    fn process_pull_into_descriptors_using_queue(&self) -> Vec<PullIntoDescriptor> {
+        let mut filled_pull_intos = vec![];
+        loop {
+            // Step 2.1.
+            if self.queue_total_size.get() == 0 {
+                break;
+            }
+
+            // Steps 2.2-2.3.
+            let ready = {
+                let mut pending_pull_intos = self.pending_pull_intos.borrow_mut();
+                match pending_pull_intos.front_mut() {
+                    Some(descriptor) => self.fill_pull_into_descriptor_from_queue(descriptor),
+                    None => break,
+                }
+            };
+            if !ready {
+                break;
+            }
+            filled_pull_intos.push(
+                self.pending_pull_intos
+                    .borrow_mut()
+                    .pop_front()
+                    .expect("There should be a pending pull-into"),
+            );
+        }
+        filled_pull_intos
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-fill-pull-into-descriptor-from-queue>
+    fn fill_pull_into_descriptor_from_queue(&self, descriptor: &mut PullIntoDescriptor) -> bool {
+        // Steps 1-4.
+        let max_bytes_to_copy = cmp::min(
+            self.queue_total_size.get(),
+            descriptor.byte_length - descriptor.bytes_filled,
+        );
+        let max_bytes_filled = descriptor.bytes_filled + max_bytes_to_copy;
+        let mut total_bytes_to_copy_remaining = max_bytes_to_copy;
+        let mut ready = false;
+
+        // Steps 5-7.
+        let max_aligned_bytes = max_bytes_filled - max_bytes_filled % descriptor.element_size;
+        if max_aligned_bytes >= descriptor.minimum_fill {
+            total_bytes_to_copy_remaining = max_aligned_bytes - descriptor.bytes_filled;
+            ready = true;
+        }
+
+        // Step 9.
+        let cx = self.global().get_cx();
+        let mut queue = self.queue.borrow_mut();
+        while total_bytes_to_copy_remaining > 0 {
+            let head = queue.front_mut().expect("The queue should not be empty");
+            let bytes_to_copy = cmp::min(total_bytes_to_copy_remaining, head.len());
+            write_view_bytes(
+                cx,
+                descriptor.view.get(),
+                descriptor.bytes_filled,
+                &head[..bytes_to_copy],
+            );
+            if bytes_to_copy == head.len() {
+                queue.pop_front();
+            } else {
+                head.drain(..bytes_to_copy);
+            }
+            self.queue_total_size
+                .set(self.queue_total_size.get() - bytes_to_copy);
+            descriptor.bytes_filled += bytes_to_copy;
+            total_bytes_to_copy_remaining -= bytes_to_copy;
+        }
+
+        // Step 11.
+        ready
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-convert-pull-into-descriptor>
+    fn convert_pull_into_descriptor(
+        &self,
+        descriptor: &PullIntoDescriptor,
+        rval: MutableHandleObject,
+    ) {
+        let cx = self.global().get_cx();
+        let bytes = view_bytes(cx, descriptor.view.get());
+        create_view(
+            cx,
+            view_type(cx, descriptor.view.get()),
+            &bytes[..descriptor.bytes_filled],
+            rval,
+        );
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-commit-pull-into-descriptor>
+    fn commit_pull_into_descriptor(&self, descriptor: PullIntoDescriptor) {
+        let stream = self.stream();
+        let cx = self.global().get_cx();
+
+        // Steps 2-3.
+        let done = stream.is_closed();
+
+        // Steps 4-6.
+        rooted!(in(*cx) let mut view = ptr::null_mut::<JSObject>());
+        self.convert_pull_into_descriptor(&descriptor, view.handle_mut());
+        rooted!(in(*cx) let filled_view = ObjectValue(view.get()));
+        if descriptor.reader_type == ReaderType::Default {
+            stream.fulfill_read_request(filled_view.handle(), done);
+        } else {
+            stream.fulfill_read_into_request(filled_view.handle(), done);
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-close>
+    fn close(&self) -> ErrorResult {
+        // Step 1.
+        let stream = self.stream();
+
+        // Step 2.
+        if self.close_requested.get() || !stream.is_readable() {
+            return Ok(());
+        }
+
+        // Step 3.
+        if self.queue_total_size.get() > 0 {
+            self.close_requested.set(true);
+            return Ok(());
+        }
+
+        // Step 4.
+        let unaligned = self
+            .pending_pull_intos
+            .borrow()
+            .front()
+            .map_or(false, |descriptor| {
+                descriptor.bytes_filled % descriptor.element_size != 0
+            });
+        if unaligned {
+            let global = self.global();
+            let cx = global.get_cx();
+            rooted!(in(*cx) let mut e = UndefinedValue());
+            new_type_error(
+                &global,
+                "The stream was closed in the middle of an element",
+                e.handle_mut(),
+            );
+            self.error(e.handle());
+            return Err(Error::Type(
+                "The stream was closed in the middle of an element".to_owned(),
+            ));
+        }
+
+        // Steps 5-6.
+        stream.close();
+        Ok(())
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-error>
+    pub fn error(&self, e: HandleValue) {
+        // Steps 1-2.
+        let stream = self.stream();
+        if !stream.is_readable() {
+            return;
+        }
+
+        // Steps 3-5.
+        self.clear_pending_pull_intos();
+        self.reset_queue();
+        stream.error_stream(e);
+    }
+
+    /// <https://streams.spec.whatwg.org/#rbs-controller-private-cancel>
+    pub fn perform_cancel_steps(&self, reason: HandleValue) -> Rc<Promise> {
+        // Steps 1-2.
+        self.clear_pending_pull_intos();
+        self.reset_queue();
+
+        // Steps 3-5.
+        self.underlying_source.cancel(&self.global(), reason)
+    }
+
+    /// <https://streams.spec.whatwg.org/#rbs-controller-private-pull>
+    pub fn perform_pull_steps(&self, read_request: ReadRequest) {
+        // Step 1.
+        let stream = self.stream();
+
+        // Step 3.
+        if self.queue_total_size.get() > 0 {
+            self.fill_read_request_from_queue(read_request);
+            return;
+        }
+
+        // Step 5.
+        if let Some(auto_allocate_chunk_size) = self.auto_allocate_chunk_size {
+            let cx = self.global().get_cx();
+            let byte_length = auto_allocate_chunk_size as usize;
+            rooted!(in(*cx) let mut view = ptr::null_mut::<JSObject>());
+            create_view(cx, Type::Uint8, &vec![0; byte_length], view.handle_mut());
+            self.pending_pull_intos
+                .borrow_mut()
+                .push_back(PullIntoDescriptor {
+                    view: Heap::boxed(view.get()),
+                    byte_length,
+                    bytes_filled: 0,
+                    minimum_fill: 1,
+                    element_size: 1,
+                    reader_type: ReaderType::Default,
+                });
+        }
+
+        // Steps 6-7.
+        stream.add_read_request(read_request);
+        self.call_pull_if_needed();
+    }
+
+    /// <https://streams.spec.whatwg.org/#abstract-opdef-readablebytestreamcontroller-releasesteps>
+    pub fn perform_release_steps(&self) {
+        let mut pending_pull_intos = self.pending_pull_intos.borrow_mut();
+        if let Some(mut first) = pending_pull_intos.pop_front() {
+            first.reader_type = ReaderType::None;
+            pending_pull_intos.clear();
+            pending_pull_intos.push_back(first);
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-pull-into>
+    pub fn pull_into(&self, view: *mut JSObject, read_into_request: ReadIntoRequest) {
+        // Step 1.
+        let stream = self.stream();
+        let global = self.global();
+        let cx = global.get_cx();
+
+        // Steps 2-14.
+        let element_size = element_size(view_type(cx, view));
+        let descriptor = PullIntoDescriptor {
+            view: Heap::boxed(view),
+            byte_length: view_bytes(cx, view).len(),
+            bytes_filled: 0,
+            minimum_fill: element_size,
+            element_size,
+            reader_type: ReaderType::Byob,
+        };
+
+        // Step 15.
+        if !self.pending_pull_intos.borrow().is_empty() {
+            self.pending_pull_intos.borrow_mut().push_back(descriptor);
+            stream.add_read_into_request(read_into_request);
+            return;
+        }
+
+        // Step 16.
+        if stream.is_closed() {
+            rooted!(in(*cx) let mut empty_view = ptr::null_mut::<JSObject>());
+            create_view(cx, view_type(cx, view), &[], empty_view.handle_mut());
+            rooted!(in(*cx) let empty_view = ObjectValue(empty_view.get()));
+            read_into_request.close_steps(empty_view.handle());
+            return;
+        }
+
+        // Step 17.
+        let mut descriptor = descriptor;
+        if self.queue_total_size.get() > 0 {
+            // Step 17.1.
+            if self.fill_pull_into_descriptor_from_queue(&mut descriptor) {
+                rooted!(in(*cx) let mut filled_view = ptr::null_mut::<JSObject>());
+                self.convert_pull_into_descriptor(&descriptor, filled_view.handle_mut());
+                self.handle_queue_drain();
+                rooted!(in(*cx) let filled_view = ObjectValue(filled_view.get()));
+                read_into_request.chunk_steps(filled_view.handle());
+                return;
+            }
+
+            // Step 17.2.
+            if self.close_requested.get() {
+                rooted!(in(*cx) let mut e = UndefinedValue());
+                new_type_error(&global, "The stream is closing", e.handle_mut());
+                self.error(e.handle());
+                read_into_request.error_steps(e.handle());
+                return;
+            }
+        }
+
+        // Steps 18-20.
+        self.pending_pull_intos.borrow_mut().push_back(descriptor);
+        stream.add_read_into_request(read_into_request);
+        self.call_pull_if_needed();
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-respond>
+    ///
+    /// The bytes were written to the view of the BYOB request, and are copied
+    /// to the view of the first pending pull-into.
+    pub fn respond(&self, bytes_written: usize, written: &[u8]) -> ErrorResult {
+        // Steps 1-2.
+        let (bytes_filled, byte_length, view) = {
+            let pending_pull_intos = self.pending_pull_intos.borrow();
+            let first = pending_pull_intos
+                .front()
+                .expect("There should be a pending pull-into");
+            (first.bytes_filled, first.byte_length, first.view.get())
+        };
+
+        // Steps 3-4.
+        if self.stream().is_closed() {
+            if bytes_written != 0 {
+                return Err(Error::Type(
+                    "A closed stream can only respond with zero bytes".to_owned(),
+                ));
+            }
+        } else {
+            if bytes_written == 0 {
+                return Err(Error::Type(
+                    "A readable stream cannot respond with zero bytes".to_owned(),
+                ));
+            }
+            if bytes_filled + bytes_written > byte_length {
+                return Err(Error::Range("Too many bytes were written".to_owned()));
+            }
+        }
+
+        // Step 5.
+        let cx = self.global().get_cx();
+        write_view_bytes(cx, view, bytes_filled, &written[..bytes_written]);
+
+        // Step 6.
+        self.respond_internal(bytes_written);
+        Ok(())
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-respond-internal>
+    fn respond_internal(&self, bytes_written: usize) {
+        // Step 2.
+        self.invalidate_byob_request();
+
+        // Steps 3-4.
+        if self.stream().is_closed() {
+            self.respond_in_closed_state();
+        } else {
+            self.respond_in_readable_state(bytes_written);
+        }
+
+        // Step 5.
+        self.call_pull_if_needed();
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-respond-in-closed-state>
+    fn respond_in_closed_state(&self) {
+        // Step 2.
+        let first_is_detached = self
+            .pending_pull_intos
+            .borrow()
+            .front()
+            .map_or(false, |descriptor| {
+                descriptor.reader_type == ReaderType::None
+            });
+        if first_is_detached {
+            self.pending_pull_intos.borrow_mut().pop_front();
+        }
+
+        // Step 3.
+        let stream = self.stream();
+        if stream.has_byob_reader() {
+            while stream.get_num_read_into_requests() > 0 {
+                let descriptor = match self.pending_pull_intos.borrow_mut().pop_front() {
+                    Some(descriptor) => descriptor,
+                    None => break,
+                };
+                self.commit_pull_into_descriptor(descriptor);
+            }
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-respond-in-readable-state>
+    fn respond_in_readable_state(&self, bytes_written: usize) {
+        // Step 2.
+        let (reader_type, bytes_filled, minimum_fill) = {
+            let mut pending_pull_intos = self.pending_pull_intos.borrow_mut();
+            let first = pending_pull_intos
+                .front_mut()
+                .expect("There should be a pending pull-into");
+            first.bytes_filled += bytes_written;
+            (first.reader_type, first.bytes_filled, first.minimum_fill)
+        };
+
+        // Step 3.
+        if reader_type == ReaderType::None {
+            self.enqueue_detached_pull_into_to_queue();
+            for descriptor in self.process_pull_into_descriptors_using_queue() {
+                self.commit_pull_into_descriptor(descriptor);
+            }
+            return;
+        }
+
+        // Step 4.
+        if bytes_filled < minimum_fill {
+            return;
+        }
+
+        // Step 5.
+        let mut descriptor = self
+            .pending_pull_intos
+            .borrow_mut()
+            .pop_front()
+            .expect("There should be a pending pull-into");
+
+        // Step 6.
+        let remainder_size = descriptor.bytes_filled % descriptor.element_size;
+
+        // Step 7.
+        if remainder_size > 0 {
+            let cx = self.global().get_cx();
+            let bytes = view_bytes(cx, descriptor.view.get());
+            let end = descriptor.bytes_filled;
+            self.enqueue_chunk_to_queue(bytes[end - remainder_size..end].to_vec());
+        }
+
+        // Steps 8-10.
+        descriptor.bytes_filled -= remainder_size;
+        let filled_pull_intos = self.process_pull_into_descriptors_using_queue();
+        self.commit_pull_into_descriptor(descriptor);
+        for descriptor in filled_pull_intos {
+            self.commit_pull_into_descriptor(descriptor);
+        }
+    }
+
+    /// Does the controller have a pull-into waiting for bytes?
+    pub fn has_pending_pull_intos(&self) -> bool {
+        !self.pending_pull_intos.borrow().is_empty()
+    }
+
+    /// The bytes the view of a BYOB request can hold.
+    pub fn get_byob_request_length(&self) -> usize {
+        self.pending_pull_intos
+            .borrow()
+            .front()
+            .map_or(0, |descriptor| {
+                descriptor.byte_length - descriptor.bytes_filled
+            })
+    }
+}
+
+impl ReadableByteStreamControllerMethods for ReadableByteStreamController {
+    /// <https://streams.spec.whatwg.org/#rbs-controller-byob-request>
+    fn GetByobRequest(&self) -> Option<DomRoot<ReadableStreamBYOBRequest>> {
+        // Step 1.
+        if self.byob_request.get().is_none() && !self.pending_pull_intos.borrow().is_empty() {
+            let global = self.global();
+            let cx = global.get_cx();
+            rooted!(in(*cx) let mut view = ptr::null_mut::<JSObject>());
+            create_view(
+                cx,
+                Type::Uint8,
+                &vec![0; self.get_byob_request_length()],
+                view.handle_mut(),
+            );
+            let byob_request = ReadableStreamBYOBRequest::new(&global, self, view.get());
+            self.byob_request.set(Some(&*byob_request));
+        }
+
+        // Step 2.
+        self.byob_request.get()
+    }
+
+    /// <https://streams.spec.whatwg.org/#rbs-controller-desired-size>
+    fn GetDesiredSize(&self) -> Option<f64> {
+        self.get_desired_size()
+    }
+
+    /// <https://streams.spec.whatwg.org/#rbs-controller-close>
+    fn Close(&self) -> ErrorResult {
+        // Steps 1-2.
+        if self.close_requested.get() || !self.stream().is_readable() {
+            return Err(Error::Type("The stream cannot be closed".to_owned()));
+        }
+
+        // Step 3.
+        self.close()
+    }
+
+    /// <https://streams.spec.whatwg.org/#rbs-controller-enqueue>
+    #[allow(unsafe_code)]
+    fn Enqueue(&self, chunk: CustomAutoRooterGuard<ArrayBufferView>) -> ErrorResult {
+        // Steps 1-2.
+        let bytes = unsafe { chunk.as_slice() }.to_vec();
+        if bytes.is_empty() {
+            return Err(Error::Type("The chunk is empty".to_owned()));
+        }
+
+        // Steps 3-4.
+        if self.close_requested.get() || !self.stream().is_readable() {
+            return Err(Error::Type("Chunks cannot be enqueued".to_owned()));
+        }
+
+        // Step 5.
+        self.enqueue(bytes);
+        Ok(())
+    }
+
+    /// <https://streams.spec.whatwg.org/#rbs-controller-error>
+    fn Error(&self, _cx: JSContext, e: HandleValue) {
+        self.error(e);
+    }
+}
+
+/// The algorithms of a controller that wait for a promise.
+#[derive(Clone, Copy, JSTraceable, MallocSizeOf)]
+enum ControllerStep {
+    /// The start algorithm settled.
+    Start,
+    /// The pull algorithm settled.
+    Pull,
+}
+
+#[derive(JSTraceable, MallocSizeOf)]
+struct ControllerPromiseHandler {
+    controller: DomRoot<ReadableByteStreamController>,
+    step: ControllerStep,
+    fulfilled: bool,
+}
+
+impl Callback for ControllerPromiseHandler {
+    fn callback(&self, _cx: JSContext, v: HandleValue, _realm: InRealm) {
+        let controller = &self.controller;
+        if !self.fulfilled {
+            controller.error(v);
+            return;
+        }
+        match self.step {
+            ControllerStep::Start => {
+                controller.started.set(true);
+            },
+            ControllerStep::Pull => {
+                controller.pulling.set(false);
+                if !controller.pull_again.replace(false) {
+                    return;
+                }
+            },
+        }
+        controller.call_pull_if_needed();
+    }
+}
+
+/// The type of the elements of a view, which is `MaxTypedArrayViewType` for a `DataView`.
+#[allow(unsafe_code)]
+fn view_type(cx: JSContext, view: *mut JSObject) -> Type {
+    typedarray!(in(*cx) let view: ArrayBufferView = view);
+    view.map_or(Type::Uint8, |view| view.get_array_type())
+}
+
+fn element_size(view_type: Type) -> usize {
+    match view_type {
+        Type::Int16 | Type::Uint16 => 2,
+        Type::Int32 | Type::Uint32 | Type::Float32 => 4,
+        Type::Float64 | Type::BigInt64 | Type::BigUint64 => 8,
+        _ => 1,
+    }
+}
+
+/// A copy of the bytes of a view.
+#[allow(unsafe_code)]
+pub fn view_bytes(cx: JSContext, view: *mut JSObject) -> Vec<u8> {
+    typedarray!(in(*cx) let view: ArrayBufferView = view);
+    view.map_or(vec![], |view| unsafe { view.as_slice() }.to_vec())
+}
+
+/// Copies bytes into a view, starting at an offset.
+#[allow(unsafe_code)]
+fn write_view_bytes(cx: JSContext, view: *mut JSObject, offset: usize, bytes: &[u8]) {
+    typedarray!(in(*cx) let view: ArrayBufferView = view);
+    if let Ok(mut view) = view {
+        // No script runs while the view is written to.
+        let dest = unsafe { view.as_mut_slice() };
+        dest[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+}
+
+#[allow(unsafe_code)]
+unsafe fn create_typed_array<T>(cx: JSContext, length: usize, rval: MutableHandleObject)
+where
+    T: TypedArrayElementCreator,
+{
+    let _ = <TypedArray<T, *mut JSObject>>::create(*cx, CreateWith::Length(length as _), rval);
+}
+
+/// Creates a view of a given type, holding a copy of some bytes.
+#[allow(unsafe_code)]
+pub fn create_view(cx: JSContext, view_type: Type, bytes: &[u8], mut rval: MutableHandleObject) {
+    let length = bytes.len() / element_size(view_type);
+    unsafe {
+        match view_type {
+            Type::Int8 => create_typed_array::<Int8>(cx, length, rval),
+            Type::Uint8Clamped => create_typed_array::<ClampedU8>(cx, length, rval),
+            Type::Int16 => create_typed_array::<Int16>(cx, length, rval),
+            Type::Uint16 => create_typed_array::<Uint16>(cx, length, rval),
+            Type::Int32 => create_typed_array::<Int32>(cx, length, rval),
+            Type::Uint32 => create_typed_array::<Uint32>(cx, length, rval),
+            Type::Float32 => create_typed_array::<Float32>(cx, length, rval),
+            Type::Float64 => create_typed_array::<Float64>(cx, length, rval),
+            Type::MaxTypedArrayViewType => {
+                rooted!(in(*cx) let mut buffer = ptr::null_mut::<JSObject>());
+                let _ = ArrayBuffer::create(*cx, CreateWith::Slice(bytes), buffer.handle_mut());
+                rval.set(JS_NewDataView(
+                    *cx,
+                    buffer.handle().into(),
+                    0,
+                    bytes.len() as _,
+                ));
+                return;
+            },
+            _ => create_typed_array::<Uint8>(cx, length, rval),
+        }
+    }
+    write_view_bytes(cx, rval.get(), 0, bytes);
+}
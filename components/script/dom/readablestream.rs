@@ -1,105 +1,185 @@
 /* This Source Code Form is subject to the terms of the Mozilla Public
  * License, v. 2.0. If a copy of the MPL was not distributed with this
- * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
-
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::abortsignal::{AbortAlgorithm, AbortSignal};
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::QueuingStrategyBinding::QueuingStrategy;
+use crate::dom::bindings::codegen::Bindings::ReadableByteStreamControllerBinding::ReadableByteStreamControllerMethods;
+use crate::dom::bindings::codegen::Bindings::ReadableStreamBinding::{
+    ReadableStreamGetReaderOptions, ReadableStreamMethods, ReadableStreamReaderMode,
+    ReadableWritablePair, StreamPipeOptions,
+};
+use crate::dom::bindings::codegen::Bindings::UnderlyingSourceBinding::{
+    ReadableStreamType, UnderlyingSource,
+};
+use crate::dom::bindings::codegen::UnionTypes::ReadableStreamDefaultReaderOrReadableStreamBYOBReader as ReadableStreamReader;
 use crate::dom::bindings::conversions::{ConversionBehavior, ConversionResult};
-use crate::dom::bindings::error::Error;
+use crate::dom::bindings::error::{Error, Fallible};
 use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
-use crate::dom::bindings::root::DomRoot;
-use crate::dom::bindings::settings_stack::{AutoEntryScript, AutoIncumbentScript};
+use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
 use crate::dom::bindings::utils::get_dictionary_property;
 use crate::dom::globalscope::GlobalScope;
 use crate::dom::promise::Promise;
-use crate::js::conversions::FromJSValConvertible;
+use crate::dom::promisenativehandler::{Callback, PromiseNativeHandler};
+use crate::dom::queuingstrategy::{extract_high_water_mark, extract_size_algorithm, SizeAlgorithm};
+use crate::dom::readablebytestreamcontroller::{view_bytes, ReadableByteStreamController};
+use crate::dom::readablestreambyobreader::{ReadIntoRequest, ReadableStreamBYOBReader};
+use crate::dom::readablestreamdefaultcontroller::ReadableStreamDefaultController;
+use crate::dom::readablestreamdefaultreader::{ReadRequest, ReadableStreamDefaultReader};
+use crate::dom::underlyingsourcecontainer::{resolved_promise, UnderlyingSourceType};
+use crate::dom::writablestream::WritableStream;
+use crate::dom::writablestreamdefaultwriter::WritableStreamDefaultWriter;
+use crate::js::conversions::{FromJSValConvertible, ToJSValConvertible};
+use crate::microtask::{Microtask, MicrotaskRunnable};
 use crate::realms::{enter_realm, InRealm};
 use crate::script_runtime::JSContext as SafeJSContext;
 use dom_struct::dom_struct;
-use js::glue::{
-    CreateReadableStreamUnderlyingSource, DeleteReadableStreamUnderlyingSource,
-    ReadableStreamUnderlyingSourceTraps,
-};
-use js::jsapi::{HandleObject, HandleValue, Heap, JSContext, JSObject};
-use js::jsapi::{
-    IsReadableStream, NewReadableExternalSourceStreamObject, ReadableStreamClose,
-    ReadableStreamDefaultReaderRead, ReadableStreamError, ReadableStreamGetReader,
-    ReadableStreamIsDisturbed, ReadableStreamIsLocked, ReadableStreamIsReadable,
-    ReadableStreamReaderMode, ReadableStreamReaderReleaseLock, ReadableStreamUnderlyingSource,
-    ReadableStreamUpdateDataAvailableFromSource, UnwrapReadableStream,
-};
-use js::jsval::JSVal;
-use js::jsval::UndefinedValue;
-use js::rust::HandleValue as SafeHandleValue;
-use js::rust::IntoHandle;
-use std::cell::{Cell, RefCell};
-use std::os::raw::c_void;
-use std::ptr::{self, NonNull};
+use js::jsapi::{Heap, JSObject};
+use js::jsval::{JSVal, ObjectValue, UndefinedValue};
+use js::rust::{HandleValue as SafeHandleValue, MutableHandleValue as SafeMutableHandleValue};
+use js::typedarray::{ArrayBufferView, Uint8Array};
+use std::cell::Cell;
+use std::ptr;
 use std::rc::Rc;
-use std::slice;
-
-static UNDERLYING_SOURCE_TRAPS: ReadableStreamUnderlyingSourceTraps =
-    ReadableStreamUnderlyingSourceTraps {
-        requestData: Some(request_data),
-        writeIntoReadRequestBuffer: Some(write_into_read_request_buffer),
-        cancel: Some(cancel),
-        onClosed: Some(close),
-        onErrored: Some(error),
-        finalize: Some(finalize),
-    };
 
+/// <https://streams.spec.whatwg.org/#readablestream-state>
+#[derive(Clone, Copy, Debug, JSTraceable, MallocSizeOf, PartialEq)]
+pub enum ReadableStreamState {
+    Readable,
+    Closed,
+    Errored,
+}
+
+/// The kinds of underlying sources fed by native code.
+#[derive(Clone, Copy, JSTraceable, MallocSizeOf)]
+pub enum ExternalUnderlyingSource {
+    /// Facilitate partial integration with sources
+    /// that are currently read into memory.
+    Memory(usize),
+    /// A blob as underlying source, with a known total size.
+    Blob(usize),
+    /// A fetch response as underlying source.
+    FetchResponse,
+}
+
+/// <https://streams.spec.whatwg.org/#rs-class>
 #[dom_struct]
 pub struct ReadableStream {
     reflector_: Reflector,
-    #[ignore_malloc_size_of = "SM handles JS values"]
-    js_stream: Heap<*mut JSObject>,
-    #[ignore_malloc_size_of = "SM handles JS values"]
-    js_reader: Heap<*mut JSObject>,
-    has_reader: Cell<bool>,
-    #[ignore_malloc_size_of = "Rc is hard"]
-    external_underlying_source: Option<Rc<ExternalUnderlyingSourceController>>,
+    /// <https://streams.spec.whatwg.org/#readablestream-controller>,
+    /// for streams that are not byte streams.
+    default_controller: MutNullableDom<ReadableStreamDefaultController>,
+    /// <https://streams.spec.whatwg.org/#readablestream-controller>,
+    /// for byte streams.
+    byte_controller: MutNullableDom<ReadableByteStreamController>,
+    /// <https://streams.spec.whatwg.org/#readablestream-reader>,
+    /// when it is a default reader.
+    default_reader: MutNullableDom<ReadableStreamDefaultReader>,
+    /// <https://streams.spec.whatwg.org/#readablestream-reader>,
+    /// when it is a BYOB reader.
+    byob_reader: MutNullableDom<ReadableStreamBYOBReader>,
+    /// <https://streams.spec.whatwg.org/#readablestream-state>
+    state: Cell<ReadableStreamState>,
+    /// <https://streams.spec.whatwg.org/#readablestream-storederror>
+    #[ignore_malloc_size_of = "Defined in rust-mozjs"]
+    stored_error: Heap<JSVal>,
+    /// <https://streams.spec.whatwg.org/#readablestream-disturbed>
+    disturbed: Cell<bool>,
 }
 
 impl ReadableStream {
-    fn new_inherited(
-        external_underlying_source: Option<Rc<ExternalUnderlyingSourceController>>,
-    ) -> ReadableStream {
+    /// <https://streams.spec.whatwg.org/#initialize-readable-stream>
+    #[allow(unrooted_must_root)]
+    fn new_inherited() -> ReadableStream {
         ReadableStream {
             reflector_: Reflector::new(),
-            js_stream: Heap::default(),
-            js_reader: Heap::default(),
-            has_reader: Default::default(),
-            external_underlying_source: external_underlying_source,
+            default_controller: MutNullableDom::new(None),
+            byte_controller: MutNullableDom::new(None),
+            default_reader: MutNullableDom::new(None),
+            byob_reader: MutNullableDom::new(None),
+            state: Cell::new(ReadableStreamState::Readable),
+            stored_error: Heap::default(),
+            disturbed: Cell::new(false),
         }
     }
 
-    fn new(
-        global: &GlobalScope,
-        external_underlying_source: Option<Rc<ExternalUnderlyingSourceController>>,
-    ) -> DomRoot<ReadableStream> {
-        reflect_dom_object(
-            Box::new(ReadableStream::new_inherited(external_underlying_source)),
-            global,
-        )
+    fn new(global: &GlobalScope) -> DomRoot<ReadableStream> {
+        reflect_dom_object(Box::new(ReadableStream::new_inherited()), global)
     }
 
-    /// Used from RustCodegen.py
-    #[allow(unsafe_code)]
-    pub unsafe fn from_js(
+    /// <https://streams.spec.whatwg.org/#rs-constructor>
+    #[allow(non_snake_case, unsafe_code)]
+    pub fn Constructor(
         cx: SafeJSContext,
-        obj: *mut JSObject,
-        realm: InRealm,
-    ) -> Result<DomRoot<ReadableStream>, ()> {
-        if !IsReadableStream(obj) {
-            return Err(());
-        }
+        global: &GlobalScope,
+        underlying_source: Option<*mut JSObject>,
+        strategy: &QueuingStrategy,
+    ) -> Fallible<DomRoot<ReadableStream>> {
+        // Steps 1-2.
+        let underlying_source_obj = underlying_source.unwrap_or(ptr::null_mut());
+        rooted!(in(*cx) let underlying_source_val = if underlying_source_obj.is_null() {
+            UndefinedValue()
+        } else {
+            ObjectValue(underlying_source_obj)
+        });
+        let underlying_source_dict = match UnderlyingSource::new(cx, underlying_source_val.handle())
+        {
+            Ok(ConversionResult::Success(dict)) => dict,
+            Ok(ConversionResult::Failure(error)) => return Err(Error::Type(error.to_string())),
+            Err(()) => return Err(Error::JSFailed),
+        };
 
-        let global = GlobalScope::from_safe_context(cx, realm);
+        // Step 3.
+        let stream = ReadableStream::new(global);
 
-        let stream = ReadableStream::new(&global, None);
-        stream.js_stream.set(UnwrapReadableStream(obj));
+        let source = UnderlyingSourceType::new_js(underlying_source_obj, &underlying_source_dict);
+        if underlying_source_dict.type_ == Some(ReadableStreamType::Bytes) {
+            // Step 4.1.
+            if strategy.size.is_some() {
+                return Err(Error::Range(
+                    "A byte stream cannot have a size function".to_owned(),
+                ));
+            }
+
+            // Steps 4.2-4.3.
+            let high_water_mark = extract_high_water_mark(strategy, 0.)?;
+            ReadableByteStreamController::set_up_from_underlying_source(
+                &stream,
+                source,
+                underlying_source_dict.autoAllocateChunkSize,
+                high_water_mark,
+            )?;
+        } else {
+            // Steps 5.2-5.4.
+            let size_algorithm = extract_size_algorithm(strategy);
+            let high_water_mark = extract_high_water_mark(strategy, 1.)?;
+            ReadableStreamDefaultController::set_up(
+                &stream,
+                source,
+                high_water_mark,
+                size_algorithm,
+            )?;
+        }
 
         Ok(stream)
     }
 
+    /// <https://streams.spec.whatwg.org/#create-readable-stream>
+    ///
+    /// Creates a stream with a default controller, used by streams that are
+    /// set up by other streams.
+    pub fn new_with_source(
+        global: &GlobalScope,
+        source: UnderlyingSourceType,
+        high_water_mark: f64,
+        size_algorithm: SizeAlgorithm,
+    ) -> Fallible<DomRoot<ReadableStream>> {
+        let stream = ReadableStream::new(global);
+        ReadableStreamDefaultController::set_up(&stream, source, high_water_mark, size_algorithm)?;
+        Ok(stream)
+    }
+
     /// Build a stream backed by a Rust source that has already been read into memory.
     pub fn new_from_bytes(global: &GlobalScope, bytes: Vec<u8>) -> DomRoot<ReadableStream> {
         let stream = ReadableStream::new_with_external_underlying_source(
@@ -111,399 +191,1555 @@ impl ReadableStream {
         stream
     }
 
-    /// Build a stream backed by a Rust underlying source.
-    #[allow(unsafe_code)]
+    /// Build a byte stream backed by a Rust underlying source.
     pub fn new_with_external_underlying_source(
         global: &GlobalScope,
         source: ExternalUnderlyingSource,
     ) -> DomRoot<ReadableStream> {
-        let _ar = enter_realm(global);
-        let _ais = AutoIncumbentScript::new(global);
-        let cx = global.get_cx();
+        let stream = ReadableStream::new(global);
+        ReadableByteStreamController::set_up_from_underlying_source(
+            &stream,
+            UnderlyingSourceType::External(source),
+            None,
+            0.,
+        )
+        .expect("Setting up a native byte stream cannot fail");
+        stream
+    }
 
-        let source = Rc::new(ExternalUnderlyingSourceController::new(source));
+    pub fn set_default_controller(&self, controller: &ReadableStreamDefaultController) {
+        assert!(self.byte_controller.get().is_none());
+        self.default_controller.set(Some(controller));
+    }
 
-        let stream = ReadableStream::new(&global, Some(source.clone()));
+    pub fn set_byte_controller(&self, controller: &ReadableByteStreamController) {
+        assert!(self.default_controller.get().is_none());
+        self.byte_controller.set(Some(controller));
+    }
 
-        unsafe {
-            let js_wrapper = CreateReadableStreamUnderlyingSource(
-                &UNDERLYING_SOURCE_TRAPS,
-                &*source as *const _ as *const c_void,
-            );
+    /// The controller of a stream that is not a byte stream.
+    pub fn get_default_controller(&self) -> DomRoot<ReadableStreamDefaultController> {
+        self.default_controller
+            .get()
+            .expect("Stream should have a default controller")
+    }
 
-            rooted!(in(*cx)
-                let js_stream = NewReadableExternalSourceStreamObject(
-                    *cx,
-                    js_wrapper,
-                    ptr::null_mut(),
-                    HandleObject::null(),
-                )
-            );
+    /// The controller of a byte stream.
+    pub fn get_byte_controller(&self) -> DomRoot<ReadableByteStreamController> {
+        self.byte_controller
+            .get()
+            .expect("Stream should have a byte controller")
+    }
+
+    pub fn is_byte_stream(&self) -> bool {
+        self.byte_controller.get().is_some()
+    }
+
+    pub fn set_default_reader(&self, reader: Option<&ReadableStreamDefaultReader>) {
+        self.default_reader.set(reader);
+    }
+
+    pub fn set_byob_reader(&self, reader: Option<&ReadableStreamBYOBReader>) {
+        self.byob_reader.set(reader);
+    }
+
+    pub fn get_default_reader(&self) -> Option<DomRoot<ReadableStreamDefaultReader>> {
+        self.default_reader.get()
+    }
+
+    pub fn get_byob_reader(&self) -> Option<DomRoot<ReadableStreamBYOBReader>> {
+        self.byob_reader.get()
+    }
+
+    pub fn state(&self) -> ReadableStreamState {
+        self.state.get()
+    }
+
+    pub fn is_readable(&self) -> bool {
+        self.state.get() == ReadableStreamState::Readable
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.state.get() == ReadableStreamState::Closed
+    }
+
+    pub fn is_errored(&self) -> bool {
+        self.state.get() == ReadableStreamState::Errored
+    }
+
+    pub fn get_stored_error(&self, mut rval: SafeMutableHandleValue) {
+        rval.set(self.stored_error.get());
+    }
+
+    pub fn set_disturbed(&self) {
+        self.disturbed.set(true);
+    }
+
+    /// <https://streams.spec.whatwg.org/#is-readable-stream-disturbed>
+    pub fn is_disturbed(&self) -> bool {
+        self.disturbed.get()
+    }
+
+    /// <https://streams.spec.whatwg.org/#is-readable-stream-locked>
+    pub fn is_locked(&self) -> bool {
+        self.default_reader.get().is_some() || self.byob_reader.get().is_some()
+    }
 
-            stream.js_stream.set(UnwrapReadableStream(js_stream.get()));
+    /// <https://streams.spec.whatwg.org/#readable-stream-has-default-reader>
+    pub fn has_default_reader(&self) -> bool {
+        self.default_reader.get().is_some()
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-has-byob-reader>
+    pub fn has_byob_reader(&self) -> bool {
+        self.byob_reader.get().is_some()
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-get-num-read-requests>
+    pub fn get_num_read_requests(&self) -> usize {
+        self.default_reader
+            .get()
+            .map_or(0, |reader| reader.get_num_read_requests())
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-get-num-read-into-requests>
+    pub fn get_num_read_into_requests(&self) -> usize {
+        self.byob_reader
+            .get()
+            .map_or(0, |reader| reader.get_num_read_into_requests())
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-add-read-request>
+    pub fn add_read_request(&self, read_request: ReadRequest) {
+        assert!(self.is_readable());
+        self.default_reader
+            .get()
+            .expect("Stream should have a default reader")
+            .add_read_request(read_request);
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-add-read-into-request>
+    pub fn add_read_into_request(&self, read_into_request: ReadIntoRequest) {
+        assert!(self.is_readable() || self.is_closed());
+        self.byob_reader
+            .get()
+            .expect("Stream should have a BYOB reader")
+            .add_read_into_request(read_into_request);
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-fulfill-read-request>
+    pub fn fulfill_read_request(&self, chunk: SafeHandleValue, done: bool) {
+        let reader = self
+            .default_reader
+            .get()
+            .expect("Stream should have a default reader");
+        let read_request = reader.take_first_read_request();
+        if done {
+            read_request.close_steps();
+        } else {
+            read_request.chunk_steps(chunk);
         }
+    }
 
-        stream
+    /// <https://streams.spec.whatwg.org/#readable-stream-fulfill-read-into-request>
+    pub fn fulfill_read_into_request(&self, chunk: SafeHandleValue, done: bool) {
+        let reader = self
+            .byob_reader
+            .get()
+            .expect("Stream should have a BYOB reader");
+        let read_into_request = reader.take_first_read_into_request();
+        if done {
+            read_into_request.close_steps(chunk);
+        } else {
+            read_into_request.chunk_steps(chunk);
+        }
     }
 
-    /// Get a pointer to the underlying JS object.
-    pub fn get_js_stream(&self) -> NonNull<JSObject> {
-        NonNull::new(self.js_stream.get())
-            .expect("Couldn't get a non-null pointer to JS stream object.")
+    /// <https://streams.spec.whatwg.org/#readable-stream-close>
+    pub fn close(&self) {
+        // Steps 1-2.
+        assert!(self.is_readable());
+        self.state.set(ReadableStreamState::Closed);
+
+        // Steps 3-5.
+        if let Some(reader) = self.default_reader.get() {
+            reader.resolve_closed_promise();
+            // Step 6.
+            reader.close_read_requests();
+        } else if let Some(reader) = self.byob_reader.get() {
+            reader.resolve_closed_promise();
+        }
     }
 
-    #[allow(unsafe_code)]
-    pub fn enqueue_native(&self, bytes: Vec<u8>) {
+    /// <https://streams.spec.whatwg.org/#readable-stream-error>
+    pub fn error_stream(&self, e: SafeHandleValue) {
+        // Steps 1-3.
+        assert!(self.is_readable());
+        self.state.set(ReadableStreamState::Errored);
+        self.stored_error.set(e.get());
+
+        // Steps 4-8.
+        if let Some(reader) = self.default_reader.get() {
+            reader.reject_closed_promise(e);
+            reader.error_read_requests(e);
+        } else if let Some(reader) = self.byob_reader.get() {
+            reader.reject_closed_promise(e);
+            reader.error_read_into_requests(e);
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-cancel>
+    pub fn cancel(&self, reason: SafeHandleValue) -> Rc<Promise> {
         let global = self.global();
-        let _ar = enter_realm(&*global);
         let cx = global.get_cx();
 
-        let handle = unsafe { self.js_stream.handle() };
+        // Step 1.
+        self.disturbed.set(true);
+
+        // Step 2.
+        if self.is_closed() {
+            return resolved_promise(&global);
+        }
+
+        // Step 3.
+        if self.is_errored() {
+            rooted!(in(*cx) let stored_error = self.stored_error.get());
+            return Promise::new_rejected(&global, cx, stored_error.handle())
+                .expect("Couldn't reject a promise");
+        }
+
+        // Step 4.
+        self.close();
+
+        // Step 5.
+        if let Some(reader) = self.byob_reader.get() {
+            reader.close_read_into_requests();
+        }
+
+        // Step 6.
+        let source_cancel_promise = match self.default_controller.get() {
+            Some(controller) => controller.perform_cancel_steps(reason),
+            None => self.get_byte_controller().perform_cancel_steps(reason),
+        };
 
-        self.external_underlying_source
-            .as_ref()
-            .expect("No external source to enqueue bytes.")
-            .enqueue_chunk(cx, handle, bytes);
+        // Step 7.
+        let result_promise = Promise::new(&global);
+        let handler = PromiseNativeHandler::new(
+            &global,
+            Some(Box::new(ResolveWithUndefined {
+                promise: result_promise.clone(),
+            })),
+            Some(Box::new(RejectWithReason {
+                promise: result_promise.clone(),
+            })),
+        );
+        let realm = enter_realm(&*global);
+        let comp = InRealm::Entered(&realm);
+        source_cancel_promise.append_native_handler(&handler, comp);
+        result_promise
     }
 
-    #[allow(unsafe_code)]
-    pub fn error_native(&self, error: Error) {
-        let global = self.global();
-        let _ar = enter_realm(&*global);
-        let cx = global.get_cx();
+    /// The [[PullSteps]] of the controller.
+    pub fn perform_pull_steps(&self, read_request: ReadRequest) {
+        match self.default_controller.get() {
+            Some(controller) => controller.perform_pull_steps(read_request),
+            None => self.get_byte_controller().perform_pull_steps(read_request),
+        }
+    }
 
-        unsafe {
-            rooted!(in(*cx) let mut js_error = UndefinedValue());
-            error.to_jsval(*cx, &global, js_error.handle_mut());
-            ReadableStreamError(
-                *cx,
-                self.js_stream.handle(),
-                js_error.handle().into_handle(),
-            );
+    /// The [[ReleaseSteps]] of the controller.
+    pub fn perform_release_steps(&self) {
+        match self.default_controller.get() {
+            Some(controller) => controller.perform_release_steps(),
+            None => self.get_byte_controller().perform_release_steps(),
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readablestream-error>
+    ///
+    /// Error the stream with a JS value, if it is still readable.
+    pub fn error(&self, error: SafeHandleValue) {
+        match self.default_controller.get() {
+            Some(controller) => controller.error(error),
+            None => self.get_byte_controller().error(error),
         }
     }
 
     #[allow(unsafe_code)]
-    pub fn close_native(&self) {
+    pub fn error_native(&self, error: Error) {
         let global = self.global();
         let _ar = enter_realm(&*global);
         let cx = global.get_cx();
 
-        let handle = unsafe { self.js_stream.handle() };
+        rooted!(in(*cx) let mut js_error = UndefinedValue());
+        unsafe { error.to_jsval(*cx, &global, js_error.handle_mut()) };
+        self.error(js_error.handle());
+    }
 
-        self.external_underlying_source
-            .as_ref()
-            .expect("No external source to close.")
-            .close(cx, handle);
+    /// Enqueue bytes provided by a native source.
+    pub fn enqueue_native(&self, bytes: Vec<u8>) {
+        self.get_byte_controller().enqueue_native(bytes);
+    }
+
+    /// <https://streams.spec.whatwg.org/#readablestream-close>
+    ///
+    /// Close a stream fed by a native source.
+    pub fn close_native(&self) {
+        self.get_byte_controller().close_native();
     }
 
     /// Does the stream have all data in memory?
     pub fn in_memory(&self) -> bool {
-        self.external_underlying_source
-            .as_ref()
-            .map(|source| source.in_memory())
-            .unwrap_or(false)
+        self.byte_controller
+            .get()
+            .map_or(false, |controller| controller.source_in_memory())
     }
 
     /// Return bytes for synchronous use, if the stream has all data in memory.
     pub fn get_in_memory_bytes(&self) -> Option<Vec<u8>> {
-        self.external_underlying_source
-            .as_ref()
-            .and_then(|source| source.get_in_memory_bytes())
+        if !self.in_memory() || self.is_disturbed() {
+            return None;
+        }
+        Some(self.get_byte_controller().get_queued_bytes())
     }
 
     /// Acquires a reader and locks the stream,
     /// must be done before `read_a_chunk`.
-    #[allow(unsafe_code)]
     pub fn start_reading(&self) -> Result<(), ()> {
         if self.is_locked() || self.is_disturbed() {
             return Err(());
         }
+        ReadableStreamDefaultReader::acquire(&self.global(), self)
+            .map(|_| ())
+            .map_err(|_| ())
+    }
 
+    /// Read a chunk from the stream,
+    /// must be called after `start_reading`,
+    /// and before `stop_reading`.
+    pub fn read_a_chunk(&self) -> Rc<Promise> {
+        self.default_reader
+            .get()
+            .expect("Attempt to read stream chunk without having acquired a reader.")
+            .read()
+    }
+
+    /// Releases the lock on the reader,
+    /// must be done after `start_reading`.
+    pub fn stop_reading(&self) {
+        self.default_reader
+            .get()
+            .expect("ReadableStream::stop_reading called on a readerless stream.")
+            .release();
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-tee>
+    #[allow(unrooted_must_root)]
+    pub fn default_tee(&self) -> Fallible<Vec<DomRoot<ReadableStream>>> {
         let global = self.global();
-        let _ar = enter_realm(&*global);
-        let cx = global.get_cx();
 
-        unsafe {
-            rooted!(in(*cx) let reader = ReadableStreamGetReader(
-                *cx,
-                self.js_stream.handle(),
-                ReadableStreamReaderMode::Default,
-            ));
+        // Step 3.
+        let reader = ReadableStreamDefaultReader::acquire(&global, self)?;
+
+        // Steps 4-11.
+        let tee_state = Rc::new(DefaultTeeState {
+            stream: Dom::from_ref(self),
+            reader: Dom::from_ref(&*reader),
+            branch_1: MutNullableDom::new(None),
+            branch_2: MutNullableDom::new(None),
+            reading: Cell::new(false),
+            read_again: Cell::new(false),
+            canceled_1: Cell::new(false),
+            canceled_2: Cell::new(false),
+            reason_1: Heap::default(),
+            reason_2: Heap::default(),
+            cancel_promise: Promise::new(&global),
+        });
+
+        // Steps 12-17.
+        let branch_1 = ReadableStream::new_with_source(
+            &global,
+            UnderlyingSourceType::Tee(tee_state.clone(), TeeBranch::One),
+            1.,
+            SizeAlgorithm::ReturnOne,
+        )?;
+        let branch_2 = ReadableStream::new_with_source(
+            &global,
+            UnderlyingSourceType::Tee(tee_state.clone(), TeeBranch::Two),
+            1.,
+            SizeAlgorithm::ReturnOne,
+        )?;
+        tee_state.branch_1.set(Some(&*branch_1));
+        tee_state.branch_2.set(Some(&*branch_2));
+
+        // Step 18.
+        let handler = PromiseNativeHandler::new(
+            &global,
+            None,
+            Some(Box::new(TeeClosedPromiseRejectionHandler {
+                tee_state: tee_state.clone(),
+            })),
+        );
+        let realm = enter_realm(&*global);
+        let comp = InRealm::Entered(&realm);
+        reader
+            .closed_promise()
+            .append_native_handler(&handler, comp);
+
+        // Step 19.
+        Ok(vec![branch_1, branch_2])
+    }
+
+    /// <https://streams.spec.whatwg.org/#abstract-opdef-readablebytestreamtee>
+    #[allow(unrooted_must_root)]
+    pub fn byte_tee(&self) -> Fallible<Vec<DomRoot<ReadableStream>>> {
+        let global = self.global();
+
+        // Step 2.
+        let reader = ReadableStreamDefaultReader::acquire(&global, self)?;
+
+        // Steps 3-11.
+        let tee_state = Rc::new(ByteTeeState {
+            stream: Dom::from_ref(self),
+            default_reader: MutNullableDom::new(Some(&*reader)),
+            byob_reader: MutNullableDom::new(None),
+            reader_generation: Cell::new(0),
+            branch_1: MutNullableDom::new(None),
+            branch_2: MutNullableDom::new(None),
+            reading: Cell::new(false),
+            read_again_for_branch_1: Cell::new(false),
+            read_again_for_branch_2: Cell::new(false),
+            canceled_1: Cell::new(false),
+            canceled_2: Cell::new(false),
+            reason_1: Heap::default(),
+            reason_2: Heap::default(),
+            cancel_promise: Promise::new(&global),
+        });
+
+        // Steps 12-13.
+        let branch_1 = ReadableStream::new(&global);
+        ReadableByteStreamController::set_up_from_underlying_source(
+            &branch_1,
+            UnderlyingSourceType::ByteTee(tee_state.clone(), TeeBranch::One),
+            None,
+            0.,
+        )?;
+        let branch_2 = ReadableStream::new(&global);
+        ReadableByteStreamController::set_up_from_underlying_source(
+            &branch_2,
+            UnderlyingSourceType::ByteTee(tee_state.clone(), TeeBranch::Two),
+            None,
+            0.,
+        )?;
+        tee_state.branch_1.set(Some(&*branch_1));
+        tee_state.branch_2.set(Some(&*branch_2));
+
+        // Step 14.
+        tee_state.forward_reader_error();
+
+        // Step 15.
+        Ok(vec![branch_1, branch_2])
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-pipe-to>
+    #[allow(unrooted_must_root)]
+    pub fn pipe_to(
+        &self,
+        dest: &WritableStream,
+        prevent_close: bool,
+        prevent_abort: bool,
+        prevent_cancel: bool,
+        signal: Option<&AbortSignal>,
+    ) -> Rc<Promise> {
+        let global = self.global();
+
+        // Steps 1-6.
+        let reader = ReadableStreamDefaultReader::acquire(&global, self)
+            .expect("The source of a pipe should not be locked");
+        let writer = WritableStreamDefaultWriter::acquire(&global, dest)
+            .expect("The destination of a pipe should not be locked");
+
+        // Step 7.
+        self.disturbed.set(true);
+
+        // Steps 8-13.
+        let pipe = Rc::new(PipeTo {
+            source: Dom::from_ref(self),
+            dest: Dom::from_ref(dest),
+            reader: Dom::from_ref(&*reader),
+            writer: Dom::from_ref(&*writer),
+            prevent_close,
+            prevent_abort,
+            prevent_cancel,
+            result_promise: Promise::new(&global),
+            shutting_down: Cell::new(false),
+            finalized: Cell::new(false),
+            shutdown_action: Cell::new(None),
+            shutdown_error: Heap::default(),
+            has_shutdown_error: Cell::new(false),
+            pending_write: DomRefCell::new(None),
+            second_action: DomRefCell::new(None),
+            action_rejection: Heap::default(),
+            has_action_rejection: Cell::new(false),
+        });
+
+        // Step 14.
+        if let Some(signal) = signal {
+            // Step 14.2.
+            if signal.aborted() {
+                let cx = global.get_cx();
+                rooted!(in(*cx) let reason = signal.abort_reason());
+                pipe.abort(reason.handle());
+                return pipe.result_promise.clone();
+            }
 
-            // Note: the stream is locked to the reader.
-            self.js_reader.set(reader.get());
+            // Step 14.3.
+            signal.add(AbortAlgorithm::StreamPipe(pipe.clone()));
         }
 
-        self.has_reader.set(true);
-        Ok(())
+        // Step 15.
+        pipe.watch_closed_promises();
+        pipe.run();
+
+        // Step 16.
+        pipe.result_promise.clone()
     }
+}
 
-    /// Read a chunk from the stream,
-    /// must be called after `start_reading`,
-    /// and before `stop_reading`.
-    #[allow(unsafe_code)]
-    pub fn read_a_chunk(&self) -> Rc<Promise> {
-        if !self.has_reader.get() {
-            panic!("Attempt to read stream chunk without having acquired a reader.");
+impl ReadableStreamMethods for ReadableStream {
+    /// <https://streams.spec.whatwg.org/#rs-locked>
+    fn Locked(&self) -> bool {
+        self.is_locked()
+    }
+
+    /// <https://streams.spec.whatwg.org/#rs-cancel>
+    fn Cancel(&self, _cx: SafeJSContext, reason: SafeHandleValue) -> Rc<Promise> {
+        // Step 1.
+        if self.is_locked() {
+            let promise = Promise::new(&self.global());
+            promise.reject_error(Error::Type("The stream is locked".to_owned()));
+            return promise;
         }
 
+        // Step 2.
+        self.cancel(reason)
+    }
+
+    /// <https://streams.spec.whatwg.org/#rs-get-reader>
+    fn GetReader(
+        &self,
+        options: &ReadableStreamGetReaderOptions,
+    ) -> Fallible<ReadableStreamReader> {
         let global = self.global();
-        let _ar = enter_realm(&*global);
-        let _aes = AutoEntryScript::new(&*global);
+        match options.mode {
+            // Step 1.
+            None => ReadableStreamDefaultReader::acquire(&global, self)
+                .map(ReadableStreamReader::ReadableStreamDefaultReader),
+            // Steps 2-3.
+            Some(ReadableStreamReaderMode::Byob) => {
+                ReadableStreamBYOBReader::acquire(&global, self)
+                    .map(ReadableStreamReader::ReadableStreamBYOBReader)
+            },
+        }
+    }
 
-        let cx = global.get_cx();
+    /// <https://streams.spec.whatwg.org/#rs-pipe-through>
+    fn PipeThrough(
+        &self,
+        transform: &ReadableWritablePair,
+        options: &StreamPipeOptions,
+    ) -> Fallible<DomRoot<ReadableStream>> {
+        // Step 1.
+        if self.is_locked() {
+            return Err(Error::Type("The stream is locked".to_owned()));
+        }
 
-        unsafe {
-            rooted!(in(*cx) let promise_obj = ReadableStreamDefaultReaderRead(
-                *cx,
-                self.js_reader.handle(),
-            ));
-            Promise::new_with_js_promise(promise_obj.handle(), cx)
+        // Step 2.
+        if transform.writable.is_locked() {
+            return Err(Error::Type("The writable stream is locked".to_owned()));
         }
+
+        // Steps 3-4.
+        let promise = self.pipe_to(
+            &transform.writable,
+            options.preventClose,
+            options.preventAbort,
+            options.preventCancel,
+            options.signal.as_deref(),
+        );
+
+        // Step 5.
+        promise.set_promise_is_handled();
+
+        // Step 6.
+        Ok(transform.readable.clone())
     }
 
-    /// Releases the lock on the reader,
-    /// must be done after `start_reading`.
-    #[allow(unsafe_code)]
-    pub fn stop_reading(&self) {
-        if !self.has_reader.get() {
-            panic!("ReadableStream::stop_reading called on a readerless stream.");
+    /// <https://streams.spec.whatwg.org/#rs-pipe-to>
+    fn PipeTo(&self, destination: &WritableStream, options: &StreamPipeOptions) -> Rc<Promise> {
+        // Step 1.
+        if self.is_locked() {
+            let promise = Promise::new(&self.global());
+            promise.reject_error(Error::Type("The stream is locked".to_owned()));
+            return promise;
         }
 
-        self.has_reader.set(false);
+        // Step 2.
+        if destination.is_locked() {
+            let promise = Promise::new(&self.global());
+            promise.reject_error(Error::Type("The destination is locked".to_owned()));
+            return promise;
+        }
 
-        let global = self.global();
-        let _ar = enter_realm(&*global);
-        let cx = global.get_cx();
+        // Steps 3-4.
+        self.pipe_to(
+            destination,
+            options.preventClose,
+            options.preventAbort,
+            options.preventCancel,
+            options.signal.as_deref(),
+        )
+    }
 
-        unsafe {
-            ReadableStreamReaderReleaseLock(*cx, self.js_reader.handle());
-            // Note: is this the way to nullify the Heap?
-            self.js_reader.set(ptr::null_mut());
+    /// <https://streams.spec.whatwg.org/#rs-tee>
+    fn Tee(&self) -> Fallible<Vec<DomRoot<ReadableStream>>> {
+        // Steps 1-3 of <https://streams.spec.whatwg.org/#readable-stream-tee>.
+        if self.is_byte_stream() {
+            self.byte_tee()
+        } else {
+            self.default_tee()
         }
     }
+}
 
-    #[allow(unsafe_code)]
-    pub fn is_locked(&self) -> bool {
-        // If we natively took a reader, we're locked.
-        if self.has_reader.get() {
-            return true;
+/// Resolves a promise with undefined, whatever the value it is called with.
+#[derive(JSTraceable, MallocSizeOf)]
+pub struct ResolveWithUndefined {
+    #[ignore_malloc_size_of = "Rc is hard"]
+    pub promise: Rc<Promise>,
+}
+
+impl Callback for ResolveWithUndefined {
+    fn callback(&self, _cx: SafeJSContext, _v: SafeHandleValue, _realm: InRealm) {
+        self.promise.resolve_native(&());
+    }
+}
+
+/// Rejects a promise with the reason it is called with.
+#[derive(JSTraceable, MallocSizeOf)]
+pub struct RejectWithReason {
+    #[ignore_malloc_size_of = "Rc is hard"]
+    pub promise: Rc<Promise>,
+}
+
+impl Callback for RejectWithReason {
+    fn callback(&self, cx: SafeJSContext, v: SafeHandleValue, _realm: InRealm) {
+        self.promise.reject(cx, v);
+    }
+}
+
+/// The branches of a teed stream.
+#[derive(Clone, Copy, JSTraceable, MallocSizeOf, PartialEq)]
+pub enum TeeBranch {
+    One,
+    Two,
+}
+
+impl TeeBranch {
+    fn other(self) -> TeeBranch {
+        match self {
+            TeeBranch::One => TeeBranch::Two,
+            TeeBranch::Two => TeeBranch::One,
+        }
+    }
+}
+
+/// The state shared by the algorithms of
+/// <https://streams.spec.whatwg.org/#readable-stream-default-tee>.
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+#[unrooted_must_root_lint::allow_unrooted_in_rc]
+pub struct DefaultTeeState {
+    stream: Dom<ReadableStream>,
+    reader: Dom<ReadableStreamDefaultReader>,
+    branch_1: MutNullableDom<ReadableStream>,
+    branch_2: MutNullableDom<ReadableStream>,
+    reading: Cell<bool>,
+    read_again: Cell<bool>,
+    canceled_1: Cell<bool>,
+    canceled_2: Cell<bool>,
+    #[ignore_malloc_size_of = "Defined in rust-mozjs"]
+    reason_1: Heap<JSVal>,
+    #[ignore_malloc_size_of = "Defined in rust-mozjs"]
+    reason_2: Heap<JSVal>,
+    #[ignore_malloc_size_of = "Rc is hard"]
+    cancel_promise: Rc<Promise>,
+}
+
+impl DefaultTeeState {
+    fn branch_controller(&self, branch: TeeBranch) -> DomRoot<ReadableStreamDefaultController> {
+        let branch = match branch {
+            TeeBranch::One => &self.branch_1,
+            TeeBranch::Two => &self.branch_2,
+        };
+        branch
+            .get()
+            .expect("Tee branches should be set up")
+            .get_default_controller()
+    }
+
+    fn canceled(&self, branch: TeeBranch) -> bool {
+        match branch {
+            TeeBranch::One => self.canceled_1.get(),
+            TeeBranch::Two => self.canceled_2.get(),
         }
+    }
+
+    /// The chunk steps of the read request, which are delayed by a microtask.
+    pub fn chunk_steps(self: &Rc<Self>, chunk: SafeHandleValue) {
+        let global = self.stream.global();
+        global.enqueue_microtask(Microtask::ReadableStreamTeeReadRequest(
+            ReadableStreamTeeReadRequestMicrotask {
+                tee_state: self.clone(),
+                chunk: Heap::boxed(chunk.get()),
+            },
+        ));
+    }
 
-        // Otherwise, still double-check that script didn't lock the stream.
-        let cx = self.global().get_cx();
-        let mut locked_or_disturbed = false;
+    /// The chunk steps of the read request, once the microtask runs.
+    fn run_chunk_steps(self: &Rc<Self>, chunk: SafeHandleValue) {
+        // Step 1.
+        self.read_again.set(false);
 
-        unsafe {
-            ReadableStreamIsLocked(*cx, self.js_stream.handle(), &mut locked_or_disturbed);
+        // Steps 2-5.
+        for branch in [TeeBranch::One, TeeBranch::Two].iter() {
+            if !self.canceled(*branch) {
+                let _ = self.branch_controller(*branch).enqueue(chunk);
+            }
         }
 
-        locked_or_disturbed
+        // Step 6.
+        self.reading.set(false);
+
+        // Step 7.
+        if self.read_again.get() {
+            self.pull();
+        }
     }
 
+    /// The close steps of the read request.
+    pub fn close_steps(&self) {
+        // Step 1.
+        self.reading.set(false);
+
+        // Steps 2-3.
+        for branch in [TeeBranch::One, TeeBranch::Two].iter() {
+            if !self.canceled(*branch) {
+                self.branch_controller(*branch).close();
+            }
+        }
+
+        // Step 4.
+        if !self.canceled_1.get() || !self.canceled_2.get() {
+            self.cancel_promise.resolve_native(&());
+        }
+    }
+
+    /// The error steps of the read request.
+    pub fn error_steps(&self) {
+        self.reading.set(false);
+    }
+
+    /// The pull algorithm of both branches.
+    pub fn pull(self: &Rc<Self>) -> Rc<Promise> {
+        let global = self.stream.global();
+
+        // Step 1.
+        if self.reading.get() {
+            self.read_again.set(true);
+            return resolved_promise(&global);
+        }
+
+        // Step 2.
+        self.reading.set(true);
+
+        // Steps 3-4.
+        self.reader
+            .read_with_request(ReadRequest::DefaultTee(self.clone()));
+
+        // Step 5.
+        resolved_promise(&global)
+    }
+
+    /// The cancel algorithms of the branches.
     #[allow(unsafe_code)]
-    pub fn is_disturbed(&self) -> bool {
-        // Check that script didn't disturb the stream.
-        let cx = self.global().get_cx();
-        let mut locked_or_disturbed = false;
+    pub fn cancel(&self, branch: TeeBranch, reason: SafeHandleValue) -> Rc<Promise> {
+        // Steps 1-2.
+        let other_canceled = match branch {
+            TeeBranch::One => {
+                self.canceled_1.set(true);
+                self.reason_1.set(reason.get());
+                self.canceled_2.get()
+            },
+            TeeBranch::Two => {
+                self.canceled_2.set(true);
+                self.reason_2.set(reason.get());
+                self.canceled_1.get()
+            },
+        };
 
-        unsafe {
-            ReadableStreamIsDisturbed(*cx, self.js_stream.handle(), &mut locked_or_disturbed);
+        // Step 3.
+        if other_canceled {
+            let global = self.stream.global();
+            let cx = global.get_cx();
+            let _ac = enter_realm(&*global);
+            rooted!(in(*cx) let mut composite_reason = UndefinedValue());
+            let reasons = vec![self.reason_1.get(), self.reason_2.get()];
+            unsafe { reasons.to_jsval(*cx, composite_reason.handle_mut()) };
+            let cancel_result = self.stream.cancel(composite_reason.handle());
+            rooted!(in(*cx) let cancel_result = ObjectValue(cancel_result.promise_obj().get()));
+            self.cancel_promise.resolve(cx, cancel_result.handle());
         }
 
-        locked_or_disturbed
+        // Step 4.
+        self.cancel_promise.clone()
     }
 }
 
-#[allow(unsafe_code)]
-unsafe extern "C" fn request_data(
-    source: *const c_void,
-    cx: *mut JSContext,
-    stream: HandleObject,
-    desired_size: usize,
-) {
-    let source = &*(source as *const ExternalUnderlyingSourceController);
-    source.pull(SafeJSContext::from_ptr(cx), stream, desired_size);
+/// <https://streams.spec.whatwg.org/#ref-for-read-request-chunk-steps>
+/// of the read requests of a tee.
+#[derive(JSTraceable, MallocSizeOf)]
+pub struct ReadableStreamTeeReadRequestMicrotask {
+    #[ignore_malloc_size_of = "Rc is hard"]
+    tee_state: Rc<DefaultTeeState>,
+    #[ignore_malloc_size_of = "Defined in rust-mozjs"]
+    chunk: Box<Heap<JSVal>>,
 }
 
-#[allow(unsafe_code)]
-unsafe extern "C" fn write_into_read_request_buffer(
-    source: *const c_void,
-    _cx: *mut JSContext,
-    _stream: HandleObject,
-    buffer: *mut c_void,
-    length: usize,
-    bytes_written: *mut usize,
-) {
-    let source = &*(source as *const ExternalUnderlyingSourceController);
-    let slice = slice::from_raw_parts_mut(buffer as *mut u8, length);
-    source.write_into_buffer(slice);
-
-    // Currently we're always able to completely fulfill the write request.
-    *bytes_written = length;
+impl MicrotaskRunnable for ReadableStreamTeeReadRequestMicrotask {
+    fn handler(&self) {
+        let cx = self.tee_state.stream.global().get_cx();
+        rooted!(in(*cx) let chunk = self.chunk.get());
+        self.tee_state.run_chunk_steps(chunk.handle());
+    }
 }
 
-#[allow(unsafe_code)]
-unsafe extern "C" fn cancel(
-    _source: *const c_void,
-    _cx: *mut JSContext,
-    _stream: HandleObject,
-    _reason: HandleValue,
-    _resolve_to: *mut JSVal,
-) {
+/// Step 18 of <https://streams.spec.whatwg.org/#readable-stream-default-tee>.
+#[derive(JSTraceable, MallocSizeOf)]
+struct TeeClosedPromiseRejectionHandler {
+    #[ignore_malloc_size_of = "Rc is hard"]
+    tee_state: Rc<DefaultTeeState>,
 }
 
-#[allow(unsafe_code)]
-unsafe extern "C" fn close(_source: *const c_void, _cx: *mut JSContext, _stream: HandleObject) {}
+impl Callback for TeeClosedPromiseRejectionHandler {
+    fn callback(&self, _cx: SafeJSContext, v: SafeHandleValue, _realm: InRealm) {
+        // Steps 18.1-18.2.
+        self.tee_state.branch_controller(TeeBranch::One).error(v);
+        self.tee_state.branch_controller(TeeBranch::Two).error(v);
 
-#[allow(unsafe_code)]
-unsafe extern "C" fn error(
-    _source: *const c_void,
-    _cx: *mut JSContext,
-    _stream: HandleObject,
-    _reason: HandleValue,
-) {
-}
-
-#[allow(unsafe_code)]
-unsafe extern "C" fn finalize(source: *mut ReadableStreamUnderlyingSource) {
-    DeleteReadableStreamUnderlyingSource(source);
+        // Step 18.3.
+        if !self.tee_state.canceled_1.get() || !self.tee_state.canceled_2.get() {
+            self.tee_state.cancel_promise.resolve_native(&());
+        }
+    }
 }
 
-pub enum ExternalUnderlyingSource {
-    /// Facilitate partial integration with sources
-    /// that are currently read into memory.
-    Memory(usize),
-    /// A blob as underlying source, with a known total size.
-    Blob(usize),
-    /// A fetch response as underlying source.
-    FetchResponse,
+/// The state shared by the algorithms of
+/// <https://streams.spec.whatwg.org/#abstract-opdef-readablebytestreamtee>.
+///
+/// The chunks read from the stream are copies of its bytes, so cloning them
+/// for the second branch cannot fail.
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+#[unrooted_must_root_lint::allow_unrooted_in_rc]
+pub struct ByteTeeState {
+    stream: Dom<ReadableStream>,
+    /// The reader of the stream, while it is a default reader.
+    default_reader: MutNullableDom<ReadableStreamDefaultReader>,
+    /// The reader of the stream, while it is a BYOB reader.
+    byob_reader: MutNullableDom<ReadableStreamBYOBReader>,
+    /// Incremented whenever the reader is replaced, so that only the errors of
+    /// the current reader are forwarded.
+    reader_generation: Cell<u32>,
+    branch_1: MutNullableDom<ReadableStream>,
+    branch_2: MutNullableDom<ReadableStream>,
+    reading: Cell<bool>,
+    read_again_for_branch_1: Cell<bool>,
+    read_again_for_branch_2: Cell<bool>,
+    canceled_1: Cell<bool>,
+    canceled_2: Cell<bool>,
+    #[ignore_malloc_size_of = "Defined in rust-mozjs"]
+    reason_1: Heap<JSVal>,
+    #[ignore_malloc_size_of = "Defined in rust-mozjs"]
+    reason_2: Heap<JSVal>,
+    #[ignore_malloc_size_of = "Rc is hard"]
+    cancel_promise: Rc<Promise>,
 }
 
-#[derive(JSTraceable, MallocSizeOf)]
-struct ExternalUnderlyingSourceController {
-    /// Loosely matches the underlying queue,
-    /// <https://streams.spec.whatwg.org/#internal-queues>
-    buffer: RefCell<Vec<u8>>,
-    /// Has the stream been closed by native code?
-    closed: Cell<bool>,
-    /// Does this stream contains all it's data in memory?
-    in_memory: Cell<bool>,
-}
-
-impl ExternalUnderlyingSourceController {
-    fn new(source: ExternalUnderlyingSource) -> ExternalUnderlyingSourceController {
-        let (buffer, in_mem) = match source {
-            ExternalUnderlyingSource::Blob(size) => (Vec::with_capacity(size), false),
-            ExternalUnderlyingSource::Memory(size) => (Vec::with_capacity(size), true),
-            ExternalUnderlyingSource::FetchResponse => (vec![], false),
+impl ByteTeeState {
+    fn branch_controller(&self, branch: TeeBranch) -> DomRoot<ReadableByteStreamController> {
+        let branch = match branch {
+            TeeBranch::One => &self.branch_1,
+            TeeBranch::Two => &self.branch_2,
         };
-        ExternalUnderlyingSourceController {
-            buffer: RefCell::new(buffer),
-            closed: Cell::new(false),
-            in_memory: Cell::new(in_mem),
+        branch
+            .get()
+            .expect("Tee branches should be set up")
+            .get_byte_controller()
+    }
+
+    fn canceled(&self, branch: TeeBranch) -> bool {
+        match branch {
+            TeeBranch::One => self.canceled_1.get(),
+            TeeBranch::Two => self.canceled_2.get(),
         }
     }
 
-    /// Does the stream have all data in memory?
-    pub fn in_memory(&self) -> bool {
-        self.in_memory.get()
+    fn resolve_cancel_promise_unless_both_canceled(&self) {
+        if !self.canceled_1.get() || !self.canceled_2.get() {
+            self.cancel_promise.resolve_native(&());
+        }
     }
 
-    /// Return bytes synchronously if the stream has all data in memory.
-    pub fn get_in_memory_bytes(&self) -> Option<Vec<u8>> {
-        if self.in_memory.get() {
-            return Some(self.buffer.borrow().clone());
+    /// Responds to the pending pull-into of a branch with some bytes, as
+    /// `ReadableByteStreamControllerRespondWithNewView` does.
+    fn respond(&self, branch: TeeBranch, bytes: &[u8]) {
+        let controller = self.branch_controller(branch);
+        if controller.has_pending_pull_intos() {
+            let _ = controller.respond(bytes.len(), bytes);
         }
-        None
     }
 
-    /// Signal available bytes if the stream is currently readable.
-    #[allow(unsafe_code)]
-    fn maybe_signal_available_bytes(
-        &self,
-        cx: SafeJSContext,
-        stream: HandleObject,
-        available: usize,
-    ) {
-        if available == 0 {
-            return;
+    /// forwardReaderError, for the current reader.
+    fn forward_reader_error(self: &Rc<Self>) {
+        let global = self.stream.global();
+        let closed_promise = match self.default_reader.get() {
+            Some(reader) => reader.closed_promise(),
+            None => self
+                .byob_reader
+                .get()
+                .expect("The tee should have a reader")
+                .closed_promise(),
+        };
+        let handler = PromiseNativeHandler::new(
+            &global,
+            None,
+            Some(Box::new(ByteTeeReaderErrorHandler {
+                tee_state: self.clone(),
+                generation: self.reader_generation.get(),
+            })),
+        );
+        let realm = enter_realm(&*global);
+        let comp = InRealm::Entered(&realm);
+        closed_promise.append_native_handler(&handler, comp);
+    }
+
+    /// pullWithDefaultReader.
+    fn pull_with_default_reader(self: &Rc<Self>) {
+        // Step 1.
+        if let Some(byob_reader) = self.byob_reader.take() {
+            assert_eq!(byob_reader.get_num_read_into_requests(), 0);
+            byob_reader.release();
+            let reader = ReadableStreamDefaultReader::acquire(&self.stream.global(), &self.stream)
+                .expect("The stream was just released");
+            self.default_reader.set(Some(&*reader));
+            self.reader_generation.set(self.reader_generation.get() + 1);
+            self.forward_reader_error();
         }
-        unsafe {
-            let mut readable = false;
-            if !ReadableStreamIsReadable(*cx, stream, &mut readable) {
-                return;
-            }
-            if readable {
-                ReadableStreamUpdateDataAvailableFromSource(*cx, stream, available as u32);
+
+        // Steps 2-3.
+        self.default_reader
+            .get()
+            .expect("The tee should have a default reader")
+            .read_with_request(ReadRequest::ByteTee(self.clone()));
+    }
+
+    /// pullWithBYOBReader.
+    fn pull_with_byob_reader(self: &Rc<Self>, view: *mut JSObject, branch: TeeBranch) {
+        // Step 1.
+        if let Some(default_reader) = self.default_reader.take() {
+            assert_eq!(default_reader.get_num_read_requests(), 0);
+            default_reader.release();
+            let reader = ReadableStreamBYOBReader::acquire(&self.stream.global(), &self.stream)
+                .expect("The stream was just released");
+            self.byob_reader.set(Some(&*reader));
+            self.reader_generation.set(self.reader_generation.get() + 1);
+            self.forward_reader_error();
+        }
+
+        // Steps 2-4.
+        self.byob_reader
+            .get()
+            .expect("The tee should have a BYOB reader")
+            .read_with_request(view, ReadIntoRequest::ByteTee(self.clone(), branch));
+    }
+
+    /// The chunk steps of the read requests, which are delayed by a microtask.
+    /// `byob_branch` is the branch a BYOB read was made for.
+    pub fn chunk_steps(self: &Rc<Self>, chunk: SafeHandleValue, byob_branch: Option<TeeBranch>) {
+        let global = self.stream.global();
+        global.enqueue_microtask(Microtask::ReadableByteStreamTeeReadRequest(
+            ReadableByteStreamTeeReadRequestMicrotask {
+                tee_state: self.clone(),
+                chunk: Heap::boxed(chunk.get()),
+                byob_branch,
+            },
+        ));
+    }
+
+    /// The chunk steps of the read requests, once the microtask runs.
+    fn run_chunk_steps(self: &Rc<Self>, chunk: SafeHandleValue, byob_branch: Option<TeeBranch>) {
+        // Step 1.
+        self.read_again_for_branch_1.set(false);
+        self.read_again_for_branch_2.set(false);
+
+        // Step 2.
+        let cx = self.stream.global().get_cx();
+        let bytes = if chunk.is_object() {
+            view_bytes(cx, chunk.to_object())
+        } else {
+            vec![]
+        };
+
+        match byob_branch {
+            // Steps 3-5 of the read request.
+            None => {
+                for branch in [TeeBranch::One, TeeBranch::Two].iter() {
+                    if !self.canceled(*branch) {
+                        self.branch_controller(*branch)
+                            .enqueue_native(bytes.clone());
+                    }
+                }
+            },
+            // Steps 3-4 of the read-into request.
+            Some(byob_branch) => {
+                if !self.canceled(byob_branch) {
+                    self.respond(byob_branch, &bytes);
+                }
+                let other_branch = byob_branch.other();
+                if !self.canceled(other_branch) {
+                    self.branch_controller(other_branch).enqueue_native(bytes);
+                }
+            },
+        }
+
+        // Step 6.
+        self.reading.set(false);
+
+        // Step 7.
+        if self.read_again_for_branch_1.get() {
+            self.pull(TeeBranch::One);
+        } else if self.read_again_for_branch_2.get() {
+            self.pull(TeeBranch::Two);
+        }
+    }
+
+    /// The close steps of the read request.
+    pub fn close_steps(&self) {
+        // Step 1.
+        self.reading.set(false);
+
+        // Steps 2-3.
+        for branch in [TeeBranch::One, TeeBranch::Two].iter() {
+            if !self.canceled(*branch) {
+                self.branch_controller(*branch).close_native();
             }
         }
+
+        // Steps 4-5.
+        for branch in [TeeBranch::One, TeeBranch::Two].iter() {
+            self.respond(*branch, &[]);
+        }
+
+        // Step 6.
+        self.resolve_cancel_promise_unless_both_canceled();
     }
 
-    /// Close a currently readable js stream.
-    #[allow(unsafe_code)]
-    fn maybe_close_js_stream(&self, cx: SafeJSContext, stream: HandleObject) {
-        unsafe {
-            let mut readable = false;
-            if !ReadableStreamIsReadable(*cx, stream, &mut readable) {
-                return;
+    /// The close steps of the read-into request.
+    pub fn byob_close_steps(&self, byob_branch: TeeBranch, chunk: SafeHandleValue) {
+        // Step 1.
+        self.reading.set(false);
+
+        // Steps 2-4.
+        let other_branch = byob_branch.other();
+        for branch in [byob_branch, other_branch].iter() {
+            if !self.canceled(*branch) {
+                self.branch_controller(*branch).close_native();
             }
-            if readable {
-                ReadableStreamClose(*cx, stream);
+        }
+
+        // Step 5.
+        if !chunk.is_undefined() {
+            for branch in [byob_branch, other_branch].iter() {
+                if !self.canceled(*branch) {
+                    self.respond(*branch, &[]);
+                }
             }
         }
+
+        // Step 6.
+        self.resolve_cancel_promise_unless_both_canceled();
     }
 
-    fn close(&self, cx: SafeJSContext, stream: HandleObject) {
-        self.closed.set(true);
-        self.maybe_close_js_stream(cx, stream);
+    /// The error steps of the read requests.
+    pub fn error_steps(&self) {
+        self.reading.set(false);
+    }
+
+    /// pull1Algorithm and pull2Algorithm.
+    pub fn pull(self: &Rc<Self>, branch: TeeBranch) -> Rc<Promise> {
+        let global = self.stream.global();
+
+        // Step 1.
+        if self.reading.get() {
+            match branch {
+                TeeBranch::One => self.read_again_for_branch_1.set(true),
+                TeeBranch::Two => self.read_again_for_branch_2.set(true),
+            }
+            return resolved_promise(&global);
+        }
+
+        // Step 2.
+        self.reading.set(true);
+
+        // Steps 3-5.
+        match self.branch_controller(branch).GetByobRequest() {
+            None => self.pull_with_default_reader(),
+            Some(byob_request) => {
+                let cx = global.get_cx();
+                rooted!(in(*cx) let view = byob_request.view());
+                self.pull_with_byob_reader(view.get(), branch);
+            },
+        }
+
+        // Step 6.
+        resolved_promise(&global)
     }
 
-    fn enqueue_chunk(&self, cx: SafeJSContext, stream: HandleObject, mut chunk: Vec<u8>) {
-        let available = {
-            let mut buffer = self.buffer.borrow_mut();
-            chunk.append(&mut buffer);
-            *buffer = chunk;
-            buffer.len()
+    /// cancel1Algorithm and cancel2Algorithm.
+    #[allow(unsafe_code)]
+    pub fn cancel(&self, branch: TeeBranch, reason: SafeHandleValue) -> Rc<Promise> {
+        // Steps 1-2.
+        let other_canceled = match branch {
+            TeeBranch::One => {
+                self.canceled_1.set(true);
+                self.reason_1.set(reason.get());
+                self.canceled_2.get()
+            },
+            TeeBranch::Two => {
+                self.canceled_2.set(true);
+                self.reason_2.set(reason.get());
+                self.canceled_1.get()
+            },
         };
-        self.maybe_signal_available_bytes(cx, stream, available);
+
+        // Step 3.
+        if other_canceled {
+            let global = self.stream.global();
+            let cx = global.get_cx();
+            let _ac = enter_realm(&*global);
+            rooted!(in(*cx) let mut composite_reason = UndefinedValue());
+            let reasons = vec![self.reason_1.get(), self.reason_2.get()];
+            unsafe { reasons.to_jsval(*cx, composite_reason.handle_mut()) };
+            let cancel_result = self.stream.cancel(composite_reason.handle());
+            rooted!(in(*cx) let cancel_result = ObjectValue(cancel_result.promise_obj().get()));
+            self.cancel_promise.resolve(cx, cancel_result.handle());
+        }
+
+        // Step 4.
+        self.cancel_promise.clone()
     }
+}
+
+/// The chunk steps of the read requests of a byte stream tee.
+#[derive(JSTraceable, MallocSizeOf)]
+pub struct ReadableByteStreamTeeReadRequestMicrotask {
+    #[ignore_malloc_size_of = "Rc is hard"]
+    tee_state: Rc<ByteTeeState>,
+    #[ignore_malloc_size_of = "Defined in rust-mozjs"]
+    chunk: Box<Heap<JSVal>>,
+    byob_branch: Option<TeeBranch>,
+}
 
+impl MicrotaskRunnable for ReadableByteStreamTeeReadRequestMicrotask {
+    fn handler(&self) {
+        let cx = self.tee_state.stream.global().get_cx();
+        rooted!(in(*cx) let chunk = self.chunk.get());
+        self.tee_state
+            .run_chunk_steps(chunk.handle(), self.byob_branch);
+    }
+}
+
+/// forwardReaderError of <https://streams.spec.whatwg.org/#abstract-opdef-readablebytestreamtee>.
+#[derive(JSTraceable, MallocSizeOf)]
+struct ByteTeeReaderErrorHandler {
+    #[ignore_malloc_size_of = "Rc is hard"]
+    tee_state: Rc<ByteTeeState>,
+    /// The generation of the reader whose closed promise was rejected.
+    generation: u32,
+}
+
+impl Callback for ByteTeeReaderErrorHandler {
+    fn callback(&self, _cx: SafeJSContext, v: SafeHandleValue, _realm: InRealm) {
+        // Step 4.1.1.
+        if self.generation != self.tee_state.reader_generation.get() {
+            return;
+        }
+
+        // Steps 4.1.2-4.1.3.
+        self.tee_state.branch_controller(TeeBranch::One).error(v);
+        self.tee_state.branch_controller(TeeBranch::Two).error(v);
+
+        // Step 4.1.4.
+        self.tee_state.resolve_cancel_promise_unless_both_canceled();
+    }
+}
+
+/// The action to take before finalizing a pipe.
+#[derive(Clone, Copy, JSTraceable, MallocSizeOf)]
+enum ShutdownAction {
+    /// Abort the destination with the shutdown error.
+    AbortDestination,
+    /// Cancel the source with the shutdown error.
+    CancelSource,
+    /// Close the destination.
+    CloseDestination,
+    /// Abort the destination and cancel the source, as the signal was aborted.
+    AbortBoth,
+}
+
+/// The state of <https://streams.spec.whatwg.org/#readable-stream-pipe-to>.
+///
+/// Chunks are read one at a time once the destination is ready for them,
+/// and the state of both streams is checked whenever a promise settles.
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+#[unrooted_must_root_lint::allow_unrooted_in_rc]
+pub struct PipeTo {
+    source: Dom<ReadableStream>,
+    dest: Dom<WritableStream>,
+    reader: Dom<ReadableStreamDefaultReader>,
+    writer: Dom<WritableStreamDefaultWriter>,
+    prevent_close: bool,
+    prevent_abort: bool,
+    prevent_cancel: bool,
+    #[ignore_malloc_size_of = "Rc is hard"]
+    result_promise: Rc<Promise>,
+    shutting_down: Cell<bool>,
+    finalized: Cell<bool>,
+    shutdown_action: Cell<Option<ShutdownAction>>,
+    #[ignore_malloc_size_of = "Defined in rust-mozjs"]
+    shutdown_error: Heap<JSVal>,
+    has_shutdown_error: Cell<bool>,
+    /// The promise of the last write, which settles after all the previous ones.
+    #[ignore_malloc_size_of = "Rc is hard"]
+    pending_write: DomRefCell<Option<Rc<Promise>>>,
+    /// The cancelation of the source, when waiting for both actions of an abort.
+    #[ignore_malloc_size_of = "Rc is hard"]
+    second_action: DomRefCell<Option<Rc<Promise>>>,
+    /// The first rejection of the actions of an abort.
+    #[ignore_malloc_size_of = "Defined in rust-mozjs"]
+    action_rejection: Heap<JSVal>,
+    has_action_rejection: Cell<bool>,
+}
+
+/// The steps of a pipe that wait for a promise.
+#[derive(Clone, Copy, JSTraceable, MallocSizeOf, PartialEq)]
+enum PipeStep {
+    /// The writer is ready for a chunk.
+    Ready,
+    /// A chunk was read.
+    Read,
+    /// One of the streams was closed or errored.
+    CheckState,
+    /// The pending writes are done.
+    PendingWritesDone,
+    /// The first of the actions of an abort settled.
+    FirstActionSettled,
+    /// The shutdown action settled.
+    ActionSettled,
+}
+
+impl PipeTo {
+    fn react(self: &Rc<Self>, promise: &Promise, step: PipeStep) {
+        let global = self.source.global();
+        let handler = PromiseNativeHandler::new(
+            &global,
+            Some(Box::new(PipeToReaction {
+                pipe: self.clone(),
+                step,
+                fulfilled: true,
+            })),
+            Some(Box::new(PipeToReaction {
+                pipe: self.clone(),
+                step,
+                fulfilled: false,
+            })),
+        );
+        let realm = enter_realm(&*global);
+        let comp = InRealm::Entered(&realm);
+        promise.append_native_handler(&handler, comp);
+    }
+
+    /// Checks the state of the streams whenever one of them is closed or errored.
+    fn watch_closed_promises(self: &Rc<Self>) {
+        self.react(&self.reader.closed_promise(), PipeStep::CheckState);
+        self.react(&self.writer.closed_promise(), PipeStep::CheckState);
+    }
+
+    /// Waits for the destination to be ready, then pipes a chunk.
+    fn run(self: &Rc<Self>) {
+        if self.check_state() || self.shutting_down.get() {
+            return;
+        }
+        self.react(&self.writer.ready_promise(), PipeStep::Ready);
+    }
+
+    /// Reads a chunk from the source.
+    fn read(self: &Rc<Self>) {
+        if self.check_state() || self.shutting_down.get() {
+            return;
+        }
+        let promise = self.reader.read();
+        self.react(&promise, PipeStep::Read);
+    }
+
+    /// Writes a chunk to the destination, and carries on with the next one.
     #[allow(unsafe_code)]
-    fn pull(&self, cx: SafeJSContext, stream: HandleObject, _desired_size: usize) {
-        // Note: for pull sources,
-        // this would be the time to ask for a chunk.
+    fn write(self: &Rc<Self>, result: SafeHandleValue) {
+        let cx = self.source.global().get_cx();
+        let done = get_read_promise_done(cx, &result).unwrap_or(true);
+        if done {
+            self.check_state();
+            return;
+        }
+
+        rooted!(in(*cx) let mut chunk = UndefinedValue());
+        rooted!(in(*cx) let object = result.to_object());
+        if get_dictionary_property(*cx, object.handle(), "value", chunk.handle_mut()).is_err() {
+            return;
+        }
+        let write_promise = self.writer.write(cx, chunk.handle());
+        write_promise.set_promise_is_handled();
+        *self.pending_write.borrow_mut() = Some(write_promise);
 
-        if self.closed.get() {
-            return self.maybe_close_js_stream(cx, stream);
+        self.run();
+    }
+
+    /// Propagates errors and closing between the streams.
+    ///
+    /// Returns whether the pipe is shutting down.
+    fn check_state(self: &Rc<Self>) -> bool {
+        if self.shutting_down.get() {
+            return true;
+        }
+        let global = self.source.global();
+        let cx = global.get_cx();
+        rooted!(in(*cx) let mut error = UndefinedValue());
+
+        // Errors must be propagated forward.
+        if self.source.is_errored() {
+            self.source.get_stored_error(error.handle_mut());
+            let action = if self.prevent_abort {
+                None
+            } else {
+                Some(ShutdownAction::AbortDestination)
+            };
+            self.shutdown(action, Some(error.handle()));
+            return true;
+        }
+
+        // Errors must be propagated backward.
+        if self.dest.is_errored() || self.dest.is_erroring() {
+            self.dest.get_stored_error(error.handle_mut());
+            let action = if self.prevent_cancel {
+                None
+            } else {
+                Some(ShutdownAction::CancelSource)
+            };
+            self.shutdown(action, Some(error.handle()));
+            return true;
         }
 
-        let available = {
-            let buffer = self.buffer.borrow();
-            buffer.len()
+        // Closing must be propagated forward.
+        if self.source.is_closed() {
+            let action = if self.prevent_close {
+                None
+            } else {
+                Some(ShutdownAction::CloseDestination)
+            };
+            self.shutdown(action, None);
+            return true;
+        }
+
+        // Closing must be propagated backward.
+        if self.dest.close_queued_or_in_flight() || self.dest.is_closed() {
+            new_type_error(
+                &global,
+                "The destination of the pipe was closed",
+                error.handle_mut(),
+            );
+            let action = if self.prevent_cancel {
+                None
+            } else {
+                Some(ShutdownAction::CancelSource)
+            };
+            self.shutdown(action, Some(error.handle()));
+            return true;
+        }
+
+        false
+    }
+
+    /// <https://streams.spec.whatwg.org/#rs-pipeTo-shutdown-with-action>
+    /// and <https://streams.spec.whatwg.org/#rs-pipeTo-shutdown>.
+    fn shutdown(self: &Rc<Self>, action: Option<ShutdownAction>, error: Option<SafeHandleValue>) {
+        // Steps 1-2.
+        if self.shutting_down.replace(true) {
+            return;
+        }
+        self.shutdown_action.set(action);
+        if let Some(error) = error {
+            self.shutdown_error.set(error.get());
+            self.has_shutdown_error.set(true);
+        }
+
+        // Step 3.
+        if self.dest.is_writable() && !self.dest.close_queued_or_in_flight() {
+            let pending_write = self.pending_write.borrow().clone();
+            if let Some(pending_write) = pending_write {
+                self.react(&pending_write, PipeStep::PendingWritesDone);
+                return;
+            }
+        }
+
+        self.perform_shutdown_action();
+    }
+
+    /// Step 4 of <https://streams.spec.whatwg.org/#rs-pipeTo-shutdown-with-action>.
+    fn perform_shutdown_action(self: &Rc<Self>) {
+        let global = self.source.global();
+        let cx = global.get_cx();
+        rooted!(in(*cx) let error = self.shutdown_error.get());
+        let promise = match self.shutdown_action.get() {
+            None => {
+                // Step 3 of <https://streams.spec.whatwg.org/#rs-pipeTo-shutdown>.
+                self.finalize();
+                return;
+            },
+            Some(ShutdownAction::AbortDestination) => self.abort_destination(error.handle()),
+            Some(ShutdownAction::CancelSource) => self.cancel_source(error.handle()),
+            Some(ShutdownAction::CloseDestination) => self.writer.close_with_error_propagation(),
+            Some(ShutdownAction::AbortBoth) => {
+                let first_action = self.abort_destination(error.handle());
+                *self.second_action.borrow_mut() = Some(self.cancel_source(error.handle()));
+                self.react(&first_action, PipeStep::FirstActionSettled);
+                return;
+            },
         };
+        self.react(&promise, PipeStep::ActionSettled);
+    }
+
+    fn abort_destination(&self, error: SafeHandleValue) -> Rc<Promise> {
+        if self.prevent_abort || !self.dest.is_writable() {
+            return resolved_promise(&self.source.global());
+        }
+        self.dest.abort(error)
+    }
+
+    fn cancel_source(&self, error: SafeHandleValue) -> Rc<Promise> {
+        if self.prevent_cancel || !self.source.is_readable() {
+            return resolved_promise(&self.source.global());
+        }
+        self.source.cancel(error)
+    }
+
+    /// <https://streams.spec.whatwg.org/#rs-pipeTo-finalize>
+    fn finalize(&self) {
+        if self.finalized.replace(true) {
+            return;
+        }
+
+        // Steps 1-2.
+        self.writer.release();
+        self.reader.release();
+
+        // Steps 3-5.
+        let cx = self.source.global().get_cx();
+        if self.has_action_rejection.get() {
+            rooted!(in(*cx) let error = self.action_rejection.get());
+            self.result_promise.reject(cx, error.handle());
+        } else if self.has_shutdown_error.get() {
+            rooted!(in(*cx) let error = self.shutdown_error.get());
+            self.result_promise.reject(cx, error.handle());
+        } else {
+            self.result_promise.resolve_native(&());
+        }
+    }
 
-        self.maybe_signal_available_bytes(cx, stream, available);
+    /// Step 14.1 of <https://streams.spec.whatwg.org/#readable-stream-pipe-to>.
+    pub fn abort(self: &Rc<Self>, reason: SafeHandleValue) {
+        self.shutdown(Some(ShutdownAction::AbortBoth), Some(reason));
     }
 
-    fn get_chunk_with_length(&self, length: usize) -> Vec<u8> {
-        let mut buffer = self.buffer.borrow_mut();
-        let buffer_len = buffer.len();
-        assert!(buffer_len >= length as usize);
-        buffer.split_off(buffer_len - length)
+    fn on_settled(self: &Rc<Self>, step: PipeStep, fulfilled: bool, v: SafeHandleValue) {
+        match step {
+            PipeStep::Ready if fulfilled => self.read(),
+            PipeStep::Read if fulfilled => self.write(v),
+            PipeStep::Ready | PipeStep::Read | PipeStep::CheckState => {
+                self.check_state();
+            },
+            PipeStep::PendingWritesDone => self.perform_shutdown_action(),
+            PipeStep::FirstActionSettled => {
+                if !fulfilled {
+                    self.action_rejection.set(v.get());
+                    self.has_action_rejection.set(true);
+                }
+                let second_action = self.second_action.borrow_mut().take();
+                if let Some(second_action) = second_action {
+                    self.react(&second_action, PipeStep::ActionSettled);
+                }
+            },
+            PipeStep::ActionSettled => {
+                if !fulfilled && !self.has_action_rejection.get() {
+                    self.action_rejection.set(v.get());
+                    self.has_action_rejection.set(true);
+                }
+                self.finalize();
+            },
+        }
     }
+}
+
+/// A reaction to one of the promises a pipe waits for.
+#[derive(JSTraceable, MallocSizeOf)]
+struct PipeToReaction {
+    #[ignore_malloc_size_of = "Rc is hard"]
+    pipe: Rc<PipeTo>,
+    step: PipeStep,
+    fulfilled: bool,
+}
 
-    fn write_into_buffer(&self, dest: &mut [u8]) {
-        let length = dest.len();
-        let chunk = self.get_chunk_with_length(length);
-        dest.copy_from_slice(chunk.as_slice());
+impl Callback for PipeToReaction {
+    fn callback(&self, _cx: SafeJSContext, v: SafeHandleValue, _realm: InRealm) {
+        self.pipe.on_settled(self.step, self.fulfilled, v);
     }
 }
 
+/// Creates a `TypeError` in the realm of a global.
+#[allow(unsafe_code)]
+pub fn new_type_error(global: &GlobalScope, message: &str, rval: SafeMutableHandleValue) {
+    let cx = global.get_cx();
+    let _ac = enter_realm(global);
+    unsafe { Error::Type(message.to_owned()).to_jsval(*cx, global, rval) };
+}
+
 #[allow(unsafe_code)]
 /// Get the `done` property of an object that a read promise resolved to.
 pub fn get_read_promise_done(cx: SafeJSContext, v: &SafeHandleValue) -> Result<bool, Error> {
+    if !v.is_object() {
+        return Err(Error::Type("Unknown format for read result.".to_string()));
+    }
     unsafe {
         rooted!(in(*cx) let object = v.to_object());
         rooted!(in(*cx) let mut done = UndefinedValue());
@@ -522,11 +1758,20 @@ pub fn get_read_promise_done(cx: SafeJSContext, v: &SafeHandleValue) -> Result<b
 #[allow(unsafe_code)]
 /// Get the `value` property of an object that a read promise resolved to.
 pub fn get_read_promise_bytes(cx: SafeJSContext, v: &SafeHandleValue) -> Result<Vec<u8>, Error> {
+    if !v.is_object() {
+        return Err(Error::Type("Unknown format for read result.".to_string()));
+    }
     unsafe {
         rooted!(in(*cx) let object = v.to_object());
         rooted!(in(*cx) let mut bytes = UndefinedValue());
         match get_dictionary_property(*cx, object.handle(), "value", bytes.handle_mut()) {
             Ok(true) => {
+                // Chunks of byte streams are views, whose bytes can be copied directly.
+                if bytes.is_object() {
+                    if let Ok(view) = ArrayBufferView::from(bytes.to_object()) {
+                        return Ok(view.to_vec());
+                    }
+                }
                 match Vec::<u8>::from_jsval(*cx, bytes.handle(), ConversionBehavior::EnforceRange) {
                     Ok(ConversionResult::Success(val)) => Ok(val),
                     Ok(ConversionResult::Failure(error)) => Err(Error::Type(error.to_string())),
@@ -538,3 +1783,18 @@ pub fn get_read_promise_bytes(cx: SafeJSContext, v: &SafeHandleValue) -> Result<
         }
     }
 }
+
+/// Creates a `Uint8Array` holding a copy of some bytes.
+#[allow(unsafe_code)]
+pub fn create_uint8_array(cx: SafeJSContext, bytes: &[u8], rval: SafeMutableHandleValue) {
+    rooted!(in(*cx) let mut array = ptr::null_mut::<JSObject>());
+    unsafe {
+        let _ = Uint8Array::create(
+            *cx,
+            js::typedarray::CreateWith::Slice(bytes),
+            array.handle_mut(),
+        );
+    }
+    let mut rval = rval;
+    rval.set(ObjectValue(array.get()));
+}
@@ -0,0 +1,336 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::ReadableStreamBYOBReaderBinding::ReadableStreamBYOBReaderMethods;
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{DomRoot, MutNullableDom};
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::readablestream::{
+    new_type_error, ByteTeeState, ReadableStream, ReadableStreamState, TeeBranch,
+};
+use crate::dom::readablestreamdefaultreader::{rejected_with_type_error, resolve_read_result};
+use crate::script_runtime::JSContext;
+use dom_struct::dom_struct;
+use js::jsapi::JSObject;
+use js::jsval::UndefinedValue;
+use js::rust::{CustomAutoRooterGuard, HandleValue};
+use js::typedarray::ArrayBufferView;
+use std::collections::VecDeque;
+use std::mem;
+use std::rc::Rc;
+
+/// <https://streams.spec.whatwg.org/#read-into-request>
+#[derive(JSTraceable, MallocSizeOf)]
+pub enum ReadIntoRequest {
+    /// A read-into request of `ReadableStreamBYOBReader.read()`, settling its promise.
+    Read(#[ignore_malloc_size_of = "Rc is hard"] Rc<Promise>),
+    /// A read-into request of <https://streams.spec.whatwg.org/#abstract-opdef-readablebytestreamtee>,
+    /// pulling for one of the branches.
+    ByteTee(
+        #[ignore_malloc_size_of = "Rc is hard"] Rc<ByteTeeState>,
+        TeeBranch,
+    ),
+}
+
+impl ReadIntoRequest {
+    /// <https://streams.spec.whatwg.org/#read-into-request-chunk-steps>
+    pub fn chunk_steps(&self, chunk: HandleValue) {
+        match *self {
+            ReadIntoRequest::Read(ref promise) => resolve_read_result(promise, chunk, false),
+            ReadIntoRequest::ByteTee(ref tee_state, branch) => {
+                tee_state.chunk_steps(chunk, Some(branch))
+            },
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#read-into-request-close-steps>
+    pub fn close_steps(&self, chunk: HandleValue) {
+        match *self {
+            ReadIntoRequest::Read(ref promise) => resolve_read_result(promise, chunk, true),
+            ReadIntoRequest::ByteTee(ref tee_state, branch) => {
+                tee_state.byob_close_steps(branch, chunk)
+            },
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#read-into-request-error-steps>
+    pub fn error_steps(&self, e: HandleValue) {
+        match *self {
+            ReadIntoRequest::Read(ref promise) => {
+                let cx = promise.global().get_cx();
+                promise.reject(cx, e);
+            },
+            ReadIntoRequest::ByteTee(ref tee_state, _) => tee_state.error_steps(),
+        }
+    }
+}
+
+/// <https://streams.spec.whatwg.org/#byob-reader-class>
+#[dom_struct]
+pub struct ReadableStreamBYOBReader {
+    reflector_: Reflector,
+    /// <https://streams.spec.whatwg.org/#readablestreamgenericreader-stream>
+    stream: MutNullableDom<ReadableStream>,
+    /// <https://streams.spec.whatwg.org/#readablestreamgenericreader-closedpromise>
+    #[ignore_malloc_size_of = "Rc is hard"]
+    closed_promise: DomRefCell<Rc<Promise>>,
+    /// <https://streams.spec.whatwg.org/#readablestreambyobreader-readintorequests>
+    read_into_requests: DomRefCell<VecDeque<ReadIntoRequest>>,
+}
+
+impl ReadableStreamBYOBReader {
+    fn new_inherited(global: &GlobalScope) -> ReadableStreamBYOBReader {
+        ReadableStreamBYOBReader {
+            reflector_: Reflector::new(),
+            stream: MutNullableDom::new(None),
+            closed_promise: DomRefCell::new(Promise::new(global)),
+            read_into_requests: DomRefCell::new(VecDeque::new()),
+        }
+    }
+
+    fn new(global: &GlobalScope) -> DomRoot<ReadableStreamBYOBReader> {
+        reflect_dom_object(
+            Box::new(ReadableStreamBYOBReader::new_inherited(global)),
+            global,
+        )
+    }
+
+    /// <https://streams.spec.whatwg.org/#byob-reader-constructor>
+    #[allow(non_snake_case)]
+    pub fn Constructor(
+        global: &GlobalScope,
+        stream: &ReadableStream,
+    ) -> Fallible<DomRoot<ReadableStreamBYOBReader>> {
+        ReadableStreamBYOBReader::acquire(global, stream)
+    }
+
+    /// <https://streams.spec.whatwg.org/#acquire-readable-stream-byob-reader>
+    pub fn acquire(
+        global: &GlobalScope,
+        stream: &ReadableStream,
+    ) -> Fallible<DomRoot<ReadableStreamBYOBReader>> {
+        // Step 1.
+        let reader = ReadableStreamBYOBReader::new(global);
+
+        // Step 2.
+        reader.set_up(stream)?;
+
+        // Step 3.
+        Ok(reader)
+    }
+
+    /// <https://streams.spec.whatwg.org/#set-up-readable-stream-byob-reader>
+    fn set_up(&self, stream: &ReadableStream) -> Fallible<()> {
+        // Step 1.
+        if stream.is_locked() {
+            return Err(Error::Type("The stream is locked".to_owned()));
+        }
+
+        // Step 2.
+        if !stream.is_byte_stream() {
+            return Err(Error::Type("The stream is not a byte stream".to_owned()));
+        }
+
+        // Step 3.
+        self.generic_initialize(stream);
+
+        // Step 4 is done in `new_inherited`.
+        Ok(())
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-reader-generic-initialize>
+    fn generic_initialize(&self, stream: &ReadableStream) {
+        // Steps 1-2.
+        self.stream.set(Some(stream));
+        stream.set_byob_reader(Some(self));
+
+        let global = self.global();
+        let cx = global.get_cx();
+        match stream.state() {
+            // Step 3.
+            ReadableStreamState::Readable => {},
+            // Step 4.
+            ReadableStreamState::Closed => self.resolve_closed_promise(),
+            // Step 5.
+            ReadableStreamState::Errored => {
+                rooted!(in(*cx) let mut stored_error = UndefinedValue());
+                stream.get_stored_error(stored_error.handle_mut());
+                self.reject_closed_promise(stored_error.handle());
+            },
+        }
+    }
+
+    pub fn closed_promise(&self) -> Rc<Promise> {
+        self.closed_promise.borrow().clone()
+    }
+
+    pub fn resolve_closed_promise(&self) {
+        self.closed_promise.borrow().resolve_native(&());
+    }
+
+    /// Rejects the closed promise, and marks it as handled.
+    pub fn reject_closed_promise(&self, e: HandleValue) {
+        let cx = self.global().get_cx();
+        let closed_promise = self.closed_promise.borrow().clone();
+        closed_promise.reject(cx, e);
+        closed_promise.set_promise_is_handled();
+    }
+
+    pub fn get_num_read_into_requests(&self) -> usize {
+        self.read_into_requests.borrow().len()
+    }
+
+    pub fn add_read_into_request(&self, read_into_request: ReadIntoRequest) {
+        self.read_into_requests
+            .borrow_mut()
+            .push_back(read_into_request);
+    }
+
+    pub fn take_first_read_into_request(&self) -> ReadIntoRequest {
+        self.read_into_requests
+            .borrow_mut()
+            .pop_front()
+            .expect("Reader should have a read-into request")
+    }
+
+    /// Step 5 of <https://streams.spec.whatwg.org/#readable-stream-cancel>.
+    pub fn close_read_into_requests(&self) {
+        let cx = self.global().get_cx();
+        rooted!(in(*cx) let undefined = UndefinedValue());
+        let read_into_requests =
+            mem::replace(&mut *self.read_into_requests.borrow_mut(), VecDeque::new());
+        for read_into_request in read_into_requests {
+            read_into_request.close_steps(undefined.handle());
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-byob-reader-error-read-into-requests>
+    pub fn error_read_into_requests(&self, e: HandleValue) {
+        let read_into_requests =
+            mem::replace(&mut *self.read_into_requests.borrow_mut(), VecDeque::new());
+        for read_into_request in read_into_requests {
+            read_into_request.error_steps(e);
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-byob-reader-read>
+    pub fn read_with_request(&self, view: *mut JSObject, read_into_request: ReadIntoRequest) {
+        // Step 1.
+        let stream = self.stream.get().expect("Reader should have a stream");
+
+        // Step 2.
+        stream.set_disturbed();
+
+        // Step 3.
+        if stream.is_errored() {
+            let cx = self.global().get_cx();
+            rooted!(in(*cx) let mut stored_error = UndefinedValue());
+            stream.get_stored_error(stored_error.handle_mut());
+            read_into_request.error_steps(stored_error.handle());
+            return;
+        }
+
+        // Step 4.
+        stream
+            .get_byte_controller()
+            .pull_into(view, read_into_request);
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-byob-reader-release>
+    pub fn release(&self) {
+        // Step 1.
+        self.generic_release();
+
+        // Steps 2-3.
+        let global = self.global();
+        let cx = global.get_cx();
+        rooted!(in(*cx) let mut e = UndefinedValue());
+        new_type_error(&global, "The reader was released", e.handle_mut());
+        self.error_read_into_requests(e.handle());
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-reader-generic-release>
+    fn generic_release(&self) {
+        // Steps 1-3.
+        let stream = self.stream.get().expect("Reader should have a stream");
+
+        // Steps 4-5.
+        let global = self.global();
+        let cx = global.get_cx();
+        rooted!(in(*cx) let mut e = UndefinedValue());
+        new_type_error(&global, "The reader was released", e.handle_mut());
+        if !stream.is_readable() {
+            *self.closed_promise.borrow_mut() = Promise::new(&global);
+        }
+        self.reject_closed_promise(e.handle());
+
+        // Step 6.
+        stream.perform_release_steps();
+
+        // Steps 7-8.
+        stream.set_byob_reader(None);
+        self.stream.set(None);
+    }
+}
+
+impl ReadableStreamBYOBReaderMethods for ReadableStreamBYOBReader {
+    /// <https://streams.spec.whatwg.org/#byob-reader-read>
+    #[allow(unsafe_code)]
+    fn Read(&self, view: CustomAutoRooterGuard<ArrayBufferView>) -> Rc<Promise> {
+        let global = self.global();
+
+        // Step 1.
+        if unsafe { view.as_slice() }.is_empty() {
+            return rejected_with_type_error(&global, "The view is empty");
+        }
+
+        // Step 6.
+        if self.stream.get().is_none() {
+            return rejected_with_type_error(&global, "The reader has no stream");
+        }
+
+        // Step 7.
+        let promise = Promise::new(&global);
+
+        // Steps 8-9.
+        self.read_with_request(
+            *view.underlying_object(),
+            ReadIntoRequest::Read(promise.clone()),
+        );
+
+        // Step 10.
+        promise
+    }
+
+    /// <https://streams.spec.whatwg.org/#byob-reader-release-lock>
+    fn ReleaseLock(&self) {
+        // Step 1.
+        if self.stream.get().is_none() {
+            return;
+        }
+
+        // Step 2.
+        self.release();
+    }
+
+    /// <https://streams.spec.whatwg.org/#generic-reader-closed>
+    fn Closed(&self) -> Rc<Promise> {
+        self.closed_promise.borrow().clone()
+    }
+
+    /// <https://streams.spec.whatwg.org/#generic-reader-cancel>
+    fn Cancel(&self, _cx: JSContext, reason: HandleValue) -> Rc<Promise> {
+        // Step 1.
+        let stream = match self.stream.get() {
+            Some(stream) => stream,
+            None => return rejected_with_type_error(&self.global(), "The reader has no stream"),
+        };
+
+        // Step 2.
+        stream.cancel(reason)
+    }
+}
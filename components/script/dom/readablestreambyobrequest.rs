@@ -0,0 +1,101 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::ReadableStreamBYOBRequestBinding::ReadableStreamBYOBRequestMethods;
+use crate::dom::bindings::error::{Error, ErrorResult};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{DomRoot, MutNullableDom};
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::readablebytestreamcontroller::{view_bytes, ReadableByteStreamController};
+use crate::script_runtime::JSContext;
+use dom_struct::dom_struct;
+use js::jsapi::{Heap, JSObject};
+use js::rust::CustomAutoRooterGuard;
+use js::typedarray::ArrayBufferView;
+use std::ptr::{self, NonNull};
+
+/// <https://streams.spec.whatwg.org/#rs-byob-request-class>
+///
+/// The view is a buffer of its own, whose bytes are copied to the view of
+/// the read once the source responds.
+#[dom_struct]
+pub struct ReadableStreamBYOBRequest {
+    reflector_: Reflector,
+    /// <https://streams.spec.whatwg.org/#readablestreambyobrequest-controller>
+    controller: MutNullableDom<ReadableByteStreamController>,
+    /// <https://streams.spec.whatwg.org/#readablestreambyobrequest-view>
+    #[ignore_malloc_size_of = "Defined in rust-mozjs"]
+    view: Heap<*mut JSObject>,
+}
+
+impl ReadableStreamBYOBRequest {
+    fn new_inherited(controller: &ReadableByteStreamController) -> ReadableStreamBYOBRequest {
+        ReadableStreamBYOBRequest {
+            reflector_: Reflector::new(),
+            controller: MutNullableDom::new(Some(controller)),
+            view: Heap::default(),
+        }
+    }
+
+    pub fn new(
+        global: &GlobalScope,
+        controller: &ReadableByteStreamController,
+        view: *mut JSObject,
+    ) -> DomRoot<ReadableStreamBYOBRequest> {
+        let request = reflect_dom_object(
+            Box::new(ReadableStreamBYOBRequest::new_inherited(controller)),
+            global,
+        );
+        request.view.set(view);
+        request
+    }
+
+    /// <https://streams.spec.whatwg.org/#readablestreambyobrequest-view>
+    pub fn view(&self) -> *mut JSObject {
+        self.view.get()
+    }
+
+    /// Step 2 of <https://streams.spec.whatwg.org/#readable-byte-stream-controller-invalidate-byob-request>.
+    pub fn invalidate(&self) {
+        self.controller.set(None);
+        self.view.set(ptr::null_mut());
+    }
+}
+
+impl ReadableStreamBYOBRequestMethods for ReadableStreamBYOBRequest {
+    /// <https://streams.spec.whatwg.org/#rs-byob-request-view>
+    fn GetView(&self, _cx: JSContext) -> Option<NonNull<JSObject>> {
+        NonNull::new(self.view.get())
+    }
+
+    /// <https://streams.spec.whatwg.org/#rs-byob-request-respond>
+    fn Respond(&self, bytes_written: u64) -> ErrorResult {
+        // Step 1.
+        let controller = match self.controller.get() {
+            Some(controller) => controller,
+            None => return Err(Error::Type("The request was invalidated".to_owned())),
+        };
+
+        // Step 4.
+        let written = view_bytes(self.global().get_cx(), self.view.get());
+        if bytes_written > written.len() as u64 {
+            return Err(Error::Range("Too many bytes were written".to_owned()));
+        }
+        controller.respond(bytes_written as usize, &written)
+    }
+
+    /// <https://streams.spec.whatwg.org/#rs-byob-request-respond-with-new-view>
+    #[allow(unsafe_code)]
+    fn RespondWithNewView(&self, view: CustomAutoRooterGuard<ArrayBufferView>) -> ErrorResult {
+        // Step 1.
+        let controller = match self.controller.get() {
+            Some(controller) => controller,
+            None => return Err(Error::Type("The request was invalidated".to_owned())),
+        };
+
+        // Step 3.
+        let written = unsafe { view.as_slice() }.to_vec();
+        controller.respond(written.len(), &written)
+    }
+}
@@ -0,0 +1,394 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::ReadableStreamDefaultControllerBinding::ReadableStreamDefaultControllerMethods;
+use crate::dom::bindings::codegen::UnionTypes::ReadableStreamDefaultControllerOrReadableByteStreamController as ReadableStreamController;
+use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{DomRoot, MutNullableDom};
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::promisenativehandler::{Callback, PromiseNativeHandler};
+use crate::dom::queuingstrategy::{QueueWithSizes, SizeAlgorithm};
+use crate::dom::readablestream::{ReadableStream, ReadableStreamState};
+use crate::dom::readablestreamdefaultreader::ReadRequest;
+use crate::dom::underlyingsourcecontainer::UnderlyingSourceType;
+use crate::realms::{enter_realm, InRealm};
+use crate::script_runtime::JSContext;
+use dom_struct::dom_struct;
+use js::jsapi::ExceptionStackBehavior;
+use js::jsval::UndefinedValue;
+use js::rust::wrappers::JS_SetPendingException;
+use js::rust::HandleValue;
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// <https://streams.spec.whatwg.org/#rs-default-controller-class>
+#[dom_struct]
+pub struct ReadableStreamDefaultController {
+    reflector_: Reflector,
+    /// <https://streams.spec.whatwg.org/#readablestreamdefaultcontroller-stream>
+    stream: MutNullableDom<ReadableStream>,
+    /// The source of the pull and cancel algorithms.
+    #[ignore_malloc_size_of = "Rc is hard"]
+    underlying_source: Rc<UnderlyingSourceType>,
+    /// <https://streams.spec.whatwg.org/#readablestreamdefaultcontroller-queue>
+    queue: DomRefCell<QueueWithSizes>,
+    /// <https://streams.spec.whatwg.org/#readablestreamdefaultcontroller-started>
+    started: Cell<bool>,
+    /// <https://streams.spec.whatwg.org/#readablestreamdefaultcontroller-closerequested>
+    close_requested: Cell<bool>,
+    /// <https://streams.spec.whatwg.org/#readablestreamdefaultcontroller-pullagain>
+    pull_again: Cell<bool>,
+    /// <https://streams.spec.whatwg.org/#readablestreamdefaultcontroller-pulling>
+    pulling: Cell<bool>,
+    /// <https://streams.spec.whatwg.org/#readablestreamdefaultcontroller-strategyhwm>
+    strategy_hwm: f64,
+    /// <https://streams.spec.whatwg.org/#readablestreamdefaultcontroller-strategysizealgorithm>
+    strategy_size_algorithm: SizeAlgorithm,
+}
+
+impl ReadableStreamDefaultController {
+    #[allow(unrooted_must_root)]
+    fn new_inherited(
+        underlying_source: UnderlyingSourceType,
+        strategy_hwm: f64,
+        strategy_size_algorithm: SizeAlgorithm,
+    ) -> ReadableStreamDefaultController {
+        ReadableStreamDefaultController {
+            reflector_: Reflector::new(),
+            stream: MutNullableDom::new(None),
+            underlying_source: Rc::new(underlying_source),
+            queue: DomRefCell::new(Default::default()),
+            started: Cell::new(false),
+            close_requested: Cell::new(false),
+            pull_again: Cell::new(false),
+            pulling: Cell::new(false),
+            strategy_hwm,
+            strategy_size_algorithm,
+        }
+    }
+
+    #[allow(unrooted_must_root)]
+    fn new(
+        global: &GlobalScope,
+        underlying_source: UnderlyingSourceType,
+        strategy_hwm: f64,
+        strategy_size_algorithm: SizeAlgorithm,
+    ) -> DomRoot<ReadableStreamDefaultController> {
+        reflect_dom_object(
+            Box::new(ReadableStreamDefaultController::new_inherited(
+                underlying_source,
+                strategy_hwm,
+                strategy_size_algorithm,
+            )),
+            global,
+        )
+    }
+
+    /// <https://streams.spec.whatwg.org/#set-up-readable-stream-default-controller>
+    #[allow(unrooted_must_root)]
+    pub fn set_up(
+        stream: &ReadableStream,
+        underlying_source: UnderlyingSourceType,
+        high_water_mark: f64,
+        size_algorithm: SizeAlgorithm,
+    ) -> Fallible<()> {
+        let global = stream.global();
+        let cx = global.get_cx();
+
+        // Steps 1-8.
+        let controller = ReadableStreamDefaultController::new(
+            &global,
+            underlying_source,
+            high_water_mark,
+            size_algorithm,
+        );
+        controller.stream.set(Some(stream));
+
+        // Step 9.
+        stream.set_default_controller(&controller);
+
+        // Step 10.
+        rooted!(in(*cx) let mut start_result = UndefinedValue());
+        controller.underlying_source.start(
+            cx,
+            ReadableStreamController::ReadableStreamDefaultController(controller.clone()),
+            start_result.handle_mut(),
+        )?;
+
+        // Step 11.
+        let start_promise = Promise::new_resolved(&global, cx, start_result.handle())?;
+
+        // Steps 12-13.
+        controller.react(&start_promise, ControllerStep::Start);
+        Ok(())
+    }
+
+    fn react(&self, promise: &Promise, step: ControllerStep) {
+        let global = self.global();
+        let handler = PromiseNativeHandler::new(
+            &global,
+            Some(Box::new(ControllerPromiseHandler {
+                controller: DomRoot::from_ref(self),
+                step,
+                fulfilled: true,
+            })),
+            Some(Box::new(ControllerPromiseHandler {
+                controller: DomRoot::from_ref(self),
+                step,
+                fulfilled: false,
+            })),
+        );
+        let realm = enter_realm(&*global);
+        let comp = InRealm::Entered(&realm);
+        promise.append_native_handler(&handler, comp);
+    }
+
+    fn stream(&self) -> DomRoot<ReadableStream> {
+        self.stream.get().expect("Controller should have a stream")
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-controller-call-pull-if-needed>
+    fn call_pull_if_needed(&self) {
+        // Steps 1-2.
+        if !self.should_call_pull() {
+            return;
+        }
+
+        // Step 3.
+        if self.pulling.get() {
+            self.pull_again.set(true);
+            return;
+        }
+
+        // Steps 4-5.
+        self.pulling.set(true);
+
+        // Steps 6-8.
+        let pull_promise = self.underlying_source.pull(
+            &self.global(),
+            ReadableStreamController::ReadableStreamDefaultController(DomRoot::from_ref(self)),
+        );
+        self.react(&pull_promise, ControllerStep::Pull);
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-controller-should-call-pull>
+    fn should_call_pull(&self) -> bool {
+        // Steps 1-3.
+        if !self.can_close_or_enqueue() || !self.started.get() {
+            return false;
+        }
+
+        // Step 4.
+        let stream = self.stream();
+        if stream.is_locked() && stream.get_num_read_requests() > 0 {
+            return true;
+        }
+
+        // Steps 5-7.
+        self.get_desired_size().unwrap_or(0.) > 0.
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-controller-can-close-or-enqueue>
+    pub fn can_close_or_enqueue(&self) -> bool {
+        !self.close_requested.get() && self.stream().is_readable()
+    }
+
+    /// <https://streams.spec.whatwg.org/#rs-default-controller-has-backpressure>
+    pub fn has_backpressure(&self) -> bool {
+        !self.should_call_pull()
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-controller-get-desired-size>
+    pub fn get_desired_size(&self) -> Option<f64> {
+        match self.stream().state() {
+            ReadableStreamState::Errored => None,
+            ReadableStreamState::Closed => Some(0.),
+            ReadableStreamState::Readable => {
+                Some(self.strategy_hwm - self.queue.borrow().total_size())
+            },
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-controller-close>
+    pub fn close(&self) {
+        // Step 1.
+        if !self.can_close_or_enqueue() {
+            return;
+        }
+
+        // Steps 2-3.
+        self.close_requested.set(true);
+
+        // Step 4.
+        if self.queue.borrow().is_empty() {
+            self.stream().close();
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-controller-enqueue>
+    #[allow(unsafe_code)]
+    pub fn enqueue(&self, chunk: HandleValue) -> ErrorResult {
+        // Step 1.
+        if !self.can_close_or_enqueue() {
+            return Ok(());
+        }
+
+        // Step 3.
+        let stream = self.stream();
+        if stream.is_locked() && stream.get_num_read_requests() > 0 {
+            stream.fulfill_read_request(chunk, false);
+        } else {
+            // Step 4.
+            let result = self
+                .strategy_size_algorithm
+                .size(chunk)
+                .and_then(|size| self.queue.borrow_mut().enqueue_value_with_size(chunk, size));
+            if let Err(error) = result {
+                // The stream is errored with the exception, which is then rethrown.
+                let global = self.global();
+                let cx = global.get_cx();
+                let _ac = enter_realm(&*global);
+                rooted!(in(*cx) let mut e = UndefinedValue());
+                unsafe { error.to_jsval(*cx, &global, e.handle_mut()) };
+                self.error(e.handle());
+                unsafe { JS_SetPendingException(*cx, e.handle(), ExceptionStackBehavior::Capture) };
+                return Err(Error::JSFailed);
+            }
+        }
+
+        // Step 5.
+        self.call_pull_if_needed();
+        Ok(())
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-controller-error>
+    pub fn error(&self, e: HandleValue) {
+        // Steps 1-2.
+        let stream = self.stream();
+        if !stream.is_readable() {
+            return;
+        }
+
+        // Steps 3-5.
+        self.queue.borrow_mut().reset();
+        stream.error_stream(e);
+    }
+
+    /// <https://streams.spec.whatwg.org/#rs-default-controller-private-cancel>
+    pub fn perform_cancel_steps(&self, reason: HandleValue) -> Rc<Promise> {
+        // Step 1.
+        self.queue.borrow_mut().reset();
+
+        // Steps 2-4.
+        self.underlying_source.cancel(&self.global(), reason)
+    }
+
+    /// <https://streams.spec.whatwg.org/#rs-default-controller-private-pull>
+    pub fn perform_pull_steps(&self, read_request: ReadRequest) {
+        // Step 1.
+        let stream = self.stream();
+
+        // Step 2.
+        if !self.queue.borrow().is_empty() {
+            // Step 2.1.
+            let cx = self.global().get_cx();
+            rooted!(in(*cx) let mut chunk = UndefinedValue());
+            self.queue.borrow_mut().dequeue_value(chunk.handle_mut());
+
+            // Step 2.2.
+            if self.close_requested.get() && self.queue.borrow().is_empty() {
+                stream.close();
+            } else {
+                // Step 2.3.
+                self.call_pull_if_needed();
+            }
+
+            // Step 2.4.
+            read_request.chunk_steps(chunk.handle());
+        } else {
+            // Steps 3.1-3.2.
+            stream.add_read_request(read_request);
+            self.call_pull_if_needed();
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#abstract-opdef-readablestreamdefaultcontroller-releasesteps>
+    pub fn perform_release_steps(&self) {}
+}
+
+impl ReadableStreamDefaultControllerMethods for ReadableStreamDefaultController {
+    /// <https://streams.spec.whatwg.org/#rs-default-controller-desired-size>
+    fn GetDesiredSize(&self) -> Option<f64> {
+        self.get_desired_size()
+    }
+
+    /// <https://streams.spec.whatwg.org/#rs-default-controller-close>
+    fn Close(&self) -> ErrorResult {
+        // Step 1.
+        if !self.can_close_or_enqueue() {
+            return Err(Error::Type("The stream cannot be closed".to_owned()));
+        }
+
+        // Step 2.
+        self.close();
+        Ok(())
+    }
+
+    /// <https://streams.spec.whatwg.org/#rs-default-controller-enqueue>
+    fn Enqueue(&self, _cx: JSContext, chunk: HandleValue) -> ErrorResult {
+        // Step 1.
+        if !self.can_close_or_enqueue() {
+            return Err(Error::Type("Chunks cannot be enqueued".to_owned()));
+        }
+
+        // Step 2.
+        self.enqueue(chunk)
+    }
+
+    /// <https://streams.spec.whatwg.org/#rs-default-controller-error>
+    fn Error(&self, _cx: JSContext, e: HandleValue) {
+        self.error(e);
+    }
+}
+
+/// The algorithms of a controller that wait for a promise.
+#[derive(Clone, Copy, JSTraceable, MallocSizeOf)]
+enum ControllerStep {
+    /// The start algorithm settled.
+    Start,
+    /// The pull algorithm settled.
+    Pull,
+}
+
+#[derive(JSTraceable, MallocSizeOf)]
+struct ControllerPromiseHandler {
+    controller: DomRoot<ReadableStreamDefaultController>,
+    step: ControllerStep,
+    fulfilled: bool,
+}
+
+impl Callback for ControllerPromiseHandler {
+    fn callback(&self, _cx: JSContext, v: HandleValue, _realm: InRealm) {
+        let controller = &self.controller;
+        if !self.fulfilled {
+            controller.error(v);
+            return;
+        }
+        match self.step {
+            ControllerStep::Start => {
+                controller.started.set(true);
+            },
+            ControllerStep::Pull => {
+                controller.pulling.set(false);
+                if !controller.pull_again.replace(false) {
+                    return;
+                }
+            },
+        }
+        controller.call_pull_if_needed();
+    }
+}
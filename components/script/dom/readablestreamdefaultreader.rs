@@ -0,0 +1,357 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::ReadableStreamDefaultReaderBinding::ReadableStreamDefaultReaderMethods;
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{DomRoot, MutNullableDom};
+use crate::dom::bindings::utils::set_dictionary_property;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::readablestream::{
+    new_type_error, ByteTeeState, DefaultTeeState, ReadableStream, ReadableStreamState,
+};
+use crate::realms::enter_realm;
+use crate::script_runtime::JSContext;
+use dom_struct::dom_struct;
+use js::jsapi::JS_NewPlainObject;
+use js::jsval::{BooleanValue, ObjectValue, UndefinedValue};
+use js::rust::{HandleValue, MutableHandleValue};
+use std::collections::VecDeque;
+use std::mem;
+use std::rc::Rc;
+
+/// <https://streams.spec.whatwg.org/#read-request>
+#[derive(JSTraceable, MallocSizeOf)]
+pub enum ReadRequest {
+    /// A read request of `ReadableStreamDefaultReader.read()`, settling its promise.
+    Read(#[ignore_malloc_size_of = "Rc is hard"] Rc<Promise>),
+    /// A read request of <https://streams.spec.whatwg.org/#readable-stream-default-tee>.
+    DefaultTee(#[ignore_malloc_size_of = "Rc is hard"] Rc<DefaultTeeState>),
+    /// A read request of <https://streams.spec.whatwg.org/#abstract-opdef-readablebytestreamtee>.
+    ByteTee(#[ignore_malloc_size_of = "Rc is hard"] Rc<ByteTeeState>),
+}
+
+impl ReadRequest {
+    /// <https://streams.spec.whatwg.org/#read-request-chunk-steps>
+    pub fn chunk_steps(&self, chunk: HandleValue) {
+        match *self {
+            ReadRequest::Read(ref promise) => resolve_read_result(promise, chunk, false),
+            ReadRequest::DefaultTee(ref tee_state) => tee_state.chunk_steps(chunk),
+            ReadRequest::ByteTee(ref tee_state) => tee_state.chunk_steps(chunk, None),
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#read-request-close-steps>
+    pub fn close_steps(&self) {
+        match *self {
+            ReadRequest::Read(ref promise) => {
+                let cx = promise.global().get_cx();
+                rooted!(in(*cx) let undefined = UndefinedValue());
+                resolve_read_result(promise, undefined.handle(), true);
+            },
+            ReadRequest::DefaultTee(ref tee_state) => tee_state.close_steps(),
+            ReadRequest::ByteTee(ref tee_state) => tee_state.close_steps(),
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#read-request-error-steps>
+    pub fn error_steps(&self, e: HandleValue) {
+        match *self {
+            ReadRequest::Read(ref promise) => {
+                let cx = promise.global().get_cx();
+                promise.reject(cx, e);
+            },
+            ReadRequest::DefaultTee(ref tee_state) => tee_state.error_steps(),
+            ReadRequest::ByteTee(ref tee_state) => tee_state.error_steps(),
+        }
+    }
+}
+
+/// Creates a `ReadableStreamReadResult` dictionary.
+#[allow(unsafe_code)]
+pub fn create_read_result(
+    global: &GlobalScope,
+    value: HandleValue,
+    done: bool,
+    mut rval: MutableHandleValue,
+) {
+    let cx = global.get_cx();
+    let _ac = enter_realm(global);
+    rooted!(in(*cx) let result = unsafe { JS_NewPlainObject(*cx) });
+    rooted!(in(*cx) let done = BooleanValue(done));
+    set_dictionary_property(*cx, result.handle(), "value", value)
+        .expect("Couldn't set the value of a read result");
+    set_dictionary_property(*cx, result.handle(), "done", done.handle())
+        .expect("Couldn't set the done flag of a read result");
+    rval.set(ObjectValue(result.get()));
+}
+
+/// Resolves the promise of a read with a `ReadableStreamReadResult`.
+pub fn resolve_read_result(promise: &Promise, value: HandleValue, done: bool) {
+    let global = promise.global();
+    let cx = global.get_cx();
+    rooted!(in(*cx) let mut result = UndefinedValue());
+    create_read_result(&global, value, done, result.handle_mut());
+    promise.resolve(cx, result.handle());
+}
+
+/// A promise rejected with a `TypeError`.
+pub fn rejected_with_type_error(global: &GlobalScope, message: &str) -> Rc<Promise> {
+    let promise = Promise::new(global);
+    promise.reject_error(Error::Type(message.to_owned()));
+    promise
+}
+
+/// <https://streams.spec.whatwg.org/#default-reader-class>
+#[dom_struct]
+pub struct ReadableStreamDefaultReader {
+    reflector_: Reflector,
+    /// <https://streams.spec.whatwg.org/#readablestreamgenericreader-stream>
+    stream: MutNullableDom<ReadableStream>,
+    /// <https://streams.spec.whatwg.org/#readablestreamgenericreader-closedpromise>
+    #[ignore_malloc_size_of = "Rc is hard"]
+    closed_promise: DomRefCell<Rc<Promise>>,
+    /// <https://streams.spec.whatwg.org/#readablestreamdefaultreader-readrequests>
+    read_requests: DomRefCell<VecDeque<ReadRequest>>,
+}
+
+impl ReadableStreamDefaultReader {
+    fn new_inherited(global: &GlobalScope) -> ReadableStreamDefaultReader {
+        ReadableStreamDefaultReader {
+            reflector_: Reflector::new(),
+            stream: MutNullableDom::new(None),
+            closed_promise: DomRefCell::new(Promise::new(global)),
+            read_requests: DomRefCell::new(VecDeque::new()),
+        }
+    }
+
+    fn new(global: &GlobalScope) -> DomRoot<ReadableStreamDefaultReader> {
+        reflect_dom_object(
+            Box::new(ReadableStreamDefaultReader::new_inherited(global)),
+            global,
+        )
+    }
+
+    /// <https://streams.spec.whatwg.org/#default-reader-constructor>
+    #[allow(non_snake_case)]
+    pub fn Constructor(
+        global: &GlobalScope,
+        stream: &ReadableStream,
+    ) -> Fallible<DomRoot<ReadableStreamDefaultReader>> {
+        ReadableStreamDefaultReader::acquire(global, stream)
+    }
+
+    /// <https://streams.spec.whatwg.org/#acquire-readable-stream-reader>
+    pub fn acquire(
+        global: &GlobalScope,
+        stream: &ReadableStream,
+    ) -> Fallible<DomRoot<ReadableStreamDefaultReader>> {
+        // Step 1.
+        let reader = ReadableStreamDefaultReader::new(global);
+
+        // Step 2.
+        reader.set_up(stream)?;
+
+        // Step 3.
+        Ok(reader)
+    }
+
+    /// <https://streams.spec.whatwg.org/#set-up-readable-stream-default-reader>
+    fn set_up(&self, stream: &ReadableStream) -> Fallible<()> {
+        // Step 1.
+        if stream.is_locked() {
+            return Err(Error::Type("The stream is locked".to_owned()));
+        }
+
+        // Step 2.
+        self.generic_initialize(stream);
+
+        // Step 3 is done in `new_inherited`.
+        Ok(())
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-reader-generic-initialize>
+    fn generic_initialize(&self, stream: &ReadableStream) {
+        // Steps 1-2.
+        self.stream.set(Some(stream));
+        stream.set_default_reader(Some(self));
+
+        let global = self.global();
+        let cx = global.get_cx();
+        match stream.state() {
+            // Step 3.
+            ReadableStreamState::Readable => {},
+            // Step 4.
+            ReadableStreamState::Closed => self.resolve_closed_promise(),
+            // Step 5.
+            ReadableStreamState::Errored => {
+                rooted!(in(*cx) let mut stored_error = UndefinedValue());
+                stream.get_stored_error(stored_error.handle_mut());
+                self.reject_closed_promise(stored_error.handle());
+            },
+        }
+    }
+
+    pub fn closed_promise(&self) -> Rc<Promise> {
+        self.closed_promise.borrow().clone()
+    }
+
+    pub fn resolve_closed_promise(&self) {
+        self.closed_promise.borrow().resolve_native(&());
+    }
+
+    /// Rejects the closed promise, and marks it as handled.
+    pub fn reject_closed_promise(&self, e: HandleValue) {
+        let cx = self.global().get_cx();
+        let closed_promise = self.closed_promise();
+        closed_promise.reject(cx, e);
+        closed_promise.set_promise_is_handled();
+    }
+
+    pub fn get_num_read_requests(&self) -> usize {
+        self.read_requests.borrow().len()
+    }
+
+    pub fn add_read_request(&self, read_request: ReadRequest) {
+        self.read_requests.borrow_mut().push_back(read_request);
+    }
+
+    pub fn take_first_read_request(&self) -> ReadRequest {
+        self.read_requests
+            .borrow_mut()
+            .pop_front()
+            .expect("Reader should have a read request")
+    }
+
+    /// Step 6 of <https://streams.spec.whatwg.org/#readable-stream-close>.
+    pub fn close_read_requests(&self) {
+        let read_requests = mem::replace(&mut *self.read_requests.borrow_mut(), VecDeque::new());
+        for read_request in read_requests {
+            read_request.close_steps();
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-reader-error-read-requests>
+    pub fn error_read_requests(&self, e: HandleValue) {
+        let read_requests = mem::replace(&mut *self.read_requests.borrow_mut(), VecDeque::new());
+        for read_request in read_requests {
+            read_request.error_steps(e);
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-reader-read>
+    pub fn read_with_request(&self, read_request: ReadRequest) {
+        // Step 1.
+        let stream = self.stream.get().expect("Reader should have a stream");
+
+        // Step 2.
+        stream.set_disturbed();
+
+        match stream.state() {
+            // Step 3.
+            ReadableStreamState::Closed => read_request.close_steps(),
+            // Step 4.
+            ReadableStreamState::Errored => {
+                let cx = self.global().get_cx();
+                rooted!(in(*cx) let mut stored_error = UndefinedValue());
+                stream.get_stored_error(stored_error.handle_mut());
+                read_request.error_steps(stored_error.handle());
+            },
+            // Step 5.
+            ReadableStreamState::Readable => stream.perform_pull_steps(read_request),
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#default-reader-read>, for a reader
+    /// that has a stream.
+    pub fn read(&self) -> Rc<Promise> {
+        // Steps 2-4.
+        let promise = Promise::new(&self.global());
+
+        // Step 5.
+        self.read_with_request(ReadRequest::Read(promise.clone()));
+
+        // Step 6.
+        promise
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-reader-release>
+    pub fn release(&self) {
+        // Step 1.
+        self.generic_release();
+
+        // Steps 2-3.
+        let global = self.global();
+        let cx = global.get_cx();
+        rooted!(in(*cx) let mut e = UndefinedValue());
+        new_type_error(&global, "The reader was released", e.handle_mut());
+        self.error_read_requests(e.handle());
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-reader-generic-release>
+    fn generic_release(&self) {
+        // Steps 1-3.
+        let stream = self.stream.get().expect("Reader should have a stream");
+
+        // Steps 4-5.
+        let global = self.global();
+        let cx = global.get_cx();
+        rooted!(in(*cx) let mut e = UndefinedValue());
+        new_type_error(&global, "The reader was released", e.handle_mut());
+        if !stream.is_readable() {
+            *self.closed_promise.borrow_mut() = Promise::new(&global);
+        }
+        self.reject_closed_promise(e.handle());
+
+        // Step 6.
+        stream.perform_release_steps();
+
+        // Steps 7-8.
+        stream.set_default_reader(None);
+        self.stream.set(None);
+    }
+}
+
+impl ReadableStreamDefaultReaderMethods for ReadableStreamDefaultReader {
+    /// <https://streams.spec.whatwg.org/#default-reader-read>
+    fn Read(&self) -> Rc<Promise> {
+        // Step 1.
+        if self.stream.get().is_none() {
+            return rejected_with_type_error(&self.global(), "The reader has no stream");
+        }
+
+        // Steps 2-6.
+        self.read()
+    }
+
+    /// <https://streams.spec.whatwg.org/#default-reader-release-lock>
+    fn ReleaseLock(&self) {
+        // Step 1.
+        if self.stream.get().is_none() {
+            return;
+        }
+
+        // Step 2.
+        self.release();
+    }
+
+    /// <https://streams.spec.whatwg.org/#generic-reader-closed>
+    fn Closed(&self) -> Rc<Promise> {
+        self.closed_promise()
+    }
+
+    /// <https://streams.spec.whatwg.org/#generic-reader-cancel>
+    fn Cancel(&self, _cx: JSContext, reason: HandleValue) -> Rc<Promise> {
+        // Step 1.
+        let stream = match self.stream.get() {
+            Some(stream) => stream,
+            None => return rejected_with_type_error(&self.global(), "The reader has no stream"),
+        };
+
+        // Step 2.
+        stream.cancel(reason)
+    }
+}
@@ -4,6 +4,7 @@
 
 use crate::body::Extractable;
 use crate::body::{consume_body, BodyMixin, BodyType};
+use crate::dom::abortsignal::AbortSignal;
 use crate::dom::bindings::cell::DomRefCell;
 use crate::dom::bindings::codegen::Bindings::HeadersBinding::{HeadersInit, HeadersMethods};
 use crate::dom::bindings::codegen::Bindings::RequestBinding::ReferrerPolicy;
@@ -24,12 +25,10 @@ use crate::dom::globalscope::GlobalScope;
 use crate::dom::headers::{Guard, Headers};
 use crate::dom::promise::Promise;
 use crate::dom::readablestream::ReadableStream;
-use crate::script_runtime::JSContext as SafeJSContext;
 use dom_struct::dom_struct;
-use http::header::{HeaderName, HeaderValue};
+use http::header::{HeaderMap, HeaderName, HeaderValue};
 use http::method::InvalidMethod;
 use http::Method as HttpMethod;
-use js::jsapi::JSObject;
 use net_traits::request::CacheMode as NetTraitsRequestCache;
 use net_traits::request::CredentialsMode as NetTraitsRequestCredentials;
 use net_traits::request::Destination as NetTraitsRequestDestination;
@@ -40,7 +39,6 @@ use net_traits::request::RequestMode as NetTraitsRequestMode;
 use net_traits::request::{Origin, Window};
 use net_traits::ReferrerPolicy as MsgReferrerPolicy;
 use servo_url::ServoUrl;
-use std::ptr::NonNull;
 use std::rc::Rc;
 use std::str::FromStr;
 
@@ -51,6 +49,7 @@ pub struct Request {
     body_stream: MutNullableDom<ReadableStream>,
     headers: MutNullableDom<Headers>,
     mime_type: DomRefCell<Vec<u8>>,
+    signal: MutNullableDom<AbortSignal>,
 }
 
 impl Request {
@@ -61,6 +60,7 @@ impl Request {
             body_stream: MutNullableDom::new(None),
             headers: Default::default(),
             mime_type: DomRefCell::new("".to_string().into_bytes()),
+            signal: Default::default(),
         }
     }
 
@@ -87,7 +87,8 @@ impl Request {
         // Step 4
         let base_url = global.api_base_url();
 
-        // Step 5 TODO: "Let signal be null."
+        // Step 5
+        let mut signal: Option<DomRoot<AbortSignal>> = None;
 
         match input {
             // Step 6
@@ -119,7 +120,8 @@ impl Request {
                 }
                 // Step 7.1
                 temporary_request = input_request.request.borrow().clone();
-                // Step 7.2 TODO: "Set signal to input's signal."
+                // Step 7.2
+                signal = Some(input_request.Signal());
             },
         }
 
@@ -298,12 +300,18 @@ impl Request {
             request.method = method;
         }
 
-        // Step 28 TODO: "If init["signal"] exists..."
+        // Step 28
+        if let Some(ref init_signal) = init.signal {
+            signal = init_signal.clone();
+        }
 
         // Step 29
         let r = Request::from_net_request(global, request);
 
-        // Step 30 TODO: "If signal is not null..."
+        // Step 30
+        let signals: Vec<DomRoot<AbortSignal>> = signal.into_iter().collect();
+        r.signal
+            .set(Some(&AbortSignal::create_dependent(global, &signals)));
 
         // Step 31
         // "or_init" looks unclear here, but it always enters the block since r
@@ -474,12 +482,51 @@ impl Request {
         *r_clone.mime_type.borrow_mut() = mime_type;
         r_clone.Headers().copy_from_headers(r.Headers())?;
         r_clone.Headers().set_guard(headers_guard);
+        let signals = vec![r.Signal()];
+        r_clone
+            .signal
+            .set(Some(&AbortSignal::create_dependent(&r.global(), &signals)));
         Ok(r_clone)
     }
 
     pub fn get_request(&self) -> NetTraitsRequest {
         self.request.borrow().clone()
     }
+
+    /// Creates a request with an immutable header list, such as the ones
+    /// returned by `Cache.keys()`.
+    pub fn new_with_parts(
+        global: &GlobalScope,
+        url: ServoUrl,
+        method: HttpMethod,
+        headers: HeaderMap,
+    ) -> DomRoot<Request> {
+        let r = Request::new(global, url);
+        {
+            let mut request = r.request.borrow_mut();
+            request.method = method;
+            request.headers = headers.clone();
+        }
+        r.Headers().set_headers(headers);
+        r.Headers().set_guard(Guard::Immutable);
+        *r.mime_type.borrow_mut() = r.Headers().extract_mime_type();
+        r
+    }
+
+    /// Creates a request with an immutable header list for a request made by
+    /// another global, such as the request of a fetch event.
+    pub fn new_with_immutable_headers(
+        global: &GlobalScope,
+        request: NetTraitsRequest,
+    ) -> DomRoot<Request> {
+        let headers = request.headers.clone();
+        let r = Request::new(global, request.url());
+        *r.request.borrow_mut() = request;
+        r.Headers().set_headers(headers);
+        r.Headers().set_guard(Guard::Immutable);
+        *r.mime_type.borrow_mut() = r.Headers().extract_mime_type();
+        r
+    }
 }
 
 fn net_request_from_global(global: &GlobalScope, url: ServoUrl) -> NetTraitsRequest {
@@ -614,9 +661,14 @@ impl RequestMethods for Request {
         DOMString::from_string(r.integrity_metadata.clone())
     }
 
+    // https://fetch.spec.whatwg.org/#dom-request-signal
+    fn Signal(&self) -> DomRoot<AbortSignal> {
+        self.signal.or_init(|| AbortSignal::new(&self.global()))
+    }
+
     /// <https://fetch.spec.whatwg.org/#dom-body-body>
-    fn GetBody(&self, _cx: SafeJSContext) -> Option<NonNull<JSObject>> {
-        self.body().map(|stream| stream.get_js_stream())
+    fn GetBody(&self) -> Option<DomRoot<ReadableStream>> {
+        self.body()
     }
 
     // https://fetch.spec.whatwg.org/#dom-body-bodyused
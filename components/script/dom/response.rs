@@ -20,15 +20,12 @@ use crate::dom::headers::{is_obs_text, is_vchar};
 use crate::dom::headers::{Guard, Headers};
 use crate::dom::promise::Promise;
 use crate::dom::readablestream::{ExternalUnderlyingSource, ReadableStream};
-use crate::script_runtime::JSContext as SafeJSContext;
 use crate::script_runtime::StreamConsumer;
 use dom_struct::dom_struct;
 use http::header::HeaderMap as HyperHeaders;
 use hyper::StatusCode;
 use hyper_serde::Serde;
-use js::jsapi::JSObject;
 use servo_url::ServoUrl;
-use std::ptr::NonNull;
 use std::rc::Rc;
 use std::str::FromStr;
 use url::Position;
@@ -359,8 +356,8 @@ impl ResponseMethods for Response {
     }
 
     /// <https://fetch.spec.whatwg.org/#dom-body-body>
-    fn GetBody(&self, _cx: SafeJSContext) -> Option<NonNull<JSObject>> {
-        self.body().map(|stream| stream.get_js_stream())
+    fn GetBody(&self) -> Option<DomRoot<ReadableStream>> {
+        self.body()
     }
 
     // https://fetch.spec.whatwg.org/#dom-body-text
@@ -408,6 +405,9 @@ impl Response {
     }
 
     pub fn set_raw_status(&self, status: Option<(u16, Vec<u8>)>) {
+        *self.status.borrow_mut() = status
+            .as_ref()
+            .and_then(|&(code, _)| StatusCode::from_u16(code).ok());
         *self.raw_status.borrow_mut() = status;
     }
 
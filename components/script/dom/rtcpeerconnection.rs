@@ -3,19 +3,24 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::MediaStreamTrackBinding::MediaStreamTrackMethods;
+use crate::dom::bindings::codegen::Bindings::PerformanceBinding::PerformanceMethods;
 use crate::dom::bindings::codegen::Bindings::RTCDataChannelBinding::RTCDataChannelInit;
+use crate::dom::bindings::codegen::Bindings::RTCDataChannelBinding::RTCDataChannelMethods;
 use crate::dom::bindings::codegen::Bindings::RTCIceCandidateBinding::RTCIceCandidateInit;
 use crate::dom::bindings::codegen::Bindings::RTCPeerConnectionBinding::RTCPeerConnectionMethods;
 use crate::dom::bindings::codegen::Bindings::RTCPeerConnectionBinding::{
     RTCAnswerOptions, RTCBundlePolicy, RTCConfiguration, RTCIceConnectionState,
     RTCIceGatheringState, RTCOfferOptions, RTCRtpTransceiverInit, RTCSignalingState,
 };
+use crate::dom::bindings::codegen::Bindings::RTCRtpReceiverBinding::RTCRtpReceiverMethods;
+use crate::dom::bindings::codegen::Bindings::RTCRtpTransceiverBinding::RTCRtpTransceiverDirection;
 use crate::dom::bindings::codegen::Bindings::RTCSessionDescriptionBinding::{
     RTCSdpType, RTCSessionDescriptionInit,
 };
 use crate::dom::bindings::codegen::UnionTypes::{MediaStreamTrackOrString, StringOrStringSequence};
 use crate::dom::bindings::error::Error;
-use crate::dom::bindings::error::Fallible;
+use crate::dom::bindings::error::{ErrorResult, Fallible};
 use crate::dom::bindings::inheritance::Castable;
 use crate::dom::bindings::refcounted::{Trusted, TrustedPromise};
 use crate::dom::bindings::reflector::reflect_dom_object;
@@ -32,8 +37,11 @@ use crate::dom::rtcdatachannel::RTCDataChannel;
 use crate::dom::rtcdatachannelevent::RTCDataChannelEvent;
 use crate::dom::rtcicecandidate::RTCIceCandidate;
 use crate::dom::rtcpeerconnectioniceevent::RTCPeerConnectionIceEvent;
+use crate::dom::rtcrtpreceiver::RTCRtpReceiver;
+use crate::dom::rtcrtpsender::RTCRtpSender;
 use crate::dom::rtcrtptransceiver::RTCRtpTransceiver;
 use crate::dom::rtcsessiondescription::RTCSessionDescription;
+use crate::dom::rtcstatsreport::{RTCStatsDetails, RTCStatsEntry, RTCStatsReport};
 use crate::dom::rtctrackevent::RTCTrackEvent;
 use crate::dom::window::Window;
 use crate::realms::{enter_realm, InRealm};
@@ -54,6 +62,7 @@ use servo_media::ServoMedia;
 use std::cell::Cell;
 use std::collections::HashMap;
 use std::rc::Rc;
+use uuid::Uuid;
 
 #[dom_struct]
 pub struct RTCPeerConnection {
@@ -75,6 +84,27 @@ pub struct RTCPeerConnection {
     signaling_state: Cell<RTCSignalingState>,
     #[ignore_malloc_size_of = "defined in servo-media"]
     data_channels: DomRefCell<HashMap<DataChannelId, Dom<RTCDataChannel>>>,
+    /// https://w3c.github.io/webrtc-pc/#dfn-data-channels-opened
+    data_channels_opened: Cell<u32>,
+    data_channels_closed: Cell<u32>,
+    /// Whether a data channel was ever created on this connection, and whether
+    /// that has been part of a completed or an ongoing negotiation.
+    has_data_channels: Cell<bool>,
+    data_channels_offered: Cell<bool>,
+    data_channels_negotiated: Cell<bool>,
+    /// https://w3c.github.io/webrtc-pc/#dfn-transceivers
+    transceivers: DomRefCell<Vec<Dom<RTCRtpTransceiver>>>,
+    /// https://w3c.github.io/webrtc-pc/#dfn-negotiationneeded
+    negotiation_needed: Cell<bool>,
+    /// https://w3c.github.io/webrtc-pc/#dfn-localicecredentialstoreplace
+    ice_restart_pending: Cell<bool>,
+    ice_restart_offered: Cell<bool>,
+    /// The ICE username fragment and password replacing the current ones in
+    /// the offers created while an ICE restart is pending.
+    ice_restart_credentials: DomRefCell<Option<(String, String)>>,
+    /// The ICE candidates gathered locally and received from the remote peer.
+    local_candidates: DomRefCell<Vec<String>>,
+    remote_candidates: DomRefCell<Vec<String>>,
 }
 
 struct RTCSignaller {
@@ -190,6 +220,18 @@ impl RTCPeerConnection {
             ice_connection_state: Cell::new(RTCIceConnectionState::New),
             signaling_state: Cell::new(RTCSignalingState::Stable),
             data_channels: DomRefCell::new(HashMap::new()),
+            data_channels_opened: Cell::new(0),
+            data_channels_closed: Cell::new(0),
+            has_data_channels: Cell::new(false),
+            data_channels_offered: Cell::new(false),
+            data_channels_negotiated: Cell::new(false),
+            transceivers: DomRefCell::new(vec![]),
+            negotiation_needed: Cell::new(false),
+            ice_restart_pending: Cell::new(false),
+            ice_restart_offered: Cell::new(false),
+            ice_restart_credentials: DomRefCell::new(None),
+            local_candidates: DomRefCell::new(vec![]),
+            remote_candidates: DomRefCell::new(vec![]),
         }
     }
 
@@ -250,6 +292,9 @@ impl RTCPeerConnection {
         if self.closed.get() {
             return;
         }
+        self.local_candidates
+            .borrow_mut()
+            .push(candidate.candidate.clone());
         let candidate = RTCIceCandidate::new(
             &self.global(),
             candidate.candidate.into(),
@@ -268,24 +313,156 @@ impl RTCPeerConnection {
     }
 
     fn on_negotiation_needed(&self) {
-        if self.closed.get() {
-            return;
-        }
-        let event = Event::new(
+        self.update_negotiation_needed_flag();
+    }
+
+    /// https://w3c.github.io/webrtc-pc/#dfn-update-the-negotiation-needed-flag
+    pub(crate) fn update_negotiation_needed_flag(&self) {
+        // Step 1 does not apply, as there is no operations chain yet.
+
+        // Step 2.
+        let this = Trusted::new(self);
+        let _ = self.global().networking_task_source().queue(
+            task!(update_negotiation_needed_flag: move || {
+                let this = this.root();
+
+                // Step 2.1.
+                if this.closed.get() {
+                    return;
+                }
+
+                // Step 2.3.
+                // The flag is updated again once the signaling state is stable.
+                if this.signaling_state.get() != RTCSignalingState::Stable {
+                    return;
+                }
+
+                // Step 2.4.
+                if !this.is_negotiation_needed() {
+                    this.negotiation_needed.set(false);
+                    return;
+                }
+
+                // Step 2.5.
+                if this.negotiation_needed.get() {
+                    return;
+                }
+
+                // Step 2.6.
+                this.negotiation_needed.set(true);
+
+                // Step 2.7.
+                let event = Event::new(
+                    &this.global(),
+                    atom!("negotiationneeded"),
+                    EventBubbles::DoesNotBubble,
+                    EventCancelable::NotCancelable,
+                );
+                event.upcast::<Event>().fire(this.upcast());
+            }),
             &self.global(),
-            atom!("negotiationneeded"),
-            EventBubbles::DoesNotBubble,
-            EventCancelable::NotCancelable,
         );
-        event.upcast::<Event>().fire(self.upcast());
     }
 
+    /// https://w3c.github.io/webrtc-pc/#dfn-check-if-negotiation-is-needed
+    fn is_negotiation_needed(&self) -> bool {
+        // Step 3.
+        if self.ice_restart_pending.get() {
+            return true;
+        }
+
+        // Step 4.
+        if self.has_data_channels.get() && !self.data_channels_negotiated.get() {
+            return true;
+        }
+
+        // Step 5.
+        // XXX The backend does not expose the negotiated media sections, so
+        // the current direction stands in for both the association with a
+        // media section and its negotiated attributes.
+        self.transceivers
+            .borrow()
+            .iter()
+            .any(|transceiver| transceiver.current_direction() != Some(transceiver.direction()))
+    }
+
+    /// Snapshots the state covered by an offer about to be applied, so it can
+    /// be considered negotiated once the signaling state returns to stable.
+    fn offer_in_progress(&self) {
+        for transceiver in self.transceivers.borrow().iter() {
+            transceiver.offer();
+        }
+        self.data_channels_offered.set(self.has_data_channels.get());
+        self.ice_restart_offered.set(self.ice_restart_pending.get());
+    }
+
+    /// The offer/answer exchange completed and the signaling state is stable.
+    fn negotiation_completed(&self) {
+        for transceiver in self.transceivers.borrow().iter() {
+            transceiver.negotiated();
+        }
+        if self.data_channels_offered.take() {
+            self.data_channels_negotiated.set(true);
+        }
+        if self.ice_restart_offered.take() {
+            self.ice_restart_pending.set(false);
+            *self.ice_restart_credentials.borrow_mut() = None;
+        }
+
+        // Anything changed in the meantime needs another round of negotiation.
+        self.negotiation_needed.set(false);
+        self.update_negotiation_needed_flag();
+    }
+
+    /// https://w3c.github.io/webrtc-pc/#dfn-process-remote-tracks
     fn on_add_stream(&self, id: MediaStreamId, ty: MediaStreamType) {
         if self.closed.get() {
             return;
         }
-        let track = MediaStreamTrack::new(&self.global(), id, ty);
-        let event = RTCTrackEvent::new(&self.global(), atom!("track"), false, false, &track);
+        let global = self.global();
+        let track = MediaStreamTrack::new(&global, id, ty);
+
+        // Hand the track to the first transceiver of the right kind which
+        // is waiting for remote media.
+        let transceiver = self
+            .transceivers
+            .borrow()
+            .iter()
+            .find(|transceiver| {
+                transceiver.kind() == ty &&
+                    !transceiver.receiver().is_receiving() &&
+                    match transceiver.direction() {
+                        RTCRtpTransceiverDirection::Sendrecv |
+                        RTCRtpTransceiverDirection::Recvonly => true,
+                        _ => false,
+                    }
+            })
+            .map(|transceiver| DomRoot::from_ref(&**transceiver));
+        let transceiver = match transceiver {
+            Some(transceiver) => transceiver,
+            None => {
+                // The remote description added a media section of its own.
+                let transceiver =
+                    self.add_transceiver(ty, None, RTCRtpTransceiverDirection::Recvonly);
+                transceiver.set_current_direction(RTCRtpTransceiverDirection::Recvonly);
+                transceiver
+            },
+        };
+
+        let receiver = transceiver.receiver();
+        receiver.set_remote_track(&track);
+        let stream = MediaStream::new(&global);
+        stream.add_track(&track);
+        let event = RTCTrackEvent::new(
+            &global,
+            atom!("track"),
+            false,
+            false,
+            receiver,
+            &track,
+            &[stream],
+            &transceiver,
+        );
         event.upcast::<Event>().fire(self.upcast());
     }
 
@@ -325,8 +502,16 @@ impl RTCPeerConnection {
                 };
 
                 match event {
-                    DataChannelEvent::Open => channel.on_open(),
-                    DataChannelEvent::Close => channel.on_close(),
+                    DataChannelEvent::Open => {
+                        self.data_channels_opened
+                            .set(self.data_channels_opened.get() + 1);
+                        channel.on_open()
+                    },
+                    DataChannelEvent::Close => {
+                        self.data_channels_closed
+                            .set(self.data_channels_closed.get() + 1);
+                        channel.on_close()
+                    },
                     DataChannelEvent::Error(error) => channel.on_error(error),
                     DataChannelEvent::OnMessage(message) => channel.on_message(message),
                     DataChannelEvent::StateChange(state) => channel.on_state_change(state),
@@ -439,9 +624,211 @@ impl RTCPeerConnection {
             EventCancelable::NotCancelable,
         );
         event.upcast::<Event>().fire(self.upcast());
+
+        if state == RTCSignalingState::Stable {
+            self.negotiation_completed();
+        }
+    }
+
+    /// Hands the tracks which are to be sent to the backend, before it
+    /// creates an offer or an answer. Tracks are only handed over then, since
+    /// the backend keeps sending them until the connection is closed.
+    fn send_pending_tracks(&self) {
+        for transceiver in self.transceivers.borrow().iter() {
+            let sender = transceiver.sender();
+            let track = match sender.track() {
+                Some(track) if !sender.is_sending() => track,
+                _ => continue,
+            };
+            match transceiver.direction() {
+                RTCRtpTransceiverDirection::Sendrecv | RTCRtpTransceiverDirection::Sendonly => {},
+                _ => continue,
+            }
+            self.controller
+                .borrow()
+                .as_ref()
+                .unwrap()
+                .add_stream(&track.id());
+            sender.set_sending();
+        }
+    }
+
+    /// https://w3c.github.io/webrtc-pc/#dfn-localicecredentialstoreplace
+    fn restart_ice(&self) {
+        self.ice_restart_pending.set(true);
+        let mut credentials = self.ice_restart_credentials.borrow_mut();
+        if credentials.is_none() {
+            // https://tools.ietf.org/html/rfc5245#section-15.4
+            let ufrag = Uuid::new_v4().to_simple().to_string()[..8].to_owned();
+            let pwd = Uuid::new_v4().to_simple().to_string();
+            *credentials = Some((ufrag, pwd));
+        }
+    }
+
+    /// Replaces the ICE credentials of an offer while an ICE restart is
+    /// pending, which makes the backend gather candidates for the new
+    /// credentials once the offer is applied.
+    fn apply_ice_restart(&self, mut desc: SessionDescription) -> SessionDescription {
+        if !self.ice_restart_pending.get() {
+            return desc;
+        }
+        if let Some((ref ufrag, ref pwd)) = *self.ice_restart_credentials.borrow() {
+            desc.sdp = desc
+                .sdp
+                .split("\r\n")
+                .map(|line| {
+                    if line.starts_with("a=ice-ufrag:") {
+                        format!("a=ice-ufrag:{}", ufrag)
+                    } else if line.starts_with("a=ice-pwd:") {
+                        format!("a=ice-pwd:{}", pwd)
+                    } else {
+                        line.to_owned()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\r\n");
+        }
+        desc
+    }
+
+    fn add_transceiver(
+        &self,
+        kind: MediaStreamType,
+        track: Option<&MediaStreamTrack>,
+        direction: RTCRtpTransceiverDirection,
+    ) -> DomRoot<RTCRtpTransceiver> {
+        let transceiver = RTCRtpTransceiver::new(&self.global(), self, kind, track, direction);
+        self.transceivers
+            .borrow_mut()
+            .push(Dom::from_ref(&*transceiver));
+        transceiver
+    }
+
+    /// https://w3c.github.io/webrtc-pc/#dom-rtcpeerconnection-getstats
+    pub(crate) fn get_stats(&self, selector: Option<&MediaStreamTrack>) -> Rc<Promise> {
+        let global = self.global();
+        let p = Promise::new(&global);
+        let transceivers = self.transceivers.borrow();
+
+        // Step 3.
+        if let Some(track) = selector {
+            let known = transceivers.iter().any(|transceiver| {
+                transceiver.sender().has_track(track) || transceiver.receiver().has_track(track)
+            });
+            if !known {
+                p.reject_error(Error::InvalidAccess);
+                return p;
+            }
+        }
+
+        // Step 5.
+        // XXX The backend does not report any statistics of its own, so the
+        // report covers what is known on this side and the ICE candidates it
+        // exchanged, but no RTP counters.
+        let performance = global.performance();
+        let timestamp = *performance.TimeOrigin() + *performance.Now();
+        let entry = |id: String, details| RTCStatsEntry {
+            id: id.into(),
+            timestamp,
+            details,
+        };
+        let mut stats = vec![];
+        if selector.is_none() {
+            stats.push(entry(
+                "RTCPeerConnection".to_owned(),
+                RTCStatsDetails::PeerConnection {
+                    data_channels_opened: self.data_channels_opened.get(),
+                    data_channels_closed: self.data_channels_closed.get(),
+                },
+            ));
+        }
+        for (index, transceiver) in transceivers.iter().enumerate() {
+            if let Some(track) = transceiver.sender().track() {
+                if selector.map_or(true, |selector| transceiver.sender().has_track(selector)) {
+                    let media_source_id = format!("RTCMediaSource_{}", index);
+                    stats.push(entry(
+                        media_source_id.clone(),
+                        RTCStatsDetails::MediaSource {
+                            track_identifier: track.Id(),
+                            kind: track.Kind(),
+                        },
+                    ));
+                    let active = match transceiver.direction() {
+                        RTCRtpTransceiverDirection::Sendrecv |
+                        RTCRtpTransceiverDirection::Sendonly => true,
+                        _ => false,
+                    };
+                    stats.push(entry(
+                        format!("RTCOutboundRTP_{}", index),
+                        RTCStatsDetails::OutboundRtp {
+                            kind: track.Kind(),
+                            media_source_id: Some(media_source_id.into()),
+                            active,
+                        },
+                    ));
+                }
+            }
+            let receiver = transceiver.receiver();
+            if receiver.is_receiving() &&
+                selector.map_or(true, |selector| receiver.has_track(selector))
+            {
+                let track = receiver.Track();
+                stats.push(entry(
+                    format!("RTCInboundRTP_{}", index),
+                    RTCStatsDetails::InboundRtp {
+                        kind: track.Kind(),
+                        track_identifier: track.Id(),
+                    },
+                ));
+            }
+        }
+        if selector.is_none() {
+            let transport_id = "RTCTransport_0";
+            stats.push(entry(
+                transport_id.to_owned(),
+                RTCStatsDetails::Transport {
+                    ice_state: self.ice_connection_state.get(),
+                },
+            ));
+            let candidates = self
+                .local_candidates
+                .borrow()
+                .iter()
+                .map(|candidate| (true, candidate.clone()))
+                .chain(
+                    self.remote_candidates
+                        .borrow()
+                        .iter()
+                        .map(|candidate| (false, candidate.clone())),
+                )
+                .collect::<Vec<_>>();
+            for (index, (local, candidate)) in candidates.into_iter().enumerate() {
+                let details =
+                    RTCStatsDetails::parse_candidate(local, transport_id.into(), &candidate);
+                if let Some(details) = details {
+                    stats.push(entry(format!("RTCIceCandidate_{}", index), details));
+                }
+            }
+            for (index, channel) in self.data_channels.borrow().values().enumerate() {
+                stats.push(entry(
+                    format!("RTCDataChannel_{}", index),
+                    RTCStatsDetails::DataChannel {
+                        label: channel.Label().0.into(),
+                        protocol: channel.Protocol().0.into(),
+                        identifier: channel.GetId(),
+                        state: channel.ReadyState(),
+                    },
+                ));
+            }
+        }
+
+        // Step 6.
+        p.resolve_native(&RTCStatsReport::new(&global, stats));
+        p
     }
 
     fn create_offer(&self) {
+        self.send_pending_tracks();
         let generation = self.offer_answer_generation.get();
         let (task_source, canceller) = self
             .global()
@@ -459,7 +846,8 @@ impl RTCPeerConnection {
                             // create a fresh one
                             this.create_offer();
                         } else {
-                            let init: RTCSessionDescriptionInit = desc.into();
+                            let init: RTCSessionDescriptionInit =
+                                this.apply_ice_restart(desc).into();
                             for promise in this.offer_promises.borrow_mut().drain(..) {
                                 promise.resolve_native(&init);
                             }
@@ -473,6 +861,7 @@ impl RTCPeerConnection {
     }
 
     fn create_answer(&self) {
+        self.send_pending_tracks();
         let generation = self.offer_answer_generation.get();
         let (task_source, canceller) = self
             .global()
@@ -567,6 +956,9 @@ impl RTCPeerConnectionMethods for RTCPeerConnection {
         // XXXManishearth this should be enqueued
         // https://w3c.github.io/webrtc-pc/#enqueue-an-operation
 
+        self.remote_candidates
+            .borrow_mut()
+            .push(candidate.candidate.to_string());
         self.controller
             .borrow_mut()
             .as_ref()
@@ -582,12 +974,15 @@ impl RTCPeerConnectionMethods for RTCPeerConnection {
     }
 
     /// https://w3c.github.io/webrtc-pc/#dom-rtcpeerconnection-createoffer
-    fn CreateOffer(&self, _options: &RTCOfferOptions, comp: InRealm) -> Rc<Promise> {
+    fn CreateOffer(&self, options: &RTCOfferOptions, comp: InRealm) -> Rc<Promise> {
         let p = Promise::new_in_current_realm(&self.global(), comp);
         if self.closed.get() {
             p.reject_error(Error::InvalidState);
             return p;
         }
+        if options.iceRestart {
+            self.restart_ice();
+        }
         self.offer_promises.borrow_mut().push(p.clone());
         self.create_offer();
         p
@@ -619,6 +1014,9 @@ impl RTCPeerConnectionMethods for RTCPeerConnection {
     fn SetLocalDescription(&self, desc: &RTCSessionDescriptionInit, comp: InRealm) -> Rc<Promise> {
         // XXXManishearth validate the current state
         let p = Promise::new_in_current_realm(&self.global(), comp);
+        if desc.type_ == RTCSdpType::Offer {
+            self.offer_in_progress();
+        }
         let this = Trusted::new(self);
         let desc: SessionDescription = desc.into();
         let trusted_promise = TrustedPromise::new(p.clone());
@@ -652,6 +1050,9 @@ impl RTCPeerConnectionMethods for RTCPeerConnection {
     fn SetRemoteDescription(&self, desc: &RTCSessionDescriptionInit, comp: InRealm) -> Rc<Promise> {
         // XXXManishearth validate the current state
         let p = Promise::new_in_current_realm(&self.global(), comp);
+        if desc.type_ == RTCSdpType::Offer {
+            self.offer_in_progress();
+        }
         let this = Trusted::new(self);
         let desc: SessionDescription = desc.into();
         let trusted_promise = TrustedPromise::new(p.clone());
@@ -684,14 +1085,17 @@ impl RTCPeerConnectionMethods for RTCPeerConnection {
     // https://w3c.github.io/webrtc-pc/#legacy-interface-extensions
     fn AddStream(&self, stream: &MediaStream) {
         for track in &*stream.get_tracks() {
-            self.controller
-                .borrow()
-                .as_ref()
-                .unwrap()
-                .add_stream(&track.id());
+            // Tracks that are already being sent are skipped.
+            let _ = self.AddTrack(track, vec![DomRoot::from_ref(stream)]);
         }
     }
 
+    /// https://w3c.github.io/webrtc-pc/#dom-rtcpeerconnection-restartice
+    fn RestartIce(&self) {
+        self.restart_ice();
+        self.update_negotiation_needed_flag();
+    }
+
     /// https://www.w3.org/TR/webrtc/#dom-rtcpeerconnection-icegatheringstate
     fn IceGatheringState(&self) -> RTCIceGatheringState {
         self.gathering_state.get()
@@ -743,16 +1147,199 @@ impl RTCPeerConnectionMethods for RTCPeerConnection {
         label: USVString,
         init: &RTCDataChannelInit,
     ) -> DomRoot<RTCDataChannel> {
-        RTCDataChannel::new(&self.global(), &self, label, init, None)
+        let channel = RTCDataChannel::new(&self.global(), &self, label, init, None);
+        if !self.has_data_channels.get() {
+            self.has_data_channels.set(true);
+            self.update_negotiation_needed_flag();
+        }
+        channel
+    }
+
+    /// https://w3c.github.io/webrtc-pc/#dom-rtcpeerconnection-getsenders
+    fn GetSenders(&self) -> Vec<DomRoot<RTCRtpSender>> {
+        self.transceivers
+            .borrow()
+            .iter()
+            .map(|transceiver| DomRoot::from_ref(transceiver.sender()))
+            .collect()
+    }
+
+    /// https://w3c.github.io/webrtc-pc/#dom-rtcpeerconnection-getreceivers
+    fn GetReceivers(&self) -> Vec<DomRoot<RTCRtpReceiver>> {
+        self.transceivers
+            .borrow()
+            .iter()
+            .map(|transceiver| DomRoot::from_ref(transceiver.receiver()))
+            .collect()
+    }
+
+    /// https://w3c.github.io/webrtc-pc/#dom-rtcpeerconnection-gettransceivers
+    fn GetTransceivers(&self) -> Vec<DomRoot<RTCRtpTransceiver>> {
+        self.transceivers
+            .borrow()
+            .iter()
+            .map(|transceiver| DomRoot::from_ref(&**transceiver))
+            .collect()
+    }
+
+    /// https://w3c.github.io/webrtc-pc/#dom-rtcpeerconnection-addtrack
+    fn AddTrack(
+        &self,
+        track: &MediaStreamTrack,
+        _streams: Vec<DomRoot<MediaStream>>,
+    ) -> Fallible<DomRoot<RTCRtpSender>> {
+        // Step 4.
+        if self.closed.get() {
+            return Err(Error::InvalidState);
+        }
+
+        // Steps 6-7.
+        let reusable = {
+            let transceivers = self.transceivers.borrow();
+            if transceivers
+                .iter()
+                .any(|transceiver| transceiver.sender().has_track(track))
+            {
+                return Err(Error::InvalidAccess);
+            }
+
+            // Step 8.
+            transceivers
+                .iter()
+                .find(|transceiver| {
+                    transceiver.kind() == track.ty() &&
+                        transceiver.sender().track().is_none() &&
+                        match transceiver.current_direction() {
+                            Some(RTCRtpTransceiverDirection::Sendrecv) |
+                            Some(RTCRtpTransceiverDirection::Sendonly) => false,
+                            _ => true,
+                        }
+                })
+                .map(|transceiver| DomRoot::from_ref(&**transceiver))
+        };
+
+        let transceiver = match reusable {
+            // Step 9.
+            Some(transceiver) => {
+                transceiver.sender().set_track(Some(track));
+                match transceiver.direction() {
+                    RTCRtpTransceiverDirection::Recvonly => {
+                        transceiver.set_direction(RTCRtpTransceiverDirection::Sendrecv)
+                    },
+                    RTCRtpTransceiverDirection::Inactive => {
+                        transceiver.set_direction(RTCRtpTransceiverDirection::Sendonly)
+                    },
+                    _ => {},
+                }
+                transceiver
+            },
+            // Step 10.
+            None => self.add_transceiver(
+                track.ty(),
+                Some(track),
+                RTCRtpTransceiverDirection::Sendrecv,
+            ),
+        };
+
+        // Step 11.
+        self.update_negotiation_needed_flag();
+
+        // Step 12.
+        Ok(DomRoot::from_ref(transceiver.sender()))
+    }
+
+    /// https://w3c.github.io/webrtc-pc/#dom-rtcpeerconnection-removetrack
+    fn RemoveTrack(&self, sender: &RTCRtpSender) -> ErrorResult {
+        // Step 3.
+        if self.closed.get() {
+            return Err(Error::InvalidState);
+        }
+
+        // Steps 4-5.
+        let transceiver = self
+            .transceivers
+            .borrow()
+            .iter()
+            .find(|transceiver| transceiver.has_sender(sender))
+            .map(|transceiver| DomRoot::from_ref(&**transceiver))
+            .ok_or(Error::InvalidAccess)?;
+
+        // Step 8.
+        if sender.track().is_none() {
+            return Ok(());
+        }
+
+        // XXX The backend has no way to stop sending a track it was handed, so
+        // rather than letting the media flow to the remote peer after the
+        // track was removed, removing it fails.
+        if sender.is_sending() {
+            return Err(Error::InvalidState);
+        }
+
+        // Step 9.
+        sender.set_track(None);
+
+        // Steps 10-11.
+        match transceiver.direction() {
+            RTCRtpTransceiverDirection::Sendrecv => {
+                transceiver.set_direction(RTCRtpTransceiverDirection::Recvonly)
+            },
+            RTCRtpTransceiverDirection::Sendonly => {
+                transceiver.set_direction(RTCRtpTransceiverDirection::Inactive)
+            },
+            _ => {},
+        }
+
+        // Step 12.
+        self.update_negotiation_needed_flag();
+        Ok(())
     }
 
     /// https://w3c.github.io/webrtc-pc/#dom-rtcpeerconnection-addtransceiver
     fn AddTransceiver(
         &self,
-        _track_or_kind: MediaStreamTrackOrString,
+        track_or_kind: MediaStreamTrackOrString,
         init: &RTCRtpTransceiverInit,
-    ) -> DomRoot<RTCRtpTransceiver> {
-        RTCRtpTransceiver::new(&self.global(), init.direction)
+    ) -> Fallible<DomRoot<RTCRtpTransceiver>> {
+        // Step 3.
+        let (kind, track) = match track_or_kind {
+            MediaStreamTrackOrString::MediaStreamTrack(ref track) => (track.ty(), Some(&**track)),
+            MediaStreamTrackOrString::String(ref kind) => match &**kind {
+                "audio" => (MediaStreamType::Audio, None),
+                "video" => (MediaStreamType::Video, None),
+                _ => {
+                    return Err(Error::Type(format!(
+                        "{} is not a valid transceiver kind",
+                        kind
+                    )))
+                },
+            },
+        };
+
+        if init.direction == RTCRtpTransceiverDirection::Stopped {
+            return Err(Error::Type(
+                "A transceiver cannot be created stopped".to_owned(),
+            ));
+        }
+
+        // Step 5.
+        if self.closed.get() {
+            return Err(Error::InvalidState);
+        }
+
+        // Steps 7-10.
+        let transceiver = self.add_transceiver(kind, track, init.direction);
+
+        // Step 11.
+        self.update_negotiation_needed_flag();
+
+        // Step 12.
+        Ok(transceiver)
+    }
+
+    /// https://w3c.github.io/webrtc-pc/#dom-rtcpeerconnection-getstats
+    fn GetStats(&self, selector: Option<&MediaStreamTrack>) -> Rc<Promise> {
+        self.get_stats(selector)
     }
 }
 
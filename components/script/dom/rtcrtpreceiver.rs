@@ -0,0 +1,103 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::RTCRtpReceiverBinding::RTCRtpReceiveParameters;
+use crate::dom::bindings::codegen::Bindings::RTCRtpReceiverBinding::RTCRtpReceiverMethods;
+use crate::dom::bindings::codegen::Bindings::RTCRtpSenderBinding::{
+    RTCRtcpParameters, RTCRtpParameters,
+};
+use crate::dom::bindings::reflector::{reflect_dom_object, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot, MutDom};
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::mediastreamtrack::MediaStreamTrack;
+use crate::dom::promise::Promise;
+use crate::dom::rtcpeerconnection::RTCPeerConnection;
+use dom_struct::dom_struct;
+use servo_media::streams::MediaStreamType;
+use servo_media::ServoMedia;
+use std::cell::Cell;
+use std::rc::Rc;
+
+#[dom_struct]
+pub struct RTCRtpReceiver {
+    reflector_: Reflector,
+    peer_connection: Dom<RTCPeerConnection>,
+    track: MutDom<MediaStreamTrack>,
+    /// Whether `track` carries media received from the remote peer, as opposed
+    /// to the placeholder track every receiver starts with.
+    receiving: Cell<bool>,
+}
+
+impl RTCRtpReceiver {
+    fn new_inherited(peer_connection: &RTCPeerConnection, track: &MediaStreamTrack) -> Self {
+        Self {
+            reflector_: Reflector::new(),
+            peer_connection: Dom::from_ref(peer_connection),
+            track: MutDom::new(track),
+            receiving: Cell::new(false),
+        }
+    }
+
+    /// https://w3c.github.io/webrtc-pc/#dfn-create-an-rtcrtpreceiver
+    pub(crate) fn new(
+        global: &GlobalScope,
+        peer_connection: &RTCPeerConnection,
+        kind: MediaStreamType,
+    ) -> DomRoot<Self> {
+        // The backend only hands out remote streams once media flows, so until
+        // then the receiver exposes a track backed by a stream of its own that
+        // never gets any data.
+        let (_, id) = ServoMedia::get().unwrap().create_stream_and_socket(kind);
+        let track = MediaStreamTrack::new(global, id, kind);
+        reflect_dom_object(
+            Box::new(Self::new_inherited(peer_connection, &track)),
+            global,
+        )
+    }
+
+    pub(crate) fn kind(&self) -> MediaStreamType {
+        self.track.get().ty()
+    }
+
+    pub(crate) fn has_track(&self, track: &MediaStreamTrack) -> bool {
+        self.track.get() == DomRoot::from_ref(track)
+    }
+
+    pub(crate) fn is_receiving(&self) -> bool {
+        self.receiving.get()
+    }
+
+    /// Switches the receiver over to the track of a stream received from the
+    /// remote peer.
+    pub(crate) fn set_remote_track(&self, track: &MediaStreamTrack) {
+        self.track.set(track);
+        self.receiving.set(true);
+    }
+}
+
+impl RTCRtpReceiverMethods for RTCRtpReceiver {
+    // https://w3c.github.io/webrtc-pc/#dom-rtcrtpreceiver-track
+    fn Track(&self) -> DomRoot<MediaStreamTrack> {
+        self.track.get()
+    }
+
+    // https://w3c.github.io/webrtc-pc/#dom-rtcrtpreceiver-getparameters
+    fn GetParameters(&self) -> RTCRtpReceiveParameters {
+        RTCRtpReceiveParameters {
+            parent: RTCRtpParameters {
+                headerExtensions: vec![],
+                rtcp: RTCRtcpParameters {
+                    cname: None,
+                    reducedSize: None,
+                },
+                codecs: vec![],
+            },
+        }
+    }
+
+    // https://w3c.github.io/webrtc-pc/#dom-rtcrtpreceiver-getstats
+    fn GetStats(&self) -> Rc<Promise> {
+        self.peer_connection.get_stats(Some(&self.track.get()))
+    }
+}
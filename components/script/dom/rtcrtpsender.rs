@@ -7,31 +7,77 @@ use crate::dom::bindings::codegen::Bindings::RTCRtpSenderBinding::{
     RTCRtcpParameters, RTCRtpParameters, RTCRtpSendParameters,
 };
 use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
-use crate::dom::bindings::root::DomRoot;
+use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
 use crate::dom::bindings::str::DOMString;
 use crate::dom::globalscope::GlobalScope;
+use crate::dom::mediastreamtrack::MediaStreamTrack;
 use crate::dom::promise::Promise;
+use crate::dom::rtcpeerconnection::RTCPeerConnection;
 use dom_struct::dom_struct;
+use std::cell::Cell;
 use std::rc::Rc;
 
 #[dom_struct]
 pub struct RTCRtpSender {
     reflector_: Reflector,
+    peer_connection: Dom<RTCPeerConnection>,
+    track: MutNullableDom<MediaStreamTrack>,
+    /// Whether the track was handed to the backend, which sends it to the
+    /// remote peer from then on.
+    sending: Cell<bool>,
 }
 
 impl RTCRtpSender {
-    fn new_inherited() -> Self {
+    fn new_inherited(
+        peer_connection: &RTCPeerConnection,
+        track: Option<&MediaStreamTrack>,
+    ) -> Self {
         Self {
             reflector_: Reflector::new(),
+            peer_connection: Dom::from_ref(peer_connection),
+            track: MutNullableDom::new(track),
+            sending: Cell::new(false),
         }
     }
 
-    pub(crate) fn new(global: &GlobalScope) -> DomRoot<Self> {
-        reflect_dom_object(Box::new(Self::new_inherited()), global)
+    pub(crate) fn new(
+        global: &GlobalScope,
+        peer_connection: &RTCPeerConnection,
+        track: Option<&MediaStreamTrack>,
+    ) -> DomRoot<Self> {
+        reflect_dom_object(
+            Box::new(Self::new_inherited(peer_connection, track)),
+            global,
+        )
+    }
+
+    pub(crate) fn track(&self) -> Option<DomRoot<MediaStreamTrack>> {
+        self.track.get()
+    }
+
+    pub(crate) fn set_track(&self, track: Option<&MediaStreamTrack>) {
+        self.track.set(track);
+    }
+
+    pub(crate) fn has_track(&self, track: &MediaStreamTrack) -> bool {
+        self.track == Some(track)
+    }
+
+    pub(crate) fn is_sending(&self) -> bool {
+        self.sending.get()
+    }
+
+    pub(crate) fn set_sending(&self) {
+        self.sending.set(true);
     }
 }
 
 impl RTCRtpSenderMethods for RTCRtpSender {
+    // https://w3c.github.io/webrtc-pc/#dom-rtcrtpsender-track
+    fn GetTrack(&self) -> Option<DomRoot<MediaStreamTrack>> {
+        self.track.get()
+    }
+
     // https://w3c.github.io/webrtc-pc/#dom-rtcrtpsender-getparameters
     fn GetParameters(&self) -> RTCRtpSendParameters {
         RTCRtpSendParameters {
@@ -54,4 +100,9 @@ impl RTCRtpSenderMethods for RTCRtpSender {
         promise.resolve_native(&());
         promise
     }
+
+    // https://w3c.github.io/webrtc-pc/#dom-rtcrtpsender-getstats
+    fn GetStats(&self) -> Rc<Promise> {
+        self.peer_connection.get_stats(self.track.get().as_deref())
+    }
 }
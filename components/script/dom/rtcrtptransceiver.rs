@@ -8,32 +8,113 @@ use crate::dom::bindings::codegen::Bindings::RTCRtpTransceiverBinding::{
 use crate::dom::bindings::reflector::{reflect_dom_object, Reflector};
 use crate::dom::bindings::root::{Dom, DomRoot};
 use crate::dom::globalscope::GlobalScope;
+use crate::dom::mediastreamtrack::MediaStreamTrack;
+use crate::dom::rtcpeerconnection::RTCPeerConnection;
+use crate::dom::rtcrtpreceiver::RTCRtpReceiver;
 use crate::dom::rtcrtpsender::RTCRtpSender;
 use dom_struct::dom_struct;
+use servo_media::streams::MediaStreamType;
 use std::cell::Cell;
 
 #[dom_struct]
 pub struct RTCRtpTransceiver {
     reflector_: Reflector,
+    peer_connection: Dom<RTCPeerConnection>,
     sender: Dom<RTCRtpSender>,
+    receiver: Dom<RTCRtpReceiver>,
     direction: Cell<RTCRtpTransceiverDirection>,
+    /// https://w3c.github.io/webrtc-pc/#dfn-currentdirection
+    current_direction: Cell<Option<RTCRtpTransceiverDirection>>,
+    /// The direction included in the offer that is currently being negotiated,
+    /// which becomes the current direction once negotiation completes.
+    offered_direction: Cell<Option<RTCRtpTransceiverDirection>>,
 }
 
 impl RTCRtpTransceiver {
-    fn new_inherited(global: &GlobalScope, direction: RTCRtpTransceiverDirection) -> Self {
-        let sender = RTCRtpSender::new(global);
+    fn new_inherited(
+        global: &GlobalScope,
+        peer_connection: &RTCPeerConnection,
+        kind: MediaStreamType,
+        track: Option<&MediaStreamTrack>,
+        direction: RTCRtpTransceiverDirection,
+    ) -> Self {
+        let sender = RTCRtpSender::new(global, peer_connection, track);
+        let receiver = RTCRtpReceiver::new(global, peer_connection, kind);
         Self {
             reflector_: Reflector::new(),
+            peer_connection: Dom::from_ref(peer_connection),
             direction: Cell::new(direction),
+            current_direction: Cell::new(None),
+            offered_direction: Cell::new(None),
             sender: Dom::from_ref(&*sender),
+            receiver: Dom::from_ref(&*receiver),
         }
     }
 
+    /// https://w3c.github.io/webrtc-pc/#dfn-create-an-rtcrtptransceiver
     pub(crate) fn new(
         global: &GlobalScope,
+        peer_connection: &RTCPeerConnection,
+        kind: MediaStreamType,
+        track: Option<&MediaStreamTrack>,
         direction: RTCRtpTransceiverDirection,
     ) -> DomRoot<Self> {
-        reflect_dom_object(Box::new(Self::new_inherited(global, direction)), global)
+        reflect_dom_object(
+            Box::new(Self::new_inherited(
+                global,
+                peer_connection,
+                kind,
+                track,
+                direction,
+            )),
+            global,
+        )
+    }
+
+    pub(crate) fn sender(&self) -> &RTCRtpSender {
+        &self.sender
+    }
+
+    pub(crate) fn has_sender(&self, sender: &RTCRtpSender) -> bool {
+        self.sender == sender
+    }
+
+    pub(crate) fn receiver(&self) -> &RTCRtpReceiver {
+        &self.receiver
+    }
+
+    pub(crate) fn kind(&self) -> MediaStreamType {
+        self.receiver.kind()
+    }
+
+    pub(crate) fn direction(&self) -> RTCRtpTransceiverDirection {
+        self.direction.get()
+    }
+
+    pub(crate) fn set_direction(&self, direction: RTCRtpTransceiverDirection) {
+        self.direction.set(direction);
+    }
+
+    pub(crate) fn current_direction(&self) -> Option<RTCRtpTransceiverDirection> {
+        self.current_direction.get()
+    }
+
+    /// Records the direction of this transceiver as part of an offer.
+    pub(crate) fn offer(&self) {
+        self.offered_direction.set(Some(self.direction.get()));
+    }
+
+    /// Negotiation completed; whatever was offered is now in effect.
+    pub(crate) fn negotiated(&self) {
+        if let Some(direction) = self.offered_direction.take() {
+            self.current_direction.set(Some(direction));
+        }
+    }
+
+    /// Marks the transceiver as negotiated with the given direction, for
+    /// transceivers that are created by the remote description itself.
+    pub(crate) fn set_current_direction(&self, direction: RTCRtpTransceiverDirection) {
+        self.current_direction.set(Some(direction));
     }
 }
 
@@ -45,11 +126,25 @@ impl RTCRtpTransceiverMethods for RTCRtpTransceiver {
 
     /// https://w3c.github.io/webrtc-pc/#dom-rtcrtptransceiver-direction
     fn SetDirection(&self, direction: RTCRtpTransceiverDirection) {
+        if self.direction.get() == direction {
+            return;
+        }
         self.direction.set(direction);
+        self.peer_connection.update_negotiation_needed_flag();
+    }
+
+    /// https://w3c.github.io/webrtc-pc/#dom-rtcrtptransceiver-currentdirection
+    fn GetCurrentDirection(&self) -> Option<RTCRtpTransceiverDirection> {
+        self.current_direction.get()
     }
 
     /// https://w3c.github.io/webrtc-pc/#dom-rtcrtptransceiver-sender
     fn Sender(&self) -> DomRoot<RTCRtpSender> {
         DomRoot::from_ref(&*self.sender)
     }
+
+    /// https://w3c.github.io/webrtc-pc/#dom-rtcrtptransceiver-receiver
+    fn Receiver(&self) -> DomRoot<RTCRtpReceiver> {
+        DomRoot::from_ref(&*self.receiver)
+    }
 }
@@ -0,0 +1,286 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::RTCDataChannelBinding::RTCDataChannelState;
+use crate::dom::bindings::codegen::Bindings::RTCIceCandidateBinding::RTCIceCandidateType;
+use crate::dom::bindings::codegen::Bindings::RTCPeerConnectionBinding::RTCIceConnectionState;
+use crate::dom::bindings::codegen::Bindings::RTCStatsReportBinding::RTCStatsReportMethods;
+use crate::dom::bindings::codegen::Bindings::RTCStatsReportBinding::{
+    RTCDataChannelStats, RTCIceCandidateStats, RTCInboundRtpStreamStats, RTCMediaSourceStats,
+    RTCOutboundRtpStreamStats, RTCPeerConnectionStats, RTCRtpStreamStats, RTCStats, RTCStatsType,
+    RTCTransportStats,
+};
+use crate::dom::bindings::iterable::Iterable;
+use crate::dom::bindings::num::Finite;
+use crate::dom::bindings::reflector::{reflect_dom_object, Reflector};
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::bindings::str::DOMString;
+use crate::dom::globalscope::GlobalScope;
+use crate::script_runtime::JSContext;
+use dom_struct::dom_struct;
+use js::conversions::ToJSValConvertible;
+use js::jsapi::JSContext as RawJSContext;
+use js::jsval::{JSVal, UndefinedValue};
+use js::rust::MutableHandleValue;
+
+/// The type specific members of a stats object.
+#[derive(Clone, JSTraceable, MallocSizeOf)]
+pub enum RTCStatsDetails {
+    /// https://w3c.github.io/webrtc-stats/#pcstats-dict*
+    PeerConnection {
+        data_channels_opened: u32,
+        data_channels_closed: u32,
+    },
+    /// https://w3c.github.io/webrtc-stats/#mediasourcestats-dict*
+    MediaSource {
+        track_identifier: DOMString,
+        kind: DOMString,
+    },
+    /// https://w3c.github.io/webrtc-stats/#outboundrtpstats-dict*
+    OutboundRtp {
+        kind: DOMString,
+        media_source_id: Option<DOMString>,
+        active: bool,
+    },
+    /// https://w3c.github.io/webrtc-stats/#inboundrtpstats-dict*
+    InboundRtp {
+        kind: DOMString,
+        track_identifier: DOMString,
+    },
+    /// https://w3c.github.io/webrtc-stats/#dcstats-dict*
+    DataChannel {
+        label: DOMString,
+        protocol: DOMString,
+        identifier: Option<u16>,
+        state: RTCDataChannelState,
+    },
+    /// https://w3c.github.io/webrtc-stats/#transportstats-dict*
+    Transport { ice_state: RTCIceConnectionState },
+    /// https://w3c.github.io/webrtc-stats/#icecandidate-dict*
+    Candidate {
+        local: bool,
+        transport_id: DOMString,
+        address: DOMString,
+        port: i32,
+        protocol: DOMString,
+        candidate_type: RTCIceCandidateType,
+        priority: i32,
+    },
+}
+
+impl RTCStatsDetails {
+    /// Reads the stats of an ICE candidate from its `candidate-attribute`,
+    /// https://tools.ietf.org/html/rfc5245#section-15.1
+    pub fn parse_candidate(
+        local: bool,
+        transport_id: DOMString,
+        candidate: &str,
+    ) -> Option<RTCStatsDetails> {
+        let candidate = candidate.trim_start_matches("a=");
+        let mut fields = candidate.strip_prefix("candidate:")?.split_whitespace();
+        // The foundation and the component ID.
+        fields.nth(1)?;
+        let protocol = fields.next()?.to_ascii_lowercase().into();
+        let priority = fields.next()?.parse().ok()?;
+        let address = fields.next()?.into();
+        let port = fields.next()?.parse().ok()?;
+        if fields.next()? != "typ" {
+            return None;
+        }
+        let candidate_type = match fields.next()? {
+            "host" => RTCIceCandidateType::Host,
+            "srflx" => RTCIceCandidateType::Srflx,
+            "prflx" => RTCIceCandidateType::Prflx,
+            "relay" => RTCIceCandidateType::Relay,
+            _ => return None,
+        };
+        Some(RTCStatsDetails::Candidate {
+            local,
+            transport_id,
+            address,
+            port,
+            protocol,
+            candidate_type,
+            priority,
+        })
+    }
+}
+
+/// A single stats object of a report. It is converted to the matching
+/// `RTCStats` derived dictionary whenever script reads it.
+#[derive(Clone, JSTraceable, MallocSizeOf)]
+pub struct RTCStatsEntry {
+    pub id: DOMString,
+    pub timestamp: f64,
+    pub details: RTCStatsDetails,
+}
+
+impl RTCStatsEntry {
+    fn base(&self, type_: RTCStatsType) -> RTCStats {
+        RTCStats {
+            id: self.id.clone(),
+            timestamp: Finite::wrap(self.timestamp),
+            type_,
+        }
+    }
+}
+
+#[allow(unsafe_code)]
+impl ToJSValConvertible for RTCStatsEntry {
+    unsafe fn to_jsval(&self, cx: *mut RawJSContext, rval: MutableHandleValue) {
+        match self.details {
+            RTCStatsDetails::PeerConnection {
+                data_channels_opened,
+                data_channels_closed,
+            } => RTCPeerConnectionStats {
+                parent: self.base(RTCStatsType::Peer_connection),
+                dataChannelsOpened: Some(data_channels_opened),
+                dataChannelsClosed: Some(data_channels_closed),
+            }
+            .to_jsval(cx, rval),
+            RTCStatsDetails::MediaSource {
+                ref track_identifier,
+                ref kind,
+            } => RTCMediaSourceStats {
+                parent: self.base(RTCStatsType::Media_source),
+                trackIdentifier: track_identifier.clone(),
+                kind: kind.clone(),
+            }
+            .to_jsval(cx, rval),
+            RTCStatsDetails::OutboundRtp {
+                ref kind,
+                ref media_source_id,
+                active,
+            } => RTCOutboundRtpStreamStats {
+                parent: RTCRtpStreamStats {
+                    parent: self.base(RTCStatsType::Outbound_rtp),
+                    kind: kind.clone(),
+                    transportId: None,
+                    codecId: None,
+                },
+                mid: None,
+                mediaSourceId: media_source_id.clone(),
+                active: Some(active),
+            }
+            .to_jsval(cx, rval),
+            RTCStatsDetails::InboundRtp {
+                ref kind,
+                ref track_identifier,
+            } => RTCInboundRtpStreamStats {
+                parent: RTCRtpStreamStats {
+                    parent: self.base(RTCStatsType::Inbound_rtp),
+                    kind: kind.clone(),
+                    transportId: None,
+                    codecId: None,
+                },
+                trackIdentifier: track_identifier.clone(),
+                mid: None,
+            }
+            .to_jsval(cx, rval),
+            RTCStatsDetails::DataChannel {
+                ref label,
+                ref protocol,
+                identifier,
+                state,
+            } => RTCDataChannelStats {
+                parent: self.base(RTCStatsType::Data_channel),
+                label: Some(label.clone()),
+                protocol: Some(protocol.clone()),
+                dataChannelIdentifier: identifier,
+                state,
+            }
+            .to_jsval(cx, rval),
+            RTCStatsDetails::Transport { ice_state } => RTCTransportStats {
+                parent: self.base(RTCStatsType::Transport),
+                iceState: Some(ice_state),
+            }
+            .to_jsval(cx, rval),
+            RTCStatsDetails::Candidate {
+                local,
+                ref transport_id,
+                ref address,
+                port,
+                ref protocol,
+                candidate_type,
+                priority,
+            } => RTCIceCandidateStats {
+                parent: self.base(if local {
+                    RTCStatsType::Local_candidate
+                } else {
+                    RTCStatsType::Remote_candidate
+                }),
+                transportId: transport_id.clone(),
+                address: Some(Some(address.clone())),
+                port: Some(port),
+                protocol: Some(protocol.clone()),
+                candidateType: candidate_type,
+                priority: Some(priority),
+            }
+            .to_jsval(cx, rval),
+        }
+    }
+}
+
+/// https://w3c.github.io/webrtc-pc/#rtcstatsreport-object
+#[dom_struct]
+pub struct RTCStatsReport {
+    reflector_: Reflector,
+    /// The stats objects, keyed by their `id`.
+    stats: Vec<RTCStatsEntry>,
+}
+
+impl RTCStatsReport {
+    fn new_inherited(stats: Vec<RTCStatsEntry>) -> RTCStatsReport {
+        RTCStatsReport {
+            reflector_: Reflector::new(),
+            stats,
+        }
+    }
+
+    pub fn new(global: &GlobalScope, stats: Vec<RTCStatsEntry>) -> DomRoot<RTCStatsReport> {
+        reflect_dom_object(Box::new(RTCStatsReport::new_inherited(stats)), global)
+    }
+}
+
+impl RTCStatsReportMethods for RTCStatsReport {
+    /// <https://webidl.spec.whatwg.org/#dom-maplike-size>
+    fn Size(&self) -> u32 {
+        self.stats.len() as u32
+    }
+
+    /// <https://webidl.spec.whatwg.org/#dom-maplike-get>
+    #[allow(unsafe_code)]
+    fn Get(&self, cx: JSContext, key: DOMString) -> JSVal {
+        match self.stats.iter().find(|entry| entry.id == key) {
+            Some(entry) => unsafe {
+                rooted!(in(*cx) let mut value = UndefinedValue());
+                entry.to_jsval(*cx, value.handle_mut());
+                value.get()
+            },
+            None => UndefinedValue(),
+        }
+    }
+
+    /// <https://webidl.spec.whatwg.org/#dom-maplike-has>
+    fn Has(&self, key: DOMString) -> bool {
+        self.stats.iter().any(|entry| entry.id == key)
+    }
+}
+
+impl Iterable for RTCStatsReport {
+    type Key = DOMString;
+    type Value = RTCStatsEntry;
+
+    fn get_iterable_length(&self) -> u32 {
+        self.stats.len() as u32
+    }
+
+    fn get_value_at_index(&self, n: u32) -> RTCStatsEntry {
+        self.stats[n as usize].clone()
+    }
+
+    fn get_key_at_index(&self, n: u32) -> DOMString {
+        self.stats[n as usize].id.clone()
+    }
+}
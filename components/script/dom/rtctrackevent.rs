@@ -11,38 +11,71 @@ use crate::dom::bindings::root::{Dom, DomRoot};
 use crate::dom::bindings::str::DOMString;
 use crate::dom::event::Event;
 use crate::dom::globalscope::GlobalScope;
+use crate::dom::mediastream::MediaStream;
 use crate::dom::mediastreamtrack::MediaStreamTrack;
+use crate::dom::rtcrtpreceiver::RTCRtpReceiver;
+use crate::dom::rtcrtptransceiver::RTCRtpTransceiver;
 use crate::dom::window::Window;
+use crate::realms::enter_realm;
+use crate::script_runtime::JSContext;
 use dom_struct::dom_struct;
+use js::conversions::ToJSValConvertible;
+use js::jsapi::Heap;
+use js::jsval::{JSVal, UndefinedValue};
 use servo_atoms::Atom;
 
 #[dom_struct]
 pub struct RTCTrackEvent {
     event: Event,
+    receiver: Dom<RTCRtpReceiver>,
     track: Dom<MediaStreamTrack>,
+    #[ignore_malloc_size_of = "mozjs"]
+    streams: Heap<JSVal>,
+    transceiver: Dom<RTCRtpTransceiver>,
 }
 
 impl RTCTrackEvent {
     #[allow(unrooted_must_root)]
-    fn new_inherited(track: &MediaStreamTrack) -> RTCTrackEvent {
+    fn new_inherited(
+        receiver: &RTCRtpReceiver,
+        track: &MediaStreamTrack,
+        transceiver: &RTCRtpTransceiver,
+    ) -> RTCTrackEvent {
         RTCTrackEvent {
             event: Event::new_inherited(),
+            receiver: Dom::from_ref(receiver),
             track: Dom::from_ref(track),
+            streams: Heap::default(),
+            transceiver: Dom::from_ref(transceiver),
         }
     }
 
+    #[allow(unsafe_code)]
     pub fn new(
         global: &GlobalScope,
         type_: Atom,
         bubbles: bool,
         cancelable: bool,
+        receiver: &RTCRtpReceiver,
         track: &MediaStreamTrack,
+        streams: &[DomRoot<MediaStream>],
+        transceiver: &RTCRtpTransceiver,
     ) -> DomRoot<RTCTrackEvent> {
-        let trackevent = reflect_dom_object(Box::new(RTCTrackEvent::new_inherited(&track)), global);
+        let trackevent = reflect_dom_object(
+            Box::new(RTCTrackEvent::new_inherited(receiver, track, transceiver)),
+            global,
+        );
         {
             let event = trackevent.upcast::<Event>();
             event.init_event(type_, bubbles, cancelable);
         }
+        let _ac = enter_realm(&*global);
+        let cx = global.get_cx();
+        unsafe {
+            rooted!(in(*cx) let mut streams_val = UndefinedValue());
+            streams.to_jsval(*cx, streams_val.handle_mut());
+            trackevent.streams.set(streams_val.get());
+        }
         trackevent
     }
 
@@ -57,17 +90,35 @@ impl RTCTrackEvent {
             Atom::from(type_),
             init.parent.bubbles,
             init.parent.cancelable,
+            &init.receiver,
             &init.track,
+            &*init.streams,
+            &init.transceiver,
         ))
     }
 }
 
 impl RTCTrackEventMethods for RTCTrackEvent {
+    // https://w3c.github.io/webrtc-pc/#dom-rtctrackevent-receiver
+    fn Receiver(&self) -> DomRoot<RTCRtpReceiver> {
+        DomRoot::from_ref(&*self.receiver)
+    }
+
     // https://w3c.github.io/webrtc-pc/#dom-rtctrackevent-track
     fn Track(&self) -> DomRoot<MediaStreamTrack> {
         DomRoot::from_ref(&*self.track)
     }
 
+    // https://w3c.github.io/webrtc-pc/#dom-rtctrackevent-streams
+    fn Streams(&self, _cx: JSContext) -> JSVal {
+        self.streams.get()
+    }
+
+    // https://w3c.github.io/webrtc-pc/#dom-rtctrackevent-transceiver
+    fn Transceiver(&self) -> DomRoot<RTCRtpTransceiver> {
+        DomRoot::from_ref(&*self.transceiver)
+    }
+
     // https://dom.spec.whatwg.org/#dom-event-istrusted
     fn IsTrusted(&self) -> bool {
         self.event.IsTrusted()
@@ -15,6 +15,7 @@ use crate::dom::document::Document;
 use crate::dom::eventtarget::EventTarget;
 use crate::dom::node::{window_from_node, Node};
 use crate::dom::range::Range;
+use crate::editing;
 use crate::task_source::TaskSource;
 use dom_struct::dom_struct;
 use std::cell::Cell;
@@ -78,6 +79,7 @@ impl Selection {
     }
 
     pub fn queue_selectionchange_task(&self) {
+        editing::update_selection_for_layout(&self.document);
         if self.task_queued.get() {
             // Spec doesn't specify not to queue multiple tasks,
             // but it's much easier to code range operations if
@@ -5,6 +5,7 @@
 use crate::devtools;
 use crate::dom::abstractworker::WorkerScriptMsg;
 use crate::dom::abstractworkerglobalscope::{run_worker_event_loop, WorkerEventLoopMethods};
+use crate::dom::bindings::codegen::Bindings::RequestBinding::{RequestInfo, RequestInit};
 use crate::dom::bindings::codegen::Bindings::ServiceWorkerGlobalScopeBinding;
 use crate::dom::bindings::codegen::Bindings::ServiceWorkerGlobalScopeBinding::ServiceWorkerGlobalScopeMethods;
 use crate::dom::bindings::codegen::Bindings::WorkerBinding::WorkerType;
@@ -17,11 +18,14 @@ use crate::dom::event::Event;
 use crate::dom::eventtarget::EventTarget;
 use crate::dom::extendableevent::ExtendableEvent;
 use crate::dom::extendablemessageevent::ExtendableMessageEvent;
+use crate::dom::fetchevent::FetchEvent;
 use crate::dom::globalscope::GlobalScope;
 use crate::dom::identityhub::Identities;
+use crate::dom::promise::Promise;
+use crate::dom::request::Request;
 use crate::dom::worker::TrustedWorkerAddress;
 use crate::dom::workerglobalscope::WorkerGlobalScope;
-use crate::fetch::load_whole_resource;
+use crate::fetch::{self, load_whole_resource};
 use crate::realms::{enter_realm, AlreadyInRealm, InRealm};
 use crate::script_runtime::{
     new_rt_and_cx, CommonScriptMsg, ContextForRequestInterrupt, JSContext as SafeJSContext,
@@ -32,18 +36,22 @@ use crate::task_source::TaskSourceName;
 use crossbeam_channel::{after, unbounded, Receiver, Sender};
 use devtools_traits::DevtoolScriptControlMsg;
 use dom_struct::dom_struct;
+use http::header::{HeaderName, HeaderValue};
 use ipc_channel::ipc::{IpcReceiver, IpcSender};
 use ipc_channel::router::ROUTER;
 use js::jsapi::{JSContext, JS_AddInterruptCallback};
 use js::jsval::UndefinedValue;
 use msg::constellation_msg::PipelineId;
-use net_traits::request::{CredentialsMode, Destination, ParserMetadata, Referrer, RequestBuilder};
+use net_traits::request::{
+    CredentialsMode, Destination, ParserMetadata, Referrer, RequestBuilder, ServiceWorkersMode,
+};
 use net_traits::{CustomResponseMediator, IpcSend};
 use parking_lot::Mutex;
 use script_traits::{ScopeThings, ServiceWorkerMsg, WorkerGlobalScopeInit, WorkerScriptLoadOrigin};
 use servo_config::pref;
 use servo_rand::random;
 use servo_url::ServoUrl;
+use std::rc::Rc;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 use std::thread::{self, JoinHandle};
@@ -54,8 +62,9 @@ use style::thread_state::{self, ThreadState};
 pub enum ServiceWorkerScriptMsg {
     /// Message common to all workers
     CommonWorker(WorkerScriptMsg),
-    /// Message to request a custom response by the service worker
-    Response(CustomResponseMediator),
+    /// Message to request a custom response by the service worker, with the value of the
+    /// navigation preload header if navigation preload applies to the request
+    Response(CustomResponseMediator, Option<Vec<u8>>),
     /// Wake-up call from the task queue.
     WakeUp,
 }
@@ -347,6 +356,7 @@ impl ServiceWorkerGlobalScope {
 
                 let request = RequestBuilder::new(script_url, referrer)
                     .destination(Destination::ServiceWorker)
+                    .service_workers_mode(ServiceWorkersMode::None)
                     .credentials_mode(CredentialsMode::Include)
                     .parser_metadata(ParserMetadata::NotParserInserted)
                     .use_url_credentials(true)
@@ -451,12 +461,8 @@ impl ServiceWorkerGlobalScope {
             CommonWorker(WorkerScriptMsg::Common(msg)) => {
                 self.upcast::<WorkerGlobalScope>().process_event(msg);
             },
-            Response(mediator) => {
-                // TODO XXXcreativcoder This will eventually use a FetchEvent interface to fire event
-                // when we have the Request and Response dom api's implemented
-                // https://w3c.github.io/ServiceWorker/#fetchevent-interface
-                self.upcast::<EventTarget>().fire_event(atom!("fetch"));
-                let _ = mediator.response_chan.send(None);
+            Response(mediator, preload_header_value) => {
+                self.dispatch_fetch(mediator, preload_header_value);
             },
             WakeUp => {},
         }
@@ -472,6 +478,83 @@ impl ServiceWorkerGlobalScope {
         let event = ExtendableEvent::new(self, atom!("activate"), false, false);
         let event = (&*event).upcast::<Event>();
         self.upcast::<EventTarget>().dispatch_event(event);
+        let _ = self
+            .swmanager_sender
+            .send(ServiceWorkerMsg::Activate(self.scope_url.clone()));
+    }
+
+    /// The steps of <https://w3c.github.io/ServiceWorker/#handle-fetch> that fire
+    /// the fetch event; the response is sent through the mediator once known.
+    fn dispatch_fetch(
+        &self,
+        mediator: CustomResponseMediator,
+        preload_header_value: Option<Vec<u8>>,
+    ) {
+        let global = self.upcast::<GlobalScope>();
+        let realm = enter_realm(&*global);
+        let comp = InRealm::Entered(&realm);
+        let CustomResponseMediator {
+            response_chan,
+            request,
+            ..
+        } = mediator;
+
+        // Step 24.3.
+        let preload_response = match preload_header_value {
+            Some(value) => self.navigation_preload(&request, value, comp),
+            None => {
+                let promise = Promise::new_in_current_realm(global, comp);
+                promise.resolve_native(&());
+                promise
+            },
+        };
+
+        // Step 24.3.2.
+        let request = Request::new_with_immutable_headers(global, request.build());
+        let handled = Promise::new_in_current_realm(global, comp);
+        let event = FetchEvent::new(
+            global,
+            atom!("fetch"),
+            false,
+            true,
+            &request,
+            preload_response,
+            DOMString::new(),
+            DOMString::new(),
+            DOMString::new(),
+            handled,
+            Some(response_chan),
+        );
+
+        // Step 24.3.3.
+        let status = event.upcast::<Event>().fire(self.upcast());
+        event.finish_dispatch(status);
+    }
+
+    /// Fetches the navigation preload response of a navigation, with the
+    /// `Service-Worker-Navigation-Preload` header set to the registration's header value.
+    fn navigation_preload(
+        &self,
+        request: &RequestBuilder,
+        header_value: Vec<u8>,
+        comp: InRealm,
+    ) -> Rc<Promise> {
+        let global = self.upcast::<GlobalScope>();
+        let mut headers = request.headers.clone();
+        if let Ok(value) = HeaderValue::from_bytes(&header_value) {
+            headers.insert(
+                HeaderName::from_static("service-worker-navigation-preload"),
+                value,
+            );
+        }
+        let preload_request = request.clone().headers(headers).build();
+        let preload_request = Request::new_with_immutable_headers(global, preload_request);
+        fetch::Fetch(
+            global,
+            RequestInfo::Request(preload_request),
+            RequestInit::empty(),
+            comp,
+        )
     }
 }
 
@@ -488,6 +571,9 @@ unsafe extern "C" fn interrupt_callback(cx: *mut JSContext) -> bool {
 }
 
 impl ServiceWorkerGlobalScopeMethods for ServiceWorkerGlobalScope {
+    // https://w3c.github.io/ServiceWorker/#dom-serviceworkerglobalscope-onfetch
+    event_handler!(fetch, GetOnfetch, SetOnfetch);
+
     // https://w3c.github.io/ServiceWorker/#dom-serviceworkerglobalscope-onmessage
     event_handler!(message, GetOnmessage, SetOnmessage);
 
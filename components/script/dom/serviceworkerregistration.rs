@@ -16,7 +16,7 @@ use crate::dom::workerglobalscope::prepare_workerscope_init;
 use devtools_traits::WorkerId;
 use dom_struct::dom_struct;
 use msg::constellation_msg::ServiceWorkerRegistrationId;
-use script_traits::{ScopeThings, WorkerScriptLoadOrigin};
+use script_traits::{NavigationPreloadUpdate, ScopeThings, ScriptMsg, WorkerScriptLoadOrigin};
 use servo_url::ServoUrl;
 use std::cell::Cell;
 use uuid::Uuid;
@@ -85,6 +85,7 @@ impl ServiceWorkerRegistration {
     }
 
     pub fn set_navigation_preload_header_value(&self, value: ByteString) {
+        self.update_navigation_preload(NavigationPreloadUpdate::HeaderValue(value.to_vec()));
         let mut header_value = self.navigation_preload_header_value.borrow_mut();
         *header_value = Some(value);
     }
@@ -94,7 +95,20 @@ impl ServiceWorkerRegistration {
     }
 
     pub fn set_navigation_preload_enabled(&self, flag: bool) {
-        self.navigation_preload_enabled.set(flag)
+        self.navigation_preload_enabled.set(flag);
+        self.update_navigation_preload(NavigationPreloadUpdate::Enabled(flag));
+    }
+
+    /// Mirrors the navigation preload state in the service worker manager,
+    /// which uses it when handling navigations.
+    fn update_navigation_preload(&self, update: NavigationPreloadUpdate) {
+        let _ =
+            self.global()
+                .script_to_constellation_chan()
+                .send(ScriptMsg::UpdateNavigationPreload(
+                    self.scope.clone(),
+                    update,
+                ));
     }
 
     pub fn get_uninstalling(&self) -> bool {
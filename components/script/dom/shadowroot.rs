@@ -43,6 +43,8 @@ pub struct ShadowRoot {
     document_or_shadow_root: DocumentOrShadowRoot,
     document: Dom<Document>,
     host: MutNullableDom<Element>,
+    /// <https://dom.spec.whatwg.org/#shadowroot-mode>
+    mode: ShadowRootMode,
     /// List of author styles associated with nodes in this shadow tree.
     author_styles: DomRefCell<AuthorStyles<StyleSheetInDocument>>,
     stylesheet_list: MutNullableDom<StyleSheetList>,
@@ -51,7 +53,7 @@ pub struct ShadowRoot {
 
 impl ShadowRoot {
     #[allow(unrooted_must_root)]
-    fn new_inherited(host: &Element, document: &Document) -> ShadowRoot {
+    fn new_inherited(host: &Element, document: &Document, mode: ShadowRootMode) -> ShadowRoot {
         let document_fragment = DocumentFragment::new_inherited(document);
         let node = document_fragment.upcast::<Node>();
         node.set_flag(NodeFlags::IS_IN_SHADOW_TREE, true);
@@ -64,15 +66,16 @@ impl ShadowRoot {
             document_or_shadow_root: DocumentOrShadowRoot::new(document.window()),
             document: Dom::from_ref(document),
             host: MutNullableDom::new(Some(host)),
+            mode,
             author_styles: DomRefCell::new(AuthorStyles::new()),
             stylesheet_list: MutNullableDom::new(None),
             window: Dom::from_ref(document.window()),
         }
     }
 
-    pub fn new(host: &Element, document: &Document) -> DomRoot<ShadowRoot> {
+    pub fn new(host: &Element, document: &Document, mode: ShadowRootMode) -> DomRoot<ShadowRoot> {
         reflect_dom_object(
-            Box::new(ShadowRoot::new_inherited(host, document)),
+            Box::new(ShadowRoot::new_inherited(host, document, mode)),
             document.window(),
         )
     }
@@ -219,7 +222,7 @@ impl ShadowRootMethods for ShadowRoot {
 
     /// https://dom.spec.whatwg.org/#dom-shadowroot-mode
     fn Mode(&self) -> ShadowRootMode {
-        ShadowRootMode::Closed
+        self.mode
     }
 
     /// https://dom.spec.whatwg.org/#dom-shadowroot-host
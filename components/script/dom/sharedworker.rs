@@ -0,0 +1,249 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::abstractworker::SimpleWorkerErrorHandler;
+use crate::dom::bindings::codegen::Bindings::RequestBinding::RequestCredentials;
+use crate::dom::bindings::codegen::Bindings::SharedWorkerBinding::SharedWorkerMethods;
+use crate::dom::bindings::codegen::Bindings::WorkerBinding::WorkerType;
+use crate::dom::bindings::codegen::UnionTypes::StringOrWorkerOptions;
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::refcounted::Trusted;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject};
+use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::bindings::str::{DOMString, USVString};
+use crate::dom::eventtarget::EventTarget;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::messageport::MessagePort;
+use crate::dom::sharedworkerglobalscope::SharedWorkerGlobalScope;
+use crate::dom::window::Window;
+use crate::dom::workerglobalscope::prepare_workerscope_init;
+use crate::task::TaskOnce;
+use crate::task_source::TaskSource;
+use crate::task_source::TaskSourceName;
+use devtools_traits::{DevtoolsPageInfo, ScriptToDevtoolsControlMsg, WorkerId};
+use dom_struct::dom_struct;
+use ipc_channel::ipc::{self, IpcReceiver};
+use ipc_channel::router::ROUTER;
+use msg::constellation_msg::SharedWorkerId;
+use script_traits::WorkerScriptLoadOrigin;
+use script_traits::{
+    ScriptMsg, SharedWorkerConnectInfo, SharedWorkerConnectionMsg, SharedWorkerControlMsg,
+};
+use servo_url::ServoUrl;
+use uuid::Uuid;
+
+pub type TrustedSharedWorkerAddress = Trusted<SharedWorker>;
+
+// https://html.spec.whatwg.org/multipage/#sharedworker
+#[dom_struct]
+pub struct SharedWorker {
+    eventtarget: EventTarget,
+    port: Dom<MessagePort>,
+}
+
+impl SharedWorker {
+    fn new_inherited(port: &MessagePort) -> SharedWorker {
+        SharedWorker {
+            eventtarget: EventTarget::new_inherited(),
+            port: Dom::from_ref(port),
+        }
+    }
+
+    pub fn new(global: &GlobalScope, port: &MessagePort) -> DomRoot<SharedWorker> {
+        reflect_dom_object(Box::new(SharedWorker::new_inherited(port)), global)
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-sharedworker
+    #[allow(non_snake_case)]
+    pub fn Constructor(
+        global: &GlobalScope,
+        script_url: USVString,
+        options: StringOrWorkerOptions,
+    ) -> Fallible<DomRoot<SharedWorker>> {
+        // Step 1.
+        let (name, worker_type, credentials) = match options {
+            StringOrWorkerOptions::String(name) => {
+                (name, WorkerType::Classic, RequestCredentials::Same_origin)
+            },
+            StringOrWorkerOptions::WorkerOptions(options) => {
+                (options.name, options.type_, options.credentials)
+            },
+        };
+
+        // Step 3-4.
+        let worker_url = match global.api_base_url().join(&script_url) {
+            Ok(url) => url,
+            Err(_) => return Err(Error::Syntax),
+        };
+
+        // Step 5-7.
+        let outside_port = MessagePort::new(global);
+        global.track_message_port(&outside_port, None);
+        let worker = SharedWorker::new(global, &outside_port);
+
+        // Step 11.2, the inside port is created here and shipped to the worker,
+        // which receives it with the connect event.
+        let inside_port = MessagePort::new(global);
+        global.track_message_port(&inside_port, None);
+        global.entangle_ports(
+            outside_port.message_port_id().clone(),
+            inside_port.message_port_id().clone(),
+        );
+        let inside_port_impl = global.mark_port_as_transferred(inside_port.message_port_id());
+
+        // Step 11.1, the constellation finds the worker matching the origin, url and name,
+        // and connects the inside port to it.
+        let (control_sender, control_receiver) = ipc::channel().expect("ipc channel failure");
+        let (response_sender, response_receiver) = ipc::channel().expect("ipc channel failure");
+
+        // Step 11.3, run a new worker if there was no matching one,
+        // once the constellation replied.
+        let (task_source, canceller) = (
+            global.dom_manipulation_task_source(),
+            global.task_canceller(TaskSourceName::DOMManipulation),
+        );
+        let trusted_worker = Trusted::new(&*worker);
+        let mut new_worker_info = Some((
+            worker_url.clone(),
+            name.clone(),
+            worker_type,
+            credentials,
+            control_receiver,
+        ));
+        ROUTER.add_route(
+            response_receiver.to_opaque(),
+            Box::new(move |message| {
+                let trusted_worker = trusted_worker.clone();
+                match message.to() {
+                    Ok(SharedWorkerConnectionMsg::Connected) => {},
+                    Ok(SharedWorkerConnectionMsg::Start(worker_id)) => {
+                        let (worker_url, name, worker_type, credentials, control_receiver) =
+                            match new_worker_info.take() {
+                                Some(info) => info,
+                                None => return,
+                            };
+                        let _ = task_source.queue_with_canceller(
+                            task!(run_shared_worker: move || {
+                                let worker = trusted_worker.root();
+                                worker.run_worker(
+                                    worker_id,
+                                    worker_url,
+                                    name,
+                                    worker_type,
+                                    credentials,
+                                    control_receiver,
+                                );
+                            }),
+                            &canceller,
+                        );
+                    },
+                    // Step 11.2, or the worker went away with the event loop
+                    // of another client, which ran it.
+                    Ok(SharedWorkerConnectionMsg::Error) => {
+                        let _ = task_source.queue_with_canceller(
+                            task!(shared_worker_error: move || {
+                                SharedWorker::dispatch_simple_error(trusted_worker);
+                            }),
+                            &canceller,
+                        );
+                    },
+                    Err(e) => warn!("Failed to connect to a shared worker ({:?}).", e),
+                }
+            }),
+        );
+
+        let info = SharedWorkerConnectInfo {
+            origin: global.origin().immutable().clone(),
+            script_url: worker_url,
+            name: String::from(name),
+            module: worker_type == WorkerType::Module,
+            credentials: credentials.into(),
+            port: inside_port_impl,
+            control_sender,
+        };
+        let _ = global
+            .script_to_constellation_chan()
+            .send(ScriptMsg::ConnectToSharedWorker(info, response_sender));
+
+        Ok(worker)
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#run-a-worker>
+    fn run_worker(
+        &self,
+        worker_id: SharedWorkerId,
+        worker_url: ServoUrl,
+        name: DOMString,
+        worker_type: WorkerType,
+        credentials: RequestCredentials,
+        control_receiver: IpcReceiver<SharedWorkerControlMsg>,
+    ) {
+        let global = self.global();
+        let worker_load_origin = WorkerScriptLoadOrigin {
+            referrer_url: None,
+            referrer_policy: None,
+            pipeline_id: global.pipeline_id(),
+        };
+
+        let (devtools_sender, devtools_receiver) = ipc::channel().unwrap();
+        let devtools_worker_id = WorkerId(Uuid::new_v4());
+        if let Some(ref chan) = global.devtools_chan() {
+            if let Some(window) = global.downcast::<Window>() {
+                let page_info = DevtoolsPageInfo {
+                    title: format!("SharedWorker for {}", worker_url),
+                    url: worker_url.clone(),
+                };
+                let _ = chan.send(ScriptToDevtoolsControlMsg::NewGlobal(
+                    (
+                        window.window_proxy().browsing_context_id(),
+                        global.pipeline_id(),
+                        Some(devtools_worker_id),
+                    ),
+                    devtools_sender.clone(),
+                    page_info,
+                ));
+            }
+        }
+
+        let init =
+            prepare_workerscope_init(&global, Some(devtools_sender), Some(devtools_worker_id));
+
+        SharedWorkerGlobalScope::run_sharedworker_scope(
+            init,
+            worker_url,
+            devtools_receiver,
+            Trusted::new(self),
+            global.script_chan(),
+            worker_load_origin,
+            String::from(name),
+            worker_type,
+            credentials.into(),
+            worker_id,
+            control_receiver,
+        );
+    }
+
+    pub fn dispatch_simple_error(address: TrustedSharedWorkerAddress) {
+        let worker = address.root();
+        worker.upcast().fire_event(atom!("error"));
+    }
+}
+
+impl SharedWorkerMethods for SharedWorker {
+    // https://html.spec.whatwg.org/multipage/#dom-sharedworker-port
+    fn Port(&self) -> DomRoot<MessagePort> {
+        DomRoot::from_ref(&*self.port)
+    }
+
+    // https://html.spec.whatwg.org/multipage/#handler-abstractworker-onerror
+    event_handler!(error, GetOnerror, SetOnerror);
+}
+
+impl TaskOnce for SimpleWorkerErrorHandler<SharedWorker> {
+    #[allow(unrooted_must_root)]
+    fn run_once(self) {
+        SharedWorker::dispatch_simple_error(self.addr);
+    }
+}
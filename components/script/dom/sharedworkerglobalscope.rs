@@ -0,0 +1,518 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::devtools;
+use crate::dom::abstractworker::SimpleWorkerErrorHandler;
+use crate::dom::abstractworkerglobalscope::{run_worker_event_loop, WorkerEventLoopMethods};
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::SharedWorkerGlobalScopeBinding;
+use crate::dom::bindings::codegen::Bindings::SharedWorkerGlobalScopeBinding::SharedWorkerGlobalScopeMethods;
+use crate::dom::bindings::codegen::Bindings::WorkerBinding::WorkerType;
+use crate::dom::bindings::codegen::UnionTypes::WindowProxyOrMessagePortOrServiceWorker;
+use crate::dom::bindings::conversions::ToJSValConvertible;
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::refcounted::Trusted;
+use crate::dom::bindings::root::{DomRoot, RootCollection, ThreadLocalStackRoots};
+use crate::dom::bindings::str::DOMString;
+use crate::dom::dedicatedworkerglobalscope::AutoWorkerReset;
+use crate::dom::event::Event;
+use crate::dom::eventtarget::EventTarget;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::identityhub::Identities;
+use crate::dom::messageevent::MessageEvent;
+use crate::dom::messageport::MessagePort;
+use crate::dom::sharedworker::TrustedSharedWorkerAddress;
+use crate::dom::worker::TrustedWorkerAddress;
+use crate::dom::workerglobalscope::WorkerGlobalScope;
+use crate::fetch::load_whole_resource;
+use crate::realms::enter_realm;
+use crate::script_module::{fetch_external_module_script, ModuleOwner, ScriptFetchOptions};
+use crate::script_runtime::ScriptThreadEventCategory::WorkerEvent;
+use crate::script_runtime::{
+    new_rt_and_cx, CommonScriptMsg, JSContext as SafeJSContext, Runtime, ScriptChan,
+};
+use crate::task_queue::{QueuedTask, QueuedTaskConversion, TaskQueue};
+use crate::task_source::networking::NetworkingTaskSource;
+use crate::task_source::TaskSourceName;
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use devtools_traits::DevtoolScriptControlMsg;
+use dom_struct::dom_struct;
+use ipc_channel::ipc::IpcReceiver;
+use ipc_channel::router::ROUTER;
+use js::jsval::UndefinedValue;
+use msg::constellation_msg::{PipelineId, SharedWorkerId};
+use net_traits::request::{CredentialsMode, Destination, ParserMetadata};
+use net_traits::request::{Referrer, RequestBuilder, RequestMode};
+use net_traits::IpcSend;
+use parking_lot::Mutex;
+use script_traits::transferable::MessagePortImpl;
+use script_traits::{ScriptMsg, SharedWorkerControlMsg};
+use script_traits::{WorkerGlobalScopeInit, WorkerScriptLoadOrigin};
+use servo_rand::random;
+use servo_url::ServoUrl;
+use std::cell::Cell;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use style::thread_state::{self, ThreadState};
+
+/// Messages used to control the shared worker event loop
+pub enum SharedWorkerScriptMsg {
+    /// Message common to all workers
+    Common(CommonScriptMsg),
+    /// Wake-up call from the task queue.
+    WakeUp,
+}
+
+impl QueuedTaskConversion for SharedWorkerScriptMsg {
+    fn task_source_name(&self) -> Option<&TaskSourceName> {
+        match self {
+            SharedWorkerScriptMsg::Common(CommonScriptMsg::Task(
+                _category,
+                _boxed,
+                _pipeline_id,
+                task_source,
+            )) => Some(&task_source),
+            _ => None,
+        }
+    }
+
+    fn pipeline_id(&self) -> Option<PipelineId> {
+        // Workers always return None, since the pipeline_id is only used to check for document activity,
+        // and this check does not apply to worker event-loops.
+        None
+    }
+
+    fn into_queued_task(self) -> Option<QueuedTask> {
+        match self {
+            SharedWorkerScriptMsg::Common(CommonScriptMsg::Task(
+                category,
+                boxed,
+                pipeline_id,
+                task_source,
+            )) => Some((None, category, boxed, pipeline_id, task_source)),
+            _ => None,
+        }
+    }
+
+    fn from_queued_task(queued_task: QueuedTask) -> Self {
+        let (_worker, category, boxed, pipeline_id, task_source) = queued_task;
+        let script_msg = CommonScriptMsg::Task(category, boxed, pipeline_id, task_source);
+        SharedWorkerScriptMsg::Common(script_msg)
+    }
+
+    fn inactive_msg() -> Self {
+        // Inactive is only relevant in the context of a browsing-context event-loop.
+        panic!("Workers should never receive messages marked as inactive");
+    }
+
+    fn wake_up_msg() -> Self {
+        SharedWorkerScriptMsg::WakeUp
+    }
+
+    fn is_wake_up(&self) -> bool {
+        match self {
+            SharedWorkerScriptMsg::WakeUp => true,
+            _ => false,
+        }
+    }
+}
+
+pub enum MixedMessage {
+    FromSharedWorker(SharedWorkerScriptMsg),
+    FromDevtools(DevtoolScriptControlMsg),
+    FromControl(SharedWorkerControlMsg),
+}
+
+#[derive(Clone, JSTraceable)]
+pub struct SharedWorkerChan {
+    pub sender: Sender<SharedWorkerScriptMsg>,
+}
+
+impl ScriptChan for SharedWorkerChan {
+    fn send(&self, msg: CommonScriptMsg) -> Result<(), ()> {
+        self.sender
+            .send(SharedWorkerScriptMsg::Common(msg))
+            .map_err(|_| ())
+    }
+
+    fn clone(&self) -> Box<dyn ScriptChan + Send> {
+        Box::new(SharedWorkerChan {
+            sender: self.sender.clone(),
+        })
+    }
+}
+
+unsafe_no_jsmanaged_fields!(TaskQueue<SharedWorkerScriptMsg>);
+
+// https://html.spec.whatwg.org/multipage/#sharedworkerglobalscope
+#[dom_struct]
+pub struct SharedWorkerGlobalScope {
+    workerglobalscope: WorkerGlobalScope,
+    #[ignore_malloc_size_of = "Defined in std"]
+    task_queue: TaskQueue<SharedWorkerScriptMsg>,
+    #[ignore_malloc_size_of = "Defined in std"]
+    own_sender: Sender<SharedWorkerScriptMsg>,
+    /// The id of this worker in the constellation.
+    worker_id: SharedWorkerId,
+    /// Whether the worker script has run, before which new connections are kept pending.
+    script_ran: Cell<bool>,
+    /// The ports of the clients that connected before the worker script ran.
+    pending_connections: DomRefCell<Vec<MessagePortImpl>>,
+    /// A receiver of control messages from the constellation.
+    #[ignore_malloc_size_of = "Channels are hard"]
+    control_receiver: Receiver<SharedWorkerControlMsg>,
+}
+
+impl WorkerEventLoopMethods for SharedWorkerGlobalScope {
+    type WorkerMsg = SharedWorkerScriptMsg;
+    type ControlMsg = SharedWorkerControlMsg;
+    type Event = MixedMessage;
+
+    fn task_queue(&self) -> &TaskQueue<SharedWorkerScriptMsg> {
+        &self.task_queue
+    }
+
+    fn handle_event(&self, event: MixedMessage) -> bool {
+        self.handle_mixed_message(event)
+    }
+
+    fn handle_worker_post_event(&self, _worker: &TrustedWorkerAddress) -> Option<AutoWorkerReset> {
+        None
+    }
+
+    fn from_control_msg(&self, msg: SharedWorkerControlMsg) -> MixedMessage {
+        MixedMessage::FromControl(msg)
+    }
+
+    fn from_worker_msg(&self, msg: SharedWorkerScriptMsg) -> MixedMessage {
+        MixedMessage::FromSharedWorker(msg)
+    }
+
+    fn from_devtools_msg(&self, msg: DevtoolScriptControlMsg) -> MixedMessage {
+        MixedMessage::FromDevtools(msg)
+    }
+
+    fn control_receiver(&self) -> &Receiver<SharedWorkerControlMsg> {
+        &self.control_receiver
+    }
+}
+
+impl SharedWorkerGlobalScope {
+    fn new_inherited(
+        init: WorkerGlobalScopeInit,
+        worker_name: DOMString,
+        worker_type: WorkerType,
+        worker_url: ServoUrl,
+        from_devtools_receiver: Receiver<DevtoolScriptControlMsg>,
+        runtime: Runtime,
+        own_sender: Sender<SharedWorkerScriptMsg>,
+        receiver: Receiver<SharedWorkerScriptMsg>,
+        worker_id: SharedWorkerId,
+        control_receiver: Receiver<SharedWorkerControlMsg>,
+        closing: Arc<AtomicBool>,
+    ) -> SharedWorkerGlobalScope {
+        SharedWorkerGlobalScope {
+            workerglobalscope: WorkerGlobalScope::new_inherited(
+                init,
+                worker_name,
+                worker_type,
+                worker_url,
+                runtime,
+                from_devtools_receiver,
+                closing,
+                Arc::new(Mutex::new(Identities::new())),
+            ),
+            task_queue: TaskQueue::new(receiver, own_sender.clone()),
+            own_sender,
+            worker_id,
+            script_ran: Cell::new(false),
+            pending_connections: DomRefCell::new(vec![]),
+            control_receiver,
+        }
+    }
+
+    #[allow(unsafe_code)]
+    pub fn new(
+        init: WorkerGlobalScopeInit,
+        worker_name: DOMString,
+        worker_type: WorkerType,
+        worker_url: ServoUrl,
+        from_devtools_receiver: Receiver<DevtoolScriptControlMsg>,
+        runtime: Runtime,
+        own_sender: Sender<SharedWorkerScriptMsg>,
+        receiver: Receiver<SharedWorkerScriptMsg>,
+        worker_id: SharedWorkerId,
+        control_receiver: Receiver<SharedWorkerControlMsg>,
+        closing: Arc<AtomicBool>,
+    ) -> DomRoot<SharedWorkerGlobalScope> {
+        let cx = runtime.cx();
+        let scope = Box::new(SharedWorkerGlobalScope::new_inherited(
+            init,
+            worker_name,
+            worker_type,
+            worker_url,
+            from_devtools_receiver,
+            runtime,
+            own_sender,
+            receiver,
+            worker_id,
+            control_receiver,
+            closing,
+        ));
+        unsafe { SharedWorkerGlobalScopeBinding::Wrap(SafeJSContext::from_ptr(cx), scope) }
+    }
+
+    // https://html.spec.whatwg.org/multipage/#run-a-worker
+    //
+    // The worker runs on its own runtime rather than a child of the client's,
+    // since it outlives the client that started it for as long as other clients
+    // are connected. It is however started in the event-loop of that first client,
+    // so in multiprocess mode it lives in that client's content process.
+    pub fn run_sharedworker_scope(
+        init: WorkerGlobalScopeInit,
+        worker_url: ServoUrl,
+        from_devtools_receiver: IpcReceiver<DevtoolScriptControlMsg>,
+        worker: TrustedSharedWorkerAddress,
+        parent_sender: Box<dyn ScriptChan + Send>,
+        worker_load_origin: WorkerScriptLoadOrigin,
+        worker_name: String,
+        worker_type: WorkerType,
+        credentials_mode: CredentialsMode,
+        worker_id: SharedWorkerId,
+        control_receiver: IpcReceiver<SharedWorkerControlMsg>,
+    ) -> JoinHandle<()> {
+        let serialized_worker_url = worker_url.to_string();
+        let origin = init.origin.clone();
+
+        thread::Builder::new()
+            .name(format!("SharedWorker for {}", serialized_worker_url))
+            .spawn(move || {
+                thread_state::initialize(ThreadState::SCRIPT | ThreadState::IN_WORKER);
+
+                let roots = RootCollection::new();
+                let _stack_roots = ThreadLocalStackRoots::new(&roots);
+
+                let WorkerScriptLoadOrigin {
+                    referrer_url,
+                    referrer_policy,
+                    pipeline_id,
+                } = worker_load_origin;
+
+                let (own_sender, receiver) = unbounded();
+                let runtime = new_rt_and_cx(Some(NetworkingTaskSource(
+                    Box::new(SharedWorkerChan {
+                        sender: own_sender.clone(),
+                    }),
+                    pipeline_id,
+                )));
+
+                let (devtools_mpsc_chan, devtools_mpsc_port) = unbounded();
+                ROUTER.route_ipc_receiver_to_crossbeam_sender(
+                    from_devtools_receiver,
+                    devtools_mpsc_chan,
+                );
+
+                let (control_mpsc_chan, control_mpsc_port) = unbounded();
+                ROUTER.route_ipc_receiver_to_crossbeam_sender(control_receiver, control_mpsc_chan);
+
+                let global = SharedWorkerGlobalScope::new(
+                    init,
+                    DOMString::from_string(worker_name),
+                    worker_type,
+                    worker_url.clone(),
+                    devtools_mpsc_port,
+                    runtime,
+                    own_sender,
+                    receiver,
+                    worker_id,
+                    control_mpsc_port,
+                    Arc::new(AtomicBool::new(false)),
+                );
+                let scope = global.upcast::<WorkerGlobalScope>();
+                let global_scope = global.upcast::<GlobalScope>();
+
+                match worker_type {
+                    WorkerType::Classic => {
+                        let referrer = referrer_url
+                            .map(|url| Referrer::ReferrerUrl(url))
+                            .unwrap_or_else(|| global_scope.get_referrer());
+
+                        let request = RequestBuilder::new(worker_url, referrer)
+                            .destination(Destination::SharedWorker)
+                            .mode(RequestMode::SameOrigin)
+                            .credentials_mode(CredentialsMode::CredentialsSameOrigin)
+                            .parser_metadata(ParserMetadata::NotParserInserted)
+                            .use_url_credentials(true)
+                            .pipeline_id(Some(pipeline_id))
+                            .referrer_policy(referrer_policy)
+                            .origin(origin);
+
+                        let (metadata, bytes) = match load_whole_resource(
+                            request,
+                            &global_scope.resource_threads().sender(),
+                            &global_scope,
+                        ) {
+                            Err(_) => {
+                                warn!("error loading script {}", serialized_worker_url);
+                                let _ = parent_sender.send(CommonScriptMsg::Task(
+                                    WorkerEvent,
+                                    Box::new(SimpleWorkerErrorHandler::new(worker)),
+                                    Some(pipeline_id),
+                                    TaskSourceName::DOMManipulation,
+                                ));
+                                global.notify_closed();
+                                return;
+                            },
+                            Ok((metadata, bytes)) => (metadata, bytes),
+                        };
+                        scope.set_url(metadata.final_url);
+                        global_scope.set_https_state(metadata.https_state);
+                        let source = String::from_utf8_lossy(&bytes);
+
+                        scope.execute_script(DOMString::from(source));
+                        global.start_pending_connections();
+                    },
+                    WorkerType::Module => {
+                        // https://html.spec.whatwg.org/multipage/#fetch-a-module-worker-script-tree
+                        // The module script runs once the module graph has been fetched,
+                        // from within the worker event loop.
+                        let options = ScriptFetchOptions {
+                            credentials_mode,
+                            referrer_policy,
+                            ..ScriptFetchOptions::default_classic_script(&global_scope)
+                        };
+
+                        fetch_external_module_script(
+                            ModuleOwner::Worker(Trusted::new(scope)),
+                            worker_url,
+                            Destination::SharedWorker,
+                            options,
+                        );
+                    },
+                }
+
+                let reporter_name = format!("shared-worker-reporter-{}", random::<u64>());
+                global_scope.mem_profiler_chan().run_with_memory_reporting(
+                    || {
+                        // Step 29, Run the responsible event loop specified
+                        // by inside settings until it is destroyed.
+                        // The worker processing model remains on this step
+                        // until the event loop is destroyed,
+                        // which happens after the closing flag is set to true.
+                        while !scope.is_closing() {
+                            run_worker_event_loop(&*global, None);
+                        }
+                    },
+                    reporter_name,
+                    scope.script_chan(),
+                    CommonScriptMsg::CollectReports,
+                );
+                global.notify_closed();
+                scope.clear_js_runtime();
+            })
+            .expect("Thread spawning failed")
+    }
+
+    pub fn script_chan(&self) -> Box<dyn ScriptChan + Send> {
+        Box::new(SharedWorkerChan {
+            sender: self.own_sender.clone(),
+        })
+    }
+
+    /// Start the connections kept pending while the worker script had not run yet,
+    /// and those made from now on.
+    pub fn start_pending_connections(&self) {
+        self.script_ran.set(true);
+        let pending_connections: Vec<_> = self.pending_connections.borrow_mut().drain(..).collect();
+        for port_impl in pending_connections {
+            self.connect(port_impl);
+        }
+    }
+
+    /// Let the constellation know the worker is gone,
+    /// so that new clients start a new one.
+    fn notify_closed(&self) {
+        let _ = self
+            .upcast::<GlobalScope>()
+            .script_to_constellation_chan()
+            .send(ScriptMsg::SharedWorkerClosed(self.worker_id));
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#dom-sharedworker>
+    /// The steps firing a `connect` event with the worker side of a client's port.
+    #[allow(unsafe_code)]
+    fn connect(&self, port_impl: MessagePortImpl) {
+        let global = self.upcast::<GlobalScope>();
+        let port = MessagePort::new_transferred(
+            global,
+            port_impl.message_port_id().clone(),
+            port_impl.entangled_port_id(),
+        );
+        global.track_message_port(&port, Some(port_impl));
+
+        let _ac = enter_realm(self);
+        let cx = global.get_cx();
+        rooted!(in(*cx) let mut data = UndefinedValue());
+        unsafe { DOMString::new().to_jsval(*cx, data.handle_mut()) };
+        let source = WindowProxyOrMessagePortOrServiceWorker::MessagePort(port.clone());
+        let event = MessageEvent::new(
+            global,
+            atom!("connect"),
+            false,
+            false,
+            data.handle(),
+            DOMString::new(),
+            Some(&source),
+            DOMString::new(),
+            vec![port],
+        );
+        event.upcast::<Event>().fire(self.upcast::<EventTarget>());
+    }
+
+    fn handle_mixed_message(&self, msg: MixedMessage) -> bool {
+        match msg {
+            MixedMessage::FromDevtools(msg) => match msg {
+                DevtoolScriptControlMsg::EvaluateJS(_pipe_id, string, sender) => {
+                    devtools::handle_evaluate_js(self.upcast(), string, sender)
+                },
+                DevtoolScriptControlMsg::WantsLiveNotifications(_pipe_id, bool_val) => {
+                    devtools::handle_wants_live_notifications(self.upcast(), bool_val)
+                },
+                _ => debug!("got an unusable devtools control message inside the worker!"),
+            },
+            MixedMessage::FromSharedWorker(SharedWorkerScriptMsg::Common(msg)) => {
+                self.upcast::<WorkerGlobalScope>().process_event(msg);
+            },
+            MixedMessage::FromSharedWorker(SharedWorkerScriptMsg::WakeUp) => {},
+            MixedMessage::FromControl(SharedWorkerControlMsg::Connect(port_impl)) => {
+                if self.script_ran.get() {
+                    self.connect(port_impl);
+                } else {
+                    self.pending_connections.borrow_mut().push(port_impl);
+                }
+            },
+            MixedMessage::FromControl(SharedWorkerControlMsg::Exit) => {
+                self.upcast::<WorkerGlobalScope>().close();
+                return false;
+            },
+        }
+        true
+    }
+}
+
+impl SharedWorkerGlobalScopeMethods for SharedWorkerGlobalScope {
+    // https://html.spec.whatwg.org/multipage/#dom-sharedworkerglobalscope-name
+    fn Name(&self) -> DOMString {
+        self.upcast::<WorkerGlobalScope>().worker_name()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-sharedworkerglobalscope-close
+    fn Close(&self) {
+        self.upcast::<WorkerGlobalScope>().close();
+    }
+
+    // https://html.spec.whatwg.org/multipage/#handler-sharedworkerglobalscope-onconnect
+    event_handler!(connect, GetOnconnect, SetOnconnect);
+}
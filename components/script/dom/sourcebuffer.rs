@@ -0,0 +1,478 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::HTMLMediaElementBinding::HTMLMediaElementMethods;
+use crate::dom::bindings::codegen::Bindings::MediaSourceBinding::MediaSourceMethods;
+use crate::dom::bindings::codegen::Bindings::MediaSourceBinding::{EndOfStreamError, ReadyState};
+use crate::dom::bindings::codegen::Bindings::SourceBufferBinding::SourceBufferMethods;
+use crate::dom::bindings::codegen::UnionTypes::ArrayBufferViewOrArrayBuffer;
+use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::refcounted::Trusted;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject};
+use crate::dom::bindings::root::{DomRoot, MutNullableDom};
+use crate::dom::eventtarget::EventTarget;
+use crate::dom::mediasegmentparser::{
+    ByteStreamFormat, InitializationSegment, MediaSegment, Segment, SegmentParser,
+};
+use crate::dom::mediasource::MediaSource;
+use crate::dom::timeranges::{TimeRanges, TimeRangesContainer};
+use crate::dom::window::Window;
+use crate::task_source::TaskSource;
+use dom_struct::dom_struct;
+use servo_atoms::Atom;
+use std::cell::Cell;
+
+/// The number of bytes a source buffer can hold before appending fails with
+/// a `QuotaExceededError`.
+const QUOTA: usize = 150 * 1024 * 1024;
+
+/// Gaps between media segments shorter than this, such as the ones left by
+/// the rounding of timestamps, don't split buffered ranges.
+const GAP_TOLERANCE: f64 = 0.01;
+
+#[dom_struct]
+pub struct SourceBuffer {
+    eventtarget: EventTarget,
+    /// The media source this buffer belongs to, until it is removed from it.
+    media_source: MutNullableDom<MediaSource>,
+    /// <https://w3c.github.io/media-source/#dom-sourcebuffer-updating>
+    updating: Cell<bool>,
+    parser: DomRefCell<SegmentParser>,
+    /// The last initialization segment appended.
+    initialization_segment: DomRefCell<Option<Vec<u8>>>,
+    /// The media segments appended, sorted by start time.
+    media_segments: DomRefCell<Vec<MediaSegment>>,
+    /// Incremented when an append or a removal is aborted, so that its
+    /// queued task does nothing.
+    generation: Cell<u32>,
+    /// Whether the player is seeking and flushed the data pushed to it, in
+    /// which case nothing is pushed until it asks for the data at the seek
+    /// position.
+    flushing: Cell<bool>,
+}
+
+impl SourceBuffer {
+    fn new_inherited(media_source: &MediaSource, format: ByteStreamFormat) -> SourceBuffer {
+        SourceBuffer {
+            eventtarget: EventTarget::new_inherited(),
+            media_source: MutNullableDom::new(Some(media_source)),
+            updating: Cell::new(false),
+            parser: DomRefCell::new(SegmentParser::new(format)),
+            initialization_segment: DomRefCell::new(None),
+            media_segments: DomRefCell::new(vec![]),
+            generation: Cell::new(0),
+            flushing: Cell::new(false),
+        }
+    }
+
+    pub fn new(
+        window: &Window,
+        media_source: &MediaSource,
+        format: ByteStreamFormat,
+    ) -> DomRoot<SourceBuffer> {
+        reflect_dom_object(
+            Box::new(SourceBuffer::new_inherited(media_source, format)),
+            window,
+        )
+    }
+
+    pub fn is_updating(&self) -> bool {
+        self.updating.get()
+    }
+
+    /// Drop this buffer's data and its link to its media source, once it
+    /// has been removed from it.
+    pub fn detach(&self) {
+        self.abort_buffer_append();
+        self.media_source.set(None);
+        self.parser.borrow_mut().reset();
+        *self.initialization_segment.borrow_mut() = None;
+        self.media_segments.borrow_mut().clear();
+        self.flushing.set(false);
+    }
+
+    /// Abort an ongoing buffer append or range removal.
+    /// <https://w3c.github.io/media-source/#dom-sourcebuffer-abort>, step 4.
+    pub fn abort_buffer_append(&self) {
+        if !self.updating.get() {
+            return;
+        }
+        self.generation.set(self.generation.get().wrapping_add(1));
+        self.updating.set(false);
+        self.queue_event(atom!("abort"));
+        self.queue_event(atom!("updateend"));
+    }
+
+    /// The presentation time ranges of the media segments, in seconds.
+    pub fn buffered_ranges(&self) -> Vec<(f64, f64)> {
+        let mut ranges: Vec<(f64, f64)> = vec![];
+        for segment in self.media_segments.borrow().iter() {
+            if segment.end <= segment.start {
+                continue;
+            }
+            match ranges.last_mut() {
+                Some(last) if segment.start <= last.1 + GAP_TOLERANCE => {
+                    last.1 = last.1.max(segment.end);
+                },
+                _ => ranges.push((segment.start, segment.end)),
+            }
+        }
+        ranges
+    }
+
+    /// Stop pushing data to the media element's player, which drops what it
+    /// was pushed when seeking, until `push_from` is called.
+    pub fn flush(&self) {
+        self.flushing.set(true);
+    }
+
+    /// Push the initialization segment and the media segments that end after
+    /// `time` to the media element's player.
+    pub fn push_from(&self, time: f64) {
+        self.flushing.set(false);
+        if let Some(ref segment) = *self.initialization_segment.borrow() {
+            self.push_data(segment.clone());
+        }
+        for segment in self.media_segments.borrow().iter() {
+            if segment.end > time {
+                self.push_data(segment.data.clone());
+            }
+        }
+    }
+
+    fn push_data(&self, data: Vec<u8>) {
+        if self.flushing.get() {
+            return;
+        }
+        if let Some(media_element) = self
+            .media_source
+            .get()
+            .and_then(|media_source| media_source.media_element())
+        {
+            media_element.push_media_source_data(data);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.initialization_segment
+            .borrow()
+            .as_ref()
+            .map_or(0, |segment| segment.len()) +
+            self.media_segments
+                .borrow()
+                .iter()
+                .map(|segment| segment.data.len())
+                .sum::<usize>() +
+            self.parser.borrow().pending_len()
+    }
+
+    /// <https://w3c.github.io/media-source/#sourcebuffer-prepare-append>
+    fn prepare_append(&self, new_data_len: usize) -> ErrorResult {
+        // Step 1.
+        let media_source = self.media_source.get().ok_or(Error::InvalidState)?;
+
+        // Step 2.
+        if self.updating.get() {
+            return Err(Error::InvalidState);
+        }
+
+        // Step 3.
+        if let Some(media_element) = media_source.media_element() {
+            if media_element.GetError().is_some() {
+                return Err(Error::InvalidState);
+            }
+        }
+
+        // Step 4.
+        media_source.open_if_ended();
+
+        // Step 5.
+        self.evict_coded_frames(new_data_len);
+
+        // Step 6.
+        if self.len() + new_data_len > QUOTA {
+            return Err(Error::QuotaExceeded);
+        }
+
+        Ok(())
+    }
+
+    /// Remove the media segments that end before the current playback
+    /// position until there is room for `new_data_len` bytes.
+    /// <https://w3c.github.io/media-source/#sourcebuffer-coded-frame-eviction>
+    fn evict_coded_frames(&self, new_data_len: usize) {
+        let current_time = self
+            .media_source
+            .get()
+            .and_then(|media_source| media_source.media_element())
+            .map_or(0., |media_element| *media_element.CurrentTime());
+        let mut len = self.len();
+        self.media_segments.borrow_mut().retain(|segment| {
+            if len + new_data_len <= QUOTA || segment.end > current_time {
+                return true;
+            }
+            len -= segment.data.len();
+            false
+        });
+    }
+
+    /// <https://w3c.github.io/media-source/#sourcebuffer-buffer-append>
+    fn buffer_append(&self) {
+        // Step 1.
+        let segments = match self.parser.borrow_mut().parse() {
+            Ok(segments) => segments,
+            Err(error) => {
+                warn!("Could not parse appended media data: {:?}", error);
+                return self.append_error();
+            },
+        };
+        for segment in segments {
+            match segment {
+                Segment::Initialization(segment) => self.initialization_segment_received(segment),
+                Segment::Media(segment) => self.media_segment_received(segment),
+            }
+        }
+
+        // Step 3.
+        self.updating.set(false);
+
+        // Step 4.
+        self.queue_event(atom!("update"));
+
+        // Step 5.
+        self.queue_event(atom!("updateend"));
+    }
+
+    /// <https://w3c.github.io/media-source/#sourcebuffer-append-error>
+    fn append_error(&self) {
+        // Step 1.
+        self.parser.borrow_mut().reset();
+
+        // Step 2.
+        self.updating.set(false);
+
+        // Steps 3-4.
+        self.queue_event(atom!("error"));
+        self.queue_event(atom!("updateend"));
+
+        // Step 5.
+        if let Some(media_source) = self.media_source.get() {
+            media_source.end_of_stream(Some(EndOfStreamError::Decode));
+        }
+    }
+
+    /// <https://w3c.github.io/media-source/#sourcebuffer-init-segment-received>
+    fn initialization_segment_received(&self, segment: InitializationSegment) {
+        let media_source = match self.media_source.get() {
+            Some(media_source) => media_source,
+            None => return,
+        };
+
+        // Step 1.
+        if media_source.Duration().is_nan() {
+            let _ = media_source.change_duration(segment.duration.unwrap_or(f64::INFINITY));
+        }
+
+        // Steps 2-4.
+        // Audio, video and text tracks aren't supported.
+
+        // Step 5.
+        let first_initialization_segment = self.initialization_segment.borrow().is_none();
+        if first_initialization_segment {
+            media_source.active_source_buffers().add(self);
+        }
+
+        self.push_data(segment.data.clone());
+        *self.initialization_segment.borrow_mut() = Some(segment.data);
+    }
+
+    /// Buffer a media segment, replacing the segments that start within it.
+    /// <https://w3c.github.io/media-source/#sourcebuffer-coded-frame-processing>
+    fn media_segment_received(&self, segment: MediaSegment) {
+        self.push_data(segment.data.clone());
+
+        let end = segment.end;
+        {
+            let mut segments = self.media_segments.borrow_mut();
+            segments.retain(|s| s.start < segment.start || s.start >= segment.end);
+            let index = segments
+                .iter()
+                .position(|s| s.start > segment.start)
+                .unwrap_or(segments.len());
+            segments.insert(index, segment);
+        }
+
+        // Step 5.
+        if let Some(media_source) = self.media_source.get() {
+            if end > media_source.Duration() {
+                let _ = media_source.change_duration(end);
+            }
+        }
+    }
+
+    /// Remove the media segments entirely within `start` and `end`.
+    /// <https://w3c.github.io/media-source/#sourcebuffer-coded-frame-removal>
+    fn coded_frame_removal(&self, start: f64, end: f64) {
+        self.media_segments
+            .borrow_mut()
+            .retain(|segment| segment.start < start || segment.end > end);
+    }
+
+    /// Set the updating flag, queue an `updatestart` event, and queue a task
+    /// running `steps` unless aborted.
+    fn queue_update<F>(&self, steps: F)
+    where
+        F: FnOnce(&SourceBuffer) + Send + 'static,
+    {
+        self.updating.set(true);
+        self.queue_event(atom!("updatestart"));
+
+        let this = Trusted::new(self);
+        let generation = self.generation.get();
+        let window = self.global();
+        let window = window.as_window();
+        let _ = window.task_manager().media_element_task_source().queue(
+            task!(source_buffer_update: move || {
+                let this = this.root();
+                if this.generation.get() == generation {
+                    steps(&this);
+                }
+            }),
+            window.upcast(),
+        );
+    }
+
+    fn queue_event(&self, name: Atom) {
+        let window = self.global();
+        let window = window.as_window();
+        window
+            .task_manager()
+            .media_element_task_source()
+            .queue_simple_event(self.upcast(), name, window);
+    }
+}
+
+impl SourceBufferMethods for SourceBuffer {
+    // https://w3c.github.io/media-source/#dom-sourcebuffer-updating
+    fn Updating(&self) -> bool {
+        self.updating.get()
+    }
+
+    // https://w3c.github.io/media-source/#dom-sourcebuffer-buffered
+    fn Buffered(&self) -> Fallible<DomRoot<TimeRanges>> {
+        // Step 1.
+        if self.media_source.get().is_none() {
+            return Err(Error::InvalidState);
+        }
+
+        // Steps 2-6.
+        let mut container = TimeRangesContainer::new();
+        for (start, end) in self.buffered_ranges() {
+            let _ = container.add(start, end);
+        }
+        Ok(TimeRanges::new(self.global().as_window(), container))
+    }
+
+    // https://w3c.github.io/media-source/#dom-sourcebuffer-onupdatestart
+    event_handler!(updatestart, GetOnupdatestart, SetOnupdatestart);
+
+    // https://w3c.github.io/media-source/#dom-sourcebuffer-onupdate
+    event_handler!(update, GetOnupdate, SetOnupdate);
+
+    // https://w3c.github.io/media-source/#dom-sourcebuffer-onupdateend
+    event_handler!(updateend, GetOnupdateend, SetOnupdateend);
+
+    // https://w3c.github.io/media-source/#dom-sourcebuffer-onerror
+    event_handler!(error, GetOnerror, SetOnerror);
+
+    // https://w3c.github.io/media-source/#dom-sourcebuffer-onabort
+    event_handler!(abort, GetOnabort, SetOnabort);
+
+    // https://w3c.github.io/media-source/#dom-sourcebuffer-appendbuffer
+    fn AppendBuffer(&self, data: ArrayBufferViewOrArrayBuffer) -> ErrorResult {
+        let data = match data {
+            ArrayBufferViewOrArrayBuffer::ArrayBufferView(ref data) => data.to_vec(),
+            ArrayBufferViewOrArrayBuffer::ArrayBuffer(ref data) => data.to_vec(),
+        };
+
+        // Step 1.
+        self.prepare_append(data.len())?;
+
+        // Step 2.
+        self.parser.borrow_mut().append(&data);
+
+        // Steps 3-5.
+        self.queue_update(|this| this.buffer_append());
+        Ok(())
+    }
+
+    // https://w3c.github.io/media-source/#dom-sourcebuffer-abort
+    fn Abort(&self) -> ErrorResult {
+        // Step 1.
+        let media_source = self.media_source.get().ok_or(Error::InvalidState)?;
+
+        // Step 2.
+        if media_source.ready_state() != ReadyState::Open {
+            return Err(Error::InvalidState);
+        }
+
+        // Steps 3-4.
+        // Range removals are aborted along with buffer appends.
+        self.abort_buffer_append();
+
+        // Step 5.
+        self.parser.borrow_mut().reset();
+
+        // Steps 6-7.
+        // Append windows aren't supported.
+        Ok(())
+    }
+
+    // https://w3c.github.io/media-source/#dom-sourcebuffer-remove
+    fn Remove(&self, start: f64, end: f64) -> ErrorResult {
+        // Step 1.
+        let media_source = self.media_source.get().ok_or(Error::InvalidState)?;
+
+        // Step 2.
+        if self.updating.get() {
+            return Err(Error::InvalidState);
+        }
+
+        // Step 3.
+        let duration = media_source.Duration();
+        if duration.is_nan() {
+            return Err(Error::Type("The duration is unknown".to_owned()));
+        }
+
+        // Step 4.
+        if start < 0. || start > duration {
+            return Err(Error::Type(
+                "Invalid start of the range to remove".to_owned(),
+            ));
+        }
+
+        // Step 5.
+        if end.is_nan() || end <= start {
+            return Err(Error::Type("Invalid end of the range to remove".to_owned()));
+        }
+
+        // Step 6.
+        media_source.open_if_ended();
+
+        // Step 7.
+        self.queue_update(move |this| {
+            // https://w3c.github.io/media-source/#sourcebuffer-range-removal
+            // Step 6.
+            this.coded_frame_removal(start, end);
+
+            // Steps 7-9.
+            this.updating.set(false);
+            this.queue_event(atom!("update"));
+            this.queue_event(atom!("updateend"));
+        });
+        Ok(())
+    }
+}
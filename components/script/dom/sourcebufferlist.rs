@@ -0,0 +1,117 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::SourceBufferListBinding::SourceBufferListMethods;
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject};
+use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::eventtarget::EventTarget;
+use crate::dom::sourcebuffer::SourceBuffer;
+use crate::dom::window::Window;
+use crate::task_source::TaskSource;
+use dom_struct::dom_struct;
+use servo_atoms::Atom;
+
+#[dom_struct]
+pub struct SourceBufferList {
+    eventtarget: EventTarget,
+    buffers: DomRefCell<Vec<Dom<SourceBuffer>>>,
+}
+
+impl SourceBufferList {
+    fn new_inherited() -> SourceBufferList {
+        SourceBufferList {
+            eventtarget: EventTarget::new_inherited(),
+            buffers: DomRefCell::new(vec![]),
+        }
+    }
+
+    pub fn new(window: &Window) -> DomRoot<SourceBufferList> {
+        reflect_dom_object(Box::new(SourceBufferList::new_inherited()), window)
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffers.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffers.borrow().is_empty()
+    }
+
+    pub fn contains(&self, buffer: &SourceBuffer) -> bool {
+        self.buffers.borrow().iter().any(|b| &**b == buffer)
+    }
+
+    pub fn buffers(&self) -> Vec<DomRoot<SourceBuffer>> {
+        self.buffers
+            .borrow()
+            .iter()
+            .map(|buffer| DomRoot::from_ref(&**buffer))
+            .collect()
+    }
+
+    /// Append a buffer and queue an `addsourcebuffer` event.
+    pub fn add(&self, buffer: &SourceBuffer) {
+        self.buffers.borrow_mut().push(Dom::from_ref(buffer));
+        self.queue_event(atom!("addsourcebuffer"));
+    }
+
+    /// Remove a buffer and queue a `removesourcebuffer` event, returning
+    /// whether the buffer was in the list.
+    pub fn remove(&self, buffer: &SourceBuffer) -> bool {
+        let position = self.buffers.borrow().iter().position(|b| &**b == buffer);
+        match position {
+            Some(position) => {
+                self.buffers.borrow_mut().remove(position);
+                self.queue_event(atom!("removesourcebuffer"));
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Remove all the buffers and queue a single `removesourcebuffer` event.
+    pub fn clear(&self) {
+        if self.is_empty() {
+            return;
+        }
+        self.buffers.borrow_mut().clear();
+        self.queue_event(atom!("removesourcebuffer"));
+    }
+
+    fn queue_event(&self, name: Atom) {
+        let window = self.global();
+        let window = window.as_window();
+        window
+            .task_manager()
+            .media_element_task_source()
+            .queue_simple_event(self.upcast(), name, window);
+    }
+}
+
+impl SourceBufferListMethods for SourceBufferList {
+    // https://w3c.github.io/media-source/#dom-sourcebufferlist-length
+    fn Length(&self) -> u32 {
+        self.len() as u32
+    }
+
+    // https://w3c.github.io/media-source/#dfn-sourcebufferlist-getter
+    fn IndexedGetter(&self, index: u32) -> Option<DomRoot<SourceBuffer>> {
+        self.buffers
+            .borrow()
+            .get(index as usize)
+            .map(|buffer| DomRoot::from_ref(&**buffer))
+    }
+
+    // https://w3c.github.io/media-source/#dom-sourcebufferlist-onaddsourcebuffer
+    event_handler!(addsourcebuffer, GetOnaddsourcebuffer, SetOnaddsourcebuffer);
+
+    // https://w3c.github.io/media-source/#dom-sourcebufferlist-onremovesourcebuffer
+    event_handler!(
+        removesourcebuffer,
+        GetOnremovesourcebuffer,
+        SetOnremovesourcebuffer
+    );
+}
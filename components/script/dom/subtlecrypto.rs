@@ -0,0 +1,2504 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::CryptoKeyBinding::{CryptoKeyPair, KeyType, KeyUsage};
+use crate::dom::bindings::codegen::Bindings::SubtleCryptoBinding::{
+    AesCbcParams, AesCtrParams, AesDerivedKeyParams, AesGcmParams, AesKeyGenParams, Algorithm,
+    EcKeyGenParams, EcKeyImportParams, EcdhKeyDeriveParams, EcdsaParams, HkdfParams,
+    HmacImportParams, HmacKeyGenParams, JsonWebKey, KeyFormat, Pbkdf2Params, RsaHashedImportParams,
+    RsaHashedKeyGenParams, RsaOaepParams, RsaOtherPrimesInfo, RsaPssParams, SubtleCryptoMethods,
+};
+use crate::dom::bindings::codegen::UnionTypes::{
+    ArrayBufferViewOrArrayBuffer as BufferSource, ArrayBufferViewOrArrayBufferOrJsonWebKey,
+    ObjectOrString as AlgorithmIdentifier,
+};
+use crate::dom::bindings::conversions::ConversionResult;
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::bindings::str::DOMString;
+use crate::dom::bindings::utils::set_dictionary_property;
+use crate::dom::cryptokey::{CryptoKey, KeyAlgorithm, KeyData, KeyHandle};
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::{Promise, TrustedPromise};
+use crate::realms::{enter_realm, InRealm};
+use crate::script_runtime::JSContext;
+use crate::task_source::{TaskSource, TaskSourceName};
+use dom_struct::dom_struct;
+use js::conversions::ToJSValConvertible;
+use js::jsapi::{JSObject, JS_NewPlainObject};
+use js::jsval::{ObjectValue, UndefinedValue};
+use js::typedarray::{ArrayBuffer, CreateWith};
+use openssl::aes::{self, AesKey};
+use openssl::bn::{BigNum, BigNumContext, BigNumRef};
+use openssl::derive::Deriver;
+use openssl::ec::{EcGroup, EcKey, EcPoint, PointConversionForm};
+use openssl::ecdsa::EcdsaSig;
+use openssl::encrypt::{Decrypter, Encrypter};
+use openssl::error::ErrorStack;
+use openssl::hash::{self, MessageDigest};
+use openssl::memcmp;
+use openssl::nid::Nid;
+use openssl::pkcs5;
+use openssl::pkey::{HasPublic, Id, PKey, PKeyRef, Private, Public};
+use openssl::rsa::{Padding, Rsa};
+use openssl::sign::{RsaPssSaltlen, Signer, Verifier};
+use openssl::symm::{self, Cipher};
+use servo_rand::{RngCore, ServoRng};
+use std::cmp;
+use std::ptr;
+use std::rc::Rc;
+
+/// <https://w3c.github.io/webcrypto/#recognized-algorithm-name>
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CryptoAlgorithm {
+    RsassaPkcs1V15,
+    RsaPss,
+    RsaOaep,
+    Ecdsa,
+    Ecdh,
+    AesCtr,
+    AesCbc,
+    AesGcm,
+    AesKw,
+    Hmac,
+    Sha1,
+    Sha256,
+    Sha384,
+    Sha512,
+    Hkdf,
+    Pbkdf2,
+}
+
+const RECOGNIZED_ALGORITHMS: &[CryptoAlgorithm] = &[
+    CryptoAlgorithm::RsassaPkcs1V15,
+    CryptoAlgorithm::RsaPss,
+    CryptoAlgorithm::RsaOaep,
+    CryptoAlgorithm::Ecdsa,
+    CryptoAlgorithm::Ecdh,
+    CryptoAlgorithm::AesCtr,
+    CryptoAlgorithm::AesCbc,
+    CryptoAlgorithm::AesGcm,
+    CryptoAlgorithm::AesKw,
+    CryptoAlgorithm::Hmac,
+    CryptoAlgorithm::Sha1,
+    CryptoAlgorithm::Sha256,
+    CryptoAlgorithm::Sha384,
+    CryptoAlgorithm::Sha512,
+    CryptoAlgorithm::Hkdf,
+    CryptoAlgorithm::Pbkdf2,
+];
+
+impl CryptoAlgorithm {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            CryptoAlgorithm::RsassaPkcs1V15 => "RSASSA-PKCS1-v1_5",
+            CryptoAlgorithm::RsaPss => "RSA-PSS",
+            CryptoAlgorithm::RsaOaep => "RSA-OAEP",
+            CryptoAlgorithm::Ecdsa => "ECDSA",
+            CryptoAlgorithm::Ecdh => "ECDH",
+            CryptoAlgorithm::AesCtr => "AES-CTR",
+            CryptoAlgorithm::AesCbc => "AES-CBC",
+            CryptoAlgorithm::AesGcm => "AES-GCM",
+            CryptoAlgorithm::AesKw => "AES-KW",
+            CryptoAlgorithm::Hmac => "HMAC",
+            CryptoAlgorithm::Sha1 => "SHA-1",
+            CryptoAlgorithm::Sha256 => "SHA-256",
+            CryptoAlgorithm::Sha384 => "SHA-384",
+            CryptoAlgorithm::Sha512 => "SHA-512",
+            CryptoAlgorithm::Hkdf => "HKDF",
+            CryptoAlgorithm::Pbkdf2 => "PBKDF2",
+        }
+    }
+
+    /// Algorithm names are matched ASCII case-insensitively.
+    fn from_name(name: &str) -> Option<CryptoAlgorithm> {
+        RECOGNIZED_ALGORITHMS
+            .iter()
+            .find(|algorithm| algorithm.as_str().eq_ignore_ascii_case(name))
+            .cloned()
+    }
+
+    /// The digest of a hash algorithm, which normalization guarantees `self`
+    /// to be.
+    fn message_digest(&self) -> MessageDigest {
+        match *self {
+            CryptoAlgorithm::Sha1 => MessageDigest::sha1(),
+            CryptoAlgorithm::Sha256 => MessageDigest::sha256(),
+            CryptoAlgorithm::Sha384 => MessageDigest::sha384(),
+            CryptoAlgorithm::Sha512 => MessageDigest::sha512(),
+            _ => unreachable!("{} is not a hash algorithm", self.as_str()),
+        }
+    }
+
+    /// The block size of a hash algorithm in bits, which is the default
+    /// length of HMAC keys.
+    fn block_size(&self) -> u32 {
+        match *self {
+            CryptoAlgorithm::Sha1 | CryptoAlgorithm::Sha256 => 512,
+            _ => 1024,
+        }
+    }
+
+    /// The suffix of the JSON Web Algorithms names using a hash algorithm.
+    fn jwk_hash_suffix(&self) -> &'static str {
+        match *self {
+            CryptoAlgorithm::Sha1 => "1",
+            CryptoAlgorithm::Sha256 => "256",
+            CryptoAlgorithm::Sha384 => "384",
+            _ => "512",
+        }
+    }
+}
+
+/// <https://w3c.github.io/webcrypto/#dfn-NamedCurve>
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NamedCurve {
+    P256,
+    P384,
+    P521,
+}
+
+impl NamedCurve {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            NamedCurve::P256 => "P-256",
+            NamedCurve::P384 => "P-384",
+            NamedCurve::P521 => "P-521",
+        }
+    }
+
+    /// Curve names, unlike algorithm names, are case-sensitive.
+    fn from_name(name: &str) -> Option<NamedCurve> {
+        match name {
+            "P-256" => Some(NamedCurve::P256),
+            "P-384" => Some(NamedCurve::P384),
+            "P-521" => Some(NamedCurve::P521),
+            _ => None,
+        }
+    }
+
+    fn nid(&self) -> Nid {
+        match *self {
+            NamedCurve::P256 => Nid::X9_62_PRIME256V1,
+            NamedCurve::P384 => Nid::SECP384R1,
+            NamedCurve::P521 => Nid::SECP521R1,
+        }
+    }
+
+    fn group(&self) -> Fallible<EcGroup> {
+        EcGroup::from_curve_name(self.nid()).map_err(operation_error)
+    }
+
+    /// The length in bytes of the coordinates and of the private keys on
+    /// this curve.
+    fn coordinate_length(&self) -> usize {
+        match *self {
+            NamedCurve::P256 => 32,
+            NamedCurve::P384 => 48,
+            NamedCurve::P521 => 66,
+        }
+    }
+
+    /// The JSON Web Algorithms name of ECDSA on this curve.
+    fn jwk_ecdsa_algorithm(&self) -> &'static str {
+        match *self {
+            NamedCurve::P256 => "ES256",
+            NamedCurve::P384 => "ES384",
+            NamedCurve::P521 => "ES512",
+        }
+    }
+}
+
+/// The operations algorithms are registered for,
+/// <https://w3c.github.io/webcrypto/#dfn-supportedAlgorithms>.
+#[derive(Clone, Copy, PartialEq)]
+enum Operation {
+    Encrypt,
+    Decrypt,
+    Sign,
+    Verify,
+    Digest,
+    GenerateKey,
+    DeriveBits,
+    ImportKey,
+    WrapKey,
+    UnwrapKey,
+    GetKeyLength,
+}
+
+impl Operation {
+    fn supports(&self, algorithm: CryptoAlgorithm) -> bool {
+        match *self {
+            Operation::Encrypt | Operation::Decrypt => matches!(
+                algorithm,
+                CryptoAlgorithm::RsaOaep |
+                    CryptoAlgorithm::AesCtr |
+                    CryptoAlgorithm::AesCbc |
+                    CryptoAlgorithm::AesGcm
+            ),
+            Operation::Sign | Operation::Verify => matches!(
+                algorithm,
+                CryptoAlgorithm::RsassaPkcs1V15 |
+                    CryptoAlgorithm::RsaPss |
+                    CryptoAlgorithm::Ecdsa |
+                    CryptoAlgorithm::Hmac
+            ),
+            Operation::Digest => matches!(
+                algorithm,
+                CryptoAlgorithm::Sha1 |
+                    CryptoAlgorithm::Sha256 |
+                    CryptoAlgorithm::Sha384 |
+                    CryptoAlgorithm::Sha512
+            ),
+            Operation::GenerateKey => matches!(
+                algorithm,
+                CryptoAlgorithm::RsassaPkcs1V15 |
+                    CryptoAlgorithm::RsaPss |
+                    CryptoAlgorithm::RsaOaep |
+                    CryptoAlgorithm::Ecdsa |
+                    CryptoAlgorithm::Ecdh |
+                    CryptoAlgorithm::AesCtr |
+                    CryptoAlgorithm::AesCbc |
+                    CryptoAlgorithm::AesGcm |
+                    CryptoAlgorithm::AesKw |
+                    CryptoAlgorithm::Hmac
+            ),
+            Operation::DeriveBits => matches!(
+                algorithm,
+                CryptoAlgorithm::Ecdh | CryptoAlgorithm::Hkdf | CryptoAlgorithm::Pbkdf2
+            ),
+            Operation::ImportKey => !Operation::Digest.supports(algorithm),
+            Operation::WrapKey | Operation::UnwrapKey => algorithm == CryptoAlgorithm::AesKw,
+            Operation::GetKeyLength => matches!(
+                algorithm,
+                CryptoAlgorithm::AesCtr |
+                    CryptoAlgorithm::AesCbc |
+                    CryptoAlgorithm::AesGcm |
+                    CryptoAlgorithm::AesKw |
+                    CryptoAlgorithm::Hmac |
+                    CryptoAlgorithm::Hkdf |
+                    CryptoAlgorithm::Pbkdf2
+            ),
+        }
+    }
+}
+
+/// The result of normalizing an algorithm for an operation, which holds no
+/// JavaScript values so that it can be sent to the thread performing the
+/// operation.
+///
+/// <https://w3c.github.io/webcrypto/#algorithm-normalization-normalize-an-algorithm>
+enum NormalizedAlgorithm {
+    /// An algorithm whose operation takes no parameters other than its name.
+    Algorithm(CryptoAlgorithm),
+    /// <https://w3c.github.io/webcrypto/#aes-ctr-params>
+    AesCtr { counter: Vec<u8>, length: u8 },
+    /// <https://w3c.github.io/webcrypto/#aes-cbc-params>
+    AesCbc { iv: Vec<u8> },
+    /// <https://w3c.github.io/webcrypto/#aes-gcm-params>
+    AesGcm {
+        iv: Vec<u8>,
+        additional_data: Option<Vec<u8>>,
+        tag_length: Option<u8>,
+    },
+    /// <https://w3c.github.io/webcrypto/#aes-keygen-params> and
+    /// <https://w3c.github.io/webcrypto/#aes-derivedkey-params>
+    AesKeyLength { name: CryptoAlgorithm, length: u16 },
+    /// <https://w3c.github.io/webcrypto/#hmac-importparams> and
+    /// <https://w3c.github.io/webcrypto/#hmac-keygen-params>
+    Hmac {
+        hash: CryptoAlgorithm,
+        length: Option<u32>,
+    },
+    /// <https://w3c.github.io/webcrypto/#RsaHashedKeyGenParams-dictionary>
+    RsaHashedKeyGen {
+        name: CryptoAlgorithm,
+        modulus_length: u32,
+        public_exponent: Vec<u8>,
+        hash: CryptoAlgorithm,
+    },
+    /// <https://w3c.github.io/webcrypto/#RsaHashedImportParams-dictionary>
+    RsaHashedImport {
+        name: CryptoAlgorithm,
+        hash: CryptoAlgorithm,
+    },
+    /// <https://w3c.github.io/webcrypto/#RsaPssParams-dictionary>
+    RsaPss { salt_length: u32 },
+    /// <https://w3c.github.io/webcrypto/#RsaOaepParams-dictionary>
+    RsaOaep { label: Option<Vec<u8>> },
+    /// <https://w3c.github.io/webcrypto/#EcdsaParams-dictionary>
+    Ecdsa { hash: CryptoAlgorithm },
+    /// <https://w3c.github.io/webcrypto/#EcKeyGenParams-dictionary> and
+    /// <https://w3c.github.io/webcrypto/#EcKeyImportParams-dictionary>
+    EcKey {
+        name: CryptoAlgorithm,
+        named_curve: String,
+    },
+    /// <https://w3c.github.io/webcrypto/#dh-EcdhKeyDeriveParams>
+    EcdhKeyDerive { public: KeyData },
+    /// <https://w3c.github.io/webcrypto/#hkdf-params>
+    Hkdf {
+        hash: CryptoAlgorithm,
+        salt: Vec<u8>,
+        info: Vec<u8>,
+    },
+    /// <https://w3c.github.io/webcrypto/#pbkdf2-params>
+    Pbkdf2 {
+        hash: CryptoAlgorithm,
+        salt: Vec<u8>,
+        iterations: u32,
+    },
+}
+
+impl NormalizedAlgorithm {
+    fn name(&self) -> CryptoAlgorithm {
+        match *self {
+            NormalizedAlgorithm::Algorithm(name) |
+            NormalizedAlgorithm::AesKeyLength { name, .. } |
+            NormalizedAlgorithm::RsaHashedKeyGen { name, .. } |
+            NormalizedAlgorithm::RsaHashedImport { name, .. } |
+            NormalizedAlgorithm::EcKey { name, .. } => name,
+            NormalizedAlgorithm::AesCtr { .. } => CryptoAlgorithm::AesCtr,
+            NormalizedAlgorithm::AesCbc { .. } => CryptoAlgorithm::AesCbc,
+            NormalizedAlgorithm::AesGcm { .. } => CryptoAlgorithm::AesGcm,
+            NormalizedAlgorithm::Hmac { .. } => CryptoAlgorithm::Hmac,
+            NormalizedAlgorithm::RsaPss { .. } => CryptoAlgorithm::RsaPss,
+            NormalizedAlgorithm::RsaOaep { .. } => CryptoAlgorithm::RsaOaep,
+            NormalizedAlgorithm::Ecdsa { .. } => CryptoAlgorithm::Ecdsa,
+            NormalizedAlgorithm::EcdhKeyDerive { .. } => CryptoAlgorithm::Ecdh,
+            NormalizedAlgorithm::Hkdf { .. } => CryptoAlgorithm::Hkdf,
+            NormalizedAlgorithm::Pbkdf2 { .. } => CryptoAlgorithm::Pbkdf2,
+        }
+    }
+}
+
+/// Converts `value` to the given dictionary type, returning from the
+/// enclosing function if the conversion fails.
+macro_rules! convert_dictionary {
+    ($cx:expr, $value:expr, $dictionary:ident) => {
+        match $dictionary::new($cx, $value) {
+            Ok(ConversionResult::Success(dictionary)) => dictionary,
+            Ok(ConversionResult::Failure(error)) => return Err(Error::Type(error.to_string())),
+            Err(()) => return Err(Error::JSFailed),
+        }
+    };
+}
+
+/// <https://w3c.github.io/webcrypto/#algorithm-normalization-normalize-an-algorithm>
+#[allow(unsafe_code)]
+fn normalize_algorithm(
+    cx: JSContext,
+    algorithm: &AlgorithmIdentifier,
+    operation: Operation,
+) -> Fallible<NormalizedAlgorithm> {
+    rooted!(in(*cx) let mut value = UndefinedValue());
+    match *algorithm {
+        // Step 1.
+        AlgorithmIdentifier::String(ref name) => unsafe {
+            rooted!(in(*cx) let object = JS_NewPlainObject(*cx));
+            rooted!(in(*cx) let mut name_value = UndefinedValue());
+            name.to_jsval(*cx, name_value.handle_mut());
+            set_dictionary_property(*cx, object.handle(), "name", name_value.handle())
+                .map_err(|_| Error::JSFailed)?;
+            value.set(ObjectValue(object.get()));
+        },
+        AlgorithmIdentifier::Object(ref object) => value.set(ObjectValue(object.get())),
+    }
+    let value = value.handle();
+
+    // Steps 2-9.
+    let initial_algorithm = convert_dictionary!(cx, value, Algorithm);
+    let name = match CryptoAlgorithm::from_name(&initial_algorithm.name) {
+        Some(name) if operation.supports(name) => name,
+        _ => return Err(Error::NotSupported),
+    };
+
+    // Step 10.
+    let normalized = match name {
+        CryptoAlgorithm::RsassaPkcs1V15 | CryptoAlgorithm::RsaPss | CryptoAlgorithm::RsaOaep
+            if operation == Operation::GenerateKey =>
+        {
+            let params = convert_dictionary!(cx, value, RsaHashedKeyGenParams);
+            NormalizedAlgorithm::RsaHashedKeyGen {
+                name,
+                modulus_length: params.parent.modulusLength,
+                public_exponent: buffer_source_to_vec(&params.parent.publicExponent),
+                hash: normalize_hash(cx, &params.hash)?,
+            }
+        }
+        CryptoAlgorithm::RsassaPkcs1V15 | CryptoAlgorithm::RsaPss | CryptoAlgorithm::RsaOaep
+            if operation == Operation::ImportKey =>
+        {
+            let params = convert_dictionary!(cx, value, RsaHashedImportParams);
+            NormalizedAlgorithm::RsaHashedImport {
+                name,
+                hash: normalize_hash(cx, &params.hash)?,
+            }
+        }
+        CryptoAlgorithm::RsaPss if matches!(operation, Operation::Sign | Operation::Verify) => {
+            let params = convert_dictionary!(cx, value, RsaPssParams);
+            NormalizedAlgorithm::RsaPss {
+                salt_length: params.saltLength,
+            }
+        },
+        CryptoAlgorithm::RsaOaep
+            if matches!(operation, Operation::Encrypt | Operation::Decrypt) =>
+        {
+            let params = convert_dictionary!(cx, value, RsaOaepParams);
+            NormalizedAlgorithm::RsaOaep {
+                label: params.label.as_ref().map(buffer_source_to_vec),
+            }
+        }
+        CryptoAlgorithm::Ecdsa | CryptoAlgorithm::Ecdh if operation == Operation::GenerateKey => {
+            let params = convert_dictionary!(cx, value, EcKeyGenParams);
+            NormalizedAlgorithm::EcKey {
+                name,
+                named_curve: params.namedCurve.to_string(),
+            }
+        },
+        CryptoAlgorithm::Ecdsa | CryptoAlgorithm::Ecdh if operation == Operation::ImportKey => {
+            let params = convert_dictionary!(cx, value, EcKeyImportParams);
+            NormalizedAlgorithm::EcKey {
+                name,
+                named_curve: params.namedCurve.to_string(),
+            }
+        },
+        CryptoAlgorithm::Ecdsa => {
+            let params = convert_dictionary!(cx, value, EcdsaParams);
+            NormalizedAlgorithm::Ecdsa {
+                hash: normalize_hash(cx, &params.hash)?,
+            }
+        },
+        CryptoAlgorithm::Ecdh if operation == Operation::DeriveBits => {
+            let params = convert_dictionary!(cx, value, EcdhKeyDeriveParams);
+            NormalizedAlgorithm::EcdhKeyDerive {
+                public: params.public.data(),
+            }
+        },
+        CryptoAlgorithm::AesCtr if matches!(operation, Operation::Encrypt | Operation::Decrypt) => {
+            let params = convert_dictionary!(cx, value, AesCtrParams);
+            NormalizedAlgorithm::AesCtr {
+                counter: buffer_source_to_vec(&params.counter),
+                length: params.length,
+            }
+        },
+        CryptoAlgorithm::AesCbc if matches!(operation, Operation::Encrypt | Operation::Decrypt) => {
+            let params = convert_dictionary!(cx, value, AesCbcParams);
+            NormalizedAlgorithm::AesCbc {
+                iv: buffer_source_to_vec(&params.iv),
+            }
+        },
+        CryptoAlgorithm::AesGcm if matches!(operation, Operation::Encrypt | Operation::Decrypt) => {
+            let params = convert_dictionary!(cx, value, AesGcmParams);
+            NormalizedAlgorithm::AesGcm {
+                iv: buffer_source_to_vec(&params.iv),
+                additional_data: params.additionalData.as_ref().map(buffer_source_to_vec),
+                tag_length: params.tagLength,
+            }
+        },
+        CryptoAlgorithm::AesCtr |
+        CryptoAlgorithm::AesCbc |
+        CryptoAlgorithm::AesGcm |
+        CryptoAlgorithm::AesKw
+            if operation == Operation::GenerateKey =>
+        {
+            let params = convert_dictionary!(cx, value, AesKeyGenParams);
+            NormalizedAlgorithm::AesKeyLength {
+                name,
+                length: params.length,
+            }
+        }
+        CryptoAlgorithm::AesCtr |
+        CryptoAlgorithm::AesCbc |
+        CryptoAlgorithm::AesGcm |
+        CryptoAlgorithm::AesKw
+            if operation == Operation::GetKeyLength =>
+        {
+            let params = convert_dictionary!(cx, value, AesDerivedKeyParams);
+            NormalizedAlgorithm::AesKeyLength {
+                name,
+                length: params.length,
+            }
+        }
+        CryptoAlgorithm::Hmac if operation == Operation::GenerateKey => {
+            let params = convert_dictionary!(cx, value, HmacKeyGenParams);
+            NormalizedAlgorithm::Hmac {
+                hash: normalize_hash(cx, &params.hash)?,
+                length: params.length,
+            }
+        },
+        CryptoAlgorithm::Hmac
+            if matches!(operation, Operation::ImportKey | Operation::GetKeyLength) =>
+        {
+            let params = convert_dictionary!(cx, value, HmacImportParams);
+            NormalizedAlgorithm::Hmac {
+                hash: normalize_hash(cx, &params.hash)?,
+                length: params.length,
+            }
+        }
+        CryptoAlgorithm::Hkdf if operation == Operation::DeriveBits => {
+            let params = convert_dictionary!(cx, value, HkdfParams);
+            NormalizedAlgorithm::Hkdf {
+                hash: normalize_hash(cx, &params.hash)?,
+                salt: buffer_source_to_vec(&params.salt),
+                info: buffer_source_to_vec(&params.info),
+            }
+        },
+        CryptoAlgorithm::Pbkdf2 if operation == Operation::DeriveBits => {
+            let params = convert_dictionary!(cx, value, Pbkdf2Params);
+            NormalizedAlgorithm::Pbkdf2 {
+                hash: normalize_hash(cx, &params.hash)?,
+                salt: buffer_source_to_vec(&params.salt),
+                iterations: params.iterations,
+            }
+        },
+        _ => NormalizedAlgorithm::Algorithm(name),
+    };
+
+    // Step 11.
+    Ok(normalized)
+}
+
+/// Normalizes the `hash` member of an algorithm dictionary.
+fn normalize_hash(cx: JSContext, hash: &AlgorithmIdentifier) -> Fallible<CryptoAlgorithm> {
+    Ok(normalize_algorithm(cx, hash, Operation::Digest)?.name())
+}
+
+fn buffer_source_to_vec(source: &BufferSource) -> Vec<u8> {
+    match *source {
+        BufferSource::ArrayBufferView(ref view) => view.to_vec(),
+        BufferSource::ArrayBuffer(ref buffer) => buffer.to_vec(),
+    }
+}
+
+fn operation_error(_: ErrorStack) -> Error {
+    Error::Operation
+}
+
+fn data_error(_: ErrorStack) -> Error {
+    Error::Data
+}
+
+fn random_bytes(length: usize) -> Vec<u8> {
+    let mut bytes = vec![0; length];
+    ServoRng::new().fill_bytes(&mut bytes);
+    bytes
+}
+
+/// Prepends zeros to `bytes` to make it `length` bytes long.
+fn pad_bytes(bytes: Vec<u8>, length: usize) -> Vec<u8> {
+    let mut padded = vec![0; length.saturating_sub(bytes.len())];
+    padded.extend(bytes);
+    padded
+}
+
+fn base64url_encode(bytes: &[u8]) -> String {
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+fn base64url_decode(string: &Option<String>) -> Fallible<Vec<u8>> {
+    let string = string.as_ref().ok_or(Error::Data)?;
+    base64::decode_config(string, base64::URL_SAFE_NO_PAD).map_err(|_| Error::Data)
+}
+
+fn big_num(string: &Option<String>) -> Fallible<BigNum> {
+    BigNum::from_slice(&base64url_decode(string)?).map_err(data_error)
+}
+
+/// The usages in `usages` that are also in `allowed`, without duplicates
+/// and in the order of `allowed`.
+fn usage_intersection(usages: &[KeyUsage], allowed: &[KeyUsage]) -> Vec<KeyUsage> {
+    allowed
+        .iter()
+        .filter(|usage| usages.contains(usage))
+        .cloned()
+        .collect()
+}
+
+/// Throws a `SyntaxError` if `usages` contains a usage not in `allowed`.
+fn check_usages(usages: &[KeyUsage], allowed: &[KeyUsage]) -> Fallible<()> {
+    if usages.iter().any(|usage| !allowed.contains(usage)) {
+        return Err(Error::Syntax);
+    }
+    Ok(())
+}
+
+/// Checks that `key` can be used for `usage` with the algorithm named
+/// `name`, as done by most methods of `SubtleCrypto`.
+fn check_key(key: &KeyData, name: CryptoAlgorithm, usage: KeyUsage) -> Fallible<()> {
+    if key.algorithm.name() != name || !key.usages.contains(&usage) {
+        return Err(Error::InvalidAccess);
+    }
+    Ok(())
+}
+
+/// The hash of an RSA, HMAC or ECDSA key.
+fn key_hash(key: &KeyData) -> Fallible<CryptoAlgorithm> {
+    match key.algorithm {
+        KeyAlgorithm::RsaHashed { hash, .. } | KeyAlgorithm::Hmac { hash, .. } => Ok(hash),
+        _ => Err(Error::InvalidAccess),
+    }
+}
+
+fn key_named_curve(key: &KeyData) -> Fallible<NamedCurve> {
+    match key.algorithm {
+        KeyAlgorithm::Ec { named_curve, .. } => Ok(named_curve),
+        _ => Err(Error::InvalidAccess),
+    }
+}
+
+fn secret_key(key: &KeyData) -> Fallible<&[u8]> {
+    match key.handle {
+        KeyHandle::Secret(ref bytes) => Ok(bytes),
+        _ => Err(Error::InvalidAccess),
+    }
+}
+
+fn private_key(key: &KeyData) -> Fallible<&PKey<Private>> {
+    match key.handle {
+        KeyHandle::Private(ref pkey) => Ok(pkey),
+        _ => Err(Error::InvalidAccess),
+    }
+}
+
+fn public_key(key: &KeyData) -> Fallible<&PKey<Public>> {
+    match key.handle {
+        KeyHandle::Public(ref pkey) => Ok(pkey),
+        _ => Err(Error::InvalidAccess),
+    }
+}
+
+/// The public key of the key pair whose private key is `pkey`.
+fn public_key_of(pkey: &PKey<Private>) -> Result<PKey<Public>, ErrorStack> {
+    PKey::public_key_from_der(&pkey.public_key_to_der()?)
+}
+
+/// A key in JSON Web Key format, which can be sent to the thread performing
+/// the operations and serialized when wrapping keys.
+///
+/// <https://w3c.github.io/webcrypto/#JsonWebKey-dictionary>
+#[derive(Default, Deserialize, Serialize)]
+struct Jwk {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kty: Option<String>,
+    #[serde(rename = "use", skip_serializing_if = "Option::is_none")]
+    use_: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    key_ops: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    alg: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ext: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    crv: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    x: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    y: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    d: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    n: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    e: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    p: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    q: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dp: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dq: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    qi: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    oth: Option<Vec<JwkOtherPrimesInfo>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    k: Option<String>,
+}
+
+/// <https://w3c.github.io/webcrypto/#dfn-RsaOtherPrimesInfo>
+#[derive(Deserialize, Serialize)]
+struct JwkOtherPrimesInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    r: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    d: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    t: Option<String>,
+}
+
+fn to_string(member: &Option<DOMString>) -> Option<String> {
+    member.as_ref().map(|string| string.to_string())
+}
+
+fn to_dom_string(member: &Option<String>) -> Option<DOMString> {
+    member
+        .as_ref()
+        .map(|string| DOMString::from(string.as_str()))
+}
+
+impl Jwk {
+    fn from_dictionary(jwk: &JsonWebKey) -> Jwk {
+        Jwk {
+            kty: to_string(&jwk.kty),
+            use_: to_string(&jwk.use_),
+            key_ops: jwk
+                .key_ops
+                .as_ref()
+                .map(|ops| ops.iter().map(|op| op.to_string()).collect()),
+            alg: to_string(&jwk.alg),
+            ext: jwk.ext,
+            crv: to_string(&jwk.crv),
+            x: to_string(&jwk.x),
+            y: to_string(&jwk.y),
+            d: to_string(&jwk.d),
+            n: to_string(&jwk.n),
+            e: to_string(&jwk.e),
+            p: to_string(&jwk.p),
+            q: to_string(&jwk.q),
+            dp: to_string(&jwk.dp),
+            dq: to_string(&jwk.dq),
+            qi: to_string(&jwk.qi),
+            oth: jwk.oth.as_ref().map(|oth| {
+                oth.iter()
+                    .map(|info| JwkOtherPrimesInfo {
+                        r: to_string(&info.r),
+                        d: to_string(&info.d),
+                        t: to_string(&info.t),
+                    })
+                    .collect()
+            }),
+            k: to_string(&jwk.k),
+        }
+    }
+
+    fn to_dictionary(&self) -> JsonWebKey {
+        let mut jwk = JsonWebKey::empty();
+        jwk.kty = to_dom_string(&self.kty);
+        jwk.use_ = to_dom_string(&self.use_);
+        jwk.key_ops = self
+            .key_ops
+            .as_ref()
+            .map(|ops| ops.iter().map(|op| DOMString::from(op.as_str())).collect());
+        jwk.alg = to_dom_string(&self.alg);
+        jwk.ext = self.ext;
+        jwk.crv = to_dom_string(&self.crv);
+        jwk.x = to_dom_string(&self.x);
+        jwk.y = to_dom_string(&self.y);
+        jwk.d = to_dom_string(&self.d);
+        jwk.n = to_dom_string(&self.n);
+        jwk.e = to_dom_string(&self.e);
+        jwk.p = to_dom_string(&self.p);
+        jwk.q = to_dom_string(&self.q);
+        jwk.dp = to_dom_string(&self.dp);
+        jwk.dq = to_dom_string(&self.dq);
+        jwk.qi = to_dom_string(&self.qi);
+        jwk.oth = self.oth.as_ref().map(|oth| {
+            oth.iter()
+                .map(|info| RsaOtherPrimesInfo {
+                    r: to_dom_string(&info.r),
+                    d: to_dom_string(&info.d),
+                    t: to_dom_string(&info.t),
+                })
+                .collect()
+        });
+        jwk.k = to_dom_string(&self.k);
+        jwk
+    }
+
+    /// Performs the checks shared by the JWK import steps of all algorithms.
+    fn check(
+        &self,
+        kty: &str,
+        expected_use: &str,
+        extractable: bool,
+        usages: &[KeyUsage],
+    ) -> Fallible<()> {
+        if self.kty.as_ref().map(|kty| kty.as_str()) != Some(kty) {
+            return Err(Error::Data);
+        }
+        if !usages.is_empty() {
+            if let Some(ref use_) = self.use_ {
+                if use_ != expected_use {
+                    return Err(Error::Data);
+                }
+            }
+        }
+        if let Some(ref key_ops) = self.key_ops {
+            if usages
+                .iter()
+                .any(|usage| !key_ops.iter().any(|op| op == usage.as_str()))
+            {
+                return Err(Error::Data);
+            }
+        }
+        if self.ext == Some(false) && extractable {
+            return Err(Error::Data);
+        }
+        Ok(())
+    }
+
+    /// Checks that the `alg` member, if present, is `expected`.
+    fn check_alg(&self, expected: &str) -> Fallible<()> {
+        match self.alg {
+            Some(ref alg) if alg != expected => Err(Error::Data),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The data a key is imported from.
+enum KeyMaterial {
+    Bytes(Vec<u8>),
+    Jwk(Jwk),
+}
+
+lazy_static! {
+    /// The threads shared by every `SubtleCrypto` of this process to perform
+    /// cryptographic operations in parallel.
+    static ref WEBCRYPTO_THREAD_POOL: rayon::ThreadPool = rayon::ThreadPoolBuilder::new()
+        .num_threads(WEBCRYPTO_THREAD_COUNT)
+        .thread_name(|index| format!("WebCrypto#{}", index))
+        .build()
+        .expect("Failed to create the WebCrypto thread pool");
+}
+
+/// The number of threads in `WEBCRYPTO_THREAD_POOL`.
+const WEBCRYPTO_THREAD_COUNT: usize = 4;
+
+/// The value a promise returned by `SubtleCrypto` is resolved with, which is
+/// created on the script thread once the operation is done.
+enum OperationResult {
+    Bytes(Vec<u8>),
+    Boolean(bool),
+    Key(KeyData),
+    KeyPair {
+        public_key: KeyData,
+        private_key: KeyData,
+    },
+    Jwk(Jwk),
+}
+
+/// <https://w3c.github.io/webcrypto/#subtlecrypto-interface>
+#[dom_struct]
+pub struct SubtleCrypto {
+    reflector_: Reflector,
+}
+
+impl SubtleCrypto {
+    fn new_inherited() -> SubtleCrypto {
+        SubtleCrypto {
+            reflector_: Reflector::new(),
+        }
+    }
+
+    pub fn new(global: &GlobalScope) -> DomRoot<SubtleCrypto> {
+        reflect_dom_object(Box::new(SubtleCrypto::new_inherited()), global)
+    }
+
+    /// Runs `operation` in parallel, then queues a task to settle `promise`
+    /// with its result.
+    fn perform_in_parallel<F>(&self, promise: &Rc<Promise>, operation: F)
+    where
+        F: FnOnce() -> Fallible<OperationResult> + Send + 'static,
+    {
+        let global = self.global();
+        let task_source = global.dom_manipulation_task_source();
+        let canceller = global.task_canceller(TaskSourceName::DOMManipulation);
+        let trusted_promise = TrustedPromise::new(promise.clone());
+        WEBCRYPTO_THREAD_POOL.spawn(move || {
+            let result = operation();
+            let _ = task_source.queue_with_canceller(
+                task!(settle_crypto_promise: move || {
+                    settle_promise(&trusted_promise.root(), result);
+                }),
+                &canceller,
+            );
+        });
+    }
+}
+
+#[allow(unsafe_code, unrooted_must_root)]
+fn settle_promise(promise: &Promise, result: Fallible<OperationResult>) {
+    let result = match result {
+        Ok(result) => result,
+        Err(error) => return promise.reject_error(error),
+    };
+    let global = promise.global();
+    let cx = global.get_cx();
+    let _ac = enter_realm(&*global);
+    match result {
+        OperationResult::Bytes(bytes) => {
+            rooted!(in(*cx) let mut array_buffer = ptr::null_mut::<JSObject>());
+            unsafe {
+                assert!(ArrayBuffer::create(
+                    *cx,
+                    CreateWith::Slice(&bytes),
+                    array_buffer.handle_mut()
+                )
+                .is_ok());
+            }
+            rooted!(in(*cx) let value = ObjectValue(array_buffer.get()));
+            promise.resolve(cx, value.handle());
+        },
+        OperationResult::Boolean(value) => promise.resolve_native(&value),
+        OperationResult::Key(key) => promise.resolve_native(&CryptoKey::new(&global, key)),
+        OperationResult::KeyPair {
+            public_key,
+            private_key,
+        } => {
+            let mut pair = CryptoKeyPair::empty();
+            pair.publicKey = Some(CryptoKey::new(&global, public_key));
+            pair.privateKey = Some(CryptoKey::new(&global, private_key));
+            promise.resolve_native(&*pair);
+        },
+        OperationResult::Jwk(jwk) => promise.resolve_native(&jwk.to_dictionary()),
+    }
+}
+
+/// Rejects `$promise` with the error of `$result` and returns it from the
+/// enclosing method if `$result` is an error.
+macro_rules! try_or_reject {
+    ($promise:expr, $result:expr) => {
+        match $result {
+            Ok(value) => value,
+            Err(error) => {
+                $promise.reject_error(error);
+                return $promise;
+            },
+        }
+    };
+}
+
+impl SubtleCryptoMethods for SubtleCrypto {
+    /// <https://w3c.github.io/webcrypto/#dfn-SubtleCrypto-method-encrypt>
+    fn Encrypt(
+        &self,
+        cx: JSContext,
+        algorithm: AlgorithmIdentifier,
+        key: &CryptoKey,
+        data: BufferSource,
+        comp: InRealm,
+    ) -> Rc<Promise> {
+        // Steps 1-3.
+        let data = buffer_source_to_vec(&data);
+        let promise = Promise::new_in_current_realm(&self.global(), comp);
+        let normalized = try_or_reject!(
+            promise,
+            normalize_algorithm(cx, &algorithm, Operation::Encrypt)
+        );
+
+        // Steps 4-10.
+        let key = key.data();
+        self.perform_in_parallel(&promise, move || {
+            check_key(&key, normalized.name(), KeyUsage::Encrypt)?;
+            encrypt(&normalized, &key, &data).map(OperationResult::Bytes)
+        });
+        promise
+    }
+
+    /// <https://w3c.github.io/webcrypto/#dfn-SubtleCrypto-method-decrypt>
+    fn Decrypt(
+        &self,
+        cx: JSContext,
+        algorithm: AlgorithmIdentifier,
+        key: &CryptoKey,
+        data: BufferSource,
+        comp: InRealm,
+    ) -> Rc<Promise> {
+        // Steps 1-3.
+        let data = buffer_source_to_vec(&data);
+        let promise = Promise::new_in_current_realm(&self.global(), comp);
+        let normalized = try_or_reject!(
+            promise,
+            normalize_algorithm(cx, &algorithm, Operation::Decrypt)
+        );
+
+        // Steps 4-10.
+        let key = key.data();
+        self.perform_in_parallel(&promise, move || {
+            check_key(&key, normalized.name(), KeyUsage::Decrypt)?;
+            decrypt(&normalized, &key, &data).map(OperationResult::Bytes)
+        });
+        promise
+    }
+
+    /// <https://w3c.github.io/webcrypto/#dfn-SubtleCrypto-method-sign>
+    fn Sign(
+        &self,
+        cx: JSContext,
+        algorithm: AlgorithmIdentifier,
+        key: &CryptoKey,
+        data: BufferSource,
+        comp: InRealm,
+    ) -> Rc<Promise> {
+        // Steps 1-3.
+        let data = buffer_source_to_vec(&data);
+        let promise = Promise::new_in_current_realm(&self.global(), comp);
+        let normalized = try_or_reject!(
+            promise,
+            normalize_algorithm(cx, &algorithm, Operation::Sign)
+        );
+
+        // Steps 4-10.
+        let key = key.data();
+        self.perform_in_parallel(&promise, move || {
+            check_key(&key, normalized.name(), KeyUsage::Sign)?;
+            sign(&normalized, &key, &data).map(OperationResult::Bytes)
+        });
+        promise
+    }
+
+    /// <https://w3c.github.io/webcrypto/#dfn-SubtleCrypto-method-verify>
+    fn Verify(
+        &self,
+        cx: JSContext,
+        algorithm: AlgorithmIdentifier,
+        key: &CryptoKey,
+        signature: BufferSource,
+        data: BufferSource,
+        comp: InRealm,
+    ) -> Rc<Promise> {
+        // Steps 1-4.
+        let signature = buffer_source_to_vec(&signature);
+        let data = buffer_source_to_vec(&data);
+        let promise = Promise::new_in_current_realm(&self.global(), comp);
+        let normalized = try_or_reject!(
+            promise,
+            normalize_algorithm(cx, &algorithm, Operation::Verify)
+        );
+
+        // Steps 5-11.
+        let key = key.data();
+        self.perform_in_parallel(&promise, move || {
+            check_key(&key, normalized.name(), KeyUsage::Verify)?;
+            verify(&normalized, &key, &signature, &data).map(OperationResult::Boolean)
+        });
+        promise
+    }
+
+    /// <https://w3c.github.io/webcrypto/#dfn-SubtleCrypto-method-digest>
+    fn Digest(
+        &self,
+        cx: JSContext,
+        algorithm: AlgorithmIdentifier,
+        data: BufferSource,
+        comp: InRealm,
+    ) -> Rc<Promise> {
+        // Steps 1-3.
+        let data = buffer_source_to_vec(&data);
+        let promise = Promise::new_in_current_realm(&self.global(), comp);
+        let normalized = try_or_reject!(
+            promise,
+            normalize_algorithm(cx, &algorithm, Operation::Digest)
+        );
+
+        // Steps 4-8.
+        self.perform_in_parallel(&promise, move || {
+            let digest =
+                hash::hash(normalized.name().message_digest(), &data).map_err(operation_error)?;
+            Ok(OperationResult::Bytes(digest.to_vec()))
+        });
+        promise
+    }
+
+    /// <https://w3c.github.io/webcrypto/#dfn-SubtleCrypto-method-generateKey>
+    fn GenerateKey(
+        &self,
+        cx: JSContext,
+        algorithm: AlgorithmIdentifier,
+        extractable: bool,
+        key_usages: Vec<KeyUsage>,
+        comp: InRealm,
+    ) -> Rc<Promise> {
+        // Steps 1-3.
+        let promise = Promise::new_in_current_realm(&self.global(), comp);
+        let normalized = try_or_reject!(
+            promise,
+            normalize_algorithm(cx, &algorithm, Operation::GenerateKey)
+        );
+
+        // Steps 4-9.
+        self.perform_in_parallel(&promise, move || {
+            let result = generate_key(&normalized, extractable, &key_usages)?;
+            match result {
+                OperationResult::Key(ref key) if key.usages.is_empty() => Err(Error::Syntax),
+                OperationResult::KeyPair {
+                    ref private_key, ..
+                } if private_key.usages.is_empty() => Err(Error::Syntax),
+                result => Ok(result),
+            }
+        });
+        promise
+    }
+
+    /// <https://w3c.github.io/webcrypto/#dfn-SubtleCrypto-method-deriveKey>
+    fn DeriveKey(
+        &self,
+        cx: JSContext,
+        algorithm: AlgorithmIdentifier,
+        base_key: &CryptoKey,
+        derived_key_type: AlgorithmIdentifier,
+        extractable: bool,
+        key_usages: Vec<KeyUsage>,
+        comp: InRealm,
+    ) -> Rc<Promise> {
+        // Steps 1-7.
+        let promise = Promise::new_in_current_realm(&self.global(), comp);
+        let normalized = try_or_reject!(
+            promise,
+            normalize_algorithm(cx, &algorithm, Operation::DeriveBits)
+        );
+        let normalized_derived_key_algorithm_import = try_or_reject!(
+            promise,
+            normalize_algorithm(cx, &derived_key_type, Operation::ImportKey)
+        );
+        let normalized_derived_key_algorithm_length = try_or_reject!(
+            promise,
+            normalize_algorithm(cx, &derived_key_type, Operation::GetKeyLength)
+        );
+
+        // Steps 8-16.
+        let base_key = base_key.data();
+        self.perform_in_parallel(&promise, move || {
+            check_key(&base_key, normalized.name(), KeyUsage::DeriveKey)?;
+            let length = get_key_length(&normalized_derived_key_algorithm_length)?;
+            let secret = derive_bits(&normalized, &base_key, length)?;
+            let key = import_key(
+                KeyFormat::Raw,
+                KeyMaterial::Bytes(secret),
+                &normalized_derived_key_algorithm_import,
+                extractable,
+                &key_usages,
+            )?;
+            if key.key_type != KeyType::Public && key.usages.is_empty() {
+                return Err(Error::Syntax);
+            }
+            Ok(OperationResult::Key(key))
+        });
+        promise
+    }
+
+    /// <https://w3c.github.io/webcrypto/#dfn-SubtleCrypto-method-deriveBits>
+    fn DeriveBits(
+        &self,
+        cx: JSContext,
+        algorithm: AlgorithmIdentifier,
+        base_key: &CryptoKey,
+        length: Option<u32>,
+        comp: InRealm,
+    ) -> Rc<Promise> {
+        // Steps 1-4.
+        let promise = Promise::new_in_current_realm(&self.global(), comp);
+        let normalized = try_or_reject!(
+            promise,
+            normalize_algorithm(cx, &algorithm, Operation::DeriveBits)
+        );
+
+        // Steps 5-10.
+        let base_key = base_key.data();
+        self.perform_in_parallel(&promise, move || {
+            check_key(&base_key, normalized.name(), KeyUsage::DeriveBits)?;
+            derive_bits(&normalized, &base_key, length).map(OperationResult::Bytes)
+        });
+        promise
+    }
+
+    /// <https://w3c.github.io/webcrypto/#dfn-SubtleCrypto-method-importKey>
+    fn ImportKey(
+        &self,
+        cx: JSContext,
+        format: KeyFormat,
+        key_data: ArrayBufferViewOrArrayBufferOrJsonWebKey,
+        algorithm: AlgorithmIdentifier,
+        extractable: bool,
+        key_usages: Vec<KeyUsage>,
+        comp: InRealm,
+    ) -> Rc<Promise> {
+        // Steps 1-3.
+        let promise = Promise::new_in_current_realm(&self.global(), comp);
+        let normalized = try_or_reject!(
+            promise,
+            normalize_algorithm(cx, &algorithm, Operation::ImportKey)
+        );
+
+        // Step 4.
+        let key_data = match (format, key_data) {
+            (KeyFormat::Jwk, ArrayBufferViewOrArrayBufferOrJsonWebKey::JsonWebKey(jwk)) => {
+                KeyMaterial::Jwk(Jwk::from_dictionary(&jwk))
+            },
+            (KeyFormat::Jwk, _) | (_, ArrayBufferViewOrArrayBufferOrJsonWebKey::JsonWebKey(_)) => {
+                promise.reject_error(Error::Type("Invalid key data for the format".to_owned()));
+                return promise;
+            },
+            (_, ArrayBufferViewOrArrayBufferOrJsonWebKey::ArrayBufferView(view)) => {
+                KeyMaterial::Bytes(view.to_vec())
+            },
+            (_, ArrayBufferViewOrArrayBufferOrJsonWebKey::ArrayBuffer(buffer)) => {
+                KeyMaterial::Bytes(buffer.to_vec())
+            },
+        };
+
+        // Steps 5-10.
+        self.perform_in_parallel(&promise, move || {
+            let key = import_key(format, key_data, &normalized, extractable, &key_usages)?;
+            if key.key_type != KeyType::Public && key.usages.is_empty() {
+                return Err(Error::Syntax);
+            }
+            Ok(OperationResult::Key(key))
+        });
+        promise
+    }
+
+    /// <https://w3c.github.io/webcrypto/#dfn-SubtleCrypto-method-exportKey>
+    fn ExportKey(&self, format: KeyFormat, key: &CryptoKey, comp: InRealm) -> Rc<Promise> {
+        // Steps 1-2.
+        let promise = Promise::new_in_current_realm(&self.global(), comp);
+
+        // Steps 3-7.
+        let key = key.data();
+        self.perform_in_parallel(&promise, move || {
+            if !key.extractable {
+                return Err(Error::InvalidAccess);
+            }
+            Ok(match export_key(format, &key)? {
+                KeyMaterial::Bytes(bytes) => OperationResult::Bytes(bytes),
+                KeyMaterial::Jwk(jwk) => OperationResult::Jwk(jwk),
+            })
+        });
+        promise
+    }
+
+    /// <https://w3c.github.io/webcrypto/#dfn-SubtleCrypto-method-wrapKey>
+    fn WrapKey(
+        &self,
+        cx: JSContext,
+        format: KeyFormat,
+        key: &CryptoKey,
+        wrapping_key: &CryptoKey,
+        wrap_algorithm: AlgorithmIdentifier,
+        comp: InRealm,
+    ) -> Rc<Promise> {
+        // Steps 1-5.
+        let promise = Promise::new_in_current_realm(&self.global(), comp);
+        let normalized = try_or_reject!(
+            promise,
+            normalize_algorithm(cx, &wrap_algorithm, Operation::WrapKey)
+                .or_else(|_| normalize_algorithm(cx, &wrap_algorithm, Operation::Encrypt))
+        );
+
+        // Steps 6-14.
+        let key = key.data();
+        let wrapping_key = wrapping_key.data();
+        self.perform_in_parallel(&promise, move || {
+            check_key(&wrapping_key, normalized.name(), KeyUsage::WrapKey)?;
+            if !key.extractable {
+                return Err(Error::InvalidAccess);
+            }
+            let bytes = match export_key(format, &key)? {
+                KeyMaterial::Bytes(bytes) => bytes,
+                KeyMaterial::Jwk(jwk) => serde_json::to_vec(&jwk).map_err(|_| Error::Operation)?,
+            };
+            let wrapped = match normalized {
+                NormalizedAlgorithm::Algorithm(CryptoAlgorithm::AesKw) => {
+                    aes_kw_wrap(secret_key(&wrapping_key)?, &bytes)?
+                },
+                _ => encrypt(&normalized, &wrapping_key, &bytes)?,
+            };
+            Ok(OperationResult::Bytes(wrapped))
+        });
+        promise
+    }
+
+    /// <https://w3c.github.io/webcrypto/#dfn-SubtleCrypto-method-unwrapKey>
+    fn UnwrapKey(
+        &self,
+        cx: JSContext,
+        format: KeyFormat,
+        wrapped_key: BufferSource,
+        unwrapping_key: &CryptoKey,
+        unwrap_algorithm: AlgorithmIdentifier,
+        unwrapped_key_algorithm: AlgorithmIdentifier,
+        extractable: bool,
+        key_usages: Vec<KeyUsage>,
+        comp: InRealm,
+    ) -> Rc<Promise> {
+        // Steps 1-8.
+        let wrapped_key = buffer_source_to_vec(&wrapped_key);
+        let promise = Promise::new_in_current_realm(&self.global(), comp);
+        let normalized = try_or_reject!(
+            promise,
+            normalize_algorithm(cx, &unwrap_algorithm, Operation::UnwrapKey)
+                .or_else(|_| normalize_algorithm(cx, &unwrap_algorithm, Operation::Decrypt))
+        );
+        let normalized_key_algorithm = try_or_reject!(
+            promise,
+            normalize_algorithm(cx, &unwrapped_key_algorithm, Operation::ImportKey)
+        );
+
+        // Steps 9-17.
+        let unwrapping_key = unwrapping_key.data();
+        self.perform_in_parallel(&promise, move || {
+            check_key(&unwrapping_key, normalized.name(), KeyUsage::UnwrapKey)?;
+            let bytes = match normalized {
+                NormalizedAlgorithm::Algorithm(CryptoAlgorithm::AesKw) => {
+                    aes_kw_unwrap(secret_key(&unwrapping_key)?, &wrapped_key)?
+                },
+                _ => decrypt(&normalized, &unwrapping_key, &wrapped_key)?,
+            };
+            let key_data = match format {
+                KeyFormat::Jwk => {
+                    KeyMaterial::Jwk(serde_json::from_slice(&bytes).map_err(|_| Error::Data)?)
+                },
+                _ => KeyMaterial::Bytes(bytes),
+            };
+            let key = import_key(
+                format,
+                key_data,
+                &normalized_key_algorithm,
+                extractable,
+                &key_usages,
+            )?;
+            if key.key_type != KeyType::Public && key.usages.is_empty() {
+                return Err(Error::Syntax);
+            }
+            Ok(OperationResult::Key(key))
+        });
+        promise
+    }
+}
+
+/// The encrypt operations of RSA-OAEP, AES-CTR, AES-CBC and AES-GCM.
+fn encrypt(normalized: &NormalizedAlgorithm, key: &KeyData, data: &[u8]) -> Fallible<Vec<u8>> {
+    match *normalized {
+        // https://w3c.github.io/webcrypto/#rsa-oaep-operations
+        NormalizedAlgorithm::RsaOaep { ref label } => {
+            let digest = key_hash(key)?.message_digest();
+            let mut encrypter = Encrypter::new(public_key(key)?).map_err(operation_error)?;
+            encrypter
+                .set_rsa_padding(Padding::PKCS1_OAEP)
+                .and_then(|_| encrypter.set_rsa_oaep_md(digest))
+                .and_then(|_| encrypter.set_rsa_mgf1_md(digest))
+                .map_err(operation_error)?;
+            if let Some(ref label) = *label {
+                encrypter
+                    .set_rsa_oaep_label(label)
+                    .map_err(operation_error)?;
+            }
+            let mut output = vec![0; encrypter.encrypt_len(data).map_err(operation_error)?];
+            let length = encrypter
+                .encrypt(data, &mut output)
+                .map_err(operation_error)?;
+            output.truncate(length);
+            Ok(output)
+        },
+        // https://w3c.github.io/webcrypto/#aes-ctr-operations
+        NormalizedAlgorithm::AesCtr {
+            ref counter,
+            length,
+        } => aes_ctr(secret_key(key)?, counter, length, data),
+        // https://w3c.github.io/webcrypto/#aes-cbc-operations
+        NormalizedAlgorithm::AesCbc { ref iv } => {
+            if iv.len() != 16 {
+                return Err(Error::Operation);
+            }
+            let key = secret_key(key)?;
+            symm::encrypt(aes_cbc_cipher(key)?, key, Some(iv), data).map_err(operation_error)
+        },
+        // https://w3c.github.io/webcrypto/#aes-gcm-operations
+        NormalizedAlgorithm::AesGcm {
+            ref iv,
+            ref additional_data,
+            tag_length,
+        } => {
+            let tag_length = aes_gcm_tag_length(tag_length)?;
+            let key = secret_key(key)?;
+            let mut tag = vec![0; tag_length];
+            let mut output = symm::encrypt_aead(
+                aes_gcm_cipher(key)?,
+                key,
+                Some(iv),
+                additional_data.as_ref().map_or(&[], |data| data.as_slice()),
+                data,
+                &mut tag,
+            )
+            .map_err(operation_error)?;
+            output.extend(tag);
+            Ok(output)
+        },
+        _ => Err(Error::NotSupported),
+    }
+}
+
+/// The decrypt operations of RSA-OAEP, AES-CTR, AES-CBC and AES-GCM.
+fn decrypt(normalized: &NormalizedAlgorithm, key: &KeyData, data: &[u8]) -> Fallible<Vec<u8>> {
+    match *normalized {
+        // https://w3c.github.io/webcrypto/#rsa-oaep-operations
+        NormalizedAlgorithm::RsaOaep { ref label } => {
+            let digest = key_hash(key)?.message_digest();
+            let mut decrypter = Decrypter::new(private_key(key)?).map_err(operation_error)?;
+            decrypter
+                .set_rsa_padding(Padding::PKCS1_OAEP)
+                .and_then(|_| decrypter.set_rsa_oaep_md(digest))
+                .and_then(|_| decrypter.set_rsa_mgf1_md(digest))
+                .map_err(operation_error)?;
+            if let Some(ref label) = *label {
+                decrypter
+                    .set_rsa_oaep_label(label)
+                    .map_err(operation_error)?;
+            }
+            let mut output = vec![0; decrypter.decrypt_len(data).map_err(operation_error)?];
+            let length = decrypter
+                .decrypt(data, &mut output)
+                .map_err(operation_error)?;
+            output.truncate(length);
+            Ok(output)
+        },
+        // https://w3c.github.io/webcrypto/#aes-ctr-operations
+        NormalizedAlgorithm::AesCtr {
+            ref counter,
+            length,
+        } => aes_ctr(secret_key(key)?, counter, length, data),
+        // https://w3c.github.io/webcrypto/#aes-cbc-operations
+        NormalizedAlgorithm::AesCbc { ref iv } => {
+            if iv.len() != 16 {
+                return Err(Error::Operation);
+            }
+            let key = secret_key(key)?;
+            symm::decrypt(aes_cbc_cipher(key)?, key, Some(iv), data).map_err(operation_error)
+        },
+        // https://w3c.github.io/webcrypto/#aes-gcm-operations
+        NormalizedAlgorithm::AesGcm {
+            ref iv,
+            ref additional_data,
+            tag_length,
+        } => {
+            let tag_length = aes_gcm_tag_length(tag_length)?;
+            if data.len() < tag_length {
+                return Err(Error::Operation);
+            }
+            let key = secret_key(key)?;
+            let (ciphertext, tag) = data.split_at(data.len() - tag_length);
+            symm::decrypt_aead(
+                aes_gcm_cipher(key)?,
+                key,
+                Some(iv),
+                additional_data.as_ref().map_or(&[], |data| data.as_slice()),
+                ciphertext,
+                tag,
+            )
+            .map_err(operation_error)
+        },
+        _ => Err(Error::NotSupported),
+    }
+}
+
+fn aes_cbc_cipher(key: &[u8]) -> Fallible<Cipher> {
+    match key.len() {
+        16 => Ok(Cipher::aes_128_cbc()),
+        24 => Ok(Cipher::aes_192_cbc()),
+        32 => Ok(Cipher::aes_256_cbc()),
+        _ => Err(Error::Operation),
+    }
+}
+
+fn aes_gcm_cipher(key: &[u8]) -> Fallible<Cipher> {
+    match key.len() {
+        16 => Ok(Cipher::aes_128_gcm()),
+        24 => Ok(Cipher::aes_192_gcm()),
+        32 => Ok(Cipher::aes_256_gcm()),
+        _ => Err(Error::Operation),
+    }
+}
+
+/// The length in bytes of the authentication tag of AES-GCM.
+fn aes_gcm_tag_length(tag_length: Option<u8>) -> Fallible<usize> {
+    match tag_length.unwrap_or(128) {
+        length @ 32 |
+        length @ 64 |
+        length @ 96 |
+        length @ 104 |
+        length @ 112 |
+        length @ 120 |
+        length @ 128 => Ok(length as usize / 8),
+        _ => Err(Error::Operation),
+    }
+}
+
+/// Encrypts or decrypts `data` with AES-CTR, where only the rightmost
+/// `length` bits of the counter block are incremented.
+fn aes_ctr(key: &[u8], counter: &[u8], length: u8, data: &[u8]) -> Fallible<Vec<u8>> {
+    if counter.len() != 16 || length == 0 || length > 128 {
+        return Err(Error::Operation);
+    }
+    let cipher = match key.len() {
+        16 => Cipher::aes_128_ctr(),
+        24 => Cipher::aes_192_ctr(),
+        32 => Cipher::aes_256_ctr(),
+        _ => return Err(Error::Operation),
+    };
+
+    let mut counter_block = [0; 16];
+    counter_block.copy_from_slice(counter);
+    let counter = u128::from_be_bytes(counter_block);
+    let mask = if length == 128 {
+        u128::max_value()
+    } else {
+        (1 << length) - 1
+    };
+
+    // The counter must not repeat.
+    let blocks = ((data.len() + 15) / 16) as u128;
+    if length < 128 && blocks > 1 << length {
+        return Err(Error::Operation);
+    }
+
+    // OpenSSL increments the whole counter block, so the data after the
+    // counter bits wrap around is processed separately.
+    let blocks_before_wrap = (mask - (counter & mask)).saturating_add(1);
+    let split = cmp::min(blocks_before_wrap, blocks) as usize * 16;
+    let split = cmp::min(split, data.len());
+    let mut output = symm::encrypt(cipher, key, Some(counter_block.as_ref()), &data[..split])
+        .map_err(operation_error)?;
+    if split < data.len() {
+        let wrapped_counter = (counter & !mask).to_be_bytes();
+        output.extend(
+            symm::encrypt(cipher, key, Some(wrapped_counter.as_ref()), &data[split..])
+                .map_err(operation_error)?,
+        );
+    }
+    Ok(output)
+}
+
+/// <https://w3c.github.io/webcrypto/#aes-kw-operations>
+fn aes_kw_wrap(key: &[u8], data: &[u8]) -> Fallible<Vec<u8>> {
+    if data.len() % 8 != 0 || data.len() < 16 {
+        return Err(Error::Operation);
+    }
+    let key = AesKey::new_encrypt(key).map_err(|_| Error::Operation)?;
+    let mut output = vec![0; data.len() + 8];
+    aes::wrap_key(&key, None, &mut output, data).map_err(|_| Error::Operation)?;
+    Ok(output)
+}
+
+/// <https://w3c.github.io/webcrypto/#aes-kw-operations>
+fn aes_kw_unwrap(key: &[u8], data: &[u8]) -> Fallible<Vec<u8>> {
+    if data.len() % 8 != 0 || data.len() < 24 {
+        return Err(Error::Operation);
+    }
+    let key = AesKey::new_decrypt(key).map_err(|_| Error::Operation)?;
+    let mut output = vec![0; data.len() - 8];
+    aes::unwrap_key(&key, None, &mut output, data).map_err(|_| Error::Operation)?;
+    Ok(output)
+}
+
+/// The sign operations of RSASSA-PKCS1-v1_5, RSA-PSS, ECDSA and HMAC.
+fn sign(normalized: &NormalizedAlgorithm, key: &KeyData, data: &[u8]) -> Fallible<Vec<u8>> {
+    match *normalized {
+        // https://w3c.github.io/webcrypto/#rsassa-pkcs1-operations
+        NormalizedAlgorithm::Algorithm(CryptoAlgorithm::RsassaPkcs1V15) => {
+            let digest = key_hash(key)?.message_digest();
+            let mut signer = Signer::new(digest, private_key(key)?).map_err(operation_error)?;
+            signer.update(data).map_err(operation_error)?;
+            signer.sign_to_vec().map_err(operation_error)
+        },
+        // https://w3c.github.io/webcrypto/#rsa-pss-operations
+        NormalizedAlgorithm::RsaPss { salt_length } => {
+            let digest = key_hash(key)?.message_digest();
+            let mut signer = Signer::new(digest, private_key(key)?).map_err(operation_error)?;
+            signer
+                .set_rsa_padding(Padding::PKCS1_PSS)
+                .and_then(|_| signer.set_rsa_pss_saltlen(RsaPssSaltlen::custom(salt_length as i32)))
+                .and_then(|_| signer.set_rsa_mgf1_md(digest))
+                .and_then(|_| signer.update(data))
+                .map_err(operation_error)?;
+            signer.sign_to_vec().map_err(operation_error)
+        },
+        // https://w3c.github.io/webcrypto/#ecdsa-operations
+        NormalizedAlgorithm::Ecdsa { hash } => {
+            let length = key_named_curve(key)?.coordinate_length();
+            let ec_key = private_key(key)?.ec_key().map_err(operation_error)?;
+            let digest = hash::hash(hash.message_digest(), data).map_err(operation_error)?;
+            let signature = EcdsaSig::sign(&digest, &ec_key).map_err(operation_error)?;
+            let mut output = pad_bytes(signature.r().to_vec(), length);
+            output.extend(pad_bytes(signature.s().to_vec(), length));
+            Ok(output)
+        },
+        // https://w3c.github.io/webcrypto/#hmac-operations
+        NormalizedAlgorithm::Algorithm(CryptoAlgorithm::Hmac) => {
+            hmac(key_hash(key)?.message_digest(), secret_key(key)?, data).map_err(operation_error)
+        },
+        _ => Err(Error::NotSupported),
+    }
+}
+
+/// The verify operations of RSASSA-PKCS1-v1_5, RSA-PSS, ECDSA and HMAC.
+fn verify(
+    normalized: &NormalizedAlgorithm,
+    key: &KeyData,
+    signature: &[u8],
+    data: &[u8],
+) -> Fallible<bool> {
+    match *normalized {
+        // https://w3c.github.io/webcrypto/#rsassa-pkcs1-operations
+        NormalizedAlgorithm::Algorithm(CryptoAlgorithm::RsassaPkcs1V15) => {
+            let digest = key_hash(key)?.message_digest();
+            let mut verifier = Verifier::new(digest, public_key(key)?).map_err(operation_error)?;
+            verifier.update(data).map_err(operation_error)?;
+            Ok(verifier.verify(signature).unwrap_or(false))
+        },
+        // https://w3c.github.io/webcrypto/#rsa-pss-operations
+        NormalizedAlgorithm::RsaPss { salt_length } => {
+            let digest = key_hash(key)?.message_digest();
+            let mut verifier = Verifier::new(digest, public_key(key)?).map_err(operation_error)?;
+            verifier
+                .set_rsa_padding(Padding::PKCS1_PSS)
+                .and_then(|_| {
+                    verifier.set_rsa_pss_saltlen(RsaPssSaltlen::custom(salt_length as i32))
+                })
+                .and_then(|_| verifier.set_rsa_mgf1_md(digest))
+                .and_then(|_| verifier.update(data))
+                .map_err(operation_error)?;
+            Ok(verifier.verify(signature).unwrap_or(false))
+        },
+        // https://w3c.github.io/webcrypto/#ecdsa-operations
+        NormalizedAlgorithm::Ecdsa { hash } => {
+            let length = key_named_curve(key)?.coordinate_length();
+            let ec_key = public_key(key)?.ec_key().map_err(operation_error)?;
+            if signature.len() != 2 * length {
+                return Ok(false);
+            }
+            let digest = hash::hash(hash.message_digest(), data).map_err(operation_error)?;
+            let (r, s) = signature.split_at(length);
+            let signature = BigNum::from_slice(r)
+                .and_then(|r| BigNum::from_slice(s).map(|s| (r, s)))
+                .and_then(|(r, s)| EcdsaSig::from_private_components(r, s))
+                .map_err(operation_error)?;
+            Ok(signature.verify(&digest, &ec_key).unwrap_or(false))
+        },
+        // https://w3c.github.io/webcrypto/#hmac-operations
+        NormalizedAlgorithm::Algorithm(CryptoAlgorithm::Hmac) => {
+            let mac = hmac(key_hash(key)?.message_digest(), secret_key(key)?, data)
+                .map_err(operation_error)?;
+            Ok(mac.len() == signature.len() && memcmp::eq(&mac, signature))
+        },
+        _ => Err(Error::NotSupported),
+    }
+}
+
+fn hmac(digest: MessageDigest, key: &[u8], data: &[u8]) -> Result<Vec<u8>, ErrorStack> {
+    let key = PKey::hmac(key)?;
+    let mut signer = Signer::new(digest, &key)?;
+    signer.update(data)?;
+    signer.sign_to_vec()
+}
+
+/// The generate key operations of all algorithms that support it.
+fn generate_key(
+    normalized: &NormalizedAlgorithm,
+    extractable: bool,
+    usages: &[KeyUsage],
+) -> Fallible<OperationResult> {
+    match *normalized {
+        // https://w3c.github.io/webcrypto/#rsassa-pkcs1-operations
+        // https://w3c.github.io/webcrypto/#rsa-pss-operations
+        // https://w3c.github.io/webcrypto/#rsa-oaep-operations
+        NormalizedAlgorithm::RsaHashedKeyGen {
+            name,
+            modulus_length,
+            ref public_exponent,
+            hash,
+        } => {
+            let (public_usages, private_usages) = rsa_usages(name);
+            check_usages(usages, &[public_usages, private_usages].concat())?;
+            let exponent = BigNum::from_slice(public_exponent).map_err(operation_error)?;
+            // OpenSSL does not reject exponents it cannot find suitable primes
+            // for, such as even ones, and keeps looking for them forever.
+            if exponent.num_bits() < 2 || !exponent.is_bit_set(0) {
+                return Err(Error::Operation);
+            }
+            let rsa = Rsa::generate_with_e(modulus_length, &exponent).map_err(operation_error)?;
+            let private = PKey::from_rsa(rsa).map_err(operation_error)?;
+            let public = public_key_of(&private).map_err(operation_error)?;
+            let algorithm = KeyAlgorithm::RsaHashed {
+                name,
+                modulus_length,
+                public_exponent: public_exponent.clone(),
+                hash,
+            };
+            Ok(OperationResult::KeyPair {
+                public_key: KeyData {
+                    key_type: KeyType::Public,
+                    extractable: true,
+                    algorithm: algorithm.clone(),
+                    usages: usage_intersection(usages, public_usages),
+                    handle: KeyHandle::Public(public),
+                },
+                private_key: KeyData {
+                    key_type: KeyType::Private,
+                    extractable,
+                    algorithm,
+                    usages: usage_intersection(usages, private_usages),
+                    handle: KeyHandle::Private(private),
+                },
+            })
+        },
+        // https://w3c.github.io/webcrypto/#ecdsa-operations
+        // https://w3c.github.io/webcrypto/#ecdh-operations
+        NormalizedAlgorithm::EcKey {
+            name,
+            ref named_curve,
+        } => {
+            let (public_usages, private_usages) = ec_usages(name);
+            check_usages(usages, &[public_usages, private_usages].concat())?;
+            let named_curve = NamedCurve::from_name(named_curve).ok_or(Error::NotSupported)?;
+            let ec_key = EcKey::generate(&named_curve.group()?).map_err(operation_error)?;
+            let private = PKey::from_ec_key(ec_key).map_err(operation_error)?;
+            let public = public_key_of(&private).map_err(operation_error)?;
+            let algorithm = KeyAlgorithm::Ec { name, named_curve };
+            Ok(OperationResult::KeyPair {
+                public_key: KeyData {
+                    key_type: KeyType::Public,
+                    extractable: true,
+                    algorithm: algorithm.clone(),
+                    usages: usage_intersection(usages, public_usages),
+                    handle: KeyHandle::Public(public),
+                },
+                private_key: KeyData {
+                    key_type: KeyType::Private,
+                    extractable,
+                    algorithm,
+                    usages: usage_intersection(usages, private_usages),
+                    handle: KeyHandle::Private(private),
+                },
+            })
+        },
+        // https://w3c.github.io/webcrypto/#aes-ctr-operations
+        // https://w3c.github.io/webcrypto/#aes-cbc-operations
+        // https://w3c.github.io/webcrypto/#aes-gcm-operations
+        // https://w3c.github.io/webcrypto/#aes-kw-operations
+        NormalizedAlgorithm::AesKeyLength { name, length } => {
+            let allowed_usages = aes_usages(name);
+            check_usages(usages, allowed_usages)?;
+            if !matches!(length, 128 | 192 | 256) {
+                return Err(Error::Operation);
+            }
+            Ok(OperationResult::Key(KeyData {
+                key_type: KeyType::Secret,
+                extractable,
+                algorithm: KeyAlgorithm::Aes { name, length },
+                usages: usage_intersection(usages, allowed_usages),
+                handle: KeyHandle::Secret(random_bytes(length as usize / 8)),
+            }))
+        },
+        // https://w3c.github.io/webcrypto/#hmac-operations
+        NormalizedAlgorithm::Hmac { hash, length } => {
+            check_usages(usages, HMAC_USAGES)?;
+            let length = match length {
+                Some(0) => return Err(Error::Operation),
+                Some(length) => length,
+                None => hash.block_size(),
+            };
+            Ok(OperationResult::Key(KeyData {
+                key_type: KeyType::Secret,
+                extractable,
+                algorithm: KeyAlgorithm::Hmac { hash, length },
+                usages: usage_intersection(usages, HMAC_USAGES),
+                handle: KeyHandle::Secret(random_bytes((length as usize + 7) / 8)),
+            }))
+        },
+        _ => Err(Error::NotSupported),
+    }
+}
+
+const HMAC_USAGES: &[KeyUsage] = &[KeyUsage::Sign, KeyUsage::Verify];
+
+const KDF_USAGES: &[KeyUsage] = &[KeyUsage::DeriveKey, KeyUsage::DeriveBits];
+
+/// The usages allowed for AES keys.
+fn aes_usages(name: CryptoAlgorithm) -> &'static [KeyUsage] {
+    match name {
+        CryptoAlgorithm::AesKw => &[KeyUsage::WrapKey, KeyUsage::UnwrapKey],
+        _ => &[
+            KeyUsage::Encrypt,
+            KeyUsage::Decrypt,
+            KeyUsage::WrapKey,
+            KeyUsage::UnwrapKey,
+        ],
+    }
+}
+
+/// The usages allowed for the public and private RSA keys.
+fn rsa_usages(name: CryptoAlgorithm) -> (&'static [KeyUsage], &'static [KeyUsage]) {
+    match name {
+        CryptoAlgorithm::RsaOaep => (
+            &[KeyUsage::Encrypt, KeyUsage::WrapKey],
+            &[KeyUsage::Decrypt, KeyUsage::UnwrapKey],
+        ),
+        _ => (&[KeyUsage::Verify], &[KeyUsage::Sign]),
+    }
+}
+
+/// The usages allowed for the public and private EC keys.
+fn ec_usages(name: CryptoAlgorithm) -> (&'static [KeyUsage], &'static [KeyUsage]) {
+    match name {
+        CryptoAlgorithm::Ecdh => (&[], KDF_USAGES),
+        _ => (&[KeyUsage::Verify], &[KeyUsage::Sign]),
+    }
+}
+
+/// <https://w3c.github.io/webcrypto/#dfn-get-key-length>
+fn get_key_length(normalized: &NormalizedAlgorithm) -> Fallible<Option<u32>> {
+    match *normalized {
+        NormalizedAlgorithm::AesKeyLength { length, .. } => {
+            if !matches!(length, 128 | 192 | 256) {
+                return Err(Error::Operation);
+            }
+            Ok(Some(length as u32))
+        },
+        NormalizedAlgorithm::Hmac { hash, length } => match length {
+            Some(0) => Err(Error::Type("Invalid HMAC key length".to_owned())),
+            Some(length) => Ok(Some(length)),
+            None => Ok(Some(hash.block_size())),
+        },
+        _ => Ok(None),
+    }
+}
+
+/// The derive bits operations of ECDH, HKDF and PBKDF2.
+fn derive_bits(
+    normalized: &NormalizedAlgorithm,
+    key: &KeyData,
+    length: Option<u32>,
+) -> Fallible<Vec<u8>> {
+    match *normalized {
+        // https://w3c.github.io/webcrypto/#ecdh-operations
+        NormalizedAlgorithm::EcdhKeyDerive { ref public } => {
+            if public.key_type != KeyType::Public ||
+                public.algorithm.name() != CryptoAlgorithm::Ecdh ||
+                key_named_curve(public)? != key_named_curve(key)?
+            {
+                return Err(Error::InvalidAccess);
+            }
+            let mut deriver = Deriver::new(private_key(key)?).map_err(operation_error)?;
+            deriver
+                .set_peer(public_key(public)?)
+                .map_err(operation_error)?;
+            let mut secret = vec![0; deriver.len().map_err(operation_error)?];
+            let secret_length = deriver.derive(&mut secret).map_err(operation_error)?;
+            secret.truncate(secret_length);
+            match length {
+                None => Ok(secret),
+                Some(length) if length as usize > secret.len() * 8 => Err(Error::Operation),
+                Some(length) => {
+                    secret.truncate((length as usize + 7) / 8);
+                    if length % 8 != 0 {
+                        if let Some(last) = secret.last_mut() {
+                            *last &= 0xFF << (8 - length % 8);
+                        }
+                    }
+                    Ok(secret)
+                },
+            }
+        },
+        // https://w3c.github.io/webcrypto/#hkdf-operations
+        NormalizedAlgorithm::Hkdf {
+            hash,
+            ref salt,
+            ref info,
+        } => {
+            let length = match length {
+                Some(length) if length % 8 == 0 => length as usize / 8,
+                _ => return Err(Error::Operation),
+            };
+            let digest = hash.message_digest();
+            if length > 255 * digest.size() {
+                return Err(Error::Operation);
+            }
+            hkdf(digest, secret_key(key)?, salt, info, length).map_err(operation_error)
+        },
+        // https://w3c.github.io/webcrypto/#pbkdf2-operations
+        NormalizedAlgorithm::Pbkdf2 {
+            hash,
+            ref salt,
+            iterations,
+        } => {
+            let length = match length {
+                Some(length) if length % 8 == 0 => length as usize / 8,
+                _ => return Err(Error::Operation),
+            };
+            if iterations == 0 {
+                return Err(Error::Operation);
+            }
+            let mut output = vec![0; length];
+            if length > 0 {
+                pkcs5::pbkdf2_hmac(
+                    secret_key(key)?,
+                    salt,
+                    iterations as usize,
+                    hash.message_digest(),
+                    &mut output,
+                )
+                .map_err(operation_error)?;
+            }
+            Ok(output)
+        },
+        _ => Err(Error::NotSupported),
+    }
+}
+
+/// <https://tools.ietf.org/html/rfc5869>
+fn hkdf(
+    digest: MessageDigest,
+    key: &[u8],
+    salt: &[u8],
+    info: &[u8],
+    length: usize,
+) -> Result<Vec<u8>, ErrorStack> {
+    // Step 1, extract.
+    let default_salt = vec![0; digest.size()];
+    let salt = if salt.is_empty() { &default_salt } else { salt };
+    let pseudorandom_key = hmac(digest, salt, key)?;
+
+    // Step 2, expand.
+    let mut output = Vec::with_capacity(length);
+    let mut block = vec![];
+    let mut counter = 1u8;
+    while output.len() < length {
+        let mut input = block;
+        input.extend_from_slice(info);
+        input.push(counter);
+        block = hmac(digest, &pseudorandom_key, &input)?;
+        output.extend_from_slice(&block);
+        counter = counter.wrapping_add(1);
+    }
+    output.truncate(length);
+    Ok(output)
+}
+
+/// The import key operations of all algorithms.
+fn import_key(
+    format: KeyFormat,
+    key_data: KeyMaterial,
+    normalized: &NormalizedAlgorithm,
+    extractable: bool,
+    usages: &[KeyUsage],
+) -> Fallible<KeyData> {
+    match *normalized {
+        NormalizedAlgorithm::RsaHashedImport { name, hash } => {
+            import_rsa_key(format, key_data, name, hash, extractable, usages)
+        },
+        NormalizedAlgorithm::EcKey {
+            name,
+            ref named_curve,
+        } => {
+            let named_curve = NamedCurve::from_name(named_curve).ok_or(Error::NotSupported)?;
+            import_ec_key(format, key_data, name, named_curve, extractable, usages)
+        },
+        NormalizedAlgorithm::Hmac { hash, length } => {
+            import_hmac_key(format, key_data, hash, length, extractable, usages)
+        },
+        NormalizedAlgorithm::Algorithm(name) => match name {
+            CryptoAlgorithm::AesCtr |
+            CryptoAlgorithm::AesCbc |
+            CryptoAlgorithm::AesGcm |
+            CryptoAlgorithm::AesKw => import_aes_key(format, key_data, name, extractable, usages),
+            CryptoAlgorithm::Hkdf | CryptoAlgorithm::Pbkdf2 => {
+                import_kdf_key(format, key_data, name, extractable, usages)
+            },
+            _ => Err(Error::NotSupported),
+        },
+        _ => Err(Error::NotSupported),
+    }
+}
+
+/// <https://w3c.github.io/webcrypto/#aes-ctr-operations>, and likewise for
+/// the other AES algorithms.
+fn import_aes_key(
+    format: KeyFormat,
+    key_data: KeyMaterial,
+    name: CryptoAlgorithm,
+    extractable: bool,
+    usages: &[KeyUsage],
+) -> Fallible<KeyData> {
+    let allowed_usages = aes_usages(name);
+    check_usages(usages, allowed_usages)?;
+    let bytes = match (format, key_data) {
+        (KeyFormat::Raw, KeyMaterial::Bytes(bytes)) => bytes,
+        (KeyFormat::Jwk, KeyMaterial::Jwk(jwk)) => {
+            jwk.check("oct", "enc", extractable, usages)?;
+            let bytes = base64url_decode(&jwk.k)?;
+            jwk.check_alg(&aes_jwk_algorithm(name, bytes.len() * 8))?;
+            bytes
+        },
+        _ => return Err(Error::NotSupported),
+    };
+    if !matches!(bytes.len(), 16 | 24 | 32) {
+        return Err(Error::Data);
+    }
+    Ok(KeyData {
+        key_type: KeyType::Secret,
+        extractable,
+        algorithm: KeyAlgorithm::Aes {
+            name,
+            length: (bytes.len() * 8) as u16,
+        },
+        usages: usage_intersection(usages, allowed_usages),
+        handle: KeyHandle::Secret(bytes),
+    })
+}
+
+/// The JSON Web Algorithms name of an AES algorithm.
+fn aes_jwk_algorithm(name: CryptoAlgorithm, length: usize) -> String {
+    let mode = match name {
+        CryptoAlgorithm::AesCtr => "CTR",
+        CryptoAlgorithm::AesCbc => "CBC",
+        CryptoAlgorithm::AesGcm => "GCM",
+        _ => "KW",
+    };
+    format!("A{}{}", length, mode)
+}
+
+/// <https://w3c.github.io/webcrypto/#hmac-operations>
+fn import_hmac_key(
+    format: KeyFormat,
+    key_data: KeyMaterial,
+    hash: CryptoAlgorithm,
+    length: Option<u32>,
+    extractable: bool,
+    usages: &[KeyUsage],
+) -> Fallible<KeyData> {
+    check_usages(usages, HMAC_USAGES)?;
+    let bytes = match (format, key_data) {
+        (KeyFormat::Raw, KeyMaterial::Bytes(bytes)) => bytes,
+        (KeyFormat::Jwk, KeyMaterial::Jwk(jwk)) => {
+            jwk.check("oct", "sig", extractable, usages)?;
+            let bytes = base64url_decode(&jwk.k)?;
+            jwk.check_alg(&format!("HS{}", hash.jwk_hash_suffix()))?;
+            bytes
+        },
+        _ => return Err(Error::NotSupported),
+    };
+    if bytes.is_empty() {
+        return Err(Error::Data);
+    }
+    let data_length = bytes.len() as u32 * 8;
+    let length = match length {
+        Some(length) if length > data_length || length <= data_length - 8 => {
+            return Err(Error::Data);
+        },
+        Some(length) => length,
+        None => data_length,
+    };
+    Ok(KeyData {
+        key_type: KeyType::Secret,
+        extractable,
+        algorithm: KeyAlgorithm::Hmac { hash, length },
+        usages: usage_intersection(usages, HMAC_USAGES),
+        handle: KeyHandle::Secret(bytes),
+    })
+}
+
+/// <https://w3c.github.io/webcrypto/#hkdf-operations> and
+/// <https://w3c.github.io/webcrypto/#pbkdf2-operations>
+fn import_kdf_key(
+    format: KeyFormat,
+    key_data: KeyMaterial,
+    name: CryptoAlgorithm,
+    extractable: bool,
+    usages: &[KeyUsage],
+) -> Fallible<KeyData> {
+    let bytes = match (format, key_data) {
+        (KeyFormat::Raw, KeyMaterial::Bytes(bytes)) => bytes,
+        _ => return Err(Error::NotSupported),
+    };
+    check_usages(usages, KDF_USAGES)?;
+    if extractable {
+        return Err(Error::Syntax);
+    }
+    Ok(KeyData {
+        key_type: KeyType::Secret,
+        extractable,
+        algorithm: KeyAlgorithm::Plain(name),
+        usages: usage_intersection(usages, KDF_USAGES),
+        handle: KeyHandle::Secret(bytes),
+    })
+}
+
+/// The JSON Web Algorithms name of an RSA algorithm.
+fn rsa_jwk_algorithm(name: CryptoAlgorithm, hash: CryptoAlgorithm) -> String {
+    match name {
+        CryptoAlgorithm::RsaOaep if hash == CryptoAlgorithm::Sha1 => "RSA-OAEP".to_owned(),
+        CryptoAlgorithm::RsaOaep => format!("RSA-OAEP-{}", hash.jwk_hash_suffix()),
+        CryptoAlgorithm::RsaPss => format!("PS{}", hash.jwk_hash_suffix()),
+        _ => format!("RS{}", hash.jwk_hash_suffix()),
+    }
+}
+
+/// <https://w3c.github.io/webcrypto/#rsassa-pkcs1-operations>, and likewise
+/// for RSA-PSS and RSA-OAEP.
+fn import_rsa_key(
+    format: KeyFormat,
+    key_data: KeyMaterial,
+    name: CryptoAlgorithm,
+    hash: CryptoAlgorithm,
+    extractable: bool,
+    usages: &[KeyUsage],
+) -> Fallible<KeyData> {
+    let (public_usages, private_usages) = rsa_usages(name);
+    let handle = match (format, key_data) {
+        (KeyFormat::Spki, KeyMaterial::Bytes(bytes)) => {
+            check_usages(usages, public_usages)?;
+            let pkey = PKey::public_key_from_der(&bytes).map_err(data_error)?;
+            if pkey.id() != Id::RSA {
+                return Err(Error::Data);
+            }
+            KeyHandle::Public(pkey)
+        },
+        (KeyFormat::Pkcs8, KeyMaterial::Bytes(bytes)) => {
+            check_usages(usages, private_usages)?;
+            let pkey = PKey::private_key_from_der(&bytes).map_err(data_error)?;
+            if pkey.id() != Id::RSA {
+                return Err(Error::Data);
+            }
+            KeyHandle::Private(pkey)
+        },
+        (KeyFormat::Jwk, KeyMaterial::Jwk(jwk)) => {
+            let is_private = jwk.d.is_some();
+            check_usages(
+                usages,
+                if is_private {
+                    private_usages
+                } else {
+                    public_usages
+                },
+            )?;
+            let expected_use = if name == CryptoAlgorithm::RsaOaep {
+                "enc"
+            } else {
+                "sig"
+            };
+            jwk.check("RSA", expected_use, extractable, usages)?;
+            jwk.check_alg(&rsa_jwk_algorithm(name, hash))?;
+            if jwk.oth.is_some() {
+                return Err(Error::NotSupported);
+            }
+            let n = big_num(&jwk.n)?;
+            let e = big_num(&jwk.e)?;
+            if is_private {
+                let rsa = Rsa::from_private_components(
+                    n,
+                    e,
+                    big_num(&jwk.d)?,
+                    big_num(&jwk.p)?,
+                    big_num(&jwk.q)?,
+                    big_num(&jwk.dp)?,
+                    big_num(&jwk.dq)?,
+                    big_num(&jwk.qi)?,
+                )
+                .map_err(data_error)?;
+                if !rsa.check_key().unwrap_or(false) {
+                    return Err(Error::Data);
+                }
+                KeyHandle::Private(PKey::from_rsa(rsa).map_err(data_error)?)
+            } else {
+                let rsa = Rsa::from_public_components(n, e).map_err(data_error)?;
+                KeyHandle::Public(PKey::from_rsa(rsa).map_err(data_error)?)
+            }
+        },
+        _ => return Err(Error::NotSupported),
+    };
+
+    let (key_type, allowed_usages, modulus_length, public_exponent) = match handle {
+        KeyHandle::Public(ref pkey) => {
+            let rsa = pkey.rsa().map_err(data_error)?;
+            (
+                KeyType::Public,
+                public_usages,
+                rsa.n().num_bits(),
+                rsa.e().to_vec(),
+            )
+        },
+        KeyHandle::Private(ref pkey) => {
+            let rsa = pkey.rsa().map_err(data_error)?;
+            (
+                KeyType::Private,
+                private_usages,
+                rsa.n().num_bits(),
+                rsa.e().to_vec(),
+            )
+        },
+        KeyHandle::Secret(_) => unreachable!(),
+    };
+    Ok(KeyData {
+        key_type,
+        extractable,
+        algorithm: KeyAlgorithm::RsaHashed {
+            name,
+            modulus_length: modulus_length as u32,
+            public_exponent,
+            hash,
+        },
+        usages: usage_intersection(usages, allowed_usages),
+        handle,
+    })
+}
+
+/// Checks that an imported EC key is on `named_curve`.
+fn check_ec_curve<T>(pkey: &PKeyRef<T>, named_curve: NamedCurve) -> Fallible<()>
+where
+    T: HasPublic,
+{
+    let ec_key = pkey.ec_key().map_err(data_error)?;
+    if ec_key.group().curve_name() != Some(named_curve.nid()) {
+        return Err(Error::Data);
+    }
+    Ok(())
+}
+
+/// <https://w3c.github.io/webcrypto/#ecdsa-operations> and
+/// <https://w3c.github.io/webcrypto/#ecdh-operations>
+fn import_ec_key(
+    format: KeyFormat,
+    key_data: KeyMaterial,
+    name: CryptoAlgorithm,
+    named_curve: NamedCurve,
+    extractable: bool,
+    usages: &[KeyUsage],
+) -> Fallible<KeyData> {
+    let (public_usages, private_usages) = ec_usages(name);
+    let group = named_curve.group()?;
+    let handle = match (format, key_data) {
+        (KeyFormat::Raw, KeyMaterial::Bytes(bytes)) => {
+            check_usages(usages, public_usages)?;
+            let mut context = BigNumContext::new().map_err(operation_error)?;
+            let point = EcPoint::from_bytes(&group, &bytes, &mut context).map_err(data_error)?;
+            let ec_key = EcKey::from_public_key(&group, &point).map_err(data_error)?;
+            ec_key.check_key().map_err(data_error)?;
+            KeyHandle::Public(PKey::from_ec_key(ec_key).map_err(data_error)?)
+        },
+        (KeyFormat::Spki, KeyMaterial::Bytes(bytes)) => {
+            check_usages(usages, public_usages)?;
+            let pkey = PKey::public_key_from_der(&bytes).map_err(data_error)?;
+            if pkey.id() != Id::EC {
+                return Err(Error::Data);
+            }
+            check_ec_curve(&pkey, named_curve)?;
+            KeyHandle::Public(pkey)
+        },
+        (KeyFormat::Pkcs8, KeyMaterial::Bytes(bytes)) => {
+            check_usages(usages, private_usages)?;
+            let pkey = PKey::private_key_from_der(&bytes).map_err(data_error)?;
+            if pkey.id() != Id::EC {
+                return Err(Error::Data);
+            }
+            check_ec_curve(&pkey, named_curve)?;
+            KeyHandle::Private(pkey)
+        },
+        (KeyFormat::Jwk, KeyMaterial::Jwk(jwk)) => {
+            let is_private = jwk.d.is_some();
+            check_usages(
+                usages,
+                if is_private {
+                    private_usages
+                } else {
+                    public_usages
+                },
+            )?;
+            if name == CryptoAlgorithm::Ecdsa {
+                jwk.check("EC", "sig", extractable, usages)?;
+                jwk.check_alg(named_curve.jwk_ecdsa_algorithm())?;
+            } else {
+                jwk.check("EC", "enc", extractable, usages)?;
+            }
+            if jwk.crv.as_ref().map(|crv| crv.as_str()) != Some(named_curve.as_str()) {
+                return Err(Error::Data);
+            }
+            let x = base64url_decode(&jwk.x)?;
+            let y = base64url_decode(&jwk.y)?;
+            let length = named_curve.coordinate_length();
+            if x.len() != length || y.len() != length {
+                return Err(Error::Data);
+            }
+            let x = BigNum::from_slice(&x).map_err(data_error)?;
+            let y = BigNum::from_slice(&y).map_err(data_error)?;
+            let public =
+                EcKey::from_public_key_affine_coordinates(&group, &x, &y).map_err(data_error)?;
+            if is_private {
+                let d = base64url_decode(&jwk.d)?;
+                if d.len() != length {
+                    return Err(Error::Data);
+                }
+                let d = BigNum::from_slice(&d).map_err(data_error)?;
+                let ec_key = EcKey::from_private_components(&group, &d, public.public_key())
+                    .map_err(data_error)?;
+                ec_key.check_key().map_err(data_error)?;
+                KeyHandle::Private(PKey::from_ec_key(ec_key).map_err(data_error)?)
+            } else {
+                public.check_key().map_err(data_error)?;
+                KeyHandle::Public(PKey::from_ec_key(public).map_err(data_error)?)
+            }
+        },
+        _ => return Err(Error::NotSupported),
+    };
+
+    let (key_type, allowed_usages) = match handle {
+        KeyHandle::Private(_) => (KeyType::Private, private_usages),
+        _ => (KeyType::Public, public_usages),
+    };
+    Ok(KeyData {
+        key_type,
+        extractable,
+        algorithm: KeyAlgorithm::Ec { name, named_curve },
+        usages: usage_intersection(usages, allowed_usages),
+        handle,
+    })
+}
+
+/// The export key operations of all algorithms.
+fn export_key(format: KeyFormat, key: &KeyData) -> Fallible<KeyMaterial> {
+    let key_ops = || {
+        Some(
+            key.usages
+                .iter()
+                .map(|usage| usage.as_str().to_owned())
+                .collect(),
+        )
+    };
+    match (format, &key.algorithm, &key.handle) {
+        (KeyFormat::Raw, &KeyAlgorithm::Aes { .. }, &KeyHandle::Secret(ref bytes)) |
+        (KeyFormat::Raw, &KeyAlgorithm::Hmac { .. }, &KeyHandle::Secret(ref bytes)) => {
+            Ok(KeyMaterial::Bytes(bytes.clone()))
+        },
+        (KeyFormat::Jwk, &KeyAlgorithm::Aes { name, length }, &KeyHandle::Secret(ref bytes)) => {
+            Ok(KeyMaterial::Jwk(Jwk {
+                kty: Some("oct".to_owned()),
+                k: Some(base64url_encode(bytes)),
+                alg: Some(aes_jwk_algorithm(name, length as usize)),
+                key_ops: key_ops(),
+                ext: Some(key.extractable),
+                ..Default::default()
+            }))
+        },
+        (KeyFormat::Jwk, &KeyAlgorithm::Hmac { hash, .. }, &KeyHandle::Secret(ref bytes)) => {
+            Ok(KeyMaterial::Jwk(Jwk {
+                kty: Some("oct".to_owned()),
+                k: Some(base64url_encode(bytes)),
+                alg: Some(format!("HS{}", hash.jwk_hash_suffix())),
+                key_ops: key_ops(),
+                ext: Some(key.extractable),
+                ..Default::default()
+            }))
+        },
+        (KeyFormat::Spki, &KeyAlgorithm::RsaHashed { .. }, &KeyHandle::Public(ref pkey)) |
+        (KeyFormat::Spki, &KeyAlgorithm::Ec { .. }, &KeyHandle::Public(ref pkey)) => pkey
+            .public_key_to_der()
+            .map(KeyMaterial::Bytes)
+            .map_err(operation_error),
+        (KeyFormat::Pkcs8, &KeyAlgorithm::RsaHashed { .. }, &KeyHandle::Private(ref pkey)) |
+        (KeyFormat::Pkcs8, &KeyAlgorithm::Ec { .. }, &KeyHandle::Private(ref pkey)) => {
+            private_key_to_pkcs8(pkey).map(KeyMaterial::Bytes)
+        },
+        (KeyFormat::Jwk, &KeyAlgorithm::RsaHashed { name, hash, .. }, handle) => {
+            let mut jwk = Jwk {
+                kty: Some("RSA".to_owned()),
+                alg: Some(rsa_jwk_algorithm(name, hash)),
+                key_ops: key_ops(),
+                ext: Some(key.extractable),
+                ..Default::default()
+            };
+            match *handle {
+                KeyHandle::Public(ref pkey) => {
+                    let rsa = pkey.rsa().map_err(operation_error)?;
+                    jwk.n = Some(base64url_encode(&rsa.n().to_vec()));
+                    jwk.e = Some(base64url_encode(&rsa.e().to_vec()));
+                },
+                KeyHandle::Private(ref pkey) => {
+                    let rsa = pkey.rsa().map_err(operation_error)?;
+                    let encode = |number: Option<&BigNumRef>| {
+                        number.map(|number| base64url_encode(&number.to_vec()))
+                    };
+                    jwk.n = Some(base64url_encode(&rsa.n().to_vec()));
+                    jwk.e = Some(base64url_encode(&rsa.e().to_vec()));
+                    jwk.d = Some(base64url_encode(&rsa.d().to_vec()));
+                    jwk.p = encode(rsa.p());
+                    jwk.q = encode(rsa.q());
+                    jwk.dp = encode(rsa.dmp1());
+                    jwk.dq = encode(rsa.dmq1());
+                    jwk.qi = encode(rsa.iqmp());
+                },
+                KeyHandle::Secret(_) => return Err(Error::Operation),
+            }
+            Ok(KeyMaterial::Jwk(jwk))
+        },
+        (KeyFormat::Raw, &KeyAlgorithm::Ec { named_curve, .. }, &KeyHandle::Public(ref pkey)) => {
+            let ec_key = pkey.ec_key().map_err(operation_error)?;
+            let group = named_curve.group()?;
+            let mut context = BigNumContext::new().map_err(operation_error)?;
+            ec_key
+                .public_key()
+                .to_bytes(&group, PointConversionForm::UNCOMPRESSED, &mut context)
+                .map(KeyMaterial::Bytes)
+                .map_err(operation_error)
+        },
+        (KeyFormat::Jwk, &KeyAlgorithm::Ec { name, named_curve }, handle) => {
+            let group = named_curve.group()?;
+            let length = named_curve.coordinate_length();
+            let (public_key, private_key) = match *handle {
+                KeyHandle::Public(ref pkey) => {
+                    let ec_key = pkey.ec_key().map_err(operation_error)?;
+                    (ec_key.public_key().to_owned(&group), None)
+                },
+                KeyHandle::Private(ref pkey) => {
+                    let ec_key = pkey.ec_key().map_err(operation_error)?;
+                    (
+                        ec_key.public_key().to_owned(&group),
+                        Some(pad_bytes(ec_key.private_key().to_vec(), length)),
+                    )
+                },
+                KeyHandle::Secret(_) => return Err(Error::Operation),
+            };
+            let public_key = public_key.map_err(operation_error)?;
+            let mut context = BigNumContext::new().map_err(operation_error)?;
+            let mut x = BigNum::new().map_err(operation_error)?;
+            let mut y = BigNum::new().map_err(operation_error)?;
+            public_key
+                .affine_coordinates_gfp(&group, &mut x, &mut y, &mut context)
+                .map_err(operation_error)?;
+            Ok(KeyMaterial::Jwk(Jwk {
+                kty: Some("EC".to_owned()),
+                crv: Some(named_curve.as_str().to_owned()),
+                x: Some(base64url_encode(&pad_bytes(x.to_vec(), length))),
+                y: Some(base64url_encode(&pad_bytes(y.to_vec(), length))),
+                d: private_key.map(|d| base64url_encode(&d)),
+                alg: if name == CryptoAlgorithm::Ecdsa {
+                    Some(named_curve.jwk_ecdsa_algorithm().to_owned())
+                } else {
+                    None
+                },
+                key_ops: key_ops(),
+                ext: Some(key.extractable),
+                ..Default::default()
+            }))
+        },
+        (KeyFormat::Raw, &KeyAlgorithm::Ec { .. }, _) |
+        (KeyFormat::Spki, _, &KeyHandle::Private(_)) |
+        (KeyFormat::Pkcs8, _, &KeyHandle::Public(_)) => Err(Error::InvalidAccess),
+        _ => Err(Error::NotSupported),
+    }
+}
+
+/// Serializes a private key in the PKCS #8 format, which OpenSSL only
+/// provides PEM encoded.
+fn private_key_to_pkcs8(pkey: &PKey<Private>) -> Fallible<Vec<u8>> {
+    let pem = pkey.private_key_to_pem_pkcs8().map_err(operation_error)?;
+    let pem = String::from_utf8(pem).map_err(|_| Error::Operation)?;
+    let base64: String = pem
+        .lines()
+        .filter(|line| !line.starts_with("-----"))
+        .collect();
+    base64::decode(&base64).map_err(|_| Error::Operation)
+}
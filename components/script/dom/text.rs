@@ -9,24 +9,31 @@ use crate::dom::bindings::codegen::Bindings::TextBinding::TextMethods;
 use crate::dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
 use crate::dom::bindings::error::{Error, Fallible};
 use crate::dom::bindings::inheritance::Castable;
-use crate::dom::bindings::root::DomRoot;
+use crate::dom::bindings::root::{DomRoot, LayoutDom};
 use crate::dom::bindings::str::DOMString;
 use crate::dom::characterdata::CharacterData;
 use crate::dom::document::Document;
-use crate::dom::node::Node;
+use crate::dom::htmlslotelement::HTMLSlotElement;
+use crate::dom::node::{Node, NodeDamage};
 use crate::dom::window::Window;
 use dom_struct::dom_struct;
+use std::cell::Cell;
+use std::ops::Range;
 
 /// An HTML text node.
 #[dom_struct]
 pub struct Text {
     characterdata: CharacterData,
+    /// The byte range of the data selected in an editing host, for layout
+    /// to paint the selection or caret.
+    selection_for_layout: Cell<Option<(usize, usize)>>,
 }
 
 impl Text {
     pub fn new_inherited(text: DOMString, document: &Document) -> Text {
         Text {
             characterdata: CharacterData::new_inherited(text, document),
+            selection_for_layout: Cell::new(None),
         }
     }
 
@@ -39,6 +46,25 @@ impl Text {
         let document = window.Document();
         Ok(Text::new(text, &document))
     }
+
+    pub fn set_selection_for_layout(&self, selection: Option<Range<usize>>) {
+        let selection = selection.map(|range| (range.start, range.end));
+        if self.selection_for_layout.get() != selection {
+            self.selection_for_layout.set(selection);
+            self.upcast::<Node>().dirty(NodeDamage::OtherNodeDamage);
+        }
+    }
+}
+
+pub trait LayoutTextHelpers {
+    fn selection_for_layout(self) -> Option<Range<usize>>;
+}
+
+impl LayoutTextHelpers for LayoutDom<'_, Text> {
+    #[allow(unsafe_code)]
+    fn selection_for_layout(self) -> Option<Range<usize>> {
+        unsafe { self.unsafe_get().selection_for_layout.get() }.map(|(start, end)| start..end)
+    }
 }
 
 impl TextMethods for Text {
@@ -97,4 +123,9 @@ impl TextMethods for Text {
         }
         DOMString::from(text)
     }
+
+    // https://dom.spec.whatwg.org/#dom-slotable-assignedslot
+    fn GetAssignedSlot(&self) -> Option<DomRoot<HTMLSlotElement>> {
+        self.upcast::<Node>().find_a_slot(true)
+    }
 }
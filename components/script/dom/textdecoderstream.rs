@@ -0,0 +1,206 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::TextDecoderBinding::TextDecoderOptions;
+use crate::dom::bindings::codegen::Bindings::TextDecoderStreamBinding::TextDecoderStreamMethods;
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::bindings::str::DOMString;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::readablestream::ReadableStream;
+use crate::dom::transformstream::TransformStream;
+use crate::dom::transformstreamdefaultcontroller::{
+    buffer_source_bytes, TransformStreamDefaultController, TransformerType,
+};
+use crate::dom::writablestream::WritableStream;
+use crate::js::conversions::ToJSValConvertible;
+use crate::script_runtime::JSContext;
+use dom_struct::dom_struct;
+use encoding_rs::{Decoder, DecoderResult, Encoding};
+use js::jsval::UndefinedValue;
+use js::rust::HandleValue;
+use std::cell::RefCell;
+
+/// The state of the transformer of a `TextDecoderStream`.
+#[derive(JSTraceable, MallocSizeOf)]
+pub struct TextDecoderTransformer {
+    /// <https://encoding.spec.whatwg.org/#textdecodercommon-encoding>
+    encoding: &'static Encoding,
+    /// <https://encoding.spec.whatwg.org/#textdecoder-error-mode>
+    fatal: bool,
+    /// <https://encoding.spec.whatwg.org/#textdecoder-ignore-bom-flag>
+    ignore_bom: bool,
+    /// <https://encoding.spec.whatwg.org/#textdecodercommon-decoder>
+    #[ignore_malloc_size_of = "defined in encoding_rs"]
+    decoder: RefCell<Decoder>,
+}
+
+impl TextDecoderTransformer {
+    fn new(encoding: &'static Encoding, fatal: bool, ignore_bom: bool) -> TextDecoderTransformer {
+        TextDecoderTransformer {
+            encoding,
+            fatal,
+            ignore_bom,
+            decoder: RefCell::new(if ignore_bom {
+                encoding.new_decoder_without_bom_handling()
+            } else {
+                encoding.new_decoder_with_bom_removal()
+            }),
+        }
+    }
+
+    /// <https://encoding.spec.whatwg.org/#decode-and-enqueue-a-chunk>
+    pub fn transform(
+        &self,
+        cx: JSContext,
+        controller: &TransformStreamDefaultController,
+        chunk: HandleValue,
+    ) -> Fallible<()> {
+        // Steps 1-2.
+        let input = buffer_source_bytes(chunk)?;
+
+        // Steps 3-4.
+        self.decode_and_enqueue(cx, controller, &input, false)
+    }
+
+    /// <https://encoding.spec.whatwg.org/#flush-and-enqueue>
+    pub fn flush(
+        &self,
+        cx: JSContext,
+        controller: &TransformStreamDefaultController,
+    ) -> Fallible<()> {
+        self.decode_and_enqueue(cx, controller, &[], true)
+    }
+
+    /// Decodes `input`, enqueuing the output if it is not empty, and throws
+    /// a `TypeError` on errors if the error mode is fatal.
+    #[allow(unsafe_code)]
+    fn decode_and_enqueue(
+        &self,
+        cx: JSContext,
+        controller: &TransformStreamDefaultController,
+        input: &[u8],
+        last: bool,
+    ) -> Fallible<()> {
+        let output = {
+            let mut decoder = self.decoder.borrow_mut();
+            if self.fatal {
+                let mut output = String::with_capacity(
+                    decoder
+                        .max_utf8_buffer_length_without_replacement(input.len())
+                        .unwrap(),
+                );
+                match decoder.decode_to_string_without_replacement(input, &mut output, last) {
+                    (DecoderResult::InputEmpty, _) => output,
+                    _ => return Err(Error::Type("Decoding failed".to_owned())),
+                }
+            } else {
+                let mut output =
+                    String::with_capacity(decoder.max_utf8_buffer_length(input.len()).unwrap());
+                let _ = decoder.decode_to_string(input, &mut output, last);
+                output
+            }
+        };
+
+        if output.is_empty() {
+            return Ok(());
+        }
+        rooted!(in(*cx) let mut chunk = UndefinedValue());
+        unsafe { DOMString::from(output).to_jsval(*cx, chunk.handle_mut()) };
+        controller.enqueue(chunk.handle())
+    }
+}
+
+/// <https://encoding.spec.whatwg.org/#interface-textdecoderstream>
+#[dom_struct]
+pub struct TextDecoderStream {
+    reflector_: Reflector,
+    encoding: &'static Encoding,
+    fatal: bool,
+    ignore_bom: bool,
+    /// <https://streams.spec.whatwg.org/#generictransformstream-transform>
+    transform: Dom<TransformStream>,
+}
+
+impl TextDecoderStream {
+    fn new_inherited(
+        encoding: &'static Encoding,
+        fatal: bool,
+        ignore_bom: bool,
+        transform: &TransformStream,
+    ) -> TextDecoderStream {
+        TextDecoderStream {
+            reflector_: Reflector::new(),
+            encoding,
+            fatal,
+            ignore_bom,
+            transform: Dom::from_ref(transform),
+        }
+    }
+
+    /// <https://encoding.spec.whatwg.org/#dom-textdecoderstream>
+    #[allow(non_snake_case, unrooted_must_root)]
+    pub fn Constructor(
+        global: &GlobalScope,
+        label: DOMString,
+        options: &TextDecoderOptions,
+    ) -> Fallible<DomRoot<TextDecoderStream>> {
+        // Steps 1-2.
+        let encoding = match Encoding::for_label_no_replacement(label.as_bytes()) {
+            Some(encoding) => encoding,
+            None => {
+                return Err(Error::Range(
+                    "The given encoding is not supported.".to_owned(),
+                ))
+            },
+        };
+
+        // Steps 3-9.
+        let transform = TransformStream::new_with_transformer(
+            global,
+            TransformerType::TextDecoder(TextDecoderTransformer::new(
+                encoding,
+                options.fatal,
+                options.ignoreBOM,
+            )),
+        )?;
+        Ok(reflect_dom_object(
+            Box::new(TextDecoderStream::new_inherited(
+                encoding,
+                options.fatal,
+                options.ignoreBOM,
+                &transform,
+            )),
+            global,
+        ))
+    }
+}
+
+impl TextDecoderStreamMethods for TextDecoderStream {
+    /// <https://encoding.spec.whatwg.org/#dom-textdecoder-encoding>
+    fn Encoding(&self) -> DOMString {
+        DOMString::from(self.encoding.name().to_ascii_lowercase())
+    }
+
+    /// <https://encoding.spec.whatwg.org/#dom-textdecoder-fatal>
+    fn Fatal(&self) -> bool {
+        self.fatal
+    }
+
+    /// <https://encoding.spec.whatwg.org/#dom-textdecoder-ignorebom>
+    fn IgnoreBOM(&self) -> bool {
+        self.ignore_bom
+    }
+
+    /// <https://streams.spec.whatwg.org/#dom-generictransformstream-readable>
+    fn Readable(&self) -> DomRoot<ReadableStream> {
+        self.transform.get_readable()
+    }
+
+    /// <https://streams.spec.whatwg.org/#dom-generictransformstream-writable>
+    fn Writable(&self) -> DomRoot<WritableStream> {
+        self.transform.get_writable()
+    }
+}
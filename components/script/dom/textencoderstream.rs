@@ -0,0 +1,143 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::TextEncoderStreamBinding::TextEncoderStreamMethods;
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::bindings::str::DOMString;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::readablestream::ReadableStream;
+use crate::dom::transformstream::TransformStream;
+use crate::dom::transformstreamdefaultcontroller::{
+    TransformStreamDefaultController, TransformerType,
+};
+use crate::dom::writablestream::WritableStream;
+use crate::script_runtime::JSContext;
+use dom_struct::dom_struct;
+use js::conversions::latin1_to_string;
+use js::jsapi::{JS_GetTwoByteStringCharsAndLength, JS_StringHasLatin1Chars};
+use js::rust::{HandleValue, ToString};
+use std::cell::Cell;
+use std::{ptr, slice};
+
+/// The state of the transformer of a `TextEncoderStream`.
+#[derive(Default, JSTraceable, MallocSizeOf)]
+pub struct TextEncoderTransformer {
+    /// <https://encoding.spec.whatwg.org/#textencoderstream-pending-high-surrogate>
+    pending_high_surrogate: Cell<Option<u16>>,
+}
+
+impl TextEncoderTransformer {
+    /// <https://encoding.spec.whatwg.org/#encode-and-enqueue-a-chunk>
+    #[allow(unsafe_code)]
+    pub fn transform(
+        &self,
+        cx: JSContext,
+        controller: &TransformStreamDefaultController,
+        chunk: HandleValue,
+    ) -> Fallible<()> {
+        // Step 1.
+        let input = unsafe {
+            let string = ToString(*cx, chunk);
+            if string.is_null() {
+                return Err(Error::JSFailed);
+            }
+            if JS_StringHasLatin1Chars(string) {
+                latin1_to_string(*cx, string).encode_utf16().collect()
+            } else {
+                let mut length = 0;
+                let chars =
+                    JS_GetTwoByteStringCharsAndLength(*cx, ptr::null(), string, &mut length);
+                assert!(!chars.is_null());
+                slice::from_raw_parts(chars, length as usize).to_vec()
+            }
+        };
+
+        // Steps 2-4.
+        let mut output = String::new();
+        for unit in input {
+            // Step 4.3, with
+            // <https://encoding.spec.whatwg.org/#convert-code-unit-to-scalar-value>.
+            if let Some(high) = self.pending_high_surrogate.take() {
+                if (0xDC00..=0xDFFF).contains(&unit) {
+                    let scalar = 0x10000 + ((high as u32 - 0xD800) << 10) + (unit as u32 - 0xDC00);
+                    output.push(std::char::from_u32(scalar).unwrap());
+                    continue;
+                }
+                output.push('\u{FFFD}');
+            }
+            match unit {
+                0xD800..=0xDBFF => self.pending_high_surrogate.set(Some(unit)),
+                0xDC00..=0xDFFF => output.push('\u{FFFD}'),
+                _ => output.push(std::char::from_u32(unit as u32).unwrap()),
+            }
+        }
+
+        // Steps 5-6.
+        if output.is_empty() {
+            return Ok(());
+        }
+        controller.enqueue_bytes(cx, output.as_bytes())
+    }
+
+    /// <https://encoding.spec.whatwg.org/#encode-and-flush>
+    pub fn flush(
+        &self,
+        cx: JSContext,
+        controller: &TransformStreamDefaultController,
+    ) -> Fallible<()> {
+        if self.pending_high_surrogate.take().is_none() {
+            return Ok(());
+        }
+        controller.enqueue_bytes(cx, &[0xEF, 0xBF, 0xBD])
+    }
+}
+
+/// <https://encoding.spec.whatwg.org/#interface-textencoderstream>
+#[dom_struct]
+pub struct TextEncoderStream {
+    reflector_: Reflector,
+    /// <https://streams.spec.whatwg.org/#generictransformstream-transform>
+    transform: Dom<TransformStream>,
+}
+
+impl TextEncoderStream {
+    fn new_inherited(transform: &TransformStream) -> TextEncoderStream {
+        TextEncoderStream {
+            reflector_: Reflector::new(),
+            transform: Dom::from_ref(transform),
+        }
+    }
+
+    /// <https://encoding.spec.whatwg.org/#dom-textencoderstream>
+    #[allow(non_snake_case, unrooted_must_root)]
+    pub fn Constructor(global: &GlobalScope) -> Fallible<DomRoot<TextEncoderStream>> {
+        let transform = TransformStream::new_with_transformer(
+            global,
+            TransformerType::TextEncoder(Default::default()),
+        )?;
+        Ok(reflect_dom_object(
+            Box::new(TextEncoderStream::new_inherited(&transform)),
+            global,
+        ))
+    }
+}
+
+impl TextEncoderStreamMethods for TextEncoderStream {
+    /// <https://encoding.spec.whatwg.org/#dom-textencoder-encoding>
+    fn Encoding(&self) -> DOMString {
+        DOMString::from("utf-8")
+    }
+
+    /// <https://streams.spec.whatwg.org/#dom-generictransformstream-readable>
+    fn Readable(&self) -> DomRoot<ReadableStream> {
+        self.transform.get_readable()
+    }
+
+    /// <https://streams.spec.whatwg.org/#dom-generictransformstream-writable>
+    fn Writable(&self) -> DomRoot<WritableStream> {
+        self.transform.get_writable()
+    }
+}
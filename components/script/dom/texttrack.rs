@@ -11,6 +11,7 @@ use crate::dom::bindings::reflector::{reflect_dom_object, DomObject};
 use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
 use crate::dom::bindings::str::DOMString;
 use crate::dom::eventtarget::EventTarget;
+use crate::dom::htmltrackelement::HTMLTrackElement;
 use crate::dom::texttrackcue::TextTrackCue;
 use crate::dom::texttrackcuelist::TextTrackCueList;
 use crate::dom::texttracklist::TextTrackList;
@@ -21,13 +22,18 @@ use std::cell::Cell;
 #[dom_struct]
 pub struct TextTrack {
     eventtarget: EventTarget,
-    kind: TextTrackKind,
-    label: String,
-    language: String,
-    id: String,
+    kind: Cell<TextTrackKind>,
+    label: DomRefCell<String>,
+    language: DomRefCell<String>,
+    id: DomRefCell<String>,
     mode: Cell<TextTrackMode>,
     cue_list: MutNullableDom<TextTrackCueList>,
+    /// The cues whose active flag is set, in text track cue order.
+    /// <https://html.spec.whatwg.org/multipage/#text-track-cue-active-flag>
+    active_cues: DomRefCell<Vec<Dom<TextTrackCue>>>,
     track_list: DomRefCell<Option<Dom<TextTrackList>>>,
+    /// The `track` element this text track corresponds to, if any.
+    track_element: MutNullableDom<HTMLTrackElement>,
 }
 
 impl TextTrack {
@@ -41,13 +47,15 @@ impl TextTrack {
     ) -> TextTrack {
         TextTrack {
             eventtarget: EventTarget::new_inherited(),
-            kind: kind,
-            label: label.into(),
-            language: language.into(),
-            id: id.into(),
+            kind: Cell::new(kind),
+            label: DomRefCell::new(label.into()),
+            language: DomRefCell::new(language.into()),
+            id: DomRefCell::new(id.into()),
             mode: Cell::new(mode),
             cue_list: Default::default(),
+            active_cues: Default::default(),
             track_list: DomRefCell::new(track_list.map(|t| Dom::from_ref(t))),
+            track_element: Default::default(),
         }
     }
 
@@ -73,8 +81,24 @@ impl TextTrack {
             .or_init(|| TextTrackCueList::new(&self.global().as_window(), &[]))
     }
 
-    pub fn id(&self) -> &str {
-        &self.id
+    pub fn id(&self) -> String {
+        self.id.borrow().clone()
+    }
+
+    pub fn set_id(&self, id: DOMString) {
+        *self.id.borrow_mut() = id.into();
+    }
+
+    pub fn set_kind(&self, kind: TextTrackKind) {
+        self.kind.set(kind);
+    }
+
+    pub fn set_label(&self, label: DOMString) {
+        *self.label.borrow_mut() = label.into();
+    }
+
+    pub fn set_language(&self, language: DOMString) {
+        *self.language.borrow_mut() = language.into();
     }
 
     pub fn add_track_list(&self, track_list: &TextTrackList) {
@@ -84,27 +108,89 @@ impl TextTrack {
     pub fn remove_track_list(&self) {
         *self.track_list.borrow_mut() = None;
     }
+
+    fn track_list(&self) -> Option<DomRoot<TextTrackList>> {
+        self.track_list
+            .borrow()
+            .as_ref()
+            .map(|list| DomRoot::from_ref(&**list))
+    }
+
+    pub fn track_element(&self) -> Option<DomRoot<HTMLTrackElement>> {
+        self.track_element.get()
+    }
+
+    pub fn set_track_element(&self, track_element: &HTMLTrackElement) {
+        self.track_element.set(Some(track_element));
+    }
+
+    pub fn active_cues(&self) -> Vec<DomRoot<TextTrackCue>> {
+        self.active_cues
+            .borrow()
+            .iter()
+            .map(|cue| DomRoot::from_ref(&**cue))
+            .collect()
+    }
+
+    pub fn set_active_cues(&self, cues: &[&TextTrackCue]) {
+        *self.active_cues.borrow_mut() = cues.iter().map(|cue| Dom::from_ref(*cue)).collect();
+    }
+
+    /// Replace all the cues of this track, e.g. after its `track` element
+    /// loaded a new resource.
+    pub fn reset_cues(&self, cues: &[&TextTrackCue]) {
+        let cue_list = self.get_cues();
+        for cue in cue_list.cues() {
+            cue.set_track(None);
+        }
+        let new_cue_list = TextTrackCueList::new(&self.global().as_window(), &[]);
+        for cue in cues {
+            cue.set_track(Some(self));
+            new_cue_list.add(cue);
+        }
+        self.cue_list.set(Some(&new_cue_list));
+        self.cues_changed();
+    }
+
+    /// Keep the cues in text track cue order when the timing of one of them
+    /// changes.
+    pub fn cue_timing_changed(&self, cue: &TextTrackCue) {
+        let cues = self.get_cues();
+        if let Some(index) = cues.find(cue) {
+            cues.remove(index);
+            cues.add(cue);
+        }
+        self.cues_changed();
+    }
+
+    /// Notify the media element this track belongs to that its cues changed,
+    /// so that the active cues and their rendering are updated.
+    pub fn cues_changed(&self) {
+        if let Some(media_element) = self.track_list().and_then(|list| list.media_element()) {
+            media_element.text_tracks_changed();
+        }
+    }
 }
 
 impl TextTrackMethods for TextTrack {
     // https://html.spec.whatwg.org/multipage/#dom-texttrack-kind
     fn Kind(&self) -> TextTrackKind {
-        self.kind
+        self.kind.get()
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-texttrack-label
     fn Label(&self) -> DOMString {
-        DOMString::from(self.label.clone())
+        DOMString::from(self.label.borrow().clone())
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-texttrack-language
     fn Language(&self) -> DOMString {
-        DOMString::from(self.language.clone())
+        DOMString::from(self.language.borrow().clone())
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-texttrack-id
     fn Id(&self) -> DOMString {
-        DOMString::from(self.id.clone())
+        DOMString::from(self.id())
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-texttrack-mode
@@ -114,7 +200,22 @@ impl TextTrackMethods for TextTrack {
 
     // https://html.spec.whatwg.org/multipage/#dom-texttrack-mode
     fn SetMode(&self, value: TextTrackMode) {
-        self.mode.set(value)
+        if self.mode.get() == value {
+            return;
+        }
+        self.mode.set(value);
+
+        // https://html.spec.whatwg.org/multipage/#start-the-track-processing-model
+        if value != TextTrackMode::Disabled {
+            if let Some(track_element) = self.track_element() {
+                track_element.start_track_processing();
+            }
+        }
+
+        // https://html.spec.whatwg.org/multipage/#text-track-mode
+        if let Some(track_list) = self.track_list() {
+            track_list.mode_changed();
+        }
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-texttrack-cues
@@ -127,9 +228,17 @@ impl TextTrackMethods for TextTrack {
 
     // https://html.spec.whatwg.org/multipage/#dom-texttrack-activecues
     fn GetActiveCues(&self) -> Option<DomRoot<TextTrackCueList>> {
-        // XXX implement active cues logic
-        //      https://github.com/servo/servo/issues/22314
-        Some(TextTrackCueList::new(&self.global().as_window(), &[]))
+        match self.Mode() {
+            TextTrackMode::Disabled => None,
+            _ => {
+                let active_cues = self.active_cues();
+                let active_cues: Vec<&TextTrackCue> = active_cues.iter().map(|c| &**c).collect();
+                Some(TextTrackCueList::new(
+                    &self.global().as_window(),
+                    &active_cues,
+                ))
+            },
+        }
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-texttrack-addcue
@@ -145,7 +254,9 @@ impl TextTrackMethods for TextTrack {
             }
         }
         // Step 4
+        cue.set_track(Some(self));
         self.get_cues().add(cue);
+        self.cues_changed();
         Ok(())
     }
 
@@ -159,6 +270,8 @@ impl TextTrackMethods for TextTrack {
         }?;
         // Step 2
         cues.remove(index);
+        cue.set_track(None);
+        self.cues_changed();
         Ok(())
     }
 
@@ -6,7 +6,7 @@ use crate::dom::bindings::cell::DomRefCell;
 use crate::dom::bindings::codegen::Bindings::TextTrackCueBinding::TextTrackCueMethods;
 use crate::dom::bindings::num::Finite;
 use crate::dom::bindings::reflector::reflect_dom_object;
-use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::bindings::root::{DomRoot, MutNullableDom};
 use crate::dom::bindings::str::DOMString;
 use crate::dom::eventtarget::EventTarget;
 use crate::dom::texttrack::TextTrack;
@@ -18,7 +18,7 @@ use std::cell::Cell;
 pub struct TextTrackCue {
     eventtarget: EventTarget,
     id: DomRefCell<DOMString>,
-    track: Option<Dom<TextTrack>>,
+    track: MutNullableDom<TextTrack>,
     start_time: Cell<f64>,
     end_time: Cell<f64>,
     pause_on_exit: Cell<bool>,
@@ -34,7 +34,7 @@ impl TextTrackCue {
         TextTrackCue {
             eventtarget: EventTarget::new_inherited(),
             id: DomRefCell::new(id),
-            track: track.map(Dom::from_ref),
+            track: MutNullableDom::new(track),
             start_time: Cell::new(start_time),
             end_time: Cell::new(end_time),
             pause_on_exit: Cell::new(false),
@@ -60,7 +60,23 @@ impl TextTrackCue {
     }
 
     pub fn get_track(&self) -> Option<DomRoot<TextTrack>> {
-        self.track.as_ref().map(|t| DomRoot::from_ref(&**t))
+        self.track.get()
+    }
+
+    pub fn set_track(&self, track: Option<&TextTrack>) {
+        self.track.set(track);
+    }
+
+    pub fn start_time(&self) -> f64 {
+        self.start_time.get()
+    }
+
+    pub fn end_time(&self) -> f64 {
+        self.end_time.get()
+    }
+
+    pub fn pause_on_exit(&self) -> bool {
+        self.pause_on_exit.get()
     }
 }
 
@@ -88,6 +104,9 @@ impl TextTrackCueMethods for TextTrackCue {
     // https://html.spec.whatwg.org/multipage/#dom-texttrackcue-starttime
     fn SetStartTime(&self, value: Finite<f64>) {
         self.start_time.set(*value);
+        if let Some(track) = self.get_track() {
+            track.cue_timing_changed(self);
+        }
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-texttrackcue-endtime
@@ -98,6 +117,9 @@ impl TextTrackCueMethods for TextTrackCue {
     // https://html.spec.whatwg.org/multipage/#dom-texttrackcue-endtime
     fn SetEndTime(&self, value: Finite<f64>) {
         self.end_time.set(*value);
+        if let Some(track) = self.get_track() {
+            track.cue_timing_changed(self);
+        }
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-texttrackcue-pauseonexit
@@ -46,13 +46,32 @@ impl TextTrackCueList {
             .map(|(i, _)| i)
     }
 
+    /// Add a cue, keeping the list in text track cue order.
+    ///
+    /// <https://html.spec.whatwg.org/multipage/#text-track-cue-order>
     pub fn add(&self, cue: &TextTrackCue) {
         // Only add a cue if it does not exist in the list
         if self.find(cue).is_none() {
-            self.dom_cues.borrow_mut().push(Dom::from_ref(cue));
+            let mut cues = self.dom_cues.borrow_mut();
+            let index = cues
+                .iter()
+                .position(|c| {
+                    c.start_time() > cue.start_time() ||
+                        (c.start_time() == cue.start_time() && c.end_time() < cue.end_time())
+                })
+                .unwrap_or(cues.len());
+            cues.insert(index, Dom::from_ref(cue));
         }
     }
 
+    pub fn cues(&self) -> Vec<DomRoot<TextTrackCue>> {
+        self.dom_cues
+            .borrow()
+            .iter()
+            .map(|c| DomRoot::from_ref(&**c))
+            .collect()
+    }
+
     pub fn remove(&self, idx: usize) {
         self.dom_cues.borrow_mut().remove(idx);
     }
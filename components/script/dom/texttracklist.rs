@@ -12,6 +12,7 @@ use crate::dom::bindings::root::{Dom, DomRoot};
 use crate::dom::bindings::str::DOMString;
 use crate::dom::event::Event;
 use crate::dom::eventtarget::EventTarget;
+use crate::dom::htmlmediaelement::HTMLMediaElement;
 use crate::dom::texttrack::TextTrack;
 use crate::dom::trackevent::TrackEvent;
 use crate::dom::window::Window;
@@ -22,18 +23,40 @@ use dom_struct::dom_struct;
 pub struct TextTrackList {
     eventtarget: EventTarget,
     dom_tracks: DomRefCell<Vec<Dom<TextTrack>>>,
+    media_element: Option<Dom<HTMLMediaElement>>,
 }
 
 impl TextTrackList {
-    pub fn new_inherited(tracks: &[&TextTrack]) -> TextTrackList {
+    pub fn new_inherited(
+        tracks: &[&TextTrack],
+        media_element: Option<&HTMLMediaElement>,
+    ) -> TextTrackList {
         TextTrackList {
             eventtarget: EventTarget::new_inherited(),
             dom_tracks: DomRefCell::new(tracks.iter().map(|g| Dom::from_ref(&**g)).collect()),
+            media_element: media_element.map(|m| Dom::from_ref(m)),
         }
     }
 
-    pub fn new(window: &Window, tracks: &[&TextTrack]) -> DomRoot<TextTrackList> {
-        reflect_dom_object(Box::new(TextTrackList::new_inherited(tracks)), window)
+    pub fn new(
+        window: &Window,
+        tracks: &[&TextTrack],
+        media_element: Option<&HTMLMediaElement>,
+    ) -> DomRoot<TextTrackList> {
+        reflect_dom_object(
+            Box::new(TextTrackList::new_inherited(tracks, media_element)),
+            window,
+        )
+    }
+
+    pub fn len(&self) -> usize {
+        self.dom_tracks.borrow().len()
+    }
+
+    pub fn media_element(&self) -> Option<DomRoot<HTMLMediaElement>> {
+        self.media_element
+            .as_ref()
+            .map(|media_element| DomRoot::from_ref(&**media_element))
     }
 
     pub fn item(&self, idx: usize) -> Option<DomRoot<TextTrack>> {
@@ -91,19 +114,36 @@ impl TextTrackList {
                 &canceller,
             );
             track.add_track_list(self);
+            track.cues_changed();
         }
     }
 
-    // FIXME(#22314, dlrobertson) allow TextTracks to be
-    // removed from the TextTrackList.
-    #[allow(dead_code)]
     pub fn remove(&self, idx: usize) {
-        if let Some(track) = self.dom_tracks.borrow().get(idx) {
-            track.remove_track_list();
-        }
+        let track = match self.item(idx) {
+            Some(track) => track,
+            None => return,
+        };
+        track.remove_track_list();
+        track.set_active_cues(&[]);
         self.dom_tracks.borrow_mut().remove(idx);
         self.upcast::<EventTarget>()
             .fire_event(atom!("removetrack"));
+        if let Some(media_element) = self.media_element() {
+            media_element.text_tracks_changed();
+        }
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#text-track-mode>
+    pub fn mode_changed(&self) {
+        let global = self.global();
+        let window = global.as_window();
+        window
+            .task_manager()
+            .media_element_task_source()
+            .queue_simple_event(self.upcast(), atom!("change"), window);
+        if let Some(media_element) = self.media_element() {
+            media_element.text_tracks_changed();
+        }
     }
 }
 
@@ -124,7 +164,7 @@ impl TextTrackListMethods for TextTrackList {
         self.dom_tracks
             .borrow()
             .iter()
-            .filter(|track| track.id() == &id_str)
+            .filter(|track| track.id() == id_str)
             .next()
             .map(|t| DomRoot::from_ref(&**t))
     }
@@ -0,0 +1,416 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::callback::ExceptionHandling;
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::QueuingStrategyBinding::QueuingStrategy;
+use crate::dom::bindings::codegen::Bindings::TransformStreamBinding::TransformStreamMethods;
+use crate::dom::bindings::codegen::Bindings::TransformerBinding::Transformer;
+use crate::dom::bindings::conversions::ConversionResult;
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::promisenativehandler::{Callback, PromiseNativeHandler};
+use crate::dom::queuingstrategy::{extract_high_water_mark, extract_size_algorithm, SizeAlgorithm};
+use crate::dom::readablestream::{ReadableStream, RejectWithReason, ResolveWithUndefined};
+use crate::dom::transformstreamdefaultcontroller::{
+    TransformStreamDefaultController, TransformerType,
+};
+use crate::dom::underlyingsourcecontainer::{resolved_promise, UnderlyingSourceType};
+use crate::dom::writablestream::WritableStream;
+use crate::dom::writablestreamdefaultcontroller::UnderlyingSinkType;
+use crate::realms::{enter_realm, InRealm};
+use crate::script_runtime::JSContext;
+use dom_struct::dom_struct;
+use js::jsapi::{Heap, JSObject};
+use js::jsval::{JSVal, ObjectValue, UndefinedValue};
+use js::rust::HandleValue;
+use std::cell::Cell;
+use std::ptr;
+use std::rc::Rc;
+
+/// <https://streams.spec.whatwg.org/#ts-class>
+#[dom_struct]
+pub struct TransformStream {
+    reflector_: Reflector,
+    /// <https://streams.spec.whatwg.org/#transformstream-backpressure>
+    backpressure: Cell<bool>,
+    /// <https://streams.spec.whatwg.org/#transformstream-backpressurechangepromise>
+    #[ignore_malloc_size_of = "Rc is hard"]
+    backpressure_change_promise: DomRefCell<Option<Rc<Promise>>>,
+    /// <https://streams.spec.whatwg.org/#transformstream-controller>
+    controller: MutNullableDom<TransformStreamDefaultController>,
+    /// <https://streams.spec.whatwg.org/#transformstream-readable>
+    readable: MutNullableDom<ReadableStream>,
+    /// <https://streams.spec.whatwg.org/#transformstream-writable>
+    writable: MutNullableDom<WritableStream>,
+    /// The promise returned by the start algorithms of both sides, which
+    /// settles once the transformer has started.
+    #[ignore_malloc_size_of = "Rc is hard"]
+    start_promise: Rc<Promise>,
+}
+
+impl TransformStream {
+    fn new_inherited(global: &GlobalScope) -> TransformStream {
+        TransformStream {
+            reflector_: Reflector::new(),
+            backpressure: Cell::new(false),
+            backpressure_change_promise: DomRefCell::new(None),
+            controller: MutNullableDom::new(None),
+            readable: MutNullableDom::new(None),
+            writable: MutNullableDom::new(None),
+            start_promise: Promise::new(global),
+        }
+    }
+
+    fn new(global: &GlobalScope) -> DomRoot<TransformStream> {
+        reflect_dom_object(Box::new(TransformStream::new_inherited(global)), global)
+    }
+
+    /// <https://streams.spec.whatwg.org/#ts-constructor>
+    #[allow(non_snake_case)]
+    pub fn Constructor(
+        cx: JSContext,
+        global: &GlobalScope,
+        transformer: Option<*mut JSObject>,
+        writable_strategy: &QueuingStrategy,
+        readable_strategy: &QueuingStrategy,
+    ) -> Fallible<DomRoot<TransformStream>> {
+        // Steps 1-2.
+        let transformer_obj = transformer.unwrap_or(ptr::null_mut());
+        rooted!(in(*cx) let transformer_val = if transformer_obj.is_null() {
+            UndefinedValue()
+        } else {
+            ObjectValue(transformer_obj)
+        });
+        let transformer_dict = match Transformer::new(cx, transformer_val.handle()) {
+            Ok(ConversionResult::Success(dict)) => dict,
+            Ok(ConversionResult::Failure(error)) => return Err(Error::Type(error.to_string())),
+            Err(()) => return Err(Error::JSFailed),
+        };
+
+        // Steps 3-4.
+        if !transformer_dict.readableType.get().is_undefined() {
+            return Err(Error::Range("Invalid readable type".to_owned()));
+        }
+        if !transformer_dict.writableType.get().is_undefined() {
+            return Err(Error::Range("Invalid writable type".to_owned()));
+        }
+
+        // Steps 5-8.
+        let readable_high_water_mark = extract_high_water_mark(readable_strategy, 0.)?;
+        let readable_size_algorithm = extract_size_algorithm(readable_strategy);
+        let writable_high_water_mark = extract_high_water_mark(writable_strategy, 1.)?;
+        let writable_size_algorithm = extract_size_algorithm(writable_strategy);
+
+        // Steps 9-10.
+        let stream = TransformStream::new(global);
+        stream.initialize(
+            writable_high_water_mark,
+            writable_size_algorithm,
+            readable_high_water_mark,
+            readable_size_algorithm,
+        )?;
+
+        // Step 11.
+        let controller = TransformStreamDefaultController::set_up(
+            &stream,
+            TransformerType::new_js(transformer_obj, &transformer_dict),
+        );
+
+        // Steps 12-13.
+        match transformer_dict.start {
+            Some(ref start) => {
+                rooted!(in(*cx) let this_object = transformer_obj);
+                let result = start.Call_(
+                    &this_object.handle(),
+                    &controller,
+                    ExceptionHandling::Rethrow,
+                )?;
+                rooted!(in(*cx) let result = result);
+                stream.start_promise.resolve(cx, result.handle());
+            },
+            None => stream.start_promise.resolve_native(&()),
+        }
+
+        Ok(stream)
+    }
+
+    /// <https://streams.spec.whatwg.org/#transformstream-set-up>
+    ///
+    /// Creates a stream for other specifications, whose transformer is
+    /// implemented natively.
+    pub fn new_with_transformer(
+        global: &GlobalScope,
+        transformer: TransformerType,
+    ) -> Fallible<DomRoot<TransformStream>> {
+        // Steps 1-6.
+        let stream = TransformStream::new(global);
+        stream.initialize(1., SizeAlgorithm::ReturnOne, 0., SizeAlgorithm::ReturnOne)?;
+
+        // Steps 7-9.
+        TransformStreamDefaultController::set_up(&stream, transformer);
+        stream.start_promise.resolve_native(&());
+        Ok(stream)
+    }
+
+    /// <https://streams.spec.whatwg.org/#initialize-transform-stream>
+    #[allow(unrooted_must_root)]
+    fn initialize(
+        &self,
+        writable_high_water_mark: f64,
+        writable_size_algorithm: SizeAlgorithm,
+        readable_high_water_mark: f64,
+        readable_size_algorithm: SizeAlgorithm,
+    ) -> Fallible<()> {
+        let global = self.global();
+
+        // Steps 1-5.
+        let writable = WritableStream::new_with_sink(
+            &global,
+            UnderlyingSinkType::Transform(Dom::from_ref(self)),
+            writable_high_water_mark,
+            writable_size_algorithm,
+        )?;
+        self.writable.set(Some(&writable));
+
+        // Steps 6-10.
+        let readable = ReadableStream::new_with_source(
+            &global,
+            UnderlyingSourceType::Transform(Dom::from_ref(self)),
+            readable_high_water_mark,
+            readable_size_algorithm,
+        )?;
+        self.readable.set(Some(&readable));
+
+        // Steps 11-13.
+        self.set_backpressure(true);
+        Ok(())
+    }
+
+    pub fn set_controller(&self, controller: &TransformStreamDefaultController) {
+        self.controller.set(Some(controller));
+    }
+
+    fn controller(&self) -> DomRoot<TransformStreamDefaultController> {
+        self.controller
+            .get()
+            .expect("Stream should have a controller")
+    }
+
+    pub fn get_readable(&self) -> DomRoot<ReadableStream> {
+        self.readable
+            .get()
+            .expect("Stream should have a readable side")
+    }
+
+    pub fn get_writable(&self) -> DomRoot<WritableStream> {
+        self.writable
+            .get()
+            .expect("Stream should have a writable side")
+    }
+
+    pub fn get_backpressure(&self) -> bool {
+        self.backpressure.get()
+    }
+
+    pub fn start_promise(&self) -> Rc<Promise> {
+        self.start_promise.clone()
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-error>
+    pub fn error(&self, e: HandleValue) {
+        self.get_readable().get_default_controller().error(e);
+        self.error_writable_and_unblock_write(e);
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-error-writable-and-unblock-write>
+    pub fn error_writable_and_unblock_write(&self, e: HandleValue) {
+        // Step 2.
+        self.get_writable().get_controller().error_if_needed(e);
+
+        // Step 3.
+        if self.backpressure.get() {
+            self.set_backpressure(false);
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-set-backpressure>
+    pub fn set_backpressure(&self, backpressure: bool) {
+        // Step 2.
+        let previous = self.backpressure_change_promise.borrow_mut().take();
+        if let Some(previous) = previous {
+            previous.resolve_native(&());
+        }
+
+        // Steps 3-4.
+        *self.backpressure_change_promise.borrow_mut() = Some(Promise::new(&self.global()));
+        self.backpressure.set(backpressure);
+    }
+
+    fn backpressure_change_promise(&self) -> Rc<Promise> {
+        self.backpressure_change_promise
+            .borrow()
+            .clone()
+            .expect("Stream should have a backpressure change promise")
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-default-sink-write-algorithm>
+    pub fn sink_write_algorithm(&self, chunk: HandleValue) -> Rc<Promise> {
+        // Step 3.
+        if self.backpressure.get() {
+            let global = self.global();
+            let promise = Promise::new(&global);
+            let handler = PromiseNativeHandler::new(
+                &global,
+                Some(Box::new(BackpressureChangeHandler {
+                    stream: DomRoot::from_ref(self),
+                    chunk: Heap::boxed(chunk.get()),
+                    promise: promise.clone(),
+                })),
+                Some(Box::new(RejectWithReason {
+                    promise: promise.clone(),
+                })),
+            );
+            let realm = enter_realm(&*global);
+            let comp = InRealm::Entered(&realm);
+            self.backpressure_change_promise()
+                .append_native_handler(&handler, comp);
+            return promise;
+        }
+
+        // Step 4.
+        self.controller().perform_transform(chunk)
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-default-sink-abort-algorithm>
+    pub fn sink_abort_algorithm(&self, reason: HandleValue) -> Rc<Promise> {
+        self.error(reason);
+        resolved_promise(&self.global())
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-default-sink-close-algorithm>
+    pub fn sink_close_algorithm(&self) -> Rc<Promise> {
+        let global = self.global();
+
+        // Steps 1-4.
+        let flush_promise = self.controller().flush();
+
+        // Step 5.
+        let promise = Promise::new(&global);
+        let handler = PromiseNativeHandler::new(
+            &global,
+            Some(Box::new(FlushHandler {
+                stream: DomRoot::from_ref(self),
+                promise: promise.clone(),
+                fulfilled: true,
+            })),
+            Some(Box::new(FlushHandler {
+                stream: DomRoot::from_ref(self),
+                promise: promise.clone(),
+                fulfilled: false,
+            })),
+        );
+        let realm = enter_realm(&*global);
+        let comp = InRealm::Entered(&realm);
+        flush_promise.append_native_handler(&handler, comp);
+        promise
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-default-source-pull>
+    pub fn source_pull_algorithm(&self) -> Rc<Promise> {
+        self.set_backpressure(false);
+        self.backpressure_change_promise()
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-default-source-cancel>
+    pub fn source_cancel_algorithm(&self, reason: HandleValue) -> Rc<Promise> {
+        self.error_writable_and_unblock_write(reason);
+        resolved_promise(&self.global())
+    }
+}
+
+impl TransformStreamMethods for TransformStream {
+    /// <https://streams.spec.whatwg.org/#ts-readable>
+    fn Readable(&self) -> DomRoot<ReadableStream> {
+        self.get_readable()
+    }
+
+    /// <https://streams.spec.whatwg.org/#ts-writable>
+    fn Writable(&self) -> DomRoot<WritableStream> {
+        self.get_writable()
+    }
+}
+
+/// Step 3.2 of <https://streams.spec.whatwg.org/#transform-stream-default-sink-write-algorithm>.
+#[derive(JSTraceable, MallocSizeOf)]
+struct BackpressureChangeHandler {
+    stream: DomRoot<TransformStream>,
+    #[ignore_malloc_size_of = "Defined in rust-mozjs"]
+    chunk: Box<Heap<JSVal>>,
+    #[ignore_malloc_size_of = "Rc is hard"]
+    promise: Rc<Promise>,
+}
+
+impl Callback for BackpressureChangeHandler {
+    fn callback(&self, cx: JSContext, _v: HandleValue, realm: InRealm) {
+        // Steps 3.2.1-3.2.3.
+        let writable = self.stream.get_writable();
+        if writable.is_erroring() {
+            rooted!(in(*cx) let mut stored_error = UndefinedValue());
+            writable.get_stored_error(stored_error.handle_mut());
+            self.promise.reject(cx, stored_error.handle());
+            return;
+        }
+
+        // Step 3.2.5.
+        rooted!(in(*cx) let chunk = self.chunk.get());
+        let transform_promise = self.stream.controller().perform_transform(chunk.handle());
+        let handler = PromiseNativeHandler::new(
+            &self.stream.global(),
+            Some(Box::new(ResolveWithUndefined {
+                promise: self.promise.clone(),
+            })),
+            Some(Box::new(RejectWithReason {
+                promise: self.promise.clone(),
+            })),
+        );
+        transform_promise.append_native_handler(&handler, realm);
+    }
+}
+
+/// Step 5 of <https://streams.spec.whatwg.org/#transform-stream-default-sink-close-algorithm>.
+#[derive(JSTraceable, MallocSizeOf)]
+struct FlushHandler {
+    stream: DomRoot<TransformStream>,
+    #[ignore_malloc_size_of = "Rc is hard"]
+    promise: Rc<Promise>,
+    fulfilled: bool,
+}
+
+impl Callback for FlushHandler {
+    fn callback(&self, cx: JSContext, v: HandleValue, _realm: InRealm) {
+        let readable = self.stream.get_readable();
+        rooted!(in(*cx) let mut stored_error = UndefinedValue());
+        if self.fulfilled {
+            // Step 5.1.
+            if readable.is_errored() {
+                readable.get_stored_error(stored_error.handle_mut());
+                self.promise.reject(cx, stored_error.handle());
+                return;
+            }
+
+            // Step 5.2.
+            readable.get_default_controller().close();
+            self.promise.resolve_native(&());
+        } else {
+            // Steps 5.3.1-5.3.2.
+            self.stream.error(v);
+            readable.get_stored_error(stored_error.handle_mut());
+            self.promise.reject(cx, stored_error.handle());
+        }
+    }
+}
@@ -0,0 +1,338 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::callback::ExceptionHandling;
+use crate::dom::bindings::codegen::Bindings::TransformStreamDefaultControllerBinding::TransformStreamDefaultControllerMethods;
+use crate::dom::bindings::codegen::Bindings::TransformerBinding::{
+    Transformer, TransformerFlushCallback, TransformerTransformCallback,
+};
+use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{DomRoot, MutNullableDom};
+use crate::dom::compressionstream::CompressionTransformer;
+use crate::dom::decompressionstream::DecompressionTransformer;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::promisenativehandler::{Callback, PromiseNativeHandler};
+use crate::dom::readablestream::{create_uint8_array, new_type_error, ResolveWithUndefined};
+use crate::dom::textdecoderstream::TextDecoderTransformer;
+use crate::dom::textencoderstream::TextEncoderTransformer;
+use crate::dom::transformstream::TransformStream;
+use crate::dom::underlyingsourcecontainer::{promise_from_result, resolved_promise};
+use crate::realms::{enter_realm, InRealm};
+use crate::script_runtime::JSContext;
+use dom_struct::dom_struct;
+use js::jsapi::{ExceptionStackBehavior, Heap, JSObject, JS_ClearPendingException};
+use js::jsval::UndefinedValue;
+use js::rust::wrappers::JS_SetPendingException;
+use js::rust::HandleValue;
+use js::typedarray::{ArrayBuffer, ArrayBufferView};
+use std::rc::Rc;
+
+/// A transformer set up from script with the `TransformStream` constructor.
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+pub struct JsTransformer {
+    /// The object the callbacks were taken from, which is their `this` value.
+    #[ignore_malloc_size_of = "Defined in rust-mozjs"]
+    object: Box<Heap<*mut JSObject>>,
+    #[ignore_malloc_size_of = "Rc is hard"]
+    transform: Option<Rc<TransformerTransformCallback>>,
+    #[ignore_malloc_size_of = "Rc is hard"]
+    flush: Option<Rc<TransformerFlushCallback>>,
+}
+
+/// <https://streams.spec.whatwg.org/#transformer-api>
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+pub enum TransformerType {
+    /// A transformer set up from script.
+    Js(JsTransformer),
+    /// The transformer of a `TextEncoderStream`.
+    TextEncoder(TextEncoderTransformer),
+    /// The transformer of a `TextDecoderStream`.
+    TextDecoder(TextDecoderTransformer),
+    /// The transformer of a `CompressionStream`.
+    Compression(CompressionTransformer),
+    /// The transformer of a `DecompressionStream`.
+    Decompression(DecompressionTransformer),
+}
+
+impl TransformerType {
+    /// Takes the algorithms of a transformer from its dictionary.
+    pub fn new_js(object: *mut JSObject, dict: &Transformer) -> TransformerType {
+        TransformerType::Js(JsTransformer {
+            object: Heap::boxed(object),
+            transform: dict.transform.clone(),
+            flush: dict.flush.clone(),
+        })
+    }
+
+    /// The transform algorithm.
+    fn transform(
+        &self,
+        global: &GlobalScope,
+        chunk: HandleValue,
+        controller: &TransformStreamDefaultController,
+    ) -> Rc<Promise> {
+        let cx = global.get_cx();
+        // Chunks and errors created by the native transformers belong to the
+        // realm of the stream, not to whichever realm called write().
+        let _ac = enter_realm(global);
+        let result = match *self {
+            TransformerType::Js(ref transformer) => match transformer.transform {
+                Some(ref transform) => {
+                    rooted!(in(*cx) let this_object = transformer.object.get());
+                    let result = transform.Call_(
+                        &this_object.handle(),
+                        chunk,
+                        controller,
+                        ExceptionHandling::Rethrow,
+                    );
+                    return promise_from_result(global, result);
+                },
+                // Step 2 of
+                // <https://streams.spec.whatwg.org/#set-up-transform-stream-default-controller-from-transformer>.
+                None => controller.enqueue(chunk),
+            },
+            TransformerType::TextEncoder(ref transformer) => {
+                transformer.transform(cx, controller, chunk)
+            },
+            TransformerType::TextDecoder(ref transformer) => {
+                transformer.transform(cx, controller, chunk)
+            },
+            TransformerType::Compression(ref transformer) => {
+                transformer.transform(cx, controller, chunk)
+            },
+            TransformerType::Decompression(ref transformer) => {
+                transformer.transform(cx, controller, chunk)
+            },
+        };
+        promise_from_result(global, result.map(|()| UndefinedValue()))
+    }
+
+    /// The flush algorithm.
+    fn flush(
+        &self,
+        global: &GlobalScope,
+        controller: &TransformStreamDefaultController,
+    ) -> Rc<Promise> {
+        let cx = global.get_cx();
+        let _ac = enter_realm(global);
+        let result = match *self {
+            TransformerType::Js(ref transformer) => match transformer.flush {
+                Some(ref flush) => {
+                    rooted!(in(*cx) let this_object = transformer.object.get());
+                    let result = flush.Call_(
+                        &this_object.handle(),
+                        controller,
+                        ExceptionHandling::Rethrow,
+                    );
+                    return promise_from_result(global, result);
+                },
+                None => return resolved_promise(global),
+            },
+            TransformerType::TextEncoder(ref transformer) => transformer.flush(cx, controller),
+            TransformerType::TextDecoder(ref transformer) => transformer.flush(cx, controller),
+            TransformerType::Compression(ref transformer) => transformer.flush(cx, controller),
+            TransformerType::Decompression(ref transformer) => transformer.flush(cx, controller),
+        };
+        promise_from_result(global, result.map(|()| UndefinedValue()))
+    }
+}
+
+/// <https://streams.spec.whatwg.org/#ts-default-controller-class>
+#[dom_struct]
+pub struct TransformStreamDefaultController {
+    reflector_: Reflector,
+    /// <https://streams.spec.whatwg.org/#transformstreamdefaultcontroller-stream>
+    stream: MutNullableDom<TransformStream>,
+    /// The transformer of the transform and flush algorithms.
+    transformer: TransformerType,
+}
+
+impl TransformStreamDefaultController {
+    #[allow(unrooted_must_root)]
+    fn new_inherited(transformer: TransformerType) -> TransformStreamDefaultController {
+        TransformStreamDefaultController {
+            reflector_: Reflector::new(),
+            stream: MutNullableDom::new(None),
+            transformer,
+        }
+    }
+
+    #[allow(unrooted_must_root)]
+    fn new(
+        global: &GlobalScope,
+        transformer: TransformerType,
+    ) -> DomRoot<TransformStreamDefaultController> {
+        reflect_dom_object(
+            Box::new(TransformStreamDefaultController::new_inherited(transformer)),
+            global,
+        )
+    }
+
+    /// <https://streams.spec.whatwg.org/#set-up-transform-stream-default-controller>
+    #[allow(unrooted_must_root)]
+    pub fn set_up(
+        stream: &TransformStream,
+        transformer: TransformerType,
+    ) -> DomRoot<TransformStreamDefaultController> {
+        let controller = TransformStreamDefaultController::new(&stream.global(), transformer);
+        controller.stream.set(Some(stream));
+        stream.set_controller(&controller);
+        controller
+    }
+
+    fn stream(&self) -> DomRoot<TransformStream> {
+        self.stream.get().expect("Controller should have a stream")
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-default-controller-enqueue>
+    #[allow(unsafe_code)]
+    pub fn enqueue(&self, chunk: HandleValue) -> ErrorResult {
+        // Steps 1-2.
+        let stream = self.stream();
+        let readable = stream.get_readable();
+        let readable_controller = readable.get_default_controller();
+
+        // Step 3.
+        if !readable_controller.can_close_or_enqueue() {
+            return Err(Error::Type(
+                "The readable side cannot be enqueued to".to_owned(),
+            ));
+        }
+
+        // Steps 4-5.
+        if readable_controller.enqueue(chunk).is_err() {
+            // The readable side was errored with the exception, which is
+            // rethrown once the writable side is errored as well.
+            let cx = self.global().get_cx();
+            unsafe { JS_ClearPendingException(*cx) };
+            rooted!(in(*cx) let mut stored_error = UndefinedValue());
+            readable.get_stored_error(stored_error.handle_mut());
+            stream.error_writable_and_unblock_write(stored_error.handle());
+            unsafe {
+                JS_SetPendingException(*cx, stored_error.handle(), ExceptionStackBehavior::Capture)
+            };
+            return Err(Error::JSFailed);
+        }
+
+        // Steps 6-7.
+        let backpressure = readable_controller.has_backpressure();
+        if backpressure != stream.get_backpressure() {
+            assert!(backpressure);
+            stream.set_backpressure(true);
+        }
+        Ok(())
+    }
+
+    /// Enqueues a `Uint8Array` with a copy of `bytes`, for native transformers.
+    pub fn enqueue_bytes(&self, cx: JSContext, bytes: &[u8]) -> ErrorResult {
+        rooted!(in(*cx) let mut chunk = UndefinedValue());
+        create_uint8_array(cx, bytes, chunk.handle_mut());
+        self.enqueue(chunk.handle())
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-default-controller-terminate>
+    pub fn terminate(&self) {
+        // Steps 1-3.
+        let stream = self.stream();
+        stream.get_readable().get_default_controller().close();
+
+        // Steps 4-5.
+        let global = self.global();
+        let cx = global.get_cx();
+        rooted!(in(*cx) let mut error = UndefinedValue());
+        new_type_error(&global, "The stream was terminated", error.handle_mut());
+        stream.error_writable_and_unblock_write(error.handle());
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-default-controller-perform-transform>
+    pub fn perform_transform(&self, chunk: HandleValue) -> Rc<Promise> {
+        let global = self.global();
+
+        // Step 1.
+        let transform_promise = self.transformer.transform(&global, chunk, self);
+
+        // Step 2.
+        let promise = Promise::new(&global);
+        let handler = PromiseNativeHandler::new(
+            &global,
+            Some(Box::new(ResolveWithUndefined {
+                promise: promise.clone(),
+            })),
+            Some(Box::new(TransformRejectionHandler {
+                stream: self.stream(),
+                promise: promise.clone(),
+            })),
+        );
+        let realm = enter_realm(&*global);
+        let comp = InRealm::Entered(&realm);
+        transform_promise.append_native_handler(&handler, comp);
+        promise
+    }
+
+    /// The flush algorithm, called by
+    /// <https://streams.spec.whatwg.org/#transform-stream-default-sink-close-algorithm>.
+    pub fn flush(&self) -> Rc<Promise> {
+        self.transformer.flush(&self.global(), self)
+    }
+}
+
+impl TransformStreamDefaultControllerMethods for TransformStreamDefaultController {
+    /// <https://streams.spec.whatwg.org/#ts-default-controller-desired-size>
+    fn GetDesiredSize(&self) -> Option<f64> {
+        self.stream()
+            .get_readable()
+            .get_default_controller()
+            .get_desired_size()
+    }
+
+    /// <https://streams.spec.whatwg.org/#ts-default-controller-enqueue>
+    fn Enqueue(&self, _cx: JSContext, chunk: HandleValue) -> ErrorResult {
+        self.enqueue(chunk)
+    }
+
+    /// <https://streams.spec.whatwg.org/#ts-default-controller-error>
+    fn Error(&self, _cx: JSContext, reason: HandleValue) {
+        self.stream().error(reason);
+    }
+
+    /// <https://streams.spec.whatwg.org/#ts-default-controller-terminate>
+    fn Terminate(&self) -> ErrorResult {
+        self.terminate();
+        Ok(())
+    }
+}
+
+/// Step 2 of <https://streams.spec.whatwg.org/#transform-stream-default-controller-perform-transform>.
+#[derive(JSTraceable, MallocSizeOf)]
+struct TransformRejectionHandler {
+    stream: DomRoot<TransformStream>,
+    #[ignore_malloc_size_of = "Rc is hard"]
+    promise: Rc<Promise>,
+}
+
+impl Callback for TransformRejectionHandler {
+    fn callback(&self, cx: JSContext, v: HandleValue, _realm: InRealm) {
+        self.stream.error(v);
+        self.promise.reject(cx, v);
+    }
+}
+
+/// Copies the bytes of a chunk that must be a `BufferSource`.
+#[allow(unsafe_code)]
+pub fn buffer_source_bytes(chunk: HandleValue) -> Fallible<Vec<u8>> {
+    if chunk.is_object() {
+        let object = chunk.to_object();
+        if let Ok(view) = unsafe { ArrayBufferView::from(object) } {
+            return Ok(unsafe { view.as_slice() }.to_vec());
+        }
+        if let Ok(buffer) = unsafe { ArrayBuffer::from(object) } {
+            return Ok(unsafe { buffer.as_slice() }.to_vec());
+        }
+    }
+    Err(Error::Type("The chunk is not a BufferSource".to_owned()))
+}
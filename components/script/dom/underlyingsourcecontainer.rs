@@ -0,0 +1,183 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The underlying sources of readable streams, and the algorithms that are
+//! derived from them.
+
+use crate::dom::bindings::callback::ExceptionHandling;
+use crate::dom::bindings::codegen::Bindings::UnderlyingSourceBinding::{
+    UnderlyingSource, UnderlyingSourceCancelCallback, UnderlyingSourcePullCallback,
+    UnderlyingSourceStartCallback,
+};
+use crate::dom::bindings::codegen::UnionTypes::ReadableStreamDefaultControllerOrReadableByteStreamController as ReadableStreamController;
+use crate::dom::bindings::error::Fallible;
+use crate::dom::bindings::root::Dom;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::readablestream::{
+    ByteTeeState, DefaultTeeState, ExternalUnderlyingSource, TeeBranch,
+};
+use crate::dom::transformstream::TransformStream;
+use crate::script_runtime::JSContext;
+use js::jsapi::{Heap, JSObject};
+use js::jsval::{JSVal, ObjectValue, UndefinedValue};
+use js::rust::{HandleValue, MutableHandleValue};
+use std::rc::Rc;
+
+/// An underlying source set up from script with the `ReadableStream` constructor.
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+pub struct JsUnderlyingSource {
+    /// The object the callbacks were taken from, which is their `this` value.
+    #[ignore_malloc_size_of = "Defined in rust-mozjs"]
+    object: Box<Heap<*mut JSObject>>,
+    #[ignore_malloc_size_of = "Rc is hard"]
+    start: Option<Rc<UnderlyingSourceStartCallback>>,
+    #[ignore_malloc_size_of = "Rc is hard"]
+    pull: Option<Rc<UnderlyingSourcePullCallback>>,
+    #[ignore_malloc_size_of = "Rc is hard"]
+    cancel: Option<Rc<UnderlyingSourceCancelCallback>>,
+}
+
+/// <https://streams.spec.whatwg.org/#underlying-source-api>
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+#[unrooted_must_root_lint::allow_unrooted_in_rc]
+pub enum UnderlyingSourceType {
+    /// A source fed by native code.
+    External(ExternalUnderlyingSource),
+    /// A source set up from script.
+    Js(JsUnderlyingSource),
+    /// A branch of a stream that was teed.
+    Tee(
+        #[ignore_malloc_size_of = "Rc is hard"] Rc<DefaultTeeState>,
+        TeeBranch,
+    ),
+    /// A branch of a byte stream that was teed.
+    ByteTee(
+        #[ignore_malloc_size_of = "Rc is hard"] Rc<ByteTeeState>,
+        TeeBranch,
+    ),
+    /// The readable side of a transform stream.
+    Transform(Dom<TransformStream>),
+}
+
+impl UnderlyingSourceType {
+    /// Takes the algorithms of a source from its dictionary.
+    pub fn new_js(object: *mut JSObject, dict: &UnderlyingSource) -> UnderlyingSourceType {
+        UnderlyingSourceType::Js(JsUnderlyingSource {
+            object: Heap::boxed(object),
+            start: dict.start.clone(),
+            pull: dict.pull.clone(),
+            cancel: dict.cancel.clone(),
+        })
+    }
+
+    /// Does the source have all its data in memory?
+    pub fn in_memory(&self) -> bool {
+        matches!(
+            *self,
+            UnderlyingSourceType::External(ExternalUnderlyingSource::Memory(_))
+        )
+    }
+
+    /// The start algorithm, whose result is resolved by the controller.
+    #[allow(unsafe_code)]
+    pub fn start(
+        &self,
+        cx: JSContext,
+        controller: ReadableStreamController,
+        mut rval: MutableHandleValue,
+    ) -> Fallible<()> {
+        match *self {
+            UnderlyingSourceType::Js(ref source) => {
+                if let Some(ref start) = source.start {
+                    rooted!(in(*cx) let this_object = source.object.get());
+                    let result = start.Call_(
+                        &this_object.handle(),
+                        controller,
+                        ExceptionHandling::Rethrow,
+                    )?;
+                    rval.set(result);
+                    return Ok(());
+                }
+            },
+            UnderlyingSourceType::Transform(ref stream) => {
+                rval.set(ObjectValue(stream.start_promise().promise_obj().get()));
+                return Ok(());
+            },
+            _ => {},
+        }
+        rval.set(UndefinedValue());
+        Ok(())
+    }
+
+    /// The pull algorithm.
+    pub fn pull(&self, global: &GlobalScope, controller: ReadableStreamController) -> Rc<Promise> {
+        let cx = global.get_cx();
+        match *self {
+            UnderlyingSourceType::Js(ref source) => match source.pull {
+                Some(ref pull) => {
+                    rooted!(in(*cx) let this_object = source.object.get());
+                    let result = pull.Call_(
+                        &this_object.handle(),
+                        controller,
+                        ExceptionHandling::Rethrow,
+                    );
+                    promise_from_result(global, result)
+                },
+                None => resolved_promise(global),
+            },
+            UnderlyingSourceType::Tee(ref state, _) => state.pull(),
+            UnderlyingSourceType::ByteTee(ref state, branch) => state.pull(branch),
+            UnderlyingSourceType::Transform(ref stream) => stream.source_pull_algorithm(),
+            UnderlyingSourceType::External(_) => resolved_promise(global),
+        }
+    }
+
+    /// The cancel algorithm.
+    pub fn cancel(&self, global: &GlobalScope, reason: HandleValue) -> Rc<Promise> {
+        let cx = global.get_cx();
+        match *self {
+            UnderlyingSourceType::Js(ref source) => match source.cancel {
+                Some(ref cancel) => {
+                    rooted!(in(*cx) let this_object = source.object.get());
+                    let result =
+                        cancel.Call_(&this_object.handle(), reason, ExceptionHandling::Rethrow);
+                    promise_from_result(global, result)
+                },
+                None => resolved_promise(global),
+            },
+            UnderlyingSourceType::Tee(ref state, branch) => state.cancel(branch, reason),
+            UnderlyingSourceType::ByteTee(ref state, branch) => state.cancel(branch, reason),
+            UnderlyingSourceType::Transform(ref stream) => stream.source_cancel_algorithm(reason),
+            UnderlyingSourceType::External(_) => resolved_promise(global),
+        }
+    }
+}
+
+/// A promise resolved with undefined.
+pub fn resolved_promise(global: &GlobalScope) -> Rc<Promise> {
+    let promise = Promise::new(global);
+    promise.resolve_native(&());
+    promise
+}
+
+/// A promise resolved with the result of invoking a callback, or rejected with
+/// the exception it threw.
+#[allow(unsafe_code)]
+pub fn promise_from_result(global: &GlobalScope, result: Fallible<JSVal>) -> Rc<Promise> {
+    let cx = global.get_cx();
+    match result {
+        Ok(value) => {
+            rooted!(in(*cx) let value = value);
+            Promise::new_resolved(global, cx, value.handle()).expect("Couldn't resolve a promise")
+        },
+        Err(error) => {
+            rooted!(in(*cx) let mut rval = UndefinedValue());
+            unsafe { error.to_jsval(*cx, global, rval.handle_mut()) };
+            Promise::new_rejected(global, cx, rval.handle()).expect("Couldn't reject a promise")
+        },
+    }
+}
@@ -10,6 +10,7 @@ use crate::dom::bindings::root::{DomRoot, MutNullableDom};
 use crate::dom::bindings::str::{DOMString, USVString};
 use crate::dom::blob::Blob;
 use crate::dom::globalscope::GlobalScope;
+use crate::dom::mediasource::MediaSource;
 use crate::dom::urlhelper::UrlHelper;
 use crate::dom::urlsearchparams::URLSearchParams;
 use dom_struct::dom_struct;
@@ -122,6 +123,14 @@ impl URL {
         DOMString::from(URL::unicode_serialization_blob_url(&origin, &id))
     }
 
+    // https://w3c.github.io/media-source/#dom-url-createobjecturl
+    pub fn CreateObjectURL_(global: &GlobalScope, media_source: &MediaSource) -> DOMString {
+        let origin = get_blob_origin(&global.get_url());
+        let url = URL::unicode_serialization_blob_url(&origin, &Uuid::new_v4());
+        global.add_media_source_url(ServoUrl::parse(&url).unwrap(), media_source);
+        DOMString::from(url)
+    }
+
     // https://w3c.github.io/FileAPI/#dfn-revokeObjectURL
     pub fn RevokeObjectURL(global: &GlobalScope, url: DOMString) {
         // If the value provided for the url argument is not a Blob URL OR
@@ -130,6 +139,9 @@ impl URL {
         let origin = get_blob_origin(&global.get_url());
 
         if let Ok(url) = ServoUrl::parse(&url) {
+            if global.remove_media_source_url(&url) {
+                return;
+            }
             if url.fragment().is_none() && origin == get_blob_origin(&url) {
                 if let Ok((id, _)) = parse_blob_url(&url) {
                     let resource_threads = global.resource_threads();
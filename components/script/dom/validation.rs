@@ -74,10 +74,7 @@ pub trait Validatable {
 
         // Step 1.2.
         if !event.DefaultPrevented() {
-            println!(
-                "Validation error: {}",
-                validation_message_for_flags(&self.validity_state(), flags)
-            );
+            debug!("Validation error: {}", self.validation_message());
             if let Some(html_elem) = self.as_element().downcast::<HTMLElement>() {
                 html_elem.Focus();
             }
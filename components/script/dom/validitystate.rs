@@ -15,6 +15,7 @@ use std::fmt;
 
 // https://html.spec.whatwg.org/multipage/#validity-states
 bitflags! {
+    #[derive(JSTraceable, MallocSizeOf)]
     pub struct ValidationFlags: u32 {
         const VALUE_MISSING    = 0b0000000001;
         const TYPE_MISMATCH    = 0b0000000010;
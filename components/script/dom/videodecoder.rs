@@ -0,0 +1,380 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::callback::ExceptionHandling;
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::VideoDecoderBinding::{
+    VideoDecoderConfig, VideoDecoderInit, VideoDecoderMethods, VideoDecoderSupport,
+    VideoFrameOutputCallback,
+};
+use crate::dom::bindings::codegen::Bindings::WebCodecsBinding::{
+    CodecState, WebCodecsErrorCallback,
+};
+use crate::dom::bindings::error::{Error, ErrorResult};
+use crate::dom::bindings::refcounted::Trusted;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::bindings::trace::RootedTraceableBox;
+use crate::dom::domexception::{DOMErrorName, DOMException};
+use crate::dom::encodedvideochunk::EncodedVideoChunk;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::videoframe::VideoFrame;
+use crate::dom::webcodecs;
+use crate::task_source::{TaskSource, TaskSourceName};
+use dom_struct::dom_struct;
+use ipc_channel::ipc;
+use ipc_channel::router::ROUTER;
+use media::codecs::{self, CodecKind, Decoder, DecoderEvent, DecoderSettings};
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+/// https://w3c.github.io/webcodecs/#videodecoder-interface
+#[dom_struct]
+pub struct VideoDecoder {
+    reflector_: Reflector,
+    /// https://w3c.github.io/webcodecs/#dom-videodecoder-output-callback-slot
+    #[ignore_malloc_size_of = "Rc"]
+    output_callback: Rc<VideoFrameOutputCallback>,
+    /// https://w3c.github.io/webcodecs/#dom-videodecoder-error-callback-slot
+    #[ignore_malloc_size_of = "Rc"]
+    error_callback: Rc<WebCodecsErrorCallback>,
+    /// https://w3c.github.io/webcodecs/#dom-videodecoder-state
+    state: Cell<CodecState>,
+    /// https://w3c.github.io/webcodecs/#dom-videodecoder-decodequeuesize
+    decode_queue_size: Cell<u32>,
+    /// https://w3c.github.io/webcodecs/#dom-videodecoder-key-chunk-required-slot
+    key_chunk_required: Cell<bool>,
+    /// https://w3c.github.io/webcodecs/#dom-videodecoder-pending-flush-promises-slot
+    #[ignore_malloc_size_of = "promises are hard"]
+    pending_flush_promises: DomRefCell<VecDeque<Rc<Promise>>>,
+    /// Bumped whenever the decoder is reset, so that the queued control
+    /// messages and the backend events of the previous configuration are
+    /// dropped.
+    generation: Cell<u32>,
+    #[ignore_malloc_size_of = "Arc"]
+    decoder: DomRefCell<Option<Arc<Mutex<dyn Decoder>>>>,
+}
+
+impl VideoDecoder {
+    fn new_inherited(init: &VideoDecoderInit) -> VideoDecoder {
+        VideoDecoder {
+            reflector_: Reflector::new(),
+            output_callback: init.output.clone(),
+            error_callback: init.error.clone(),
+            state: Cell::new(CodecState::Unconfigured),
+            decode_queue_size: Cell::new(0),
+            key_chunk_required: Cell::new(true),
+            pending_flush_promises: DomRefCell::new(VecDeque::new()),
+            generation: Cell::new(0),
+            decoder: DomRefCell::new(None),
+        }
+    }
+
+    fn new(global: &GlobalScope, init: &VideoDecoderInit) -> DomRoot<VideoDecoder> {
+        reflect_dom_object(Box::new(VideoDecoder::new_inherited(init)), global)
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-videodecoder-videodecoder
+    #[allow(non_snake_case)]
+    pub fn Constructor(global: &GlobalScope, init: &VideoDecoderInit) -> DomRoot<VideoDecoder> {
+        VideoDecoder::new(global, init)
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-videodecoder-isconfigsupported
+    #[allow(non_snake_case)]
+    pub fn IsConfigSupported(
+        global: &GlobalScope,
+        config: RootedTraceableBox<VideoDecoderConfig>,
+    ) -> Rc<Promise> {
+        let promise = Promise::new(global);
+
+        // Step 1.
+        if !is_valid_config(&config) {
+            promise.reject_error(Error::Type("Invalid VideoDecoderConfig".to_owned()));
+            return promise;
+        }
+
+        // Steps 2-3.
+        let supported = codecs::supports_codec(&config.codec, CodecKind::VideoDecoder);
+        // The description is not echoed back, it is only meaningful to the
+        // decoder which was configured with it.
+        let echoed_config = RootedTraceableBox::new(VideoDecoderConfig {
+            codec: config.codec.clone(),
+            description: None,
+            codedWidth: config.codedWidth,
+            codedHeight: config.codedHeight,
+            displayAspectWidth: config.displayAspectWidth,
+            displayAspectHeight: config.displayAspectHeight,
+            hardwareAcceleration: config.hardwareAcceleration,
+            optimizeForLatency: config.optimizeForLatency,
+        });
+        let mut support = VideoDecoderSupport::empty();
+        support.supported = Some(supported);
+        support.config = Some(echoed_config);
+        promise.resolve_native(&*support);
+        promise
+    }
+
+    fn create_decoder(&self, settings: DecoderSettings) -> Result<Arc<Mutex<dyn Decoder>>, ()> {
+        let global = self.global();
+        let (sender, receiver) = ipc::channel::<DecoderEvent>().unwrap();
+
+        let decoder = codecs::create_decoder(settings, sender)
+            .map_err(|error| warn!("Could not create video decoder {:?}", error))?;
+
+        let this = Trusted::new(self);
+        let generation = self.generation.get();
+        let task_source = global.dom_manipulation_task_source();
+        let canceller = global.task_canceller(TaskSourceName::DOMManipulation);
+        ROUTER.add_route(
+            receiver.to_opaque(),
+            Box::new(move |message| {
+                let event = message.to().unwrap();
+                let this = this.clone();
+                let _ = task_source.queue_with_canceller(
+                    task!(handle_video_decoder_event: move || {
+                        this.root().handle_decoder_event(generation, event);
+                    }),
+                    &canceller,
+                );
+            }),
+        );
+
+        Ok(decoder)
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-videodecoder-configure
+    /// Step 5, run as a control message.
+    fn configure_backend(&self, supported: bool, settings: DecoderSettings) {
+        if let Some(decoder) = self.decoder.borrow_mut().take() {
+            decoder.lock().unwrap().close();
+        }
+        // Step 5.1.
+        if !supported {
+            return self.close_with_error(DOMErrorName::NotSupportedError);
+        }
+        // Steps 5.2-5.3.
+        match self.create_decoder(settings) {
+            Ok(decoder) => *self.decoder.borrow_mut() = Some(decoder),
+            Err(()) => self.close_with_error(DOMErrorName::NotSupportedError),
+        }
+    }
+
+    fn handle_decoder_event(&self, generation: u32, event: DecoderEvent) {
+        if generation != self.generation.get() {
+            return;
+        }
+        match event {
+            DecoderEvent::VideoFrame(frame) => {
+                // https://w3c.github.io/webcodecs/#output-videoframes
+                let frame = VideoFrame::new_from_decoded(&self.global(), frame);
+                let _ = self
+                    .output_callback
+                    .Call__(&frame, ExceptionHandling::Report);
+            },
+            DecoderEvent::AudioData { .. } => {
+                warn!("Video decoder produced audio data");
+            },
+            DecoderEvent::Flushed => {
+                // https://w3c.github.io/webcodecs/#dom-videodecoder-flush
+                // Step 5.
+                if let Some(promise) = self.pending_flush_promises.borrow_mut().pop_front() {
+                    promise.resolve_native(&());
+                }
+            },
+            DecoderEvent::Error(message) => {
+                warn!("Video decoding failed: {}", message);
+                self.close_with_error(DOMErrorName::EncodingError);
+            },
+        }
+    }
+
+    /// Queues a task to run `f` once the preceding control messages ran,
+    /// unless the decoder is reset in the meantime.
+    /// https://w3c.github.io/webcodecs/#enqueues-a-control-message
+    fn queue_control_message<F>(&self, f: F)
+    where
+        F: FnOnce(&VideoDecoder) + Send + 'static,
+    {
+        let global = self.global();
+        let this = Trusted::new(self);
+        let generation = self.generation.get();
+        let _ = global.dom_manipulation_task_source().queue(
+            task!(video_decoder_control_message: move || {
+                let this = this.root();
+                if this.generation.get() == generation {
+                    f(&*this);
+                }
+            }),
+            &global,
+        );
+    }
+
+    /// https://w3c.github.io/webcodecs/#reset-videodecoder
+    fn reset(&self, exception: DOMErrorName) -> ErrorResult {
+        // Step 1.
+        if self.state.get() == CodecState::Closed {
+            return Err(Error::InvalidState);
+        }
+        // Steps 2-6.
+        self.state.set(CodecState::Unconfigured);
+        if let Some(decoder) = self.decoder.borrow_mut().take() {
+            decoder.lock().unwrap().close();
+        }
+        self.generation.set(self.generation.get() + 1);
+        self.key_chunk_required.set(true);
+        self.decode_queue_size.set(0);
+        // Step 7.
+        let global = self.global();
+        for promise in self.pending_flush_promises.borrow_mut().drain(..) {
+            promise.reject_native(&DOMException::new(&global, exception));
+        }
+        Ok(())
+    }
+
+    /// https://w3c.github.io/webcodecs/#close-videodecoder
+    fn close_with_error(&self, exception: DOMErrorName) {
+        // Step 1.
+        if self.reset(exception).is_err() {
+            return;
+        }
+        // Step 2.
+        self.state.set(CodecState::Closed);
+        // Step 4.
+        if exception != DOMErrorName::AbortError {
+            let error = DOMException::new(&self.global(), exception);
+            let _ = self
+                .error_callback
+                .Call__(&error, ExceptionHandling::Report);
+        }
+    }
+}
+
+impl VideoDecoderMethods for VideoDecoder {
+    /// https://w3c.github.io/webcodecs/#dom-videodecoder-state
+    fn State(&self) -> CodecState {
+        self.state.get()
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-videodecoder-decodequeuesize
+    fn DecodeQueueSize(&self) -> u32 {
+        self.decode_queue_size.get()
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-videodecoder-configure
+    fn Configure(&self, config: RootedTraceableBox<VideoDecoderConfig>) -> ErrorResult {
+        // Step 1.
+        if !is_valid_config(&config) {
+            return Err(Error::Type("Invalid VideoDecoderConfig".to_owned()));
+        }
+        // Step 2.
+        if self.state.get() == CodecState::Closed {
+            return Err(Error::InvalidState);
+        }
+        // Steps 3-4.
+        self.state.set(CodecState::Configured);
+        self.key_chunk_required.set(true);
+
+        // Step 5.
+        let settings = DecoderSettings::Video {
+            codec: config.codec.to_string(),
+            description: config
+                .description
+                .as_ref()
+                .map(webcodecs::buffer_source_to_vec),
+            coded_width: config.codedWidth,
+            coded_height: config.codedHeight,
+        };
+        let supported = codecs::supports_codec(&config.codec, CodecKind::VideoDecoder);
+        self.queue_control_message(move |decoder| decoder.configure_backend(supported, settings));
+        Ok(())
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-videodecoder-decode
+    fn Decode(&self, chunk: &EncodedVideoChunk) -> ErrorResult {
+        // Step 1.
+        if self.state.get() != CodecState::Configured {
+            return Err(Error::InvalidState);
+        }
+        // Step 2.
+        if self.key_chunk_required.get() {
+            if !chunk.is_key() {
+                return Err(Error::Data);
+            }
+            self.key_chunk_required.set(false);
+        }
+        // Step 3.
+        self.decode_queue_size.set(self.decode_queue_size.get() + 1);
+        // Step 4.
+        let chunk = chunk.to_backend_chunk();
+        self.queue_control_message(move |decoder| {
+            decoder
+                .decode_queue_size
+                .set(decoder.decode_queue_size.get() - 1);
+            if let Some(ref backend) = *decoder.decoder.borrow() {
+                backend.lock().unwrap().decode(chunk);
+            }
+        });
+        Ok(())
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-videodecoder-flush
+    fn Flush(&self) -> Rc<Promise> {
+        let promise = Promise::new(&self.global());
+        // Step 1.
+        if self.state.get() != CodecState::Configured {
+            promise.reject_error(Error::InvalidState);
+            return promise;
+        }
+        // Steps 2-4.
+        self.key_chunk_required.set(true);
+        self.pending_flush_promises
+            .borrow_mut()
+            .push_back(promise.clone());
+        self.queue_control_message(|decoder| {
+            if let Some(ref backend) = *decoder.decoder.borrow() {
+                backend.lock().unwrap().flush();
+            }
+        });
+        promise
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-videodecoder-reset
+    fn Reset(&self) -> ErrorResult {
+        self.reset(DOMErrorName::AbortError)
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-videodecoder-close
+    fn Close(&self) -> ErrorResult {
+        if self.state.get() == CodecState::Closed {
+            return Err(Error::InvalidState);
+        }
+        self.close_with_error(DOMErrorName::AbortError);
+        Ok(())
+    }
+}
+
+/// https://w3c.github.io/webcodecs/#valid-videodecoderconfig
+fn is_valid_config(config: &VideoDecoderConfig) -> bool {
+    // Step 1.
+    if !webcodecs::is_valid_codec_string(&config.codec) {
+        return false;
+    }
+    // Steps 2-3.
+    match (config.codedWidth, config.codedHeight) {
+        (None, None) => {},
+        (Some(width), Some(height)) if width != 0 && height != 0 => {},
+        _ => return false,
+    }
+    // Steps 4-5.
+    match (config.displayAspectWidth, config.displayAspectHeight) {
+        (None, None) => {},
+        (Some(width), Some(height)) if width != 0 && height != 0 => {},
+        _ => return false,
+    }
+    true
+}
@@ -0,0 +1,406 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::callback::ExceptionHandling;
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::VideoDecoderBinding::VideoDecoderConfig;
+use crate::dom::bindings::codegen::Bindings::VideoEncoderBinding::{
+    EncodedVideoChunkMetadata, EncodedVideoChunkOutputCallback, VideoEncoderConfig,
+    VideoEncoderEncodeOptions, VideoEncoderInit, VideoEncoderMethods, VideoEncoderSupport,
+};
+use crate::dom::bindings::codegen::Bindings::WebCodecsBinding::{
+    CodecState, HardwareAcceleration, WebCodecsErrorCallback,
+};
+use crate::dom::bindings::error::{Error, ErrorResult};
+use crate::dom::bindings::refcounted::Trusted;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::bindings::str::DOMString;
+use crate::dom::bindings::trace::RootedTraceableBox;
+use crate::dom::domexception::{DOMErrorName, DOMException};
+use crate::dom::encodedvideochunk::EncodedVideoChunk;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::videoframe::VideoFrame;
+use crate::dom::webcodecs;
+use crate::task_source::{TaskSource, TaskSourceName};
+use dom_struct::dom_struct;
+use ipc_channel::ipc;
+use ipc_channel::router::ROUTER;
+use media::codecs::{self, CodecKind, Encoder, EncoderEvent, EncoderSettings};
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+/// The decoder configuration matching the chunks produced by the current
+/// encoder configuration.
+/// https://w3c.github.io/webcodecs/#dom-videoencoder-active-output-config-slot
+#[derive(JSTraceable, MallocSizeOf)]
+struct OutputConfig {
+    codec: DOMString,
+    width: u32,
+    height: u32,
+    display_width: u32,
+    display_height: u32,
+}
+
+/// https://w3c.github.io/webcodecs/#videoencoder-interface
+#[dom_struct]
+pub struct VideoEncoder {
+    reflector_: Reflector,
+    /// https://w3c.github.io/webcodecs/#dom-videoencoder-output-callback-slot
+    #[ignore_malloc_size_of = "Rc"]
+    output_callback: Rc<EncodedVideoChunkOutputCallback>,
+    /// https://w3c.github.io/webcodecs/#dom-videoencoder-error-callback-slot
+    #[ignore_malloc_size_of = "Rc"]
+    error_callback: Rc<WebCodecsErrorCallback>,
+    /// https://w3c.github.io/webcodecs/#dom-videoencoder-state
+    state: Cell<CodecState>,
+    /// https://w3c.github.io/webcodecs/#dom-videoencoder-encodequeuesize
+    encode_queue_size: Cell<u32>,
+    /// https://w3c.github.io/webcodecs/#dom-videoencoder-pending-flush-promises-slot
+    #[ignore_malloc_size_of = "promises are hard"]
+    pending_flush_promises: DomRefCell<VecDeque<Rc<Promise>>>,
+    /// The output configuration which still has to be reported to script
+    /// along with the next chunk.
+    pending_output_config: DomRefCell<Option<OutputConfig>>,
+    /// Bumped whenever the encoder is reset, so that the queued control
+    /// messages and the backend events of the previous configuration are
+    /// dropped.
+    generation: Cell<u32>,
+    #[ignore_malloc_size_of = "Arc"]
+    encoder: DomRefCell<Option<Arc<Mutex<dyn Encoder>>>>,
+}
+
+impl VideoEncoder {
+    fn new_inherited(init: &VideoEncoderInit) -> VideoEncoder {
+        VideoEncoder {
+            reflector_: Reflector::new(),
+            output_callback: init.output.clone(),
+            error_callback: init.error.clone(),
+            state: Cell::new(CodecState::Unconfigured),
+            encode_queue_size: Cell::new(0),
+            pending_flush_promises: DomRefCell::new(VecDeque::new()),
+            pending_output_config: DomRefCell::new(None),
+            generation: Cell::new(0),
+            encoder: DomRefCell::new(None),
+        }
+    }
+
+    fn new(global: &GlobalScope, init: &VideoEncoderInit) -> DomRoot<VideoEncoder> {
+        reflect_dom_object(Box::new(VideoEncoder::new_inherited(init)), global)
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-videoencoder-videoencoder
+    #[allow(non_snake_case)]
+    pub fn Constructor(global: &GlobalScope, init: &VideoEncoderInit) -> DomRoot<VideoEncoder> {
+        VideoEncoder::new(global, init)
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-videoencoder-isconfigsupported
+    #[allow(non_snake_case)]
+    pub fn IsConfigSupported(global: &GlobalScope, config: &VideoEncoderConfig) -> Rc<Promise> {
+        let promise = Promise::new(global);
+
+        // Step 1.
+        if !is_valid_config(config) {
+            promise.reject_error(Error::Type("Invalid VideoEncoderConfig".to_owned()));
+            return promise;
+        }
+
+        // Steps 2-3.
+        let supported = codecs::supports_codec(&config.codec, CodecKind::VideoEncoder);
+        let mut support = VideoEncoderSupport::empty();
+        support.supported = Some(supported);
+        support.config = Some(VideoEncoderConfig {
+            codec: config.codec.clone(),
+            width: config.width,
+            height: config.height,
+            displayWidth: config.displayWidth,
+            displayHeight: config.displayHeight,
+            bitrate: config.bitrate,
+            framerate: config.framerate,
+            hardwareAcceleration: config.hardwareAcceleration,
+        });
+        promise.resolve_native(&support);
+        promise
+    }
+
+    fn create_encoder(&self, settings: EncoderSettings) -> Result<Arc<Mutex<dyn Encoder>>, ()> {
+        let global = self.global();
+        let (sender, receiver) = ipc::channel::<EncoderEvent>().unwrap();
+
+        let encoder = codecs::create_encoder(settings, sender)
+            .map_err(|error| warn!("Could not create video encoder {:?}", error))?;
+
+        let this = Trusted::new(self);
+        let generation = self.generation.get();
+        let task_source = global.dom_manipulation_task_source();
+        let canceller = global.task_canceller(TaskSourceName::DOMManipulation);
+        ROUTER.add_route(
+            receiver.to_opaque(),
+            Box::new(move |message| {
+                let event = message.to().unwrap();
+                let this = this.clone();
+                let _ = task_source.queue_with_canceller(
+                    task!(handle_video_encoder_event: move || {
+                        this.root().handle_encoder_event(generation, event);
+                    }),
+                    &canceller,
+                );
+            }),
+        );
+
+        Ok(encoder)
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-videoencoder-configure
+    /// Step 5, run as a control message.
+    fn configure_backend(
+        &self,
+        supported: bool,
+        settings: EncoderSettings,
+        output_config: OutputConfig,
+    ) {
+        if let Some(encoder) = self.encoder.borrow_mut().take() {
+            encoder.lock().unwrap().close();
+        }
+        // Step 5.1.
+        if !supported {
+            return self.close_with_error(DOMErrorName::NotSupportedError);
+        }
+        // Steps 5.2-5.3.
+        match self.create_encoder(settings) {
+            Ok(encoder) => {
+                *self.encoder.borrow_mut() = Some(encoder);
+                *self.pending_output_config.borrow_mut() = Some(output_config);
+            },
+            Err(()) => self.close_with_error(DOMErrorName::NotSupportedError),
+        }
+    }
+
+    fn handle_encoder_event(&self, generation: u32, event: EncoderEvent) {
+        if generation != self.generation.get() {
+            return;
+        }
+        match event {
+            EncoderEvent::Chunk(chunk) => self.output_chunk(chunk),
+            EncoderEvent::Flushed => {
+                // https://w3c.github.io/webcodecs/#dom-videoencoder-flush
+                // Step 5.
+                if let Some(promise) = self.pending_flush_promises.borrow_mut().pop_front() {
+                    promise.resolve_native(&());
+                }
+            },
+            EncoderEvent::Error(message) => {
+                warn!("Video encoding failed: {}", message);
+                self.close_with_error(DOMErrorName::EncodingError);
+            },
+        }
+    }
+
+    /// https://w3c.github.io/webcodecs/#output-encodedvideochunks
+    fn output_chunk(&self, chunk: media::codecs::EncodedChunk) {
+        let global = self.global();
+        // Steps 1-3.
+        let chunk = EncodedVideoChunk::new_from_encoded(&global, chunk);
+        // Steps 4-6.
+        let mut metadata = EncodedVideoChunkMetadata::empty();
+        if let Some(config) = self.pending_output_config.borrow_mut().take() {
+            // The backend produces streams which carry their parameter sets
+            // in-band, so there is no description to hand out.
+            metadata.decoderConfig = Some(RootedTraceableBox::new(VideoDecoderConfig {
+                codec: config.codec,
+                description: None,
+                codedWidth: Some(config.width),
+                codedHeight: Some(config.height),
+                displayAspectWidth: Some(config.display_width),
+                displayAspectHeight: Some(config.display_height),
+                hardwareAcceleration: HardwareAcceleration::No_preference,
+                optimizeForLatency: None,
+            }));
+        }
+        // Step 7.
+        let _ = self
+            .output_callback
+            .Call__(&chunk, metadata, ExceptionHandling::Report);
+    }
+
+    /// Queues a task to run `f` once the preceding control messages ran,
+    /// unless the encoder is reset in the meantime.
+    /// https://w3c.github.io/webcodecs/#enqueues-a-control-message
+    fn queue_control_message<F>(&self, f: F)
+    where
+        F: FnOnce(&VideoEncoder) + Send + 'static,
+    {
+        let global = self.global();
+        let this = Trusted::new(self);
+        let generation = self.generation.get();
+        let _ = global.dom_manipulation_task_source().queue(
+            task!(video_encoder_control_message: move || {
+                let this = this.root();
+                if this.generation.get() == generation {
+                    f(&*this);
+                }
+            }),
+            &global,
+        );
+    }
+
+    /// https://w3c.github.io/webcodecs/#reset-videoencoder
+    fn reset(&self, exception: DOMErrorName) -> ErrorResult {
+        // Step 1.
+        if self.state.get() == CodecState::Closed {
+            return Err(Error::InvalidState);
+        }
+        // Steps 2-6.
+        self.state.set(CodecState::Unconfigured);
+        if let Some(encoder) = self.encoder.borrow_mut().take() {
+            encoder.lock().unwrap().close();
+        }
+        self.generation.set(self.generation.get() + 1);
+        self.pending_output_config.borrow_mut().take();
+        self.encode_queue_size.set(0);
+        // Step 7.
+        let global = self.global();
+        for promise in self.pending_flush_promises.borrow_mut().drain(..) {
+            promise.reject_native(&DOMException::new(&global, exception));
+        }
+        Ok(())
+    }
+
+    /// https://w3c.github.io/webcodecs/#close-videoencoder
+    fn close_with_error(&self, exception: DOMErrorName) {
+        // Step 1.
+        if self.reset(exception).is_err() {
+            return;
+        }
+        // Step 2.
+        self.state.set(CodecState::Closed);
+        // Step 4.
+        if exception != DOMErrorName::AbortError {
+            let error = DOMException::new(&self.global(), exception);
+            let _ = self
+                .error_callback
+                .Call__(&error, ExceptionHandling::Report);
+        }
+    }
+}
+
+impl VideoEncoderMethods for VideoEncoder {
+    /// https://w3c.github.io/webcodecs/#dom-videoencoder-state
+    fn State(&self) -> CodecState {
+        self.state.get()
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-videoencoder-encodequeuesize
+    fn EncodeQueueSize(&self) -> u32 {
+        self.encode_queue_size.get()
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-videoencoder-configure
+    fn Configure(&self, config: &VideoEncoderConfig) -> ErrorResult {
+        // Step 1.
+        if !is_valid_config(config) {
+            return Err(Error::Type("Invalid VideoEncoderConfig".to_owned()));
+        }
+        // Step 2.
+        if self.state.get() == CodecState::Closed {
+            return Err(Error::InvalidState);
+        }
+        // Step 3.
+        self.state.set(CodecState::Configured);
+
+        // Step 4.
+        let settings = EncoderSettings {
+            codec: config.codec.to_string(),
+            width: config.width,
+            height: config.height,
+            bitrate: config.bitrate,
+            framerate: config.framerate.map(|framerate| *framerate),
+        };
+        let output_config = OutputConfig {
+            codec: config.codec.clone(),
+            width: config.width,
+            height: config.height,
+            display_width: config.displayWidth.unwrap_or(config.width),
+            display_height: config.displayHeight.unwrap_or(config.height),
+        };
+        let supported = codecs::supports_codec(&config.codec, CodecKind::VideoEncoder);
+        self.queue_control_message(move |encoder| {
+            encoder.configure_backend(supported, settings, output_config)
+        });
+        Ok(())
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-videoencoder-encode
+    fn Encode(&self, frame: &VideoFrame, options: &VideoEncoderEncodeOptions) -> ErrorResult {
+        // Step 1.
+        let frame = frame
+            .to_backend_frame()
+            .ok_or_else(|| Error::Type("The frame is closed".to_owned()))?;
+        // Step 2.
+        if self.state.get() != CodecState::Configured {
+            return Err(Error::InvalidState);
+        }
+        // Step 4.
+        self.encode_queue_size.set(self.encode_queue_size.get() + 1);
+        // Step 5.
+        let key_frame = options.keyFrame;
+        self.queue_control_message(move |encoder| {
+            encoder
+                .encode_queue_size
+                .set(encoder.encode_queue_size.get() - 1);
+            if let Some(ref backend) = *encoder.encoder.borrow() {
+                backend.lock().unwrap().encode(frame, key_frame);
+            }
+        });
+        Ok(())
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-videoencoder-flush
+    fn Flush(&self) -> Rc<Promise> {
+        let promise = Promise::new(&self.global());
+        // Step 1.
+        if self.state.get() != CodecState::Configured {
+            promise.reject_error(Error::InvalidState);
+            return promise;
+        }
+        // Steps 2-4.
+        self.pending_flush_promises
+            .borrow_mut()
+            .push_back(promise.clone());
+        self.queue_control_message(|encoder| {
+            if let Some(ref backend) = *encoder.encoder.borrow() {
+                backend.lock().unwrap().flush();
+            }
+        });
+        promise
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-videoencoder-reset
+    fn Reset(&self) -> ErrorResult {
+        self.reset(DOMErrorName::AbortError)
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-videoencoder-close
+    fn Close(&self) -> ErrorResult {
+        if self.state.get() == CodecState::Closed {
+            return Err(Error::InvalidState);
+        }
+        self.close_with_error(DOMErrorName::AbortError);
+        Ok(())
+    }
+}
+
+/// https://w3c.github.io/webcodecs/#valid-videoencoderconfig
+fn is_valid_config(config: &VideoEncoderConfig) -> bool {
+    // Steps 1-5.
+    webcodecs::is_valid_codec_string(&config.codec) &&
+        config.width != 0 &&
+        config.height != 0 &&
+        config.displayWidth != Some(0) &&
+        config.displayHeight != Some(0)
+}
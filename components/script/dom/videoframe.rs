@@ -0,0 +1,435 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::CanvasRenderingContext2DBinding::CanvasImageSource;
+use crate::dom::bindings::codegen::Bindings::VideoFrameBinding::{
+    PlaneLayout, VideoFrameBufferInit, VideoFrameInit, VideoFrameMethods, VideoPixelFormat,
+};
+use crate::dom::bindings::codegen::UnionTypes::ArrayBufferViewOrArrayBuffer;
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::element::cors_setting_for_element;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::htmlcanvaselement::utils as canvas_utils;
+use crate::dom::promise::Promise;
+use crate::dom::webcodecs;
+use crate::unpremultiplytable::UNPREMULTIPLY_TABLE;
+use dom_struct::dom_struct;
+use euclid::default::Size2D;
+use ipc_channel::ipc::IpcSharedMemory;
+use media::codecs::VideoFrameData;
+use net_traits::image_cache::ImageResponse;
+use pixels::{self, PixelFormat};
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// https://w3c.github.io/webcodecs/#videoframe-interface
+#[dom_struct]
+pub struct VideoFrame {
+    reflector_: Reflector,
+    /// https://w3c.github.io/webcodecs/#dom-videoframe-resource-reference-slot
+    ///
+    /// The pixels of the frame, shared with its clones. Only the packed 8-bit
+    /// RGB formats are supported, so this always holds four bytes per pixel.
+    /// `None` once the frame is closed.
+    #[ignore_malloc_size_of = "Arc"]
+    resource: DomRefCell<Option<Arc<Vec<u8>>>>,
+    format: VideoPixelFormat,
+    coded_width: u32,
+    coded_height: u32,
+    display_width: u32,
+    display_height: u32,
+    duration: Option<u64>,
+    timestamp: i64,
+}
+
+impl VideoFrame {
+    fn new_inherited(
+        resource: Arc<Vec<u8>>,
+        format: VideoPixelFormat,
+        coded_size: Size2D<u32>,
+        display_size: Size2D<u32>,
+        timestamp: i64,
+        duration: Option<u64>,
+    ) -> VideoFrame {
+        VideoFrame {
+            reflector_: Reflector::new(),
+            resource: DomRefCell::new(Some(resource)),
+            format,
+            coded_width: coded_size.width,
+            coded_height: coded_size.height,
+            display_width: display_size.width,
+            display_height: display_size.height,
+            duration,
+            timestamp,
+        }
+    }
+
+    fn new(
+        global: &GlobalScope,
+        resource: Arc<Vec<u8>>,
+        format: VideoPixelFormat,
+        coded_size: Size2D<u32>,
+        display_size: Size2D<u32>,
+        timestamp: i64,
+        duration: Option<u64>,
+    ) -> DomRoot<VideoFrame> {
+        reflect_dom_object(
+            Box::new(VideoFrame::new_inherited(
+                resource,
+                format,
+                coded_size,
+                display_size,
+                timestamp,
+                duration,
+            )),
+            global,
+        )
+    }
+
+    /// Wraps a frame produced by a media backend decoder.
+    pub fn new_from_decoded(global: &GlobalScope, frame: VideoFrameData) -> DomRoot<VideoFrame> {
+        let size = Size2D::new(frame.width, frame.height);
+        VideoFrame::new(
+            global,
+            Arc::new(frame.data),
+            VideoPixelFormat::BGRA,
+            size,
+            size,
+            frame.timestamp,
+            frame.duration,
+        )
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-videoframe-videoframe
+    #[allow(non_snake_case)]
+    pub fn Constructor(
+        global: &GlobalScope,
+        image: CanvasImageSource,
+        init: &VideoFrameInit,
+    ) -> Fallible<DomRoot<VideoFrame>> {
+        let (data, size) = match image {
+            CanvasImageSource::HTMLImageElement(ref image) => {
+                // Step 1.
+                if !image.is_usable()? {
+                    return Err(Error::InvalidState);
+                }
+                // Step 2.
+                if !image.same_origin(global.origin()) {
+                    return Err(Error::Security);
+                }
+                let url = image.get_url().ok_or(Error::InvalidState)?;
+                let cors_setting = cors_setting_for_element(image.upcast());
+                let img = match canvas_utils::request_image_from_cache(
+                    global.as_window(),
+                    url,
+                    cors_setting,
+                ) {
+                    ImageResponse::Loaded(img, _) => img,
+                    ImageResponse::PlaceholderLoaded(_, _) |
+                    ImageResponse::None |
+                    ImageResponse::MetadataLoaded(_) => return Err(Error::InvalidState),
+                };
+                let data = match img.format {
+                    PixelFormat::BGRA8 => img.bytes.to_vec(),
+                    _ => return Err(Error::NotSupported),
+                };
+                (data, Size2D::new(img.width, img.height))
+            },
+            CanvasImageSource::HTMLCanvasElement(ref canvas) => {
+                // Steps 1-2.
+                if !canvas.is_valid() {
+                    return Err(Error::InvalidState);
+                }
+                if !canvas.origin_is_clean() {
+                    return Err(Error::Security);
+                }
+                let (data, size) = canvas.fetch_all_data().ok_or(Error::InvalidState)?;
+                (unpremultiplied(data, size), size)
+            },
+            CanvasImageSource::OffscreenCanvas(ref canvas) => {
+                // Steps 1-2.
+                if !canvas.is_valid() {
+                    return Err(Error::InvalidState);
+                }
+                if !canvas.origin_is_clean() {
+                    return Err(Error::Security);
+                }
+                let (data, size) = canvas.fetch_all_data().ok_or(Error::InvalidState)?;
+                (unpremultiplied(data, size), size)
+            },
+            CanvasImageSource::VideoFrame(ref frame) => {
+                // Step 1.
+                let resource = frame.resource().ok_or(Error::InvalidState)?;
+                // Step 4.1.
+                let coded_size = Size2D::new(frame.coded_width, frame.coded_height);
+                let default_display_size = Size2D::new(frame.display_width, frame.display_height);
+                let display_size =
+                    display_size(init.displayWidth, init.displayHeight, default_display_size)?;
+                return Ok(VideoFrame::new(
+                    global,
+                    resource,
+                    frame.format,
+                    coded_size,
+                    display_size,
+                    init.timestamp.unwrap_or(frame.timestamp),
+                    init.duration.or(frame.duration),
+                ));
+            },
+            CanvasImageSource::CSSStyleValue(_) => return Err(Error::NotSupported),
+        };
+
+        // Step 4.2.
+        let timestamp = init
+            .timestamp
+            .ok_or_else(|| Error::Type("A timestamp is required".to_owned()))?;
+        let display_size = display_size(init.displayWidth, init.displayHeight, size)?;
+        Ok(VideoFrame::new(
+            global,
+            Arc::new(data),
+            VideoPixelFormat::BGRA,
+            size,
+            display_size,
+            timestamp,
+            init.duration,
+        ))
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-videoframe-videoframe-data-init
+    #[allow(non_snake_case)]
+    pub fn Constructor_(
+        global: &GlobalScope,
+        data: ArrayBufferViewOrArrayBuffer,
+        init: &VideoFrameBufferInit,
+    ) -> Fallible<DomRoot<VideoFrame>> {
+        // Step 1.
+        if init.codedWidth == 0 || init.codedHeight == 0 {
+            return Err(Error::Type("The coded size must not be empty".to_owned()));
+        }
+        let coded_size = Size2D::new(init.codedWidth, init.codedHeight);
+        let display_size = display_size(init.displayWidth, init.displayHeight, coded_size)?;
+
+        // TODO: Support the planar YUV formats.
+        if !is_packed_rgb(init.format) {
+            return Err(Error::NotSupported);
+        }
+
+        // Steps 2-4.
+        let allocation_size = coded_size.area() as usize * 4;
+        let mut data = webcodecs::buffer_source_to_vec(&data);
+        if data.len() < allocation_size {
+            return Err(Error::Type(
+                "The buffer is too small for the coded size".to_owned(),
+            ));
+        }
+        data.truncate(allocation_size);
+
+        Ok(VideoFrame::new(
+            global,
+            Arc::new(data),
+            init.format,
+            coded_size,
+            display_size,
+            init.timestamp,
+            init.duration,
+        ))
+    }
+
+    fn resource(&self) -> Option<Arc<Vec<u8>>> {
+        self.resource.borrow().clone()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.resource.borrow().is_none()
+    }
+
+    /// The pixels of the frame as non-premultiplied BGRA, the layout the
+    /// canvas and WebGL code works with. `None` if the frame is closed.
+    pub fn get_bgra_data(&self) -> Option<(Vec<u8>, Size2D<u32>)> {
+        let mut data = self.resource()?.to_vec();
+        match self.format {
+            VideoPixelFormat::BGRA => {},
+            VideoPixelFormat::BGRX => set_opaque(&mut data),
+            VideoPixelFormat::RGBA => pixels::rgba8_byte_swap_colors_inplace(&mut data),
+            VideoPixelFormat::RGBX => {
+                pixels::rgba8_byte_swap_colors_inplace(&mut data);
+                set_opaque(&mut data);
+            },
+            format => unreachable!("Unsupported pixel format {:?}", format),
+        }
+        Some((data, Size2D::new(self.coded_width, self.coded_height)))
+    }
+
+    /// The frame in the form the media backend encoders take.
+    pub fn to_backend_frame(&self) -> Option<VideoFrameData> {
+        let (data, size) = self.get_bgra_data()?;
+        Some(VideoFrameData {
+            width: size.width,
+            height: size.height,
+            timestamp: self.timestamp,
+            duration: self.duration,
+            data,
+        })
+    }
+}
+
+impl VideoFrameMethods for VideoFrame {
+    /// https://w3c.github.io/webcodecs/#dom-videoframe-format
+    fn GetFormat(&self) -> Option<VideoPixelFormat> {
+        if self.is_closed() {
+            return None;
+        }
+        Some(self.format)
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-videoframe-codedwidth
+    fn CodedWidth(&self) -> u32 {
+        if self.is_closed() {
+            return 0;
+        }
+        self.coded_width
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-videoframe-codedheight
+    fn CodedHeight(&self) -> u32 {
+        if self.is_closed() {
+            return 0;
+        }
+        self.coded_height
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-videoframe-displaywidth
+    fn DisplayWidth(&self) -> u32 {
+        if self.is_closed() {
+            return 0;
+        }
+        self.display_width
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-videoframe-displayheight
+    fn DisplayHeight(&self) -> u32 {
+        if self.is_closed() {
+            return 0;
+        }
+        self.display_height
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-videoframe-duration
+    fn GetDuration(&self) -> Option<u64> {
+        self.duration
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-videoframe-timestamp
+    fn Timestamp(&self) -> i64 {
+        self.timestamp
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-videoframe-allocationsize
+    fn AllocationSize(&self) -> Fallible<u32> {
+        if self.is_closed() {
+            return Err(Error::InvalidState);
+        }
+        Ok(self.coded_width * self.coded_height * 4)
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-videoframe-copyto
+    fn CopyTo(&self, mut destination: ArrayBufferViewOrArrayBuffer) -> Rc<Promise> {
+        let promise = Promise::new(&self.global());
+
+        // Step 1.
+        let resource = match self.resource() {
+            Some(resource) => resource,
+            None => {
+                promise.reject_error(Error::InvalidState);
+                return promise;
+            },
+        };
+
+        // Steps 2-6.
+        if let Err(error) = webcodecs::copy_to_buffer_source(&mut destination, &resource) {
+            promise.reject_error(error);
+            return promise;
+        }
+
+        let layout = PlaneLayout {
+            offset: 0,
+            stride: self.coded_width * 4,
+        };
+        promise.resolve_native(&vec![layout]);
+        promise
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-videoframe-clone
+    fn Clone(&self) -> Fallible<DomRoot<VideoFrame>> {
+        // Step 1.
+        let resource = self.resource().ok_or(Error::InvalidState)?;
+        // Step 2.
+        Ok(VideoFrame::new(
+            &self.global(),
+            resource,
+            self.format,
+            Size2D::new(self.coded_width, self.coded_height),
+            Size2D::new(self.display_width, self.display_height),
+            self.timestamp,
+            self.duration,
+        ))
+    }
+
+    /// https://w3c.github.io/webcodecs/#dom-videoframe-close
+    fn Close(&self) {
+        self.resource.borrow_mut().take();
+    }
+}
+
+fn is_packed_rgb(format: VideoPixelFormat) -> bool {
+    match format {
+        VideoPixelFormat::RGBA |
+        VideoPixelFormat::RGBX |
+        VideoPixelFormat::BGRA |
+        VideoPixelFormat::BGRX => true,
+        VideoPixelFormat::I420 |
+        VideoPixelFormat::I420A |
+        VideoPixelFormat::I422 |
+        VideoPixelFormat::I444 |
+        VideoPixelFormat::NV12 => false,
+    }
+}
+
+/// https://w3c.github.io/webcodecs/#videoframe-initialize-visible-rect-and-display-size
+fn display_size(
+    width: Option<u32>,
+    height: Option<u32>,
+    default: Size2D<u32>,
+) -> Fallible<Size2D<u32>> {
+    match (width, height) {
+        (None, None) => Ok(default),
+        (Some(width), Some(height)) if width != 0 && height != 0 => Ok(Size2D::new(width, height)),
+        _ => Err(Error::Type(
+            "displayWidth and displayHeight must both be given and be non-zero".to_owned(),
+        )),
+    }
+}
+
+fn set_opaque(pixels: &mut [u8]) {
+    for pixel in pixels.chunks_mut(4) {
+        pixel[3] = 0xff;
+    }
+}
+
+/// Turns the premultiplied BGRA data of a canvas into non-premultiplied BGRA.
+fn unpremultiplied(data: Option<IpcSharedMemory>, size: Size2D<u32>) -> Vec<u8> {
+    let mut data = data
+        .map(|data| data.to_vec())
+        .unwrap_or_else(|| vec![0; size.area() as usize * 4]);
+    for pixel in data.chunks_mut(4) {
+        let alpha = 256 * pixel[3] as usize;
+        pixel[0] = UNPREMULTIPLY_TABLE[alpha + pixel[0] as usize];
+        pixel[1] = UNPREMULTIPLY_TABLE[alpha + pixel[1] as usize];
+        pixel[2] = UNPREMULTIPLY_TABLE[alpha + pixel[2] as usize];
+    }
+    data
+}
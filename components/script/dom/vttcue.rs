@@ -3,21 +3,35 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::NodeBinding::NodeMethods;
+use crate::dom::bindings::codegen::Bindings::TextTrackCueBinding::TextTrackCueMethods;
 use crate::dom::bindings::codegen::Bindings::VTTCueBinding::{
     self, AlignSetting, AutoKeyword, DirectionSetting, LineAlignSetting, PositionAlignSetting,
     VTTCueMethods,
 };
+use crate::dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
 use crate::dom::bindings::error::{Error, ErrorResult};
+use crate::dom::bindings::inheritance::Castable;
 use crate::dom::bindings::num::Finite;
 use crate::dom::bindings::reflector::{reflect_dom_object, DomObject};
 use crate::dom::bindings::root::{Dom, DomRoot};
 use crate::dom::bindings::str::DOMString;
+use crate::dom::customelementregistry::CustomElementCreationMode;
+use crate::dom::document::Document;
 use crate::dom::documentfragment::DocumentFragment;
+use crate::dom::element::{Element, ElementCreator};
 use crate::dom::globalscope::GlobalScope;
+use crate::dom::node::Node;
+use crate::dom::processinginstruction::ProcessingInstruction;
+use crate::dom::text::Text;
 use crate::dom::texttrackcue::TextTrackCue;
 use crate::dom::vttregion::VTTRegion;
+use crate::dom::webvttparser::{
+    self, CueNode, CueNodeKind, LineAlignment, PositionAlignment, TextAlignment, WritingDirection,
+};
 use crate::dom::window::Window;
 use dom_struct::dom_struct;
+use html5ever::{LocalName, QualName};
 use std::cell::Cell;
 
 #[dom_struct]
@@ -78,6 +92,316 @@ impl VTTCue {
     ) -> DomRoot<Self> {
         VTTCue::new(&window.global(), *start_time, *end_time, text)
     }
+
+    /// Create a cue from one parsed out of a WebVTT file.
+    ///
+    /// <https://w3c.github.io/webvtt/#collect-webvtt-cue-timings-and-settings>
+    pub fn from_webvtt(
+        global: &GlobalScope,
+        cue: &webvttparser::Cue,
+        region: Option<&VTTRegion>,
+    ) -> DomRoot<Self> {
+        let vtt_cue = VTTCue::new(
+            global,
+            cue.start_time,
+            cue.end_time,
+            DOMString::from(cue.text.clone()),
+        );
+        vtt_cue
+            .upcast::<TextTrackCue>()
+            .SetId(DOMString::from(cue.id.clone()));
+        *vtt_cue.region.borrow_mut() = region.map(Dom::from_ref);
+        vtt_cue.vertical.set(match cue.writing_direction {
+            WritingDirection::Horizontal => DirectionSetting::_empty,
+            WritingDirection::VerticalGrowingLeft => DirectionSetting::Rl,
+            WritingDirection::VerticalGrowingRight => DirectionSetting::Lr,
+        });
+        vtt_cue.snap_to_lines.set(cue.snap_to_lines);
+        *vtt_cue.line.borrow_mut() = cue
+            .line
+            .map_or(LineAndPositionSetting::Auto, LineAndPositionSetting::Double);
+        vtt_cue.line_align.set(match cue.line_alignment {
+            LineAlignment::Start => LineAlignSetting::Start,
+            LineAlignment::Center => LineAlignSetting::Center,
+            LineAlignment::End => LineAlignSetting::End,
+        });
+        *vtt_cue.position.borrow_mut() = cue
+            .position
+            .map_or(LineAndPositionSetting::Auto, LineAndPositionSetting::Double);
+        vtt_cue.position_align.set(match cue.position_alignment {
+            PositionAlignment::LineLeft => PositionAlignSetting::Line_left,
+            PositionAlignment::Center => PositionAlignSetting::Center,
+            PositionAlignment::LineRight => PositionAlignSetting::Line_right,
+            PositionAlignment::Auto => PositionAlignSetting::Auto,
+        });
+        vtt_cue.size.set(cue.size);
+        vtt_cue.align.set(match cue.text_alignment {
+            TextAlignment::Start => AlignSetting::Start,
+            TextAlignment::Center => AlignSetting::Center,
+            TextAlignment::End => AlignSetting::End,
+            TextAlignment::Left => AlignSetting::Left,
+            TextAlignment::Right => AlignSetting::Right,
+        });
+        vtt_cue
+    }
+
+    /// Ask the media element showing this cue, if any, to render it again.
+    fn rendering_changed(&self) {
+        if let Some(track) = self.upcast::<TextTrackCue>().get_track() {
+            track.cues_changed();
+        }
+    }
+
+    /// The region this cue is rendered in, if any. Only horizontal cues with
+    /// an automatic line and the full size are rendered in their region.
+    ///
+    /// <https://w3c.github.io/webvtt/#webvtt-cue-region>
+    pub fn rendered_region(&self) -> Option<DomRoot<VTTRegion>> {
+        let line_is_auto = match *self.line.borrow() {
+            LineAndPositionSetting::Auto => true,
+            LineAndPositionSetting::Double(_) => false,
+        };
+        if self.vertical.get() != DirectionSetting::_empty ||
+            !line_is_auto ||
+            self.size.get() != 100.
+        {
+            return None;
+        }
+        self.GetRegion()
+    }
+
+    /// Whether this cue is laid out with the other cues whose line is
+    /// computed from the number of showing text tracks, stacked from the end
+    /// of the video viewport.
+    pub fn has_automatic_line(&self) -> bool {
+        match *self.line.borrow() {
+            LineAndPositionSetting::Auto => self.snap_to_lines.get(),
+            LineAndPositionSetting::Double(_) => false,
+        }
+    }
+
+    /// <https://w3c.github.io/webvtt/#cue-computed-line>
+    ///
+    /// `showing_tracks_before` is the number of showing text tracks before
+    /// the track of this cue in the list of text tracks of its media element.
+    pub fn computed_line(&self, showing_tracks_before: usize) -> f64 {
+        match *self.line.borrow() {
+            // Step 1.
+            LineAndPositionSetting::Double(line)
+                if !self.snap_to_lines.get() && (line < 0. || line > 100.) =>
+            {
+                100.
+            },
+            // Step 2.
+            LineAndPositionSetting::Double(line) => line,
+            // Step 3.
+            LineAndPositionSetting::Auto if !self.snap_to_lines.get() => 100.,
+            // Steps 4 to 8.
+            LineAndPositionSetting::Auto => -(showing_tracks_before as f64 + 1.),
+        }
+    }
+
+    /// <https://w3c.github.io/webvtt/#cue-computed-position>
+    fn computed_position(&self) -> f64 {
+        match *self.position.borrow() {
+            LineAndPositionSetting::Double(position) => position,
+            LineAndPositionSetting::Auto => match self.align.get() {
+                AlignSetting::Left => 0.,
+                AlignSetting::Right => 100.,
+                _ => 50.,
+            },
+        }
+    }
+
+    /// <https://w3c.github.io/webvtt/#cue-computed-position-alignment>
+    ///
+    /// The base direction of the cue text is assumed to be left-to-right.
+    fn computed_position_alignment(&self) -> PositionAlignSetting {
+        match (self.position_align.get(), self.align.get()) {
+            (PositionAlignSetting::Auto, AlignSetting::Left) |
+            (PositionAlignSetting::Auto, AlignSetting::Start) => PositionAlignSetting::Line_left,
+            (PositionAlignSetting::Auto, AlignSetting::Right) |
+            (PositionAlignSetting::Auto, AlignSetting::End) => PositionAlignSetting::Line_right,
+            (PositionAlignSetting::Auto, AlignSetting::Center) => PositionAlignSetting::Center,
+            (alignment, _) => alignment,
+        }
+    }
+
+    /// Build the box of this cue, as a child of the text track display of a
+    /// video element, a region box or a stack of automatically positioned
+    /// cues.
+    ///
+    /// When `line` is `None`, the box is laid out in the normal flow of its
+    /// parent, otherwise it is absolutely positioned at that computed line.
+    ///
+    /// <https://w3c.github.io/webvtt/#apply-webvtt-cue-settings>
+    pub fn create_cue_box(&self, document: &Document, line: Option<f64>) -> DomRoot<Element> {
+        let is_horizontal = self.vertical.get() == DirectionSetting::_empty;
+
+        // Steps 2 to 5.
+        let position = self.computed_position();
+        let alignment = self.computed_position_alignment();
+        let maximum_size = match alignment {
+            PositionAlignSetting::Line_left => 100. - position,
+            PositionAlignSetting::Line_right => position,
+            _ if position <= 50. => position * 2.,
+            _ => (100. - position) * 2.,
+        };
+        let size = self.size.get().min(maximum_size);
+
+        // Step 7.
+        let offset = match alignment {
+            PositionAlignSetting::Line_left => position,
+            PositionAlignSetting::Line_right => position - size,
+            _ => position - size / 2.,
+        };
+
+        let mut style = match self.vertical.get() {
+            DirectionSetting::Rl => String::from("writing-mode: vertical-rl; "),
+            DirectionSetting::Lr => String::from("writing-mode: vertical-lr; "),
+            DirectionSetting::_empty => String::new(),
+        };
+        style.push_str(&format!(
+            "text-align: {}; ",
+            match self.align.get() {
+                AlignSetting::Start => "start",
+                AlignSetting::Center => "center",
+                AlignSetting::End => "end",
+                AlignSetting::Left => "left",
+                AlignSetting::Right => "right",
+            }
+        ));
+        let (offset_property, size_property) = if is_horizontal {
+            ("left", "width")
+        } else {
+            ("top", "height")
+        };
+        style.push_str(&format!("{}: {}%; ", size_property, size));
+
+        match line {
+            None => {
+                let margin = if is_horizontal {
+                    "margin-left"
+                } else {
+                    "margin-top"
+                };
+                style.push_str(&format!("{}: {}%; ", margin, offset));
+            },
+            Some(line) => {
+                style.push_str(&format!(
+                    "position: absolute; {}: {}%; ",
+                    offset_property, offset
+                ));
+                if self.snap_to_lines.get() {
+                    // Step 10: lines are counted from the start of the
+                    // block flow direction when positive, and from its end
+                    // when negative.
+                    let (start, end) = match self.vertical.get() {
+                        DirectionSetting::_empty => ("top", "bottom"),
+                        DirectionSetting::Rl => ("right", "left"),
+                        DirectionSetting::Lr => ("left", "right"),
+                    };
+                    if line >= 0. {
+                        style.push_str(&format!("{}: {}em; ", start, line * LINE_HEIGHT));
+                    } else {
+                        style.push_str(&format!("{}: {}em; ", end, (-line - 1.) * LINE_HEIGHT));
+                    }
+                } else {
+                    // Step 8.
+                    let (line_property, axis) = if is_horizontal {
+                        ("top", "Y")
+                    } else {
+                        ("left", "X")
+                    };
+                    style.push_str(&format!("{}: {}%; ", line_property, line));
+                    match self.line_align.get() {
+                        LineAlignSetting::Start => {},
+                        LineAlignSetting::Center => {
+                            style.push_str(&format!("transform: translate{}(-50%); ", axis))
+                        },
+                        LineAlignSetting::End => {
+                            style.push_str(&format!("transform: translate{}(-100%); ", axis))
+                        },
+                    }
+                }
+            },
+        }
+
+        let cue_box = create_html_element(document, local_name!("div"));
+        cue_box.set_string_attribute(&local_name!("style"), DOMString::from(style));
+
+        // The element holding the cue text is the one matched by `::cue`.
+        let cue_root = create_html_element(document, local_name!("span"));
+        cue_root.set_tokenlist_attribute(&local_name!("class"), DOMString::from("cue"));
+        self.append_cue_text(document, cue_root.upcast());
+        cue_box
+            .upcast::<Node>()
+            .AppendChild(cue_root.upcast())
+            .unwrap();
+        cue_box
+    }
+
+    /// <https://w3c.github.io/webvtt/#webvtt-cue-text-dom-construction-rules>
+    fn append_cue_text(&self, document: &Document, parent: &Node) {
+        let nodes = webvttparser::parse_cue_text(&self.text.borrow());
+        append_cue_nodes(document, parent, &nodes);
+    }
+}
+
+/// The line height of cue boxes, relative to the font size of the text track
+/// display.
+pub const LINE_HEIGHT: f64 = 1.2;
+
+fn create_html_element(document: &Document, local_name: LocalName) -> DomRoot<Element> {
+    Element::create(
+        QualName::new(None, ns!(html), local_name),
+        None,
+        document,
+        ElementCreator::ScriptCreated,
+        CustomElementCreationMode::Synchronous,
+    )
+}
+
+/// <https://w3c.github.io/webvtt/#webvtt-cue-text-dom-construction-rules>
+fn append_cue_nodes(document: &Document, parent: &Node, nodes: &[CueNode]) {
+    for node in nodes {
+        let child = match *node {
+            CueNode::Text(ref text) => {
+                DomRoot::upcast::<Node>(Text::new(DOMString::from(text.clone()), document))
+            },
+            CueNode::Timestamp(timestamp) => DomRoot::upcast::<Node>(ProcessingInstruction::new(
+                DOMString::from("timestamp"),
+                DOMString::from(webvttparser::serialize_timestamp(timestamp)),
+                document,
+            )),
+            CueNode::Internal(ref internal) => {
+                let element =
+                    create_html_element(document, LocalName::from(internal.kind.html_local_name()));
+                if !internal.classes.is_empty() {
+                    element.set_tokenlist_attribute(
+                        &local_name!("class"),
+                        DOMString::from(internal.classes.join(" ")),
+                    );
+                }
+                if let Some(ref annotation) = internal.annotation {
+                    match internal.kind {
+                        CueNodeKind::Voice => element.set_string_attribute(
+                            &local_name!("title"),
+                            DOMString::from(annotation.clone()),
+                        ),
+                        CueNodeKind::Language => element.set_string_attribute(
+                            &local_name!("lang"),
+                            DOMString::from(annotation.clone()),
+                        ),
+                        _ => {},
+                    }
+                }
+                append_cue_nodes(document, element.upcast(), &internal.children);
+                DomRoot::upcast::<Node>(element)
+            },
+        };
+        parent.AppendChild(&child).unwrap();
+    }
 }
 
 impl VTTCueMethods for VTTCue {
@@ -91,7 +415,8 @@ impl VTTCueMethods for VTTCue {
 
     // https://w3c.github.io/webvtt/#dom-vttcue-region
     fn SetRegion(&self, value: Option<&VTTRegion>) {
-        *self.region.borrow_mut() = value.map(|r| Dom::from_ref(r))
+        *self.region.borrow_mut() = value.map(|r| Dom::from_ref(r));
+        self.rendering_changed();
     }
 
     // https://w3c.github.io/webvtt/#dom-vttcue-vertical
@@ -102,6 +427,7 @@ impl VTTCueMethods for VTTCue {
     // https://w3c.github.io/webvtt/#dom-vttcue-vertical
     fn SetVertical(&self, value: DirectionSetting) {
         self.vertical.set(value);
+        self.rendering_changed();
     }
 
     // https://w3c.github.io/webvtt/#dom-vttcue-snaptolines
@@ -111,7 +437,8 @@ impl VTTCueMethods for VTTCue {
 
     // https://w3c.github.io/webvtt/#dom-vttcue-snaptolines
     fn SetSnapToLines(&self, value: bool) {
-        self.snap_to_lines.set(value)
+        self.snap_to_lines.set(value);
+        self.rendering_changed();
     }
 
     // https://w3c.github.io/webvtt/#dom-vttcue-line
@@ -122,6 +449,7 @@ impl VTTCueMethods for VTTCue {
     // https://w3c.github.io/webvtt/#dom-vttcue-line
     fn SetLine(&self, value: VTTCueBinding::LineAndPositionSetting) {
         *self.line.borrow_mut() = value.into();
+        self.rendering_changed();
     }
 
     // https://w3c.github.io/webvtt/#dom-vttcue-linealign
@@ -132,6 +460,7 @@ impl VTTCueMethods for VTTCue {
     // https://w3c.github.io/webvtt/#dom-vttcue-linealign
     fn SetLineAlign(&self, value: LineAlignSetting) {
         self.line_align.set(value);
+        self.rendering_changed();
     }
 
     // https://w3c.github.io/webvtt/#dom-vttcue-position
@@ -148,6 +477,7 @@ impl VTTCueMethods for VTTCue {
         }
 
         *self.position.borrow_mut() = value.into();
+        self.rendering_changed();
         Ok(())
     }
 
@@ -159,6 +489,7 @@ impl VTTCueMethods for VTTCue {
     // https://w3c.github.io/webvtt/#dom-vttcue-positionalign
     fn SetPositionAlign(&self, value: PositionAlignSetting) {
         self.position_align.set(value);
+        self.rendering_changed();
     }
 
     // https://w3c.github.io/webvtt/#dom-vttcue-size
@@ -173,6 +504,7 @@ impl VTTCueMethods for VTTCue {
         }
 
         self.size.set(*value);
+        self.rendering_changed();
         Ok(())
     }
 
@@ -184,6 +516,7 @@ impl VTTCueMethods for VTTCue {
     // https://w3c.github.io/webvtt/#dom-vttcue-align
     fn SetAlign(&self, value: AlignSetting) {
         self.align.set(value);
+        self.rendering_changed();
     }
 
     // https://w3c.github.io/webvtt/#dom-vttcue-text
@@ -194,11 +527,15 @@ impl VTTCueMethods for VTTCue {
     // https://w3c.github.io/webvtt/#dom-vttcue-text
     fn SetText(&self, value: DOMString) {
         *self.text.borrow_mut() = value;
+        self.rendering_changed();
     }
 
     // https://w3c.github.io/webvtt/#dom-vttcue-getcueashtml
     fn GetCueAsHTML(&self) -> DomRoot<DocumentFragment> {
-        todo!()
+        let document = self.global().as_window().Document();
+        let fragment = DocumentFragment::new(&document);
+        self.append_cue_text(&document, fragment.upcast());
+        fragment
     }
 }
 
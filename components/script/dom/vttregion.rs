@@ -10,6 +10,7 @@ use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
 use crate::dom::bindings::root::DomRoot;
 use crate::dom::bindings::str::DOMString;
 use crate::dom::globalscope::GlobalScope;
+use crate::dom::webvttparser;
 use crate::dom::window::Window;
 use dom_struct::dom_struct;
 use std::cell::Cell;
@@ -50,6 +51,26 @@ impl VTTRegion {
     pub fn Constructor(window: &Window) -> Fallible<DomRoot<Self>> {
         Ok(VTTRegion::new(&window.global()))
     }
+
+    /// Create a region from one parsed out of a WebVTT file.
+    ///
+    /// <https://w3c.github.io/webvtt/#collect-webvtt-region-settings>
+    pub fn from_webvtt(global: &GlobalScope, region: &webvttparser::Region) -> DomRoot<Self> {
+        let vtt_region = VTTRegion::new(global);
+        *vtt_region.id.borrow_mut() = DOMString::from(region.id.clone());
+        vtt_region.width.set(region.width);
+        vtt_region.lines.set(region.lines);
+        vtt_region.region_anchor_x.set(region.region_anchor.0);
+        vtt_region.region_anchor_y.set(region.region_anchor.1);
+        vtt_region.viewport_anchor_x.set(region.viewport_anchor.0);
+        vtt_region.viewport_anchor_y.set(region.viewport_anchor.1);
+        vtt_region.scroll.set(if region.scroll_up {
+            ScrollSetting::Up
+        } else {
+            ScrollSetting::_empty
+        });
+        vtt_region
+    }
 }
 
 impl VTTRegionMethods for VTTRegion {
@@ -0,0 +1,168 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::audionode::AudioNode;
+use crate::dom::baseaudiocontext::BaseAudioContext;
+use crate::dom::bindings::codegen::Bindings::AudioNodeBinding::{
+    ChannelCountMode, ChannelInterpretation,
+};
+use crate::dom::bindings::codegen::Bindings::WaveShaperNodeBinding::WaveShaperNodeMethods;
+use crate::dom::bindings::codegen::Bindings::WaveShaperNodeBinding::{
+    OverSampleType, WaveShaperOptions,
+};
+use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject};
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::window::Window;
+use crate::realms::enter_realm;
+use crate::script_runtime::JSContext;
+use dom_struct::dom_struct;
+use js::jsapi::{Heap, JSObject};
+use js::rust::CustomAutoRooterGuard;
+use js::typedarray::{CreateWith, Float32Array};
+use servo_media::audio::node::{AudioNodeInit, AudioNodeMessage};
+use servo_media::audio::wave_shaper_node::OverSampleType as ServoMediaOverSampleType;
+use servo_media::audio::wave_shaper_node::{WaveShaperNodeMessage, WaveShaperNodeOptions};
+use std::cell::Cell;
+use std::ptr::{self, NonNull};
+
+#[dom_struct]
+pub struct WaveShaperNode {
+    node: AudioNode,
+    /// A copy of the curve handed to the rendering thread, created when the
+    /// curve is set.
+    #[ignore_malloc_size_of = "mozjs"]
+    curve: Heap<*mut JSObject>,
+    oversample: Cell<OverSampleType>,
+}
+
+impl WaveShaperNode {
+    #[allow(unrooted_must_root)]
+    pub fn new_inherited(
+        _: &Window,
+        context: &BaseAudioContext,
+        options: &WaveShaperOptions,
+    ) -> Fallible<WaveShaperNode> {
+        let node_options =
+            options
+                .parent
+                .unwrap_or(2, ChannelCountMode::Max, ChannelInterpretation::Speakers);
+        if let Some(ref curve) = options.curve {
+            if curve.len() < 2 {
+                return Err(Error::InvalidState);
+            }
+        }
+        let node = AudioNode::new_inherited(
+            AudioNodeInit::WaveShaperNode(options.into()),
+            context,
+            node_options,
+            1, // inputs
+            1, // outputs
+        )?;
+        Ok(WaveShaperNode {
+            node,
+            curve: Heap::default(),
+            oversample: Cell::new(options.oversample),
+        })
+    }
+
+    #[allow(unrooted_must_root)]
+    pub fn new(
+        window: &Window,
+        context: &BaseAudioContext,
+        options: &WaveShaperOptions,
+    ) -> Fallible<DomRoot<WaveShaperNode>> {
+        let node = WaveShaperNode::new_inherited(window, context, options)?;
+        let node = reflect_dom_object(Box::new(node), window);
+        if let Some(ref curve) = options.curve {
+            let curve: Vec<f32> = curve.iter().map(|value| **value).collect();
+            node.set_js_curve(&curve);
+        }
+        Ok(node)
+    }
+
+    #[allow(non_snake_case)]
+    pub fn Constructor(
+        window: &Window,
+        context: &BaseAudioContext,
+        options: &WaveShaperOptions,
+    ) -> Fallible<DomRoot<WaveShaperNode>> {
+        WaveShaperNode::new(window, context, options)
+    }
+
+    #[allow(unsafe_code)]
+    fn set_js_curve(&self, curve: &[f32]) {
+        let _ac = enter_realm(self);
+        let cx = self.global().get_cx();
+        rooted!(in (*cx) let mut array = ptr::null_mut::<JSObject>());
+        let _ = unsafe { Float32Array::create(*cx, CreateWith::Slice(curve), array.handle_mut()) };
+        self.curve.set(array.get());
+    }
+}
+
+impl WaveShaperNodeMethods for WaveShaperNode {
+    // https://webaudio.github.io/web-audio-api/#dom-waveshapernode-curve
+    fn GetCurve(&self, _cx: JSContext) -> Option<NonNull<JSObject>> {
+        NonNull::new(self.curve.get())
+    }
+
+    // https://webaudio.github.io/web-audio-api/#dom-waveshapernode-curve
+    fn SetCurve(&self, curve: Option<CustomAutoRooterGuard<Float32Array>>) -> ErrorResult {
+        let curve = match curve {
+            Some(curve) => {
+                let curve = curve.to_vec();
+                if curve.len() < 2 {
+                    return Err(Error::InvalidState);
+                }
+                // Later changes to the array passed in have no effect, so
+                // keep our own copy.
+                self.set_js_curve(&curve);
+                Some(curve)
+            },
+            None => {
+                self.curve.set(ptr::null_mut());
+                None
+            },
+        };
+        self.node.message(AudioNodeMessage::WaveShaperNode(
+            WaveShaperNodeMessage::SetCurve(curve),
+        ));
+        Ok(())
+    }
+
+    // https://webaudio.github.io/web-audio-api/#dom-waveshapernode-oversample
+    fn Oversample(&self) -> OverSampleType {
+        self.oversample.get()
+    }
+
+    // https://webaudio.github.io/web-audio-api/#dom-waveshapernode-oversample
+    fn SetOversample(&self, oversample: OverSampleType) {
+        self.oversample.set(oversample);
+        self.node.message(AudioNodeMessage::WaveShaperNode(
+            WaveShaperNodeMessage::SetOverSample(oversample.into()),
+        ));
+    }
+}
+
+impl<'a> From<&'a WaveShaperOptions> for WaveShaperNodeOptions {
+    fn from(options: &'a WaveShaperOptions) -> Self {
+        Self {
+            curve: options
+                .curve
+                .as_ref()
+                .map(|curve| curve.iter().map(|value| **value).collect()),
+            oversample: options.oversample.into(),
+        }
+    }
+}
+
+impl From<OverSampleType> for ServoMediaOverSampleType {
+    fn from(oversample: OverSampleType) -> Self {
+        match oversample {
+            OverSampleType::None => ServoMediaOverSampleType::None,
+            OverSampleType::_2x => ServoMediaOverSampleType::Double,
+            OverSampleType::_4x => ServoMediaOverSampleType::Quadruple,
+        }
+    }
+}
@@ -0,0 +1,45 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Helpers shared by the WebCodecs interfaces.
+//! https://w3c.github.io/webcodecs/
+
+use crate::dom::bindings::codegen::UnionTypes::ArrayBufferViewOrArrayBuffer;
+use crate::dom::bindings::error::{Error, ErrorResult};
+
+/// Whether `codec` can be handed to the media backend at all. Whether the
+/// codec is actually supported is up to the backend.
+pub(crate) fn is_valid_codec_string(codec: &str) -> bool {
+    !codec.trim().is_empty()
+}
+
+pub(crate) fn buffer_source_to_vec(source: &ArrayBufferViewOrArrayBuffer) -> Vec<u8> {
+    match *source {
+        ArrayBufferViewOrArrayBuffer::ArrayBufferView(ref view) => view.to_vec(),
+        ArrayBufferViewOrArrayBuffer::ArrayBuffer(ref buffer) => buffer.to_vec(),
+    }
+}
+
+/// Copies `data` to the beginning of `destination`, throwing a TypeError if
+/// it does not fit.
+#[allow(unsafe_code)]
+pub(crate) fn copy_to_buffer_source(
+    destination: &mut ArrayBufferViewOrArrayBuffer,
+    data: &[u8],
+) -> ErrorResult {
+    // Safe because we don't do anything with JS until we are done writing.
+    let destination = unsafe {
+        match *destination {
+            ArrayBufferViewOrArrayBuffer::ArrayBufferView(ref mut view) => view.as_mut_slice(),
+            ArrayBufferViewOrArrayBuffer::ArrayBuffer(ref mut buffer) => buffer.as_mut_slice(),
+        }
+    };
+    if destination.len() < data.len() {
+        return Err(Error::Type(
+            "The destination buffer is too small".to_owned(),
+        ));
+    }
+    destination[..data.len()].copy_from_slice(data);
+    Ok(())
+}
@@ -8,7 +8,7 @@ use crate::dom::bindings::codegen::Bindings::WebGLRenderingContextBinding::WebGL
 use crate::dom::bindings::codegen::Bindings::WebGLRenderingContextBinding::WebGLRenderingContextMethods;
 use crate::dom::bindings::codegen::UnionTypes::ArrayBufferViewOrArrayBuffer;
 use crate::dom::bindings::codegen::UnionTypes::Float32ArrayOrUnrestrictedFloatSequence;
-use crate::dom::bindings::codegen::UnionTypes::ImageDataOrHTMLImageElementOrHTMLCanvasElementOrHTMLVideoElement;
+use crate::dom::bindings::codegen::UnionTypes::ImageDataOrHTMLImageElementOrHTMLCanvasElementOrHTMLVideoElementOrVideoFrame;
 use crate::dom::bindings::codegen::UnionTypes::Int32ArrayOrLongSequence;
 use crate::dom::bindings::codegen::UnionTypes::Uint32ArrayOrUnsignedLongSequence;
 use crate::dom::bindings::error::{ErrorResult, Fallible};
@@ -2915,7 +2915,7 @@ impl WebGL2RenderingContextMethods for WebGL2RenderingContext {
         internal_format: i32,
         format: u32,
         data_type: u32,
-        source: ImageDataOrHTMLImageElementOrHTMLCanvasElementOrHTMLVideoElement,
+        source: ImageDataOrHTMLImageElementOrHTMLCanvasElementOrHTMLVideoElementOrVideoFrame,
     ) -> ErrorResult {
         self.base
             .TexImage2D_(target, level, internal_format, format, data_type, source)
@@ -3005,7 +3005,7 @@ impl WebGL2RenderingContextMethods for WebGL2RenderingContext {
         border: i32,
         format: u32,
         type_: u32,
-        source: ImageDataOrHTMLImageElementOrHTMLCanvasElementOrHTMLVideoElement,
+        source: ImageDataOrHTMLImageElementOrHTMLCanvasElementOrHTMLVideoElementOrVideoFrame,
     ) -> Fallible<()> {
         if self.bound_pixel_unpack_buffer.get().is_some() {
             return Ok(self.base.webgl_error(InvalidOperation));
@@ -3185,7 +3185,7 @@ impl WebGL2RenderingContextMethods for WebGL2RenderingContext {
         yoffset: i32,
         format: u32,
         data_type: u32,
-        source: ImageDataOrHTMLImageElementOrHTMLCanvasElementOrHTMLVideoElement,
+        source: ImageDataOrHTMLImageElementOrHTMLCanvasElementOrHTMLVideoElementOrVideoFrame,
     ) -> ErrorResult {
         self.base
             .TexSubImage2D_(target, level, xoffset, yoffset, format, data_type, source)
@@ -692,6 +692,15 @@ impl WebGLRenderingContext {
                 },
                 None => return Ok(None),
             },
+            TexImageSource::VideoFrame(frame) => {
+                let (data, size) = frame.get_bgra_data().ok_or(Error::InvalidState)?;
+                TexPixels::new(
+                    IpcSharedMemory::from_bytes(&data),
+                    size,
+                    PixelFormat::BGRA8,
+                    false,
+                )
+            },
         }))
     }
 
@@ -74,10 +74,12 @@ fn close_the_websocket_connection(
     canceller: &TaskCanceller,
     code: Option<u16>,
     reason: String,
+    was_clean: bool,
 ) {
     let close_task = CloseTask {
         address: address,
         failed: false,
+        clean: was_clean,
         code: code,
         reason: Some(reason),
     };
@@ -92,6 +94,7 @@ fn fail_the_websocket_connection(
     let close_task = CloseTask {
         address: address,
         failed: true,
+        clean: false,
         code: Some(close_code::ABNORMAL),
         reason: None,
     };
@@ -108,7 +111,8 @@ pub struct WebSocket {
     #[ignore_malloc_size_of = "Defined in std"]
     sender: IpcSender<WebSocketDomAction>,
     binary_type: Cell<BinaryType>,
-    protocol: DomRefCell<String>, //Subprotocol selected by server
+    protocol: DomRefCell<String>,   //Subprotocol selected by server
+    extensions: DomRefCell<String>, //Extensions accepted by server
 }
 
 impl WebSocket {
@@ -122,6 +126,7 @@ impl WebSocket {
             sender: sender,
             binary_type: Cell::new(BinaryType::Blob),
             protocol: DomRefCell::new("".to_owned()),
+            extensions: DomRefCell::new("".to_owned()),
         }
     }
 
@@ -196,6 +201,7 @@ impl WebSocket {
         // Step 8.
         let request = RequestBuilder::new(url_record, Referrer::NoReferrer)
             .origin(global.origin().immutable().clone())
+            .pipeline_id(Some(global.pipeline_id()))
             .mode(RequestMode::WebSocket { protocols });
 
         let channels = FetchChannels::WebSocket {
@@ -211,10 +217,14 @@ impl WebSocket {
         ROUTER.add_route(
             dom_event_receiver.to_opaque(),
             Box::new(move |message| match message.to().unwrap() {
-                WebSocketNetworkEvent::ConnectionEstablished { protocol_in_use } => {
+                WebSocketNetworkEvent::ConnectionEstablished {
+                    protocol_in_use,
+                    extensions_in_use,
+                } => {
                     let open_thread = ConnectionEstablishedTask {
                         address: address.clone(),
                         protocol_in_use,
+                        extensions_in_use,
                     };
                     let _ = task_source.queue_with_canceller(open_thread, &canceller);
                 },
@@ -228,13 +238,18 @@ impl WebSocket {
                 WebSocketNetworkEvent::Fail => {
                     fail_the_websocket_connection(address.clone(), &task_source, &canceller);
                 },
-                WebSocketNetworkEvent::Close(code, reason) => {
+                WebSocketNetworkEvent::Close {
+                    code,
+                    reason,
+                    was_clean,
+                } => {
                     close_the_websocket_connection(
                         address.clone(),
                         &task_source,
                         &canceller,
                         code,
                         reason,
+                        was_clean,
                     );
                 },
             }),
@@ -334,6 +349,11 @@ impl WebSocketMethods for WebSocket {
         DOMString::from(self.protocol.borrow().clone())
     }
 
+    // https://html.spec.whatwg.org/multipage/#dom-websocket-extensions
+    fn Extensions(&self) -> DOMString {
+        DOMString::from(self.extensions.borrow().clone())
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-websocket-send
     fn Send(&self, data: USVString) -> ErrorResult {
         let data_byte_len = data.0.as_bytes().len() as u64;
@@ -435,6 +455,8 @@ impl WebSocketMethods for WebSocket {
                 // Kick off _Start the WebSocket Closing Handshake_
                 // https://tools.ietf.org/html/rfc6455#section-7.1.2
                 let reason = reason.map(|reason| reason.0);
+                // If code is not present but reason is, the code is 1000.
+                let code = code.or(reason.as_ref().map(|_| close_code::NORMAL));
                 let _ = self.sender.send(WebSocketDomAction::Close(code, reason));
             },
         }
@@ -447,6 +469,7 @@ impl WebSocketMethods for WebSocket {
 struct ConnectionEstablishedTask {
     address: Trusted<WebSocket>,
     protocol_in_use: Option<String>,
+    extensions_in_use: Option<String>,
 }
 
 impl TaskOnce for ConnectionEstablishedTask {
@@ -457,8 +480,10 @@ impl TaskOnce for ConnectionEstablishedTask {
         // Step 1.
         ws.ready_state.set(WebSocketRequestState::Open);
 
-        // Step 2: Extensions.
-        // TODO: Set extensions to extensions in use.
+        // Step 2.
+        if let Some(extensions) = self.extensions_in_use {
+            *ws.extensions.borrow_mut() = extensions;
+        }
 
         // Step 3.
         if let Some(protocol_name) = self.protocol_in_use {
@@ -491,6 +516,7 @@ impl TaskOnce for BufferedAmountTask {
 struct CloseTask {
     address: Trusted<WebSocket>,
     failed: bool,
+    clean: bool,
     code: Option<u16>,
     reason: Option<String>,
 }
@@ -516,7 +542,7 @@ impl TaskOnce for CloseTask {
         }
 
         // Step 3.
-        let clean_close = !self.failed;
+        let clean_close = !self.failed && self.clean;
         let code = self.code.unwrap_or(close_code::NO_STATUS);
         let reason = DOMString::from(self.reason.unwrap_or("".to_owned()));
         let close_event = CloseEvent::new(
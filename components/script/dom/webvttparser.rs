@@ -0,0 +1,886 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Parsing of WebVTT files into regions and cues, and of cue text into
+//! WebVTT node objects.
+//!
+//! <https://w3c.github.io/webvtt/#parsing>
+
+/// <https://w3c.github.io/webvtt/#webvtt-file>
+#[derive(Debug, Default, PartialEq)]
+pub struct WebVttFile {
+    pub regions: Vec<Region>,
+    /// The contents of the file's `STYLE` blocks.
+    pub stylesheets: Vec<String>,
+    pub cues: Vec<Cue>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ParseError(pub &'static str);
+
+/// <https://w3c.github.io/webvtt/#webvtt-region>
+#[derive(Clone, Debug, PartialEq)]
+pub struct Region {
+    pub id: String,
+    /// The width of the region, as a percentage of the video width.
+    pub width: f64,
+    pub lines: u32,
+    /// The anchor point of the region, as percentages of the region size.
+    pub region_anchor: (f64, f64),
+    /// Where the region anchor is placed, as percentages of the video size.
+    pub viewport_anchor: (f64, f64),
+    pub scroll_up: bool,
+}
+
+impl Default for Region {
+    fn default() -> Region {
+        Region {
+            id: String::new(),
+            width: 100.,
+            lines: 3,
+            region_anchor: (0., 100.),
+            viewport_anchor: (0., 100.),
+            scroll_up: false,
+        }
+    }
+}
+
+/// <https://w3c.github.io/webvtt/#webvtt-cue-writing-direction>
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WritingDirection {
+    Horizontal,
+    VerticalGrowingLeft,
+    VerticalGrowingRight,
+}
+
+/// <https://w3c.github.io/webvtt/#webvtt-cue-line-alignment>
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineAlignment {
+    Start,
+    Center,
+    End,
+}
+
+/// <https://w3c.github.io/webvtt/#webvtt-cue-position-alignment>
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PositionAlignment {
+    LineLeft,
+    Center,
+    LineRight,
+    Auto,
+}
+
+/// <https://w3c.github.io/webvtt/#webvtt-cue-text-alignment>
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextAlignment {
+    Start,
+    Center,
+    End,
+    Left,
+    Right,
+}
+
+/// <https://w3c.github.io/webvtt/#webvtt-cue>
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cue {
+    pub id: String,
+    pub start_time: f64,
+    pub end_time: f64,
+    /// The identifier of the region the cue is rendered in, if any.
+    pub region: Option<String>,
+    pub writing_direction: WritingDirection,
+    pub snap_to_lines: bool,
+    /// `None` means `auto`.
+    pub line: Option<f64>,
+    pub line_alignment: LineAlignment,
+    /// `None` means `auto`.
+    pub position: Option<f64>,
+    pub position_alignment: PositionAlignment,
+    pub size: f64,
+    pub text_alignment: TextAlignment,
+    /// The raw cue text, see `parse_cue_text`.
+    pub text: String,
+}
+
+impl Cue {
+    fn new(id: String, start_time: f64, end_time: f64) -> Cue {
+        Cue {
+            id,
+            start_time,
+            end_time,
+            region: None,
+            writing_direction: WritingDirection::Horizontal,
+            snap_to_lines: true,
+            line: None,
+            line_alignment: LineAlignment::Start,
+            position: None,
+            position_alignment: PositionAlignment::Auto,
+            size: 100.,
+            text_alignment: TextAlignment::Center,
+            text: String::new(),
+        }
+    }
+}
+
+enum Block {
+    Cue(Cue),
+    Stylesheet(String),
+    Region(Region),
+}
+
+/// <https://w3c.github.io/webvtt/#webvtt-parser-algorithm>
+pub fn parse(input: &str) -> Result<WebVttFile, ParseError> {
+    // Step 1.
+    let input = input
+        .replace('\0', "\u{FFFD}")
+        .replace("\r\n", "\n")
+        .replace('\r', "\n");
+    let mut scanner = Scanner::new(input.strip_prefix('\u{FEFF}').unwrap_or(&input));
+
+    // Steps 5-7.
+    if !scanner.consume_str("WEBVTT") {
+        return Err(ParseError("missing WEBVTT signature"));
+    }
+    match scanner.peek() {
+        None | Some(' ') | Some('\t') | Some('\n') => {},
+        Some(_) => return Err(ParseError("missing WEBVTT signature")),
+    }
+
+    // Steps 8-11.
+    let mut parser = FileParser {
+        file: WebVttFile::default(),
+        seen_cue: false,
+    };
+    scanner.collect_line();
+    if !scanner.consume('\n') {
+        return Ok(parser.file);
+    }
+
+    // Step 12.
+    if scanner.peek() != Some('\n') {
+        parser.collect_block(&mut scanner, true);
+    }
+
+    // Steps 13-14.
+    scanner.skip_line_feeds();
+    while !scanner.is_at_end() {
+        match parser.collect_block(&mut scanner, false) {
+            Some(Block::Cue(cue)) => parser.file.cues.push(cue),
+            Some(Block::Stylesheet(stylesheet)) => parser.file.stylesheets.push(stylesheet),
+            Some(Block::Region(region)) => {
+                parser.file.regions.retain(|r| r.id != region.id);
+                parser.file.regions.push(region);
+            },
+            None => {},
+        }
+        scanner.skip_line_feeds();
+    }
+
+    Ok(parser.file)
+}
+
+struct FileParser {
+    file: WebVttFile,
+    seen_cue: bool,
+}
+
+impl FileParser {
+    /// <https://w3c.github.io/webvtt/#collect-a-webvtt-block>
+    fn collect_block(&mut self, scanner: &mut Scanner, in_header: bool) -> Option<Block> {
+        let mut line_count = 0;
+        let mut previous_position = scanner.position;
+        let mut buffer = String::new();
+        let mut seen_arrow = false;
+        let mut cue = None;
+        let mut is_stylesheet = false;
+        let mut is_region = false;
+
+        loop {
+            let line = scanner.collect_line();
+            line_count += 1;
+            let seen_eof = !scanner.consume('\n');
+
+            if line.contains("-->") {
+                if !in_header && (line_count == 1 || (line_count == 2 && !seen_arrow)) {
+                    seen_arrow = true;
+                    previous_position = scanner.position;
+                    cue = self.parse_timings_and_settings(line, &buffer);
+                    if cue.is_some() {
+                        buffer.clear();
+                        self.seen_cue = true;
+                    }
+                } else {
+                    scanner.position = previous_position;
+                    break;
+                }
+            } else if line.is_empty() {
+                break;
+            } else {
+                if !in_header && line_count == 2 && !self.seen_cue {
+                    if is_block_header(&buffer, "STYLE") {
+                        is_stylesheet = true;
+                        buffer.clear();
+                    } else if is_block_header(&buffer, "REGION") {
+                        is_region = true;
+                        buffer.clear();
+                    }
+                }
+                if !buffer.is_empty() {
+                    buffer.push('\n');
+                }
+                buffer.push_str(line);
+                previous_position = scanner.position;
+            }
+
+            if seen_eof {
+                break;
+            }
+        }
+
+        if let Some(mut cue) = cue {
+            cue.text = buffer;
+            Some(Block::Cue(cue))
+        } else if is_stylesheet {
+            Some(Block::Stylesheet(buffer))
+        } else if is_region {
+            Some(Block::Region(parse_region_settings(&buffer)))
+        } else {
+            None
+        }
+    }
+
+    /// <https://w3c.github.io/webvtt/#collect-webvtt-cue-timings-and-settings>
+    fn parse_timings_and_settings(&self, line: &str, id: &str) -> Option<Cue> {
+        let mut scanner = Scanner::new(line);
+        scanner.skip_whitespace();
+        let start_time = scanner.collect_timestamp()?;
+        scanner.skip_whitespace();
+        if !scanner.consume_str("-->") {
+            return None;
+        }
+        scanner.skip_whitespace();
+        let end_time = scanner.collect_timestamp()?;
+
+        let mut cue = Cue::new(id.to_owned(), start_time, end_time);
+        self.parse_settings(&mut cue, scanner.rest());
+        Some(cue)
+    }
+
+    /// <https://w3c.github.io/webvtt/#parse-the-webvtt-cue-settings>
+    fn parse_settings(&self, cue: &mut Cue, input: &str) {
+        for (name, value) in settings(input) {
+            match name {
+                "region" => {
+                    if let Some(region) = self.file.regions.iter().rev().find(|r| r.id == value) {
+                        cue.region = Some(region.id.clone());
+                    }
+                },
+                "vertical" => match value {
+                    "rl" => cue.writing_direction = WritingDirection::VerticalGrowingLeft,
+                    "lr" => cue.writing_direction = WritingDirection::VerticalGrowingRight,
+                    _ => {},
+                },
+                "line" => {
+                    let (position, alignment) = split_at_comma(value);
+                    if !position.bytes().any(|b| b.is_ascii_digit()) {
+                        continue;
+                    }
+                    let (line, snap_to_lines) = if position.ends_with('%') {
+                        match parse_percentage(position) {
+                            Some(line) => (line, false),
+                            None => continue,
+                        }
+                    } else {
+                        match parse_line_number(position) {
+                            Some(line) => (line, true),
+                            None => continue,
+                        }
+                    };
+                    let line_alignment = match alignment {
+                        None | Some("start") => LineAlignment::Start,
+                        Some("center") => LineAlignment::Center,
+                        Some("end") => LineAlignment::End,
+                        Some(_) => continue,
+                    };
+                    cue.line = Some(line);
+                    cue.snap_to_lines = snap_to_lines;
+                    cue.line_alignment = line_alignment;
+                },
+                "position" => {
+                    let (position, alignment) = split_at_comma(value);
+                    let position = match parse_percentage(position) {
+                        Some(position) => position,
+                        None => continue,
+                    };
+                    let position_alignment = match alignment {
+                        None | Some("auto") => PositionAlignment::Auto,
+                        Some("line-left") => PositionAlignment::LineLeft,
+                        Some("center") => PositionAlignment::Center,
+                        Some("line-right") => PositionAlignment::LineRight,
+                        Some(_) => continue,
+                    };
+                    cue.position = Some(position);
+                    cue.position_alignment = position_alignment;
+                },
+                "size" => {
+                    if let Some(size) = parse_percentage(value) {
+                        cue.size = size;
+                    }
+                },
+                "align" => match value {
+                    "start" => cue.text_alignment = TextAlignment::Start,
+                    "center" => cue.text_alignment = TextAlignment::Center,
+                    "end" => cue.text_alignment = TextAlignment::End,
+                    "left" => cue.text_alignment = TextAlignment::Left,
+                    "right" => cue.text_alignment = TextAlignment::Right,
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+    }
+}
+
+/// Whether `buffer` is `keyword`, optionally followed by spaces or tabs.
+fn is_block_header(buffer: &str, keyword: &str) -> bool {
+    buffer.starts_with(keyword) &&
+        buffer[keyword.len()..]
+            .chars()
+            .all(|c| c == ' ' || c == '\t')
+}
+
+/// <https://w3c.github.io/webvtt/#region-settings-parsing>
+fn parse_region_settings(input: &str) -> Region {
+    let mut region = Region::default();
+    for (name, value) in settings(input) {
+        match name {
+            "id" => region.id = value.to_owned(),
+            "width" => {
+                if let Some(width) = parse_percentage(value) {
+                    region.width = width;
+                }
+            },
+            "lines" => {
+                if value.bytes().all(|b| b.is_ascii_digit()) {
+                    region.lines = value.parse().unwrap_or(u32::max_value());
+                }
+            },
+            "regionanchor" => {
+                if let Some(anchor) = parse_anchor(value) {
+                    region.region_anchor = anchor;
+                }
+            },
+            "viewportanchor" => {
+                if let Some(anchor) = parse_anchor(value) {
+                    region.viewport_anchor = anchor;
+                }
+            },
+            "scroll" => {
+                if value == "up" {
+                    region.scroll_up = true;
+                }
+            },
+            _ => {},
+        }
+    }
+    region
+}
+
+/// The `name:value` pairs of a settings list, skipping the malformed ones.
+fn settings(input: &str) -> impl Iterator<Item = (&str, &str)> {
+    input
+        .split(|c| is_whitespace(c) || c == '\n')
+        .filter_map(|setting| {
+            let index = setting.find(':')?;
+            let (name, value) = (&setting[..index], &setting[index + 1..]);
+            if name.is_empty() || value.is_empty() {
+                return None;
+            }
+            Some((name, value))
+        })
+}
+
+fn split_at_comma(value: &str) -> (&str, Option<&str>) {
+    match value.find(',') {
+        Some(index) => (&value[..index], Some(&value[index + 1..])),
+        None => (value, None),
+    }
+}
+
+/// <https://w3c.github.io/webvtt/#parse-a-percentage-string>
+fn parse_percentage(input: &str) -> Option<f64> {
+    let number = input.strip_suffix('%')?;
+    if !is_decimal(number) {
+        return None;
+    }
+    let percentage = number.parse::<f64>().ok()?;
+    if percentage > 100. {
+        return None;
+    }
+    Some(percentage)
+}
+
+/// A line number such as `-2` or `3.5`, see step 6 of the `line` setting.
+fn parse_line_number(input: &str) -> Option<f64> {
+    let number = input.strip_prefix('-').unwrap_or(input);
+    if !is_decimal(number) {
+        return None;
+    }
+    input.parse().ok()
+}
+
+/// Whether `input` is one or more digits, optionally followed by a full stop
+/// and one or more digits.
+fn is_decimal(input: &str) -> bool {
+    let mut parts = input.splitn(2, '.');
+    let is_digits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+    parts.next().map_or(false, is_digits) && parts.next().map_or(true, is_digits)
+}
+
+fn parse_anchor(value: &str) -> Option<(f64, f64)> {
+    let (x, y) = split_at_comma(value);
+    Some((parse_percentage(x)?, parse_percentage(y?)?))
+}
+
+/// <https://w3c.github.io/webvtt/#webvtt-parser-algorithm>, where whitespace
+/// excludes line feeds, which separate lines.
+fn is_whitespace(c: char) -> bool {
+    c == ' ' || c == '\t' || c == '\u{000C}'
+}
+
+/// Parse a timestamp such as `01:02.345` or `1:02:03.456` into seconds.
+pub fn parse_timestamp(input: &str) -> Option<f64> {
+    let mut scanner = Scanner::new(input);
+    let timestamp = scanner.collect_timestamp()?;
+    if !scanner.is_at_end() {
+        return None;
+    }
+    Some(timestamp)
+}
+
+/// Serialize a timestamp in seconds, with the hours always included.
+pub fn serialize_timestamp(timestamp: f64) -> String {
+    let milliseconds = (timestamp * 1000.).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        milliseconds / 3_600_000,
+        milliseconds / 60_000 % 60,
+        milliseconds / 1000 % 60,
+        milliseconds % 1000
+    )
+}
+
+struct Scanner<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(input: &'a str) -> Scanner<'a> {
+        Scanner { input, position: 0 }
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.position >= self.input.len()
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.position..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn consume(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.position += c.len_utf8();
+            return true;
+        }
+        false
+    }
+
+    fn consume_str(&mut self, s: &str) -> bool {
+        if self.rest().starts_with(s) {
+            self.position += s.len();
+            return true;
+        }
+        false
+    }
+
+    fn collect_while<F: Fn(char) -> bool>(&mut self, predicate: F) -> &'a str {
+        let rest = self.rest();
+        let len = rest.find(|c| !predicate(c)).unwrap_or(rest.len());
+        self.position += len;
+        &rest[..len]
+    }
+
+    fn collect_line(&mut self) -> &'a str {
+        self.collect_while(|c| c != '\n')
+    }
+
+    fn skip_line_feeds(&mut self) {
+        self.collect_while(|c| c == '\n');
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.collect_while(is_whitespace);
+    }
+
+    fn collect_digits(&mut self) -> &'a str {
+        self.collect_while(|c| c.is_ascii_digit())
+    }
+
+    /// <https://w3c.github.io/webvtt/#collect-a-webvtt-timestamp>
+    fn collect_timestamp(&mut self) -> Option<f64> {
+        // Steps 3-6.
+        let first = self.collect_digits();
+        if first.is_empty() {
+            return None;
+        }
+        let value1 = first.parse::<u64>().ok()?;
+        let hours_first = first.len() != 2 || value1 > 59;
+
+        // Steps 7-9.
+        if !self.consume(':') {
+            return None;
+        }
+        let value2 = self.collect_two_digits()?;
+
+        // Step 10.
+        let (hours, minutes, seconds) = if hours_first || self.peek() == Some(':') {
+            if !self.consume(':') {
+                return None;
+            }
+            (value1, value2, self.collect_two_digits()?)
+        } else {
+            (0, value1, value2)
+        };
+
+        // Steps 11-13.
+        if !self.consume('.') {
+            return None;
+        }
+        let fraction = self.collect_digits();
+        if fraction.len() != 3 {
+            return None;
+        }
+        let milliseconds = fraction.parse::<u64>().ok()?;
+
+        // Steps 14-15.
+        if minutes > 59 || seconds > 59 {
+            return None;
+        }
+        Some((hours * 3600 + minutes * 60 + seconds) as f64 + milliseconds as f64 / 1000.)
+    }
+
+    fn collect_two_digits(&mut self) -> Option<u64> {
+        let digits = self.collect_digits();
+        if digits.len() != 2 {
+            return None;
+        }
+        digits.parse().ok()
+    }
+}
+
+/// <https://w3c.github.io/webvtt/#webvtt-node-object>
+#[derive(Clone, Debug, PartialEq)]
+pub enum CueNode {
+    Text(String),
+    /// A timestamp in seconds.
+    Timestamp(f64),
+    Internal(CueInternalNode),
+}
+
+/// <https://w3c.github.io/webvtt/#webvtt-internal-node-object>
+#[derive(Clone, Debug, PartialEq)]
+pub struct CueInternalNode {
+    pub kind: CueNodeKind,
+    pub classes: Vec<String>,
+    /// The voice name of a voice object, or the language tag of a language
+    /// object.
+    pub annotation: Option<String>,
+    pub children: Vec<CueNode>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CueNodeKind {
+    Class,
+    Italic,
+    Bold,
+    Underline,
+    Ruby,
+    RubyText,
+    Voice,
+    Language,
+}
+
+impl CueNodeKind {
+    fn from_tag_name(name: &str) -> Option<CueNodeKind> {
+        Some(match name {
+            "c" => CueNodeKind::Class,
+            "i" => CueNodeKind::Italic,
+            "b" => CueNodeKind::Bold,
+            "u" => CueNodeKind::Underline,
+            "ruby" => CueNodeKind::Ruby,
+            "rt" => CueNodeKind::RubyText,
+            "v" => CueNodeKind::Voice,
+            "lang" => CueNodeKind::Language,
+            _ => return None,
+        })
+    }
+
+    fn tag_name(&self) -> &'static str {
+        match *self {
+            CueNodeKind::Class => "c",
+            CueNodeKind::Italic => "i",
+            CueNodeKind::Bold => "b",
+            CueNodeKind::Underline => "u",
+            CueNodeKind::Ruby => "ruby",
+            CueNodeKind::RubyText => "rt",
+            CueNodeKind::Voice => "v",
+            CueNodeKind::Language => "lang",
+        }
+    }
+
+    /// The local name of the HTML element this kind of node is converted to.
+    ///
+    /// <https://w3c.github.io/webvtt/#webvtt-cue-text-dom-construction-rules>
+    pub fn html_local_name(&self) -> &'static str {
+        match *self {
+            CueNodeKind::Italic => "i",
+            CueNodeKind::Bold => "b",
+            CueNodeKind::Underline => "u",
+            CueNodeKind::Ruby => "ruby",
+            CueNodeKind::RubyText => "rt",
+            CueNodeKind::Class | CueNodeKind::Voice | CueNodeKind::Language => "span",
+        }
+    }
+}
+
+/// <https://w3c.github.io/webvtt/#cue-text-parsing-rules>
+pub fn parse_cue_text(input: &str) -> Vec<CueNode> {
+    let mut root = vec![];
+    let mut open_nodes: Vec<CueInternalNode> = vec![];
+
+    fn append(root: &mut Vec<CueNode>, open_nodes: &mut Vec<CueInternalNode>, node: CueNode) {
+        match open_nodes.last_mut() {
+            Some(current) => current.children.push(node),
+            None => root.push(node),
+        }
+    }
+
+    fn close(root: &mut Vec<CueNode>, open_nodes: &mut Vec<CueInternalNode>) {
+        if let Some(node) = open_nodes.pop() {
+            append(root, open_nodes, CueNode::Internal(node));
+        }
+    }
+
+    let mut tokenizer = CueTextTokenizer {
+        scanner: Scanner::new(input),
+    };
+    while let Some(token) = tokenizer.next_token() {
+        match token {
+            CueTextToken::Text(text) => append(&mut root, &mut open_nodes, CueNode::Text(text)),
+            CueTextToken::StartTag {
+                name,
+                classes,
+                annotation,
+            } => {
+                let kind = match CueNodeKind::from_tag_name(&name) {
+                    Some(CueNodeKind::RubyText)
+                        if open_nodes.last().map(|n| n.kind) != Some(CueNodeKind::Ruby) =>
+                    {
+                        continue;
+                    }
+                    Some(kind) => kind,
+                    None => continue,
+                };
+                let annotation = match kind {
+                    CueNodeKind::Voice | CueNodeKind::Language => Some(annotation),
+                    _ => None,
+                };
+                open_nodes.push(CueInternalNode {
+                    kind,
+                    classes,
+                    annotation,
+                    children: vec![],
+                });
+            },
+            CueTextToken::EndTag(name) => match open_nodes.last().map(|n| n.kind) {
+                Some(kind) if kind.tag_name() == name => close(&mut root, &mut open_nodes),
+                Some(CueNodeKind::RubyText) if name == "ruby" => {
+                    close(&mut root, &mut open_nodes);
+                    close(&mut root, &mut open_nodes);
+                },
+                _ => {},
+            },
+            CueTextToken::Timestamp(timestamp) => {
+                if let Some(timestamp) = parse_timestamp(&timestamp) {
+                    append(&mut root, &mut open_nodes, CueNode::Timestamp(timestamp));
+                }
+            },
+        }
+    }
+    while !open_nodes.is_empty() {
+        close(&mut root, &mut open_nodes);
+    }
+    root
+}
+
+enum CueTextToken {
+    Text(String),
+    StartTag {
+        name: String,
+        classes: Vec<String>,
+        annotation: String,
+    },
+    EndTag(String),
+    Timestamp(String),
+}
+
+/// <https://w3c.github.io/webvtt/#webvtt-cue-text-tokenizer>
+struct CueTextTokenizer<'a> {
+    scanner: Scanner<'a>,
+}
+
+impl<'a> CueTextTokenizer<'a> {
+    fn next_char(&mut self) -> Option<char> {
+        let c = self.scanner.peek()?;
+        self.scanner.position += c.len_utf8();
+        Some(c)
+    }
+
+    fn next_token(&mut self) -> Option<CueTextToken> {
+        if self.scanner.is_at_end() {
+            return None;
+        }
+
+        // Data state.
+        if !self.scanner.consume('<') {
+            let mut text = String::new();
+            while let Some(c) = self.scanner.peek() {
+                match c {
+                    '<' => break,
+                    '&' => text.push_str(&self.consume_character_reference()),
+                    _ => {
+                        self.scanner.position += c.len_utf8();
+                        text.push(c);
+                    },
+                }
+            }
+            return Some(CueTextToken::Text(text));
+        }
+
+        // Tag state.
+        match self.scanner.peek() {
+            Some('/') => {
+                self.scanner.position += 1;
+                let name = self.scanner.collect_while(|c| c != '>').to_owned();
+                self.scanner.consume('>');
+                Some(CueTextToken::EndTag(name))
+            },
+            Some(c) if c.is_ascii_digit() => {
+                let timestamp = self.scanner.collect_while(|c| c != '>').to_owned();
+                self.scanner.consume('>');
+                Some(CueTextToken::Timestamp(timestamp))
+            },
+            _ => Some(self.start_tag()),
+        }
+    }
+
+    /// The start tag, start tag class and start tag annotation states.
+    fn start_tag(&mut self) -> CueTextToken {
+        let name = self
+            .scanner
+            .collect_while(|c| c != '.' && c != '>' && !is_whitespace(c) && c != '\n')
+            .to_owned();
+        let mut classes = vec![];
+        let mut annotation = String::new();
+
+        if self.scanner.consume('.') {
+            loop {
+                let class = self
+                    .scanner
+                    .collect_while(|c| c != '.' && c != '>' && !is_whitespace(c) && c != '\n');
+                if !class.is_empty() {
+                    classes.push(class.to_owned());
+                }
+                if !self.scanner.consume('.') {
+                    break;
+                }
+            }
+        }
+
+        if self.scanner.peek().map_or(false, |c| c != '>') {
+            self.next_char();
+            while let Some(c) = self.scanner.peek() {
+                match c {
+                    '>' => break,
+                    '&' => annotation.push_str(&self.consume_character_reference()),
+                    _ => {
+                        self.scanner.position += c.len_utf8();
+                        annotation.push(c);
+                    },
+                }
+            }
+        }
+        self.scanner.consume('>');
+
+        let annotation = annotation
+            .split(|c| is_whitespace(c) || c == '\n')
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        CueTextToken::StartTag {
+            name,
+            classes,
+            annotation,
+        }
+    }
+
+    /// Consume a character reference starting at an ampersand, or just the
+    /// ampersand if it doesn't start one.
+    fn consume_character_reference(&mut self) -> String {
+        debug_assert_eq!(self.scanner.peek(), Some('&'));
+        let rest = &self.scanner.rest()[1..];
+        let end = match rest.find(';') {
+            Some(end) if end <= 32 => end,
+            _ => {
+                self.scanner.position += 1;
+                return "&".to_owned();
+            },
+        };
+        let name = &rest[..end];
+        let decoded = if let Some(number) = name.strip_prefix('#') {
+            let code_point = match number.strip_prefix('x').or(number.strip_prefix('X')) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => number.parse().ok(),
+            };
+            code_point.map(|c| std::char::from_u32(c).unwrap_or('\u{FFFD}'))
+        } else {
+            match name {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "lrm" => Some('\u{200E}'),
+                "rlm" => Some('\u{200F}'),
+                "nbsp" => Some('\u{00A0}'),
+                _ => None,
+            }
+        };
+        match decoded {
+            Some(c) => {
+                self.scanner.position += end + 2;
+                c.to_string()
+            },
+            None => {
+                self.scanner.position += 1;
+                "&".to_owned()
+            },
+        }
+    }
+}
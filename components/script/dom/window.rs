@@ -30,6 +30,7 @@ use crate::dom::bindings::trace::{JSTraceable, RootedTraceableBox};
 use crate::dom::bindings::utils::{GlobalStaticData, WindowProxyHandler};
 use crate::dom::bindings::weakref::DOMTracker;
 use crate::dom::bluetooth::BluetoothExtraPermissionData;
+use crate::dom::cachestorage::CacheStorage;
 use crate::dom::crypto::Crypto;
 use crate::dom::cssstyledeclaration::{CSSModificationAccess, CSSStyleDeclaration, CSSStyleOwner};
 use crate::dom::customelementregistry::CustomElementRegistry;
@@ -113,7 +114,8 @@ use script_layout_interface::rpc::{
 };
 use script_layout_interface::{PendingImageState, TrustedNodeAddress};
 use script_traits::webdriver_msg::{WebDriverJSError, WebDriverJSResult};
-use script_traits::{ConstellationControlMsg, DocumentState, HistoryEntryReplacement, LoadData};
+use script_traits::{AudioOutputMsg, ConstellationControlMsg, DocumentState};
+use script_traits::{HistoryEntryReplacement, LoadData};
 use script_traits::{
     ScriptMsg, ScriptToConstellationChan, ScrollState, StructuredSerializedData, TimerEventId,
 };
@@ -340,6 +342,11 @@ pub struct Window {
     #[ignore_malloc_size_of = "defined in script_thread"]
     player_context: WindowGLContext,
 
+    /// The mixer of the audio output of this window's browser, while the
+    /// embedder captures it.
+    #[ignore_malloc_size_of = "channels are hard"]
+    audio_output_capture: DomRefCell<Option<IpcSender<AudioOutputMsg>>>,
+
     /// A mechanism to force the compositor to process events.
     #[ignore_malloc_size_of = "traits are cumbersome"]
     event_loop_waker: Option<Box<dyn EventLoopWaker>>,
@@ -354,6 +361,9 @@ pub struct Window {
 
     /// https://dom.spec.whatwg.org/#window-current-event
     current_event: DomRefCell<Option<Dom<Event>>>,
+
+    /// <https://html.spec.whatwg.org/multipage/#import-maps-allowed>
+    import_maps_allowed: Cell<bool>,
 }
 
 impl Window {
@@ -538,6 +548,18 @@ impl Window {
         self.player_context.clone()
     }
 
+    /// The channel that media players send their audio output to, instead of
+    /// playing it, while the embedder captures the audio output of the browser.
+    pub fn audio_output_capture(&self) -> Option<IpcSender<AudioOutputMsg>> {
+        self.audio_output_capture.borrow().clone()
+    }
+
+    /// Only media players created after this call are affected, since a
+    /// player's audio output can't be rerouted once it is created.
+    pub fn set_audio_output_capture(&self, mixer: Option<IpcSender<AudioOutputMsg>>) {
+        *self.audio_output_capture.borrow_mut() = mixer;
+    }
+
     pub fn get_event_loop_waker(&self) -> Option<Box<dyn EventLoopWaker>> {
         self.event_loop_waker.as_ref().map(|w| (*w).clone_box())
     }
@@ -825,6 +847,11 @@ impl WindowMethods for Window {
         self.upcast::<GlobalScope>().crypto()
     }
 
+    // https://w3c.github.io/ServiceWorker/#self-caches
+    fn Caches(&self) -> DomRoot<CacheStorage> {
+        self.upcast::<GlobalScope>().caches()
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-frameelement
     fn GetFrameElement(&self) -> Option<DomRoot<Element>> {
         // Steps 1-3.
@@ -1398,6 +1425,14 @@ impl Window {
         current
     }
 
+    pub(crate) fn import_maps_allowed(&self) -> bool {
+        self.import_maps_allowed.get()
+    }
+
+    pub(crate) fn set_import_maps_allowed(&self, allowed: bool) {
+        self.import_maps_allowed.set(allowed);
+    }
+
     /// https://html.spec.whatwg.org/multipage/#window-post-message-steps
     fn post_message_impl(
         &self,
@@ -2465,10 +2500,12 @@ impl Window {
             userscripts_path,
             replace_surrogates,
             player_context,
+            audio_output_capture: Default::default(),
             event_loop_waker,
             visible: Cell::new(true),
             layout_marker: DomRefCell::new(Rc::new(Cell::new(true))),
             current_event: DomRefCell::new(None),
+            import_maps_allowed: Cell::new(true),
         });
 
         unsafe { WindowBinding::Wrap(JSContext::from_ptr(runtime.cx()), win) }
@@ -138,6 +138,7 @@ impl Worker {
             worker_load_origin,
             String::from(&*worker_options.name),
             worker_options.type_,
+            worker_options.credentials.into(),
             closing.clone(),
             global.image_cache(),
             browsing_context,
@@ -18,6 +18,7 @@ use crate::dom::bindings::root::{DomRoot, MutNullableDom};
 use crate::dom::bindings::settings_stack::AutoEntryScript;
 use crate::dom::bindings::str::{DOMString, USVString};
 use crate::dom::bindings::trace::RootedTraceableBox;
+use crate::dom::cachestorage::CacheStorage;
 use crate::dom::crypto::Crypto;
 use crate::dom::dedicatedworkerglobalscope::DedicatedWorkerGlobalScope;
 use crate::dom::globalscope::GlobalScope;
@@ -25,11 +26,13 @@ use crate::dom::identityhub::Identities;
 use crate::dom::performance::Performance;
 use crate::dom::promise::Promise;
 use crate::dom::serviceworkerglobalscope::ServiceWorkerGlobalScope;
+use crate::dom::sharedworkerglobalscope::SharedWorkerGlobalScope;
 use crate::dom::window::{base64_atob, base64_btoa};
 use crate::dom::workerlocation::WorkerLocation;
 use crate::dom::workernavigator::WorkerNavigator;
 use crate::fetch;
 use crate::realms::{enter_realm, InRealm};
+use crate::script_module::ModuleTree;
 use crate::script_runtime::JSContext;
 use crate::script_runtime::{get_reports, CommonScriptMsg, Runtime, ScriptChan, ScriptPort};
 use crate::task::TaskCanceller;
@@ -52,6 +55,7 @@ use js::rust::{HandleValue, ParentRuntime};
 use msg::constellation_msg::{PipelineId, PipelineNamespace};
 use net_traits::request::{
     CredentialsMode, Destination, ParserMetadata, RequestBuilder as NetRequestInit,
+    ServiceWorkersMode,
 };
 use net_traits::IpcSend;
 use parking_lot::Mutex;
@@ -208,6 +212,10 @@ impl WorkerGlobalScope {
         *self.worker_url.borrow_mut() = url;
     }
 
+    pub fn worker_name(&self) -> DOMString {
+        self.worker_name.clone()
+    }
+
     pub fn get_worker_id(&self) -> WorkerId {
         self.worker_id.clone()
     }
@@ -240,6 +248,14 @@ impl WorkerGlobalScopeMethods for WorkerGlobalScope {
 
     // https://html.spec.whatwg.org/multipage/#dom-workerglobalscope-importscripts
     fn ImportScripts(&self, url_strings: Vec<DOMString>) -> ErrorResult {
+        // https://html.spec.whatwg.org/multipage/#import-scripts-into-worker-global-scope
+        // Step 1.
+        if self.worker_type == WorkerType::Module {
+            return Err(Error::Type(
+                "importScripts() can't be used in module workers".to_owned(),
+            ));
+        }
+
         let mut urls = Vec::with_capacity(url_strings.len());
         for url in url_strings {
             let url = self.worker_url.borrow().join(&url);
@@ -252,8 +268,15 @@ impl WorkerGlobalScopeMethods for WorkerGlobalScope {
         rooted!(in(self.runtime.borrow().as_ref().unwrap().cx()) let mut rval = UndefinedValue());
         for url in urls {
             let global_scope = self.upcast::<GlobalScope>();
+            // A service worker is never controlled by a service worker.
+            let service_workers_mode = if self.is::<ServiceWorkerGlobalScope>() {
+                ServiceWorkersMode::None
+            } else {
+                ServiceWorkersMode::All
+            };
             let request = NetRequestInit::new(url.clone(), global_scope.get_referrer())
                 .destination(Destination::Script)
+                .service_workers_mode(service_workers_mode)
                 .credentials_mode(CredentialsMode::Include)
                 .parser_metadata(ParserMetadata::NotParserInserted)
                 .use_url_credentials(true)
@@ -302,6 +325,11 @@ impl WorkerGlobalScopeMethods for WorkerGlobalScope {
         self.upcast::<GlobalScope>().crypto()
     }
 
+    // https://w3c.github.io/ServiceWorker/#self-caches
+    fn Caches(&self) -> DomRoot<CacheStorage> {
+        self.upcast::<GlobalScope>().caches()
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-windowbase64-btoa
     fn Btoa(&self, btoa: DOMString) -> Fallible<DOMString> {
         base64_btoa(btoa)
@@ -446,15 +474,43 @@ impl WorkerGlobalScope {
         }
     }
 
+    /// <https://html.spec.whatwg.org/multipage/#run-a-module-script>
+    pub fn run_a_module_script(&self, module_tree: &ModuleTree) {
+        let global = self.upcast::<GlobalScope>();
+        let _aes = AutoEntryScript::new(global);
+
+        // Step 6.
+        if module_tree.get_rethrow_error().borrow().is_some() {
+            module_tree.report_error(global);
+            return;
+        }
+
+        let record = module_tree
+            .get_record()
+            .borrow()
+            .as_ref()
+            .map(|record| record.handle());
+
+        if let Some(record) = record {
+            if let Err(exception) = module_tree.execute_module(global, record) {
+                module_tree.set_rethrow_error(exception);
+                module_tree.report_error(global);
+            }
+        }
+    }
+
     pub fn script_chan(&self) -> Box<dyn ScriptChan + Send> {
         let dedicated = self.downcast::<DedicatedWorkerGlobalScope>();
         let service_worker = self.downcast::<ServiceWorkerGlobalScope>();
+        let shared = self.downcast::<SharedWorkerGlobalScope>();
         if let Some(dedicated) = dedicated {
             return dedicated.script_chan();
         } else if let Some(service_worker) = service_worker {
             return service_worker.script_chan();
+        } else if let Some(shared) = shared {
+            return shared.script_chan();
         } else {
-            panic!("need to implement a sender for SharedWorker")
+            unreachable!("Unknown kind of worker global scope")
         }
     }
 
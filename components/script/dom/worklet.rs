@@ -10,6 +10,7 @@
 //! thread pool implementation, which only performs GC or code loading on
 //! a backup thread, not on the primary worklet thread.
 
+use crate::dom::audioworkletglobalscope::AudioWorkletRegistry;
 use crate::dom::bindings::codegen::Bindings::RequestBinding::RequestCredentials;
 use crate::dom::bindings::codegen::Bindings::WindowBinding::WindowBinding::WindowMethods;
 use crate::dom::bindings::codegen::Bindings::WorkletBinding::WorkletMethods;
@@ -80,7 +81,7 @@ pub struct Worklet {
 }
 
 impl Worklet {
-    fn new_inherited(window: &Window, global_type: WorkletGlobalScopeType) -> Worklet {
+    pub fn new_inherited(window: &Window, global_type: WorkletGlobalScopeType) -> Worklet {
         Worklet {
             reflector: Reflector::new(),
             window: Dom::from_ref(window),
@@ -252,6 +253,8 @@ pub struct WorkletThreadPool {
     control_sender_0: Sender<WorkletControl>,
     control_sender_1: Sender<WorkletControl>,
     control_sender_2: Sender<WorkletControl>,
+    // The processors registered by audio worklets.
+    audio_worklet_registry: AudioWorkletRegistry,
 }
 
 impl Drop for WorkletThreadPool {
@@ -272,6 +275,7 @@ impl WorkletThreadPool {
         let primary_sender = primary_role.sender.clone();
         let hot_backup_sender = hot_backup_role.sender.clone();
         let cold_backup_sender = cold_backup_role.sender.clone();
+        let audio_worklet_registry = global_init.audio_worklet_registry.clone();
         let init = WorkletThreadInit {
             primary_sender: primary_sender.clone(),
             hot_backup_sender: hot_backup_sender.clone(),
@@ -285,9 +289,20 @@ impl WorkletThreadPool {
             control_sender_0: WorkletThread::spawn(primary_role, init.clone()),
             control_sender_1: WorkletThread::spawn(hot_backup_role, init.clone()),
             control_sender_2: WorkletThread::spawn(cold_backup_role, init),
+            audio_worklet_registry: audio_worklet_registry,
         }
     }
 
+    /// An executor for tasks to be performed by a worklet's global scope.
+    pub fn executor(&self, worklet_id: WorkletId) -> WorkletExecutor {
+        WorkletExecutor::new(worklet_id, self.primary_sender.clone())
+    }
+
+    /// The processors registered by audio worklets.
+    pub fn audio_worklet_registry(&self) -> &AudioWorkletRegistry {
+        &self.audio_worklet_registry
+    }
+
     /// Loads a worklet module into every worklet thread.
     /// If all of the threads load successfully, the promise is resolved.
     /// If any of the threads fails to load, the promise is rejected.
@@ -333,6 +348,7 @@ impl WorkletThreadPool {
         ] {
             let _ = sender.send(WorkletControl::ExitWorklet(worklet_id));
         }
+        self.audio_worklet_registry.remove_worklet(worklet_id);
         self.wake_threads();
     }
 
@@ -751,6 +767,11 @@ impl WorkletExecutor {
         }
     }
 
+    /// The worklet this executor performs tasks for.
+    pub fn worklet_id(&self) -> WorkletId {
+        self.worklet_id
+    }
+
     /// Schedule a worklet task to be peformed by the worklet thread pool.
     pub fn schedule_a_worklet_task(&self, task: WorkletTask) {
         let _ = self
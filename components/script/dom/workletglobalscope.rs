@@ -2,6 +2,9 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use crate::dom::audioworkletglobalscope::AudioWorkletGlobalScope;
+use crate::dom::audioworkletglobalscope::AudioWorkletRegistry;
+use crate::dom::audioworkletglobalscope::AudioWorkletTask;
 use crate::dom::bindings::inheritance::Castable;
 use crate::dom::bindings::root::DomRoot;
 use crate::dom::globalscope::GlobalScope;
@@ -139,6 +142,10 @@ impl WorkletGlobalScope {
                 Some(global) => global.perform_a_worklet_task(task),
                 None => warn!("This is not a paint worklet."),
             },
+            WorkletTask::Audio(task) => match self.downcast::<AudioWorkletGlobalScope>() {
+                Some(global) => global.perform_a_worklet_task(task),
+                None => warn!("This is not an audio worklet."),
+            },
         }
     }
 }
@@ -170,6 +177,8 @@ pub struct WorkletGlobalScopeInit {
     pub gpu_id_hub: Arc<Mutex<Identities>>,
     /// Is considered secure
     pub inherited_secure_context: Option<bool>,
+    /// The processors registered in audio worklets
+    pub audio_worklet_registry: AudioWorkletRegistry,
 }
 
 /// <https://drafts.css-houdini.org/worklets/#worklet-global-scope-type>
@@ -179,6 +188,8 @@ pub enum WorkletGlobalScopeType {
     Test,
     /// A paint worklet
     Paint,
+    /// An audio worklet
+    Audio,
 }
 
 impl WorkletGlobalScopeType {
@@ -206,6 +217,13 @@ impl WorkletGlobalScopeType {
                 executor,
                 init,
             )),
+            WorkletGlobalScopeType::Audio => DomRoot::upcast(AudioWorkletGlobalScope::new(
+                runtime,
+                pipeline_id,
+                base_url,
+                executor,
+                init,
+            )),
         }
     }
 }
@@ -214,4 +232,5 @@ impl WorkletGlobalScopeType {
 pub enum WorkletTask {
     Test(TestWorkletTask),
     Paint(PaintWorkletTask),
+    Audio(AudioWorkletTask),
 }
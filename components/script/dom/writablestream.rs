@@ -0,0 +1,572 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::QueuingStrategyBinding::QueuingStrategy;
+use crate::dom::bindings::codegen::Bindings::UnderlyingSinkBinding::UnderlyingSink;
+use crate::dom::bindings::codegen::Bindings::WritableStreamBinding::WritableStreamMethods;
+use crate::dom::bindings::conversions::ConversionResult;
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{DomRoot, MutNullableDom};
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::promisenativehandler::{Callback, PromiseNativeHandler};
+use crate::dom::queuingstrategy::{extract_high_water_mark, extract_size_algorithm, SizeAlgorithm};
+use crate::dom::readablestreamdefaultreader::rejected_with_type_error;
+use crate::dom::underlyingsourcecontainer::resolved_promise;
+use crate::dom::writablestreamdefaultcontroller::{
+    UnderlyingSinkType, WritableStreamDefaultController,
+};
+use crate::dom::writablestreamdefaultwriter::WritableStreamDefaultWriter;
+use crate::realms::{enter_realm, InRealm};
+use crate::script_runtime::JSContext;
+use dom_struct::dom_struct;
+use js::jsapi::{Heap, JSObject};
+use js::jsval::{JSVal, ObjectValue, UndefinedValue};
+use js::rust::{HandleValue, MutableHandleValue};
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::mem;
+use std::ptr;
+use std::rc::Rc;
+
+/// <https://streams.spec.whatwg.org/#writablestream-state>
+#[derive(Clone, Copy, Debug, JSTraceable, MallocSizeOf, PartialEq)]
+pub enum WritableStreamState {
+    Writable,
+    Closed,
+    Erroring,
+    Errored,
+}
+
+/// <https://streams.spec.whatwg.org/#pending-abort-request>
+#[derive(JSTraceable, MallocSizeOf)]
+struct PendingAbortRequest {
+    #[ignore_malloc_size_of = "Rc is hard"]
+    promise: Rc<Promise>,
+    #[ignore_malloc_size_of = "Defined in rust-mozjs"]
+    reason: Box<Heap<JSVal>>,
+    was_already_erroring: bool,
+}
+
+/// <https://streams.spec.whatwg.org/#ws-class>
+#[dom_struct]
+pub struct WritableStream {
+    reflector_: Reflector,
+    /// <https://streams.spec.whatwg.org/#writablestream-backpressure>
+    backpressure: Cell<bool>,
+    /// <https://streams.spec.whatwg.org/#writablestream-closerequest>
+    #[ignore_malloc_size_of = "Rc is hard"]
+    close_request: DomRefCell<Option<Rc<Promise>>>,
+    /// <https://streams.spec.whatwg.org/#writablestream-controller>
+    controller: MutNullableDom<WritableStreamDefaultController>,
+    /// <https://streams.spec.whatwg.org/#writablestream-inflightwriterequest>
+    #[ignore_malloc_size_of = "Rc is hard"]
+    in_flight_write_request: DomRefCell<Option<Rc<Promise>>>,
+    /// <https://streams.spec.whatwg.org/#writablestream-inflightcloserequest>
+    #[ignore_malloc_size_of = "Rc is hard"]
+    in_flight_close_request: DomRefCell<Option<Rc<Promise>>>,
+    /// <https://streams.spec.whatwg.org/#writablestream-pendingabortrequest>
+    pending_abort_request: DomRefCell<Option<PendingAbortRequest>>,
+    /// <https://streams.spec.whatwg.org/#writablestream-state>
+    state: Cell<WritableStreamState>,
+    /// <https://streams.spec.whatwg.org/#writablestream-storederror>
+    #[ignore_malloc_size_of = "Defined in rust-mozjs"]
+    stored_error: Heap<JSVal>,
+    /// <https://streams.spec.whatwg.org/#writablestream-writer>
+    writer: MutNullableDom<WritableStreamDefaultWriter>,
+    /// <https://streams.spec.whatwg.org/#writablestream-writerequests>
+    #[ignore_malloc_size_of = "Rc is hard"]
+    write_requests: DomRefCell<VecDeque<Rc<Promise>>>,
+}
+
+impl WritableStream {
+    /// <https://streams.spec.whatwg.org/#initialize-writable-stream>
+    #[allow(unrooted_must_root)]
+    fn new_inherited() -> WritableStream {
+        WritableStream {
+            reflector_: Reflector::new(),
+            backpressure: Cell::new(false),
+            close_request: DomRefCell::new(None),
+            controller: MutNullableDom::new(None),
+            in_flight_write_request: DomRefCell::new(None),
+            in_flight_close_request: DomRefCell::new(None),
+            pending_abort_request: DomRefCell::new(None),
+            state: Cell::new(WritableStreamState::Writable),
+            stored_error: Heap::default(),
+            writer: MutNullableDom::new(None),
+            write_requests: DomRefCell::new(VecDeque::new()),
+        }
+    }
+
+    fn new(global: &GlobalScope) -> DomRoot<WritableStream> {
+        reflect_dom_object(Box::new(WritableStream::new_inherited()), global)
+    }
+
+    /// <https://streams.spec.whatwg.org/#ws-constructor>
+    #[allow(non_snake_case)]
+    pub fn Constructor(
+        cx: JSContext,
+        global: &GlobalScope,
+        underlying_sink: Option<*mut JSObject>,
+        strategy: &QueuingStrategy,
+    ) -> Fallible<DomRoot<WritableStream>> {
+        // Steps 1-2.
+        let underlying_sink_obj = underlying_sink.unwrap_or(ptr::null_mut());
+        rooted!(in(*cx) let underlying_sink_val = if underlying_sink_obj.is_null() {
+            UndefinedValue()
+        } else {
+            ObjectValue(underlying_sink_obj)
+        });
+        let underlying_sink_dict = match UnderlyingSink::new(cx, underlying_sink_val.handle()) {
+            Ok(ConversionResult::Success(dict)) => dict,
+            Ok(ConversionResult::Failure(error)) => return Err(Error::Type(error.to_string())),
+            Err(()) => return Err(Error::JSFailed),
+        };
+
+        // Step 3.
+        if !underlying_sink_dict.type_.get().is_undefined() {
+            return Err(Error::Range("Invalid type for the sink".to_owned()));
+        }
+
+        // Step 4.
+        let stream = WritableStream::new(global);
+
+        // Steps 5-7.
+        let size_algorithm = extract_size_algorithm(strategy);
+        let high_water_mark = extract_high_water_mark(strategy, 1.)?;
+        WritableStreamDefaultController::set_up(
+            &stream,
+            UnderlyingSinkType::new_js(underlying_sink_obj, &underlying_sink_dict),
+            high_water_mark,
+            size_algorithm,
+        )?;
+
+        Ok(stream)
+    }
+
+    /// <https://streams.spec.whatwg.org/#create-writable-stream>
+    pub fn new_with_sink(
+        global: &GlobalScope,
+        sink: UnderlyingSinkType,
+        high_water_mark: f64,
+        size_algorithm: SizeAlgorithm,
+    ) -> Fallible<DomRoot<WritableStream>> {
+        let stream = WritableStream::new(global);
+        WritableStreamDefaultController::set_up(&stream, sink, high_water_mark, size_algorithm)?;
+        Ok(stream)
+    }
+
+    pub fn set_controller(&self, controller: &WritableStreamDefaultController) {
+        self.controller.set(Some(controller));
+    }
+
+    pub fn get_controller(&self) -> DomRoot<WritableStreamDefaultController> {
+        self.controller
+            .get()
+            .expect("Stream should have a controller")
+    }
+
+    pub fn set_writer(&self, writer: Option<&WritableStreamDefaultWriter>) {
+        self.writer.set(writer);
+    }
+
+    pub fn state(&self) -> WritableStreamState {
+        self.state.get()
+    }
+
+    pub fn is_writable(&self) -> bool {
+        self.state.get() == WritableStreamState::Writable
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.state.get() == WritableStreamState::Closed
+    }
+
+    pub fn is_erroring(&self) -> bool {
+        self.state.get() == WritableStreamState::Erroring
+    }
+
+    pub fn is_errored(&self) -> bool {
+        self.state.get() == WritableStreamState::Errored
+    }
+
+    pub fn get_stored_error(&self, mut rval: MutableHandleValue) {
+        rval.set(self.stored_error.get());
+    }
+
+    pub fn get_backpressure(&self) -> bool {
+        self.backpressure.get()
+    }
+
+    /// <https://streams.spec.whatwg.org/#is-writable-stream-locked>
+    pub fn is_locked(&self) -> bool {
+        self.writer.get().is_some()
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-close-queued-or-in-flight>
+    pub fn close_queued_or_in_flight(&self) -> bool {
+        self.close_request.borrow().is_some() || self.in_flight_close_request.borrow().is_some()
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-has-operation-marked-in-flight>
+    pub fn has_operation_marked_in_flight(&self) -> bool {
+        self.in_flight_write_request.borrow().is_some() ||
+            self.in_flight_close_request.borrow().is_some()
+    }
+
+    /// A promise rejected with the stored error.
+    fn rejected_with_stored_error(&self) -> Rc<Promise> {
+        let global = self.global();
+        let cx = global.get_cx();
+        rooted!(in(*cx) let stored_error = self.stored_error.get());
+        Promise::new_rejected(&global, cx, stored_error.handle())
+            .expect("Couldn't reject a promise")
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-abort>
+    pub fn abort(&self, reason: HandleValue) -> Rc<Promise> {
+        let global = self.global();
+
+        // Step 1.
+        if self.is_closed() || self.is_errored() {
+            return resolved_promise(&global);
+        }
+
+        // Step 2.
+        self.get_controller().signal_abort(reason);
+
+        // Steps 3-4.
+        if self.is_closed() || self.is_errored() {
+            return resolved_promise(&global);
+        }
+
+        // Step 5.
+        if let Some(ref pending_abort_request) = *self.pending_abort_request.borrow() {
+            return pending_abort_request.promise.clone();
+        }
+
+        // Steps 6-7.
+        let was_already_erroring = self.is_erroring();
+        let reason = if was_already_erroring {
+            UndefinedValue()
+        } else {
+            reason.get()
+        };
+
+        // Steps 8-9.
+        let promise = Promise::new(&global);
+        *self.pending_abort_request.borrow_mut() = Some(PendingAbortRequest {
+            promise: promise.clone(),
+            reason: Heap::boxed(reason),
+            was_already_erroring,
+        });
+
+        // Step 10.
+        if !was_already_erroring {
+            let cx = global.get_cx();
+            rooted!(in(*cx) let reason = reason);
+            self.start_erroring(reason.handle());
+        }
+
+        // Step 11.
+        promise
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-close>
+    pub fn close(&self) -> Rc<Promise> {
+        let global = self.global();
+
+        // Step 2.
+        if self.is_closed() || self.is_errored() {
+            return rejected_with_type_error(&global, "The stream is closed or errored");
+        }
+
+        // Steps 4-5.
+        let promise = Promise::new(&global);
+        *self.close_request.borrow_mut() = Some(promise.clone());
+
+        // Step 7.
+        if let Some(writer) = self.writer.get() {
+            if self.backpressure.get() && self.is_writable() {
+                writer.resolve_ready_promise();
+            }
+        }
+
+        // Step 8.
+        self.get_controller().close();
+
+        // Step 9.
+        promise
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-add-write-request>
+    pub fn add_write_request(&self) -> Rc<Promise> {
+        let promise = Promise::new(&self.global());
+        self.write_requests.borrow_mut().push_back(promise.clone());
+        promise
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-deal-with-rejection>
+    pub fn deal_with_rejection(&self, error: HandleValue) {
+        // Steps 1-3.
+        if self.is_writable() {
+            self.start_erroring(error);
+            return;
+        }
+
+        // Steps 4-5.
+        self.finish_erroring();
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-start-erroring>
+    pub fn start_erroring(&self, reason: HandleValue) {
+        // Steps 3-5.
+        let controller = self.get_controller();
+        self.state.set(WritableStreamState::Erroring);
+        self.stored_error.set(reason.get());
+
+        // Steps 6-7.
+        if let Some(writer) = self.writer.get() {
+            writer.ensure_ready_promise_rejected(reason);
+        }
+
+        // Step 8.
+        if !self.has_operation_marked_in_flight() && controller.is_started() {
+            self.finish_erroring();
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-finish-erroring>
+    pub fn finish_erroring(&self) {
+        let global = self.global();
+        let cx = global.get_cx();
+
+        // Step 3.
+        self.state.set(WritableStreamState::Errored);
+
+        // Step 4.
+        self.get_controller().perform_error_steps();
+
+        // Steps 5-7.
+        rooted!(in(*cx) let stored_error = self.stored_error.get());
+        let write_requests = mem::replace(&mut *self.write_requests.borrow_mut(), VecDeque::new());
+        for write_request in write_requests {
+            write_request.reject(cx, stored_error.handle());
+        }
+
+        // Step 8.
+        let abort_request = match self.pending_abort_request.borrow_mut().take() {
+            Some(abort_request) => abort_request,
+            None => {
+                self.reject_close_and_closed_promise_if_needed();
+                return;
+            },
+        };
+
+        // Steps 9-11.
+        if abort_request.was_already_erroring {
+            abort_request.promise.reject(cx, stored_error.handle());
+            self.reject_close_and_closed_promise_if_needed();
+            return;
+        }
+
+        // Steps 12-14.
+        rooted!(in(*cx) let reason = abort_request.reason.get());
+        let promise = self.get_controller().perform_abort_steps(reason.handle());
+        let handler = PromiseNativeHandler::new(
+            &global,
+            Some(Box::new(AbortStepsHandler {
+                stream: DomRoot::from_ref(self),
+                promise: abort_request.promise.clone(),
+                fulfilled: true,
+            })),
+            Some(Box::new(AbortStepsHandler {
+                stream: DomRoot::from_ref(self),
+                promise: abort_request.promise,
+                fulfilled: false,
+            })),
+        );
+        let realm = enter_realm(&*global);
+        let comp = InRealm::Entered(&realm);
+        promise.append_native_handler(&handler, comp);
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-finish-in-flight-write>
+    pub fn finish_in_flight_write(&self) {
+        let request = self
+            .in_flight_write_request
+            .borrow_mut()
+            .take()
+            .expect("There should be an in-flight write request");
+        request.resolve_native(&());
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-finish-in-flight-write-with-error>
+    pub fn finish_in_flight_write_with_error(&self, error: HandleValue) {
+        let request = self
+            .in_flight_write_request
+            .borrow_mut()
+            .take()
+            .expect("There should be an in-flight write request");
+        request.reject(self.global().get_cx(), error);
+        self.deal_with_rejection(error);
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-finish-in-flight-close>
+    pub fn finish_in_flight_close(&self) {
+        // Steps 1-3.
+        let request = self
+            .in_flight_close_request
+            .borrow_mut()
+            .take()
+            .expect("There should be an in-flight close request");
+        request.resolve_native(&());
+
+        // Step 5.
+        if self.is_erroring() {
+            // Step 5.1.
+            self.stored_error.set(UndefinedValue());
+
+            // Step 5.2.
+            if let Some(abort_request) = self.pending_abort_request.borrow_mut().take() {
+                abort_request.promise.resolve_native(&());
+            }
+        }
+
+        // Step 6.
+        self.state.set(WritableStreamState::Closed);
+
+        // Steps 7-8.
+        if let Some(writer) = self.writer.get() {
+            writer.resolve_closed_promise();
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-finish-in-flight-close-with-error>
+    pub fn finish_in_flight_close_with_error(&self, error: HandleValue) {
+        let cx = self.global().get_cx();
+
+        // Steps 1-3.
+        let request = self
+            .in_flight_close_request
+            .borrow_mut()
+            .take()
+            .expect("There should be an in-flight close request");
+        request.reject(cx, error);
+
+        // Step 5.
+        if let Some(abort_request) = self.pending_abort_request.borrow_mut().take() {
+            abort_request.promise.reject(cx, error);
+        }
+
+        // Step 6.
+        self.deal_with_rejection(error);
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-mark-close-request-in-flight>
+    pub fn mark_close_request_in_flight(&self) {
+        let request = self.close_request.borrow_mut().take();
+        *self.in_flight_close_request.borrow_mut() = request;
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-mark-first-write-request-in-flight>
+    pub fn mark_first_write_request_in_flight(&self) {
+        let request = self.write_requests.borrow_mut().pop_front();
+        *self.in_flight_write_request.borrow_mut() = request;
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-reject-close-and-closed-promise-if-needed>
+    fn reject_close_and_closed_promise_if_needed(&self) {
+        let cx = self.global().get_cx();
+        rooted!(in(*cx) let stored_error = self.stored_error.get());
+
+        // Step 2.
+        if let Some(close_request) = self.close_request.borrow_mut().take() {
+            close_request.reject(cx, stored_error.handle());
+        }
+
+        // Steps 3-4.
+        if let Some(writer) = self.writer.get() {
+            writer.reject_closed_promise(stored_error.handle());
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-update-backpressure>
+    pub fn update_backpressure(&self, backpressure: bool) {
+        // Steps 3-4.
+        if let Some(writer) = self.writer.get() {
+            if backpressure != self.backpressure.get() {
+                if backpressure {
+                    writer.reset_ready_promise();
+                } else {
+                    writer.resolve_ready_promise();
+                }
+            }
+        }
+
+        // Step 5.
+        self.backpressure.set(backpressure);
+    }
+}
+
+impl WritableStreamMethods for WritableStream {
+    /// <https://streams.spec.whatwg.org/#ws-locked>
+    fn Locked(&self) -> bool {
+        self.is_locked()
+    }
+
+    /// <https://streams.spec.whatwg.org/#ws-abort>
+    fn Abort(&self, _cx: JSContext, reason: HandleValue) -> Rc<Promise> {
+        // Step 1.
+        if self.is_locked() {
+            return rejected_with_type_error(&self.global(), "The stream is locked");
+        }
+
+        // Step 2.
+        self.abort(reason)
+    }
+
+    /// <https://streams.spec.whatwg.org/#ws-close>
+    fn Close(&self) -> Rc<Promise> {
+        // Step 1.
+        if self.is_locked() {
+            return rejected_with_type_error(&self.global(), "The stream is locked");
+        }
+
+        // Step 2.
+        if self.close_queued_or_in_flight() {
+            return rejected_with_type_error(&self.global(), "The stream is already closing");
+        }
+
+        // Step 3.
+        self.close()
+    }
+
+    /// <https://streams.spec.whatwg.org/#ws-get-writer>
+    fn GetWriter(&self) -> Fallible<DomRoot<WritableStreamDefaultWriter>> {
+        WritableStreamDefaultWriter::acquire(&self.global(), self)
+    }
+}
+
+/// Step 14 of <https://streams.spec.whatwg.org/#writable-stream-finish-erroring>.
+#[derive(JSTraceable, MallocSizeOf)]
+struct AbortStepsHandler {
+    stream: DomRoot<WritableStream>,
+    #[ignore_malloc_size_of = "Rc is hard"]
+    promise: Rc<Promise>,
+    fulfilled: bool,
+}
+
+impl Callback for AbortStepsHandler {
+    fn callback(&self, cx: JSContext, v: HandleValue, _realm: InRealm) {
+        if self.fulfilled {
+            self.promise.resolve_native(&());
+        } else {
+            self.promise.reject(cx, v);
+        }
+        self.stream.reject_close_and_closed_promise_if_needed();
+    }
+}
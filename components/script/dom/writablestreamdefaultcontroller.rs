@@ -0,0 +1,496 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::abortsignal::AbortSignal;
+use crate::dom::bindings::callback::ExceptionHandling;
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::UnderlyingSinkBinding::{
+    UnderlyingSink, UnderlyingSinkAbortCallback, UnderlyingSinkCloseCallback,
+    UnderlyingSinkStartCallback, UnderlyingSinkWriteCallback,
+};
+use crate::dom::bindings::codegen::Bindings::WritableStreamDefaultControllerBinding::WritableStreamDefaultControllerMethods;
+use crate::dom::bindings::error::Fallible;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::promisenativehandler::{Callback, PromiseNativeHandler};
+use crate::dom::queuingstrategy::{QueueWithSizes, SizeAlgorithm};
+use crate::dom::transformstream::TransformStream;
+use crate::dom::underlyingsourcecontainer::{promise_from_result, resolved_promise};
+use crate::dom::writablestream::WritableStream;
+use crate::realms::{enter_realm, InRealm};
+use crate::script_runtime::JSContext;
+use dom_struct::dom_struct;
+use js::jsapi::{Heap, JSObject};
+use js::jsval::{ObjectValue, UndefinedValue};
+use js::rust::{HandleValue, MutableHandleValue};
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// An underlying sink set up from script with the `WritableStream` constructor.
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+pub struct JsUnderlyingSink {
+    /// The object the callbacks were taken from, which is their `this` value.
+    #[ignore_malloc_size_of = "Defined in rust-mozjs"]
+    object: Box<Heap<*mut JSObject>>,
+    #[ignore_malloc_size_of = "Rc is hard"]
+    start: Option<Rc<UnderlyingSinkStartCallback>>,
+    #[ignore_malloc_size_of = "Rc is hard"]
+    write: Option<Rc<UnderlyingSinkWriteCallback>>,
+    #[ignore_malloc_size_of = "Rc is hard"]
+    close: Option<Rc<UnderlyingSinkCloseCallback>>,
+    #[ignore_malloc_size_of = "Rc is hard"]
+    abort: Option<Rc<UnderlyingSinkAbortCallback>>,
+}
+
+/// <https://streams.spec.whatwg.org/#underlying-sink-api>
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+#[unrooted_must_root_lint::allow_unrooted_in_rc]
+pub enum UnderlyingSinkType {
+    /// A sink set up from script.
+    Js(JsUnderlyingSink),
+    /// The writable side of a transform stream.
+    Transform(Dom<TransformStream>),
+}
+
+impl UnderlyingSinkType {
+    /// Takes the algorithms of a sink from its dictionary.
+    pub fn new_js(object: *mut JSObject, dict: &UnderlyingSink) -> UnderlyingSinkType {
+        UnderlyingSinkType::Js(JsUnderlyingSink {
+            object: Heap::boxed(object),
+            start: dict.start.clone(),
+            write: dict.write.clone(),
+            close: dict.close.clone(),
+            abort: dict.abort.clone(),
+        })
+    }
+
+    /// The start algorithm, whose result is resolved by the controller.
+    fn start(
+        &self,
+        cx: JSContext,
+        controller: &WritableStreamDefaultController,
+        mut rval: MutableHandleValue,
+    ) -> Fallible<()> {
+        match *self {
+            UnderlyingSinkType::Js(ref sink) => {
+                if let Some(ref start) = sink.start {
+                    rooted!(in(*cx) let this_object = sink.object.get());
+                    let result = start.Call_(
+                        &this_object.handle(),
+                        controller,
+                        ExceptionHandling::Rethrow,
+                    )?;
+                    rval.set(result);
+                    return Ok(());
+                }
+                rval.set(UndefinedValue());
+            },
+            UnderlyingSinkType::Transform(ref stream) => {
+                rval.set(ObjectValue(stream.start_promise().promise_obj().get()));
+            },
+        }
+        Ok(())
+    }
+
+    /// The write algorithm.
+    fn write(
+        &self,
+        global: &GlobalScope,
+        chunk: HandleValue,
+        controller: &WritableStreamDefaultController,
+    ) -> Rc<Promise> {
+        let cx = global.get_cx();
+        match *self {
+            UnderlyingSinkType::Js(ref sink) => match sink.write {
+                Some(ref write) => {
+                    rooted!(in(*cx) let this_object = sink.object.get());
+                    let result = write.Call_(
+                        &this_object.handle(),
+                        chunk,
+                        controller,
+                        ExceptionHandling::Rethrow,
+                    );
+                    promise_from_result(global, result)
+                },
+                None => resolved_promise(global),
+            },
+            UnderlyingSinkType::Transform(ref stream) => stream.sink_write_algorithm(chunk),
+        }
+    }
+
+    /// The close algorithm.
+    fn close(&self, global: &GlobalScope) -> Rc<Promise> {
+        let cx = global.get_cx();
+        match *self {
+            UnderlyingSinkType::Js(ref sink) => match sink.close {
+                Some(ref close) => {
+                    rooted!(in(*cx) let this_object = sink.object.get());
+                    let result = close.Call_(&this_object.handle(), ExceptionHandling::Rethrow);
+                    promise_from_result(global, result)
+                },
+                None => resolved_promise(global),
+            },
+            UnderlyingSinkType::Transform(ref stream) => stream.sink_close_algorithm(),
+        }
+    }
+
+    /// The abort algorithm.
+    fn abort(&self, global: &GlobalScope, reason: HandleValue) -> Rc<Promise> {
+        let cx = global.get_cx();
+        match *self {
+            UnderlyingSinkType::Js(ref sink) => match sink.abort {
+                Some(ref abort) => {
+                    rooted!(in(*cx) let this_object = sink.object.get());
+                    let result =
+                        abort.Call_(&this_object.handle(), reason, ExceptionHandling::Rethrow);
+                    promise_from_result(global, result)
+                },
+                None => resolved_promise(global),
+            },
+            UnderlyingSinkType::Transform(ref stream) => stream.sink_abort_algorithm(reason),
+        }
+    }
+}
+
+/// <https://streams.spec.whatwg.org/#ws-default-controller-class>
+#[dom_struct]
+pub struct WritableStreamDefaultController {
+    reflector_: Reflector,
+    /// <https://streams.spec.whatwg.org/#writablestreamdefaultcontroller-stream>
+    stream: MutNullableDom<WritableStream>,
+    /// <https://streams.spec.whatwg.org/#writablestreamdefaultcontroller-signal>
+    signal: Dom<AbortSignal>,
+    /// The sink of the write, close and abort algorithms.
+    #[ignore_malloc_size_of = "Rc is hard"]
+    underlying_sink: Rc<UnderlyingSinkType>,
+    /// <https://streams.spec.whatwg.org/#writablestreamdefaultcontroller-queue>
+    queue: DomRefCell<QueueWithSizes>,
+    /// <https://streams.spec.whatwg.org/#writablestreamdefaultcontroller-started>
+    started: Cell<bool>,
+    /// <https://streams.spec.whatwg.org/#writablestreamdefaultcontroller-strategyhwm>
+    strategy_hwm: f64,
+    /// <https://streams.spec.whatwg.org/#writablestreamdefaultcontroller-strategysizealgorithm>
+    strategy_size_algorithm: SizeAlgorithm,
+}
+
+impl WritableStreamDefaultController {
+    #[allow(unrooted_must_root)]
+    fn new_inherited(
+        signal: &AbortSignal,
+        underlying_sink: UnderlyingSinkType,
+        strategy_hwm: f64,
+        strategy_size_algorithm: SizeAlgorithm,
+    ) -> WritableStreamDefaultController {
+        WritableStreamDefaultController {
+            reflector_: Reflector::new(),
+            stream: MutNullableDom::new(None),
+            signal: Dom::from_ref(signal),
+            underlying_sink: Rc::new(underlying_sink),
+            queue: DomRefCell::new(Default::default()),
+            started: Cell::new(false),
+            strategy_hwm,
+            strategy_size_algorithm,
+        }
+    }
+
+    #[allow(unrooted_must_root)]
+    fn new(
+        global: &GlobalScope,
+        underlying_sink: UnderlyingSinkType,
+        strategy_hwm: f64,
+        strategy_size_algorithm: SizeAlgorithm,
+    ) -> DomRoot<WritableStreamDefaultController> {
+        let signal = AbortSignal::new(global);
+        reflect_dom_object(
+            Box::new(WritableStreamDefaultController::new_inherited(
+                &signal,
+                underlying_sink,
+                strategy_hwm,
+                strategy_size_algorithm,
+            )),
+            global,
+        )
+    }
+
+    /// <https://streams.spec.whatwg.org/#set-up-writable-stream-default-controller>
+    #[allow(unrooted_must_root)]
+    pub fn set_up(
+        stream: &WritableStream,
+        underlying_sink: UnderlyingSinkType,
+        high_water_mark: f64,
+        size_algorithm: SizeAlgorithm,
+    ) -> Fallible<()> {
+        let global = stream.global();
+        let cx = global.get_cx();
+
+        // Steps 1-14.
+        let controller = WritableStreamDefaultController::new(
+            &global,
+            underlying_sink,
+            high_water_mark,
+            size_algorithm,
+        );
+        controller.stream.set(Some(stream));
+        stream.set_controller(&controller);
+
+        // Steps 15-16.
+        stream.update_backpressure(controller.get_backpressure());
+
+        // Step 17.
+        rooted!(in(*cx) let mut start_result = UndefinedValue());
+        controller
+            .underlying_sink
+            .start(cx, &controller, start_result.handle_mut())?;
+
+        // Step 18.
+        let start_promise = Promise::new_resolved(&global, cx, start_result.handle())?;
+
+        // Steps 19-20.
+        controller.react(&start_promise, ControllerStep::Start);
+        Ok(())
+    }
+
+    fn react(&self, promise: &Promise, step: ControllerStep) {
+        let global = self.global();
+        let handler = PromiseNativeHandler::new(
+            &global,
+            Some(Box::new(ControllerPromiseHandler {
+                controller: DomRoot::from_ref(self),
+                step,
+                fulfilled: true,
+            })),
+            Some(Box::new(ControllerPromiseHandler {
+                controller: DomRoot::from_ref(self),
+                step,
+                fulfilled: false,
+            })),
+        );
+        let realm = enter_realm(&*global);
+        let comp = InRealm::Entered(&realm);
+        promise.append_native_handler(&handler, comp);
+    }
+
+    fn stream(&self) -> DomRoot<WritableStream> {
+        self.stream.get().expect("Controller should have a stream")
+    }
+
+    pub fn is_started(&self) -> bool {
+        self.started.get()
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-get-backpressure>
+    fn get_backpressure(&self) -> bool {
+        self.get_desired_size() <= 0.
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-get-desired-size>
+    pub fn get_desired_size(&self) -> f64 {
+        self.strategy_hwm - self.queue.borrow().total_size()
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-get-chunk-size>
+    #[allow(unsafe_code)]
+    pub fn get_chunk_size(&self, chunk: HandleValue) -> f64 {
+        match self.strategy_size_algorithm.size(chunk) {
+            Ok(size) => size,
+            Err(error) => {
+                let global = self.global();
+                let cx = global.get_cx();
+                let _ac = enter_realm(&*global);
+                rooted!(in(*cx) let mut e = UndefinedValue());
+                unsafe { error.to_jsval(*cx, &global, e.handle_mut()) };
+                self.error_if_needed(e.handle());
+                1.
+            },
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-write>
+    #[allow(unsafe_code)]
+    pub fn write(&self, chunk: HandleValue, chunk_size: f64) {
+        // Step 1.
+        let result = self
+            .queue
+            .borrow_mut()
+            .enqueue_value_with_size(chunk, chunk_size);
+        if let Err(error) = result {
+            let global = self.global();
+            let cx = global.get_cx();
+            let _ac = enter_realm(&*global);
+            rooted!(in(*cx) let mut e = UndefinedValue());
+            unsafe { error.to_jsval(*cx, &global, e.handle_mut()) };
+            self.error_if_needed(e.handle());
+            return;
+        }
+
+        // Steps 3-4.
+        let stream = self.stream();
+        if !stream.close_queued_or_in_flight() && stream.is_writable() {
+            stream.update_backpressure(self.get_backpressure());
+        }
+
+        // Step 5.
+        self.advance_queue_if_needed();
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-close>
+    pub fn close(&self) {
+        self.queue.borrow_mut().enqueue_close_sentinel();
+        self.advance_queue_if_needed();
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-advance-queue-if-needed>
+    fn advance_queue_if_needed(&self) {
+        // Steps 1-3.
+        let stream = self.stream();
+        if !self.started.get() || stream.has_operation_marked_in_flight() {
+            return;
+        }
+
+        // Steps 4-5.
+        if stream.is_erroring() {
+            stream.finish_erroring();
+            return;
+        }
+
+        // Step 6.
+        if self.queue.borrow().is_empty() {
+            return;
+        }
+
+        // Steps 7-9.
+        let cx = self.global().get_cx();
+        rooted!(in(*cx) let mut value = UndefinedValue());
+        if self.queue.borrow().peek_queue_value(value.handle_mut()) {
+            self.process_close();
+        } else {
+            self.process_write(value.handle());
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-process-close>
+    fn process_close(&self) {
+        // Steps 1-2.
+        self.stream().mark_close_request_in_flight();
+
+        // Step 3.
+        let cx = self.global().get_cx();
+        rooted!(in(*cx) let mut sentinel = UndefinedValue());
+        self.queue.borrow_mut().dequeue_value(sentinel.handle_mut());
+
+        // Steps 5-7.
+        let sink_close_promise = self.underlying_sink.close(&self.global());
+        self.react(&sink_close_promise, ControllerStep::Close);
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-process-write>
+    fn process_write(&self, chunk: HandleValue) {
+        // Steps 1-2.
+        self.stream().mark_first_write_request_in_flight();
+
+        // Steps 3-4.
+        let sink_write_promise = self.underlying_sink.write(&self.global(), chunk, self);
+        self.react(&sink_write_promise, ControllerStep::Write);
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-error-if-needed>
+    pub fn error_if_needed(&self, error: HandleValue) {
+        if self.stream().is_writable() {
+            self.error(error);
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-error>
+    fn error(&self, error: HandleValue) {
+        self.stream().start_erroring(error);
+    }
+
+    /// Signals abort on the signal of the controller, as done by
+    /// <https://streams.spec.whatwg.org/#writable-stream-abort>.
+    pub fn signal_abort(&self, reason: HandleValue) {
+        self.signal.signal_abort(reason);
+    }
+
+    /// <https://streams.spec.whatwg.org/#ws-default-controller-private-abort>
+    pub fn perform_abort_steps(&self, reason: HandleValue) -> Rc<Promise> {
+        self.underlying_sink.abort(&self.global(), reason)
+    }
+
+    /// <https://streams.spec.whatwg.org/#ws-default-controller-private-error>
+    pub fn perform_error_steps(&self) {
+        self.queue.borrow_mut().reset();
+    }
+}
+
+impl WritableStreamDefaultControllerMethods for WritableStreamDefaultController {
+    /// <https://streams.spec.whatwg.org/#ws-default-controller-signal>
+    fn Signal(&self) -> DomRoot<AbortSignal> {
+        DomRoot::from_ref(&*self.signal)
+    }
+
+    /// <https://streams.spec.whatwg.org/#ws-default-controller-error>
+    fn Error(&self, _cx: JSContext, e: HandleValue) {
+        // Steps 1-2.
+        if !self.stream().is_writable() {
+            return;
+        }
+
+        // Step 3.
+        self.error(e);
+    }
+}
+
+/// The algorithms of a controller that wait for a promise.
+#[derive(Clone, Copy, JSTraceable, MallocSizeOf)]
+enum ControllerStep {
+    /// The start algorithm settled.
+    Start,
+    /// The write algorithm settled.
+    Write,
+    /// The close algorithm settled.
+    Close,
+}
+
+#[derive(JSTraceable, MallocSizeOf)]
+struct ControllerPromiseHandler {
+    controller: DomRoot<WritableStreamDefaultController>,
+    step: ControllerStep,
+    fulfilled: bool,
+}
+
+impl Callback for ControllerPromiseHandler {
+    fn callback(&self, cx: JSContext, v: HandleValue, _realm: InRealm) {
+        let controller = &self.controller;
+        let stream = controller.stream();
+        match (self.step, self.fulfilled) {
+            (ControllerStep::Start, true) => {
+                controller.started.set(true);
+                controller.advance_queue_if_needed();
+            },
+            (ControllerStep::Start, false) => {
+                controller.started.set(true);
+                stream.deal_with_rejection(v);
+            },
+            (ControllerStep::Write, true) => {
+                stream.finish_in_flight_write();
+                rooted!(in(*cx) let mut chunk = UndefinedValue());
+                controller
+                    .queue
+                    .borrow_mut()
+                    .dequeue_value(chunk.handle_mut());
+                if !stream.close_queued_or_in_flight() && stream.is_writable() {
+                    stream.update_backpressure(controller.get_backpressure());
+                }
+                controller.advance_queue_if_needed();
+            },
+            (ControllerStep::Write, false) => stream.finish_in_flight_write_with_error(v),
+            (ControllerStep::Close, true) => stream.finish_in_flight_close(),
+            (ControllerStep::Close, false) => stream.finish_in_flight_close_with_error(v),
+        }
+    }
+}
@@ -0,0 +1,333 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::WritableStreamDefaultWriterBinding::WritableStreamDefaultWriterMethods;
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{DomRoot, MutNullableDom};
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::readablestream::new_type_error;
+use crate::dom::readablestreamdefaultreader::rejected_with_type_error;
+use crate::dom::underlyingsourcecontainer::resolved_promise;
+use crate::dom::writablestream::{WritableStream, WritableStreamState};
+use crate::script_runtime::JSContext;
+use dom_struct::dom_struct;
+use js::jsval::UndefinedValue;
+use js::rust::HandleValue;
+use std::rc::Rc;
+
+/// <https://streams.spec.whatwg.org/#default-writer-class>
+#[dom_struct]
+pub struct WritableStreamDefaultWriter {
+    reflector_: Reflector,
+    /// <https://streams.spec.whatwg.org/#writablestreamdefaultwriter-stream>
+    stream: MutNullableDom<WritableStream>,
+    /// <https://streams.spec.whatwg.org/#writablestreamdefaultwriter-closedpromise>
+    #[ignore_malloc_size_of = "Rc is hard"]
+    closed_promise: DomRefCell<Rc<Promise>>,
+    /// <https://streams.spec.whatwg.org/#writablestreamdefaultwriter-readypromise>
+    #[ignore_malloc_size_of = "Rc is hard"]
+    ready_promise: DomRefCell<Rc<Promise>>,
+}
+
+impl WritableStreamDefaultWriter {
+    fn new_inherited(global: &GlobalScope) -> WritableStreamDefaultWriter {
+        WritableStreamDefaultWriter {
+            reflector_: Reflector::new(),
+            stream: MutNullableDom::new(None),
+            closed_promise: DomRefCell::new(Promise::new(global)),
+            ready_promise: DomRefCell::new(Promise::new(global)),
+        }
+    }
+
+    fn new(global: &GlobalScope) -> DomRoot<WritableStreamDefaultWriter> {
+        reflect_dom_object(
+            Box::new(WritableStreamDefaultWriter::new_inherited(global)),
+            global,
+        )
+    }
+
+    /// <https://streams.spec.whatwg.org/#default-writer-constructor>
+    #[allow(non_snake_case)]
+    pub fn Constructor(
+        global: &GlobalScope,
+        stream: &WritableStream,
+    ) -> Fallible<DomRoot<WritableStreamDefaultWriter>> {
+        WritableStreamDefaultWriter::acquire(global, stream)
+    }
+
+    /// <https://streams.spec.whatwg.org/#acquire-writable-stream-default-writer>
+    pub fn acquire(
+        global: &GlobalScope,
+        stream: &WritableStream,
+    ) -> Fallible<DomRoot<WritableStreamDefaultWriter>> {
+        let writer = WritableStreamDefaultWriter::new(global);
+        writer.set_up(stream)?;
+        Ok(writer)
+    }
+
+    /// <https://streams.spec.whatwg.org/#set-up-writable-stream-default-writer>
+    ///
+    /// The promises start out pending, and are settled here according to the
+    /// state of the stream.
+    fn set_up(&self, stream: &WritableStream) -> Fallible<()> {
+        // Step 1.
+        if stream.is_locked() {
+            return Err(Error::Type("The stream is locked".to_owned()));
+        }
+
+        // Steps 2-3.
+        self.stream.set(Some(stream));
+        stream.set_writer(Some(self));
+
+        let cx = self.global().get_cx();
+        rooted!(in(*cx) let mut stored_error = UndefinedValue());
+        match stream.state() {
+            // Step 5.
+            WritableStreamState::Writable => {
+                if stream.close_queued_or_in_flight() || !stream.get_backpressure() {
+                    self.resolve_ready_promise();
+                }
+            },
+            // Step 6.
+            WritableStreamState::Erroring => {
+                stream.get_stored_error(stored_error.handle_mut());
+                self.ensure_ready_promise_rejected(stored_error.handle());
+            },
+            // Step 7.
+            WritableStreamState::Closed => {
+                self.resolve_ready_promise();
+                self.resolve_closed_promise();
+            },
+            // Step 8.
+            WritableStreamState::Errored => {
+                stream.get_stored_error(stored_error.handle_mut());
+                self.ensure_ready_promise_rejected(stored_error.handle());
+                self.reject_closed_promise(stored_error.handle());
+            },
+        }
+
+        Ok(())
+    }
+
+    pub fn closed_promise(&self) -> Rc<Promise> {
+        self.closed_promise.borrow().clone()
+    }
+
+    pub fn ready_promise(&self) -> Rc<Promise> {
+        self.ready_promise.borrow().clone()
+    }
+
+    pub fn resolve_closed_promise(&self) {
+        self.closed_promise.borrow().resolve_native(&());
+    }
+
+    /// Rejects the closed promise, and marks it as handled.
+    pub fn reject_closed_promise(&self, e: HandleValue) {
+        let cx = self.global().get_cx();
+        let closed_promise = self.closed_promise.borrow().clone();
+        closed_promise.reject(cx, e);
+        closed_promise.set_promise_is_handled();
+    }
+
+    pub fn resolve_ready_promise(&self) {
+        self.ready_promise.borrow().resolve_native(&());
+    }
+
+    /// Replaces the ready promise with a new pending one.
+    pub fn reset_ready_promise(&self) {
+        *self.ready_promise.borrow_mut() = Promise::new(&self.global());
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-writer-ensure-ready-promise-rejected>
+    pub fn ensure_ready_promise_rejected(&self, error: HandleValue) {
+        if self.ready_promise.borrow().is_fulfilled() {
+            self.reset_ready_promise();
+        }
+        let cx = self.global().get_cx();
+        let ready_promise = self.ready_promise.borrow().clone();
+        ready_promise.reject(cx, error);
+        ready_promise.set_promise_is_handled();
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-writer-ensure-closed-promise-rejected>
+    fn ensure_closed_promise_rejected(&self, error: HandleValue) {
+        if self.closed_promise.borrow().is_fulfilled() {
+            *self.closed_promise.borrow_mut() = Promise::new(&self.global());
+        }
+        self.reject_closed_promise(error);
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-writer-close-with-error-propagation>
+    pub fn close_with_error_propagation(&self) -> Rc<Promise> {
+        let global = self.global();
+
+        // Step 1.
+        let stream = self.stream.get().expect("Writer should have a stream");
+
+        // Step 4.
+        if stream.close_queued_or_in_flight() || stream.is_closed() {
+            return resolved_promise(&global);
+        }
+
+        // Step 5.
+        if stream.is_errored() {
+            let cx = global.get_cx();
+            rooted!(in(*cx) let mut stored_error = UndefinedValue());
+            stream.get_stored_error(stored_error.handle_mut());
+            return Promise::new_rejected(&global, cx, stored_error.handle())
+                .expect("Couldn't reject a promise");
+        }
+
+        // Step 7.
+        stream.close()
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-writer-release>
+    pub fn release(&self) {
+        // Step 1.
+        let stream = self.stream.get().expect("Writer should have a stream");
+
+        // Steps 4-6.
+        let global = self.global();
+        let cx = global.get_cx();
+        rooted!(in(*cx) let mut release_error = UndefinedValue());
+        new_type_error(
+            &global,
+            "The writer was released",
+            release_error.handle_mut(),
+        );
+        self.ensure_ready_promise_rejected(release_error.handle());
+        self.ensure_closed_promise_rejected(release_error.handle());
+
+        // Steps 7-8.
+        stream.set_writer(None);
+        self.stream.set(None);
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-writer-write>
+    pub fn write(&self, cx: JSContext, chunk: HandleValue) -> Rc<Promise> {
+        let global = self.global();
+
+        // Steps 1-3.
+        let stream = self.stream.get().expect("Writer should have a stream");
+        let controller = stream.get_controller();
+        let chunk_size = controller.get_chunk_size(chunk);
+
+        // Step 4.
+        let is_same_stream = self.stream.get().map_or(false, |current| current == stream);
+        if !is_same_stream {
+            return rejected_with_type_error(&global, "The writer was released");
+        }
+
+        // Steps 5-6.
+        rooted!(in(*cx) let mut stored_error = UndefinedValue());
+        if stream.is_errored() {
+            stream.get_stored_error(stored_error.handle_mut());
+            return Promise::new_rejected(&global, cx, stored_error.handle())
+                .expect("Couldn't reject a promise");
+        }
+
+        // Step 7.
+        if stream.close_queued_or_in_flight() || stream.is_closed() {
+            return rejected_with_type_error(&global, "The stream is closing or closed");
+        }
+
+        // Step 8.
+        if stream.is_erroring() {
+            stream.get_stored_error(stored_error.handle_mut());
+            return Promise::new_rejected(&global, cx, stored_error.handle())
+                .expect("Couldn't reject a promise");
+        }
+
+        // Steps 10-12.
+        let promise = stream.add_write_request();
+        controller.write(chunk, chunk_size);
+        promise
+    }
+}
+
+impl WritableStreamDefaultWriterMethods for WritableStreamDefaultWriter {
+    /// <https://streams.spec.whatwg.org/#default-writer-closed>
+    fn Closed(&self) -> Rc<Promise> {
+        self.closed_promise()
+    }
+
+    /// <https://streams.spec.whatwg.org/#default-writer-desired-size>
+    fn GetDesiredSize(&self) -> Fallible<Option<f64>> {
+        // Step 1.
+        let stream = match self.stream.get() {
+            Some(stream) => stream,
+            None => return Err(Error::Type("The writer has no stream".to_owned())),
+        };
+
+        // Step 2, with
+        // <https://streams.spec.whatwg.org/#writable-stream-default-writer-get-desired-size>.
+        Ok(match stream.state() {
+            WritableStreamState::Errored | WritableStreamState::Erroring => None,
+            WritableStreamState::Closed => Some(0.),
+            WritableStreamState::Writable => Some(stream.get_controller().get_desired_size()),
+        })
+    }
+
+    /// <https://streams.spec.whatwg.org/#default-writer-ready>
+    fn Ready(&self) -> Rc<Promise> {
+        self.ready_promise()
+    }
+
+    /// <https://streams.spec.whatwg.org/#default-writer-abort>
+    fn Abort(&self, _cx: JSContext, reason: HandleValue) -> Rc<Promise> {
+        // Step 1.
+        let stream = match self.stream.get() {
+            Some(stream) => stream,
+            None => return rejected_with_type_error(&self.global(), "The writer has no stream"),
+        };
+
+        // Step 2.
+        stream.abort(reason)
+    }
+
+    /// <https://streams.spec.whatwg.org/#default-writer-close>
+    fn Close(&self) -> Rc<Promise> {
+        let global = self.global();
+
+        // Steps 1-2.
+        let stream = match self.stream.get() {
+            Some(stream) => stream,
+            None => return rejected_with_type_error(&global, "The writer has no stream"),
+        };
+
+        // Step 3.
+        if stream.close_queued_or_in_flight() {
+            return rejected_with_type_error(&global, "The stream is already closing");
+        }
+
+        // Step 4.
+        stream.close()
+    }
+
+    /// <https://streams.spec.whatwg.org/#default-writer-release-lock>
+    fn ReleaseLock(&self) {
+        // Steps 1-2.
+        if self.stream.get().is_none() {
+            return;
+        }
+
+        // Step 4.
+        self.release();
+    }
+
+    /// <https://streams.spec.whatwg.org/#default-writer-write>
+    fn Write(&self, cx: JSContext, chunk: HandleValue) -> Rc<Promise> {
+        // Step 1.
+        if self.stream.get().is_none() {
+            return rejected_with_type_error(&self.global(), "The writer has no stream");
+        }
+
+        // Step 2.
+        self.write(cx, chunk)
+    }
+}
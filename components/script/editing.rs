@@ -0,0 +1,1312 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Rich-text editing of `contenteditable` elements and documents in design mode.
+//!
+//! Editing operates on the document's selection through the DOM `Range`
+//! operations, so mutation observers, live ranges and custom element
+//! reactions behave exactly as if the page made the same changes itself.
+//!
+//! https://html.spec.whatwg.org/multipage/#editing-2
+//! https://w3c.github.io/editing/docs/execCommand/
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::CharacterDataBinding::CharacterDataMethods;
+use crate::dom::bindings::codegen::Bindings::DocumentBinding::DocumentMethods;
+use crate::dom::bindings::codegen::Bindings::NodeBinding::NodeMethods;
+use crate::dom::bindings::codegen::Bindings::RangeBinding::RangeMethods;
+use crate::dom::bindings::codegen::Bindings::SelectionBinding::SelectionMethods;
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::bindings::str::DOMString;
+use crate::dom::characterdata::CharacterData;
+use crate::dom::document::Document;
+use crate::dom::element::{CustomElementCreationMode, Element, ElementCreator};
+use crate::dom::event::{Event, EventStatus};
+use crate::dom::htmlelement::{ContentEditableState, HTMLElement};
+use crate::dom::htmlinputelement::HTMLInputElement;
+use crate::dom::htmltextareaelement::HTMLTextAreaElement;
+use crate::dom::inputevent::InputEvent;
+use crate::dom::mutationobserver::Mutation;
+use crate::dom::node::{window_from_node, Node, ShadowIncluding};
+use crate::dom::range::Range;
+use crate::dom::text::Text;
+use crate::textinput::CMD_OR_CONTROL;
+use euclid::default::Point2D;
+use html5ever::{LocalName, Namespace, QualName};
+use keyboard_types::{Key, KeyState, KeyboardEvent, Modifiers, ShortcutMatcher};
+use script_layout_interface::rpc::TextIndexResponse;
+
+/// A command that can be run with `document.execCommand()` or by the user
+/// typing into an editing host.
+#[derive(Clone, Copy, Debug, JSTraceable, MallocSizeOf, PartialEq)]
+pub enum EditingCommand {
+    Bold,
+    CreateLink,
+    Delete,
+    ForwardDelete,
+    InsertLineBreak,
+    InsertParagraph,
+    InsertText,
+    Italic,
+    Redo,
+    SelectAll,
+    Undo,
+}
+
+impl EditingCommand {
+    /// Parses a command name, which is matched ASCII case-insensitively.
+    pub fn from_name(name: &str) -> Option<EditingCommand> {
+        let command = match_ignore_ascii_case! { name,
+            "bold" => EditingCommand::Bold,
+            "createlink" => EditingCommand::CreateLink,
+            "delete" => EditingCommand::Delete,
+            "forwarddelete" => EditingCommand::ForwardDelete,
+            "insertlinebreak" => EditingCommand::InsertLineBreak,
+            "insertparagraph" => EditingCommand::InsertParagraph,
+            "inserttext" => EditingCommand::InsertText,
+            "italic" => EditingCommand::Italic,
+            "redo" => EditingCommand::Redo,
+            "selectall" => EditingCommand::SelectAll,
+            "undo" => EditingCommand::Undo,
+            _ => return None,
+        };
+        Some(command)
+    }
+
+    /// The `inputType` of the `beforeinput` and `input` events fired for this
+    /// command, if it fires any.
+    ///
+    /// https://w3c.github.io/input-events/#interface-InputEvent-Attributes
+    fn input_type(self) -> Option<&'static str> {
+        Some(match self {
+            EditingCommand::Bold => "formatBold",
+            EditingCommand::CreateLink => "insertLink",
+            EditingCommand::Delete => "deleteContentBackward",
+            EditingCommand::ForwardDelete => "deleteContentForward",
+            EditingCommand::InsertLineBreak => "insertLineBreak",
+            EditingCommand::InsertParagraph => "insertParagraph",
+            EditingCommand::InsertText => "insertText",
+            EditingCommand::Italic => "formatItalic",
+            EditingCommand::Redo => "historyRedo",
+            EditingCommand::Undo => "historyUndo",
+            EditingCommand::SelectAll => return None,
+        })
+    }
+
+    /// The element this command wraps content in, for inline style commands.
+    fn style_element(self) -> Option<LocalName> {
+        match self {
+            EditingCommand::Bold => Some(local_name!("b")),
+            EditingCommand::Italic => Some(local_name!("i")),
+            _ => None,
+        }
+    }
+
+    /// Whether `element` applies this command's inline style.
+    fn is_styled_by(self, element: &Element) -> bool {
+        if *element.namespace() != ns!(html) {
+            return false;
+        }
+        match self {
+            EditingCommand::Bold => matches!(
+                *element.local_name(),
+                local_name!("b") | local_name!("strong")
+            ),
+            EditingCommand::Italic => {
+                matches!(*element.local_name(), local_name!("i") | local_name!("em"))
+            },
+            _ => false,
+        }
+    }
+}
+
+/// The number of entries the undo stack keeps; older entries are dropped.
+const MAX_UNDO_ENTRIES: usize = 100;
+
+/// A change an editing command made to the DOM, with what is needed to
+/// revert it.
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+enum EditingMutation {
+    Attribute {
+        element: Dom<Element>,
+        name: LocalName,
+        namespace: Namespace,
+        old_value: Option<DOMString>,
+    },
+    CharacterData {
+        node: Dom<CharacterData>,
+        old_value: DOMString,
+    },
+    ChildList {
+        parent: Dom<Node>,
+        added: Vec<Dom<Node>>,
+        removed: Vec<Dom<Node>>,
+        prev: Option<Dom<Node>>,
+        next: Option<Dom<Node>>,
+    },
+}
+
+/// An entry of the undo or redo stack, holding the mutations a command made
+/// to the editing host.
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+struct EditingHistoryEntry {
+    command: EditingCommand,
+    host: Dom<Element>,
+    mutations: Vec<EditingMutation>,
+}
+
+/// The undo and redo stacks of a document.
+#[derive(Default, JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+pub struct EditingHistory {
+    undo: DomRefCell<Vec<EditingHistoryEntry>>,
+    redo: DomRefCell<Vec<EditingHistoryEntry>>,
+    /// The mutations made since recording started, if it did.
+    recording: DomRefCell<Option<Vec<EditingMutation>>>,
+}
+
+/// Returns the editing host `node` belongs to, if it is editable.
+///
+/// https://html.spec.whatwg.org/multipage/#editing-host
+pub fn editing_host(node: &Node) -> Option<DomRoot<Element>> {
+    let mut host = None;
+    for ancestor in node.inclusive_ancestors(ShadowIncluding::No) {
+        if let Some(element) = ancestor.downcast::<HTMLElement>() {
+            match element.content_editable_state() {
+                ContentEditableState::True => host = Some(DomRoot::from_ref(element.upcast())),
+                ContentEditableState::False => return host,
+                ContentEditableState::Inherit => {},
+            }
+        }
+    }
+    let document = node.owner_doc();
+    if document.design_mode() {
+        return document.GetDocumentElement();
+    }
+    host
+}
+
+/// Whether `element` is an editing host.
+pub fn is_editing_host(element: &Element) -> bool {
+    editing_host(element.upcast()).map_or(false, |host| &*host == element)
+}
+
+/// Returns the document's selected range and its editing host, if the
+/// selection lies within a single editing host.
+fn active_range(document: &Document) -> Option<(DomRoot<Range>, DomRoot<Element>)> {
+    let range = document.GetSelection()?.GetRangeAt(0).ok()?;
+    let host = editing_host(&range.StartContainer())?;
+    if !host
+        .upcast::<Node>()
+        .is_inclusive_ancestor_of(&range.EndContainer())
+    {
+        return None;
+    }
+    Some((range, host))
+}
+
+/// https://w3c.github.io/editing/docs/execCommand/#dom-document-querycommandenabled
+pub fn query_command_enabled(document: &Document, command: EditingCommand) -> bool {
+    let history = document.editing_history();
+    match command {
+        EditingCommand::Undo => !history.undo.borrow().is_empty(),
+        EditingCommand::Redo => !history.redo.borrow().is_empty(),
+        _ => active_range(document).is_some(),
+    }
+}
+
+/// https://w3c.github.io/editing/docs/execCommand/#dom-document-querycommandstate
+pub fn query_command_state(document: &Document, command: EditingCommand) -> bool {
+    let (all, _) = style_state(document, command);
+    all
+}
+
+/// https://w3c.github.io/editing/docs/execCommand/#dom-document-querycommandindeterm
+pub fn query_command_indeterm(document: &Document, command: EditingCommand) -> bool {
+    let (all, any) = style_state(document, command);
+    any && !all
+}
+
+/// Returns whether all and whether any of the selected text has the inline
+/// style applied by `command`.
+fn style_state(document: &Document, command: EditingCommand) -> (bool, bool) {
+    if command.style_element().is_none() {
+        return (false, false);
+    }
+    let (range, host) = match active_range(document) {
+        Some(active) => active,
+        None => return (false, false),
+    };
+    let is_styled = |node: &Node| styled_ancestor(&host, node, command).is_some();
+    let selected = selected_text(host.upcast(), &range);
+    let mut nodes = selected
+        .iter()
+        .filter(|&&(_, start, end)| start != end)
+        .map(|(text, _, _)| text.upcast::<Node>())
+        .peekable();
+    if nodes.peek().is_none() {
+        let styled = is_styled(&range.StartContainer());
+        return (styled, styled);
+    }
+    nodes.fold((true, false), |(all, any), node| {
+        let styled = is_styled(node);
+        (all && styled, any || styled)
+    })
+}
+
+/// https://w3c.github.io/editing/docs/execCommand/#execcommand()
+pub fn exec_command(document: &Document, command: EditingCommand, value: DOMString) -> bool {
+    if !query_command_enabled(document, command) {
+        return false;
+    }
+    let host = match command {
+        EditingCommand::Undo | EditingCommand::Redo => None,
+        _ => active_range(document).map(|(_, host)| host),
+    };
+    let host = run_command(document, host.as_deref(), command, &value);
+    if let Some(host) = host {
+        fire_input_event(&host, "input", command, Some(value), false);
+    }
+    true
+}
+
+/// Performs the default action of a `keydown` event dispatched to `target`,
+/// when it is editable.
+pub fn handle_keydown(document: &Document, target: &Node, event: &KeyboardEvent) {
+    if target.is::<HTMLInputElement>() || target.is::<HTMLTextAreaElement>() {
+        return;
+    }
+    let host = match editing_host(target) {
+        Some(host) => host,
+        None => return,
+    };
+
+    let mods = event.modifiers;
+    let action = ShortcutMatcher::new(KeyState::Down, event.key.clone(), mods)
+        .shortcut(CMD_OR_CONTROL | Modifiers::SHIFT, 'Z', || {
+            Some((EditingCommand::Redo, None))
+        })
+        .shortcut(CMD_OR_CONTROL, 'Z', || Some((EditingCommand::Undo, None)))
+        .shortcut(CMD_OR_CONTROL, 'Y', || Some((EditingCommand::Redo, None)))
+        .shortcut(CMD_OR_CONTROL, 'B', || Some((EditingCommand::Bold, None)))
+        .shortcut(CMD_OR_CONTROL, 'I', || Some((EditingCommand::Italic, None)))
+        .shortcut(CMD_OR_CONTROL, 'A', || {
+            Some((EditingCommand::SelectAll, None))
+        })
+        .shortcut(Modifiers::empty(), Key::Backspace, || {
+            Some((EditingCommand::Delete, None))
+        })
+        .shortcut(Modifiers::empty(), Key::Delete, || {
+            Some((EditingCommand::ForwardDelete, None))
+        })
+        .shortcut(Modifiers::SHIFT, Key::Enter, || {
+            Some((EditingCommand::InsertLineBreak, None))
+        })
+        .shortcut(Modifiers::empty(), Key::Enter, || {
+            Some((EditingCommand::InsertParagraph, None))
+        })
+        .shortcut(Modifiers::empty(), Key::ArrowLeft, || {
+            move_caret(document, &host, Direction::Backward);
+            None
+        })
+        .shortcut(Modifiers::empty(), Key::ArrowRight, || {
+            move_caret(document, &host, Direction::Forward);
+            None
+        })
+        .otherwise(|| match event.key {
+            Key::Character(ref c) if !mods.intersects(Modifiers::CONTROL | Modifiers::META) => {
+                Some((
+                    EditingCommand::InsertText,
+                    Some(DOMString::from(c.as_str())),
+                ))
+            },
+            _ => None,
+        })
+        .unwrap();
+
+    let (command, data) = match action {
+        Some(action) => action,
+        None => return,
+    };
+    if !query_command_enabled(document, command) {
+        return;
+    }
+    if command.input_type().is_some() &&
+        fire_input_event(&host, "beforeinput", command, data.clone(), true) ==
+            EventStatus::Canceled
+    {
+        return;
+    }
+    let value = data.clone().unwrap_or_default();
+    if let Some(host) = run_command(document, Some(&host), command, &value) {
+        fire_input_event(&host, "input", command, data, false);
+    }
+}
+
+/// Moves the caret to the point the user clicked, if it is editable.
+pub fn place_caret(document: &Document, node: &Node, point_in_node: Option<Point2D<f32>>) {
+    if editing_host(node).is_none() {
+        return;
+    }
+    let selection = match document.GetSelection() {
+        Some(selection) => selection,
+        None => return,
+    };
+    let offset = match (node.downcast::<CharacterData>(), point_in_node) {
+        (Some(data), Some(point)) if node.is::<Text>() => {
+            let window = window_from_node(node);
+            let TextIndexResponse(index) = window.text_index_query(node, point);
+            let data = data.data();
+            match index {
+                Some(index) => data.chars().take(index).map(char::len_utf16).sum::<usize>() as u32,
+                None => data.encode_utf16().count() as u32,
+            }
+        },
+        _ => node.len(),
+    };
+    let _ = selection.Collapse(Some(node), offset);
+}
+
+/// Places the caret at the start of `host` when it gains focus without the
+/// selection being inside it already.
+pub fn focus_editing_host(document: &Document, host: &Element) {
+    if !is_editing_host(host) ||
+        active_range(document).map_or(false, |(_, active_host)| &*active_host == host)
+    {
+        return;
+    }
+    if let Some(selection) = document.GetSelection() {
+        let (node, offset) = first_position(host.upcast());
+        let _ = selection.Collapse(Some(&node), offset);
+    }
+}
+
+/// Lets layout paint the caret and selection in the text nodes of the
+/// focused editing host.
+pub fn update_selection_for_layout(document: &Document) {
+    let active = active_range(document).filter(|(_, host)| {
+        document.design_mode() || document.get_focused_element().as_deref() == Some(&**host)
+    });
+    let selected = match active {
+        Some((range, host)) => selected_text(host.upcast(), &range),
+        None => vec![],
+    };
+
+    let previous = document
+        .text_selected_for_layout()
+        .borrow()
+        .iter()
+        .map(|text| DomRoot::from_ref(&**text))
+        .collect::<Vec<_>>();
+    for text in previous {
+        if !selected.iter().any(|(node, _, _)| *node == text) {
+            text.set_selection_for_layout(None);
+        }
+    }
+    for (text, start, end) in &selected {
+        let data = text.upcast::<CharacterData>().data();
+        let range = utf8_offset(&data, *start)..utf8_offset(&data, *end);
+        text.set_selection_for_layout(Some(range));
+    }
+    *document.text_selected_for_layout().borrow_mut() = selected
+        .iter()
+        .map(|(text, _, _)| Dom::from_ref(&**text))
+        .collect();
+}
+
+/// Runs `command` in `host`, returning the editing host the command
+/// modified, if any.
+fn run_command(
+    document: &Document,
+    host: Option<&Element>,
+    command: EditingCommand,
+    value: &DOMString,
+) -> Option<DomRoot<Element>> {
+    match command {
+        EditingCommand::Undo => return undo(document),
+        EditingCommand::Redo => return redo(document),
+        EditingCommand::SelectAll => {
+            if let (Some(host), Some(selection)) = (host, document.GetSelection()) {
+                let _ = selection.SelectAllChildren(host.upcast());
+            }
+            return None;
+        },
+        _ => {},
+    }
+
+    let host = host?;
+    let range = document.GetSelection()?.GetRangeAt(0).ok()?;
+    let history = document.editing_history();
+    history.start_recording();
+    if command == EditingCommand::InsertText {
+        history.continue_text_insertion(host);
+    }
+    match command {
+        EditingCommand::Bold | EditingCommand::Italic => toggle_style(host, &range, command),
+        EditingCommand::CreateLink => create_link(&range, value),
+        EditingCommand::Delete => delete(host, &range, Direction::Backward),
+        EditingCommand::ForwardDelete => delete(host, &range, Direction::Forward),
+        EditingCommand::InsertLineBreak => insert_line_break(&range),
+        EditingCommand::InsertParagraph => insert_paragraph(host, &range),
+        EditingCommand::InsertText => insert_text(&range, value),
+        EditingCommand::Redo | EditingCommand::SelectAll | EditingCommand::Undo => unreachable!(),
+    }
+    if history.stop_recording(host, command, &history.undo) {
+        history.redo.borrow_mut().clear();
+    }
+    Some(DomRoot::from_ref(host))
+}
+
+fn fire_input_event(
+    host: &Element,
+    type_: &str,
+    command: EditingCommand,
+    data: Option<DOMString>,
+    cancelable: bool,
+) -> EventStatus {
+    let window = window_from_node(host);
+    let event = InputEvent::new(
+        &window,
+        DOMString::from(type_),
+        true,
+        cancelable,
+        Some(&window),
+        0,
+        data,
+        false,
+        DOMString::from(command.input_type().unwrap_or_default()),
+    );
+    event.upcast::<Event>().fire(host.upcast())
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Direction {
+    Backward,
+    Forward,
+}
+
+/// https://w3c.github.io/editing/docs/execCommand/#the-inserttext-command
+fn insert_text(range: &Range, text: &DOMString) {
+    if text.is_empty() {
+        return;
+    }
+    if !range.Collapsed() {
+        let _ = range.DeleteContents();
+    }
+    let node = range.StartContainer();
+    let offset = range.StartOffset();
+    let document = node.owner_doc();
+
+    if let Some(data) = node
+        .downcast::<CharacterData>()
+        .filter(|_| node.is::<Text>())
+    {
+        // A typed space would collapse into its neighbours, so keep it
+        // visible with a non-breaking space until more text follows it.
+        let text = preserve_spaces(&data.data(), offset, text);
+        let _ = data.InsertData(offset, text.clone());
+        collapse(
+            &document,
+            &node,
+            offset + text.encode_utf16().count() as u32,
+        );
+        return;
+    }
+
+    let new_text = Text::new(text.clone(), &document);
+    if range.InsertNode(new_text.upcast()).is_ok() {
+        let new_text = new_text.upcast::<Node>();
+        collapse(&document, new_text, new_text.len());
+    }
+}
+
+/// Replaces spaces that would otherwise collapse when inserting `text` at
+/// `offset` of `data`, and turns the non-breaking space before the insertion
+/// point back into a regular one once it is followed by other text.
+fn preserve_spaces(data: &str, offset: u32, text: &str) -> DOMString {
+    let before = previous_char(data, offset);
+    let mut previous_is_space = before.map_or(true, char::is_whitespace);
+    let at_end = offset as usize >= data.encode_utf16().count();
+    let mut result = String::with_capacity(text.len());
+    let count = text.chars().count();
+    for (i, c) in text.chars().enumerate() {
+        let c = if c == ' ' && (previous_is_space || (at_end && i + 1 == count)) {
+            '\u{a0}'
+        } else {
+            c
+        };
+        previous_is_space = c.is_whitespace();
+        result.push(c);
+    }
+    DOMString::from(result)
+}
+
+/// https://w3c.github.io/editing/docs/execCommand/#the-delete-command
+/// https://w3c.github.io/editing/docs/execCommand/#the-forwarddelete-command
+fn delete(host: &Element, range: &Range, direction: Direction) {
+    if !range.Collapsed() {
+        let _ = range.DeleteContents();
+        return;
+    }
+    let node = range.StartContainer();
+    let offset = range.StartOffset();
+    let document = node.owner_doc();
+
+    // Delete a character of the text the caret is in.
+    if let Some(data) = node
+        .downcast::<CharacterData>()
+        .filter(|_| node.is::<Text>())
+    {
+        let target = match direction {
+            Direction::Backward if offset > 0 => Some(previous_offset(&data.data(), offset)),
+            Direction::Forward if offset < node.len() => Some(next_offset(&data.data(), offset)),
+            _ => None,
+        };
+        if let Some(target) = target {
+            let (start, end) = (target.min(offset), target.max(offset));
+            let _ = data.DeleteData(start, end - start);
+            collapse(&document, &node, start);
+            return;
+        }
+    }
+
+    let host = host.upcast::<Node>();
+    let block = containing_block(host, &node);
+    let at_boundary = match direction {
+        Direction::Backward => !has_content_before(block.as_deref().unwrap_or(host), &node, offset),
+        Direction::Forward => !has_content_after(block.as_deref().unwrap_or(host), &node, offset),
+    };
+
+    // Merge the block the caret is in with the adjacent one.
+    if let Some(block) = block.filter(|_| at_boundary) {
+        let (first, second) = match direction {
+            Direction::Backward => (block.GetPreviousSibling(), Some(block)),
+            Direction::Forward => (Some(block.clone()), block.GetNextSibling()),
+        };
+        if let (Some(first), Some(second)) = (first, second) {
+            if first.is::<Element>() && second.is::<Element>() {
+                merge_blocks(&document, &first, &second);
+            }
+        }
+        return;
+    }
+
+    // Delete the adjacent line break or character.
+    let adjacent = match direction {
+        Direction::Backward => node
+            .preceding_nodes(host)
+            .find(|node| node.GetFirstChild().is_none() && has_visible_content(node)),
+        Direction::Forward => next_leaf(host, &node, offset),
+    };
+    let adjacent = match adjacent {
+        Some(adjacent) => adjacent,
+        None => return,
+    };
+    if let Some(data) = adjacent
+        .downcast::<CharacterData>()
+        .filter(|_| adjacent.is::<Text>())
+    {
+        let (start, end) = match direction {
+            Direction::Backward => {
+                let end = adjacent.len();
+                (previous_offset(&data.data(), end), end)
+            },
+            Direction::Forward => (0, next_offset(&data.data(), 0)),
+        };
+        let _ = data.DeleteData(start, end - start);
+        if direction == Direction::Backward {
+            collapse(&document, &adjacent, start);
+        }
+    } else {
+        adjacent.remove_self();
+    }
+}
+
+/// Moves the children of `second` to the end of `first` and removes it,
+/// leaving the caret where they meet.
+fn merge_blocks(document: &Document, first: &Node, second: &Node) {
+    // Drop the line break that only kept an empty block open.
+    if let Some(last) = first.GetLastChild() {
+        if is_element(&last, &local_name!("br")) {
+            last.remove_self();
+        }
+    }
+    let offset = first.len();
+    while let Some(child) = second.GetFirstChild() {
+        let _ = first.AppendChild(&child);
+    }
+    second.remove_self();
+    collapse(document, first, offset);
+}
+
+/// https://w3c.github.io/editing/docs/execCommand/#the-insertlinebreak-command
+fn insert_line_break(range: &Range) {
+    if !range.Collapsed() {
+        let _ = range.DeleteContents();
+    }
+    let document = range.StartContainer().owner_doc();
+    let br = create_element(&document, local_name!("br"));
+    let br = br.upcast::<Node>();
+    if range.InsertNode(br).is_err() {
+        return;
+    }
+    let parent = match br.GetParentNode() {
+        Some(parent) => parent,
+        None => return,
+    };
+    // A trailing line break does not render a new line on its own.
+    if br.GetNextSibling().is_none() {
+        let extra = create_element(&document, local_name!("br"));
+        let _ = parent.AppendChild(extra.upcast());
+    }
+    collapse(&document, &parent, br.index() + 1);
+}
+
+/// https://w3c.github.io/editing/docs/execCommand/#the-insertparagraph-command
+fn insert_paragraph(host: &Element, range: &Range) {
+    if !range.Collapsed() {
+        let _ = range.DeleteContents();
+    }
+    let host = host.upcast::<Node>();
+    let node = range.StartContainer();
+    let offset = range.StartOffset();
+    let document = node.owner_doc();
+
+    let block = match containing_block(host, &node) {
+        Some(block) => block,
+        None if &*node == host => return insert_line_break(range),
+        None => wrap_inline_run(host, &node),
+    };
+    let tail = split_node(&block, &node, offset);
+    ensure_not_empty(&block);
+    ensure_not_empty(&tail);
+    let (node, offset) = first_position(&tail);
+    collapse(&document, &node, offset);
+}
+
+/// Toggles the inline style of `command` on the selected content.
+///
+/// https://w3c.github.io/editing/docs/execCommand/#the-bold-command
+/// https://w3c.github.io/editing/docs/execCommand/#the-italic-command
+fn toggle_style(host: &Element, range: &Range, command: EditingCommand) {
+    // FIXME: Toggling the style at a collapsed selection should affect the
+    // text typed next.
+    if range.Collapsed() {
+        return;
+    }
+    let document = host.owner_doc();
+    let styled = query_command_state(&document, command);
+    let contents = match range.ExtractContents() {
+        Ok(contents) => contents,
+        Err(_) => return,
+    };
+    unwrap_elements(contents.upcast(), |element| command.is_styled_by(element));
+
+    if !styled {
+        let name = command.style_element().unwrap();
+        let wrapper = create_element(&document, name);
+        let wrapper = wrapper.upcast::<Node>();
+        let _ = wrapper.AppendChild(contents.upcast());
+        if range.InsertNode(wrapper).is_ok() {
+            let _ = range.SelectNodeContents(wrapper);
+        }
+        return;
+    }
+
+    // Split the styled ancestors around the insertion point, so the
+    // extracted content can go back in between them.
+    while let Some(ancestor) = styled_ancestor(host, &range.StartContainer(), command) {
+        let ancestor = ancestor.upcast::<Node>();
+        let tail = split_node(ancestor, &range.StartContainer(), range.StartOffset());
+        let parent = match ancestor.GetParentNode() {
+            Some(parent) => parent,
+            None => return,
+        };
+        if !has_visible_content(&tail) {
+            tail.remove_self();
+        }
+        let _ = range.SetStart(&parent, ancestor.index() + 1);
+        range.Collapse(true);
+        if !has_visible_content(ancestor) {
+            ancestor.remove_self();
+        }
+    }
+
+    let contents = contents.upcast::<Node>();
+    let (first, last) = match (contents.GetFirstChild(), contents.GetLastChild()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return,
+    };
+    if range.InsertNode(contents).is_ok() {
+        let _ = range.SetStartBefore(&first);
+        let _ = range.SetEndAfter(&last);
+    }
+}
+
+/// https://w3c.github.io/editing/docs/execCommand/#the-createlink-command
+fn create_link(range: &Range, url: &DOMString) {
+    if url.is_empty() {
+        return;
+    }
+    let document = range.StartContainer().owner_doc();
+    let anchor = create_element(&document, local_name!("a"));
+    anchor.set_string_attribute(&local_name!("href"), url.clone());
+    let anchor_node = anchor.upcast::<Node>();
+    if range.Collapsed() {
+        let text = Text::new(url.clone(), &document);
+        let _ = anchor_node.AppendChild(text.upcast());
+    } else {
+        let contents = match range.ExtractContents() {
+            Ok(contents) => contents,
+            Err(_) => return,
+        };
+        unwrap_elements(contents.upcast(), |element| {
+            *element.namespace() == ns!(html) && *element.local_name() == local_name!("a")
+        });
+        let _ = anchor_node.AppendChild(contents.upcast());
+    }
+    if range.InsertNode(anchor_node).is_ok() {
+        let _ = range.SelectNodeContents(anchor_node);
+    }
+}
+
+/// Moves a collapsed caret by one character, or collapses the selection
+/// towards `direction`.
+fn move_caret(document: &Document, host: &Element, direction: Direction) {
+    let range = match active_range(document) {
+        Some((range, _)) => range,
+        None => return,
+    };
+    if !range.Collapsed() {
+        range.Collapse(direction == Direction::Backward);
+        return;
+    }
+    let node = range.StartContainer();
+    let offset = range.StartOffset();
+    if let Some(data) = node
+        .downcast::<CharacterData>()
+        .filter(|_| node.is::<Text>())
+    {
+        match direction {
+            Direction::Backward if offset > 0 => {
+                return collapse(document, &node, previous_offset(&data.data(), offset));
+            },
+            Direction::Forward if offset < node.len() => {
+                return collapse(document, &node, next_offset(&data.data(), offset));
+            },
+            _ => {},
+        }
+    }
+
+    let host = host.upcast::<Node>();
+    let adjacent = match direction {
+        Direction::Backward => node
+            .preceding_nodes(host)
+            .find(|node| node.is::<Text>() && !is_empty_text(node)),
+        Direction::Forward => node
+            .following_nodes(host)
+            .find(|node| node.is::<Text>() && !is_empty_text(node)),
+    };
+    if let Some(adjacent) = adjacent {
+        let data = adjacent.downcast::<CharacterData>().unwrap();
+        let offset = match direction {
+            Direction::Backward => previous_offset(&data.data(), adjacent.len()),
+            Direction::Forward => next_offset(&data.data(), 0),
+        };
+        collapse(document, &adjacent, offset);
+    }
+}
+
+fn undo(document: &Document) -> Option<DomRoot<Element>> {
+    let history = document.editing_history();
+    replay(document, &history.undo, &history.redo)
+}
+
+fn redo(document: &Document) -> Option<DomRoot<Element>> {
+    let history = document.editing_history();
+    replay(document, &history.redo, &history.undo)
+}
+
+/// Reverts the last entry of `from`, moving it to `to` as the mutations that
+/// revert it in turn, and leaves the caret at the end of its editing host.
+fn replay(
+    document: &Document,
+    from: &DomRefCell<Vec<EditingHistoryEntry>>,
+    to: &DomRefCell<Vec<EditingHistoryEntry>>,
+) -> Option<DomRoot<Element>> {
+    let (command, host) = {
+        let from = from.borrow();
+        let entry = from.last()?;
+        (entry.command, DomRoot::from_ref(&*entry.host))
+    };
+    let history = document.editing_history();
+    history.start_recording();
+    for mutation in from.borrow().last().unwrap().mutations.iter().rev() {
+        mutation.revert();
+    }
+    from.borrow_mut().pop();
+    history.stop_recording(&host, command, to);
+    let node = host.upcast::<Node>();
+    collapse(document, node, node.len());
+    Some(host)
+}
+
+impl EditingHistory {
+    fn start_recording(&self) {
+        *self.recording.borrow_mut() = Some(vec![]);
+    }
+
+    /// Moves the mutations of the last entry of the undo stack back into the
+    /// recording if it inserted text in `host`, so that consecutive text
+    /// insertions are undone together.
+    fn continue_text_insertion(&self, host: &Element) {
+        let mut undo = self.undo.borrow_mut();
+        let continues = undo.last().map_or(false, |last| {
+            last.command == EditingCommand::InsertText && &*last.host == host
+        });
+        if !continues {
+            return;
+        }
+        if let Some(ref mut recording) = *self.recording.borrow_mut() {
+            recording.append(&mut undo.last_mut().unwrap().mutations);
+        }
+        undo.pop();
+    }
+
+    /// Stops recording, pushing what `command` changed in `host` onto
+    /// `stack`. Returns whether anything changed.
+    fn stop_recording(
+        &self,
+        host: &Element,
+        command: EditingCommand,
+        stack: &DomRefCell<Vec<EditingHistoryEntry>>,
+    ) -> bool {
+        if self.recording.borrow().as_ref().map_or(true, Vec::is_empty) {
+            *self.recording.borrow_mut() = None;
+            return false;
+        }
+        let mut stack = stack.borrow_mut();
+        stack.push(EditingHistoryEntry {
+            command,
+            host: Dom::from_ref(host),
+            mutations: self.recording.borrow_mut().take().unwrap_or_default(),
+        });
+        if stack.len() > MAX_UNDO_ENTRIES {
+            stack.remove(0);
+        }
+        true
+    }
+}
+
+/// Records `mutation` of `target` if its document is recording the changes of
+/// an editing command.
+///
+/// Only the first change of an attribute or of character data is kept, as
+/// reverting it restores the value from before any later change.
+pub fn record_mutation(target: &Node, mutation: &Mutation) {
+    let document = target.owner_doc();
+    let mut recording = document.editing_history().recording.borrow_mut();
+    let mutations = match *recording {
+        Some(ref mut mutations) => mutations,
+        None => return,
+    };
+    match *mutation {
+        Mutation::Attribute {
+            ref name,
+            ref namespace,
+            ref old_value,
+        } => {
+            let recorded = mutations.iter().any(|recorded| match *recorded {
+                EditingMutation::Attribute {
+                    ref element,
+                    name: ref recorded_name,
+                    namespace: ref recorded_namespace,
+                    ..
+                } => {
+                    element.upcast::<Node>() == target &&
+                        recorded_name == name &&
+                        recorded_namespace == namespace
+                },
+                _ => false,
+            });
+            if !recorded {
+                mutations.push(EditingMutation::Attribute {
+                    element: Dom::from_ref(target.downcast::<Element>().unwrap()),
+                    name: name.clone(),
+                    namespace: namespace.clone(),
+                    old_value: old_value.clone(),
+                });
+            }
+        },
+        Mutation::CharacterData { ref old_value } => {
+            let recorded = mutations.iter().any(|recorded| match *recorded {
+                EditingMutation::CharacterData { ref node, .. } => node.upcast::<Node>() == target,
+                _ => false,
+            });
+            if !recorded {
+                mutations.push(EditingMutation::CharacterData {
+                    node: Dom::from_ref(target.downcast::<CharacterData>().unwrap()),
+                    old_value: old_value.clone(),
+                });
+            }
+        },
+        Mutation::ChildList {
+            added,
+            removed,
+            prev,
+            next,
+        } => mutations.push(EditingMutation::ChildList {
+            parent: Dom::from_ref(target),
+            added: added
+                .unwrap_or(&[])
+                .iter()
+                .map(|node| Dom::from_ref(*node))
+                .collect(),
+            removed: removed
+                .unwrap_or(&[])
+                .iter()
+                .map(|node| Dom::from_ref(*node))
+                .collect(),
+            prev: prev.map(Dom::from_ref),
+            next: next.map(Dom::from_ref),
+        }),
+    }
+}
+
+impl EditingMutation {
+    /// Undoes this mutation, assuming the mutations made after it have been
+    /// undone already.
+    fn revert(&self) {
+        match *self {
+            EditingMutation::Attribute {
+                ref element,
+                ref name,
+                ref namespace,
+                ref old_value,
+            } => match (old_value, element.get_attribute(namespace, name)) {
+                (Some(value), Some(attr)) => {
+                    let value = element.parse_attribute(namespace, name, value.clone());
+                    attr.set_value(value, element);
+                },
+                (Some(value), None) => element.set_attribute_from_parser(
+                    QualName::new(None, namespace.clone(), name.clone()),
+                    value.clone(),
+                    None,
+                ),
+                (None, _) => {
+                    element.remove_attribute(namespace, name);
+                },
+            },
+            EditingMutation::CharacterData {
+                ref node,
+                ref old_value,
+            } => node.SetData(old_value.clone()),
+            EditingMutation::ChildList {
+                ref parent,
+                ref added,
+                ref removed,
+                ref prev,
+                ref next,
+            } => {
+                let is_child = |node: &Node| node.GetParentNode().as_deref() == Some(&**parent);
+                for node in added {
+                    if is_child(node) {
+                        let _ = parent.RemoveChild(node);
+                    }
+                }
+                // Put the removed nodes back between their old siblings, or as
+                // close to them as the siblings that are still there allow.
+                let child = match (prev, next) {
+                    (_, Some(next)) if is_child(next) => Some(DomRoot::from_ref(&**next)),
+                    (Some(prev), _) if is_child(prev) => prev.GetNextSibling(),
+                    (None, Some(_)) => parent.GetFirstChild(),
+                    _ => None,
+                };
+                for node in removed {
+                    let _ = parent.InsertBefore(node, child.as_deref());
+                }
+            },
+        }
+    }
+}
+
+fn collapse(document: &Document, node: &Node, offset: u32) {
+    if let Some(selection) = document.GetSelection() {
+        let _ = selection.Collapse(Some(node), offset);
+    }
+}
+
+fn create_element(document: &Document, name: LocalName) -> DomRoot<Element> {
+    Element::create(
+        QualName::new(None, ns!(html), name),
+        None,
+        document,
+        ElementCreator::ScriptCreated,
+        CustomElementCreationMode::Synchronous,
+    )
+}
+
+fn is_element(node: &Node, name: &LocalName) -> bool {
+    node.downcast::<Element>().map_or(false, |element| {
+        *element.namespace() == ns!(html) && element.local_name() == name
+    })
+}
+
+fn is_empty_text(node: &Node) -> bool {
+    node.is::<Text>() && node.len() == 0
+}
+
+/// Whether `node` is rendered as a block.
+///
+/// FIXME: This should use the computed `display` of the element.
+fn is_block(node: &Node) -> bool {
+    let element = match node.downcast::<Element>() {
+        Some(element) if *element.namespace() == ns!(html) => element,
+        _ => return false,
+    };
+    matches!(
+        *element.local_name(),
+        local_name!("address") |
+            local_name!("article") |
+            local_name!("aside") |
+            local_name!("blockquote") |
+            local_name!("div") |
+            local_name!("dl") |
+            local_name!("footer") |
+            local_name!("h1") |
+            local_name!("h2") |
+            local_name!("h3") |
+            local_name!("h4") |
+            local_name!("h5") |
+            local_name!("h6") |
+            local_name!("header") |
+            local_name!("li") |
+            local_name!("main") |
+            local_name!("nav") |
+            local_name!("ol") |
+            local_name!("p") |
+            local_name!("pre") |
+            local_name!("section") |
+            local_name!("ul")
+    )
+}
+
+/// Returns the nearest block inclusive ancestor of `node` inside `host`.
+fn containing_block(host: &Node, node: &Node) -> Option<DomRoot<Node>> {
+    node.inclusive_ancestors(ShadowIncluding::No)
+        .take_while(|ancestor| &**ancestor != host)
+        .find(|ancestor| is_block(ancestor))
+}
+
+/// Wraps the run of inline siblings around the child of `host` containing
+/// `node` in a new `div`, and returns it.
+fn wrap_inline_run(host: &Node, node: &Node) -> DomRoot<Node> {
+    let child = node
+        .inclusive_ancestors(ShadowIncluding::No)
+        .find(|ancestor| ancestor.GetParentNode().as_deref() == Some(host))
+        .expect("node should be inside its editing host");
+    let mut first = child.clone();
+    while let Some(previous) = first.GetPreviousSibling().filter(|node| !is_block(node)) {
+        first = previous;
+    }
+    let document = host.owner_doc();
+    let div = create_element(&document, local_name!("div"));
+    let div = div.upcast::<Node>();
+    let _ = host.InsertBefore(div, Some(&first));
+    while let Some(next) = div.GetNextSibling().filter(|node| !is_block(node)) {
+        let _ = div.AppendChild(&next);
+    }
+    DomRoot::from_ref(div)
+}
+
+/// Splits `ancestor` at the given point, moving everything after it to a
+/// shallow copy of `ancestor` inserted right after it, and returns the copy.
+fn split_node(ancestor: &Node, node: &Node, offset: u32) -> DomRoot<Node> {
+    let document = ancestor.owner_doc();
+    let tail = Node::clone(ancestor, None, CloneChildrenFlag::DoNotCloneChildren);
+    let range = Range::new(&document, node, offset, ancestor, ancestor.len());
+    if let Ok(contents) = range.ExtractContents() {
+        let _ = tail.AppendChild(contents.upcast());
+    }
+    if let Some(parent) = ancestor.GetParentNode() {
+        let _ = parent.InsertBefore(&tail, ancestor.GetNextSibling().as_deref());
+    }
+    tail
+}
+
+/// Replaces every element of `root` matching `filter` with its children.
+fn unwrap_elements<F: Fn(&Element) -> bool>(root: &Node, filter: F) {
+    let matching = root
+        .traverse_preorder(ShadowIncluding::No)
+        .filter(|node| node.downcast::<Element>().map_or(false, |e| filter(e)))
+        .collect::<Vec<_>>();
+    for element in matching {
+        let parent = match element.GetParentNode() {
+            Some(parent) => parent,
+            None => continue,
+        };
+        while let Some(child) = element.GetFirstChild() {
+            let _ = parent.InsertBefore(&child, Some(&element));
+        }
+        element.remove_self();
+    }
+}
+
+/// Returns the nearest inclusive ancestor of `node` inside `host` that
+/// applies the inline style of `command`.
+fn styled_ancestor(
+    host: &Element,
+    node: &Node,
+    command: EditingCommand,
+) -> Option<DomRoot<Element>> {
+    node.inclusive_ancestors(ShadowIncluding::No)
+        .take_while(|ancestor| &**ancestor != host.upcast::<Node>())
+        .filter_map(DomRoot::downcast::<Element>)
+        .find(|element| command.is_styled_by(element))
+}
+
+fn has_visible_content(node: &Node) -> bool {
+    node.traverse_preorder(ShadowIncluding::No).any(|node| {
+        (node.is::<Text>() && node.len() > 0) ||
+            is_element(&node, &local_name!("br")) ||
+            is_element(&node, &local_name!("img"))
+    })
+}
+
+/// Gives an empty block a line break, so that it keeps a line box the caret
+/// can be placed in.
+fn ensure_not_empty(block: &Node) {
+    if !has_visible_content(block) {
+        let br = create_element(&block.owner_doc(), local_name!("br"));
+        let _ = block.AppendChild(br.upcast());
+    }
+}
+
+/// Whether anything visible precedes the given point inside `root`.
+fn has_content_before(root: &Node, node: &Node, offset: u32) -> bool {
+    if node.is::<Text>() && offset > 0 {
+        return true;
+    }
+    let start = if node.is::<Text>() || offset == 0 {
+        DomRoot::from_ref(node)
+    } else {
+        match node.children().nth(offset as usize - 1) {
+            Some(child) if has_visible_content(&child) => return true,
+            Some(child) => child,
+            None => DomRoot::from_ref(node),
+        }
+    };
+    start
+        .preceding_nodes(root)
+        .any(|node| node.GetFirstChild().is_none() && has_visible_content(&node))
+}
+
+/// Whether anything visible follows the given point inside `root`.
+fn has_content_after(root: &Node, node: &Node, offset: u32) -> bool {
+    next_leaf(root, node, offset).is_some()
+}
+
+/// Returns the first visible leaf node after the given point inside `root`.
+fn next_leaf(root: &Node, node: &Node, offset: u32) -> Option<DomRoot<Node>> {
+    let is_visible_leaf = |node: &Node| node.GetFirstChild().is_none() && has_visible_content(node);
+    if !node.is::<Text>() {
+        if let Some(child) = node.children().nth(offset as usize) {
+            if is_visible_leaf(&child) {
+                return Some(child);
+            }
+            return child
+                .following_nodes(root)
+                .find(|node| is_visible_leaf(node));
+        }
+    }
+    node.following_nodes(root)
+        .filter(|following| !following.is_inclusive_ancestor_of(node))
+        .find(|node| is_visible_leaf(node))
+}
+
+/// Returns the first point a caret can be placed at inside `node`.
+fn first_position(node: &Node) -> (DomRoot<Node>, u32) {
+    let mut current = DomRoot::from_ref(node);
+    while let Some(child) = current.GetFirstChild() {
+        if child.is::<Text>() {
+            return (child, 0);
+        }
+        if child.GetFirstChild().is_none() {
+            break;
+        }
+        current = child;
+    }
+    (current, 0)
+}
+
+/// Returns the text nodes of `root` the range intersects, along with the
+/// selected UTF-16 offsets within them.
+fn selected_text(root: &Node, range: &Range) -> Vec<(DomRoot<Text>, u32, u32)> {
+    let start_container = range.StartContainer();
+    let end_container = range.EndContainer();
+    root.traverse_preorder(ShadowIncluding::No)
+        .filter_map(DomRoot::downcast::<Text>)
+        .filter_map(|text| {
+            let node = text.upcast::<Node>();
+            let start = if *node == *start_container {
+                range.StartOffset()
+            } else if matches!(range.ComparePoint(node, 0), Ok(0)) {
+                0
+            } else {
+                return None;
+            };
+            let end = if *node == *end_container {
+                range.EndOffset()
+            } else {
+                node.len()
+            };
+            Some((text, start, end))
+        })
+        .collect()
+}
+
+/// Returns the UTF-16 offset of the code point before `offset` in `data`.
+fn previous_offset(data: &str, offset: u32) -> u32 {
+    offset - previous_char(data, offset).map_or(0, |c| c.len_utf16() as u32)
+}
+
+/// Returns the UTF-16 offset of the code point after `offset` in `data`.
+fn next_offset(data: &str, offset: u32) -> u32 {
+    let mut position = 0;
+    for c in data.chars() {
+        position += c.len_utf16() as u32;
+        if position > offset {
+            return position;
+        }
+    }
+    offset
+}
+
+fn previous_char(data: &str, offset: u32) -> Option<char> {
+    let mut position = 0;
+    let mut previous = None;
+    for c in data.chars() {
+        if position >= offset {
+            break;
+        }
+        position += c.len_utf16() as u32;
+        previous = Some(c);
+    }
+    previous
+}
+
+/// Converts a UTF-16 offset in `data` to a byte offset.
+fn utf8_offset(data: &str, offset: u32) -> usize {
+    let mut position = 0;
+    for (index, c) in data.char_indices() {
+        if position >= offset {
+            return index;
+        }
+        position += c.len_utf16() as u32;
+    }
+    data.len()
+}
@@ -2,15 +2,18 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use crate::body::BodyMixin;
+use crate::dom::abortsignal::{AbortAlgorithm, AbortSignal};
 use crate::dom::bindings::codegen::Bindings::RequestBinding::RequestInfo;
 use crate::dom::bindings::codegen::Bindings::RequestBinding::RequestInit;
+use crate::dom::bindings::codegen::Bindings::RequestBinding::RequestMethods;
 use crate::dom::bindings::codegen::Bindings::ResponseBinding::ResponseBinding::ResponseMethods;
 use crate::dom::bindings::codegen::Bindings::ResponseBinding::ResponseType as DOMResponseType;
 use crate::dom::bindings::error::Error;
 use crate::dom::bindings::inheritance::Castable;
 use crate::dom::bindings::refcounted::{Trusted, TrustedPromise};
 use crate::dom::bindings::reflector::DomObject;
-use crate::dom::bindings::root::DomRoot;
+use crate::dom::bindings::root::{Dom, DomRoot};
 use crate::dom::bindings::trace::RootedTraceableBox;
 use crate::dom::globalscope::GlobalScope;
 use crate::dom::headers::Guard;
@@ -26,6 +29,7 @@ use crate::realms::{enter_realm, InRealm};
 use crate::task_source::TaskSourceName;
 use ipc_channel::ipc;
 use ipc_channel::router::ROUTER;
+use js::rust::HandleValue;
 use net_traits::request::{
     CorsSettings, CredentialsMode, Destination, RequestBuilder, RequestMode,
 };
@@ -42,6 +46,9 @@ use std::sync::{Arc, Mutex};
 struct FetchContext {
     fetch_promise: Option<TrustedPromise>,
     response_object: Trusted<Response>,
+    /// The signal of the request, which has to outlive the fetch, as dropping its
+    /// abort algorithms cancels the fetch.
+    request_signal: Trusted<AbortSignal>,
     resource_timing: ResourceFetchTiming,
 }
 
@@ -127,6 +134,7 @@ fn request_init_from_request(request: NetTraitsRequest) -> RequestBuilder {
         initiator: request.initiator,
         csp_list: None,
         https_state: request.https_state,
+        user_initiated: false,
     }
 }
 
@@ -145,16 +153,26 @@ pub fn Fetch(
     let response = Response::new(global);
 
     // Step 2
-    let request = match Request::Constructor(global, input, init) {
+    let request_object = match Request::Constructor(global, input, init) {
         Err(e) => {
             response.error_stream(e.clone());
             promise.reject_error(e);
             return promise;
         },
-        Ok(r) => r.get_request(),
+        Ok(r) => r,
     };
+    let request = request_object.get_request();
     let timing_type = request.timing_type();
 
+    // A fetch with an aborted signal is rejected right away.
+    let signal = request_object.Signal();
+    if signal.aborted() {
+        let cx = global.get_cx();
+        rooted!(in(*cx) let reason = signal.abort_reason());
+        abort_fetch_call(&promise, None, reason.handle());
+        return promise;
+    }
+
     let mut request_init = request_init_from_request(request);
     request_init.csp_list = global.get_csp_list().clone();
 
@@ -171,6 +189,7 @@ pub fn Fetch(
     let fetch_context = Arc::new(Mutex::new(FetchContext {
         fetch_promise: Some(TrustedPromise::new(promise.clone())),
         response_object: Trusted::new(&*response),
+        request_signal: Trusted::new(&*signal),
         resource_timing: ResourceFetchTiming::new(timing_type),
     }));
     let listener = NetworkListener {
@@ -185,17 +204,46 @@ pub fn Fetch(
             listener.notify_fetch(message.to().unwrap());
         }),
     );
+    let mut canceller = FetchCanceller::new();
+    let cancel_receiver = canceller.initialize();
     core_resource_thread
         .send(NetTraitsFetch(
             request_init,
-            FetchChannels::ResponseMsg(action_sender, None),
+            FetchChannels::ResponseMsg(action_sender, Some(cancel_receiver)),
         ))
         .unwrap();
 
+    // Aborting the signal cancels the fetch and rejects the promise.
+    signal.add(AbortAlgorithm::Fetch {
+        promise: promise.clone(),
+        response: Dom::from_ref(&*response),
+        canceller,
+    });
+
     promise
 }
 
-impl PreInvoke for FetchContext {}
+/// <https://fetch.spec.whatwg.org/#abort-fetch>
+pub fn abort_fetch_call(promise: &Promise, response: Option<&Response>, error: HandleValue) {
+    // Step 1.
+    let _ac = enter_realm(promise);
+    promise.reject(promise.global().get_cx(), error);
+
+    // Step 2 TODO: "If request's body is non-null and is readable, then cancel
+    // request's body with error."
+
+    // Steps 3-5.
+    if let Some(body) = response.and_then(|response| response.body()) {
+        body.error(error);
+    }
+}
+
+impl PreInvoke for FetchContext {
+    fn should_invoke(&self) -> bool {
+        // Once aborted, the promise and the response were settled by the abort algorithm.
+        !self.request_signal.root().aborted()
+    }
+}
 
 impl FetchResponseListener for FetchContext {
     fn process_request_body(&mut self) {
@@ -65,6 +65,8 @@ pub mod document_loader;
 mod dom;
 #[warn(deprecated)]
 mod canvas_state;
+#[warn(deprecated)]
+mod editing;
 mod euclidext;
 #[warn(deprecated)]
 pub mod fetch;
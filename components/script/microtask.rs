@@ -15,6 +15,9 @@ use crate::dom::globalscope::GlobalScope;
 use crate::dom::htmlimageelement::ImageElementMicrotask;
 use crate::dom::htmlmediaelement::MediaElementMicrotask;
 use crate::dom::mutationobserver::MutationObserver;
+use crate::dom::readablestream::{
+    ReadableByteStreamTeeReadRequestMicrotask, ReadableStreamTeeReadRequestMicrotask,
+};
 use crate::script_runtime::{notify_about_rejected_promises, JSContext};
 use crate::script_thread::ScriptThread;
 use js::jsapi::{JobQueueIsEmpty, JobQueueMayNotBeEmpty};
@@ -38,6 +41,8 @@ pub enum Microtask {
     User(UserMicrotask),
     MediaElement(MediaElementMicrotask),
     ImageElement(ImageElementMicrotask),
+    ReadableStreamTeeReadRequest(ReadableStreamTeeReadRequestMicrotask),
+    ReadableByteStreamTeeReadRequest(ReadableByteStreamTeeReadRequestMicrotask),
     CustomElementReaction,
     NotifyMutationObservers,
 }
@@ -123,6 +128,12 @@ impl MicrotaskQueue {
                     Microtask::ImageElement(ref task) => {
                         task.handler();
                     },
+                    Microtask::ReadableStreamTeeReadRequest(ref task) => {
+                        task.handler();
+                    },
+                    Microtask::ReadableByteStreamTeeReadRequest(ref task) => {
+                        task.handler();
+                    },
                     Microtask::CustomElementReaction => {
                         ScriptThread::invoke_backup_element_queue();
                     },
@@ -10,7 +10,7 @@ use crate::dom::bindings::cell::DomRefCell;
 use crate::dom::bindings::codegen::Bindings::WindowBinding::WindowBinding::WindowMethods;
 use crate::dom::bindings::conversions::jsstring_to_str;
 use crate::dom::bindings::error::report_pending_exception;
-use crate::dom::bindings::error::Error;
+use crate::dom::bindings::error::{throw_dom_exception, Error, Fallible};
 use crate::dom::bindings::inheritance::Castable;
 use crate::dom::bindings::refcounted::Trusted;
 use crate::dom::bindings::reflector::DomObject;
@@ -18,6 +18,7 @@ use crate::dom::bindings::root::DomRoot;
 use crate::dom::bindings::settings_stack::AutoIncumbentScript;
 use crate::dom::bindings::str::DOMString;
 use crate::dom::bindings::trace::RootedTraceableBox;
+use crate::dom::dedicatedworkerglobalscope::DedicatedWorkerGlobalScope;
 use crate::dom::document::Document;
 use crate::dom::dynamicmoduleowner::{DynamicModuleId, DynamicModuleOwner};
 use crate::dom::element::Element;
@@ -28,8 +29,9 @@ use crate::dom::node::document_from_node;
 use crate::dom::performanceresourcetiming::InitiatorType;
 use crate::dom::promise::Promise;
 use crate::dom::promisenativehandler::{Callback, PromiseNativeHandler};
+use crate::dom::sharedworkerglobalscope::SharedWorkerGlobalScope;
 use crate::dom::window::Window;
-use crate::dom::worker::TrustedWorkerAddress;
+use crate::dom::workerglobalscope::WorkerGlobalScope;
 use crate::network_listener::{self, NetworkListener};
 use crate::network_listener::{PreInvoke, ResourceTimingListener};
 use crate::realms::{enter_realm, AlreadyInRealm, InRealm};
@@ -38,7 +40,7 @@ use crate::task::TaskBox;
 use crate::task_source::TaskSourceName;
 use encoding_rs::UTF_8;
 use hyper_serde::Serde;
-use indexmap::IndexSet;
+use indexmap::{IndexMap, IndexSet};
 use ipc_channel::ipc;
 use ipc_channel::router::ROUTER;
 use js::jsapi::Handle as RawHandle;
@@ -68,19 +70,24 @@ use net_traits::{CoreResourceMsg, FetchChannels};
 use net_traits::{FetchMetadata, Metadata, ReferrerPolicy};
 use net_traits::{FetchResponseListener, NetworkError};
 use net_traits::{ResourceFetchTiming, ResourceTimingType};
+use serde_json::{Map as JsonMap, Value as JsonValue};
 use servo_url::ServoUrl;
 use std::collections::{HashMap, HashSet};
 use std::mem;
 use std::rc::Rc;
 use std::str::FromStr;
 use std::sync::{Arc, Mutex};
-use url::ParseError as UrlParseError;
 use uuid::Uuid;
 
 #[allow(unsafe_code)]
 unsafe fn gen_type_error(global: &GlobalScope, string: String) -> RethrowError {
+    gen_error(global, Error::Type(string))
+}
+
+#[allow(unsafe_code)]
+unsafe fn gen_error(global: &GlobalScope, error: Error) -> RethrowError {
     rooted!(in(*global.get_cx()) let mut thrown = UndefinedValue());
-    Error::Type(string).to_jsval(*global.get_cx(), &global, thrown.handle_mut());
+    error.to_jsval(*global.get_cx(), &global, thrown.handle_mut());
 
     return RethrowError(RootedTraceableBox::from_box(Heap::boxed(thrown.get())));
 }
@@ -585,55 +592,35 @@ impl ModuleTree {
                 ));
 
                 let url = ModuleTree::resolve_module_specifier(
-                    *global.get_cx(),
+                    &global,
                     &base_url,
                     specifier.handle().into_handle(),
                 );
 
-                if url.is_err() {
-                    let specifier_error =
-                        gen_type_error(&global, "Wrong module specifier".to_owned());
-
-                    return Err(specifier_error);
-                }
-
-                specifier_urls.insert(url.unwrap());
+                match url {
+                    Ok(url) => specifier_urls.insert(url),
+                    Err(error) => return Err(gen_error(&global, error)),
+                };
             }
         }
 
         Ok(specifier_urls)
     }
 
-    /// The following module specifiers are allowed by the spec:
-    ///  - a valid absolute URL
-    ///  - a valid relative URL that starts with "/", "./" or "../"
-    ///
-    /// Bareword module specifiers are currently disallowed as these may be given
-    /// special meanings in the future.
-    /// https://html.spec.whatwg.org/multipage/#resolve-a-module-specifier
+    /// <https://html.spec.whatwg.org/multipage/#resolve-a-module-specifier>
     #[allow(unsafe_code)]
     fn resolve_module_specifier(
-        cx: *mut JSContext,
-        url: &ServoUrl,
+        global: &GlobalScope,
+        base_url: &ServoUrl,
         specifier: RawHandle<*mut JSString>,
-    ) -> Result<ServoUrl, UrlParseError> {
-        let specifier_str = unsafe { jsstring_to_str(cx, *specifier) };
+    ) -> Fallible<ServoUrl> {
+        let specifier_str = unsafe { jsstring_to_str(*global.get_cx(), *specifier) };
 
-        // Step 1.
-        if let Ok(specifier_url) = ServoUrl::parse(&specifier_str) {
-            return Ok(specifier_url);
-        }
-
-        // Step 2.
-        if !specifier_str.starts_with("/") &&
-            !specifier_str.starts_with("./") &&
-            !specifier_str.starts_with("../")
-        {
-            return Err(UrlParseError::InvalidDomainCharacter);
-        }
+        // Step 4-5. Only documents can register import maps, so the import map
+        // of any other global is always empty.
+        let import_map = global.get_import_map().borrow();
 
-        // Step 3.
-        return ServoUrl::parse_with_base(Some(url), &specifier_str.clone());
+        resolve_a_module_specifier(&import_map, &specifier_str, base_url)
     }
 
     /// https://html.spec.whatwg.org/multipage/#finding-the-first-parse-error
@@ -883,8 +870,7 @@ impl Callback for ModuleHandler {
 /// It can be `worker` or `script` element
 #[derive(Clone)]
 pub(crate) enum ModuleOwner {
-    #[allow(dead_code)]
-    Worker(TrustedWorkerAddress),
+    Worker(Trusted<WorkerGlobalScope>),
     Window(Trusted<HTMLScriptElement>),
     DynamicModule(Trusted<DynamicModuleOwner>),
 }
@@ -892,7 +878,7 @@ pub(crate) enum ModuleOwner {
 impl ModuleOwner {
     pub fn global(&self) -> DomRoot<GlobalScope> {
         match &self {
-            ModuleOwner::Worker(worker) => (*worker.root().clone()).global(),
+            ModuleOwner::Worker(scope) => (*scope.root()).global(),
             ModuleOwner::Window(script) => (*script.root()).global(),
             ModuleOwner::DynamicModule(dynamic_module) => (*dynamic_module.root()).global(),
         }
@@ -904,7 +890,30 @@ impl ModuleOwner {
         fetch_options: ScriptFetchOptions,
     ) {
         match &self {
-            ModuleOwner::Worker(_) => unimplemented!(),
+            ModuleOwner::Worker(scope) => {
+                let scope = scope.root();
+                let module_tree = module_identity.get_module_tree(&self.global());
+
+                // https://html.spec.whatwg.org/multipage/#run-a-worker
+                // Step 12, "onComplete" steps.
+                let network_error = module_tree.get_network_error().borrow().is_some();
+                if network_error {
+                    if let Some(dedicated) = scope.downcast::<DedicatedWorkerGlobalScope>() {
+                        dedicated.forward_simple_error_at_worker();
+                    } else if scope.is::<SharedWorkerGlobalScope>() {
+                        // TODO: fire an error event at the SharedWorker object.
+                        scope.close();
+                    }
+                    return;
+                }
+
+                if !scope.is_closing() {
+                    scope.run_a_module_script(&module_tree);
+                }
+                if let Some(shared) = scope.downcast::<SharedWorkerGlobalScope>() {
+                    shared.start_pending_connections();
+                }
+            },
             ModuleOwner::DynamicModule(_) => unimplemented!(),
             ModuleOwner::Window(script) => {
                 let global = self.global();
@@ -1332,15 +1341,16 @@ fn fetch_an_import_module_script_graph(
     options: ScriptFetchOptions,
     promise: Rc<Promise>,
 ) -> Result<(), RethrowError> {
+    disallow_further_import_maps(global);
+
     // Step 1.
-    let url = ModuleTree::resolve_module_specifier(*global.get_cx(), &base_url, specifier);
+    let url = ModuleTree::resolve_module_specifier(&global, &base_url, specifier);
 
     // Step 2.
-    if url.is_err() {
-        let specifier_error =
-            unsafe { gen_type_error(&global, "Wrong module specifier".to_owned()) };
-        return Err(specifier_error);
-    }
+    let url = match url {
+        Ok(url) => url,
+        Err(error) => return Err(unsafe { gen_error(&global, error) }),
+    };
 
     let dynamic_module_id = DynamicModuleId(Uuid::new_v4());
 
@@ -1365,8 +1375,6 @@ fn fetch_an_import_module_script_graph(
         .referencing_private
         .set(reference_private.get());
 
-    let url = url.unwrap();
-
     let mut visited_urls = HashSet::new();
     visited_urls.insert(url.clone());
 
@@ -1404,7 +1412,7 @@ unsafe extern "C" fn HostResolveImportedModule(
     }
 
     // Step 5.
-    let url = ModuleTree::resolve_module_specifier(*global_scope.get_cx(), &base_url, specifier);
+    let url = ModuleTree::resolve_module_specifier(&global_scope, &base_url, specifier);
 
     // Step 6.
     assert!(url.is_ok());
@@ -1472,6 +1480,8 @@ pub(crate) fn fetch_external_module_script(
     destination: Destination,
     options: ScriptFetchOptions,
 ) {
+    disallow_further_import_maps(&owner.global());
+
     let mut visited_urls = HashSet::new();
     visited_urls.insert(url.clone());
 
@@ -1698,6 +1708,8 @@ pub(crate) fn fetch_inline_module_script(
     options: ScriptFetchOptions,
 ) {
     let global = owner.global();
+    disallow_further_import_maps(&global);
+
     let is_external = false;
     let module_tree = ModuleTree::new(url.clone(), is_external, HashSet::new());
 
@@ -1744,3 +1756,326 @@ pub(crate) fn fetch_inline_module_script(
         },
     }
 }
+
+/// A map from module specifiers to the URLs they resolve to. A `None` value
+/// blocks the import of the specifier.
+/// <https://html.spec.whatwg.org/multipage/#module-specifier-map>
+type SpecifierMap = IndexMap<String, Option<ServoUrl>>;
+
+/// <https://html.spec.whatwg.org/multipage/#import-map>
+#[derive(Default, JSTraceable)]
+pub struct ImportMap {
+    imports: SpecifierMap,
+    scopes: IndexMap<String, SpecifierMap>,
+}
+
+/// <https://html.spec.whatwg.org/multipage/#disallow-further-import-maps>
+fn disallow_further_import_maps(global: &GlobalScope) {
+    // Step 2.
+    if let Some(window) = global.downcast::<Window>() {
+        // Step 3.
+        window.set_import_maps_allowed(false);
+    }
+}
+
+/// <https://html.spec.whatwg.org/multipage/#parse-an-import-map-string>
+pub fn parse_an_import_map_string(input: &str, base_url: &ServoUrl) -> Fallible<ImportMap> {
+    // Step 1.
+    let parsed: JsonValue = serde_json::from_str(input).map_err(|error| {
+        warn!("Failed to parse import map: {}", error);
+        Error::Syntax
+    })?;
+
+    // Step 2.
+    let parsed = match parsed {
+        JsonValue::Object(parsed) => parsed,
+        _ => {
+            return Err(Error::Type(
+                "The import map must be a JSON object".to_owned(),
+            ))
+        },
+    };
+
+    // Step 3.
+    let imports = match parsed.get("imports") {
+        Some(JsonValue::Object(imports)) => sort_and_normalize_specifier_map(imports, base_url),
+        Some(_) => {
+            return Err(Error::Type(
+                "The \"imports\" key of an import map must be a JSON object".to_owned(),
+            ))
+        },
+        None => SpecifierMap::new(),
+    };
+
+    // Step 4.
+    let scopes = match parsed.get("scopes") {
+        Some(JsonValue::Object(scopes)) => sort_and_normalize_scopes(scopes, base_url)?,
+        Some(_) => {
+            return Err(Error::Type(
+                "The \"scopes\" key of an import map must be a JSON object".to_owned(),
+            ))
+        },
+        None => IndexMap::new(),
+    };
+
+    // Step 5.
+    for key in parsed.keys() {
+        if key != "imports" && key != "scopes" {
+            warn!("Invalid top-level key \"{}\" in import map", key);
+        }
+    }
+
+    // Step 6.
+    Ok(ImportMap { imports, scopes })
+}
+
+/// <https://html.spec.whatwg.org/multipage/#register-an-import-map>
+#[allow(unsafe_code)]
+pub(crate) fn register_import_map(global: &GlobalScope, result: Fallible<ImportMap>) {
+    match result {
+        // Step 1.
+        Err(error) => {
+            let ar = enter_realm(&*global);
+            throw_dom_exception(global.get_cx(), global, error);
+            unsafe {
+                report_pending_exception(*global.get_cx(), true, InRealm::Entered(&ar));
+            }
+        },
+        // Step 2.
+        Ok(import_map) => global.set_import_map(import_map),
+    }
+}
+
+/// <https://html.spec.whatwg.org/multipage/#sorting-and-normalizing-a-module-specifier-map>
+fn sort_and_normalize_specifier_map(
+    original_map: &JsonMap<String, JsonValue>,
+    base_url: &ServoUrl,
+) -> SpecifierMap {
+    // Step 1.
+    let mut normalized = SpecifierMap::new();
+
+    // Step 2.
+    for (specifier_key, value) in original_map {
+        // Step 2.1.
+        let normalized_specifier_key = match normalize_specifier_key(specifier_key, base_url) {
+            Some(key) => key,
+            // Step 2.2.
+            None => continue,
+        };
+
+        // Step 2.3.
+        let value = match value {
+            JsonValue::String(value) => value,
+            _ => {
+                warn!("Import map addresses must be strings");
+                normalized.insert(normalized_specifier_key, None);
+                continue;
+            },
+        };
+
+        // Step 2.4.
+        let address_url = match resolve_url_like_module_specifier(value, base_url) {
+            Some(url) => url,
+            // Step 2.5.
+            None => {
+                warn!(
+                    "Invalid address \"{}\" for the specifier key \"{}\"",
+                    value, specifier_key
+                );
+                normalized.insert(normalized_specifier_key, None);
+                continue;
+            },
+        };
+
+        // Step 2.6.
+        if specifier_key.ends_with('/') && !address_url.as_str().ends_with('/') {
+            warn!(
+                "Invalid address \"{}\" for the package specifier key \"{}\"",
+                address_url, specifier_key
+            );
+            normalized.insert(normalized_specifier_key, None);
+            continue;
+        }
+
+        // Step 2.7.
+        normalized.insert(normalized_specifier_key, Some(address_url));
+    }
+
+    // Step 3.
+    normalized.sort_by(|a, _, b, _| b.cmp(a));
+    normalized
+}
+
+/// <https://html.spec.whatwg.org/multipage/#sorting-and-normalizing-scopes>
+fn sort_and_normalize_scopes(
+    original_map: &JsonMap<String, JsonValue>,
+    base_url: &ServoUrl,
+) -> Fallible<IndexMap<String, SpecifierMap>> {
+    // Step 1.
+    let mut normalized = IndexMap::new();
+
+    // Step 2.
+    for (scope_prefix, potential_specifier_map) in original_map {
+        // Step 2.1.
+        let potential_specifier_map = match potential_specifier_map {
+            JsonValue::Object(map) => map,
+            _ => {
+                return Err(Error::Type(format!(
+                    "The value of the scope \"{}\" must be a JSON object",
+                    scope_prefix
+                )))
+            },
+        };
+
+        // Step 2.2.
+        let scope_prefix_url = match ServoUrl::parse_with_base(Some(base_url), scope_prefix) {
+            Ok(url) => url,
+            // Step 2.3.
+            Err(_) => {
+                warn!("Invalid scope prefix \"{}\" in import map", scope_prefix);
+                continue;
+            },
+        };
+
+        // Step 2.4-2.5.
+        normalized.insert(
+            scope_prefix_url.into_string(),
+            sort_and_normalize_specifier_map(potential_specifier_map, base_url),
+        );
+    }
+
+    // Step 3.
+    normalized.sort_by(|a, _, b, _| b.cmp(a));
+    Ok(normalized)
+}
+
+/// <https://html.spec.whatwg.org/multipage/#normalizing-a-specifier-key>
+fn normalize_specifier_key(specifier_key: &str, base_url: &ServoUrl) -> Option<String> {
+    // Step 1.
+    if specifier_key.is_empty() {
+        warn!("Specifier keys in import maps can't be empty");
+        return None;
+    }
+
+    // Step 2-3.
+    match resolve_url_like_module_specifier(specifier_key, base_url) {
+        Some(url) => Some(url.into_string()),
+        // Step 4.
+        None => Some(specifier_key.to_owned()),
+    }
+}
+
+/// <https://html.spec.whatwg.org/multipage/#resolve-a-module-specifier>
+/// Step 6-12.
+pub fn resolve_a_module_specifier(
+    import_map: &ImportMap,
+    specifier: &str,
+    base_url: &ServoUrl,
+) -> Fallible<ServoUrl> {
+    // Step 6.
+    let serialized_base_url = base_url.as_str();
+
+    // Step 7.
+    let as_url = resolve_url_like_module_specifier(specifier, base_url);
+
+    // Step 8.
+    let normalized_specifier = as_url.as_ref().map_or(specifier, |url| url.as_str());
+
+    // Step 9.
+    for (scope_prefix, scope_imports) in &import_map.scopes {
+        if scope_prefix == serialized_base_url ||
+            (scope_prefix.ends_with('/') && serialized_base_url.starts_with(&**scope_prefix))
+        {
+            let scope_imports_match =
+                resolve_imports_match(normalized_specifier, as_url.as_ref(), scope_imports)?;
+            if let Some(url) = scope_imports_match {
+                return Ok(url);
+            }
+        }
+    }
+
+    // Step 10.
+    let top_level_imports_match =
+        resolve_imports_match(normalized_specifier, as_url.as_ref(), &import_map.imports)?;
+    if let Some(url) = top_level_imports_match {
+        return Ok(url);
+    }
+
+    // Step 11-12.
+    as_url.ok_or_else(|| {
+        Error::Type(format!(
+            "The module specifier \"{}\" was a bare specifier, but was not remapped to anything",
+            specifier
+        ))
+    })
+}
+
+/// <https://html.spec.whatwg.org/multipage/#resolving-an-imports-match>
+fn resolve_imports_match(
+    normalized_specifier: &str,
+    as_url: Option<&ServoUrl>,
+    specifier_map: &SpecifierMap,
+) -> Fallible<Option<ServoUrl>> {
+    let blocked = || {
+        Error::Type(format!(
+            "The import of \"{}\" was blocked by the import map",
+            normalized_specifier
+        ))
+    };
+
+    // Step 1.
+    for (specifier_key, resolution_result) in specifier_map {
+        // Step 1.1.
+        if specifier_key == normalized_specifier {
+            // Step 1.1.1-1.1.3.
+            return resolution_result.clone().map(Some).ok_or_else(blocked);
+        }
+
+        // Step 1.2.
+        if specifier_key.ends_with('/') &&
+            normalized_specifier.starts_with(&**specifier_key) &&
+            as_url.map_or(true, |url| is_special_scheme(url.scheme()))
+        {
+            // Step 1.2.1.
+            let resolution_result = resolution_result.as_ref().ok_or_else(blocked)?;
+
+            // Step 1.2.2.
+            let after_prefix = &normalized_specifier[specifier_key.len()..];
+
+            // Step 1.2.3.
+            assert!(resolution_result.as_str().ends_with('/'));
+
+            // Step 1.2.4-1.2.5.
+            let url = ServoUrl::parse_with_base(Some(resolution_result), after_prefix)
+                .map_err(|_| blocked())?;
+
+            // Step 1.2.6-1.2.7.
+            if !url.as_str().starts_with(resolution_result.as_str()) {
+                return Err(blocked());
+            }
+
+            // Step 1.2.8.
+            return Ok(Some(url));
+        }
+    }
+
+    // Step 2.
+    Ok(None)
+}
+
+/// <https://html.spec.whatwg.org/multipage/#resolving-a-url-like-module-specifier>
+fn resolve_url_like_module_specifier(specifier: &str, base_url: &ServoUrl) -> Option<ServoUrl> {
+    // Step 1.
+    if specifier.starts_with('/') || specifier.starts_with("./") || specifier.starts_with("../") {
+        // Step 1.1-1.3.
+        return ServoUrl::parse_with_base(Some(base_url), specifier).ok();
+    }
+
+    // Step 2-4.
+    ServoUrl::parse(specifier).ok()
+}
+
+/// <https://url.spec.whatwg.org/#special-scheme>
+fn is_special_scheme(scheme: &str) -> bool {
+    matches!(scheme, "ftp" | "file" | "http" | "https" | "ws" | "wss")
+}
@@ -47,6 +47,7 @@ use crate::dom::event::{Event, EventBubbles, EventCancelable};
 use crate::dom::globalscope::GlobalScope;
 use crate::dom::htmlanchorelement::HTMLAnchorElement;
 use crate::dom::htmliframeelement::{HTMLIFrameElement, NavigationType};
+use crate::dom::htmlslotelement::HTMLSlotElement;
 use crate::dom::identityhub::Identities;
 use crate::dom::mutationobserver::MutationObserver;
 use crate::dom::node::{window_from_node, Node, ShadowIncluding};
@@ -134,13 +135,13 @@ use script_traits::CompositorEvent::{
     ResizeEvent, TouchEvent, WheelEvent,
 };
 use script_traits::{
-    AnimationTickType, CompositorEvent, ConstellationControlMsg, DiscardBrowsingContext,
-    DocumentActivity, EventResult, HistoryEntryReplacement, InitialScriptState, JsEvalResult,
-    LayoutMsg, LoadData, LoadOrigin, MediaSessionActionType, MouseButton, MouseEventType,
-    NewLayoutInfo, Painter, ProgressiveWebMetricType, ScriptMsg, ScriptThreadFactory,
-    ScriptToConstellationChan, StructuredSerializedData, TimerSchedulerMsg, TouchEventType,
-    TouchId, UntrustedNodeAddress, UpdatePipelineIdReason, WebrenderIpcSender, WheelDelta,
-    WindowSizeData, WindowSizeType,
+    AnimationTickType, AudioOutputMsg, CompositorEvent, ConstellationControlMsg,
+    DiscardBrowsingContext, DocumentActivity, EventResult, HistoryEntryReplacement,
+    InitialScriptState, JsEvalResult, LayoutMsg, LoadData, LoadOrigin, MediaSessionActionType,
+    MouseButton, MouseEventType, NewLayoutInfo, Painter, PictureInPictureAction,
+    ProgressiveWebMetricType, ScriptMsg, ScriptThreadFactory, ScriptToConstellationChan,
+    StructuredSerializedData, TimerSchedulerMsg, TouchEventType, TouchId, UntrustedNodeAddress,
+    UpdatePipelineIdReason, WebrenderIpcSender, WheelDelta, WindowSizeData, WindowSizeType,
 };
 use servo_atoms::Atom;
 use servo_config::opts;
@@ -624,6 +625,9 @@ pub struct ScriptThread {
     /// The unit of related similar-origin browsing contexts' list of MutationObserver objects
     mutation_observers: DomRefCell<Vec<Dom<MutationObserver>>>,
 
+    /// <https://dom.spec.whatwg.org/#signal-slot-list>
+    signal_slots: DomRefCell<Vec<Dom<HTMLSlotElement>>>,
+
     /// A handle to the WebGL thread
     webgl_chan: Option<WebGLPipeline>,
 
@@ -906,6 +910,29 @@ impl ScriptThread {
         })
     }
 
+    pub fn add_signal_slot(slot: &HTMLSlotElement) {
+        SCRIPT_THREAD_ROOT.with(|root| {
+            let script_thread = unsafe { &*root.get().unwrap() };
+            let mut signal_slots = script_thread.signal_slots.borrow_mut();
+            if !signal_slots.iter().any(|s| &**s == slot) {
+                signal_slots.push(Dom::from_ref(slot));
+            }
+        })
+    }
+
+    pub fn take_signal_slots() -> Vec<DomRoot<HTMLSlotElement>> {
+        SCRIPT_THREAD_ROOT.with(|root| {
+            let script_thread = unsafe { &*root.get().unwrap() };
+            let mut signal_slots = script_thread.signal_slots.borrow_mut();
+            let slots = signal_slots
+                .iter()
+                .map(|s| DomRoot::from_ref(&**s))
+                .collect();
+            signal_slots.clear();
+            slots
+        })
+    }
+
     pub fn mark_document_with_no_blocked_loads(doc: &Document) {
         SCRIPT_THREAD_ROOT.with(|root| {
             if let Some(script_thread) = root.get() {
@@ -1159,6 +1186,7 @@ impl ScriptThread {
                         user_agent: script_thread.user_agent.clone(),
                         gpu_id_hub: script_thread.gpu_id_hub.clone(),
                         inherited_secure_context: script_thread.inherited_secure_context.clone(),
+                        audio_worklet_registry: Default::default(),
                     };
                     Rc::new(WorkletThreadPool::spawn(init))
                 })
@@ -1381,6 +1409,8 @@ impl ScriptThread {
 
             mutation_observers: Default::default(),
 
+            signal_slots: Default::default(),
+
             layout_to_constellation_chan: state.layout_to_constellation_chan,
 
             webgl_chan: state.webgl_chan,
@@ -1817,6 +1847,8 @@ impl ScriptThread {
                 PaintMetric(..) => None,
                 ExitFullScreen(id, ..) => Some(id),
                 MediaSessionAction(..) => None,
+                PictureInPictureAction(id, ..) => Some(id),
+                SetAudioOutputCapture(id, ..) => Some(id),
                 SetWebGPUPort(..) => None,
             },
             MixedMessage::FromDevtools(_) => None,
@@ -2036,6 +2068,12 @@ impl ScriptThread {
             ConstellationControlMsg::MediaSessionAction(pipeline_id, action) => {
                 self.handle_media_session_action(pipeline_id, action)
             },
+            ConstellationControlMsg::PictureInPictureAction(pipeline_id, action) => {
+                self.handle_picture_in_picture_action(pipeline_id, action)
+            },
+            ConstellationControlMsg::SetAudioOutputCapture(pipeline_id, mixer) => {
+                self.handle_set_audio_output_capture(pipeline_id, mixer)
+            },
             ConstellationControlMsg::SetWebGPUPort(port) => {
                 if self.webgpu_port.borrow().is_some() {
                     warn!("WebGPU port already exists for this content process");
@@ -3822,7 +3860,11 @@ impl ScriptThread {
             .headers(load_data.headers)
             .body(load_data.data)
             .redirect_mode(RedirectMode::Manual)
-            .origin(incomplete.origin.immutable().clone());
+            .origin(incomplete.origin.immutable().clone())
+            .user_initiated(match load_data.load_origin {
+                LoadOrigin::Constellation | LoadOrigin::WebDriver => true,
+                LoadOrigin::Script(_) => false,
+            });
 
         let context = ParserContext::new(id, load_data.url);
         self.incomplete_parser_contexts
@@ -4001,6 +4043,35 @@ impl ScriptThread {
         };
     }
 
+    fn handle_set_audio_output_capture(
+        &self,
+        pipeline_id: PipelineId,
+        mixer: Option<IpcSender<AudioOutputMsg>>,
+    ) {
+        match self.documents.borrow().find_window(pipeline_id) {
+            Some(window) => window.set_audio_output_capture(mixer),
+            None => warn!(
+                "Audio output capture sent to closed pipeline {}.",
+                pipeline_id
+            ),
+        }
+    }
+
+    fn handle_picture_in_picture_action(
+        &self,
+        pipeline_id: PipelineId,
+        action: PictureInPictureAction,
+    ) {
+        if let Some(document) = self.documents.borrow().find_document(pipeline_id) {
+            document.handle_picture_in_picture_action(action);
+        } else {
+            warn!(
+                "Picture-in-picture action sent to closed pipeline {}.",
+                pipeline_id
+            );
+        };
+    }
+
     pub fn enqueue_microtask(job: Microtask) {
         SCRIPT_THREAD_ROOT.with(|root| {
             let script_thread = unsafe { &*root.get().unwrap() };
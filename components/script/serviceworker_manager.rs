@@ -16,12 +16,13 @@ use crate::script_runtime::ContextForRequestInterrupt;
 use crossbeam_channel::{unbounded, Receiver, RecvError, Sender};
 use ipc_channel::ipc::{self, IpcSender};
 use ipc_channel::router::ROUTER;
-use msg::constellation_msg::PipelineNamespace;
+use msg::constellation_msg::{PipelineId, PipelineNamespace};
 use msg::constellation_msg::{ServiceWorkerId, ServiceWorkerRegistrationId};
+use net_traits::request::Destination;
 use net_traits::{CoreResourceMsg, CustomResponseMediator};
 use script_traits::{
-    DOMMessage, Job, JobError, JobResult, JobResultValue, JobType, SWManagerMsg, SWManagerSenders,
-    ScopeThings, ServiceWorkerManagerFactory, ServiceWorkerMsg,
+    DOMMessage, Job, JobError, JobResult, JobResultValue, JobType, NavigationPreloadUpdate,
+    SWManagerMsg, SWManagerSenders, ScopeThings, ServiceWorkerManagerFactory, ServiceWorkerMsg,
 };
 use servo_config::pref;
 use servo_url::ImmutableOrigin;
@@ -137,6 +138,10 @@ struct ServiceWorkerRegistration {
     context: Option<ContextForRequestInterrupt>,
     /// The closing flag for the worker.
     closing: Option<Arc<AtomicBool>>,
+    /// https://w3c.github.io/ServiceWorker/#service-worker-registration-navigation-preload-enabled-flag
+    navigation_preload_enabled: bool,
+    /// https://w3c.github.io/ServiceWorker/#service-worker-registration-navigation-preload-header-value
+    navigation_preload_header_value: Vec<u8>,
 }
 
 impl ServiceWorkerRegistration {
@@ -150,6 +155,8 @@ impl ServiceWorkerRegistration {
             control_sender: None,
             context: None,
             closing: None,
+            navigation_preload_enabled: false,
+            navigation_preload_header_value: b"true".to_vec(),
         }
     }
 
@@ -205,12 +212,24 @@ impl ServiceWorkerRegistration {
             },
         }
     }
+
+    /// Very roughly <https://w3c.github.io/ServiceWorker/#activation-algorithm>:
+    /// the installing worker becomes the active worker.
+    fn activate(&mut self) {
+        if let Some(worker) = self.installing_worker.take() {
+            self.update_registration_state(RegistrationUpdateTarget::Active, worker);
+        }
+    }
 }
 
 /// A structure managing all registrations and workers for a given origin.
 pub struct ServiceWorkerManager {
     /// https://w3c.github.io/ServiceWorker/#dfn-scope-to-registration-map
     registrations: HashMap<ServoUrl, ServiceWorkerRegistration>,
+    /// The scopes of the registrations whose active workers control clients,
+    /// set when navigating to them.
+    /// <https://w3c.github.io/ServiceWorker/#dfn-service-worker-client-active-service-worker>
+    controllers: HashMap<PipelineId, ServoUrl>,
     // Will be useful to implement posting a message to a client.
     // See https://github.com/servo/servo/issues/24660
     _constellation_sender: IpcSender<SWManagerMsg>,
@@ -234,6 +253,7 @@ impl ServiceWorkerManager {
 
         ServiceWorkerManager {
             registrations: HashMap::new(),
+            controllers: HashMap::new(),
             own_sender: own_sender,
             own_port: from_constellation_receiver,
             resource_receiver: resource_port,
@@ -241,13 +261,13 @@ impl ServiceWorkerManager {
         }
     }
 
+    /// <https://w3c.github.io/ServiceWorker/#scope-match-algorithm>
     pub fn get_matching_scope(&self, load_url: &ServoUrl) -> Option<ServoUrl> {
-        for scope in self.registrations.keys() {
-            if longest_prefix_match(&scope, load_url) {
-                return Some(scope.clone());
-            }
-        }
-        None
+        self.registrations
+            .keys()
+            .filter(|scope| longest_prefix_match(scope, load_url))
+            .max_by_key(|scope| scope.path().len())
+            .cloned()
     }
 
     fn handle_message(&mut self) {
@@ -266,15 +286,48 @@ impl ServiceWorkerManager {
         }
     }
 
+    /// <https://w3c.github.io/ServiceWorker/#handle-fetch>
     fn handle_message_from_resource(&mut self, mediator: CustomResponseMediator) -> bool {
         if serviceworker_enabled() {
-            if let Some(scope) = self.get_matching_scope(&mediator.load_url) {
-                if let Some(registration) = self.registrations.get(&scope) {
-                    if let Some(ref worker) = registration.active_worker {
-                        worker.send_message(ServiceWorkerScriptMsg::Response(mediator));
-                        return true;
+            // Steps 12 and 13: navigations are handled by the registration matching
+            // the URL navigated to, which then controls the client being created,
+            // other requests by the one controlling their client.
+            let is_navigation = mediator.request.destination == Destination::Document;
+            let client = mediator.request.pipeline_id;
+            let scope = if is_navigation {
+                let scope = self
+                    .get_matching_scope(&mediator.load_url)
+                    .filter(|scope| self.registrations[scope].active_worker.is_some());
+                if let Some(client) = client {
+                    match scope {
+                        Some(ref scope) => {
+                            self.controllers.insert(client, scope.clone());
+                        },
+                        None => {
+                            self.controllers.remove(&client);
+                        },
                     }
                 }
+                scope
+            } else {
+                client.and_then(|client| self.controllers.get(&client).cloned())
+            };
+            let registration = scope.and_then(|scope| self.registrations.get(&scope));
+            if let Some(registration) = registration {
+                if let Some(ref worker) = registration.active_worker {
+                    // Step 24.3.
+                    let preload_header_value =
+                        if is_navigation && registration.navigation_preload_enabled {
+                            Some(registration.navigation_preload_header_value.clone())
+                        } else {
+                            None
+                        };
+                    worker.send_message(ServiceWorkerScriptMsg::Response(
+                        mediator,
+                        preload_header_value,
+                    ));
+                    return true;
+                }
             }
         }
         let _ = mediator.response_chan.send(None);
@@ -311,6 +364,26 @@ impl ServiceWorkerManager {
                     // TODO: https://w3c.github.io/ServiceWorker/#unregister-algorithm
                 },
             },
+            ServiceWorkerMsg::Activate(scope_url) => {
+                if let Some(registration) = self.registrations.get_mut(&scope_url) {
+                    registration.activate();
+                }
+            },
+            ServiceWorkerMsg::UpdateNavigationPreload(scope_url, update) => {
+                if let Some(registration) = self.registrations.get_mut(&scope_url) {
+                    match update {
+                        NavigationPreloadUpdate::Enabled(enabled) => {
+                            registration.navigation_preload_enabled = enabled;
+                        },
+                        NavigationPreloadUpdate::HeaderValue(value) => {
+                            registration.navigation_preload_header_value = value;
+                        },
+                    }
+                }
+            },
+            ServiceWorkerMsg::ClientExited(pipeline_id) => {
+                self.controllers.remove(&pipeline_id);
+            },
             ServiceWorkerMsg::Exit => return false,
         }
         true
@@ -15,6 +15,17 @@ pub mod area {
     pub use crate::dom::htmlareaelement::{Area, Shape};
 }
 
+pub mod importmap {
+    pub use crate::dom::bindings::error::Error;
+    pub use crate::script_module::{
+        parse_an_import_map_string, resolve_a_module_specifier, ImportMap,
+    };
+}
+
+pub mod mediasegmentparser {
+    pub use crate::dom::mediasegmentparser::{ByteStreamFormat, Segment, SegmentParser};
+}
+
 #[allow(non_snake_case)]
 pub mod size_of {
     use crate::dom::characterdata::CharacterData;
@@ -67,3 +78,10 @@ pub mod srcset {
 pub mod timeranges {
     pub use crate::dom::timeranges::TimeRangesContainer;
 }
+
+pub mod webvttparser {
+    pub use crate::dom::webvttparser::{
+        parse, parse_cue_text, CueInternalNode, CueNode, CueNodeKind, LineAlignment,
+        PositionAlignment, TextAlignment, WritingDirection,
+    };
+}
@@ -38,6 +38,7 @@ pub enum PseudoElementType {
     After,
     DetailsSummary,
     DetailsContent,
+    Backdrop,
 }
 
 impl PseudoElementType {
@@ -48,6 +49,7 @@ impl PseudoElementType {
             PseudoElementType::After => FragmentType::AfterPseudoContent,
             PseudoElementType::DetailsSummary => FragmentType::FragmentBody,
             PseudoElementType::DetailsContent => FragmentType::FragmentBody,
+            PseudoElementType::Backdrop => FragmentType::BackdropPseudoContent,
         }
     }
 
@@ -74,6 +76,7 @@ impl PseudoElementType {
             PseudoElementType::After => PseudoElement::After,
             PseudoElementType::DetailsSummary => PseudoElement::DetailsSummary,
             PseudoElementType::DetailsContent => PseudoElement::DetailsContent,
+            PseudoElementType::Backdrop => PseudoElement::Backdrop,
         }
     }
 }
@@ -210,6 +213,12 @@ pub trait ThreadSafeLayoutNode<'dom>:
             .map(|el| el.as_node())
     }
 
+    fn get_backdrop_pseudo(&self) -> Option<Self> {
+        self.as_element()
+            .and_then(|el| el.get_backdrop_pseudo())
+            .map(|el| el.as_node())
+    }
+
     fn debug_id(self) -> usize;
 
     /// Returns an iterator over this node's children.
@@ -409,6 +418,19 @@ pub trait ThreadSafeLayoutElement<'dom>:
         }
     }
 
+    /// The `::backdrop` of an element in the top layer, which is laid out
+    /// right before the element itself so that it is painted below it.
+    ///
+    /// <https://fullscreen.spec.whatwg.org/#::backdrop-pseudo-element>
+    #[inline]
+    fn get_backdrop_pseudo(&self) -> Option<Self> {
+        if self.style_data().styles.primary().in_top_layer() {
+            Some(self.with_pseudo(PseudoElementType::Backdrop))
+        } else {
+            None
+        }
+    }
+
     /// Returns the style results for the given node. If CSS selector matching
     /// has not yet been performed, fails.
     ///
@@ -78,7 +78,8 @@ use webrender_api::{BuiltDisplayListDescriptor, HitTestFlags, HitTestResult};
 
 pub use crate::script_msg::{
     DOMMessage, HistoryEntryReplacement, Job, JobError, JobResult, JobResultValue, JobType,
-    SWManagerMsg, SWManagerSenders, ScopeThings, ServiceWorkerMsg,
+    NavigationPreloadUpdate, SWManagerMsg, SWManagerSenders, ScopeThings, ServiceWorkerMsg,
+    SharedWorkerConnectInfo, SharedWorkerConnectionMsg, SharedWorkerControlMsg,
 };
 pub use crate::script_msg::{
     EventResult, IFrameSize, IFrameSizeMsg, LayoutMsg, LogEntry, ScriptMsg,
@@ -406,6 +407,11 @@ pub enum ConstellationControlMsg {
     PaintMetric(PipelineId, ProgressiveWebMetricType, u64),
     /// Notifies the media session about a user requested media session action.
     MediaSessionAction(PipelineId, MediaSessionActionType),
+    /// Notifies the picture-in-picture video about a change of its surface.
+    PictureInPictureAction(PipelineId, PictureInPictureAction),
+    /// Sets the mixer that the audio output of a pipeline is sent to, or stops
+    /// capturing its audio output when `None` is given.
+    SetAudioOutputCapture(PipelineId, Option<IpcSender<AudioOutputMsg>>),
     /// Notifies script thread that WebGPU server has started
     SetWebGPUPort(IpcReceiver<WebGPUMsg>),
 }
@@ -445,6 +451,8 @@ impl fmt::Debug for ConstellationControlMsg {
             PaintMetric(..) => "PaintMetric",
             ExitFullScreen(..) => "ExitFullScreen",
             MediaSessionAction(..) => "MediaSessionAction",
+            PictureInPictureAction(..) => "PictureInPictureAction",
+            SetAudioOutputCapture(..) => "SetAudioOutputCapture",
             SetWebGPUPort(..) => "SetWebGPUPort",
         };
         write!(formatter, "ConstellationControlMsg::{}", variant)
@@ -1110,6 +1118,36 @@ impl From<i32> for MediaSessionActionType {
     }
 }
 
+/// A change of the picture-in-picture surface hosted by the embedder.
+/// https://w3c.github.io/picture-in-picture/#picture-in-picture-window
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum PictureInPictureAction {
+    /// The user resized the surface.
+    Resize(DeviceIntSize),
+    /// The user closed the surface.
+    Close,
+}
+
+/// The sample rate of the mix of the audio output sent to the embedder.
+pub const AUDIO_OUTPUT_SAMPLE_RATE: u32 = 44100;
+
+/// Identifies a source of audio, like a media player, for the audio output mixer.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct AudioOutputSourceId(pub PipelineId, pub u64);
+
+/// Messages sent to the mixer of the audio output of a browser, while the
+/// embedder captures it.
+#[derive(Debug, Deserialize, Serialize)]
+pub enum AudioOutputMsg {
+    /// Samples of one channel of a source, with their sample rate. They are
+    /// resampled to `AUDIO_OUTPUT_SAMPLE_RATE` by the mixer.
+    Samples(AudioOutputSourceId, f32, u32, Vec<f32>),
+    /// The source will not produce any more samples.
+    RemoveSource(AudioOutputSourceId),
+    /// Stop mixing.
+    Exit,
+}
+
 /// The set of WebRender operations that can be initiated by the content process.
 #[derive(Deserialize, Serialize)]
 pub enum WebrenderMsg {
@@ -2,6 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use crate::transferable::MessagePortImpl;
 use crate::AnimationState;
 use crate::AuxiliaryBrowsingContextLoadInfo;
 use crate::BroadcastMsg;
@@ -17,7 +18,7 @@ use crate::WorkerGlobalScopeInit;
 use crate::WorkerScriptLoadOrigin;
 use canvas_traits::canvas::{CanvasId, CanvasMsg};
 use devtools_traits::{ScriptToDevtoolsControlMsg, WorkerId};
-use embedder_traits::{EmbedderMsg, MediaSessionEvent};
+use embedder_traits::{EmbedderMsg, MediaSessionEvent, PictureInPictureRequest};
 use euclid::default::Size2D as UntypedSize2D;
 use euclid::Size2D;
 use gfx_traits::Epoch;
@@ -27,8 +28,8 @@ use msg::constellation_msg::{
     TopLevelBrowsingContextId,
 };
 use msg::constellation_msg::{HistoryStateId, TraversalDirection};
-use msg::constellation_msg::{ServiceWorkerId, ServiceWorkerRegistrationId};
-use net_traits::request::RequestBuilder;
+use msg::constellation_msg::{ServiceWorkerId, ServiceWorkerRegistrationId, SharedWorkerId};
+use net_traits::request::{CredentialsMode, RequestBuilder};
 use net_traits::storage_thread::StorageType;
 use net_traits::CoreResourceMsg;
 use servo_url::ImmutableOrigin;
@@ -157,6 +158,14 @@ pub enum ScriptMsg {
     /// Broadcast a message to all same-origin broadcast channels,
     /// excluding the source of the broadcast.
     ScheduleBroadcast(BroadcastChannelRouterId, BroadcastMsg),
+    /// Connect a client to the shared worker matching the origin, url and name,
+    /// with the state of the connection sent back as it changes.
+    ConnectToSharedWorker(
+        SharedWorkerConnectInfo,
+        IpcSender<SharedWorkerConnectionMsg>,
+    ),
+    /// A shared worker has closed, and should not be connected to anymore.
+    SharedWorkerClosed(SharedWorkerId),
     /// Forward a message to the embedder.
     ForwardToEmbedder(EmbedderMsg),
     /// Requests are sent to constellation and fetches are checked manually
@@ -265,6 +274,9 @@ pub enum ScriptMsg {
     ForwardDOMMessage(DOMMessage, ServoUrl),
     /// https://w3c.github.io/ServiceWorker/#schedule-job-algorithm.
     ScheduleJob(Job),
+    /// Update the navigation preload state of the service worker registration
+    /// for a scope, stored in the service worker manager.
+    UpdateNavigationPreload(ServoUrl, NavigationPreloadUpdate),
     /// Get Window Informations size and position
     GetClientWindow(IpcSender<(DeviceIntSize, DeviceIntPoint)>),
     /// Get the screen size (pixel)
@@ -274,6 +286,9 @@ pub enum ScriptMsg {
     /// Notifies the constellation about media session events
     /// (i.e. when there is metadata for the active media session, playback state changes...).
     MediaSessionEvent(PipelineId, MediaSessionEvent),
+    /// Notifies the constellation about a video of this pipeline entering,
+    /// updating or leaving picture-in-picture.
+    PictureInPicture(PictureInPictureRequest),
     /// Create a WebGPU Adapter instance
     RequestAdapter(
         IpcSender<WebGPUResponseResult>,
@@ -304,6 +319,8 @@ impl fmt::Debug for ScriptMsg {
             RemoveBroadcastChannelNameInRouter(..) => "RemoveBroadcastChannelNameInRouter",
             NewBroadcastChannelNameInRouter(..) => "NewBroadcastChannelNameInRouter",
             ScheduleBroadcast(..) => "ScheduleBroadcast",
+            ConnectToSharedWorker(..) => "ConnectToSharedWorker",
+            SharedWorkerClosed(..) => "SharedWorkerClosed",
             ForwardToEmbedder(..) => "ForwardToEmbedder",
             InitiateNavigateRequest(..) => "InitiateNavigateRequest",
             BroadcastStorageEvent(..) => "BroadcastStorageEvent",
@@ -337,10 +354,12 @@ impl fmt::Debug for ScriptMsg {
             PipelineExited => "PipelineExited",
             ForwardDOMMessage(..) => "ForwardDOMMessage",
             ScheduleJob(..) => "ScheduleJob",
+            UpdateNavigationPreload(..) => "UpdateNavigationPreload",
             GetClientWindow(..) => "GetClientWindow",
             GetScreenSize(..) => "GetScreenSize",
             GetScreenAvailSize(..) => "GetScreenAvailSize",
             MediaSessionEvent(..) => "MediaSessionEvent",
+            PictureInPicture(..) => "PictureInPicture",
             RequestAdapter(..) => "RequestAdapter",
             GetWebGPUChan(..) => "GetWebGPUChan",
             TitleChanged(..) => "TitleChanged",
@@ -364,6 +383,47 @@ pub struct ScopeThings {
     pub worker_id: WorkerId,
 }
 
+/// Entities required to connect a client to a shared worker.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SharedWorkerConnectInfo {
+    /// The origin of the client
+    pub origin: ImmutableOrigin,
+    /// The url of the worker script
+    pub script_url: ServoUrl,
+    /// The name of the worker
+    pub name: String,
+    /// Whether the worker script is a module script
+    pub module: bool,
+    /// The credentials mode of the worker script request
+    pub credentials: CredentialsMode,
+    /// The worker side of the port entangled with the port of the `SharedWorker` object
+    pub port: MessagePortImpl,
+    /// The control sender to use if a new worker has to be started
+    pub control_sender: IpcSender<SharedWorkerControlMsg>,
+}
+
+/// Messages sent by the constellation to a client connecting to a shared worker
+#[derive(Debug, Deserialize, Serialize)]
+pub enum SharedWorkerConnectionMsg {
+    /// The client is connected to the running worker
+    Connected,
+    /// There was no running worker, the client has to start one with this id
+    Start(SharedWorkerId),
+    /// The client could not connect, as the type or credentials mode of the
+    /// running worker differ from the ones requested, or lost the worker, as
+    /// the event loop running it closed
+    Error,
+}
+
+/// Messages sent by the constellation to a shared worker
+#[derive(Debug, Deserialize, Serialize)]
+pub enum SharedWorkerControlMsg {
+    /// A new client connected, with the worker side of its port
+    Connect(MessagePortImpl),
+    /// All clients are gone, shut the worker down
+    Exit,
+}
+
 /// Message that gets passed to service worker scope on postMessage
 #[derive(Debug, Deserialize, Serialize)]
 pub struct DOMMessage {
@@ -395,10 +455,28 @@ pub enum ServiceWorkerMsg {
     ForwardDOMMessage(DOMMessage, ServoUrl),
     /// https://w3c.github.io/ServiceWorker/#schedule-job-algorithm
     ScheduleJob(Job),
+    /// Sent by a service worker once it has run its script,
+    /// to become the active worker of the registration for the scope.
+    Activate(ServoUrl),
+    /// Message sent by constellation to update the navigation preload state
+    /// of the registration for a scope
+    UpdateNavigationPreload(ServoUrl, NavigationPreloadUpdate),
+    /// Message sent by constellation when a pipeline, which may be a client
+    /// controlled by a service worker, exited
+    ClientExited(PipelineId),
     /// Exit the service worker manager
     Exit,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+/// A change to the navigation preload state of a service worker registration.
+pub enum NavigationPreloadUpdate {
+    /// https://w3c.github.io/ServiceWorker/#service-worker-registration-navigation-preload-enabled-flag
+    Enabled(bool),
+    /// https://w3c.github.io/ServiceWorker/#service-worker-registration-navigation-preload-header-value
+    HeaderValue(Vec<u8>),
+}
+
 #[derive(Debug, Deserialize, PartialEq, Serialize)]
 /// https://w3c.github.io/ServiceWorker/#dfn-job-type
 pub enum JobType {
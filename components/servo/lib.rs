@@ -195,11 +195,20 @@ mod media_platform {
         {
             set_gstreamer_log_handler();
         }
+        init_codecs();
     }
 
     #[cfg(not(any(windows, target_os = "macos")))]
     pub fn init() {
         ServoMedia::init::<GStreamerBackend>();
+        init_codecs();
+    }
+
+    fn init_codecs() {
+        match media::GStreamerCodecBackend::new() {
+            Ok(backend) => media::codecs::set_codec_backend(Box::new(backend)),
+            Err(e) => warn!("Error initializing the GStreamer codecs: {}", e),
+        }
     }
 }
 
@@ -732,6 +741,31 @@ where
                 }
             },
 
+            WindowEvent::PictureInPicture(request) => {
+                self.compositor.on_picture_in_picture_request(request);
+            },
+
+            WindowEvent::PictureInPictureAction(a) => {
+                let msg = ConstellationMsg::PictureInPictureAction(a);
+                if let Err(e) = self.constellation_chan.send(msg) {
+                    warn!(
+                        "Sending PictureInPictureAction message to constellation failed ({:?}).",
+                        e
+                    );
+                }
+            },
+
+            WindowEvent::CaptureAudioOutput(top_level_browsing_context_id, sender) => {
+                let msg =
+                    ConstellationMsg::CaptureAudioOutput(top_level_browsing_context_id, sender);
+                if let Err(e) = self.constellation_chan.send(msg) {
+                    warn!(
+                        "Sending CaptureAudioOutput message to constellation failed ({:?}).",
+                        e
+                    );
+                }
+            },
+
             WindowEvent::ChangeBrowserVisibility(top_level_browsing_context_id, visible) => {
                 let msg = ConstellationMsg::ChangeBrowserVisibility(
                     top_level_browsing_context_id,
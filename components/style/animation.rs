@@ -86,57 +86,7 @@ impl PropertyAnimation {
 
     /// The output of the timing function given the progress ration of this animation.
     fn timing_function_output(&self, progress: f64) -> f64 {
-        let epsilon = 1. / (200. * self.duration);
-        match self.timing_function {
-            GenericTimingFunction::CubicBezier { x1, y1, x2, y2 } => {
-                Bezier::new(x1, y1, x2, y2).solve(progress, epsilon)
-            },
-            GenericTimingFunction::Steps(steps, pos) => {
-                let mut current_step = (progress * (steps as f64)).floor() as i32;
-
-                if pos == StepPosition::Start ||
-                    pos == StepPosition::JumpStart ||
-                    pos == StepPosition::JumpBoth
-                {
-                    current_step = current_step + 1;
-                }
-
-                // FIXME: We should update current_step according to the "before flag".
-                // In order to get the before flag, we have to know the current animation phase
-                // and whether the iteration is reversed. For now, we skip this calculation.
-                // (i.e. Treat before_flag is unset,)
-                // https://drafts.csswg.org/css-easing/#step-timing-function-algo
-
-                if progress >= 0.0 && current_step < 0 {
-                    current_step = 0;
-                }
-
-                let jumps = match pos {
-                    StepPosition::JumpBoth => steps + 1,
-                    StepPosition::JumpNone => steps - 1,
-                    StepPosition::JumpStart |
-                    StepPosition::JumpEnd |
-                    StepPosition::Start |
-                    StepPosition::End => steps,
-                };
-
-                if progress <= 1.0 && current_step > jumps {
-                    current_step = jumps;
-                }
-
-                (current_step as f64) / (jumps as f64)
-            },
-            GenericTimingFunction::Keyword(keyword) => {
-                let bezier = match keyword {
-                    TimingKeyword::Linear => return progress,
-                    TimingKeyword::Ease => Bezier::new(0.25, 0.1, 0.25, 1.),
-                    TimingKeyword::EaseIn => Bezier::new(0.42, 0., 1., 1.),
-                    TimingKeyword::EaseOut => Bezier::new(0., 0., 0.58, 1.),
-                    TimingKeyword::EaseInOut => Bezier::new(0.42, 0., 0.58, 1.),
-                };
-                bezier.solve(progress, epsilon)
-            },
-        }
+        timing_function_output(&self.timing_function, progress, self.duration)
     }
 
     /// Update the given animation at a given point of progress.
@@ -148,6 +98,67 @@ impl PropertyAnimation {
     }
 }
 
+/// The output of the given timing function for a progress ratio of an animation
+/// with the given duration in seconds. This is shared between CSS animations,
+/// CSS transitions and the effects of animations created from script.
+pub fn timing_function_output(
+    timing_function: &TimingFunction,
+    progress: f64,
+    duration: f64,
+) -> f64 {
+    let epsilon = 1. / (200. * duration);
+    match *timing_function {
+        GenericTimingFunction::CubicBezier { x1, y1, x2, y2 } => {
+            Bezier::new(x1, y1, x2, y2).solve(progress, epsilon)
+        },
+        GenericTimingFunction::Steps(steps, pos) => {
+            let mut current_step = (progress * (steps as f64)).floor() as i32;
+
+            if pos == StepPosition::Start ||
+                pos == StepPosition::JumpStart ||
+                pos == StepPosition::JumpBoth
+            {
+                current_step = current_step + 1;
+            }
+
+            // FIXME: We should update current_step according to the "before flag".
+            // In order to get the before flag, we have to know the current animation phase
+            // and whether the iteration is reversed. For now, we skip this calculation.
+            // (i.e. Treat before_flag is unset,)
+            // https://drafts.csswg.org/css-easing/#step-timing-function-algo
+
+            if progress >= 0.0 && current_step < 0 {
+                current_step = 0;
+            }
+
+            let jumps = match pos {
+                StepPosition::JumpBoth => steps + 1,
+                StepPosition::JumpNone => steps - 1,
+                StepPosition::JumpStart |
+                StepPosition::JumpEnd |
+                StepPosition::Start |
+                StepPosition::End => steps,
+            };
+
+            if progress <= 1.0 && current_step > jumps {
+                current_step = jumps;
+            }
+
+            (current_step as f64) / (jumps as f64)
+        },
+        GenericTimingFunction::Keyword(keyword) => {
+            let bezier = match keyword {
+                TimingKeyword::Linear => return progress,
+                TimingKeyword::Ease => Bezier::new(0.25, 0.1, 0.25, 1.),
+                TimingKeyword::EaseIn => Bezier::new(0.42, 0., 1., 1.),
+                TimingKeyword::EaseOut => Bezier::new(0., 0., 0.58, 1.),
+                TimingKeyword::EaseInOut => Bezier::new(0.42, 0., 0.58, 1.),
+            };
+            bezier.solve(progress, epsilon)
+        },
+    }
+}
+
 /// This structure represents the state of an animation.
 #[derive(Clone, Debug, MallocSizeOf, PartialEq)]
 pub enum AnimationState {
@@ -401,6 +412,101 @@ impl ComputedKeyframe {
         }
         computed_steps
     }
+
+    /// Fill in an `AnimationValueMap` with the values interpolated between the given
+    /// computed keyframes at a progress (a number between 0 and 1) through the current
+    /// iteration. `duration` is the duration of an iteration in seconds.
+    fn add_values_at_progress(
+        computed_steps: &[ComputedKeyframe],
+        total_progress: f64,
+        direction: AnimationDirection,
+        duration: f64,
+        map: &mut AnimationValueMap,
+    ) {
+        // Get the indices of the previous (from) keyframe and the next (to) keyframe.
+        let next_keyframe_index;
+        let prev_keyframe_index;
+        let num_steps = computed_steps.len();
+        match direction {
+            AnimationDirection::Normal => {
+                next_keyframe_index = computed_steps
+                    .iter()
+                    .position(|step| total_progress as f32 <= step.start_percentage);
+                prev_keyframe_index = next_keyframe_index
+                    .and_then(|pos| if pos != 0 { Some(pos - 1) } else { None })
+                    .unwrap_or(0);
+            },
+            AnimationDirection::Reverse => {
+                next_keyframe_index = computed_steps
+                    .iter()
+                    .rev()
+                    .position(|step| total_progress as f32 <= 1. - step.start_percentage)
+                    .map(|pos| num_steps - pos - 1);
+                prev_keyframe_index = next_keyframe_index
+                    .and_then(|pos| {
+                        if pos != num_steps - 1 {
+                            Some(pos + 1)
+                        } else {
+                            None
+                        }
+                    })
+                    .unwrap_or(num_steps - 1)
+            },
+            _ => unreachable!(),
+        }
+
+        debug!(
+            "ComputedKeyframe::add_values_at_progress: keyframe from {:?} to {:?}",
+            prev_keyframe_index, next_keyframe_index
+        );
+
+        let prev_keyframe = &computed_steps[prev_keyframe_index];
+        let next_keyframe = match next_keyframe_index {
+            Some(index) => &computed_steps[index],
+            None => return,
+        };
+
+        // If we only need to take into account one keyframe, then exit early
+        // in order to avoid doing more work.
+        let mut add_declarations_to_map = |keyframe: &ComputedKeyframe| {
+            for value in keyframe.values.iter() {
+                map.insert(value.id(), value.clone());
+            }
+        };
+        if total_progress <= 0.0 {
+            add_declarations_to_map(&prev_keyframe);
+            return;
+        }
+        if total_progress >= 1.0 {
+            add_declarations_to_map(&next_keyframe);
+            return;
+        }
+
+        let percentage_between_keyframes =
+            (next_keyframe.start_percentage - prev_keyframe.start_percentage).abs() as f64;
+        let duration_between_keyframes = percentage_between_keyframes * duration;
+        let direction_aware_prev_keyframe_start_percentage = match direction {
+            AnimationDirection::Normal => prev_keyframe.start_percentage as f64,
+            AnimationDirection::Reverse => 1. - prev_keyframe.start_percentage as f64,
+            _ => unreachable!(),
+        };
+        let progress_between_keyframes = (total_progress -
+            direction_aware_prev_keyframe_start_percentage) /
+            percentage_between_keyframes;
+
+        for (from, to) in prev_keyframe.values.iter().zip(next_keyframe.values.iter()) {
+            let animation = PropertyAnimation {
+                from: from.clone(),
+                to: to.clone(),
+                timing_function: prev_keyframe.timing_function,
+                duration: duration_between_keyframes as f64,
+            };
+
+            if let Ok(value) = animation.calculate_value(progress_between_keyframes) {
+                map.insert(value.id(), value);
+            }
+        }
+    }
 }
 
 /// A CSS Animation
@@ -662,91 +768,13 @@ impl Animation {
             .min(self.current_iteration_end_progress())
             .max(0.0);
 
-        // Get the indices of the previous (from) keyframe and the next (to) keyframe.
-        let next_keyframe_index;
-        let prev_keyframe_index;
-        let num_steps = self.computed_steps.len();
-        match self.current_direction {
-            AnimationDirection::Normal => {
-                next_keyframe_index = self
-                    .computed_steps
-                    .iter()
-                    .position(|step| total_progress as f32 <= step.start_percentage);
-                prev_keyframe_index = next_keyframe_index
-                    .and_then(|pos| if pos != 0 { Some(pos - 1) } else { None })
-                    .unwrap_or(0);
-            },
-            AnimationDirection::Reverse => {
-                next_keyframe_index = self
-                    .computed_steps
-                    .iter()
-                    .rev()
-                    .position(|step| total_progress as f32 <= 1. - step.start_percentage)
-                    .map(|pos| num_steps - pos - 1);
-                prev_keyframe_index = next_keyframe_index
-                    .and_then(|pos| {
-                        if pos != num_steps - 1 {
-                            Some(pos + 1)
-                        } else {
-                            None
-                        }
-                    })
-                    .unwrap_or(num_steps - 1)
-            },
-            _ => unreachable!(),
-        }
-
-        debug!(
-            "Animation::get_property_declaration_at_time: keyframe from {:?} to {:?}",
-            prev_keyframe_index, next_keyframe_index
+        ComputedKeyframe::add_values_at_progress(
+            &self.computed_steps,
+            total_progress,
+            self.current_direction,
+            self.duration,
+            map,
         );
-
-        let prev_keyframe = &self.computed_steps[prev_keyframe_index];
-        let next_keyframe = match next_keyframe_index {
-            Some(index) => &self.computed_steps[index],
-            None => return,
-        };
-
-        // If we only need to take into account one keyframe, then exit early
-        // in order to avoid doing more work.
-        let mut add_declarations_to_map = |keyframe: &ComputedKeyframe| {
-            for value in keyframe.values.iter() {
-                map.insert(value.id(), value.clone());
-            }
-        };
-        if total_progress <= 0.0 {
-            add_declarations_to_map(&prev_keyframe);
-            return;
-        }
-        if total_progress >= 1.0 {
-            add_declarations_to_map(&next_keyframe);
-            return;
-        }
-
-        let percentage_between_keyframes =
-            (next_keyframe.start_percentage - prev_keyframe.start_percentage).abs() as f64;
-        let duration_between_keyframes = percentage_between_keyframes * self.duration;
-        let direction_aware_prev_keyframe_start_percentage = match self.current_direction {
-            AnimationDirection::Normal => prev_keyframe.start_percentage as f64,
-            AnimationDirection::Reverse => 1. - prev_keyframe.start_percentage as f64,
-            _ => unreachable!(),
-        };
-        let progress_between_keyframes = (total_progress -
-            direction_aware_prev_keyframe_start_percentage) /
-            percentage_between_keyframes;
-
-        for (from, to) in prev_keyframe.values.iter().zip(next_keyframe.values.iter()) {
-            let animation = PropertyAnimation {
-                from: from.clone(),
-                to: to.clone(),
-                timing_function: prev_keyframe.timing_function,
-                duration: duration_between_keyframes as f64,
-            };
-
-            if let Ok(value) = animation.calculate_value(progress_between_keyframes) {
-                map.insert(value.id(), value);
-            }
-        }
     }
 }
 
@@ -886,6 +914,173 @@ impl Transition {
     }
 }
 
+/// How the values of an animation created from script are combined with the
+/// underlying value of a property.
+///
+/// <https://drafts.csswg.org/web-animations/#effect-composition>
+#[derive(Clone, Copy, Debug, MallocSizeOf, PartialEq)]
+pub enum CompositeOperation {
+    /// The animation value replaces the underlying value.
+    Replace,
+    /// The animation value is added to the underlying value.
+    Add,
+    /// The animation value is accumulated onto the underlying value.
+    Accumulate,
+}
+
+/// An animation created from script via the Web Animations API. The timing model
+/// of these animations lives in the script thread, which resolves it to a progress
+/// through the current iteration each time the animation timeline is updated.
+#[derive(Clone, MallocSizeOf)]
+pub struct ScriptAnimation {
+    /// An identifier of this animation that is unique within its document.
+    pub id: u64,
+
+    /// The keyframes of this animation as specified from script.
+    keyframes: KeyframesAnimation,
+
+    /// The computed style for each keyframe of this animation. This is empty when
+    /// the keyframes still need to be computed for the element.
+    computed_steps: Vec<ComputedKeyframe>,
+
+    /// The values of the animating properties in the style of the element
+    /// when the keyframes were computed, used for additive composition.
+    underlying_values: Vec<AnimationValue>,
+
+    /// The transformed progress of this animation through the current iteration or
+    /// `None` if the animation does not currently produce a value.
+    pub progress: Option<f64>,
+
+    /// The duration of an iteration of this animation in seconds.
+    pub duration: f64,
+
+    /// How the values of this animation are combined with the underlying values.
+    pub composite: CompositeOperation,
+
+    /// Whether this animation is running and so needs animation ticks.
+    pub running: bool,
+
+    /// Whether or not this animation is new and or has already been tracked
+    /// by the script thread.
+    pub is_new: bool,
+}
+
+impl ScriptAnimation {
+    /// Create a new animation given its keyframes. It does not produce any values
+    /// until the script thread has given it a progress.
+    pub fn new(id: u64, keyframes: KeyframesAnimation, composite: CompositeOperation) -> Self {
+        ScriptAnimation {
+            id,
+            keyframes,
+            computed_steps: vec![],
+            underlying_values: vec![],
+            progress: None,
+            duration: 0.,
+            composite,
+            running: false,
+            is_new: true,
+        }
+    }
+
+    /// Replace the keyframes of this animation. They will be computed again the
+    /// next time that the element is styled.
+    pub fn set_keyframes(&mut self, keyframes: KeyframesAnimation) {
+        self.keyframes = keyframes;
+        self.computed_steps.clear();
+        self.underlying_values.clear();
+    }
+
+    /// Whether the keyframes of this animation have changed since they were last
+    /// computed.
+    fn needs_keyframe_update(&self) -> bool {
+        !self.keyframes.steps.is_empty() && self.computed_steps.is_empty()
+    }
+
+    fn update_keyframes_for_new_style<E>(
+        &mut self,
+        element: E,
+        context: &SharedStyleContext,
+        new_style: &Arc<ComputedValues>,
+        resolver: &mut StyleResolverForElement<E>,
+    ) where
+        E: TElement,
+    {
+        // The easing of each keyframe is declared with its declarations, so
+        // keyframes without one interpolate linearly.
+        self.computed_steps = ComputedKeyframe::generate_for_keyframes(
+            element,
+            &self.keyframes,
+            context,
+            new_style,
+            TimingFunction::Keyword(TimingKeyword::Linear),
+            resolver,
+        );
+        self.underlying_values = self
+            .keyframes
+            .properties_changed
+            .iter()
+            .filter_map(|property| {
+                let property = property.to_physical(new_style.writing_mode);
+                AnimationValue::from_computed_values(property, &**new_style)
+            })
+            .collect();
+    }
+
+    /// Fill in an `AnimationValueMap` with the values of this animation at its
+    /// current progress, combining them with the values already in the map.
+    ///
+    /// <https://drafts.csswg.org/web-animations/#the-effect-value-of-a-keyframe-animation-effect>
+    fn add_values_to_map(&self, map: &mut AnimationValueMap) {
+        let progress = match self.progress {
+            Some(progress) if !self.computed_steps.is_empty() => progress,
+            _ => return,
+        };
+
+        let mut values = AnimationValueMap::default();
+        ComputedKeyframe::add_values_at_progress(
+            &self.computed_steps,
+            progress,
+            AnimationDirection::Normal,
+            self.duration,
+            &mut values,
+        );
+
+        let procedure = match self.composite {
+            CompositeOperation::Replace => {
+                map.extend(values.into_iter());
+                return;
+            },
+            CompositeOperation::Add => Procedure::Add,
+            CompositeOperation::Accumulate => Procedure::Accumulate { count: 1 },
+        };
+
+        for (id, value) in values.into_iter() {
+            let underlying_value = map
+                .get(&id)
+                .or_else(|| self.underlying_values.iter().find(|value| value.id() == id));
+            let value = match underlying_value {
+                Some(underlying_value) => {
+                    underlying_value.animate(&value, procedure).unwrap_or(value)
+                },
+                None => value,
+            };
+            map.insert(id, value);
+        }
+    }
+}
+
+impl fmt::Debug for ScriptAnimation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ScriptAnimation")
+            .field("id", &self.id)
+            .field("progress", &self.progress)
+            .field("duration", &self.duration)
+            .field("composite", &self.composite)
+            .field("running", &self.running)
+            .finish()
+    }
+}
+
 /// Holds the animation state for a particular element.
 #[derive(Debug, Default, MallocSizeOf)]
 pub struct ElementAnimationSet {
@@ -895,6 +1090,10 @@ pub struct ElementAnimationSet {
     /// The transitions for this element.
     pub transitions: Vec<Transition>,
 
+    /// The animations created from script for this element, in the order that
+    /// they were created.
+    pub script_animations: Vec<ScriptAnimation>,
+
     /// Whether or not this ElementAnimationSet has had animations or transitions
     /// which have been added, removed, or had their state changed.
     pub dirty: bool,
@@ -952,7 +1151,9 @@ impl ElementAnimationSet {
     /// Whether this `ElementAnimationSet` is empty, which means it doesn't
     /// hold any animations in any state.
     pub fn is_empty(&self) -> bool {
-        self.animations.is_empty() && self.transitions.is_empty()
+        self.animations.is_empty() &&
+            self.transitions.is_empty() &&
+            self.script_animations.is_empty()
     }
 
     /// Whether or not this state needs animation ticks for its transitions
@@ -963,7 +1164,10 @@ impl ElementAnimationSet {
             .any(|animation| animation.state.needs_to_be_ticked()) ||
             self.transitions
                 .iter()
-                .any(|transition| transition.state.needs_to_be_ticked())
+                .any(|transition| transition.state.needs_to_be_ticked()) ||
+            self.script_animations
+                .iter()
+                .any(|animation| animation.running)
     }
 
     /// The number of running animations and transitions for this `ElementAnimationSet`.
@@ -975,6 +1179,10 @@ impl ElementAnimationSet {
             self.transitions
                 .iter()
                 .filter(|transition| transition.state.needs_to_be_ticked())
+                .count() +
+            self.script_animations
+                .iter()
+                .filter(|animation| animation.running)
                 .count()
     }
 
@@ -982,7 +1190,10 @@ impl ElementAnimationSet {
     pub fn has_active_animation(&self) -> bool {
         self.animations
             .iter()
-            .any(|animation| animation.state != AnimationState::Canceled)
+            .any(|animation| animation.state != AnimationState::Canceled) ||
+            self.script_animations
+                .iter()
+                .any(|animation| animation.progress.is_some())
     }
 
     /// If this `ElementAnimationSet` has any any active transitions.
@@ -1012,6 +1223,33 @@ impl ElementAnimationSet {
         maybe_start_animations(element, &context, &new_style, self, resolver);
     }
 
+    /// Whether any of the animations created from script for this element need
+    /// their keyframes computed.
+    pub fn script_animations_need_keyframe_update(&self) -> bool {
+        self.script_animations
+            .iter()
+            .any(|animation| animation.needs_keyframe_update())
+    }
+
+    /// Compute the keyframes of the animations created from script that have
+    /// new keyframes, given the style of the element.
+    pub fn update_script_animations_for_new_style<E>(
+        &mut self,
+        element: E,
+        context: &SharedStyleContext,
+        new_style: &Arc<ComputedValues>,
+        resolver: &mut StyleResolverForElement<E>,
+    ) where
+        E: TElement,
+    {
+        for animation in self.script_animations.iter_mut() {
+            if animation.needs_keyframe_update() {
+                animation.update_keyframes_for_new_style(element, context, new_style, resolver);
+                self.dirty = true;
+            }
+        }
+    }
+
     /// Update our transitions given a new style, canceling or starting new animations
     /// when appropriate.
     pub fn update_transitions_for_new_style(
@@ -1166,6 +1404,12 @@ impl ElementAnimationSet {
             animation.get_property_declaration_at_time(now, &mut map);
         }
 
+        // Animations created from script are composited after CSS animations.
+        // https://drafts.csswg.org/web-animations/#animation-composite-order
+        for animation in &self.script_animations {
+            animation.add_values_to_map(&mut map);
+        }
+
         Some(map)
     }
 }
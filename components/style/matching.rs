@@ -683,6 +683,25 @@ trait PrivateMatchMethods: TElement {
             );
         }
 
+        // The keyframes of animations created from script don't depend on the
+        // animation properties of the style, so they are only computed when they
+        // are added or replaced.
+        if animation_set.script_animations_need_keyframe_update() {
+            let mut resolver = StyleResolverForElement::new(
+                *self,
+                context,
+                RuleInclusion::All,
+                PseudoElementResolution::IfApplicable,
+            );
+
+            animation_set.update_script_animations_for_new_style::<Self>(
+                *self,
+                &shared_context,
+                &new_values,
+                &mut resolver,
+            );
+        }
+
         animation_set.update_transitions_for_new_style(
             might_need_transitions_update,
             &shared_context,
@@ -47,6 +47,8 @@ pub enum PseudoElement {
     // APPLIES_TO_PLACEHOLDER properties expect!
 
     // Non-eager pseudos.
+    Backdrop,
+    Cue,
     DetailsSummary,
     DetailsContent,
     ServoText,
@@ -78,6 +80,8 @@ impl ToCss for PseudoElement {
             After => "::after",
             Before => "::before",
             Selection => "::selection",
+            Backdrop => "::backdrop",
+            Cue => "::cue",
             DetailsSummary => "::-servo-details-summary",
             DetailsContent => "::-servo-details-content",
             ServoText => "::-servo-text",
@@ -220,7 +224,9 @@ impl PseudoElement {
             PseudoElement::After | PseudoElement::Before | PseudoElement::Selection => {
                 PseudoElementCascadeType::Eager
             },
-            PseudoElement::DetailsSummary => PseudoElementCascadeType::Lazy,
+            PseudoElement::Backdrop | PseudoElement::Cue | PseudoElement::DetailsSummary => {
+                PseudoElementCascadeType::Lazy
+            },
             PseudoElement::DetailsContent |
             PseudoElement::ServoText |
             PseudoElement::ServoInputText |
@@ -249,7 +255,10 @@ impl PseudoElement {
     /// Property flag that properties must have to apply to this pseudo-element.
     #[inline]
     pub fn property_restriction(&self) -> Option<PropertyFlags> {
-        None
+        match *self {
+            PseudoElement::Cue => Some(PropertyFlags::APPLIES_TO_CUE),
+            _ => None,
+        }
     }
 
     /// Whether this pseudo-element should actually exist if it has
@@ -270,6 +279,9 @@ impl PseudoElement {
 /// The type used for storing `:lang` arguments.
 pub type Lang = Box<str>;
 
+/// The type used for storing `:state` arguments.
+pub type CustomState = Box<str>;
+
 /// A non tree-structural pseudo-class.
 /// See https://drafts.csswg.org/selectors-4/#structural-pseudos
 #[derive(Clone, Debug, Eq, Hash, MallocSizeOf, PartialEq, ToShmem)]
@@ -287,10 +299,12 @@ pub enum NonTSPseudoClass {
     Indeterminate,
     Lang(Lang),
     Link,
+    Modal,
     PlaceholderShown,
     ReadWrite,
     ReadOnly,
     ServoNonZeroBorder,
+    State(CustomState),
     Target,
     Visited,
 }
@@ -335,6 +349,11 @@ impl ToCss for NonTSPseudoClass {
             serialize_identifier(lang, dest)?;
             return dest.write_str(")");
         }
+        if let State(ref state) = *self {
+            dest.write_str(":state(")?;
+            serialize_identifier(state, dest)?;
+            return dest.write_str(")");
+        }
 
         dest.write_str(match *self {
             Active => ":active",
@@ -348,13 +367,14 @@ impl ToCss for NonTSPseudoClass {
             Hover => ":hover",
             Indeterminate => ":indeterminate",
             Link => ":link",
+            Modal => ":modal",
             PlaceholderShown => ":placeholder-shown",
             ReadWrite => ":read-write",
             ReadOnly => ":read-only",
             ServoNonZeroBorder => ":-servo-nonzero-border",
             Target => ":target",
             Visited => ":visited",
-            Lang(_) => unreachable!(),
+            Lang(_) | State(_) => unreachable!(),
         })
     }
 }
@@ -374,11 +394,14 @@ impl NonTSPseudoClass {
             Disabled => ElementState::IN_DISABLED_STATE,
             Checked => ElementState::IN_CHECKED_STATE,
             Indeterminate => ElementState::IN_INDETERMINATE_STATE,
+            Modal => ElementState::IN_MODAL_DIALOG_STATE,
             ReadOnly | ReadWrite => ElementState::IN_READ_WRITE_STATE,
             PlaceholderShown => ElementState::IN_PLACEHOLDER_SHOWN_STATE,
             Target => ElementState::IN_TARGET_STATE,
 
-            AnyLink | Lang(_) | Link | Visited | ServoNonZeroBorder => ElementState::empty(),
+            AnyLink | Lang(_) | Link | Visited | ServoNonZeroBorder | State(_) => {
+                ElementState::empty()
+            },
         }
     }
 
@@ -419,6 +442,11 @@ impl<'a, 'i> ::selectors::Parser<'i> for SelectorParser<'a> {
     type Impl = SelectorImpl;
     type Error = StyleParseErrorKind<'i>;
 
+    #[inline]
+    fn parse_slotted(&self) -> bool {
+        true
+    }
+
     fn parse_non_ts_pseudo_class(
         &self,
         location: SourceLocation,
@@ -437,6 +465,7 @@ impl<'a, 'i> ::selectors::Parser<'i> for SelectorParser<'a> {
             "hover" => Hover,
             "indeterminate" => Indeterminate,
             "link" => Link,
+            "modal" => Modal,
             "placeholder-shown" => PlaceholderShown,
             "read-write" => ReadWrite,
             "read-only" => ReadOnly,
@@ -466,6 +495,9 @@ impl<'a, 'i> ::selectors::Parser<'i> for SelectorParser<'a> {
             "lang" => {
                 Lang(parser.expect_ident_or_string()?.as_ref().into())
             },
+            "state" => {
+                State(parser.expect_ident()?.as_ref().into())
+            },
             _ => return Err(parser.new_custom_error(SelectorParseErrorKind::UnexpectedIdent(name.clone()))),
         };
 
@@ -482,6 +514,8 @@ impl<'a, 'i> ::selectors::Parser<'i> for SelectorParser<'a> {
             "before" => Before,
             "after" => After,
             "selection" => Selection,
+            "backdrop" => Backdrop,
+            "cue" => Cue,
             "-servo-details-summary" => {
                 if !self.in_user_agent_stylesheet() {
                     return Err(location.new_custom_error(SelectorParseErrorKind::UnexpectedIdent(name.clone())))
@@ -785,6 +785,13 @@ impl<E: TElement> StyleSharingCache<E> {
             return None;
         }
 
+        if target.element.implemented_pseudo_element() !=
+            candidate.element.implemented_pseudo_element()
+        {
+            trace!("Miss: Element-backed pseudo-element");
+            return None;
+        }
+
         // It's possible that there are no styles for either id.
         let may_match_different_id_rules =
             checks::may_match_different_id_rules(shared, target.element, candidate.element);
@@ -160,6 +160,12 @@ impl KeyframeSelector {
         &self.0
     }
 
+    /// Create a keyframe selector from a list of percentages. This is used for
+    /// keyframes that are specified from script rather than parsed from CSS.
+    pub fn new(percentages: Vec<KeyframePercentage>) -> KeyframeSelector {
+        KeyframeSelector(percentages)
+    }
+
     /// A dummy public function so we can write a unit test for this.
     pub fn new_for_unit_testing(percentages: Vec<KeyframePercentage>) -> KeyframeSelector {
         KeyframeSelector(percentages)
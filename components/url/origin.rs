@@ -75,6 +75,24 @@ impl ImmutableOrigin {
         }
     }
 
+    /// <https://w3c.github.io/webappsec-secure-contexts/#is-origin-trustworthy>
+    pub fn is_potentially_trustworthy(&self) -> bool {
+        match *self {
+            // Step 1.
+            ImmutableOrigin::Opaque(_) => false,
+            // Step 3.
+            ImmutableOrigin::Tuple(ref scheme, _, _) if scheme == "https" || scheme == "wss" => {
+                true
+            },
+            // Steps 4-5.
+            ImmutableOrigin::Tuple(_, Host::Ipv4(ref ip), _) => ip.is_loopback(),
+            ImmutableOrigin::Tuple(_, Host::Ipv6(ref ip), _) => ip.is_loopback(),
+            ImmutableOrigin::Tuple(_, Host::Domain(ref domain), _) => {
+                domain == "localhost" || domain.ends_with(".localhost")
+            },
+        }
+    }
+
     /// <https://html.spec.whatwg.org/multipage/#ascii-serialisation-of-an-origin>
     pub fn ascii_serialization(&self) -> String {
         self.clone().into_url_origin().ascii_serialization()
@@ -38,14 +38,23 @@ use gstreamer::BufferPool;
 use gstreamer::BufferPoolExt;
 use gstreamer::BufferPoolExtManual;
 use gstreamer::Caps;
+use gstreamer::ClockExt;
+use gstreamer::ClockTime;
 use gstreamer::CoreError;
 use gstreamer::Element;
+use gstreamer::ElementExt;
 use gstreamer::ErrorMessage;
+use gstreamer::Event;
 use gstreamer::FlowError;
 use gstreamer::Format;
+use gstreamer::FormattedSegment;
 use gstreamer::Fraction;
+use gstreamer::IntRange;
 use gstreamer::LoggableError;
+use gstreamer::Pad;
 use gstreamer::PadDirection;
+use gstreamer::PadExt;
+use gstreamer::PadExtManual;
 use gstreamer::PadPresence;
 use gstreamer::PadTemplate;
 use gstreamer_base::subclass::base_src::BaseSrcImpl;
@@ -72,8 +81,11 @@ use servo::compositing::windowing::EmbedderCoordinates;
 use servo::compositing::windowing::EmbedderMethods;
 use servo::compositing::windowing::WindowEvent;
 use servo::compositing::windowing::WindowMethods;
+use servo::embedder_traits::AudioOutputBuffer;
 use servo::embedder_traits::EmbedderProxy;
 use servo::embedder_traits::EventLoopWaker;
+use servo::ipc_channel::ipc;
+use servo::ipc_channel::ipc::IpcReceiver;
 use servo::msg::constellation_msg::TopLevelBrowsingContextId;
 use servo::servo_config::prefs::add_user_prefs;
 use servo::servo_config::prefs::read_prefs_map;
@@ -116,6 +128,7 @@ use webxr::glwindow::GlWindowRenderTarget as WebXRRenderTarget;
 
 pub struct ServoWebSrc {
     sender: Sender<ServoWebSrcMsg>,
+    audio_pad: Pad,
     url: Mutex<Option<String>>,
     webxr_mode: Mutex<Option<WebXRMode>>,
     prefs: Mutex<Option<String>>,
@@ -178,6 +191,7 @@ enum ServoWebSrcMsg {
         Option<WebXRMode>,
         HashMap<String, PrefValue>,
         Size2D<i32, DevicePixel>,
+        Pad,
     ),
     GetSwapChain(Sender<SwapChain<Device>>),
     SetSwapChain(SwapChainWhichImplementsDebug),
@@ -186,6 +200,105 @@ enum ServoWebSrcMsg {
     Stop,
 }
 
+/// Pushes the audio output of Servo on the audio pad.
+struct ServoWebSrcAudio {
+    pad: Pad,
+    caps: Option<Caps>,
+    // The running time when the first buffer was pushed
+    start: Option<ClockTime>,
+    // How many frames have been pushed since then
+    frames_pushed: u64,
+}
+
+impl ServoWebSrcAudio {
+    fn new(pad: Pad) -> Self {
+        Self {
+            pad,
+            caps: None,
+            start: None,
+            frames_pushed: 0,
+        }
+    }
+
+    fn run(&mut self, receiver: IpcReceiver<AudioOutputBuffer>) {
+        while let Ok(buffer) = receiver.recv() {
+            if let Err(e) = self.push(buffer) {
+                match e {
+                    FlowError::Flushing | FlowError::NotLinked => {
+                        debug!("Dropping audio ({:?})", e)
+                    },
+                    e => {
+                        warn!("Failed to push audio ({:?})", e);
+                        break;
+                    },
+                }
+            }
+        }
+        debug!("Audio capture finished");
+    }
+
+    fn push(&mut self, buffer: AudioOutputBuffer) -> Result<(), FlowError> {
+        let channels = buffer.channels.max(1);
+        let caps = Caps::builder("audio/x-raw")
+            .field("format", &AUDIO_FORMAT)
+            .field("layout", &"interleaved")
+            .field("rate", &(buffer.sample_rate as i32))
+            .field("channels", &(channels as i32))
+            .build();
+        if self.caps.is_none() {
+            self.pad
+                .push_event(Event::new_stream_start("servowebsrc-audio").build());
+        }
+        if self.caps.as_ref() != Some(&caps) {
+            debug!("Setting audio caps {:?}", caps);
+            self.pad.push_event(Event::new_caps(&caps).build());
+        }
+        if self.caps.is_none() {
+            let segment = FormattedSegment::<ClockTime>::new();
+            self.pad.push_event(Event::new_segment(&segment).build());
+        }
+        self.caps = Some(caps);
+
+        // Timestamp the audio with the running time, like the video frames.
+        let start = match self.start {
+            Some(start) => start,
+            None => {
+                let start = self.running_time();
+                self.start = Some(start);
+                start
+            },
+        };
+        let frames = buffer.samples.len() as u64 / channels as u64;
+        let to_clock_time = |frames: u64| {
+            ClockTime::from_nseconds(frames * 1_000_000_000 / buffer.sample_rate as u64)
+        };
+        let pts = start + to_clock_time(self.frames_pushed);
+        let duration = to_clock_time(frames);
+        self.frames_pushed += frames;
+
+        let mut data = Vec::with_capacity(buffer.samples.len() * 4);
+        for sample in &buffer.samples {
+            data.extend_from_slice(&sample.to_ne_bytes());
+        }
+        let mut gst_buffer = Buffer::from_mut_slice(data);
+        if let Some(gst_buffer) = gst_buffer.get_mut() {
+            gst_buffer.set_pts(pts);
+            gst_buffer.set_duration(duration);
+        }
+        self.pad.push(gst_buffer).map(|_| ())
+    }
+
+    fn running_time(&self) -> ClockTime {
+        self.pad
+            .get_parent_element()
+            .and_then(|element| {
+                let clock = element.get_clock()?;
+                Some(clock.get_time() - element.get_base_time())
+            })
+            .unwrap_or_else(|| ClockTime::from_nseconds(0))
+    }
+}
+
 const DEFAULT_URL: &'static str =
     "https://rawcdn.githack.com/mrdoob/three.js/r105/examples/webgl_animation_cloth.html";
 
@@ -200,9 +313,9 @@ struct ServoThread {
 
 impl ServoThread {
     fn new(sender: Sender<ServoWebSrcMsg>, receiver: Receiver<ServoWebSrcMsg>) -> Self {
-        let (connection, url, webxr_mode, prefs, size) = match receiver.recv() {
-            Ok(ServoWebSrcMsg::Start(connection, url, webxr_mode, prefs, size)) => {
-                (connection.0, url, webxr_mode, prefs, size)
+        let (connection, url, webxr_mode, prefs, size, audio_pad) = match receiver.recv() {
+            Ok(ServoWebSrcMsg::Start(connection, url, webxr_mode, prefs, size, audio_pad)) => {
+                (connection.0, url, webxr_mode, prefs, size, audio_pad)
             },
             e => panic!("Failed to start ({:?})", e),
         };
@@ -222,6 +335,17 @@ impl ServoThread {
         let id = TopLevelBrowsingContextId::new();
         servo.handle_events(vec![WindowEvent::NewBrowser(url, id)]);
 
+        // Only capture the audio output if someone is listening to it,
+        // since captured audio isn't played.
+        if audio_pad.is_linked() {
+            let (audio_sender, audio_receiver) = ipc::channel().expect("Failed to create channel");
+            servo.handle_events(vec![WindowEvent::CaptureAudioOutput(
+                id,
+                Some(audio_sender),
+            )]);
+            thread::spawn(move || ServoWebSrcAudio::new(audio_pad).run(audio_receiver));
+        }
+
         let swap_chain = match webxr_mode {
             None => Some(webrender_swap_chain),
             Some(..) => {
@@ -489,6 +613,11 @@ const CAPS: &str = "video/x-raw(memory:GLMemory),
   height=[1,2147483647],
   framerate=[0/1,2147483647/1]";
 
+#[cfg(target_endian = "little")]
+const AUDIO_FORMAT: &str = "F32LE";
+#[cfg(target_endian = "big")]
+const AUDIO_FORMAT: &str = "F32BE";
+
 impl ObjectSubclass for ServoWebSrc {
     const NAME: &'static str = "ServoWebSrc";
     // gstreamer-gl doesn't have support for GLBaseSrc yet
@@ -497,7 +626,11 @@ impl ObjectSubclass for ServoWebSrc {
     type Instance = ElementInstanceStruct<Self>;
     type Class = ClassStruct<Self>;
 
-    fn new() -> Self {
+    fn with_class(klass: &ClassStruct<Self>) -> Self {
+        let audio_pad_template = klass
+            .get_pad_template("audio")
+            .expect("Failed to get audio pad template");
+        let audio_pad = Pad::new_from_template(&audio_pad_template, Some("audio"));
         let (sender, receiver) = crossbeam_channel::unbounded();
         let sender_clone = sender.clone();
         thread::spawn(move || ServoThread::new(sender_clone, receiver).run());
@@ -514,6 +647,7 @@ impl ObjectSubclass for ServoWebSrc {
         let next_frame_micros = AtomicU64::new(0);
         Self {
             sender,
+            audio_pad,
             info,
             outcaps,
             url,
@@ -540,6 +674,16 @@ impl ObjectSubclass for ServoWebSrc {
         let src_pad_template =
             PadTemplate::new("src", PadDirection::Src, PadPresence::Always, &src_caps).unwrap();
         klass.add_pad_template(src_pad_template);
+
+        let audio_caps = Caps::builder("audio/x-raw")
+            .field("format", &AUDIO_FORMAT)
+            .field("layout", &"interleaved")
+            .field("rate", &IntRange::<i32>::new(1, i32::MAX))
+            .field("channels", &IntRange::<i32>::new(1, i32::MAX))
+            .build();
+        let audio_pad_template =
+            PadTemplate::new("audio", PadDirection::Src, PadPresence::Always, &audio_caps).unwrap();
+        klass.add_pad_template(audio_pad_template);
         klass.install_properties(&PROPERTIES);
     }
 
@@ -555,6 +699,9 @@ impl ObjectImpl for ServoWebSrc {
         basesrc.set_live(true);
         basesrc.set_format(Format::Time);
         basesrc.set_do_timestamp(true);
+        basesrc
+            .add_pad(&self.audio_pad)
+            .expect("Failed to add audio pad");
     }
 
     fn set_property(&self, _obj: &Object, id: usize, value: &Value) {
@@ -811,6 +958,7 @@ impl ServoWebSrc {
             webxr_mode,
             prefs,
             size,
+            self.audio_pad.clone(),
         ));
 
         // Create a new buffer pool for GL memory
@@ -29,7 +29,7 @@ use servo::embedder_traits::{
 use servo::euclid::{Point2D, Rect, Scale, Size2D, Vector2D};
 use servo::keyboard_types::{Key, KeyState, KeyboardEvent};
 use servo::msg::constellation_msg::TraversalDirection;
-use servo::script_traits::{TouchEventType, TouchId};
+use servo::script_traits::{PictureInPictureAction, TouchEventType, TouchId};
 use servo::servo_config::{opts, pref};
 use servo::servo_url::ServoUrl;
 use servo::webrender_api::units::DevicePixel;
@@ -573,6 +573,14 @@ impl ServoGlue {
         self.process_event(WindowEvent::MediaSessionAction(action))
     }
 
+    /// Close the picture-in-picture surface, if a video is shown in it.
+    pub fn exit_picture_in_picture(&mut self) -> Result<(), &'static str> {
+        info!("exit_picture_in_picture");
+        self.process_event(WindowEvent::PictureInPictureAction(
+            PictureInPictureAction::Close,
+        ))
+    }
+
     pub fn change_visibility(&mut self, visible: bool) -> Result<(), &'static str> {
         info!("change_visibility");
         if let Ok(id) = self.get_browser_id() {
@@ -775,6 +783,9 @@ impl ServoGlue {
                             ),
                     };
                 },
+                EmbedderMsg::PictureInPicture(request) => {
+                    self.events.push(WindowEvent::PictureInPicture(request));
+                },
                 EmbedderMsg::OnDevtoolsStarted(port, token) => {
                     self.callbacks
                         .host_callbacks
@@ -717,6 +717,12 @@ pub extern "C" fn media_session_action(action: CMediaSessionActionType) {
     call(|s| s.media_session_action(action.convert()));
 }
 
+#[no_mangle]
+pub extern "C" fn exit_picture_in_picture() {
+    debug!("exit_picture_in_picture");
+    call(|s| s.exit_picture_in_picture());
+}
+
 #[no_mangle]
 pub extern "C" fn change_visibility(visible: bool) {
     debug!("change_visibility");
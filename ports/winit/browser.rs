@@ -9,12 +9,12 @@ use keyboard_types::{Key, KeyboardEvent, Modifiers, ShortcutMatcher};
 use servo::compositing::windowing::{WebRenderDebugOption, WindowEvent};
 use servo::embedder_traits::{
     ContextMenuResult, EmbedderMsg, FilterPattern, PermissionRequest, PromptDefinition, PromptOrigin, PromptResult,
-    PermissionPrompt,
+    PermissionPrompt, PictureInPictureRequest,
 };
 use servo::msg::constellation_msg::TopLevelBrowsingContextId as BrowserId;
 use servo::msg::constellation_msg::TraversalDirection;
 use servo::net_traits::pub_domains::is_reg_domain;
-use servo::script_traits::TouchEventType;
+use servo::script_traits::{PictureInPictureAction, TouchEventType};
 use servo::servo_config::opts;
 use servo::servo_config::pref;
 use servo::servo_url::ServoUrl;
@@ -49,6 +49,8 @@ pub struct Browser<Window: WindowPortsMethods + ?Sized> {
     event_queue: Vec<WindowEvent>,
     clipboard_ctx: Option<ClipboardContext>,
     shutdown_requested: bool,
+    /// Whether Servo shows a video in picture-in-picture.
+    picture_in_picture: bool,
 }
 
 enum LoadingState {
@@ -80,6 +82,7 @@ where
             },
             event_queue: Vec::new(),
             shutdown_requested: false,
+            picture_in_picture: false,
         }
     }
 
@@ -177,7 +180,11 @@ where
             })
             .shortcut(Modifiers::empty(), Key::Escape, || {
                 let state = self.window.get_fullscreen();
-                if state {
+                if self.picture_in_picture {
+                    self.event_queue.push(WindowEvent::PictureInPictureAction(
+                        PictureInPictureAction::Close,
+                    ));
+                } else if state {
                     if let Some(id) = self.browser_id {
                         let event = WindowEvent::ExitFullScreen(id);
                         self.event_queue.push(event);
@@ -515,6 +522,16 @@ where
                     debug!("MediaSessionEvent received");
                     // TODO(ferjm): MediaSession support for winit based browsers.
                 },
+                EmbedderMsg::PictureInPicture(request) => {
+                    debug!("PictureInPicture received");
+                    match request {
+                        PictureInPictureRequest::Enter(..) => self.picture_in_picture = true,
+                        PictureInPictureRequest::UpdateFrame(..) => {},
+                        PictureInPictureRequest::Exit => self.picture_in_picture = false,
+                    }
+                    self.event_queue
+                        .push(WindowEvent::PictureInPicture(request));
+                },
                 EmbedderMsg::OnDevtoolsStarted(port, _token) => {
                     match port {
                         Ok(p) => info!("Devtools Server running on port {}", p),
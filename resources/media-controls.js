@@ -48,6 +48,8 @@
         </span>
         <button id="volume-switch"></button>
         <input id="volume-level" type="range" value="100" min="0" max="100" step="1"></input>
+        <button id="captions-switch" class="captions hidden"></button>
+        ${isAudioOnly ? "" : '<button id="pip-switch" class="pip hidden"></button>'}
         ${isAudioOnly ? "" : '<button id="fullscreen-switch" class="fullscreen"></button>'}
       </div>
     `;
@@ -76,6 +78,10 @@
       .padStart(2, "0")}:${secs.toString().padStart(2, "0")}`;
   }
 
+  function isCaptionTrack(track) {
+    return track.kind == "subtitles" || track.kind == "captions";
+  }
+
   class MediaControls {
     constructor() {
       this.nonce = Date.now();
@@ -102,6 +108,7 @@
 
 
       const elementNames = [
+        "captions-switch",
         "duration",
         "play-pause-button",
         "position-duration-box",
@@ -113,6 +120,7 @@
 
       if (!this.isAudioOnly) {
         elementNames.push("fullscreen-switch");
+        elementNames.push("pip-switch");
       }
 
       // Import elements.
@@ -183,7 +191,9 @@
         "emptied",
         "loadedmetadata",
         "error",
-        "suspend"
+        "suspend",
+        "enterpictureinpicture",
+        "leavepictureinpicture"
       ];
       this.mediaEvents.forEach(event => {
         this.media.addEventListener(event, this);
@@ -192,11 +202,17 @@
       this.controlEvents = [
         { el: this.elements.playPauseButton, type: "click" },
         { el: this.elements.volumeSwitch, type: "click" },
-        { el: this.elements.volumeLevel, type: "input" }
+        { el: this.elements.volumeLevel, type: "input" },
+        { el: this.elements.progress, type: "input" },
+        { el: this.elements.captionsSwitch, type: "click" },
+        { el: this.media.textTracks, type: "addtrack" },
+        { el: this.media.textTracks, type: "removetrack" },
+        { el: this.media.textTracks, type: "change" }
       ];
 
       if (!this.isAudioOnly) {
         this.controlEvents.push({ el: this.elements.fullscreenSwitch, type: "click" });
+        this.controlEvents.push({ el: this.elements.pipSwitch, type: "click" });
       }
 
       this.controlEvents.forEach(({ el, type }) => {
@@ -303,6 +319,28 @@
       if (this.elements.volumeLevel.value != volumeLevelValue) {
         this.elements.volumeLevel.value = volumeLevelValue;
       }
+
+      // Captions.
+      const captionTracks = Array.from(this.media.textTracks).filter(isCaptionTrack);
+      const captionsSwitch = this.elements.captionsSwitch;
+      captionsSwitch.classList.toggle("hidden", captionTracks.length == 0);
+      captionsSwitch.classList.toggle(
+        "captions-active",
+        captionTracks.some(track => track.mode == "showing")
+      );
+
+      // Picture-in-picture.
+      if (!this.isAudioOnly) {
+        const pipSwitch = this.elements.pipSwitch;
+        pipSwitch.classList.toggle(
+          "hidden",
+          !document.pictureInPictureEnabled || this.media.disablePictureInPicture
+        );
+        pipSwitch.classList.toggle(
+          "pip-active",
+          document.pictureInPictureElement === this.media
+        );
+      }
     }
 
     handleEvent(event) {
@@ -331,6 +369,12 @@
             case this.elements.fullscreenSwitch:
                 this.toggleFullscreen();
                 break;
+            case this.elements.captionsSwitch:
+              this.toggleCaptions();
+              break;
+            case this.elements.pipSwitch:
+              this.togglePictureInPicture();
+              break;
           }
           break;
         case "input":
@@ -338,8 +382,16 @@
             case this.elements.volumeLevel:
               this.changeVolume();
               break;
+            case this.elements.progress:
+              this.seek();
+              break;
           }
           break;
+        case "addtrack":
+        case "removetrack":
+        case "change":
+          this.render();
+          break;
         default:
           throw new Error(`Unknown event ${event.type}`);
       }
@@ -359,6 +411,8 @@
         case "volumechange":
         case "timeupdate":
         case "resize":
+        case "enterpictureinpicture":
+        case "leavepictureinpicture":
           this.render();
           break;
         case "loadedmetadata":
@@ -403,6 +457,41 @@
         }
     }
 
+    toggleCaptions() {
+      const captionTracks = Array.from(this.media.textTracks).filter(isCaptionTrack);
+      const showing = captionTracks.some(track => track.mode == "showing");
+      if (showing) {
+        captionTracks.forEach(track => {
+          if (track.mode == "showing") {
+            track.mode = "hidden";
+          }
+        });
+      } else if (captionTracks.length) {
+        captionTracks[0].mode = "showing";
+      }
+      this.render();
+    }
+
+    togglePictureInPicture() {
+      if (document.pictureInPictureElement === this.media) {
+        document.exitPictureInPicture().catch(error => {
+          console.warn(`Failed to exit picture-in-picture: ${error}`);
+        });
+      } else {
+        this.media.requestPictureInPicture().catch(error => {
+          console.warn(`Failed to enter picture-in-picture: ${error}`);
+        });
+      }
+    }
+
+    seek() {
+      const position = parseFloat(this.elements.progress.value);
+      const duration = this.media.duration;
+      if (!isNaN(position) && Number.isFinite(duration)) {
+        this.media.currentTime = (position / 100) * duration;
+      }
+    }
+
     changeVolume() {
       const volume = parseInt(this.elements.volumeLevel.value);
       if (!isNaN(volume)) {
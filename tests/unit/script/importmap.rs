@@ -0,0 +1,176 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use script::test::importmap::{
+    parse_an_import_map_string, resolve_a_module_specifier, Error, ImportMap,
+};
+use servo_url::ServoUrl;
+
+fn url(input: &str) -> ServoUrl {
+    ServoUrl::parse(input).unwrap()
+}
+
+fn import_map(input: &str) -> ImportMap {
+    parse_an_import_map_string(input, &url("https://example.com/app/index.html")).unwrap()
+}
+
+fn resolve(import_map: &ImportMap, specifier: &str, base_url: &str) -> Option<String> {
+    resolve_a_module_specifier(import_map, specifier, &url(base_url))
+        .ok()
+        .map(|url| url.into_string())
+}
+
+#[test]
+fn test_parse_invalid_import_maps() {
+    let base_url = url("https://example.com/");
+    let parse = |input| parse_an_import_map_string(input, &base_url);
+    assert!(matches!(parse("{"), Err(Error::Syntax)));
+    assert!(matches!(parse("[]"), Err(Error::Type(_))));
+    assert!(matches!(parse("\"imports\""), Err(Error::Type(_))));
+    assert!(matches!(parse(r#"{"imports": []}"#), Err(Error::Type(_))));
+    assert!(matches!(parse(r#"{"scopes": "/"}"#), Err(Error::Type(_))));
+    assert!(matches!(
+        parse(r#"{"scopes": {"/scope/": "/a.js"}}"#),
+        Err(Error::Type(_))
+    ));
+    assert!(parse("{}").is_ok());
+    assert!(parse(r#"{"imports": {}, "unknown": 1}"#).is_ok());
+}
+
+#[test]
+fn test_resolve_without_import_map() {
+    let import_map = import_map("{}");
+    let base_url = "https://example.com/app/index.html";
+    assert_eq!(
+        resolve(&import_map, "./a.js", base_url).as_deref(),
+        Some("https://example.com/app/a.js")
+    );
+    assert_eq!(
+        resolve(&import_map, "/a.js", base_url).as_deref(),
+        Some("https://example.com/a.js")
+    );
+    assert_eq!(
+        resolve(&import_map, "https://other.com/a.js", base_url).as_deref(),
+        Some("https://other.com/a.js")
+    );
+    assert!(resolve(&import_map, "a", base_url).is_none());
+    assert!(resolve(&import_map, "a.js", base_url).is_none());
+}
+
+#[test]
+fn test_resolve_bare_specifiers() {
+    let import_map = import_map(
+        r#"{
+            "imports": {
+                "moment": "/node_modules/moment/src/moment.js",
+                "lodash/": "/node_modules/lodash-es/",
+                "lodash/fp/": "./fp/"
+            }
+        }"#,
+    );
+    let base_url = "https://example.com/app/index.html";
+    assert_eq!(
+        resolve(&import_map, "moment", base_url).as_deref(),
+        Some("https://example.com/node_modules/moment/src/moment.js")
+    );
+    assert_eq!(
+        resolve(&import_map, "lodash/map.js", base_url).as_deref(),
+        Some("https://example.com/node_modules/lodash-es/map.js")
+    );
+    // The longest matching prefix wins.
+    assert_eq!(
+        resolve(&import_map, "lodash/fp/map.js", base_url).as_deref(),
+        Some("https://example.com/app/fp/map.js")
+    );
+    assert!(resolve(&import_map, "moment/locale.js", base_url).is_none());
+}
+
+#[test]
+fn test_resolve_url_like_specifiers() {
+    let import_map = import_map(
+        r#"{
+            "imports": {
+                "/app/old.js": "/app/new.js",
+                "https://cdn.example.com/lib/": "/vendor/lib/"
+            }
+        }"#,
+    );
+    let base_url = "https://example.com/app/index.html";
+    assert_eq!(
+        resolve(&import_map, "./old.js", base_url).as_deref(),
+        Some("https://example.com/app/new.js")
+    );
+    assert_eq!(
+        resolve(&import_map, "https://cdn.example.com/lib/a.js", base_url).as_deref(),
+        Some("https://example.com/vendor/lib/a.js")
+    );
+}
+
+#[test]
+fn test_resolve_scopes() {
+    let import_map = import_map(
+        r#"{
+            "imports": {"a": "/a-1.js"},
+            "scopes": {
+                "/scope/": {"a": "/a-2.js"},
+                "/scope/inner/": {"a": "/a-3.js"},
+                "/page.html": {"a": "/a-4.js"}
+            }
+        }"#,
+    );
+    assert_eq!(
+        resolve(&import_map, "a", "https://example.com/index.html").as_deref(),
+        Some("https://example.com/a-1.js")
+    );
+    assert_eq!(
+        resolve(&import_map, "a", "https://example.com/scope/index.html").as_deref(),
+        Some("https://example.com/a-2.js")
+    );
+    assert_eq!(
+        resolve(
+            &import_map,
+            "a",
+            "https://example.com/scope/inner/index.html"
+        )
+        .as_deref(),
+        Some("https://example.com/a-3.js")
+    );
+    assert_eq!(
+        resolve(&import_map, "a", "https://example.com/page.html").as_deref(),
+        Some("https://example.com/a-4.js")
+    );
+}
+
+#[test]
+fn test_resolve_blocked_specifiers() {
+    let import_map = import_map(
+        r#"{
+            "imports": {
+                "null": null,
+                "number": 1,
+                "invalid": "https://",
+                "bare": "other",
+                "no-slash/": "/no-slash",
+                "pkg/": "/pkg/"
+            }
+        }"#,
+    );
+    let base_url = "https://example.com/app/index.html";
+    for specifier in &["null", "number", "invalid", "bare", "no-slash/a.js"] {
+        assert!(
+            matches!(
+                resolve_a_module_specifier(&import_map, specifier, &url(base_url)),
+                Err(Error::Type(_))
+            ),
+            "{} should be blocked",
+            specifier
+        );
+    }
+    assert_eq!(
+        resolve(&import_map, "pkg/a.js", base_url).as_deref(),
+        Some("https://example.com/pkg/a.js")
+    );
+    // Backtracking above the address of a package prefix is blocked.
+    assert!(resolve(&import_map, "pkg/../a.js", base_url).is_none());
+}
@@ -9,6 +9,10 @@ mod htmlareaelement;
 #[cfg(test)]
 mod htmlimageelement;
 #[cfg(test)]
+mod importmap;
+#[cfg(test)]
+mod mediasegmentparser;
+#[cfg(test)]
 mod origin;
 #[cfg(all(test, target_pointer_width = "64"))]
 mod size_of;
@@ -16,6 +20,8 @@ mod size_of;
 mod textinput;
 #[cfg(test)]
 mod timeranges;
+#[cfg(test)]
+mod webvttparser;
 
 /**
 ```compile_fail,E0277
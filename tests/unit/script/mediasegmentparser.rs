@@ -0,0 +1,134 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use script::test::mediasegmentparser::{ByteStreamFormat, Segment, SegmentParser};
+
+static MP4_VIDEO: &[u8] = include_bytes!(
+    "../../wpt/web-platform-tests/media-source/mp4/test-v-128k-320x240-24fps-8kfr.mp4"
+);
+static MP4_AUDIO: &[u8] =
+    include_bytes!("../../wpt/web-platform-tests/media-source/mp4/test-a-128k-44100Hz-1ch.mp4");
+static WEBM_VIDEO: &[u8] = include_bytes!(
+    "../../wpt/web-platform-tests/media-source/webm/test-v-128k-320x240-24fps-8kfr.webm"
+);
+
+/// Append `data` in chunks of `chunk_size` bytes, returning the duration of
+/// each initialization segment and the time range of each media segment.
+fn parse(
+    format: ByteStreamFormat,
+    data: &[u8],
+    chunk_size: usize,
+) -> (Vec<Option<f64>>, Vec<(f64, f64)>) {
+    let mut parser = SegmentParser::new(format);
+    let mut durations = vec![];
+    let mut ranges = vec![];
+    for chunk in data.chunks(chunk_size) {
+        parser.append(chunk);
+        for segment in parser.parse().unwrap() {
+            match segment {
+                Segment::Initialization(segment) => durations.push(segment.duration),
+                Segment::Media(segment) => ranges.push((segment.start, segment.end)),
+            }
+        }
+    }
+    assert_eq!(parser.pending_len(), 0);
+    (durations, ranges)
+}
+
+fn check_ranges(ranges: &[(f64, f64)], expected: &[(f64, f64)]) {
+    assert_eq!(ranges.len(), expected.len());
+    for (range, expected) in ranges.iter().zip(expected) {
+        assert!((range.0 - expected.0).abs() < 1e-6, "{:?}", range);
+        assert!((range.1 - expected.1).abs() < 1e-6, "{:?}", range);
+    }
+}
+
+#[test]
+fn mime_types() {
+    assert_eq!(
+        ByteStreamFormat::from_mime_type("video/mp4"),
+        Some(ByteStreamFormat::IsoBmff)
+    );
+    assert_eq!(
+        ByteStreamFormat::from_mime_type("audio/webm"),
+        Some(ByteStreamFormat::WebM)
+    );
+    assert_eq!(ByteStreamFormat::from_mime_type("video/ogg"), None);
+}
+
+#[test]
+fn fragmented_mp4_video() {
+    let (durations, ranges) = parse(ByteStreamFormat::IsoBmff, MP4_VIDEO, MP4_VIDEO.len());
+    assert_eq!(durations, vec![Some(2.)]);
+    check_ranges(
+        &ranges,
+        &[
+            (1. / 12., 5. / 12.),
+            (5. / 12., 9. / 12.),
+            (9. / 12., 13. / 12.),
+            (13. / 12., 17. / 12.),
+            (17. / 12., 21. / 12.),
+            (21. / 12., 25. / 12.),
+        ],
+    );
+}
+
+#[test]
+fn fragmented_mp4_audio() {
+    let (durations, ranges) = parse(ByteStreamFormat::IsoBmff, MP4_AUDIO, MP4_AUDIO.len());
+    assert_eq!(durations, vec![Some(2.043)]);
+    assert_eq!(ranges.len(), 10);
+    assert_eq!(ranges[0].0, 0.);
+    for pair in ranges.windows(2) {
+        assert_eq!(pair[0].1, pair[1].0);
+    }
+}
+
+#[test]
+fn webm_video() {
+    let (durations, ranges) = parse(ByteStreamFormat::WebM, WEBM_VIDEO, WEBM_VIDEO.len());
+    assert_eq!(durations, vec![Some(2.)]);
+    check_ranges(
+        &ranges,
+        &[
+            (0., 0.334),
+            (0.333, 0.667),
+            (0.667, 0.999),
+            (1., 1.334),
+            (1.333, 1.667),
+            (1.667, 1.999),
+        ],
+    );
+}
+
+#[test]
+fn segments_split_across_appends() {
+    for &(format, data) in &[
+        (ByteStreamFormat::IsoBmff, MP4_VIDEO),
+        (ByteStreamFormat::WebM, WEBM_VIDEO),
+    ] {
+        assert_eq!(parse(format, data, 7), parse(format, data, data.len()));
+    }
+}
+
+#[test]
+fn media_segment_before_initialization_segment() {
+    // Skip the `ftyp`, `free` and `moov` boxes.
+    let mut parser = SegmentParser::new(ByteStreamFormat::IsoBmff);
+    parser.append(&MP4_VIDEO[28 + 58 + 749..]);
+    assert!(parser.parse().is_err());
+}
+
+#[test]
+fn reset_drops_partial_segments() {
+    let mut parser = SegmentParser::new(ByteStreamFormat::IsoBmff);
+    parser.append(&MP4_VIDEO[..100]);
+    assert!(parser.parse().unwrap().is_empty());
+    // The `ftyp` and `free` boxes are complete, the `moov` box isn't.
+    assert_eq!(parser.pending_len(), 100 - 28 - 58);
+    parser.reset();
+    assert_eq!(parser.pending_len(), 0);
+    parser.append(MP4_VIDEO);
+    assert_eq!(parser.parse().unwrap().len(), 7);
+}
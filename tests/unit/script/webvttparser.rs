@@ -0,0 +1,192 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use script::test::webvttparser::{
+    parse, parse_cue_text, CueInternalNode, CueNode, CueNodeKind, LineAlignment, PositionAlignment,
+    TextAlignment, WritingDirection,
+};
+
+#[test]
+fn test_signature() {
+    assert!(parse("").is_err());
+    assert!(parse("WEBVT").is_err());
+    assert!(parse("WEBVTTX").is_err());
+    assert!(parse("\u{FEFF}WEBVTT").is_ok());
+    assert!(parse("WEBVTT - some title\n").is_ok());
+    assert!(parse("WEBVTT\tsome title\n").is_ok());
+}
+
+#[test]
+fn test_cues() {
+    let file = parse(
+        "WEBVTT\r\nKind: captions\r\n\r\n\
+         1\r\n00:01.000 --> 00:04.000\r\nNever drink liquid nitrogen.\r\n\r\n\
+         00:05.000 --> 01:02:03.456\r\nIt will perforate\r\nyour stomach.\r\n\r\n\r\n\
+         00:10.000 --> oops\r\nskipped\r\n",
+    )
+    .unwrap();
+    assert_eq!(file.cues.len(), 2);
+    assert_eq!(file.cues[0].id, "1");
+    assert_eq!(file.cues[0].start_time, 1.);
+    assert_eq!(file.cues[0].end_time, 4.);
+    assert_eq!(file.cues[0].text, "Never drink liquid nitrogen.");
+    assert_eq!(file.cues[1].id, "");
+    assert!((file.cues[1].end_time - 3723.456).abs() < 1e-9);
+    assert_eq!(file.cues[1].text, "It will perforate\nyour stomach.");
+}
+
+#[test]
+fn test_cue_ending_at_arrow_line() {
+    let file =
+        parse("WEBVTT\n\n00:00.000 --> 00:01.000\nfirst\n00:01.000 --> 00:02.000\nsecond").unwrap();
+    assert_eq!(file.cues.len(), 2);
+    assert_eq!(file.cues[0].text, "first");
+    assert_eq!(file.cues[1].text, "second");
+}
+
+#[test]
+fn test_invalid_timestamps() {
+    for timing in &[
+        "00:60.000 --> 00:61.000",
+        "00:00.00 --> 00:01.000",
+        "0:00.000 --> 00:01.000",
+        "00:00:00,000 --> 00:01.000",
+        "00:00.000 -> 00:01.000",
+    ] {
+        let file = parse(&format!("WEBVTT\n\n{}\ntext\n", timing)).unwrap();
+        assert!(file.cues.is_empty(), "{} should not be a cue", timing);
+    }
+}
+
+#[test]
+fn test_cue_settings() {
+    let file = parse(
+        "WEBVTT\n\n\
+         00:00.000 --> 00:01.000 vertical:rl line:-2 position:10%,line-left size:50% align:left\n\
+         a\n\n\
+         00:00.000 --> 00:01.000 line:25%,center position:110% size:x align:middle bogus\n\
+         b\n",
+    )
+    .unwrap();
+    let cue = &file.cues[0];
+    assert_eq!(cue.writing_direction, WritingDirection::VerticalGrowingLeft);
+    assert_eq!(cue.line, Some(-2.));
+    assert!(cue.snap_to_lines);
+    assert_eq!(cue.position, Some(10.));
+    assert_eq!(cue.position_alignment, PositionAlignment::LineLeft);
+    assert_eq!(cue.size, 50.);
+    assert_eq!(cue.text_alignment, TextAlignment::Left);
+
+    let cue = &file.cues[1];
+    assert_eq!(cue.writing_direction, WritingDirection::Horizontal);
+    assert_eq!(cue.line, Some(25.));
+    assert!(!cue.snap_to_lines);
+    assert_eq!(cue.line_alignment, LineAlignment::Center);
+    assert_eq!(cue.position, None);
+    assert_eq!(cue.size, 100.);
+    assert_eq!(cue.text_alignment, TextAlignment::Center);
+}
+
+#[test]
+fn test_regions_and_stylesheets() {
+    let file = parse(
+        "WEBVTT\n\n\
+         REGION\nid:fred width:40% lines:3\nregionanchor:0%,100% viewportanchor:10%,90% scroll:up\n\n\
+         STYLE\n::cue { color: lime }\n\n\
+         REGION\nid:bill width:120%\n\n\
+         00:00.000 --> 00:01.000 region:fred\nin a region\n\n\
+         00:00.000 --> 00:01.000 region:unknown\nnot in a region\n\n\
+         STYLE\n::cue { color: red }\n",
+    )
+    .unwrap();
+    assert_eq!(file.regions.len(), 2);
+    let fred = &file.regions[0];
+    assert_eq!(fred.id, "fred");
+    assert_eq!(fred.width, 40.);
+    assert_eq!(fred.lines, 3);
+    assert_eq!(fred.region_anchor, (0., 100.));
+    assert_eq!(fred.viewport_anchor, (10., 90.));
+    assert!(fred.scroll_up);
+    assert_eq!(file.regions[1].width, 100.);
+
+    assert_eq!(file.stylesheets, vec!["::cue { color: lime }".to_owned()]);
+
+    // Style blocks are ignored once a cue has been seen.
+    assert_eq!(file.cues.len(), 2);
+    assert_eq!(file.cues[0].region.as_deref(), Some("fred"));
+    assert_eq!(file.cues[1].region, None);
+}
+
+fn internal(
+    kind: CueNodeKind,
+    classes: &[&str],
+    annotation: Option<&str>,
+    children: Vec<CueNode>,
+) -> CueNode {
+    CueNode::Internal(CueInternalNode {
+        kind,
+        classes: classes.iter().map(|c| c.to_string()).collect(),
+        annotation: annotation.map(str::to_owned),
+        children,
+    })
+}
+
+fn text(text: &str) -> CueNode {
+    CueNode::Text(text.to_owned())
+}
+
+#[test]
+fn test_cue_text() {
+    assert_eq!(
+        parse_cue_text("<v.loud Esme  Weatherwax>Hello &amp; <i>good</i>bye&nope;"),
+        vec![internal(
+            CueNodeKind::Voice,
+            &["loud"],
+            Some("Esme Weatherwax"),
+            vec![
+                text("Hello & "),
+                internal(CueNodeKind::Italic, &[], None, vec![text("good")]),
+                text("bye&nope;"),
+            ],
+        )]
+    );
+}
+
+#[test]
+fn test_cue_text_unclosed_and_unknown_tags() {
+    assert_eq!(
+        parse_cue_text("<b>bold <foo>still bold</i><00:00:01.500>later"),
+        vec![internal(
+            CueNodeKind::Bold,
+            &[],
+            None,
+            vec![
+                text("bold "),
+                text("still bold"),
+                CueNode::Timestamp(1.5),
+                text("later"),
+            ],
+        )]
+    );
+}
+
+#[test]
+fn test_cue_text_ruby() {
+    assert_eq!(
+        parse_cue_text("<rt>no</rt><ruby>漢<rt>kan</ruby><lang en>x</lang>"),
+        vec![
+            text("no"),
+            internal(
+                CueNodeKind::Ruby,
+                &[],
+                None,
+                vec![
+                    text("漢"),
+                    internal(CueNodeKind::RubyText, &[], None, vec![text("kan")]),
+                ],
+            ),
+            internal(CueNodeKind::Language, &[], Some("en"), vec![text("x")]),
+        ]
+    );
+}
@@ -10,8 +10,6 @@ function test_interfaces(interfaceNamesInGlobalScope) {
       "BigUint64Array",
       "Boolean",
       "BroadcastChannel",
-      "ByteLengthQueuingStrategy",
-      "CountQueuingStrategy",
       "Crypto",
       "DataView",
       "Date",
@@ -37,7 +35,6 @@ function test_interfaces(interfaceNamesInGlobalScope) {
       "Promise",
       "Proxy",
       "RangeError",
-      "ReadableStream",
       "ReferenceError",
       "Reflect",
       "RegExp",
@@ -7,12 +7,15 @@ importScripts("interfaces.js");
 
 // IMPORTANT: Do not change the list below without review from a DOM peer!
 test_interfaces([
+  "AbortController",
+  "AbortSignal",
   "Blob",
   "BroadcastChannel",
   "ByteLengthQueuingStrategy",
   "CanvasGradient",
   "CanvasPattern",
   "CloseEvent",
+  "CompressionStream",
   "CountQueuingStrategy",
   "DOMMatrix",
   "DOMMatrixReadOnly",
@@ -23,6 +26,7 @@ test_interfaces([
   "DOMRectReadOnly",
   "DOMStringList",
   "CustomEvent",
+  "DecompressionStream",
   "DedicatedWorkerGlobalScope",
   "DOMException",
   "ErrorEvent",
@@ -50,11 +54,20 @@ test_interfaces([
   "PerformanceResourceTiming",
   "ProgressEvent",
   "PromiseRejectionEvent",
+  "ReadableByteStreamController",
   "ReadableStream",
+  "ReadableStreamBYOBReader",
+  "ReadableStreamBYOBRequest",
+  "ReadableStreamDefaultController",
+  "ReadableStreamDefaultReader",
   "Request",
   "Response",
   "TextDecoder",
+  "TextDecoderStream",
   "TextEncoder",
+  "TextEncoderStream",
+  "TransformStream",
+  "TransformStreamDefaultController",
   "URL",
   "URLSearchParams",
   "WebSocket",
@@ -62,6 +75,9 @@ test_interfaces([
   "WorkerGlobalScope",
   "WorkerLocation",
   "WorkerNavigator",
+  "WritableStream",
+  "WritableStreamDefaultController",
+  "WritableStreamDefaultWriter",
   "XMLHttpRequest",
   "XMLHttpRequestEventTarget",
   "XMLHttpRequestUpload",
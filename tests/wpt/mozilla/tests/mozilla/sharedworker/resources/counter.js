@@ -0,0 +1,6 @@
+var connections = 0;
+
+onconnect = function(e) {
+  connections++;
+  e.ports[0].postMessage(connections);
+};
@@ -0,0 +1,22 @@
+// A sample rate at which power of two frame counts are exact durations.
+const SAMPLE_RATE = 32768;
+
+function createContext(length) {
+  return new OfflineAudioContext(1, length, SAMPLE_RATE);
+}
+
+// A source playing a single unit sample at the start of the rendering.
+function createImpulse(context) {
+  const buffer = new AudioBuffer({length: 1, sampleRate: context.sampleRate});
+  buffer.getChannelData(0)[0] = 1;
+  const source = new AudioBufferSourceNode(context, {buffer});
+  source.start();
+  return source;
+}
+
+function assert_samples_approx_equals(actual, expected, epsilon, description) {
+  assert_equals(actual.length, expected.length, `${description}: length`);
+  for (let i = 0; i < expected.length; i++) {
+    assert_approx_equals(actual[i], expected[i], epsilon, `${description}: sample ${i}`);
+  }
+}
@@ -0,0 +1,13 @@
+importScripts("/resources/testharness.js");
+
+promise_test(async () => {
+  const digests = [];
+  for (let i = 0; i < 64; i++) {
+    digests.push(crypto.subtle.digest("SHA-1", new Uint8Array(0)));
+  }
+  for (const digest of await Promise.all(digests)) {
+    assert_equals(digest.byteLength, 20);
+  }
+}, "Operations started from a worker all settle");
+
+done();
@@ -0,0 +1,54 @@
+// Connects two peer connections living in the same document, so no signaling
+// server or network access beyond the loopback interface is needed.
+function exchangeIceCandidates(pc1, pc2) {
+  function forward(from, to) {
+    from.addEventListener("icecandidate", event => {
+      if (event.candidate && to.signalingState != "closed") {
+        to.addIceCandidate(event.candidate);
+      }
+    });
+  }
+  forward(pc1, pc2);
+  forward(pc2, pc1);
+}
+
+async function negotiate(caller, callee) {
+  const offer = await caller.createOffer();
+  await caller.setLocalDescription(offer);
+  await callee.setRemoteDescription(offer);
+  const answer = await callee.createAnswer();
+  await callee.setLocalDescription(answer);
+  await caller.setRemoteDescription(answer);
+}
+
+function createLoopback(t) {
+  const pc1 = new RTCPeerConnection();
+  const pc2 = new RTCPeerConnection();
+  t.add_cleanup(() => {
+    pc1.close();
+    pc2.close();
+  });
+  exchangeIceCandidates(pc1, pc2);
+  return [pc1, pc2];
+}
+
+function iceConnected(pc) {
+  return new Promise(resolve => {
+    function check() {
+      if (pc.iceConnectionState == "connected" || pc.iceConnectionState == "completed") {
+        pc.removeEventListener("iceconnectionstatechange", check);
+        resolve();
+      }
+    }
+    pc.addEventListener("iceconnectionstatechange", check);
+    check();
+  });
+}
+
+function iceUsernameFragment(description) {
+  return description.sdp.match(/^a=ice-ufrag:(.*)\r$/m)[1];
+}
+
+function getFakeStream(constraints) {
+  return navigator.mediaDevices.getUserMedia(constraints);
+}
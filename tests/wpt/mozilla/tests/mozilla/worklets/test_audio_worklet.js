@@ -0,0 +1,29 @@
+registerProcessor("constant", class extends AudioWorkletProcessor {
+  static get parameterDescriptors() {
+    return [{
+      name: "level",
+      defaultValue: 0.5,
+      minValue: 0,
+      maxValue: 1,
+      automationRate: "k-rate",
+    }];
+  }
+
+  constructor(options) {
+    super();
+    this.offset = options.processorOptions.offset;
+  }
+
+  process(inputs, outputs, parameters) {
+    for (let channel of outputs[0]) {
+      channel.fill(parameters.level[0] + this.offset);
+    }
+    return true;
+  }
+});
+
+registerProcessor("throwing", class extends AudioWorkletProcessor {
+  process() {
+    throw new Error("process() failed");
+  }
+});